@@ -1,6 +1,6 @@
 use std::collections::BTreeSet;
 
-use casper_types::{account::AccountHash, DeployHash};
+use casper_types::{account::AccountHash, DeployHash, ProtocolVersion};
 
 use crate::core::engine_state::executable_deploy_item::ExecutableDeployItem;
 
@@ -15,6 +15,8 @@ pub struct DeployItem {
     pub gas_price: GasPrice,
     pub authorization_keys: BTreeSet<AccountHash>,
     pub deploy_hash: DeployHash,
+    /// The minimum protocol version the deploy requires to be executed, if any.
+    pub min_protocol_version: Option<ProtocolVersion>,
 }
 
 impl DeployItem {
@@ -26,6 +28,7 @@ impl DeployItem {
         gas_price: GasPrice,
         authorization_keys: BTreeSet<AccountHash>,
         deploy_hash: DeployHash,
+        min_protocol_version: Option<ProtocolVersion>,
     ) -> Self {
         DeployItem {
             address,
@@ -34,6 +37,7 @@ impl DeployItem {
             gas_price,
             authorization_keys,
             deploy_hash,
+            min_protocol_version,
         }
     }
 }
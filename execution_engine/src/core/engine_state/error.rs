@@ -50,6 +50,11 @@ pub enum Error {
     ProtocolUpgrade(ProtocolUpgradeError),
     #[error("Unsupported deploy item variant: {0}")]
     InvalidDeployItemVariant(String),
+    #[error("Deploy requires protocol version {required}, but executing with {current}")]
+    UnsupportedProtocolVersion {
+        required: ProtocolVersion,
+        current: ProtocolVersion,
+    },
 }
 
 impl From<execution::Error> for Error {
@@ -1,6 +1,8 @@
 use std::collections::VecDeque;
 
-use casper_types::{bytesrepr::FromBytes, CLTyped, CLValue, CLValueError, Key, TransferAddr};
+use casper_types::{
+    bytesrepr::FromBytes, CLTyped, CLValue, CLValueError, ContractEvent, Key, TransferAddr,
+};
 
 use super::{error, execution_effect::ExecutionEffect, op::Op};
 use crate::{
@@ -56,6 +58,7 @@ pub enum ExecutionResult {
     Success {
         effect: ExecutionEffect,
         transfers: Vec<TransferAddr>,
+        events: Vec<ContractEvent>,
         cost: Gas,
     },
 }
@@ -65,6 +68,7 @@ impl Default for ExecutionResult {
         ExecutionResult::Success {
             effect: ExecutionEffect::default(),
             transfers: Vec::default(),
+            events: Vec::default(),
             cost: Gas::default(),
         }
     }
@@ -137,6 +141,14 @@ impl ExecutionResult {
         }
     }
 
+    /// Returns the events emitted by contracts, which are only retained if execution succeeded.
+    pub fn events(&self) -> &[ContractEvent] {
+        match self {
+            ExecutionResult::Failure { .. } => &[],
+            ExecutionResult::Success { events, .. } => events,
+        }
+    }
+
     pub fn with_cost(self, cost: Gas) -> Self {
         match self {
             ExecutionResult::Failure {
@@ -151,10 +163,14 @@ impl ExecutionResult {
                 cost,
             },
             ExecutionResult::Success {
-                effect, transfers, ..
+                effect,
+                transfers,
+                events,
+                ..
             } => ExecutionResult::Success {
                 effect,
                 transfers,
+                events,
                 cost,
             },
         }
@@ -174,10 +190,14 @@ impl ExecutionResult {
                 cost,
             },
             ExecutionResult::Success {
-                cost, transfers, ..
+                cost,
+                transfers,
+                events,
+                ..
             } => ExecutionResult::Success {
                 effect,
                 transfers,
+                events,
                 cost,
             },
         }
@@ -196,9 +216,15 @@ impl ExecutionResult {
                 transfers,
                 cost,
             },
-            ExecutionResult::Success { cost, effect, .. } => ExecutionResult::Success {
+            ExecutionResult::Success {
+                cost,
+                effect,
+                events,
+                ..
+            } => ExecutionResult::Success {
                 effect,
                 transfers,
+                events,
                 cost,
             },
         }
@@ -289,10 +315,12 @@ impl From<&ExecutionResult> for casper_types::ExecutionResult {
             ExecutionResult::Success {
                 effect,
                 transfers,
+                events,
                 cost,
             } => casper_types::ExecutionResult::Success {
                 effect: effect.into(),
                 transfers: transfers.clone(),
+                events: events.clone(),
                 cost: cost.value(),
             },
             ExecutionResult::Failure {
@@ -381,12 +409,20 @@ impl ExecutionResultBuilder {
             .unwrap_or_default()
     }
 
+    pub fn events(&self) -> Vec<ContractEvent> {
+        self.session_execution_result
+            .as_ref()
+            .map(|result| result.events().to_vec())
+            .unwrap_or_default()
+    }
+
     pub fn build<R: StateReader<Key, StoredValue>>(
         self,
         reader: &R,
         correlation_id: CorrelationId,
     ) -> Result<ExecutionResult, ExecutionResultBuilderError> {
         let transfers = self.transfers();
+        let events = self.events();
         let cost = self.total_cost();
         let mut ops = AdditiveMap::new();
         let mut transforms = AdditiveMap::new();
@@ -394,6 +430,7 @@ impl ExecutionResultBuilder {
         let mut ret: ExecutionResult = ExecutionResult::Success {
             effect: Default::default(),
             transfers,
+            events,
             cost,
         };
 
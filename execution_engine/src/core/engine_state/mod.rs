@@ -93,6 +93,22 @@ pub struct EngineState<S> {
     state: S,
 }
 
+/// Fails deploys requiring a later protocol version than `protocol_version` as a precondition.
+fn check_protocol_version(
+    deploy_item: DeployItem,
+    protocol_version: ProtocolVersion,
+) -> Result<DeployItem, ExecutionResult> {
+    match deploy_item.min_protocol_version {
+        Some(required) if required > protocol_version => Err(
+            ExecutionResult::precondition_failure(Error::UnsupportedProtocolVersion {
+                required,
+                current: protocol_version,
+            }),
+        ),
+        _ => Ok(deploy_item),
+    }
+}
+
 impl<S> EngineState<S>
 where
     S: StateProvider,
@@ -419,6 +435,9 @@ where
         let mut results = ExecutionResults::with_capacity(deploys.len());
 
         for deploy_item in deploys {
+            let deploy_item = deploy_item.and_then(|deploy_item| {
+                check_protocol_version(deploy_item, exec_request.protocol_version)
+            });
             let result = match deploy_item {
                 Err(exec_result) => Ok(exec_result),
                 Ok(deploy_item) => match deploy_item.session {
@@ -188,6 +188,7 @@ impl Executor {
                     return ExecutionResult::Success {
                         effect: runtime.context().effect(),
                         transfers: runtime.context().transfers().to_owned(),
+                        events: runtime.context().events().to_owned(),
                         cost: runtime.context().gas_counter(),
                     };
                 }
@@ -212,6 +213,7 @@ impl Executor {
                     return ExecutionResult::Success {
                         effect: runtime.context().effect(),
                         transfers: runtime.context().transfers().to_owned(),
+                        events: runtime.context().events().to_owned(),
                         cost: runtime.context().gas_counter(),
                     };
                 }
@@ -236,6 +238,7 @@ impl Executor {
                     return ExecutionResult::Success {
                         effect: runtime.context().effect(),
                         transfers: runtime.context().transfers().to_owned(),
+                        events: runtime.context().events().to_owned(),
                         cost: runtime.context().gas_counter(),
                     }
                 }
@@ -259,6 +262,7 @@ impl Executor {
         ExecutionResult::Success {
             effect: runtime.context().effect(),
             transfers: runtime.context().transfers().to_owned(),
+            events: runtime.context().events().to_owned(),
             cost: runtime.context().gas_counter(),
         }
     }
@@ -338,6 +342,7 @@ impl Executor {
             Ok(()) => ExecutionResult::Success {
                 effect: runtime.context().effect(),
                 transfers: runtime.context().transfers().to_owned(),
+                events: runtime.context().events().to_owned(),
                 cost: runtime.context().gas_counter(),
             },
             Err(error) => ExecutionResult::Failure {
@@ -729,6 +734,7 @@ impl DirectSystemContractCall {
                 Ok(ret) => ExecutionResult::Success {
                     effect: runtime.context().effect(),
                     transfers: runtime.context().transfers().to_owned(),
+                    events: runtime.context().events().to_owned(),
                     cost: runtime.context().gas_counter(),
                 }
                 .take_with_ret(ret),
@@ -968,6 +968,22 @@ where
                 self.record_era_info(era_id, era_info)?;
                 Ok(Some(RuntimeValue::I32(0)))
             }
+
+            FunctionIndex::EmitEvent => {
+                // args(0) = pointer to event name in Wasm memory
+                // args(1) = size of event name
+                // args(2) = pointer to event data in Wasm memory
+                // args(3) = size of event data
+                let (name_ptr, name_size, data_ptr, data_size) = Args::parse(args)?;
+                self.charge_host_function_call(
+                    &host_function_costs.emit_event,
+                    [name_ptr, name_size, data_ptr, data_size],
+                )?;
+                scoped_instrumenter.add_property("name_size", name_size);
+                scoped_instrumenter.add_property("data_size", data_size);
+                let ret = self.emit_event(name_ptr, name_size, data_ptr, data_size)?;
+                Ok(Some(RuntimeValue::I32(api_error::i32_from(ret))))
+            }
         }
     }
 }
@@ -1015,13 +1015,25 @@ where
         self.memory.get(ptr, size).map_err(Into::into)
     }
 
-    fn t_from_mem<T: FromBytes>(&self, ptr: u32, size: u32) -> Result<T, Error> {
+    /// Charges for deserializing `size` bytes read from Wasm memory.
+    fn charge_deserialization(&mut self, size: u32) -> Result<(), Error> {
+        let cost = self
+            .protocol_data()
+            .wasm_config()
+            .host_side_costs()
+            .deserialize_cost(size as usize);
+        self.gas(cost)
+    }
+
+    fn t_from_mem<T: FromBytes>(&mut self, ptr: u32, size: u32) -> Result<T, Error> {
+        self.charge_deserialization(size)?;
         let bytes = self.bytes_from_mem(ptr, size as usize)?;
         bytesrepr::deserialize(bytes).map_err(Into::into)
     }
 
     /// Reads key (defined as `key_ptr` and `key_size` tuple) from Wasm memory.
     fn key_from_mem(&mut self, key_ptr: u32, key_size: u32) -> Result<Key, Error> {
+        self.charge_deserialization(key_size)?;
         let bytes = self.bytes_from_mem(key_ptr, key_size as usize)?;
         bytesrepr::deserialize(bytes).map_err(Into::into)
     }
@@ -1032,11 +1044,24 @@ where
         cl_value_ptr: u32,
         cl_value_size: u32,
     ) -> Result<CLValue, Error> {
+        self.charge_deserialization(cl_value_size)?;
         let bytes = self.bytes_from_mem(cl_value_ptr, cl_value_size as usize)?;
         bytesrepr::deserialize(bytes).map_err(Into::into)
     }
 
-    fn string_from_mem(&self, ptr: u32, size: u32) -> Result<String, Trap> {
+    /// Charges for a name of `size` bytes, e.g. of a named key or argument, which the host looks up
+    /// or stores in a map.
+    fn charge_key_name(&mut self, size: usize) -> Result<(), Error> {
+        let cost = self
+            .protocol_data()
+            .wasm_config()
+            .host_side_costs()
+            .key_name_cost(size);
+        self.gas(cost)
+    }
+
+    fn string_from_mem(&mut self, ptr: u32, size: u32) -> Result<String, Trap> {
+        self.charge_key_name(size as usize)?;
         let bytes = self.bytes_from_mem(ptr, size as usize)?;
         bytesrepr::deserialize(bytes).map_err(|e| Error::BytesRepr(e).into())
     }
@@ -1167,7 +1192,7 @@ where
         let value_size = value.inner_bytes().len();
 
         // Save serialized public key into host buffer
-        if let Err(error) = self.write_host_buffer(value) {
+        if let Err(error) = self.write_host_buffer(value)? {
             return Ok(Err(error));
         }
 
@@ -2094,6 +2119,10 @@ where
             *transfers = runtime.context.transfers().to_owned();
         }
 
+        // The sub-call's context started without events, so append those it emitted to ours.  If
+        // the sub-call failed, the whole execution fails and the events are discarded.
+        self.context.extend_events(runtime.context.events());
+
         let error = match result {
             Err(error) => error,
             // If `Ok` and the `host_buffer` is `None`, the contract's execution succeeded but did
@@ -2202,7 +2231,7 @@ where
 
         // leave the host buffer set to `None` if there's nothing to write there
         if result_size != 0 {
-            if let Err(error) = self.write_host_buffer(result) {
+            if let Err(error) = self.write_host_buffer(result)? {
                 return Ok(Err(error));
             }
         }
@@ -2250,7 +2279,7 @@ where
             CLValue::from_t(self.context.named_keys().clone()).map_err(Error::CLValue)?;
 
         let length = named_keys.inner_bytes().len() as u32;
-        if let Err(error) = self.write_host_buffer(named_keys) {
+        if let Err(error) = self.write_host_buffer(named_keys)? {
             return Ok(Err(error));
         }
 
@@ -2343,7 +2372,7 @@ where
         let new_urefs_value = CLValue::from_t(new_urefs)?;
         let value_size = new_urefs_value.inner_bytes().len();
         // write return value to buffer
-        if let Err(err) = self.write_host_buffer(new_urefs_value) {
+        if let Err(err) = self.write_host_buffer(new_urefs_value)? {
             return Ok(Err(err));
         }
         // Write return value size to output location
@@ -2602,7 +2631,7 @@ where
         };
 
         let value_size = cl_value.inner_bytes().len() as u32;
-        if let Err(error) = self.write_host_buffer(cl_value) {
+        if let Err(error) = self.write_host_buffer(cl_value)? {
             return Ok(Err(error));
         }
 
@@ -3049,7 +3078,7 @@ where
         };
 
         let balance_size = balance_cl_value.inner_bytes().len() as i32;
-        if let Err(error) = self.write_host_buffer(balance_cl_value) {
+        if let Err(error) = self.write_host_buffer(balance_cl_value)? {
             return Ok(Err(error));
         }
 
@@ -3094,13 +3123,21 @@ where
         self.host_buffer.is_none()
     }
 
-    /// Overwrites data in host buffer only if it's in empty state
-    fn write_host_buffer(&mut self, data: CLValue) -> Result<(), ApiError> {
-        match self.host_buffer {
-            Some(_) => return Err(ApiError::HostBufferFull),
-            None => self.host_buffer = Some(data),
+    /// Stores `data` in the host buffer, charging for its serialized size.
+    ///
+    /// The outer error is returned if the contract ran out of gas.
+    fn write_host_buffer(&mut self, data: CLValue) -> Result<Result<(), ApiError>, Error> {
+        if self.host_buffer.is_some() {
+            return Ok(Err(ApiError::HostBufferFull));
         }
-        Ok(())
+        let cost = self
+            .protocol_data()
+            .wasm_config()
+            .host_side_costs()
+            .serialize_cost(data.inner_bytes().len());
+        self.gas(cost)?;
+        self.host_buffer = Some(data);
+        Ok(Ok(()))
     }
 
     fn read_host_buffer(
@@ -3138,6 +3175,23 @@ where
         Ok(Ok(()))
     }
 
+    /// Records an event emitted by the currently executing contract or session code.
+    fn emit_event(
+        &mut self,
+        name_ptr: u32,
+        name_size: u32,
+        data_ptr: u32,
+        data_size: u32,
+    ) -> Result<Result<(), ApiError>, Trap> {
+        let name = self.string_from_mem(name_ptr, name_size)?;
+        if name.is_empty() {
+            return Ok(Err(ApiError::InvalidArgument));
+        }
+        let data = self.cl_value_from_mem(data_ptr, data_size)?;
+        self.context.emit_event(name, data);
+        Ok(Ok(()))
+    }
+
     #[cfg(feature = "test-support")]
     fn print(&mut self, text_ptr: u32, text_size: u32) -> Result<(), Trap> {
         let text = self.string_from_mem(text_ptr, text_size)?;
@@ -3151,6 +3205,7 @@ where
         name_size: usize,
         size_ptr: u32,
     ) -> Result<Result<(), ApiError>, Trap> {
+        self.charge_key_name(name_size)?;
         let name_bytes = self.bytes_from_mem(name_ptr, name_size)?;
         let name = String::from_utf8_lossy(&name_bytes);
 
@@ -3178,6 +3233,7 @@ where
         output_ptr: u32,
         output_size: usize,
     ) -> Result<Result<(), ApiError>, Trap> {
+        self.charge_key_name(name_size)?;
         let name_bytes = self.bytes_from_mem(name_ptr, name_size)?;
         let name = String::from_utf8_lossy(&name_bytes);
 
@@ -3306,7 +3362,7 @@ where
         let new_uref_value = CLValue::from_t(new_uref)?;
         let value_size = new_uref_value.inner_bytes().len();
         // write return value to buffer
-        if let Err(err) = self.write_host_buffer(new_uref_value) {
+        if let Err(err) = self.write_host_buffer(new_uref_value)? {
             return Ok(Err(err));
         }
         // Write return value size to output location
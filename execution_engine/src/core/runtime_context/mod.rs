@@ -15,9 +15,9 @@ use casper_types::{
     bytesrepr,
     bytesrepr::ToBytes,
     contracts::NamedKeys,
-    AccessRights, BlockTime, CLType, CLValue, Contract, ContractPackage, ContractPackageHash,
-    DeployHash, DeployInfo, EntryPointAccess, EntryPointType, Key, Phase, ProtocolVersion,
-    RuntimeArgs, Transfer, TransferAddr, URef, KEY_HASH_LENGTH,
+    AccessRights, BlockTime, CLType, CLValue, Contract, ContractEvent, ContractPackage,
+    ContractPackageHash, DeployHash, DeployInfo, EntryPointAccess, EntryPointType, Key, Phase,
+    ProtocolVersion, RuntimeArgs, Transfer, TransferAddr, URef, KEY_HASH_LENGTH,
 };
 
 use crate::{
@@ -105,6 +105,7 @@ pub struct RuntimeContext<'a, R> {
     protocol_data: ProtocolData,
     entry_point_type: EntryPointType,
     transfers: Vec<TransferAddr>,
+    events: Vec<ContractEvent>,
 }
 
 impl<'a, R> RuntimeContext<'a, R>
@@ -156,6 +157,7 @@ where
             phase,
             protocol_data,
             transfers,
+            events: Vec::new(),
         }
     }
 
@@ -507,6 +509,25 @@ where
         &mut self.transfers
     }
 
+    /// Returns the events emitted in this context, including those of any completed sub-calls.
+    pub fn events(&self) -> &Vec<ContractEvent> {
+        &self.events
+    }
+
+    /// Records an event emitted by the code running in this context.
+    pub fn emit_event(&mut self, name: String, data: CLValue) {
+        self.events.push(ContractEvent {
+            emitter: self.base_key.to_formatted_string(),
+            name,
+            data,
+        });
+    }
+
+    /// Appends the events emitted during a sub-call to this context's events.
+    pub fn extend_events(&mut self, events: &[ContractEvent]) {
+        self.events.extend_from_slice(events);
+    }
+
     /// Validates whether keys used in the `value` are not forged.
     fn validate_value(&self, value: &StoredValue) -> Result<(), Error> {
         match value {
@@ -5,6 +5,7 @@ pub mod additive_map;
 pub mod gas;
 pub mod account;
 pub mod host_function_costs;
+pub mod host_side_costs;
 pub mod logging;
 pub mod motes;
 pub mod newtypes;
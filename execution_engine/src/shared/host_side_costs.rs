@@ -0,0 +1,181 @@
+use datasize::DataSize;
+use rand::{distributions::Standard, prelude::*, Rng};
+use serde::{Deserialize, Serialize};
+
+use casper_types::{
+    bytesrepr::{self, FromBytes, ToBytes},
+    U512,
+};
+
+use super::gas::Gas;
+
+pub const DEFAULT_DESERIALIZE_PER_BYTE_COST: u32 = 0;
+pub const DEFAULT_SERIALIZE_PER_BYTE_COST: u32 = 0;
+pub const DEFAULT_KEY_NAME_PER_BYTE_COST: u32 = 0;
+
+/// Costs of work done by the host on behalf of a contract, which depend on the size of the data
+/// involved rather than on the host function being called.
+///
+/// All costs default to zero, so that protocol versions whose chainspec does not specify this table
+/// keep their original behavior.
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, DataSize)]
+pub struct HostSideCosts {
+    /// Gas charged per byte of a value read from Wasm memory and deserialized by the host.
+    pub deserialize_per_byte: u32,
+    /// Gas charged per byte of a value serialized by the host into the host buffer.
+    pub serialize_per_byte: u32,
+    /// Gas charged per byte of a named key or argument name passed to the host.
+    pub key_name_per_byte: u32,
+}
+
+impl HostSideCosts {
+    pub const fn new(
+        deserialize_per_byte: u32,
+        serialize_per_byte: u32,
+        key_name_per_byte: u32,
+    ) -> Self {
+        Self {
+            deserialize_per_byte,
+            serialize_per_byte,
+            key_name_per_byte,
+        }
+    }
+
+    /// Calculates gas cost for deserializing `bytes` read from Wasm memory.
+    pub fn deserialize_cost(&self, bytes: usize) -> Gas {
+        Self::calculate_gas_cost(self.deserialize_per_byte, bytes)
+    }
+
+    /// Calculates gas cost for serializing `bytes` into the host buffer.
+    pub fn serialize_cost(&self, bytes: usize) -> Gas {
+        Self::calculate_gas_cost(self.serialize_per_byte, bytes)
+    }
+
+    /// Calculates gas cost for handling a name of `bytes` length.
+    pub fn key_name_cost(&self, bytes: usize) -> Gas {
+        Self::calculate_gas_cost(self.key_name_per_byte, bytes)
+    }
+
+    fn calculate_gas_cost(per_byte: u32, bytes: usize) -> Gas {
+        let value = U512::from(per_byte) * U512::from(bytes);
+        Gas::new(value)
+    }
+}
+
+impl Default for HostSideCosts {
+    fn default() -> Self {
+        Self {
+            deserialize_per_byte: DEFAULT_DESERIALIZE_PER_BYTE_COST,
+            serialize_per_byte: DEFAULT_SERIALIZE_PER_BYTE_COST,
+            key_name_per_byte: DEFAULT_KEY_NAME_PER_BYTE_COST,
+        }
+    }
+}
+
+impl Distribution<HostSideCosts> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> HostSideCosts {
+        HostSideCosts {
+            deserialize_per_byte: rng.gen(),
+            serialize_per_byte: rng.gen(),
+            key_name_per_byte: rng.gen(),
+        }
+    }
+}
+
+impl ToBytes for HostSideCosts {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut ret = bytesrepr::unchecked_allocate_buffer(self);
+
+        ret.append(&mut self.deserialize_per_byte.to_bytes()?);
+        ret.append(&mut self.serialize_per_byte.to_bytes()?);
+        ret.append(&mut self.key_name_per_byte.to_bytes()?);
+
+        Ok(ret)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.deserialize_per_byte.serialized_length()
+            + self.serialize_per_byte.serialized_length()
+            + self.key_name_per_byte.serialized_length()
+    }
+}
+
+impl FromBytes for HostSideCosts {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (deserialize_per_byte, rem) = FromBytes::from_bytes(bytes)?;
+        let (serialize_per_byte, rem) = FromBytes::from_bytes(rem)?;
+        let (key_name_per_byte, rem) = FromBytes::from_bytes(rem)?;
+
+        Ok((
+            HostSideCosts {
+                deserialize_per_byte,
+                serialize_per_byte,
+                key_name_per_byte,
+            },
+            rem,
+        ))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use casper_types::{bytesrepr, U512};
+
+    use super::*;
+
+    const WEIGHT: usize = 123456789;
+
+    #[test]
+    fn should_be_free_by_default() {
+        let host_side_costs = HostSideCosts::default();
+
+        assert_eq!(host_side_costs.deserialize_cost(WEIGHT), Gas::default());
+        assert_eq!(host_side_costs.serialize_cost(WEIGHT), Gas::default());
+        assert_eq!(host_side_costs.key_name_cost(WEIGHT), Gas::default());
+    }
+
+    #[test]
+    fn should_calculate_gas_cost() {
+        let host_side_costs = HostSideCosts::new(2, 3, 5);
+
+        assert_eq!(
+            host_side_costs.deserialize_cost(WEIGHT),
+            Gas::new(U512::from(2) * U512::from(WEIGHT))
+        );
+        assert_eq!(
+            host_side_costs.serialize_cost(WEIGHT),
+            Gas::new(U512::from(3) * U512::from(WEIGHT))
+        );
+        assert_eq!(
+            host_side_costs.key_name_cost(WEIGHT),
+            Gas::new(U512::from(5) * U512::from(WEIGHT))
+        );
+    }
+
+    #[test]
+    fn should_serialize_and_deserialize() {
+        let host_side_costs = HostSideCosts::new(1, 2, 3);
+        bytesrepr::test_serialization_roundtrip(&host_side_costs);
+    }
+}
+
+#[cfg(any(feature = "gens", test))]
+pub mod gens {
+    use proptest::{num, prop_compose};
+
+    use super::HostSideCosts;
+
+    prop_compose! {
+        pub fn host_side_costs_arb()(
+            deserialize_per_byte in num::u32::ANY,
+            serialize_per_byte in num::u32::ANY,
+            key_name_per_byte in num::u32::ANY,
+        ) -> HostSideCosts {
+            HostSideCosts {
+                deserialize_per_byte,
+                serialize_per_byte,
+                key_name_per_byte,
+            }
+        }
+    }
+}
@@ -5,12 +5,20 @@ use serde::{Deserialize, Serialize};
 use casper_types::bytesrepr::{self, FromBytes, ToBytes};
 
 use super::{
-    host_function_costs::HostFunctionCosts, opcode_costs::OpcodeCosts, storage_costs::StorageCosts,
+    host_function_costs::HostFunctionCosts, host_side_costs::HostSideCosts,
+    opcode_costs::OpcodeCosts, storage_costs::StorageCosts,
 };
 
 pub const DEFAULT_WASM_MAX_MEMORY: u32 = 64;
 pub const DEFAULT_MAX_STACK_HEIGHT: u32 = 64 * 1024;
 
+/// Leads versioned encodings in place of `max_memory`, which as a number of Wasm pages never
+/// exceeds 65536. Encodings starting with any other value are unversioned, written before
+/// host-side costs were introduced.
+const VERSIONED_MARKER: u32 = u32::MAX;
+/// Version of the encoding including host-side costs.
+const VERSION_WITH_HOST_SIDE_COSTS: u8 = 1;
+
 #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, DataSize)]
 pub struct WasmConfig {
     /// Maximum amount of a heap memory (represented in 64kb pages) each contract can use.
@@ -23,6 +31,9 @@ pub struct WasmConfig {
     storage_costs: StorageCosts,
     /// Host function costs table
     host_function_costs: HostFunctionCosts,
+    /// Host-side costs table, free unless specified
+    #[serde(default)]
+    host_side_costs: HostSideCosts,
 }
 
 impl WasmConfig {
@@ -32,6 +43,7 @@ impl WasmConfig {
         opcode_costs: OpcodeCosts,
         storage_costs: StorageCosts,
         host_function_costs: HostFunctionCosts,
+        host_side_costs: HostSideCosts,
     ) -> Self {
         Self {
             max_memory,
@@ -39,6 +51,7 @@ impl WasmConfig {
             opcode_costs,
             storage_costs,
             host_function_costs,
+            host_side_costs,
         }
     }
 
@@ -53,6 +66,10 @@ impl WasmConfig {
     pub fn take_host_function_costs(self) -> HostFunctionCosts {
         self.host_function_costs
     }
+
+    pub fn host_side_costs(&self) -> HostSideCosts {
+        self.host_side_costs
+    }
 }
 
 impl Default for WasmConfig {
@@ -63,6 +80,7 @@ impl Default for WasmConfig {
             opcode_costs: OpcodeCosts::default(),
             storage_costs: StorageCosts::default(),
             host_function_costs: HostFunctionCosts::default(),
+            host_side_costs: HostSideCosts::default(),
         }
     }
 }
@@ -71,31 +89,57 @@ impl ToBytes for WasmConfig {
     fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
         let mut ret = bytesrepr::unchecked_allocate_buffer(self);
 
+        ret.append(&mut VERSIONED_MARKER.to_bytes()?);
+        ret.append(&mut VERSION_WITH_HOST_SIDE_COSTS.to_bytes()?);
         ret.append(&mut self.max_memory.to_bytes()?);
         ret.append(&mut self.max_stack_height.to_bytes()?);
         ret.append(&mut self.opcode_costs.to_bytes()?);
         ret.append(&mut self.storage_costs.to_bytes()?);
         ret.append(&mut self.host_function_costs.to_bytes()?);
+        ret.append(&mut self.host_side_costs.to_bytes()?);
 
         Ok(ret)
     }
 
     fn serialized_length(&self) -> usize {
-        self.max_memory.serialized_length()
+        VERSIONED_MARKER.serialized_length()
+            + VERSION_WITH_HOST_SIDE_COSTS.serialized_length()
+            + self.max_memory.serialized_length()
             + self.max_stack_height.serialized_length()
             + self.opcode_costs.serialized_length()
             + self.storage_costs.serialized_length()
             + self.host_function_costs.serialized_length()
+            + self.host_side_costs.serialized_length()
     }
 }
 
 impl FromBytes for WasmConfig {
     fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
-        let (max_memory, rem) = FromBytes::from_bytes(bytes)?;
+        let (marker, rem): (u32, _) = FromBytes::from_bytes(bytes)?;
+        let (max_memory, rem, versioned) = if marker == VERSIONED_MARKER {
+            let (version, rem): (u8, _) = FromBytes::from_bytes(rem)?;
+            if version != VERSION_WITH_HOST_SIDE_COSTS {
+                return Err(bytesrepr::Error::Formatting);
+            }
+            let (max_memory, rem) = FromBytes::from_bytes(rem)?;
+            (max_memory, rem, true)
+        } else {
+            (marker, rem, false)
+        };
         let (max_stack_height, rem) = FromBytes::from_bytes(rem)?;
         let (opcode_costs, rem) = FromBytes::from_bytes(rem)?;
         let (storage_costs, rem) = FromBytes::from_bytes(rem)?;
-        let (host_function_costs, rem) = FromBytes::from_bytes(rem)?;
+        // Unversioned encodings predate `emit_event` and host-side costs, which were free then.
+        let (host_function_costs, rem) = if versioned {
+            FromBytes::from_bytes(rem)?
+        } else {
+            HostFunctionCosts::from_bytes_without_emit_event(rem)?
+        };
+        let (host_side_costs, rem) = if versioned {
+            FromBytes::from_bytes(rem)?
+        } else {
+            (HostSideCosts::default(), rem)
+        };
 
         Ok((
             WasmConfig {
@@ -104,6 +148,7 @@ impl FromBytes for WasmConfig {
                 opcode_costs,
                 storage_costs,
                 host_function_costs,
+                host_side_costs,
             },
             rem,
         ))
@@ -118,17 +163,71 @@ impl Distribution<WasmConfig> for Standard {
             opcode_costs: rng.gen(),
             storage_costs: rng.gen(),
             host_function_costs: rng.gen(),
+            host_side_costs: rng.gen(),
         }
     }
 }
 
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn should_serialize_and_deserialize() {
+        let wasm_config: WasmConfig = rand::thread_rng().gen();
+        bytesrepr::test_serialization_roundtrip(&wasm_config);
+    }
+
+    #[test]
+    fn should_deserialize_unversioned_encoding_without_host_side_costs() {
+        let expected = WasmConfig::new(
+            17,
+            DEFAULT_MAX_STACK_HEIGHT,
+            OpcodeCosts::default(),
+            StorageCosts::default(),
+            HostFunctionCosts::default(),
+            HostSideCosts::default(),
+        );
+        // The encoding written before host-side costs and `emit_event` were introduced, followed by
+        // the next field of the enclosing record.
+        let host_function_costs = expected.take_host_function_costs();
+        let mut legacy_host_function_costs = host_function_costs.to_bytes().unwrap();
+        // `emit_event` is the last host function.
+        legacy_host_function_costs.truncate(
+            legacy_host_function_costs.len() - host_function_costs.emit_event.serialized_length(),
+        );
+        let mut bytes = Vec::new();
+        bytes.append(&mut expected.max_memory.to_bytes().unwrap());
+        bytes.append(&mut expected.max_stack_height.to_bytes().unwrap());
+        bytes.append(&mut expected.opcode_costs().to_bytes().unwrap());
+        bytes.append(&mut expected.storage_costs().to_bytes().unwrap());
+        bytes.append(&mut legacy_host_function_costs);
+        bytes.push(0xff);
+
+        let (wasm_config, rem) = WasmConfig::from_bytes(&bytes).unwrap();
+        assert_eq!(wasm_config, expected);
+        assert_eq!(rem, &[0xff]);
+    }
+
+    #[test]
+    fn should_not_deserialize_unknown_version() {
+        let mut bytes = WasmConfig::default().to_bytes().unwrap();
+        bytes[VERSIONED_MARKER.serialized_length()] = VERSION_WITH_HOST_SIDE_COSTS + 1;
+        assert_eq!(
+            WasmConfig::from_bytes(&bytes).unwrap_err(),
+            bytesrepr::Error::Formatting
+        );
+    }
+}
+
 #[cfg(any(feature = "gens", test))]
 pub mod gens {
     use proptest::{num, prop_compose};
 
     use super::WasmConfig;
     use crate::shared::{
-        host_function_costs::gens::host_function_costs_arb, opcode_costs::gens::opcode_costs_arb,
+        host_function_costs::gens::host_function_costs_arb,
+        host_side_costs::gens::host_side_costs_arb, opcode_costs::gens::opcode_costs_arb,
         storage_costs::gens::storage_costs_arb,
     };
 
@@ -139,6 +238,7 @@ pub mod gens {
             opcode_costs in opcode_costs_arb(),
             storage_costs in storage_costs_arb(),
             host_function_costs in host_function_costs_arb(),
+            host_side_costs in host_side_costs_arb(),
         ) -> WasmConfig {
             WasmConfig {
                 max_memory,
@@ -146,6 +246,7 @@ pub mod gens {
                 opcode_costs,
                 storage_costs,
                 host_function_costs,
+                host_side_costs,
             }
         }
     }
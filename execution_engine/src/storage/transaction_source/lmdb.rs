@@ -74,10 +74,21 @@ impl LmdbEnvironment {
         path: P,
         map_size: usize,
         max_readers: u32,
+    ) -> Result<Self, error::Error> {
+        Self::with_flags(path, map_size, max_readers, EnvironmentFlags::empty())
+    }
+
+    /// Creates an environment opened with `flags` in addition to the default ones, e.g.
+    /// `EnvironmentFlags::NO_SYNC` to leave flushing commits to disk to the caller.
+    pub fn with_flags<P: AsRef<Path>>(
+        path: P,
+        map_size: usize,
+        max_readers: u32,
+        flags: EnvironmentFlags,
     ) -> Result<Self, error::Error> {
         let env = Environment::new()
             // Set the flag to manage our own directory like in the storage component.
-            .set_flags(EnvironmentFlags::NO_SUB_DIR)
+            .set_flags(EnvironmentFlags::NO_SUB_DIR | flags)
             .set_max_dbs(MAX_DBS)
             .set_map_size(map_size)
             .set_max_readers(max_readers)
@@ -5,7 +5,8 @@ use casper_execution_engine::{
     shared::newtypes::Blake2bHash,
 };
 use casper_types::{
-    account::AccountHash, ContractHash, ContractVersion, DeployHash, HashAddr, RuntimeArgs,
+    account::AccountHash, ContractHash, ContractVersion, DeployHash, HashAddr, ProtocolVersion,
+    RuntimeArgs,
 };
 
 use crate::internal::{utils, DEFAULT_GAS_PRICE};
@@ -18,6 +19,7 @@ struct DeployItemData {
     pub gas_price: u64,
     pub authorization_keys: BTreeSet<AccountHash>,
     pub deploy_hash: DeployHash,
+    pub min_protocol_version: Option<ProtocolVersion>,
 }
 
 pub struct DeployItemBuilder {
@@ -205,6 +207,11 @@ impl DeployItemBuilder {
         self
     }
 
+    pub fn with_min_protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.deploy_item.min_protocol_version = Some(protocol_version);
+        self
+    }
+
     pub fn build(self) -> DeployItem {
         DeployItem {
             address: self
@@ -222,6 +229,7 @@ impl DeployItemBuilder {
             gas_price: self.deploy_item.gas_price,
             authorization_keys: self.deploy_item.authorization_keys,
             deploy_hash: self.deploy_item.deploy_hash,
+            min_protocol_version: self.deploy_item.min_protocol_version,
         }
     }
 }
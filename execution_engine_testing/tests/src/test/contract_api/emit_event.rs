@@ -0,0 +1,66 @@
+use casper_engine_test_support::{
+    internal::{ExecuteRequestBuilder, InMemoryWasmTestBuilder, DEFAULT_RUN_GENESIS_REQUEST},
+    DEFAULT_ACCOUNT_ADDR,
+};
+use casper_types::{runtime_args, CLValue, Key, RuntimeArgs, U512};
+
+const CONTRACT_EMIT_EVENT: &str = "emit_event.wasm";
+const EVENT_NAME: &str = "amount_recorded";
+const ARG_AMOUNT: &str = "amount";
+const ARG_REVERT: &str = "revert";
+
+fn exec_emit_event(amount: U512, revert: bool) -> InMemoryWasmTestBuilder {
+    let exec_request = ExecuteRequestBuilder::standard(
+        *DEFAULT_ACCOUNT_ADDR,
+        CONTRACT_EMIT_EVENT,
+        runtime_args! { ARG_AMOUNT => amount, ARG_REVERT => revert },
+    )
+    .build();
+
+    let mut builder = InMemoryWasmTestBuilder::default();
+    builder
+        .run_genesis(&DEFAULT_RUN_GENESIS_REQUEST)
+        .exec(exec_request);
+    builder
+}
+
+#[ignore]
+#[test]
+fn should_record_emitted_event() {
+    let amount = U512::from(42);
+    let mut builder = exec_emit_event(amount, false);
+    builder.commit().expect_success();
+
+    let exec_result = builder
+        .get_exec_result(0)
+        .expect("should have exec result")
+        .first()
+        .cloned()
+        .expect("should have response");
+
+    let events = exec_result.events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].emitter,
+        Key::Account(*DEFAULT_ACCOUNT_ADDR).to_formatted_string()
+    );
+    assert_eq!(events[0].name, EVENT_NAME);
+    assert_eq!(events[0].data, CLValue::from_t(amount).unwrap());
+}
+
+#[ignore]
+#[test]
+fn should_discard_events_of_failed_execution() {
+    let mut builder = exec_emit_event(U512::from(42), true);
+    builder.commit();
+
+    let exec_result = builder
+        .get_exec_result(0)
+        .expect("should have exec result")
+        .first()
+        .cloned()
+        .expect("should have response");
+
+    assert!(exec_result.is_failure());
+    assert!(exec_result.events().is_empty());
+}
@@ -1,6 +1,7 @@
 mod account;
 mod blake2b;
 mod create_purse;
+mod emit_event;
 mod get_arg;
 mod get_blocktime;
 mod get_caller;
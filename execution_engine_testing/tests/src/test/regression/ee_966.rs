@@ -16,6 +16,7 @@ use casper_execution_engine::{
     },
     shared::{
         host_function_costs::HostFunctionCosts,
+        host_side_costs::HostSideCosts,
         opcode_costs::OpcodeCosts,
         storage_costs::StorageCosts,
         wasm_config::{WasmConfig, DEFAULT_MAX_STACK_HEIGHT, DEFAULT_WASM_MAX_MEMORY},
@@ -36,6 +37,7 @@ static DOUBLED_WASM_MEMORY_LIMIT: Lazy<WasmConfig> = Lazy::new(|| {
         OpcodeCosts::default(),
         StorageCosts::default(),
         HostFunctionCosts::default(),
+        HostSideCosts::default(),
     )
 });
 static NEW_PROTOCOL_VERSION: Lazy<ProtocolVersion> = Lazy::new(|| {
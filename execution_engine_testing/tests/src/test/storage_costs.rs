@@ -15,6 +15,7 @@ use casper_execution_engine::{
     core::engine_state::upgrade::ActivationPoint,
     shared::{
         host_function_costs::{HostFunction, HostFunctionCosts},
+        host_side_costs::HostSideCosts,
         opcode_costs::OpcodeCosts,
         storage_costs::StorageCosts,
         stored_value::StoredValue,
@@ -124,6 +125,7 @@ static NEW_HOST_FUNCTION_COSTS: Lazy<HostFunctionCosts> = Lazy::new(|| HostFunct
     remove_contract_user_group_urefs: HostFunction::fixed(0),
     print: HostFunction::fixed(0),
     blake2b: HostFunction::fixed(0),
+    emit_event: HostFunction::fixed(0),
 });
 static STORAGE_COSTS_ONLY: Lazy<WasmConfig> = Lazy::new(|| {
     WasmConfig::new(
@@ -132,6 +134,7 @@ static STORAGE_COSTS_ONLY: Lazy<WasmConfig> = Lazy::new(|| {
         NEW_OPCODE_COSTS,
         StorageCosts::default(),
         *NEW_HOST_FUNCTION_COSTS,
+        HostSideCosts::default(),
     )
 });
 static NEW_PROTOCOL_VERSION: Lazy<ProtocolVersion> = Lazy::new(|| {
@@ -7,6 +7,7 @@ use casper_execution_engine::{
     core::engine_state::upgrade::ActivationPoint,
     shared::{
         host_function_costs::HostFunctionCosts,
+        host_side_costs::HostSideCosts,
         opcode_costs::{
             OpcodeCosts, DEFAULT_ADD_COST, DEFAULT_BIT_COST, DEFAULT_CONST_COST,
             DEFAULT_CONTROL_FLOW_COST, DEFAULT_CONVERSION_COST, DEFAULT_CURRENT_MEMORY_COST,
@@ -59,6 +60,7 @@ fn get_upgraded_wasm_config() -> WasmConfig {
         opcode_cost,
         storage_costs,
         host_function_costs,
+        HostSideCosts::default(),
     )
 }
 
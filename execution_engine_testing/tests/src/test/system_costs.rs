@@ -13,6 +13,7 @@ use casper_execution_engine::{
     shared::{
         gas::Gas,
         host_function_costs::{Cost, HostFunction, HostFunctionCosts},
+        host_side_costs::HostSideCosts,
         motes::Motes,
         opcode_costs::OpcodeCosts,
         storage_costs::StorageCosts,
@@ -831,6 +832,7 @@ fn should_verify_wasm_add_bid_wasm_cost_is_not_recursive() {
         remove_contract_user_group_urefs: HostFunction::fixed(0),
         print: HostFunction::fixed(0),
         blake2b: HostFunction::fixed(0),
+        emit_event: HostFunction::fixed(0),
     };
 
     let new_wasm_config = WasmConfig::new(
@@ -839,6 +841,7 @@ fn should_verify_wasm_add_bid_wasm_cost_is_not_recursive() {
         new_opcode_costs,
         new_storage_costs,
         new_host_function_costs,
+        HostSideCosts::default(),
     );
 
     let new_wasmless_transfer_cost = 0;
@@ -0,0 +1,68 @@
+//! Measures how long another task sharing the reactor's thread waits to be polled while the
+//! reactor handles a busy queue, with and without yielding.
+//!
+//! Run with `cargo bench -p casper-node --bench yield_latency`.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::runtime::Builder;
+
+use casper_node::reactor::{YieldBudget, YieldPolicyConfig};
+
+/// Time the simulated reactor spends handling a single event.
+const EVENT_DURATION: Duration = Duration::from_micros(100);
+
+/// Number of events the simulated reactor handles per run.
+const EVENT_COUNT: u32 = 2_000;
+
+/// Returns the longest time another task on the same thread had to wait to be polled while a
+/// simulated reactor handled a busy queue under `config`.
+async fn max_latency_of_other_task(config: &YieldPolicyConfig) -> Duration {
+    let done = Arc::new(AtomicBool::new(false));
+    let probe_done = Arc::clone(&done);
+    let probe = tokio::spawn(async move {
+        let mut max_latency = Duration::default();
+        while !probe_done.load(Ordering::SeqCst) {
+            let before = Instant::now();
+            tokio::task::yield_now().await;
+            max_latency = max_latency.max(before.elapsed());
+        }
+        max_latency
+    });
+    // Let the probe start.
+    tokio::task::yield_now().await;
+
+    let mut budget = YieldBudget::new(config);
+    for _ in 0..EVENT_COUNT {
+        let started = Instant::now();
+        while started.elapsed() < EVENT_DURATION {}
+        budget.consume().await;
+    }
+    done.store(true, Ordering::SeqCst);
+    probe.await.expect("probe should not panic")
+}
+
+fn main() {
+    let mut runtime = Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()
+        .expect("should build runtime");
+    for &max_events_per_yield in &[0, 64, 32, 8, 1] {
+        let config = YieldPolicyConfig {
+            max_events_per_yield,
+            ..Default::default()
+        };
+        let max_latency = runtime.block_on(max_latency_of_other_task(&config));
+        println!(
+            "max_events_per_yield = {:>2}: max latency of another task {:?}",
+            max_events_per_yield, max_latency
+        );
+    }
+}
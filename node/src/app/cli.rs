@@ -3,26 +3,34 @@
 //! Most configuration is done via config files (see [`config`](../config/index.html) for details).
 
 pub mod arglang;
+pub mod ban_list;
+pub mod keygen;
+pub mod profile;
 
 use std::{
     env, fs,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::atomic::Ordering,
 };
 
 use anyhow::{self, bail, Context};
 use regex::Regex;
 use structopt::StructOpt;
 use toml::{value::Table, Value};
-use tracing::{info, trace};
+use tracing::{info, trace, warn};
 
+use self::profile::Profile;
 use crate::config;
 use casper_node::{
     logging,
-    reactor::{initializer, joiner, validator, Runner},
+    reactor::{self, initializer, joiner, read_replica, validator, Runner},
     setup_signal_hooks,
+    types::FeatureFlags,
     utils::WithDir,
+    DeployFilter, TERMINATION_REQUESTED,
 };
+use casper_types::{AsymmetricType, PublicKey};
 use prometheus::Registry;
 
 // We override the standard allocator to gather metrics and tune the allocator via th MALLOC_CONF
@@ -37,11 +45,26 @@ static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 pub enum Cli {
     /// Run the validator node.
     ///
-    /// Loads the configuration values from the given configuration file or uses defaults if not
-    /// given, then runs the reactor.
+    /// Loads the configuration values from the given configuration file, the given profile or
+    /// both, then runs the reactor. Values in the configuration file take precedence over those of
+    /// the profile, hence it only needs to contain the deviating ones if a profile is given.
     Validator {
+        /// Named network profile to take the configuration from: mainnet, testnet or local.
+        #[structopt(long)]
+        profile: Option<Profile>,
+
         /// Path to configuration file.
-        config: PathBuf,
+        config: Option<PathBuf>,
+
+        /// Run a single-node developer network with pre-funded accounts and rounds of about a
+        /// second, set up in the directory given by --dev-dir. Starts a new chain every time,
+        /// based on the local profile unless a profile or configuration file is given.
+        #[structopt(long)]
+        dev: bool,
+
+        /// Directory to keep the keys and data of the developer network in.
+        #[structopt(long, default_value = "casper-dev")]
+        dev_dir: PathBuf,
 
         #[structopt(
             short = "C",
@@ -54,6 +77,29 @@ pub enum Cli {
         /// <SECTION>.<KEY>=<VALUE>.  For example, '-C=node.chainspec_config_path=chainspec.toml'
         config_ext: Vec<ConfigExt>,
     },
+    /// Run a read replica serving JSON-RPC queries from the storage of a running node.
+    ///
+    /// Uses the same configuration file as the node, which must allow read replicas through
+    /// `storage.max_read_replicas`. Queries are served on `read_replica.address`.
+    ReadReplica {
+        /// Named network profile to take the configuration from: mainnet, testnet or local.
+        #[structopt(long)]
+        profile: Option<Profile>,
+
+        /// Path to configuration file.
+        config: Option<PathBuf>,
+
+        #[structopt(
+            short = "C",
+            long,
+            env = "NODE_CONFIG",
+            use_delimiter(true),
+            value_delimiter(";")
+        )]
+        /// Overrides and extensions for configuration file entries in the form
+        /// <SECTION>.<KEY>=<VALUE>.  For example, '-C=read_replica.address=0.0.0.0:7778'
+        config_ext: Vec<ConfigExt>,
+    },
     /// Migrate modified values from the old config as required after an upgrade.
     MigrateConfig {
         /// Path to configuration file of previous version of node.
@@ -72,6 +118,77 @@ pub enum Cli {
         #[structopt(long)]
         new_config: PathBuf,
     },
+    /// Run WASM conformance fixtures against the contract runtime and report any differences.
+    ///
+    /// Each JSON file in the fixtures directory contains a "deploy" and its "expected" execution
+    /// result, which is compared with the result of executing the deploy right after genesis.
+    Conformance {
+        /// Path to the chainspec to run genesis with.
+        #[structopt(short, long)]
+        chainspec: PathBuf,
+        /// Directory containing the fixtures.
+        fixtures: PathBuf,
+        /// Replace the expected results of fixtures which do not conform with the actual ones.
+        #[structopt(long)]
+        update: bool,
+    },
+    /// Export the deploys in the storage of a stopped node to a portable archive.
+    ///
+    /// The archive can be imported into the storage of a node on another network with
+    /// `import-deploys`.
+    ExportDeploys {
+        /// Path to configuration file of the node.
+        config: PathBuf,
+        /// File to write the archive to.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Only export the deploys included in blocks of this era.
+        #[structopt(long)]
+        era: Option<u64>,
+        /// Only export the deploys of this account, given as a hex-encoded public key.
+        #[structopt(long)]
+        account: Option<String>,
+    },
+    /// Import the deploys of an archive into the storage of a stopped node.
+    ///
+    /// Each deploy is revalidated against the node's chainspec as if it was received at the time
+    /// it was created, and rejected if it is not acceptable, e.g. since it is for a different
+    /// chain.
+    ImportDeploys {
+        /// Path to configuration file of the node.
+        config: PathBuf,
+        /// The archive to import.
+        input: PathBuf,
+    },
+    /// Replay the events of a queue dump into a sandboxed node, to reproduce a crash offline.
+    ///
+    /// The node is set up from the configuration file, but with its storage in a temporary
+    /// directory and its network isolated on localhost. The dump is the `queue_dump_replay-*.bin`
+    /// file written alongside every queue dump.
+    ReplayQueueDump {
+        /// Path to configuration file of the node which wrote the dump.
+        config: PathBuf,
+        /// The replayable queue dump.
+        dump: PathBuf,
+        /// Maximum number of events to process, including those caused by the replayed ones.
+        #[structopt(long, default_value = "100000")]
+        max_events: u64,
+    },
+    /// Print the parameters differing between two chainspec versions.
+    ///
+    /// The parameters in effect at the latest protocol version of each chainspec are compared.
+    /// Changes made by an upgrade point, which all nodes have to switch to at its activation
+    /// point, are flagged with `!`.
+    DiffChainspecs {
+        /// Path to the old chainspec file.
+        old: PathBuf,
+        /// Path to the new chainspec file.
+        new: PathBuf,
+    },
+    /// Generate, inspect and convert validator keys and network TLS identities.
+    Keygen(keygen::Keygen),
+    /// Sign and inspect ban lists shared with other operators.
+    BanList(ban_list::BanList),
 }
 
 #[derive(Debug)]
@@ -139,12 +256,42 @@ impl Cli {
     /// Executes selected CLI command.
     pub async fn run(self) -> anyhow::Result<()> {
         match self {
-            Cli::Validator { config, config_ext } => {
+            Cli::Validator {
+                profile,
+                config,
+                dev,
+                dev_dir,
+                config_ext,
+            } => {
                 // Setup UNIX signal hooks.
                 setup_signal_hooks();
 
-                let validator_config = Self::init(&config, config_ext)?;
-                info!(version = %env!("CARGO_PKG_VERSION"), "node starting up");
+                let (profile, dev_dir) = if dev {
+                    let profile = match (profile, &config) {
+                        (None, None) => Some(Profile::Local),
+                        _ => profile,
+                    };
+                    (profile, Some(env::current_dir()?.join(dev_dir)))
+                } else {
+                    (profile, None)
+                };
+                let validator_config = Self::init_with_profile(
+                    profile,
+                    config.as_deref(),
+                    config_ext,
+                    dev_dir.as_deref(),
+                )?;
+                info!(version = %env!("CARGO_PKG_VERSION"), ?profile, "node starting up");
+
+                let root = Self::root(config.as_deref())?;
+                let queue_snapshot_path = validator_config
+                    .value()
+                    .node
+                    .queue_snapshot_path
+                    .as_ref()
+                    .map(|path| root.join(path));
+                let shutdown_config = validator_config.value().shutdown.clone();
+                reactor::start_watchdog(&validator_config.value().watchdog);
 
                 // We use a `ChaCha20Rng` for the production node. For one, we want to completely
                 // eliminate any chance of runtime failures, regardless of how small (these
@@ -172,6 +319,11 @@ impl Cli {
 
                 initializer_runner.run(&mut rng).await;
 
+                if TERMINATION_REQUESTED.load(Ordering::SeqCst) {
+                    info!("terminating during initialization");
+                    return Ok(());
+                }
+
                 info!("finished initialization");
 
                 let initializer = initializer_runner.into_inner();
@@ -179,10 +331,6 @@ impl Cli {
                     bail!("failed to initialize successfully");
                 }
 
-                let root = config
-                    .parent()
-                    .map(|path| path.to_owned())
-                    .unwrap_or_else(|| "/".into());
                 let mut joiner_runner = Runner::<joiner::Reactor>::with_metrics(
                     WithDir::new(root, initializer),
                     &mut rng,
@@ -191,13 +339,52 @@ impl Cli {
                 .await?;
                 joiner_runner.run(&mut rng).await;
 
+                if TERMINATION_REQUESTED.load(Ordering::SeqCst) {
+                    info!("terminating during joining");
+                    return Ok(());
+                }
+
                 info!("finished joining");
 
                 let config = joiner_runner.into_inner().into_validator_config().await;
 
                 let mut validator_runner =
                     Runner::<validator::Reactor>::with_metrics(config, &mut rng, &registry).await?;
+                if let Some(path) = &queue_snapshot_path {
+                    if let Err(error) = validator_runner.replay_queues(path).await {
+                        warn!(path = %path.display(), %error, "could not replay event queues");
+                    }
+                }
                 validator_runner.run(&mut rng).await;
+
+                if TERMINATION_REQUESTED.load(Ordering::SeqCst) {
+                    validator_runner.shutdown(&mut rng, &shutdown_config).await;
+                }
+
+                if let Some(path) = &queue_snapshot_path {
+                    if TERMINATION_REQUESTED.load(Ordering::SeqCst) {
+                        if let Err(error) = validator_runner.persist_queues(path).await {
+                            warn!(path = %path.display(), %error, "could not persist event queues");
+                        }
+                    }
+                }
+            }
+            Cli::ReadReplica {
+                profile,
+                config,
+                config_ext,
+            } => {
+                setup_signal_hooks();
+
+                let config = Self::init_with_profile(profile, config.as_deref(), config_ext, None)?;
+                info!(version = %env!("CARGO_PKG_VERSION"), "read replica starting up");
+
+                let mut rng = casper_node::new_rng();
+                let registry = Registry::new();
+                let mut runner =
+                    Runner::<read_replica::ReadReplica>::with_metrics(config, &mut rng, &registry)
+                        .await?;
+                runner.run(&mut rng).await;
             }
             Cli::MigrateConfig {
                 old_config,
@@ -235,6 +422,95 @@ impl Cli {
                 info!(version = %env!("CARGO_PKG_VERSION"), "migrating data");
                 casper_node::migrate_data(WithDir::new(old_root, old_config), new_config)?;
             }
+            Cli::Conformance {
+                chainspec,
+                fixtures,
+                update,
+            } => {
+                let outcomes =
+                    casper_node::run_conformance_fixtures(&chainspec, &fixtures, update)?;
+                let mut failed = 0;
+                for outcome in &outcomes {
+                    if outcome.diffs.is_empty() {
+                        println!("{}: ok", outcome.name);
+                        continue;
+                    }
+                    if outcome.updated {
+                        println!("{}: updated", outcome.name);
+                    } else {
+                        println!("{}: FAILED", outcome.name);
+                        failed += 1;
+                    }
+                    for diff in &outcome.diffs {
+                        println!("    {}", diff);
+                    }
+                }
+                if failed > 0 {
+                    bail!("{} of {} fixtures failed", failed, outcomes.len());
+                }
+            }
+            Cli::ExportDeploys {
+                config,
+                output,
+                era,
+                account,
+            } => {
+                let config = Self::init(&config, vec![])?;
+                let account = account
+                    .map(|hex| {
+                        PublicKey::from_hex(&hex).map_err(|error| {
+                            anyhow::anyhow!("invalid account public key {}: {}", hex, error)
+                        })
+                    })
+                    .transpose()?;
+                let filter = DeployFilter {
+                    era_id: era,
+                    account,
+                };
+                let exported = casper_node::export_deploys(config, &filter, &output)?;
+                println!("exported {} deploys to {}", exported, output.display());
+            }
+            Cli::ImportDeploys { config, input } => {
+                let config = Self::init(&config, vec![])?;
+                let summary = casper_node::import_deploys(config, &input)?;
+                println!(
+                    "imported {} deploys, {} already stored, {} rejected",
+                    summary.imported, summary.already_stored, summary.rejected
+                );
+            }
+            Cli::ReplayQueueDump {
+                config,
+                dump,
+                max_events,
+            } => {
+                setup_signal_hooks();
+
+                let config = Self::init(&config, vec![])?;
+                let summary = casper_node::replay_queue_dump(config, &dump, max_events).await?;
+                println!(
+                    "replayed {} events, processed {} events in total",
+                    summary.replayed, summary.processed
+                );
+            }
+            Cli::DiffChainspecs { old, new } => {
+                let diff = casper_node::diff_chainspecs(&old, &new)?;
+                println!(
+                    "{} parameters changed from protocol version {} to {}",
+                    diff.changes.len(),
+                    diff.old_version,
+                    diff.new_version
+                );
+                for change in &diff.changes {
+                    let flag = if change.requires_coordinated_activation() {
+                        '!'
+                    } else {
+                        ' '
+                    };
+                    println!("{} {}", flag, change);
+                }
+            }
+            Cli::Keygen(keygen) => keygen.run()?,
+            Cli::BanList(ban_list) => ban_list.run()?,
         }
 
         Ok(())
@@ -245,21 +521,55 @@ impl Cli {
         config: &Path,
         config_ext: Vec<ConfigExt>,
     ) -> anyhow::Result<WithDir<validator::Config>> {
-        // Determine the parent directory of the configuration file, if any.
-        // Otherwise, we default to `/`.
-        let root = config
-            .parent()
-            .map(|path| path.to_owned())
-            .unwrap_or_else(|| "/".into());
-
-        // The app supports running without a config file, using default values.
-        let encoded_config = fs::read_to_string(&config)
-            .context("could not read configuration file")
-            .with_context(|| config.display().to_string())?;
-
-        // Get the TOML table version of the config indicated from CLI args, or from a new
-        // defaulted config instance if one is not provided.
-        let mut config_table: Value = toml::from_str(&encoded_config)?;
+        Self::init_with_profile(None, Some(config), config_ext, None)
+    }
+
+    /// Returns the directory relative paths in the configuration are resolved against.
+    ///
+    /// This is the parent directory of the configuration file, defaulting to `/` if it has none.
+    /// Without a configuration file, it is the current working directory.
+    fn root(config: Option<&Path>) -> anyhow::Result<PathBuf> {
+        match config {
+            Some(config) => Ok(config
+                .parent()
+                .map(|path| path.to_owned())
+                .unwrap_or_else(|| "/".into())),
+            None => env::current_dir().context("could not determine current directory"),
+        }
+    }
+
+    /// Parses the config file and/or profile for the current version of casper-node, and
+    /// initializes logging.
+    ///
+    /// The values of the config file are applied on top of those of the profile. If a developer
+    /// directory is given, a developer network is set up in it and the config is adjusted to run
+    /// it.
+    fn init_with_profile(
+        profile: Option<Profile>,
+        config: Option<&Path>,
+        config_ext: Vec<ConfigExt>,
+        dev_dir: Option<&Path>,
+    ) -> anyhow::Result<WithDir<validator::Config>> {
+        let root = Self::root(config)?;
+
+        // Get the TOML table version of the profile, if one is selected.
+        let mut config_table = match profile {
+            Some(profile) => profile.config_table()?,
+            None => Value::Table(Table::new()),
+        };
+
+        match config {
+            Some(config) => {
+                let encoded_config = fs::read_to_string(&config)
+                    .context("could not read configuration file")
+                    .with_context(|| config.display().to_string())?;
+                profile::merge(&mut config_table, toml::from_str(&encoded_config)?);
+            }
+            None if profile.is_none() => {
+                bail!("either a configuration file or a profile (--profile) must be given")
+            }
+            None => (),
+        }
 
         // If any command line overrides to the config values are passed, apply them.
         for item in config_ext {
@@ -267,9 +577,32 @@ impl Cli {
         }
 
         // Create validator config, including any overridden values.
-        let validator_config: validator::Config = config_table.try_into()?;
-        logging::init_with_config(&validator_config.logging)?;
+        let mut validator_config: validator::Config = config_table.try_into()?;
+        logging::init_with_config_in(&root, &validator_config.logging)?;
+
+        if let Some(dev_dir) = dev_dir {
+            let dev_node = casper_node::setup_dev_node(&mut validator_config, dev_dir)?;
+            info!(
+                dir = %dev_dir.display(),
+                validator = %dev_node.validator.to_hex(),
+                "set up developer network"
+            );
+            for account in &dev_node.accounts {
+                info!(
+                    public_key = %account.public_key.to_hex(),
+                    secret_key = %account.secret_key_path.display(),
+                    "pre-funded account"
+                );
+            }
+        }
         trace!("{}", config::to_string(&validator_config)?);
+        info!(
+            features = %FeatureFlags::from_config(&validator_config),
+            "feature flags"
+        );
+
+        // Report all configuration problems at once, rather than failing at the first use of each.
+        validator_config.validate(&root)?;
 
         Ok(WithDir::new(root, validator_config))
     }
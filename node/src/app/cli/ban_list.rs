@@ -0,0 +1,76 @@
+//! The `ban-list` subcommands.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::Context;
+use structopt::StructOpt;
+
+use casper_node::{
+    crypto::AsymmetricKeyExt,
+    types::{BanEntry, SignedBanList},
+};
+use casper_types::SecretKey;
+
+/// Sign and inspect ban lists shared with other operators.
+#[derive(Debug, StructOpt)]
+pub enum BanList {
+    /// Sign a list of bans with an operator secret key.
+    ///
+    /// The entries are read from a JSON array of objects with a "target" (`{"node_id": ...}` or
+    /// `{"ip": ...}`), a "reason" and an optional "expires" timestamp.
+    Sign {
+        /// JSON file containing the entries to sign.
+        entries: PathBuf,
+        /// PEM-encoded secret key of the issuing operator.
+        #[structopt(short, long)]
+        secret_key: PathBuf,
+        /// File to write the signed ban list to, instead of printing it.
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Verify a signed ban list and print its issuer and entries.
+    Inspect {
+        /// JSON file containing the signed ban list.
+        input: PathBuf,
+    },
+}
+
+impl BanList {
+    /// Executes the selected `ban-list` subcommand.
+    pub fn run(self) -> anyhow::Result<()> {
+        match self {
+            BanList::Sign {
+                entries,
+                secret_key,
+                output,
+            } => {
+                let data = fs::read(&entries)
+                    .with_context(|| format!("could not read '{}'", entries.display()))?;
+                let entries: Vec<BanEntry> =
+                    serde_json::from_slice(&data).context("could not parse ban list entries")?;
+                let secret_key = SecretKey::from_file(&secret_key)?;
+                let mut rng = casper_node::new_rng();
+                let json = SignedBanList::sign(entries, &secret_key, &mut rng).to_json();
+                match output {
+                    Some(path) => fs::write(&path, json)
+                        .with_context(|| format!("could not write '{}'", path.display()))?,
+                    None => println!("{}", json),
+                }
+            }
+            BanList::Inspect { input } => {
+                let ban_list = SignedBanList::from_file(&input)?;
+                println!("issuer: {}", ban_list.issuer());
+                println!("created: {}", ban_list.created());
+                for entry in ban_list.entries() {
+                    match entry.expires {
+                        Some(expires) => {
+                            println!("{} until {}: {}", entry.target, expires, entry.reason)
+                        }
+                        None => println!("{}: {}", entry.target, entry.reason),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! The `keygen` subcommands.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::Context;
+use structopt::StructOpt;
+
+use casper_node::keygen::{self, KeyAlgorithm, KeyFormat, KeyMaterial};
+
+/// Generate, inspect and convert validator keys and network TLS identities.
+#[derive(Debug, StructOpt)]
+pub enum Keygen {
+    /// Generate a validator key pair.
+    ///
+    /// Writes "secret_key.pem", "public_key.pem" and "public_key_hex" to the output directory and
+    /// prints the public key.
+    Validator {
+        /// Directory to write the key files to. Created if it does not exist.
+        output_dir: PathBuf,
+        /// Signature algorithm of the key pair: "ed25519" or "secp256k1".
+        #[structopt(short, long, default_value = "ed25519")]
+        algorithm: KeyAlgorithm,
+        /// Overwrite existing key files.
+        #[structopt(short, long)]
+        force: bool,
+    },
+    /// Generate a network TLS identity.
+    ///
+    /// Writes "node_cert.pem" and "node_key.pem" to the output directory and prints the resulting
+    /// node ID.
+    Network {
+        /// Directory to write the identity files to. Created if it does not exist.
+        output_dir: PathBuf,
+        /// Overwrite existing identity files.
+        #[structopt(short, long)]
+        force: bool,
+    },
+    /// Print the public key and account hash of a validator key, or the node ID and fingerprint
+    /// of a network certificate.
+    Inspect {
+        /// PEM- or hex-encoded key or certificate file.
+        input: PathBuf,
+        /// Decode hex input as a secret key rather than a public key.
+        #[structopt(long)]
+        secret: bool,
+    },
+    /// Convert a key or certificate between PEM and hex encoding.
+    Convert {
+        /// PEM- or hex-encoded key or certificate file.
+        input: PathBuf,
+        /// Target encoding: "pem" or "hex".
+        #[structopt(long)]
+        to: KeyFormat,
+        /// Decode hex input as a secret key rather than a public key.
+        #[structopt(long)]
+        secret: bool,
+        /// File to write the result to, instead of printing it.
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+impl Keygen {
+    /// Executes the selected `keygen` subcommand.
+    pub fn run(self) -> anyhow::Result<()> {
+        match self {
+            Keygen::Validator {
+                output_dir,
+                algorithm,
+                force,
+            } => {
+                let public_key = keygen::generate_validator_keys(&output_dir, algorithm, force)?;
+                println!("{}", KeyMaterial::PublicKey(public_key));
+            }
+            Keygen::Network { output_dir, force } => {
+                keygen::generate_network_identity(&output_dir, force)?;
+                let cert = output_dir.join(keygen::NETWORK_CERT_PEM);
+                println!("{}", KeyMaterial::from_file(cert, false)?);
+            }
+            Keygen::Inspect { input, secret } => {
+                println!("{}", KeyMaterial::from_file(input, secret)?);
+            }
+            Keygen::Convert {
+                input,
+                to,
+                secret,
+                output,
+            } => {
+                let encoded = KeyMaterial::from_file(input, secret)?.encode(to)?;
+                match output {
+                    Some(path) => fs::write(&path, encoded)
+                        .with_context(|| format!("could not write '{}'", path.display()))?,
+                    None => println!("{}", encoded.trim_end()),
+                }
+            }
+        }
+        Ok(())
+    }
+}
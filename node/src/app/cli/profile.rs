@@ -0,0 +1,131 @@
+//! Named network profiles.
+//!
+//! A profile bundles everything needed to join a network: the location of its chainspec, the
+//! addresses of its known nodes and defaults for all other configuration values, so that a node
+//! can be started with `--profile <NAME>` alone. A config file given as well only needs to contain
+//! the values deviating from the profile.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, Context};
+use toml::Value;
+
+/// Defaults of the profiles for public networks.
+const PRODUCTION_DEFAULTS: &str =
+    include_str!("../../../../resources/production/config-example.toml");
+/// Defaults of the profile for local networks.
+const LOCAL_DEFAULTS: &str = include_str!("../../../../resources/local/config.toml");
+
+/// A named network profile.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Profile {
+    /// The Casper main network.
+    Mainnet,
+    /// The Casper test network.
+    Testnet,
+    /// A local development network.
+    Local,
+}
+
+impl Profile {
+    /// The names of all profiles, as accepted on the command line.
+    pub const NAMES: &'static [&'static str] = &["mainnet", "testnet", "local"];
+
+    /// Returns the configuration of the profile as a TOML table.
+    ///
+    /// The profile's values are applied on top of the example configuration it is based on.
+    pub fn config_table(self) -> anyhow::Result<Value> {
+        let (defaults, profile) = match self {
+            Profile::Mainnet => (
+                PRODUCTION_DEFAULTS,
+                include_str!("../../../../resources/profiles/mainnet.toml"),
+            ),
+            Profile::Testnet => (
+                PRODUCTION_DEFAULTS,
+                include_str!("../../../../resources/profiles/testnet.toml"),
+            ),
+            Profile::Local => (
+                LOCAL_DEFAULTS,
+                include_str!("../../../../resources/profiles/local.toml"),
+            ),
+        };
+        let mut table: Value = toml::from_str(defaults)
+            .with_context(|| format!("could not parse defaults of profile {}", self))?;
+        let overlay =
+            toml::from_str(profile).with_context(|| format!("could not parse profile {}", self))?;
+        merge(&mut table, overlay);
+        Ok(table)
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Profile::Mainnet => "mainnet",
+            Profile::Testnet => "testnet",
+            Profile::Local => "local",
+        };
+        formatter.write_str(name)
+    }
+}
+
+impl FromStr for Profile {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "mainnet" => Ok(Profile::Mainnet),
+            "testnet" => Ok(Profile::Testnet),
+            "local" => Ok(Profile::Local),
+            _ => Err(anyhow!(
+                "unknown profile {:?}, expected one of {}",
+                input,
+                Profile::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Merges `overlay` into `base`, recursing into tables present in both and replacing all other
+/// values.
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_node::reactor::validator::Config;
+
+    use super::*;
+
+    #[test]
+    fn bundled_profiles_should_parse() {
+        for name in Profile::NAMES {
+            let profile: Profile = name.parse().unwrap();
+            assert_eq!(profile.to_string(), *name);
+            let _config: Config = profile.config_table().unwrap().try_into().unwrap();
+        }
+    }
+
+    #[test]
+    fn merge_should_only_replace_given_values() {
+        let mut base: Value = toml::from_str("[a]\nx = 1\ny = [1, 2]\n[b]\nz = 3\n").unwrap();
+        let overlay: Value = toml::from_str("[a]\ny = []\n[c]\nw = 4\n").unwrap();
+        merge(&mut base, overlay);
+        let expected: Value =
+            toml::from_str("[a]\nx = 1\ny = []\n[b]\nz = 3\n[c]\nw = 4\n").unwrap();
+        assert_eq!(base, expected);
+    }
+}
@@ -0,0 +1,323 @@
+//! # clogfmt
+//!
+//! Reads logs written by the node in JSON format (`logging.format = "json"`) from the given files
+//! or from stdin, and prints every record matching the given filters.
+//!
+//! `--level` keeps records at the given level or a more severe one, `--target` keeps records whose
+//! target is the given module or one of its submodules, e.g. `--target casper_node::reactor` keeps
+//! records of `casper_node::reactor::validator`, but not of `casper_node::reactor_foo`. Several
+//! targets can be given, keeping records matching any of them.
+//!
+//! `--deploy` keeps records logged within the `deploy` span of a deploy whose hash starts with the
+//! given hex string, following that deploy's lifecycle from acceptance through gossip, proposal and
+//! execution to finalization.
+//!
+//! Records are debug-printed by default. With `--pretty`, timestamp, level, target and fields are
+//! printed in aligned columns instead, with the level colored if stdout is a terminal.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use ansi_term::Color;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use structopt::StructOpt;
+use tracing::Level;
+
+/// Command line arguments.
+#[derive(Debug, StructOpt)]
+#[structopt(about = "Filters and prints JSON formatted casper-node logs")]
+struct Args {
+    /// Only print records at this level or a more severe one.
+    #[structopt(long, parse(try_from_str = Level::from_str))]
+    level: Option<Level>,
+
+    /// Only print records whose target is this module or one of its submodules.
+    #[structopt(long = "target")]
+    targets: Vec<String>,
+
+    /// Only print records concerning the deploy whose hex-encoded hash starts with this prefix.
+    #[structopt(long)]
+    deploy: Option<String>,
+
+    /// Print records in aligned columns instead of debug-printing them.
+    #[structopt(long)]
+    pretty: bool,
+
+    /// Log files to read, stdin if none is given.
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+}
+
+/// Width of the target column in pretty output, longer targets shift the fields to the right.
+const TARGET_WIDTH: usize = 40;
+
+/// Name of the field holding the message of a record.
+const MESSAGE_FIELD: &str = "message";
+
+/// Key of the list of spans a record occurred in.
+const SPANS_KEY: &str = "spans";
+
+/// Name of the span field holding the deploy hash, see `casper_node::logging::deploy_span`.
+const DEPLOY_HASH_FIELD: &str = "deploy_hash";
+
+/// How records are printed.
+#[derive(Clone, Copy, Debug)]
+enum Output {
+    /// Debug-printed.
+    Debug,
+    /// In aligned columns, with the level colored if `color` is set.
+    Pretty { color: bool },
+}
+
+/// A single record as written by the JSON formatter of `tracing_subscriber`.
+#[derive(Debug, Deserialize)]
+struct LogRecord {
+    timestamp: String,
+    level: String,
+    target: String,
+    #[serde(default)]
+    fields: BTreeMap<String, Value>,
+    /// The spans the event occurred in and any further keys.
+    #[serde(flatten)]
+    spans: BTreeMap<String, Value>,
+}
+
+/// The filters selecting which records are printed.
+#[derive(Debug)]
+struct Filter {
+    level: Option<Level>,
+    targets: Vec<String>,
+    /// Lowercase prefix of a deploy hash.
+    deploy: Option<String>,
+}
+
+impl Filter {
+    /// Returns whether `record` passes all filters.
+    fn matches(&self, record: &LogRecord) -> bool {
+        if let Some(max_level) = self.level {
+            // A record with an unknown level can't be compared, so it is kept.
+            if let Ok(level) = Level::from_str(&record.level) {
+                if level > max_level {
+                    return false;
+                }
+            }
+        }
+        if let Some(prefix) = &self.deploy {
+            if !concerns_deploy(record, prefix) {
+                return false;
+            }
+        }
+        self.targets.is_empty()
+            || self
+                .targets
+                .iter()
+                .any(|target| is_module_or_submodule(&record.target, target))
+    }
+}
+
+/// Returns whether `target` is `module` or a submodule of it.
+fn is_module_or_submodule(target: &str, module: &str) -> bool {
+    match target.strip_prefix(module) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+/// Returns whether `record` occurred within a span of a deploy whose hash starts with `prefix`.
+fn concerns_deploy(record: &LogRecord, prefix: &str) -> bool {
+    let spans = match record.spans.get(SPANS_KEY) {
+        Some(Value::Array(spans)) => spans,
+        _ => return false,
+    };
+    spans.iter().any(|span| match span.get(DEPLOY_HASH_FIELD) {
+        Some(Value::String(deploy_hash)) => deploy_hash.starts_with(prefix),
+        _ => false,
+    })
+}
+
+/// Writes `record` in aligned columns: timestamp, level, target, then the message and the other
+/// fields as `key=value` pairs.
+fn write_pretty<W: Write>(out: &mut W, record: &LogRecord, color: bool) -> io::Result<()> {
+    let level = format!("{:<5}", record.level);
+    let level = match Level::from_str(&record.level) {
+        Ok(parsed) if color => {
+            let color = match parsed {
+                Level::TRACE => Color::Purple,
+                Level::DEBUG => Color::Blue,
+                Level::INFO => Color::Green,
+                Level::WARN => Color::Yellow,
+                Level::ERROR => Color::Red,
+            };
+            color.paint(level).to_string()
+        }
+        _ => level,
+    };
+    write!(
+        out,
+        "{} {} {:<width$}",
+        record.timestamp,
+        level,
+        record.target,
+        width = TARGET_WIDTH
+    )?;
+
+    if let Some(message) = record.fields.get(MESSAGE_FIELD) {
+        match message {
+            Value::String(message) => write!(out, " {}", message)?,
+            other => write!(out, " {}", other)?,
+        }
+    }
+    for (key, value) in &record.fields {
+        if key != MESSAGE_FIELD {
+            write!(out, "; {}={}", key, value)?;
+        }
+    }
+    writeln!(out)
+}
+
+/// Returns whether stdout is a terminal.
+fn stdout_is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+/// Prints the records of `reader` which match `filter`.
+fn process<R: BufRead, W: Write>(
+    reader: R,
+    name: &str,
+    filter: &Filter,
+    output: Output,
+    out: &mut W,
+) -> Result<()> {
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.with_context(|| format!("failed to read {}", name))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<LogRecord>(&line) {
+            Ok(record) => {
+                if filter.matches(&record) {
+                    match output {
+                        Output::Debug => writeln!(out, "{:?}", record)?,
+                        Output::Pretty { color } => write_pretty(out, &record, color)?,
+                    }
+                }
+            }
+            Err(error) => eprintln!(
+                "{}:{}: skipping unparseable line: {}",
+                name,
+                index + 1,
+                error
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let args = Args::from_args();
+    let filter = Filter {
+        level: args.level,
+        targets: args.targets,
+        deploy: args.deploy.map(|prefix| prefix.to_lowercase()),
+    };
+    let output = if args.pretty {
+        Output::Pretty {
+            color: stdout_is_tty(),
+        }
+    } else {
+        Output::Debug
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    if args.files.is_empty() {
+        let stdin = io::stdin();
+        process(stdin.lock(), "stdin", &filter, output, &mut out)?;
+    } else {
+        for path in &args.files {
+            let file =
+                File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+            process(
+                BufReader::new(file),
+                &path.display().to_string(),
+                &filter,
+                output,
+                &mut out,
+            )?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(level: &str, target: &str) -> LogRecord {
+        LogRecord {
+            timestamp: "Jan 05 18:47:41.653".to_string(),
+            level: level.to_string(),
+            target: target.to_string(),
+            fields: BTreeMap::new(),
+            spans: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn should_filter_by_level_and_target_prefix() {
+        let filter = Filter {
+            level: Some(Level::WARN),
+            targets: vec!["casper_node::reactor".to_string()],
+            deploy: None,
+        };
+        assert!(filter.matches(&record("ERROR", "casper_node::reactor")));
+        assert!(filter.matches(&record("WARN", "casper_node::reactor::validator")));
+        assert!(!filter.matches(&record("INFO", "casper_node::reactor")));
+        assert!(!filter.matches(&record("WARN", "casper_node::reactor_foo")));
+        assert!(!filter.matches(&record("WARN", "casper_node::components")));
+
+        let unfiltered = Filter {
+            level: None,
+            targets: vec![],
+            deploy: None,
+        };
+        assert!(unfiltered.matches(&record("TRACE", "hyper::proto")));
+    }
+
+    #[test]
+    fn should_filter_by_deploy_span() {
+        let line = r#"{"timestamp":"Jan 05 18:47:41.653","level":"DEBUG","target":"casper_node::components::block_executor","fields":{"message":"executing deploy"},"span":{"name":"deploy","deploy_hash":"0a1b2c"},"spans":[{"name":"crank","ev":7},{"name":"deploy","deploy_hash":"0a1b2c"}]}"#;
+        let executing: LogRecord = serde_json::from_str(line).unwrap();
+        let filter = |prefix: &str| Filter {
+            level: None,
+            targets: vec![],
+            deploy: Some(prefix.to_string()),
+        };
+        assert!(filter("0a1b").matches(&executing));
+        assert!(!filter("0a1c").matches(&executing));
+        assert!(!filter("0a1b").matches(&record("INFO", "casper_node::reactor")));
+    }
+
+    #[test]
+    fn should_print_pretty_columns() {
+        let line = r#"{"timestamp":"Jan 05 18:47:41.653","level":"INFO","target":"casper_node::reactor","fields":{"message":"reactor main loop is ready","peers":3}}"#;
+        let record: LogRecord = serde_json::from_str(line).unwrap();
+        let mut out = Vec::new();
+        write_pretty(&mut out, &record, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "Jan 05 18:47:41.653 INFO  {:<40} reactor main loop is ready; peers=3\n",
+                "casper_node::reactor"
+            )
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! Comparison of the parameters of two chainspec versions.
+//!
+//! To review an upgrade, the parameters in effect at the latest protocol version of each chainspec,
+//! i.e. those of the genesis configuration with the changes of all upgrade points applied, are
+//! compared one by one, down to the individual costs and limits.
+//!
+//! Each change is attributed to how it takes effect: parameters changed by an upgrade point only
+//! apply from its activation point on, so all nodes have to switch to them at the same height,
+//! while changes to the rest of the genesis configuration only apply to networks launched from the
+//! new chainspec.
+
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+use semver::Version;
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    components::chainspec_loader::{self, EmergencyValidators, UpgradePoint},
+    utils::Loadable,
+    Chainspec,
+};
+
+/// Error comparing two chainspecs.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Error loading a chainspec.
+    #[error("error loading chainspec {path}: {error}")]
+    LoadChainspec {
+        /// The file path.
+        path: String,
+        /// The loading error.
+        error: chainspec_loader::Error,
+    },
+
+    /// The parameters of a chainspec could not be encoded for comparison.
+    #[error("error encoding chainspec parameters: {0}")]
+    Encoding(#[from] serde_json::Error),
+}
+
+/// How a changed parameter takes effect.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Activation {
+    /// The parameter is set by an upgrade point, and changes at its activation point.
+    Upgrade {
+        /// The height of the activation point.
+        height: u64,
+        /// The protocol version activated.
+        protocol_version: Version,
+    },
+    /// The parameter is part of the genesis configuration, which a running network can't change.
+    Genesis,
+}
+
+impl Display for Activation {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Activation::Upgrade {
+                height,
+                protocol_version,
+            } => write!(
+                formatter,
+                "coordinated activation at height {}, protocol version {}",
+                height, protocol_version
+            ),
+            Activation::Genesis => write!(formatter, "genesis only"),
+        }
+    }
+}
+
+/// A parameter differing between two chainspecs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParameterChange {
+    /// The path of the parameter, e.g. `wasm_config.opcode_costs.add`.
+    pub path: String,
+    /// The old value, `None` if the parameter was added.
+    pub old: Option<String>,
+    /// The new value, `None` if the parameter was removed.
+    pub new: Option<String>,
+    /// How the change takes effect.
+    pub activation: Activation,
+}
+
+impl ParameterChange {
+    /// Returns whether all nodes of a running network have to switch to the new value at the same
+    /// height.
+    pub fn requires_coordinated_activation(&self) -> bool {
+        matches!(self.activation, Activation::Upgrade { .. })
+    }
+}
+
+impl Display for ParameterChange {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        let old = self.old.as_deref().unwrap_or("<none>");
+        let new = self.new.as_deref().unwrap_or("<none>");
+        write!(
+            formatter,
+            "{}: {} -> {} ({})",
+            self.path, old, new, self.activation
+        )
+    }
+}
+
+/// The differences between the parameters of two chainspecs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainspecDiff {
+    /// The latest protocol version of the old chainspec.
+    pub old_version: Version,
+    /// The latest protocol version of the new chainspec.
+    pub new_version: Version,
+    /// The changed parameters, ordered by their paths.
+    pub changes: Vec<ParameterChange>,
+}
+
+/// Loads the chainspecs at `old` and `new` and compares their parameters.
+pub fn diff_chainspecs(old: &Path, new: &Path) -> Result<ChainspecDiff, Error> {
+    let load = |path: &Path| {
+        Chainspec::from_file(path).map_err(|error| Error::LoadChainspec {
+            path: path.display().to_string(),
+            error,
+        })
+    };
+    let (old, new) = (load(old)?, load(new)?);
+    Ok(ChainspecDiff {
+        old_version: old.latest_protocol_version(),
+        new_version: new.latest_protocol_version(),
+        changes: diff(&old, &new)?,
+    })
+}
+
+/// The parameters of a chainspec in effect at its latest protocol version.
+#[derive(Serialize)]
+struct Parameters<'a> {
+    #[serde(flatten)]
+    genesis: chainspec_loader::GenesisConfig,
+    emergency_validators: Option<&'a EmergencyValidators>,
+}
+
+impl<'a> Parameters<'a> {
+    fn new(chainspec: &'a Chainspec) -> Self {
+        let mut genesis = chainspec.genesis.clone();
+        for upgrade_point in &chainspec.upgrades {
+            genesis.protocol_version = upgrade_point.protocol_version.clone();
+            if let Some(wasm_config) = upgrade_point.new_wasm_config {
+                genesis.wasm_config = wasm_config;
+            }
+            if let Some(system_config) = upgrade_point.new_system_config {
+                genesis.system_config = system_config;
+            }
+            if let Some(deploy_config) = upgrade_point.new_deploy_config {
+                genesis.deploy_config = deploy_config;
+            }
+            if let Some(validator_slots) = upgrade_point.new_validator_slots {
+                genesis.validator_slots = validator_slots;
+            }
+        }
+        // Emergency validators only apply until the next upgrade point.
+        let emergency_validators = chainspec
+            .upgrades
+            .last()
+            .and_then(|upgrade_point| upgrade_point.emergency_validators.as_ref());
+        Parameters {
+            genesis,
+            emergency_validators,
+        }
+    }
+}
+
+fn diff(old: &Chainspec, new: &Chainspec) -> Result<Vec<ParameterChange>, serde_json::Error> {
+    let old_parameters = serde_json::to_value(Parameters::new(old))?;
+    let new_parameters = serde_json::to_value(Parameters::new(new))?;
+    let mut changes = Vec::new();
+    diff_values(
+        String::new(),
+        Some(&old_parameters),
+        Some(&new_parameters),
+        &mut changes,
+    );
+    Ok(changes
+        .into_iter()
+        .map(|(path, old_value, new_value)| {
+            let activation = activation(&path, &new.upgrades);
+            ParameterChange {
+                path,
+                old: old_value.map(render),
+                new: new_value.map(render),
+                activation,
+            }
+        })
+        .collect())
+}
+
+/// Collects the paths and values of the leaves differing between `old` and `new`.
+///
+/// Objects are compared by key, and arrays of objects, like the genesis accounts, by index. Other
+/// arrays, like ratios, are compared as a whole.
+fn diff_values(
+    path: String,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<(String, Option<Value>, Option<Value>)>,
+) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let key_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_values(key_path, old.get(key), new.get(key), changes);
+            }
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new)))
+            if old.iter().chain(new.iter()).any(Value::is_object) =>
+        {
+            for index in 0..old.len().max(new.len()) {
+                let index_path = format!("{}[{}]", path, index);
+                diff_values(index_path, old.get(index), new.get(index), changes);
+            }
+        }
+        (old, new) if old != new => changes.push((path, old.cloned(), new.cloned())),
+        _ => (),
+    }
+}
+
+/// Returns how the parameter at `path` takes effect, given the upgrade points of the new chainspec.
+fn activation(path: &str, upgrades: &[UpgradePoint]) -> Activation {
+    let parameter = path.split(|c| c == '.' || c == '[').next().unwrap_or(path);
+    let is_set_by = |upgrade_point: &UpgradePoint| match parameter {
+        "protocol_version" | "emergency_validators" => true,
+        "wasm_config" => upgrade_point.new_wasm_config.is_some(),
+        "system_config" => upgrade_point.new_system_config.is_some(),
+        "deploy_config" => upgrade_point.new_deploy_config.is_some(),
+        "validator_slots" => upgrade_point.new_validator_slots.is_some(),
+        _ => false,
+    };
+    upgrades
+        .iter()
+        .rev()
+        .find(|upgrade_point| is_set_by(upgrade_point))
+        .map_or(Activation::Genesis, |upgrade_point| Activation::Upgrade {
+            height: upgrade_point.activation_point.height,
+            protocol_version: upgrade_point.protocol_version.clone(),
+        })
+}
+
+/// Renders a value, strings without quotes.
+fn render(value: Value) -> String {
+    match value {
+        Value::String(string) => string,
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{components::chainspec_loader::ActivationPoint, testing::TestRng, types::TimeDiff};
+
+    #[test]
+    fn should_attribute_changes_to_activation() {
+        let mut rng = TestRng::new();
+        let mut old = Chainspec::random(&mut rng);
+        old.upgrades.clear();
+        assert!(diff(&old, &old).unwrap().is_empty());
+
+        let mut new = old.clone();
+        new.genesis.name = format!("{}-renamed", old.genesis.name);
+        let mut deploy_config = old.genesis.deploy_config;
+        deploy_config.max_ttl = TimeDiff::from(old.genesis.deploy_config.max_ttl.millis() + 1);
+        let protocol_version = Version::new(old.genesis.protocol_version.major + 1, 0, 0);
+        new.upgrades.push(UpgradePoint {
+            activation_point: ActivationPoint { height: 100 },
+            protocol_version: protocol_version.clone(),
+            new_wasm_config: None,
+            new_system_config: None,
+            new_deploy_config: Some(deploy_config),
+            new_validator_slots: None,
+            emergency_validators: None,
+        });
+
+        let upgrade = Activation::Upgrade {
+            height: 100,
+            protocol_version: protocol_version.clone(),
+        };
+        let changes = diff(&old, &new).unwrap();
+        let paths: Vec<_> = changes
+            .iter()
+            .map(|change| (change.path.as_str(), &change.activation))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ("deploy_config.max_ttl", &upgrade),
+                ("name", &Activation::Genesis),
+                ("protocol_version", &upgrade),
+            ]
+        );
+        assert_eq!(changes[2].new, Some(protocol_version.to_string()));
+        assert!(changes[0].requires_coordinated_activation());
+        assert!(!changes[1].requires_coordinated_activation());
+    }
+
+    #[test]
+    fn should_compare_arrays_of_objects_by_index() {
+        let old = serde_json::json!({ "accounts": [{ "balance": 1 }], "ratio": [1, 2] });
+        let new = serde_json::json!({
+            "accounts": [{ "balance": 2 }, { "balance": 3 }],
+            "ratio": [1, 3]
+        });
+        let mut changes = Vec::new();
+        diff_values(String::new(), Some(&old), Some(&new), &mut changes);
+        let paths: Vec<_> = changes.iter().map(|(path, _, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["accounts[0].balance", "accounts[1]", "ratio"]);
+        assert_eq!(changes[1].1, None);
+    }
+}
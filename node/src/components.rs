@@ -2,6 +2,7 @@
 //!
 //! Components are the building blocks of the whole application, wired together inside a reactor.
 //! Each component has a unified interface, expressed by the `Component` trait.
+pub(crate) mod binary_port;
 pub(crate) mod block_executor;
 pub(crate) mod block_proposer;
 pub(crate) mod block_validator;
@@ -11,6 +12,8 @@ pub(crate) mod collector;
 pub(crate) mod consensus;
 pub mod contract_runtime;
 pub(crate) mod deploy_acceptor;
+pub(crate) mod disk_monitor;
+pub(crate) mod era_metrics;
 pub(crate) mod event_stream_server;
 pub(crate) mod fetcher;
 pub(crate) mod gossiper;
@@ -19,12 +22,13 @@ pub(crate) mod linear_chain_fast_sync;
 pub(crate) mod linear_chain_sync;
 pub(crate) mod rest_server;
 pub(crate) mod rpc_server;
-// The `in_memory_network` is public for use in doctests.
-#[cfg(test)]
+// The `in_memory_network` is public for use in doctests and by the `casper-node-testing` crate.
+#[cfg(any(test, feature = "testing"))]
 pub mod in_memory_network;
 
 pub(crate) mod metrics;
 pub(crate) mod network;
+pub(crate) mod notifier;
 pub(crate) mod small_network;
 pub(crate) mod storage;
 
@@ -0,0 +1,346 @@
+//! Binary port
+//!
+//! The binary port serves low-level queries, like raw reads of blocks and global state tries or
+//! snapshots of the event queues, to tooling running on the same machine as the node, so it does
+//! not have to open the node's LMDB files while the node is running. See `protocol` for the
+//! messages.
+//!
+//! The port is a Unix domain socket, separate from the node-to-node port and only reachable
+//! locally. Clients are authenticated by their OS user: the socket file is only accessible to the
+//! user and group running the node, and of the clients connecting, only the user running the node,
+//! root and the users listed in `allowed_uids` are served.
+//!
+//! Like the API servers, the binary port stops accepting connections and closes the open ones when
+//! the node starts shutting down.
+
+mod config;
+mod protocol;
+
+use std::{
+    collections::HashSet,
+    fs::{self, Permissions},
+    io,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::Path,
+};
+
+use bytes::Bytes;
+use datasize::DataSize;
+use futures::{
+    future::{self, Either},
+    SinkExt, StreamExt,
+};
+use thiserror::Error;
+use tokio::{
+    net::{UnixListener, UnixStream},
+    sync::watch,
+    task::JoinHandle,
+};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use tracing::{debug, info, warn};
+
+use crate::{
+    effect::{
+        requests::{ContractRuntimeRequest, StorageRequest},
+        EffectBuilder,
+    },
+    utils,
+};
+pub use config::Config;
+pub use protocol::{Request, Response};
+
+/// Permissions of the socket file, read and write for the user and group running the node.
+const SOCKET_MODE: u32 = 0o660;
+
+/// A helper trait capturing all of this components Request type dependencies.
+pub(crate) trait ReactorEventT:
+    From<StorageRequest> + From<ContractRuntimeRequest> + Send + 'static
+{
+}
+
+impl<REv> ReactorEventT for REv where
+    REv: From<StorageRequest> + From<ContractRuntimeRequest> + Send + 'static
+{
+}
+
+/// Error opening the binary port.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A file other than a socket exists at the socket path.
+    #[error("{0} exists and is not a socket")]
+    NotASocket(String),
+    /// The socket could not be set up.
+    #[error("failed to listen on {path}: {error}")]
+    Listen {
+        /// The socket path.
+        path: String,
+        /// The underlying error.
+        #[source]
+        error: io::Error,
+    },
+}
+
+#[derive(DataSize, Debug)]
+pub(crate) struct BinaryPort {
+    /// Dropped to signal the server and all connections to exit.
+    #[data_size(skip)]
+    shutdown_sender: Option<watch::Sender<()>>,
+    /// The task handle which will only join once the server loop has exited.
+    #[data_size(skip)]
+    server_join_handle: Option<JoinHandle<()>>,
+}
+
+impl BinaryPort {
+    /// Opens the binary port as configured, if enabled.
+    pub(crate) fn new<REv>(
+        config: &Config,
+        effect_builder: EffectBuilder<REv>,
+    ) -> Result<Self, Error>
+    where
+        REv: ReactorEventT,
+    {
+        if !config.enable {
+            return Ok(BinaryPort {
+                shutdown_sender: None,
+                server_join_handle: None,
+            });
+        }
+
+        let listener = listen(Path::new(&config.socket_path))?;
+        info!(path = %config.socket_path, "binary port listening");
+
+        let mut allowed_uids: HashSet<u32> = config.allowed_uids.iter().copied().collect();
+        // Safe, as `geteuid` has no preconditions and cannot fail.
+        let _ = allowed_uids.insert(unsafe { libc::geteuid() });
+        let _ = allowed_uids.insert(0);
+
+        let (shutdown_sender, shutdown_receiver) = watch::channel(());
+        let server_join_handle = tokio::spawn(run(
+            listener,
+            effect_builder,
+            allowed_uids,
+            config.max_frame_size as usize,
+            shutdown_receiver,
+        ));
+
+        Ok(BinaryPort {
+            shutdown_sender: Some(shutdown_sender),
+            server_join_handle: Some(server_join_handle),
+        })
+    }
+
+    /// Stops accepting new connections and closes the open ones.
+    pub(crate) fn stop(&mut self) {
+        self.shutdown_sender = None;
+    }
+
+    /// Returns whether the server has exited since it was stopped.
+    pub(crate) fn has_stopped(&mut self) -> bool {
+        utils::has_exited(&mut self.server_join_handle)
+    }
+}
+
+/// Binds the socket at `path`, replacing a stale socket left behind by an earlier run.
+fn listen(path: &Path) -> Result<UnixListener, Error> {
+    let listen_error = |error| Error::Listen {
+        path: path.display().to_string(),
+        error,
+    };
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            fs::remove_file(path).map_err(listen_error)?
+        }
+        Ok(_) => return Err(Error::NotASocket(path.display().to_string())),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+        Err(error) => return Err(listen_error(error)),
+    }
+    let listener = UnixListener::bind(path).map_err(listen_error)?;
+    fs::set_permissions(path, Permissions::from_mode(SOCKET_MODE)).map_err(listen_error)?;
+    Ok(listener)
+}
+
+/// Accepts connections until `shutdown_receiver` is closed.
+async fn run<REv>(
+    mut listener: UnixListener,
+    effect_builder: EffectBuilder<REv>,
+    allowed_uids: HashSet<u32>,
+    max_frame_size: usize,
+    shutdown_receiver: watch::Receiver<()>,
+) where
+    REv: ReactorEventT,
+{
+    let mut shutdown = shutdown_signal(shutdown_receiver.clone());
+    loop {
+        let accepted = match future::select(Box::pin(listener.accept()), shutdown).await {
+            Either::Left((accepted, pending_shutdown)) => {
+                shutdown = pending_shutdown;
+                accepted
+            }
+            Either::Right(_) => break,
+        };
+        let stream = match accepted {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                warn!(%error, "binary port failed to accept connection");
+                continue;
+            }
+        };
+        match stream.peer_cred() {
+            Ok(credentials) if allowed_uids.contains(&credentials.uid) => {
+                debug!(uid = credentials.uid, "binary port client connected");
+                tokio::spawn(serve(
+                    stream,
+                    effect_builder,
+                    max_frame_size,
+                    shutdown_receiver.clone(),
+                ));
+            }
+            Ok(credentials) => {
+                warn!(
+                    uid = credentials.uid,
+                    "binary port rejected client of unknown user"
+                )
+            }
+            Err(error) => warn!(%error, "binary port failed to authenticate client"),
+        }
+    }
+    info!("binary port stopped");
+}
+
+/// Answers the requests of a single client, until it disconnects or `shutdown_receiver` is closed.
+async fn serve<REv>(
+    stream: UnixStream,
+    effect_builder: EffectBuilder<REv>,
+    max_frame_size: usize,
+    shutdown_receiver: watch::Receiver<()>,
+) where
+    REv: ReactorEventT,
+{
+    let codec = LengthDelimitedCodec::builder()
+        .max_frame_length(max_frame_size)
+        .new_codec();
+    let mut framed = Framed::new(stream, codec);
+    let mut shutdown = shutdown_signal(shutdown_receiver);
+    loop {
+        let frame = match future::select(framed.next(), shutdown).await {
+            Either::Left((Some(Ok(frame)), pending_shutdown)) => {
+                shutdown = pending_shutdown;
+                frame
+            }
+            Either::Left((Some(Err(error)), _)) => {
+                debug!(%error, "binary port connection failed");
+                return;
+            }
+            Either::Left((None, _)) | Either::Right(_) => return,
+        };
+        // Clients are local, but a request is untrusted input all the same.
+        let response = match utils::bounded::from_bincode(&frame, utils::bounded::Limits::RPC) {
+            Ok(request) => handle_request(effect_builder, request).await,
+            Err(error) => Response::InvalidRequest(error.to_string()),
+        };
+        let encoded = match bincode::serialize(&response) {
+            Ok(encoded) => encoded,
+            Err(error) => {
+                warn!(%error, "binary port failed to encode response");
+                return;
+            }
+        };
+        if let Err(error) = framed.send(Bytes::from(encoded)).await {
+            debug!(%error, "binary port failed to send response");
+            return;
+        }
+    }
+}
+
+async fn handle_request<REv>(effect_builder: EffectBuilder<REv>, request: Request) -> Response
+where
+    REv: ReactorEventT,
+{
+    match request {
+        Request::GetBlock(block_hash) => Response::Block(
+            effect_builder
+                .get_block_from_storage(block_hash)
+                .await
+                .map(Box::new),
+        ),
+        Request::GetBlockAtHeight(height) => Response::Block(
+            effect_builder
+                .get_block_at_height_from_storage(height)
+                .await
+                .map(Box::new),
+        ),
+        Request::GetBlockHeaders {
+            start_height,
+            end_height,
+            max_count,
+        } => Response::BlockHeaders(
+            effect_builder
+                .get_block_headers_by_height_from_storage(
+                    start_height..end_height,
+                    max_count as usize,
+                )
+                .await,
+        ),
+        Request::ReadTrie(trie_key) => Response::Trie(
+            effect_builder
+                .read_trie(trie_key.into())
+                .await
+                .map(Box::new),
+        ),
+        Request::GetComponentStatus => {
+            Response::ComponentStatus(effect_builder.get_component_stats())
+        }
+    }
+}
+
+/// Returns a future completing once `shutdown_receiver` is closed.
+fn shutdown_signal(mut shutdown_receiver: watch::Receiver<()>) -> future::BoxFuture<'static, ()> {
+    Box::pin(async move { while shutdown_receiver.recv().await.is_some() {} })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{crypto::hash::Digest, types::ComponentStatus};
+
+    #[test]
+    fn should_roundtrip_messages() {
+        let request = Request::ReadTrie(Digest::default());
+        let encoded = bincode::serialize(&request).unwrap();
+        assert_eq!(
+            utils::bounded::from_bincode::<Request>(&encoded, utils::bounded::Limits::RPC).unwrap(),
+            request
+        );
+
+        let response = Response::ComponentStatus(vec![ComponentStatus {
+            name: "storage".to_string(),
+            events_processed: 3,
+            average_handling_time_ns: 1_000,
+            last_error: None,
+            queued_events: 1,
+        }]);
+        let encoded = bincode::serialize(&response).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Response>(&encoded).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn should_replace_stale_socket_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("port.socket");
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.enter(|| {
+            drop(listen(&path).unwrap());
+            // The socket file is left behind by the dropped listener.
+            drop(listen(&path).unwrap());
+        });
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, SOCKET_MODE);
+
+        let file = dir.path().join("file");
+        fs::write(&file, b"data").unwrap();
+        assert!(matches!(listen(&file), Err(Error::NotASocket(_))));
+    }
+}
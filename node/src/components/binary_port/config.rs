@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use datasize::DataSize;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::ConfigValidator;
+
+/// Default path of the socket, relative to the config directory.
+const DEFAULT_SOCKET_PATH: &str = "binary_port.socket";
+
+/// Default maximum size of a request or response frame in bytes.
+const DEFAULT_MAX_FRAME_SIZE: u32 = 64 * 1024 * 1024;
+
+/// Configuration of the local binary port.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Whether to open the binary port.
+    pub enable: bool,
+    /// Path of the Unix domain socket the binary port listens on.
+    ///
+    /// Relative paths are resolved against the config directory.
+    pub socket_path: String,
+    /// IDs of the OS users allowed to connect in addition to the user running the node and root.
+    ///
+    /// The socket is only accessible to the user and group running the node, so these users also
+    /// need to be members of that group.
+    pub allowed_uids: Vec<u32>,
+    /// Maximum size of a request or response frame in bytes.
+    pub max_frame_size: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            enable: false,
+            socket_path: DEFAULT_SOCKET_PATH.to_string(),
+            allowed_uids: Vec::new(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+}
+
+impl Config {
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        if !self.enable {
+            return;
+        }
+        validator.ensure(
+            !self.socket_path.is_empty(),
+            "socket_path",
+            "must not be empty",
+        );
+        validator.ensure_non_zero("max_frame_size", self.max_frame_size);
+    }
+
+    /// Resolves a relative `socket_path` against `root`.
+    pub(crate) fn resolve_path(&mut self, root: &Path) {
+        self.socket_path = root.join(&self.socket_path).display().to_string();
+    }
+}
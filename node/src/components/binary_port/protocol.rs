@@ -0,0 +1,56 @@
+//! The messages exchanged over the binary port.
+//!
+//! Every message is sent as a frame prefixed by its length as a big endian `u32`, containing the
+//! message encoded with bincode. A client sends a `Request` and receives exactly one `Response` for
+//! it, in order, before sending the next one.
+
+use serde::{Deserialize, Serialize};
+
+use casper_execution_engine::{shared::stored_value::StoredValue, storage::trie::Trie};
+use casper_types::Key;
+
+use crate::{
+    components::storage::BlockHeaderBatch,
+    crypto::hash::Digest,
+    types::{Block, BlockHash, ComponentStatus},
+};
+
+/// A query sent to the binary port.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Request {
+    /// Reads a block from storage by its hash.
+    GetBlock(BlockHash),
+    /// Reads the block at the given height from storage.
+    GetBlockAtHeight(u64),
+    /// Reads the headers of the blocks stored at a range of heights, in a bounded batch.
+    ///
+    /// To scan the whole chain, e.g. for indexing, the request is repeated starting at the
+    /// `next_height` of the previous batch until that is `None`.
+    GetBlockHeaders {
+        /// The first height to read.
+        start_height: u64,
+        /// The height to stop before.
+        end_height: u64,
+        /// Maximum number of headers to return, capped at `MAX_BLOCK_HEADER_BATCH`.
+        max_count: u32,
+    },
+    /// Reads a trie of the global state by its hash.
+    ReadTrie(Digest),
+    /// Takes a snapshot of the event handling statistics and queued events per component.
+    GetComponentStatus,
+}
+
+/// The answer to a `Request`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum Response {
+    /// The block requested, if it is stored.
+    Block(Option<Box<Block>>),
+    /// The block headers requested.
+    BlockHeaders(BlockHeaderBatch),
+    /// The trie requested, if it is stored.
+    Trie(Option<Box<Trie<Key, StoredValue>>>),
+    /// The statistics of every component.
+    ComponentStatus(Vec<ComponentStatus>),
+    /// The request could not be decoded.
+    InvalidRequest(String),
+}
@@ -1,18 +1,28 @@
 //! Block executor component.
+//!
+//! Optionally, the block executor audits its own execution: a configurable percentage of the
+//! blocks proposed by other validators is executed a second time from the same pre-state hash once
+//! added to the linear chain. Execution is deterministic, so a re-execution resulting in a
+//! different state root hash indicates a bug which could cause this node to fork off the network.
+mod config;
+mod divergence;
 mod event;
 mod metrics;
 
 use std::{
-    collections::{BTreeMap, HashMap, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     convert::Infallible,
     fmt::Debug,
+    path::PathBuf,
 };
 
 use datasize::DataSize;
 use itertools::Itertools;
 use prometheus::Registry;
+use rand::Rng;
 use smallvec::SmallVec;
-use tracing::{debug, error, trace};
+use tracing::{debug, error, info, trace, warn};
+use tracing_futures::Instrument;
 
 use casper_execution_engine::{
     core::engine_state::{
@@ -27,7 +37,11 @@ use casper_types::{ExecutionResult, ProtocolVersion, PublicKey, U512};
 
 use crate::{
     components::{
-        block_executor::{event::State, metrics::BlockExecutorMetrics},
+        block_executor::{
+            divergence::DivergenceReport,
+            event::{Audit, State},
+            metrics::BlockExecutorMetrics,
+        },
         Component,
     },
     crypto::hash::Digest,
@@ -39,11 +53,13 @@ use crate::{
         },
         EffectBuilder, EffectExt, Effects,
     },
+    logging,
     types::{
         Block, BlockHash, BlockLike, Deploy, DeployHash, DeployHeader, FinalizedBlock, NodeId,
     },
     NodeRng,
 };
+pub use config::Config;
 pub(crate) use event::Event;
 
 /// A helper trait whose bounds represent the requirements for a reactor event that `BlockExecutor`
@@ -70,6 +86,24 @@ impl<REv> ReactorEventT for REv where
 {
 }
 
+/// Capability to execute deploys and commit their effects to global state, see
+/// `EffectBuilder::request_execute` and `EffectBuilder::request_commit`.
+///
+/// Only the block executor can create it, as only executing whole blocks keeps the global state
+/// consistent with the linear chain.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DeployExecutionCapability(());
+
+/// Capability to have consensus sign a block of the linear chain, see
+/// `EffectBuilder::handle_linear_chain_block`.
+///
+/// Only the block executor can create it, as only blocks which have been executed may be signed. It
+/// is handed directly to the linear chain along with every executed block, see
+/// `EffectBuilder::put_executed_block_to_linear_chain`. It can't be cloned, so that it never
+/// travels in an announcement, where every consumer would receive a copy.
+#[derive(Debug)]
+pub(crate) struct BlockSigningCapability(());
+
 #[derive(DataSize, Debug)]
 struct ExecutedBlockSummary {
     hash: BlockHash,
@@ -92,22 +126,52 @@ pub(crate) struct BlockExecutor {
     parent_map: HashMap<BlockHeight, ExecutedBlockSummary>,
     /// Finalized blocks waiting for their pre-state hash to start executing.
     exec_queue: HashMap<BlockHeight, (FinalizedBlock, VecDeque<Deploy>)>,
+    /// Percentage of the blocks proposed by other validators to audit.
+    audit_percentage: u8,
+    /// Our public key, if we are auditing blocks.
+    our_public_key: Option<PublicKey>,
+    /// Heights of the blocks selected for an audit once they have been executed.
+    audit_heights: HashSet<BlockHeight>,
+    /// Post-state hashes of received blocks being executed, by height.
+    expected_state_roots: HashMap<BlockHeight, Digest>,
+    /// Directory for forensic reports of state root divergences.
+    diagnostics_path: PathBuf,
     /// Metrics to track current chain height.
     #[data_size(skip)]
     metrics: BlockExecutorMetrics,
 }
 
 impl BlockExecutor {
-    pub(crate) fn new(genesis_state_root_hash: Digest, registry: Registry) -> Self {
+    /// Creates a new block executor.
+    ///
+    /// The diagnostics path of `config` is expected to be resolved already, see
+    /// `Config::resolve_path`.
+    pub(crate) fn new(
+        genesis_state_root_hash: Digest,
+        config: &Config,
+        registry: Registry,
+    ) -> Self {
         let metrics = BlockExecutorMetrics::new(registry).unwrap();
         BlockExecutor {
             genesis_state_root_hash,
             parent_map: HashMap::new(),
             exec_queue: HashMap::new(),
+            audit_percentage: config.audit_percentage,
+            our_public_key: None,
+            audit_heights: HashSet::new(),
+            expected_state_roots: HashMap::new(),
+            diagnostics_path: PathBuf::from(&config.diagnostics_path),
             metrics,
         }
     }
 
+    /// Enables auditing the execution of blocks proposed by validators other than
+    /// `our_public_key`, at the configured percentage.
+    pub(crate) fn with_audit(mut self, our_public_key: PublicKey) -> Self {
+        self.our_public_key = Some(our_public_key);
+        self
+    }
+
     /// Adds the "parent map" to the instance of `BlockExecutor`.
     ///
     /// When transitioning from `joiner` to `validator` states we need
@@ -175,13 +239,51 @@ impl BlockExecutor {
             })
     }
 
-    /// Creates and announces the linear chain block.
+    /// Creates and announces the linear chain block, or checks the outcome of an audit.
     fn finalize_block_execution<REv: ReactorEventT>(
         &mut self,
         effect_builder: EffectBuilder<REv>,
-        state: Box<State>,
+        mut state: Box<State>,
         next_era_validator_weights: Option<BTreeMap<PublicKey, U512>>,
     ) -> Effects<Event> {
+        if let Some(audit) = state.audit.take() {
+            self.finish_audit(&state.finalized_block, audit, state.state_root_hash);
+            return Effects::new();
+        }
+
+        // The pre-state hash is only known until the block is created.
+        let audited_block = if self.audit_heights.remove(&state.finalized_block.height()) {
+            self.pre_state_hash(&state.finalized_block)
+                .map(|pre_state_hash| (state.finalized_block.clone(), pre_state_hash))
+        } else {
+            None
+        };
+        let divergence = match self
+            .expected_state_roots
+            .remove(&state.finalized_block.height())
+        {
+            Some(expected) if expected != state.state_root_hash => {
+                error!(
+                    height = state.finalized_block.height(),
+                    expected_state_root_hash = %expected,
+                    computed_state_root_hash = %state.state_root_hash,
+                    "state root hash of executed block differs from received block, \
+                     writing forensic report"
+                );
+                self.pre_state_hash(&state.finalized_block)
+                    .map(|pre_state_hash| {
+                        DivergenceReport::new(
+                            state.finalized_block.clone(),
+                            pre_state_hash,
+                            expected,
+                            state.state_root_hash,
+                            &state.execution_results,
+                        )
+                    })
+            }
+            _ => None,
+        };
+
         // The state hash of the last execute-commit cycle is used as the block's post state
         // hash.
         let next_height = state.finalized_block.height() + 1;
@@ -195,9 +297,36 @@ impl BlockExecutor {
             next_era_validator_weights,
         );
 
-        let mut effects = effect_builder
-            .announce_linear_chain_block(block, state.execution_results)
-            .ignore();
+        let mut effects = Effects::new();
+        if let Some((finalized_block, pre_state_hash)) = audited_block {
+            let audit = Audit {
+                block_hash: *block.hash(),
+                state_root_hash: *block.state_root_hash(),
+            };
+            effects.extend(self.start_audit(
+                effect_builder,
+                finalized_block,
+                pre_state_hash,
+                audit,
+            ));
+        }
+        let execution_results = state.execution_results;
+        match divergence {
+            // Announcing the block is delayed until the report is written, as it may well cause
+            // the node to stop.
+            Some(report) => effects.extend(report.collect_trie_paths(effect_builder).event(
+                move |report| Event::DivergenceReportReady {
+                    report: Box::new(report),
+                    block: Box::new(block),
+                    execution_results,
+                },
+            )),
+            None => effects.extend(linear_chain_block_created(
+                effect_builder,
+                block,
+                execution_results,
+            )),
+        }
         // If the child is already finalized, start execution.
         if let Some((finalized_block, deploys)) = self.exec_queue.remove(&next_height) {
             effects.extend(self.handle_get_deploys_result(
@@ -268,13 +397,18 @@ impl BlockExecutor {
             state.finalized_block.proposer(),
         );
 
+        let deploy_span = logging::deploy_span(&deploy_hash);
+        deploy_span
+            .in_scope(|| debug!(height = state.finalized_block.height(), "executing deploy"));
+
         // TODO: this is currently working coincidentally because we are passing only one
         // deploy_item per exec. The execution results coming back from the ee lacks the
         // mapping between deploy_hash and execution result, and this outer logic is enriching it
         // with the deploy hash. If we were passing multiple deploys per exec the relation between
         // the deploy and the execution results would be lost.
         effect_builder
-            .request_execute(execute_request)
+            .request_execute(DeployExecutionCapability(()), execute_request)
+            .instrument(deploy_span)
             .event(move |result| Event::DeployExecutionResult {
                 state,
                 deploy_hash,
@@ -295,6 +429,7 @@ impl BlockExecutor {
                 remaining_deploys: deploys,
                 execution_results: HashMap::new(),
                 state_root_hash,
+                audit: None,
             });
             self.execute_next_deploy_or_create_block(effect_builder, state)
         } else {
@@ -335,6 +470,7 @@ impl BlockExecutor {
                         remaining_deploys: deploys,
                         execution_results: HashMap::new(),
                         state_root_hash,
+                        audit: None,
                     });
                     self.execute_next_deploy_or_create_block(effect_builder, state)
                 } else {
@@ -393,7 +529,11 @@ impl BlockExecutor {
             }
         };
         effect_builder
-            .request_commit(state.state_root_hash, execution_effect.transforms)
+            .request_commit(
+                DeployExecutionCapability(()),
+                state.state_root_hash,
+                execution_effect.transforms,
+            )
             .event(|commit_result| Event::CommitExecutionEffects {
                 state,
                 commit_result,
@@ -434,6 +574,70 @@ impl BlockExecutor {
         block
     }
 
+    /// Returns whether to audit the execution of `finalized_block`.
+    fn should_audit(&self, rng: &mut NodeRng, finalized_block: &FinalizedBlock) -> bool {
+        match self.our_public_key {
+            Some(our_public_key) if finalized_block.proposer() != our_public_key => {
+                rng.gen_range(0, 100) < self.audit_percentage
+            }
+            _ => false,
+        }
+    }
+
+    /// Executes an already executed block a second time, starting from `pre_state_hash`.
+    fn start_audit<REv: ReactorEventT>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        finalized_block: FinalizedBlock,
+        pre_state_hash: Digest,
+        audit: Audit,
+    ) -> Effects<Event> {
+        debug!(height = finalized_block.height(), block_hash = %audit.block_hash, "auditing block");
+        let deploy_hashes = finalized_block
+            .proto_block()
+            .deploys()
+            .iter()
+            .map(|hash| **hash)
+            .collect::<SmallVec<_>>();
+        let state = Box::new(State {
+            finalized_block,
+            remaining_deploys: VecDeque::new(),
+            execution_results: HashMap::new(),
+            state_root_hash: pre_state_hash,
+            audit: Some(audit),
+        });
+        effect_builder
+            .get_deploys_from_storage(deploy_hashes)
+            .event(move |deploys| Event::GetAuditDeploysResult { state, deploys })
+    }
+
+    /// Compares the post-state hash of a re-execution with that of the first execution.
+    fn finish_audit(
+        &self,
+        finalized_block: &FinalizedBlock,
+        audit: Audit,
+        state_root_hash: Digest,
+    ) {
+        self.metrics.audited_blocks.inc();
+        if state_root_hash == audit.state_root_hash {
+            info!(
+                height = finalized_block.height(),
+                block_hash = %audit.block_hash,
+                "audit of block execution succeeded"
+            );
+        } else {
+            self.metrics.audit_divergences.inc();
+            error!(
+                height = finalized_block.height(),
+                block_hash = %audit.block_hash,
+                expected_state_root_hash = %audit.state_root_hash,
+                %state_root_hash,
+                "AUDIT FAILED: executing the block again resulted in a different state root hash, \
+                 block execution is not deterministic and this node may fork off the network"
+            );
+        }
+    }
+
     fn pre_state_hash(&mut self, finalized_block: &FinalizedBlock) -> Option<Digest> {
         if finalized_block.is_genesis_child() {
             Some(self.genesis_state_root_hash)
@@ -448,6 +652,44 @@ impl BlockExecutor {
     }
 }
 
+/// Looks up whether `finalized_block` has been executed before, so it is only executed if it is
+/// new.
+fn check_if_block_exists<REv: ReactorEventT>(
+    effect_builder: EffectBuilder<REv>,
+    finalized_block: FinalizedBlock,
+) -> Effects<Event> {
+    effect_builder
+        .get_block_at_height_local(finalized_block.height())
+        .event(move |maybe_block| {
+            maybe_block.map(Box::new).map_or_else(
+                || Event::BlockIsNew(finalized_block),
+                Event::BlockAlreadyExists,
+            )
+        })
+}
+
+/// Hands a newly executed block to the linear chain, along with the capability to have it signed,
+/// and announces it.
+fn linear_chain_block_created<REv: ReactorEventT>(
+    effect_builder: EffectBuilder<REv>,
+    block: Block,
+    execution_results: HashMap<DeployHash, (DeployHeader, ExecutionResult)>,
+) -> Effects<Event> {
+    let results = execution_results
+        .iter()
+        .map(|(hash, (_header, result))| (*hash, result.clone()))
+        .collect();
+    let mut effects = effect_builder
+        .put_executed_block_to_linear_chain(BlockSigningCapability(()), block.clone(), results)
+        .ignore();
+    effects.extend(
+        effect_builder
+            .announce_linear_chain_block(block, execution_results)
+            .ignore(),
+    );
+    effects
+}
+
 impl<REv: ReactorEventT> Component<REv> for BlockExecutor {
     type Event = Event;
     type ConstructionError = Infallible;
@@ -455,27 +697,37 @@ impl<REv: ReactorEventT> Component<REv> for BlockExecutor {
     fn handle_event(
         &mut self,
         effect_builder: EffectBuilder<REv>,
-        _rng: &mut NodeRng,
+        rng: &mut NodeRng,
         event: Self::Event,
     ) -> Effects<Self::Event> {
         match event {
             Event::Request(BlockExecutorRequest::ExecuteBlock(finalized_block)) => {
                 debug!(?finalized_block, "execute block");
+                check_if_block_exists(effect_builder, finalized_block)
+            }
+            Event::Request(BlockExecutorRequest::ExecuteReceivedBlock {
+                finalized_block,
+                expected_state_root_hash,
+            }) => {
+                debug!(?finalized_block, %expected_state_root_hash, "execute received block");
+                self.expected_state_roots
+                    .insert(finalized_block.height(), expected_state_root_hash);
+                check_if_block_exists(effect_builder, finalized_block)
+            }
+            Event::BlockAlreadyExists(block) => {
+                self.expected_state_roots.remove(&block.height());
                 effect_builder
-                    .get_block_at_height_local(finalized_block.height())
-                    .event(move |maybe_block| {
-                        maybe_block.map(Box::new).map_or_else(
-                            || Event::BlockIsNew(finalized_block),
-                            Event::BlockAlreadyExists,
-                        )
-                    })
+                    .handle_linear_chain_block(BlockSigningCapability(()), block.take_header())
+                    .ignore()
             }
-            Event::BlockAlreadyExists(block) => effect_builder
-                .handle_linear_chain_block(block.take_header())
-                .ignore(),
             // If we haven't executed the block before in the past (for example during
             // joining), do it now.
-            Event::BlockIsNew(finalized_block) => self.get_deploys(effect_builder, finalized_block),
+            Event::BlockIsNew(finalized_block) => {
+                if self.should_audit(rng, &finalized_block) {
+                    self.audit_heights.insert(finalized_block.height());
+                }
+                self.get_deploys(effect_builder, finalized_block)
+            }
             Event::GetDeploysResult {
                 finalized_block,
                 deploys,
@@ -506,6 +758,22 @@ impl<REv: ReactorEventT> Component<REv> for BlockExecutor {
                 )
             }
 
+            Event::GetAuditDeploysResult { mut state, deploys } => {
+                match deploys.into_iter().collect::<Option<VecDeque<_>>>() {
+                    Some(deploys) => {
+                        state.remaining_deploys = deploys;
+                        self.execute_next_deploy_or_create_block(effect_builder, state)
+                    }
+                    None => {
+                        warn!(
+                            height = state.finalized_block.height(),
+                            "skipping audit of block with deploys missing from storage"
+                        );
+                        Effects::new()
+                    }
+                }
+            }
+
             Event::DeployExecutionResult {
                 state,
                 deploy_hash,
@@ -547,6 +815,17 @@ impl<REv: ReactorEventT> Component<REv> for BlockExecutor {
                 }
             }
 
+            Event::DivergenceReportReady {
+                report,
+                block,
+                execution_results,
+            } => {
+                if let Some(path) = report.write(&self.diagnostics_path) {
+                    error!(path = %path.display(), "wrote state root divergence report");
+                }
+                linear_chain_block_created(effect_builder, *block, execution_results)
+            }
+
             Event::RunStepResult { mut state, result } => {
                 trace!(?result, "run step result");
                 match result {
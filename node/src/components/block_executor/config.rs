@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use datasize::DataSize;
+use serde::{Deserialize, Serialize};
+
+/// Default directory for diagnostics reports.
+const DEFAULT_DIAGNOSTICS_PATH: &str = "diagnostics";
+
+/// Configuration options for block execution.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Percentage of the blocks proposed by other validators which are executed a second time
+    /// after being added to the linear chain, to check that the result does not differ. `0`
+    /// disables auditing.
+    pub audit_percentage: u8,
+    /// Directory for forensic reports of state root divergences. Relative paths are resolved
+    /// against the config directory.
+    pub diagnostics_path: String,
+}
+
+impl Config {
+    /// Resolves a relative `diagnostics_path` against `root`.
+    pub(crate) fn resolve_path(&mut self, root: &Path) {
+        self.diagnostics_path = root.join(&self.diagnostics_path).display().to_string();
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            audit_percentage: 0,
+            diagnostics_path: DEFAULT_DIAGNOSTICS_PATH.to_string(),
+        }
+    }
+}
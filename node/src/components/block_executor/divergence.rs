@@ -0,0 +1,176 @@
+//! Forensic reports of state root divergences.
+//!
+//! If executing a block received from a peer results in a different post-state hash than the one
+//! the block carries, this node and the network disagree on the global state. As the node cannot
+//! continue syncing in that case, everything needed to analyze the divergence offline is written
+//! to a report in the diagnostics directory first.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use tracing::{error, warn};
+
+use casper_execution_engine::core::engine_state::{QueryRequest, QueryResult};
+use casper_types::{bytesrepr::ToBytes, ExecutionResult, Key};
+
+use super::ReactorEventT;
+use crate::{
+    crypto::hash::Digest,
+    effect::EffectBuilder,
+    types::{DeployHash, DeployHeader, FinalizedBlock},
+    utils,
+};
+
+/// Maximum number of keys whose trie paths are included in a report.
+const MAX_TRIE_PATHS: usize = 1_000;
+
+/// The deploy of a diverging block and the outcome of executing it.
+#[derive(Debug, Serialize)]
+struct ExecutedDeploy {
+    deploy_hash: DeployHash,
+    /// The execution result, including the transforms it applied.
+    execution_result: Option<ExecutionResult>,
+}
+
+/// The path to a key in the trie of the computed post-state.
+#[derive(Debug, Serialize)]
+struct TriePath {
+    key: String,
+    /// The hex-encoded Merkle proof of the key's value, if it could be obtained.
+    merkle_proof: Option<String>,
+    /// Why there is no proof, otherwise.
+    error: Option<String>,
+}
+
+/// A forensic report of a state root divergence.
+#[derive(Debug, Serialize)]
+pub struct DivergenceReport {
+    node_version: String,
+    finalized_block: FinalizedBlock,
+    pre_state_hash: Digest,
+    /// The post-state hash of the received block.
+    expected_state_root_hash: Digest,
+    /// The post-state hash resulting from executing the block locally.
+    computed_state_root_hash: Digest,
+    /// The deploys in the order they were executed. The effects of the step run at the end of an
+    /// era are not included.
+    deploys: Vec<ExecutedDeploy>,
+    /// The paths of all keys written by the deploys, in the computed post-state.
+    trie_paths: Vec<TriePath>,
+}
+
+impl DivergenceReport {
+    /// Creates a report without any trie paths yet.
+    pub(super) fn new(
+        finalized_block: FinalizedBlock,
+        pre_state_hash: Digest,
+        expected_state_root_hash: Digest,
+        computed_state_root_hash: Digest,
+        execution_results: &HashMap<DeployHash, (DeployHeader, ExecutionResult)>,
+    ) -> Self {
+        let deploys = finalized_block
+            .proto_block()
+            .deploys()
+            .iter()
+            .map(|deploy_hash| ExecutedDeploy {
+                deploy_hash: *deploy_hash,
+                execution_result: execution_results
+                    .get(deploy_hash)
+                    .map(|(_, execution_result)| execution_result.clone()),
+            })
+            .collect();
+        DivergenceReport {
+            node_version: crate::VERSION_STRING.clone(),
+            finalized_block,
+            pre_state_hash,
+            expected_state_root_hash,
+            computed_state_root_hash,
+            deploys,
+            trie_paths: Vec::new(),
+        }
+    }
+
+    /// Returns the keys written by the deploys.
+    fn written_keys(&self) -> BTreeSet<String> {
+        self.deploys
+            .iter()
+            .filter_map(|deploy| deploy.execution_result.as_ref())
+            .flat_map(|execution_result| {
+                let effect = match execution_result {
+                    ExecutionResult::Failure { effect, .. }
+                    | ExecutionResult::Success { effect, .. } => effect,
+                };
+                effect.transforms.iter().map(|entry| entry.key.clone())
+            })
+            .collect()
+    }
+
+    /// Adds the trie paths of the keys written by the deploys, as queried from the contract
+    /// runtime.
+    pub(super) async fn collect_trie_paths<REv: ReactorEventT>(
+        mut self,
+        effect_builder: EffectBuilder<REv>,
+    ) -> Self {
+        let keys = self.written_keys();
+        if keys.len() > MAX_TRIE_PATHS {
+            warn!(
+                keys = keys.len(),
+                "too many keys written by diverging block, only including the first {}",
+                MAX_TRIE_PATHS
+            );
+        }
+        for formatted_key in keys.into_iter().take(MAX_TRIE_PATHS) {
+            let result = match Key::from_formatted_str(&formatted_key) {
+                Ok(key) => {
+                    let request =
+                        QueryRequest::new(self.computed_state_root_hash.into(), key, Vec::new());
+                    match effect_builder.query_global_state(request).await {
+                        Ok(QueryResult::Success { proofs, .. }) => proofs
+                            .to_bytes()
+                            .map(hex::encode)
+                            .map_err(|error| format!("could not encode proof: {:?}", error)),
+                        Ok(query_result) => Err(format!("query failed: {:?}", query_result)),
+                        Err(error) => Err(format!("query failed to execute: {:?}", error)),
+                    }
+                }
+                Err(error) => Err(format!("could not parse key: {:?}", error)),
+            };
+            let (merkle_proof, error) = match result {
+                Ok(merkle_proof) => (Some(merkle_proof), None),
+                Err(error) => (None, Some(error)),
+            };
+            self.trie_paths.push(TriePath {
+                key: formatted_key,
+                merkle_proof,
+                error,
+            });
+        }
+        self
+    }
+
+    /// Writes the report as JSON to a new file in `dir`, returning its path.
+    pub(super) fn write(&self, dir: &Path) -> Option<PathBuf> {
+        let path = dir.join(format!(
+            "state_root_divergence_{}_{}.json",
+            self.finalized_block.height(),
+            self.computed_state_root_hash
+        ));
+        let result = fs::create_dir_all(dir)
+            .map_err(|error| error.to_string())
+            .and_then(|()| serde_json::to_vec_pretty(self).map_err(|error| error.to_string()))
+            .and_then(|serialized| {
+                utils::write_file(&path, serialized).map_err(|error| error.to_string())
+            });
+        match result {
+            Ok(()) => Some(path),
+            Err(error) => {
+                error!(path = %path.display(), %error, "could not write state root divergence report");
+                None
+            }
+        }
+    }
+}
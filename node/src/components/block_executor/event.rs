@@ -14,6 +14,7 @@ use casper_execution_engine::{
 };
 use casper_types::ExecutionResult;
 
+use super::divergence::DivergenceReport;
 use crate::{
     crypto::hash::Digest,
     effect::requests::BlockExecutorRequest,
@@ -46,6 +47,13 @@ pub enum Event {
         /// If it's the first block after Genesis then `parent` is `None`.
         parent: Option<(BlockHash, Digest, Digest)>,
     },
+    /// Received the deploys of a block selected for an audit.
+    GetAuditDeploysResult {
+        /// State of the audit, without any deploys yet.
+        state: Box<State>,
+        /// The deploys in the order they appear in the block, `None` if missing from storage.
+        deploys: Vec<Option<Deploy>>,
+    },
     /// The result of executing a single deploy.
     DeployExecutionResult {
         /// State of this request.
@@ -71,6 +79,26 @@ pub enum Event {
         /// The result.
         result: Result<StepResult, engine_state::Error>,
     },
+    /// The forensic report of a block whose post-state hash differs from the received one is
+    /// complete.
+    DivergenceReportReady {
+        /// The report.
+        report: Box<DivergenceReport>,
+        /// The block created by executing it, to be announced once the report is written.
+        block: Box<Block>,
+        /// The results of executing its deploys.
+        execution_results: HashMap<DeployHash, (DeployHeader, ExecutionResult)>,
+    },
+}
+
+impl Event {
+    /// Returns the hash of the deploy the event concerns, if it concerns a single one.
+    pub(crate) fn deploy_hash(&self) -> Option<DeployHash> {
+        match self {
+            Event::DeployExecutionResult { deploy_hash, .. } => Some(*deploy_hash),
+            _ => None,
+        }
+    }
 }
 
 impl Display for Event {
@@ -96,6 +124,12 @@ impl Display for Event {
                 parent.is_some(),
                 finalized_block.height()
             ),
+            Event::GetAuditDeploysResult { state, deploys } => write!(
+                f,
+                "fetch deploys for audit of block with height {} has {} deploys",
+                state.finalized_block.height(),
+                deploys.len()
+            ),
             Event::DeployExecutionResult {
                 state,
                 deploy_hash,
@@ -152,6 +186,11 @@ impl Display for Event {
                 state.state_root_hash,
                 result
             ),
+            Event::DivergenceReportReady { block, .. } => write!(
+                f,
+                "state root divergence report ready for block at height {}",
+                block.height()
+            ),
             Event::BlockAlreadyExists(block) => {
                 write!(f, "Block at height {} was executed before", block.height())
             }
@@ -171,4 +210,15 @@ pub struct State {
     /// Current state root hash of global storage.  Is initialized with the parent block's
     /// state hash, and is updated after each commit.
     pub state_root_hash: Digest,
+    /// The outcome of the first execution, if this is a re-execution for an audit.
+    pub audit: Option<Audit>,
+}
+
+/// The outcome of executing a block which a re-execution for an audit is expected to reproduce.
+#[derive(Debug)]
+pub struct Audit {
+    /// The hash of the block created by the first execution.
+    pub block_hash: BlockHash,
+    /// The post-state hash of the first execution.
+    pub state_root_hash: Digest,
 }
@@ -1,9 +1,13 @@
-use prometheus::{IntGauge, Registry};
+use prometheus::{IntCounter, IntGauge, Registry};
 
 #[derive(Debug, Clone)]
 pub struct BlockExecutorMetrics {
     /// The current chain height.
     pub chain_height: IntGauge,
+    /// The number of blocks re-executed for an audit.
+    pub audited_blocks: IntCounter,
+    /// The number of audited blocks whose re-execution resulted in a different state root hash.
+    pub audit_divergences: IntCounter,
     /// registry component.
     registry: Registry,
 }
@@ -11,9 +15,21 @@ pub struct BlockExecutorMetrics {
 impl BlockExecutorMetrics {
     pub fn new(registry: Registry) -> Result<Self, prometheus::Error> {
         let chain_height = IntGauge::new("chain_height", "current chain height")?;
+        let audited_blocks = IntCounter::new(
+            "block_executor_audited_blocks",
+            "number of blocks re-executed for an audit",
+        )?;
+        let audit_divergences = IntCounter::new(
+            "block_executor_audit_divergences",
+            "number of audited blocks whose re-execution resulted in a different state root hash",
+        )?;
         registry.register(Box::new(chain_height.clone()))?;
+        registry.register(Box::new(audited_blocks.clone()))?;
+        registry.register(Box::new(audit_divergences.clone()))?;
         Ok(BlockExecutorMetrics {
             chain_height,
+            audited_blocks,
+            audit_divergences,
             registry,
         })
     }
@@ -24,6 +40,12 @@ impl Drop for BlockExecutorMetrics {
         self.registry
             .unregister(Box::new(self.chain_height.clone()))
             .expect("did not expect deregistering chain_height to fail");
+        self.registry
+            .unregister(Box::new(self.audited_blocks.clone()))
+            .expect("did not expect deregistering audited_blocks to fail");
+        self.registry
+            .unregister(Box::new(self.audit_divergences.clone()))
+            .expect("did not expect deregistering audit_divergences to fail");
     }
 }
 
@@ -5,6 +5,7 @@
 
 mod deploy_sets;
 mod event;
+mod footprint;
 mod metrics;
 
 #[cfg(test)]
@@ -13,6 +14,7 @@ mod tests;
 use std::{
     collections::{HashMap, HashSet},
     convert::Infallible,
+    iter,
     time::Duration,
 };
 
@@ -22,17 +24,22 @@ use semver::Version;
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-    components::{chainspec_loader::DeployConfig, Component},
+    components::{
+        chainspec_loader::{Chainspec, DeployConfig},
+        Component,
+    },
     effect::{
         requests::{BlockProposerRequest, ProtoBlockRequest, StateStoreRequest, StorageRequest},
         EffectBuilder, EffectExt, Effects,
     },
-    types::{DeployHash, DeployHeader, ProtoBlock, Timestamp},
+    logging,
+    types::{DeployHash, DeployHeader, FinalizedBlock, ProtoBlock, Timestamp},
     NodeRng,
 };
 use casper_execution_engine::shared::gas::Gas;
 pub(crate) use deploy_sets::BlockProposerDeploySets;
 pub(crate) use event::{DeployType, Event};
+use footprint::DeployFootprint;
 use metrics::BlockProposerMetrics;
 use num_traits::Zero;
 
@@ -112,6 +119,10 @@ impl BlockProposer {
             sets,
             next_finalized_block,
         });
+        effect_builder.subscribe(|block: FinalizedBlock| Event::FinalizedProtoBlock {
+            block: block.proto_block().clone(),
+            height: block.height(),
+        });
 
         let block_proposer = BlockProposer {
             state: BlockProposerState::Initializing {
@@ -151,12 +162,16 @@ where
                     next_finalized_block,
                 },
             ) => {
+                let sets = sets
+                    .unwrap_or_default()
+                    .with_next_finalized(next_finalized_block);
+                let footprints = footprints(&sets.pending);
                 let mut new_ready_state = BlockProposerReady {
-                    sets: sets
-                        .unwrap_or_default()
-                        .with_next_finalized(next_finalized_block),
+                    sets,
+                    footprints,
                     deploy_config: chainspec.genesis.deploy_config,
                     state_key: deploy_sets::create_storage_key(&chainspec),
+                    protocol_versions: protocol_versions(&chainspec),
                     request_queue: Default::default(),
                     unhandled_finalized: Default::default(),
                 };
@@ -199,6 +214,8 @@ where
 struct BlockProposerReady {
     /// Set of deploys currently stored in the block proposer.
     sets: BlockProposerDeploySets,
+    /// The footprints of the deploys in `self.sets.pending`, used to assemble proto blocks.
+    footprints: HashMap<DeployHash, DeployFootprint>,
     /// `unhandled_finalized` is a set of hashes for deploys that the `BlockProposer` has not yet
     /// seen but were reported as reported to `finalized_deploys()`. They are used to
     /// filter deploys for proposal, similar to `self.sets.finalized_deploys`.
@@ -207,6 +224,9 @@ struct BlockProposerReady {
     deploy_config: DeployConfig,
     /// Key for storing the block proposer state.
     state_key: Vec<u8>,
+    /// The protocol versions of the chain by the height they take effect at, in ascending order.
+    #[data_size(skip)]
+    protocol_versions: Vec<(BlockHeight, Version)>,
     /// The queue of requests awaiting being handled.
     request_queue: RequestQueue,
 }
@@ -332,6 +352,13 @@ impl BlockProposerReady {
         if self.sets.finalized_deploys.contains_key(&hash) {
             info!(%hash, "deploy rejected from the buffer");
         } else {
+            // Deploys without a footprint stay pending, but are never proposed.
+            match DeployFootprint::new(&deploy_or_transfer) {
+                Some(footprint) => {
+                    self.footprints.insert(hash, footprint);
+                }
+                None => error!(%hash, "payment_amount couldn't be converted from motes to gas"),
+            }
             self.sets.pending.insert(hash, deploy_or_transfer);
             info!(%hash, "added deploy to the buffer");
         }
@@ -343,6 +370,8 @@ impl BlockProposerReady {
         I: IntoIterator<Item = DeployHash>,
     {
         for deploy_hash in deploys.into_iter() {
+            logging::deploy_span(&deploy_hash).in_scope(|| debug!("deploy finalized"));
+            self.footprints.remove(&deploy_hash);
             match self.sets.pending.remove(&deploy_hash) {
                 Some(deploy_type) => {
                     self.sets
@@ -425,11 +454,14 @@ impl BlockProposerReady {
         let mut wasm_deploys = Vec::new();
         let mut block_gas_running_total = Gas::zero();
         let mut block_size_running_total = 0usize;
+        // The block is at least at the next height to be finalized, so near an upgrade, deploys
+        // requiring the new protocol version are held back until its first block is proposed.
+        let protocol_version = self.protocol_version_at(self.sets.next_finalized);
 
-        for (hash, deploy_type) in self.sets.pending.iter() {
+        for (hash, footprint) in self.footprints.iter() {
             let at_max_transfers = transfers.len() == max_transfers;
             let at_max_deploys = wasm_deploys.len() == max_deploys
-                || (deploy_type.is_wasm()
+                || (!footprint.is_transfer
                     && block_size_running_total + DEPLOY_APPROX_MIN_SIZE >= max_block_size_bytes);
 
             if at_max_deploys && at_max_transfers {
@@ -437,35 +469,26 @@ impl BlockProposerReady {
             }
 
             if !self.is_deploy_valid(
-                &deploy_type.header(),
+                &footprint.header,
                 block_timestamp,
                 &deploy_config,
                 &past_deploys,
             ) || past_deploys.contains(hash)
                 || self.sets.finalized_deploys.contains_key(hash)
+                || !supports(protocol_version, footprint.min_protocol_version.as_ref())
             {
                 continue;
             }
 
             // always include wasm-less transfers if we are under the max for them
-            if deploy_type.is_transfer() && !at_max_transfers {
+            if footprint.is_transfer && !at_max_transfers {
                 transfers.push(*hash);
-            } else if deploy_type.is_wasm() && !at_max_deploys {
-                if block_size_running_total + deploy_type.size() > max_block_size_bytes {
+            } else if !footprint.is_transfer && !at_max_deploys {
+                if block_size_running_total + footprint.size > max_block_size_bytes {
                     continue;
                 }
-                let payment_amount_gas = match Gas::from_motes(
-                    deploy_type.payment_amount(),
-                    deploy_type.header().gas_price(),
-                ) {
-                    Some(value) => value,
-                    None => {
-                        error!("payment_amount couldn't be converted from motes to gas");
-                        continue;
-                    }
-                };
                 let gas_running_total = if let Some(gas_running_total) =
-                    block_gas_running_total.checked_add(payment_amount_gas)
+                    block_gas_running_total.checked_add(footprint.gas_estimate)
                 {
                     gas_running_total
                 } else {
@@ -478,19 +501,69 @@ impl BlockProposerReady {
                 }
                 wasm_deploys.push(*hash);
                 block_gas_running_total = gas_running_total;
-                block_size_running_total += deploy_type.size();
+                block_size_running_total += footprint.size;
             }
         }
 
+        for hash in wasm_deploys.iter().chain(&transfers) {
+            logging::deploy_span(hash).in_scope(|| debug!("deploy proposed"));
+        }
         ProtoBlock::new(wasm_deploys, transfers, random_bit)
     }
 
     /// Prunes expired deploy information from the BlockProposer, returns the total deploys pruned.
     fn prune(&mut self, current_instant: Timestamp) -> usize {
-        self.sets.prune(current_instant)
+        let pruned = self.sets.prune(current_instant);
+        let pending = &self.sets.pending;
+        self.footprints.retain(|hash, _| pending.contains_key(hash));
+        pruned
     }
 
     fn contains_finalized(&self, dep: &DeployHash) -> bool {
         self.sets.finalized_deploys.contains_key(dep) || self.unhandled_finalized.contains(dep)
     }
+
+    /// Returns the protocol version in effect at `height`.
+    fn protocol_version_at(&self, height: BlockHeight) -> Option<&Version> {
+        self.protocol_versions
+            .iter()
+            .rev()
+            .find(|(activation_height, _)| *activation_height <= height)
+            .map(|(_, version)| version)
+    }
+}
+
+/// Returns whether a block of `protocol_version` may contain a deploy, i.e. whether it meets the
+/// deploy's minimum protocol version, if any.
+fn supports(protocol_version: Option<&Version>, min_protocol_version: Option<&Version>) -> bool {
+    match (protocol_version, min_protocol_version) {
+        (Some(current), Some(required)) => required <= current,
+        _ => true,
+    }
+}
+
+/// Returns the protocol versions of the chain by the height they take effect at, in ascending
+/// order.
+fn protocol_versions(chainspec: &Chainspec) -> Vec<(BlockHeight, Version)> {
+    let mut protocol_versions: Vec<_> = iter::once((0, chainspec.genesis.protocol_version.clone()))
+        .chain(chainspec.upgrades.iter().map(|upgrade| {
+            (
+                upgrade.activation_point.height,
+                upgrade.protocol_version.clone(),
+            )
+        }))
+        .collect();
+    protocol_versions.sort_by_key(|(height, _)| *height);
+    protocol_versions
+}
+
+/// Computes the footprints of the given pending deploys, skipping any whose payment can't be
+/// converted to gas.
+fn footprints(pending: &HashMap<DeployHash, DeployType>) -> HashMap<DeployHash, DeployFootprint> {
+    pending
+        .iter()
+        .filter_map(|(hash, deploy_type)| {
+            DeployFootprint::new(deploy_type).map(|footprint| (*hash, footprint))
+        })
+        .collect()
 }
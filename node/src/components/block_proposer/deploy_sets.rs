@@ -64,14 +64,20 @@ impl Display for BlockProposerDeploySets {
     }
 }
 
+/// The version of the serialized layout of `BlockProposerDeploySets`.
+///
+/// Must be increased whenever the layout changes, so that sets stored in an older layout are not
+/// loaded (which would fail to deserialize) but ignored instead.
+const DEPLOY_SETS_FORMAT_VERSION: u32 = 2;
+
 /// Create a state storage key for block proposer deploy sets based on a chainspec.
 ///
 /// We namespace based on a chainspec to prevent validators from loading data for a different chain
 /// if they forget to clear their state.
 pub fn create_storage_key(chainspec: &Chainspec) -> Vec<u8> {
     format!(
-        "block_proposer_deploy_sets:version={},chain_name={}",
-        chainspec.genesis.protocol_version, chainspec.genesis.name
+        "block_proposer_deploy_sets:format={},version={},chain_name={}",
+        DEPLOY_SETS_FORMAT_VERSION, chainspec.genesis.protocol_version, chainspec.genesis.name
     )
     .into()
 }
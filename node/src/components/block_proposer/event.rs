@@ -6,6 +6,7 @@ use std::{
 use datasize::DataSize;
 use derive_more::From;
 use fmt::Display;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 
 use super::{BlockHeight, BlockProposerDeploySets};
@@ -24,12 +25,16 @@ pub enum DeployType {
         header: DeployHeader,
         payment_amount: Motes,
         size: usize,
+        #[data_size(skip)]
+        min_protocol_version: Option<Version>,
     },
     /// Represents a wasm deploy.
     Other {
         header: DeployHeader,
         payment_amount: Motes,
         size: usize,
+        #[data_size(skip)]
+        min_protocol_version: Option<Version>,
     },
 }
 
@@ -66,6 +71,20 @@ impl DeployType {
         }
     }
 
+    /// Access the minimum protocol version required from all variants.
+    pub fn min_protocol_version(&self) -> Option<&Version> {
+        match self {
+            Self::Transfer {
+                min_protocol_version,
+                ..
+            } => min_protocol_version.as_ref(),
+            Self::Other {
+                min_protocol_version,
+                ..
+            } => min_protocol_version.as_ref(),
+        }
+    }
+
     /// Asks if the variant is a Transfer.
     pub fn is_transfer(&self) -> bool {
         matches!(self, DeployType::Transfer { .. })
@@ -106,6 +125,16 @@ pub enum Event {
     },
 }
 
+impl Event {
+    /// Returns the hash of the deploy the event concerns, if it concerns a single one.
+    pub(crate) fn deploy_hash(&self) -> Option<DeployHash> {
+        match self {
+            Event::BufferDeploy { hash, .. } => Some(*hash),
+            _ => None,
+        }
+    }
+}
+
 impl Display for Event {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
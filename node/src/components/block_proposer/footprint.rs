@@ -0,0 +1,49 @@
+use datasize::DataSize;
+use semver::Version;
+
+use super::event::DeployType;
+use crate::types::DeployHeader;
+use casper_execution_engine::shared::gas::Gas;
+use num_traits::Zero;
+
+/// The resources a pending deploy takes up in a block.
+///
+/// Footprints are computed once when a deploy is buffered, so that assembling a proto block only
+/// has to select from them instead of converting payments to gas on every proposal.
+#[derive(Clone, DataSize, Debug)]
+pub(super) struct DeployFootprint {
+    /// The header of the deploy, including its account and declared dependencies.
+    pub(super) header: DeployHeader,
+    /// The serialized size of the deploy in bytes.
+    pub(super) size: usize,
+    /// The gas the deploy's payment amounts to at its gas price, zero for transfers.
+    #[data_size(skip)]
+    pub(super) gas_estimate: Gas,
+    /// Whether the deploy is a wasm-less transfer.
+    pub(super) is_transfer: bool,
+    /// The minimum protocol version the deploy requires, if any.
+    #[data_size(skip)]
+    pub(super) min_protocol_version: Option<Version>,
+}
+
+impl DeployFootprint {
+    /// Computes the footprint of a deploy.
+    ///
+    /// Returns `None` if the payment amount of a wasm deploy cannot be converted to gas at the
+    /// deploy's gas price.
+    pub(super) fn new(deploy_type: &DeployType) -> Option<Self> {
+        let header = deploy_type.header().clone();
+        let gas_estimate = if deploy_type.is_transfer() {
+            Gas::zero()
+        } else {
+            Gas::from_motes(deploy_type.payment_amount(), header.gas_price())?
+        };
+        Some(DeployFootprint {
+            header,
+            size: deploy_type.size(),
+            gas_estimate,
+            is_transfer: deploy_type.is_transfer(),
+            min_protocol_version: deploy_type.min_protocol_version().cloned(),
+        })
+    }
+}
@@ -7,7 +7,7 @@ use casper_types::{
 use crate::{
     crypto::AsymmetricKeyExt,
     testing::TestRng,
-    types::{BlockLike, Deploy, DeployHash, TimeDiff},
+    types::{BlockLike, Deploy, DeployHash, TimeDiff, ARG_MIN_PROTOCOL_VERSION},
 };
 
 use super::*;
@@ -93,8 +93,10 @@ fn generate_deploy(
 fn create_test_proposer() -> BlockProposerReady {
     BlockProposerReady {
         sets: Default::default(),
+        footprints: Default::default(),
         deploy_config: Default::default(),
         state_key: b"block-proposer-test".to_vec(),
+        protocol_versions: vec![(0, Version::new(1, 0, 0))],
         request_queue: Default::default(),
         unhandled_finalized: Default::default(),
     }
@@ -316,6 +318,68 @@ fn should_successfully_prune() {
     assert_eq!(proposer.sets.finalized_deploys.len(), 0);
 }
 
+#[test]
+fn should_keep_footprints_in_sync_with_pending_deploys() {
+    let creation_time = Timestamp::from(100);
+    let test_time = Timestamp::from(120);
+    let expired_time = Timestamp::from(201);
+    let ttl = TimeDiff::from(Duration::from_millis(100));
+
+    let mut rng = crate::new_rng();
+    let deploy1 = generate_deploy(
+        &mut rng,
+        creation_time,
+        ttl,
+        vec![],
+        default_gas_payment(),
+        DEFAULT_TEST_GAS_PRICE,
+    );
+    let deploy2 = generate_deploy(
+        &mut rng,
+        creation_time,
+        ttl,
+        vec![],
+        default_gas_payment(),
+        DEFAULT_TEST_GAS_PRICE,
+    );
+    // A gas price of zero makes the payment unconvertible to gas.
+    let deploy3 = generate_deploy(
+        &mut rng,
+        creation_time,
+        ttl,
+        vec![],
+        default_gas_payment(),
+        0,
+    );
+    let mut proposer = create_test_proposer();
+
+    proposer.add_deploy_or_transfer(creation_time, *deploy1.id(), deploy1.deploy_type().unwrap());
+    proposer.add_deploy_or_transfer(creation_time, *deploy2.id(), deploy2.deploy_type().unwrap());
+    proposer.add_deploy_or_transfer(creation_time, *deploy3.id(), deploy3.deploy_type().unwrap());
+
+    // The deploy without a footprint is kept pending, but never proposed.
+    assert_eq!(proposer.footprints.len(), 2);
+    assert_eq!(proposer.sets.pending.len(), 3);
+    assert!(!proposer.footprints.contains_key(deploy3.id()));
+    let block =
+        proposer.propose_proto_block(DeployConfig::default(), test_time, HashSet::new(), true);
+    assert!(!block.wasm_deploys().contains(deploy3.id()));
+    let footprint = &proposer.footprints[deploy1.id()];
+    assert_eq!(footprint.size, deploy1.serialized_length());
+    assert_eq!(footprint.gas_estimate, default_gas_payment());
+    assert!(!footprint.is_transfer);
+
+    proposer.finalized_deploys(vec![*deploy1.id()]);
+    assert!(!proposer.footprints.contains_key(deploy1.id()));
+    assert!(proposer.footprints.contains_key(deploy2.id()));
+
+    assert_eq!(proposer.prune(test_time), 0);
+    assert_eq!(proposer.footprints.len(), 1);
+    proposer.prune(expired_time);
+    assert!(proposer.footprints.is_empty());
+    assert!(proposer.sets.pending.is_empty());
+}
+
 #[test]
 fn should_keep_track_of_unhandled_deploys() {
     let creation_time = Timestamp::from(100);
@@ -670,3 +734,45 @@ fn should_return_deploy_dependencies() {
     assert_eq!(deploys2.len(), 1);
     assert!(deploys2.contains(deploy2.id()));
 }
+
+#[test]
+fn should_hold_back_deploys_requiring_later_protocol_version() {
+    let creation_time = Timestamp::from(100);
+    let ttl = TimeDiff::from(Duration::from_millis(100));
+    let block_time = Timestamp::from(120);
+
+    let mut rng = crate::new_rng();
+    let deploy = Deploy::new(
+        creation_time,
+        ttl,
+        DEFAULT_TEST_GAS_PRICE,
+        vec![],
+        "chain".to_string(),
+        ExecutableDeployItem::ModuleBytes {
+            module_bytes: Bytes::new(),
+            args: runtime_args! {
+                ARG_AMOUNT => default_gas_payment().value(),
+                ARG_MIN_PROTOCOL_VERSION => "2.0.0".to_string()
+            },
+        },
+        ExecutableDeployItem::ModuleBytes {
+            module_bytes: Bytes::new(),
+            args: RuntimeArgs::new(),
+        },
+        &SecretKey::random(&mut rng),
+        &mut rng,
+    );
+
+    let mut proposer = create_test_proposer();
+    proposer.protocol_versions.push((10, Version::new(2, 0, 0)));
+    proposer.add_deploy_or_transfer(creation_time, *deploy.id(), deploy.deploy_type().unwrap());
+
+    let propose = |proposer: &mut BlockProposerReady| {
+        proposer.propose_proto_block(DeployConfig::default(), block_time, HashSet::new(), true)
+    };
+    proposer.sets.next_finalized = 9;
+    assert!(propose(&mut proposer).deploys().is_empty());
+
+    proposer.sets.next_finalized = 10;
+    assert_eq!(propose(&mut proposer).wasm_deploys(), &vec![*deploy.id()]);
+}
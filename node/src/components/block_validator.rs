@@ -21,13 +21,13 @@ use datasize::DataSize;
 use derive_more::{Display, From};
 use semver::Version;
 use smallvec::{smallvec, SmallVec};
-use tracing::error;
+use tracing::{debug, error};
 
 use crate::{
     components::Component,
     effect::{
         requests::{BlockValidationRequest, FetcherRequest, StorageRequest},
-        EffectBuilder, EffectExt, EffectOptionExt, Effects, Responder,
+        EffectBuilder, EffectExt, Effects, Responder,
     },
     types::{BlockLike, Deploy, DeployHash},
     Chainspec, NodeRng,
@@ -51,6 +51,13 @@ pub enum Event<T, I> {
     #[display(fmt = "deploy {} missing", _0)]
     DeployMissing(DeployHash),
 
+    /// All deploys of a block have been fetched or given up on.
+    #[display(fmt = "fetched deploys of a block, {} missing", "missing.len()")]
+    DeploysFetched {
+        /// The deploys which could not be fetched.
+        missing: Vec<DeployHash>,
+    },
+
     /// An event changing the current state to BlockValidatorReady, once the chainspec has been
     /// loaded.
     #[display(fmt = "block validator loaded")]
@@ -136,39 +143,36 @@ where
                         let missing_deploys: HashSet<DeployHash> =
                             entry.key().deploys().iter().map(|hash| **hash).collect();
 
-                        let in_flight = &mut self.in_flight;
-                        let chainspec = Arc::clone(&self.chainspec);
-                        let fetch_effects: Effects<Event<T, I>> = block_deploys
-                            .iter()
-                            .flat_map(|deploy_hash| {
-                                let chainspec = Arc::clone(&chainspec);
-                                // For every request, increase the number of in-flight...
-                                in_flight.inc(deploy_hash);
+                        // For every request, increase the number of in-flight...
+                        for deploy_hash in &block_deploys {
+                            self.in_flight.inc(deploy_hash);
+                        }
 
-                                // ...then request it.
-                                let deploy_hash = *deploy_hash;
-                                let validate_deploy =
-                                    move |result: FetchResult<Deploy, I>| match result {
-                                        FetchResult::FromStorage(deploy)
-                                        | FetchResult::FromPeer(deploy, _) => {
-                                            if deploy.header().is_valid(
-                                                &chainspec.genesis.deploy_config,
-                                                block_timestamp,
-                                            ) {
-                                                Event::DeployFound(deploy_hash)
-                                            } else {
-                                                Event::DeployMissing(deploy_hash)
-                                            }
-                                        }
-                                    };
-                                effect_builder
-                                    .fetch_deploy(deploy_hash, sender.clone())
-                                    .map_or_else(validate_deploy, move || {
-                                        Event::DeployMissing(deploy_hash)
-                                    })
-                            })
-                            .collect();
-                        effects.extend(fetch_effects);
+                        // ...then request them.
+                        let chainspec = Arc::clone(&self.chainspec);
+                        let validate_deploy =
+                            move |deploy_hash, result: Option<FetchResult<Deploy, I>>| {
+                                let is_valid = match result {
+                                    Some(FetchResult::FromStorage(deploy))
+                                    | Some(FetchResult::FromPeer(deploy, _)) => {
+                                        deploy.header().is_valid(
+                                            &chainspec.genesis.deploy_config,
+                                            block_timestamp,
+                                        )
+                                    }
+                                    None => false,
+                                };
+                                if is_valid {
+                                    Event::DeployFound(deploy_hash)
+                                } else {
+                                    Event::DeployMissing(deploy_hash)
+                                }
+                            };
+                        effects.extend(
+                            effect_builder
+                                .fetch_many(block_deploys, sender, validate_deploy)
+                                .event(|missing| Event::DeploysFetched { missing }),
+                        );
 
                         entry.insert(BlockValidationState {
                             missing_deploys,
@@ -220,6 +224,15 @@ where
                     }
                 });
             }
+            Event::DeploysFetched { missing } => {
+                // The outcome for each deploy has been handled already as it arrived.
+                if !missing.is_empty() {
+                    debug!(
+                        ?missing,
+                        "could not fetch all deploys of a block to validate"
+                    );
+                }
+            }
             Event::Loaded { .. } => {}
         }
         effects
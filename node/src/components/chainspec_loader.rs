@@ -19,7 +19,7 @@ use derive_more::From;
 use once_cell::sync::Lazy;
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
 
 use casper_execution_engine::core::engine_state::{self, genesis::GenesisResult};
 
@@ -34,12 +34,15 @@ use crate::{
     NodeRng,
 };
 pub use chainspec::Chainspec;
-pub(crate) use chainspec::{DeployConfig, HighwayConfig, UpgradePoint};
+pub(crate) use chainspec::{
+    ActivationPoint, DeployConfig, EmergencyValidators, GenesisConfig, HighwayConfig, UpgradePoint,
+};
 pub use error::Error;
 
 static CHAINSPEC_INFO: Lazy<ChainspecInfo> = Lazy::new(|| ChainspecInfo {
     name: String::from("casper-example"),
     root_hash: Some(Digest::from([2u8; Digest::LENGTH])),
+    protocol_version: Version::new(1, 0, 0),
 });
 
 /// `ChainspecHandler` events.
@@ -76,11 +79,22 @@ pub struct ChainspecInfo {
     name: String,
     // If `Some` then genesis process returned a valid post state hash.
     root_hash: Option<Digest>,
+    // The latest protocol version the chainspec supports, including scheduled upgrades.
+    #[data_size(skip)]
+    protocol_version: Version,
 }
 
 impl ChainspecInfo {
-    pub(crate) fn new(name: String, root_hash: Option<Digest>) -> ChainspecInfo {
-        ChainspecInfo { name, root_hash }
+    pub(crate) fn new(
+        name: String,
+        root_hash: Option<Digest>,
+        protocol_version: Version,
+    ) -> ChainspecInfo {
+        ChainspecInfo {
+            name,
+            root_hash,
+            protocol_version,
+        }
     }
 
     pub fn name(&self) -> String {
@@ -90,6 +104,10 @@ impl ChainspecInfo {
     pub fn root_hash(&self) -> Option<Digest> {
         self.root_hash
     }
+
+    pub fn protocol_version(&self) -> &Version {
+        &self.protocol_version
+    }
 }
 
 impl DocExample for ChainspecInfo {
@@ -103,6 +121,7 @@ impl From<ChainspecLoader> for ChainspecInfo {
         ChainspecInfo::new(
             chainspec_loader.chainspec.genesis.name.clone(),
             chainspec_loader.genesis_state_root_hash,
+            chainspec_loader.chainspec.latest_protocol_version(),
         )
     }
 }
@@ -124,6 +143,16 @@ impl ChainspecLoader {
     where
         REv: From<Event> + From<StorageRequest> + Send,
     {
+        for upgrade_point in &chainspec.upgrades {
+            if let Some(emergency_validators) = &upgrade_point.emergency_validators {
+                warn!(
+                    activation_height = upgrade_point.activation_point.height,
+                    validator_count = emergency_validators.weights().len(),
+                    validators_hash = %emergency_validators.hash(),
+                    "EMERGENCY: the chainspec replaces the validator set computed by the auction"
+                );
+            }
+        }
         let version = chainspec.genesis.protocol_version.clone();
         let effects = effect_builder
             .put_chainspec(chainspec.clone())
@@ -1,4 +1,5 @@
 use std::{
+    collections::{btree_map::Entry, BTreeMap},
     fmt::{self, Debug, Formatter},
     path::Path,
     str::FromStr,
@@ -20,14 +21,18 @@ use casper_execution_engine::{
 };
 use casper_types::{auction::EraId, PublicKey, U512};
 
-use super::{config, error::GenesisLoadError, Error};
-#[cfg(test)]
-use crate::testing::TestRng;
+use super::{
+    config,
+    error::{EmergencyValidatorsError, GenesisLoadError},
+    Error,
+};
 use crate::{
     crypto::hash::{self, Digest},
     types::{TimeDiff, Timestamp},
     utils::Loadable,
 };
+#[cfg(test)]
+use crate::{crypto::AsymmetricKeyExt, testing::TestRng};
 
 #[derive(Copy, Clone, DataSize, Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
@@ -35,6 +40,8 @@ use crate::{
 pub struct DeployConfig {
     pub(crate) max_payment_cost: Motes,
     pub(crate) max_ttl: TimeDiff,
+    pub(crate) min_ttl: TimeDiff,
+    pub(crate) max_timestamp_drift: TimeDiff,
     pub(crate) max_dependencies: u8,
     pub(crate) max_block_size: u32,
     pub(crate) block_max_deploy_count: u32,
@@ -47,6 +54,8 @@ impl Default for DeployConfig {
         DeployConfig {
             max_payment_cost: Motes::zero(),
             max_ttl: TimeDiff::from_str("1day").unwrap(),
+            min_ttl: TimeDiff::from_str("1min").unwrap(),
+            max_timestamp_drift: TimeDiff::from_str("30s").unwrap(),
             max_dependencies: 10,
             max_block_size: 10_485_760,
             block_max_deploy_count: 10,
@@ -64,6 +73,8 @@ impl DeployConfig {
             rng.gen_range::<_, u64, u64>(1_000_000, 1_000_000_000),
         ));
         let max_ttl = TimeDiff::from(rng.gen_range(60_000, 3_600_000));
+        let min_ttl = TimeDiff::from(rng.gen_range(0, 60_000));
+        let max_timestamp_drift = TimeDiff::from(rng.gen_range(0, 60_000));
         let max_dependencies = rng.gen();
         let max_block_size = rng.gen_range(1_000_000, 1_000_000_000);
         let block_max_deploy_count = rng.gen();
@@ -73,6 +84,8 @@ impl DeployConfig {
         DeployConfig {
             max_payment_cost,
             max_ttl,
+            min_ttl,
+            max_timestamp_drift,
             max_dependencies,
             max_block_size,
             block_max_deploy_count,
@@ -326,6 +339,9 @@ pub(crate) struct UpgradePoint {
     pub(crate) new_system_config: Option<SystemConfig>,
     pub(crate) new_deploy_config: Option<DeployConfig>,
     pub(crate) new_validator_slots: Option<u32>,
+    /// A validator set replacing the one computed by the auction, from the activation point until
+    /// the next upgrade point.
+    pub(crate) emergency_validators: Option<EmergencyValidators>,
 }
 
 #[cfg(test)]
@@ -348,6 +364,19 @@ impl UpgradePoint {
         };
         let new_validator_slots = rng.gen::<Option<u32>>();
         let new_system_config = rng.gen();
+        let emergency_validators = if rng.gen() {
+            let validators = (0..rng.gen_range(1, 5))
+                .map(|_| {
+                    (
+                        PublicKey::random(rng),
+                        U512::from(rng.gen_range(1, 1_000_000)),
+                    )
+                })
+                .collect();
+            Some(EmergencyValidators(validators))
+        } else {
+            None
+        };
 
         UpgradePoint {
             activation_point,
@@ -356,7 +385,96 @@ impl UpgradePoint {
             new_system_config,
             new_deploy_config,
             new_validator_slots,
+            emergency_validators,
+        }
+    }
+}
+
+/// A validator set replacing the one computed by the auction.
+///
+/// This is only meant for recovering a network which lost the keys of so many validators that it
+/// can't finalize blocks anymore. Nodes refuse to start with a chainspec containing one unless
+/// their consensus config confirms it.
+#[derive(Clone, DataSize, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct EmergencyValidators(BTreeMap<PublicKey, U512>);
+
+impl EmergencyValidators {
+    /// Creates a validator set from the given validators and weights.
+    ///
+    /// Fails if the set is empty, lists a validator twice, contains a weight of zero or if the
+    /// total weight overflows.
+    pub(crate) fn new<I>(validators: I) -> Result<Self, EmergencyValidatorsError>
+    where
+        I: IntoIterator<Item = (PublicKey, U512)>,
+    {
+        let mut weights = BTreeMap::new();
+        let mut total_weight = U512::zero();
+        for (public_key, weight) in validators {
+            if weight.is_zero() {
+                return Err(EmergencyValidatorsError::ZeroWeight(public_key));
+            }
+            total_weight = total_weight
+                .checked_add(weight)
+                .ok_or(EmergencyValidatorsError::TotalWeightOverflow)?;
+            match weights.entry(public_key) {
+                Entry::Vacant(entry) => {
+                    let _ = entry.insert(weight);
+                }
+                Entry::Occupied(_) => {
+                    return Err(EmergencyValidatorsError::DuplicateValidator(public_key));
+                }
+            }
+        }
+        if weights.is_empty() {
+            return Err(EmergencyValidatorsError::Empty);
+        }
+        Ok(EmergencyValidators(weights))
+    }
+
+    /// Returns the validators and their weights.
+    pub(crate) fn weights(&self) -> &BTreeMap<PublicKey, U512> {
+        &self.0
+    }
+
+    /// Returns the hash of the validator set, which the consensus config has to confirm.
+    pub(crate) fn hash(&self) -> Digest {
+        let serialized = bincode::serialize(&self.0)
+            .unwrap_or_else(|error| panic!("failed to serialize validator set: {}", error));
+        hash::hash(&serialized)
+    }
+
+    /// Checks that the set can be activated at `height`, with `validator_slots` in effect.
+    fn validate(&self, height: u64, validator_slots: u32) -> Result<(), EmergencyValidatorsError> {
+        if height == 0 {
+            return Err(EmergencyValidatorsError::AtGenesis);
+        }
+        if self.0.len() > validator_slots as usize {
+            return Err(EmergencyValidatorsError::TooManyValidators {
+                count: self.0.len(),
+                slots: validator_slots,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Loadable for EmergencyValidators {
+    type Error = EmergencyValidatorsError;
+
+    fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Self::Error> {
+        #[derive(Debug, Deserialize)]
+        struct ParsedValidator {
+            public_key: PublicKey,
+            weight: U512,
         }
+
+        let mut reader = ReaderBuilder::new().has_headers(false).from_path(path)?;
+        let mut validators = vec![];
+        for result in reader.deserialize() {
+            let parsed: ParsedValidator = result?;
+            validators.push((parsed.public_key, parsed.weight));
+        }
+        EmergencyValidators::new(validators)
     }
 }
 
@@ -382,6 +500,31 @@ impl Chainspec {
         self.genesis.validate_config();
     }
 
+    /// Checks the emergency validator sets of the upgrade points against the validator slots in
+    /// effect at their activation points.
+    pub(crate) fn validate_emergency_validators(&self) -> Result<(), Error> {
+        let mut validator_slots = self.genesis.validator_slots;
+        for upgrade_point in &self.upgrades {
+            validator_slots = upgrade_point.new_validator_slots.unwrap_or(validator_slots);
+            if let Some(emergency_validators) = &upgrade_point.emergency_validators {
+                let height = upgrade_point.activation_point.height;
+                emergency_validators
+                    .validate(height, validator_slots)
+                    .map_err(|error| Error::InvalidEmergencyValidators { height, error })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the largest `max_block_size` in effect at genesis or after any of the upgrades.
+    pub(crate) fn max_block_size(&self) -> u32 {
+        self.upgrades
+            .iter()
+            .filter_map(|upgrade_point| upgrade_point.new_deploy_config)
+            .map(|deploy_config| deploy_config.max_block_size)
+            .fold(self.genesis.deploy_config.max_block_size, u32::max)
+    }
+
     /// Serializes `self` and hashes the resulting bytes.
     pub(crate) fn hash(&self) -> Digest {
         let serialized_chainspec = bincode::serialize(self).unwrap_or_else(|error| {
@@ -433,6 +576,7 @@ mod tests {
 
     use casper_execution_engine::shared::{
         host_function_costs::{HostFunction, HostFunctionCosts},
+        host_side_costs::HostSideCosts,
         opcode_costs::OpcodeCosts,
         storage_costs::StorageCosts,
         wasm_config::WasmConfig,
@@ -482,6 +626,7 @@ mod tests {
             remove_contract_user_group_urefs: HostFunction::new(131, [0, 1, 2, 3, 4, 5]),
             print: HostFunction::new(123, [0, 1]),
             blake2b: HostFunction::new(133, [0, 1, 2, 3]),
+            emit_event: HostFunction::new(142, [0, 1, 2, 3]),
         });
     static EXPECTED_GENESIS_WASM_CONFIG: Lazy<WasmConfig> = Lazy::new(|| {
         WasmConfig::new(
@@ -490,11 +635,14 @@ mod tests {
             EXPECTED_GENESIS_COSTS,
             EXPECTED_GENESIS_STORAGE_COSTS,
             *EXPECTED_GENESIS_HOST_FUNCTION_COSTS,
+            HostSideCosts::default(),
         )
     });
 
     const EXPECTED_GENESIS_STORAGE_COSTS: StorageCosts = StorageCosts::new(101);
 
+    const EXPECTED_UPGRADE_HOST_SIDE_COSTS: HostSideCosts = HostSideCosts::new(41, 42, 43);
+
     const EXPECTED_GENESIS_COSTS: OpcodeCosts = OpcodeCosts {
         bit: 13,
         add: 14,
@@ -578,6 +726,14 @@ mod tests {
             spec.genesis.deploy_config.max_ttl,
             TimeDiff::from(26300160000)
         );
+        assert_eq!(
+            spec.genesis.deploy_config.min_ttl,
+            TimeDiff::from(7_200_000)
+        );
+        assert_eq!(
+            spec.genesis.deploy_config.max_timestamp_drift,
+            TimeDiff::from(180_000)
+        );
         assert_eq!(spec.genesis.deploy_config.max_dependencies, 11);
         assert_eq!(spec.genesis.deploy_config.max_block_size, 12);
         assert_eq!(spec.genesis.deploy_config.block_max_deploy_count, 125);
@@ -600,6 +756,10 @@ mod tests {
             upgrade0.new_wasm_config.as_ref().unwrap().opcode_costs(),
             EXPECTED_UPGRADE_COSTS,
         );
+        assert_eq!(
+            new_wasm_config.host_side_costs(),
+            EXPECTED_UPGRADE_HOST_SIDE_COSTS
+        );
 
         assert_eq!(new_wasm_config.max_memory, 17);
         assert_eq!(new_wasm_config.max_stack_height, 19);
@@ -612,6 +772,14 @@ mod tests {
             upgrade0.new_deploy_config.unwrap().max_ttl,
             TimeDiff::from(1104516000000)
         );
+        assert_eq!(
+            upgrade0.new_deploy_config.unwrap().min_ttl,
+            TimeDiff::from(86_400_000)
+        );
+        assert_eq!(
+            upgrade0.new_deploy_config.unwrap().max_timestamp_drift,
+            TimeDiff::from(40_000)
+        );
         assert_eq!(upgrade0.new_deploy_config.unwrap().max_dependencies, 36);
         assert_eq!(upgrade0.new_deploy_config.unwrap().max_block_size, 37);
         assert_eq!(
@@ -629,6 +797,25 @@ mod tests {
         assert_eq!(upgrade1.protocol_version, Version::from((0, 3, 0)));
         assert!(upgrade1.new_wasm_config.is_none());
         assert!(upgrade1.new_deploy_config.is_none());
+        assert!(upgrade0.emergency_validators.is_none());
+        let emergency_weights: Vec<_> = upgrade1
+            .emergency_validators
+            .as_ref()
+            .expect("should have emergency validators")
+            .weights()
+            .iter()
+            .map(|(public_key, weight)| {
+                let account = spec
+                    .genesis
+                    .accounts
+                    .iter()
+                    .position(|account| account.public_key() == Some(*public_key));
+                (account, weight.as_u64())
+            })
+            .collect();
+        assert_eq!(emergency_weights.len(), 2);
+        assert!(emergency_weights.contains(&(Some(1), 50)));
+        assert!(emergency_weights.contains(&(Some(3), 70)));
     }
 
     #[test]
@@ -637,6 +824,48 @@ mod tests {
         check_spec(spec);
     }
 
+    #[test]
+    fn should_take_largest_max_block_size_of_all_upgrades() {
+        let spec = Chainspec::from_resources("test/valid/chainspec.toml");
+        assert_eq!(spec.max_block_size(), 37);
+    }
+
+    #[test]
+    fn should_reject_invalid_emergency_validators() {
+        let mut rng = crate::new_rng();
+        let alice = PublicKey::random(&mut rng);
+        let bob = PublicKey::random(&mut rng);
+
+        assert!(matches!(
+            EmergencyValidators::new(vec![]),
+            Err(EmergencyValidatorsError::Empty)
+        ));
+        assert!(matches!(
+            EmergencyValidators::new(vec![(alice, U512::one()), (alice, U512::one())]),
+            Err(EmergencyValidatorsError::DuplicateValidator(key)) if key == alice
+        ));
+        assert!(matches!(
+            EmergencyValidators::new(vec![(alice, U512::one()), (bob, U512::zero())]),
+            Err(EmergencyValidatorsError::ZeroWeight(key)) if key == bob
+        ));
+        assert!(matches!(
+            EmergencyValidators::new(vec![(alice, U512::max_value()), (bob, U512::one())]),
+            Err(EmergencyValidatorsError::TotalWeightOverflow)
+        ));
+
+        let validators =
+            EmergencyValidators::new(vec![(alice, U512::one()), (bob, U512::one())]).unwrap();
+        assert!(validators.validate(1, 2).is_ok());
+        assert!(matches!(
+            validators.validate(0, 2),
+            Err(EmergencyValidatorsError::AtGenesis)
+        ));
+        assert!(matches!(
+            validators.validate(1, 1),
+            Err(EmergencyValidatorsError::TooManyValidators { count: 2, slots: 1 })
+        ));
+    }
+
     #[test]
     fn bincode_roundtrip() {
         let mut rng = crate::new_rng();
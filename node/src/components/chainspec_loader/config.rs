@@ -74,6 +74,15 @@ struct UpgradePoint {
     new_system_config: Option<SystemConfig>,
     new_deploy_config: Option<DeployConfig>,
     new_validator_slots: Option<u32>,
+    emergency_validators_path: Option<External<chainspec::EmergencyValidators>>,
+}
+
+/// Returns the path of the emergency validators file of the upgrade to `protocol_version`.
+///
+/// Each upgrade has its own file, so that the validator sets of several upgrades don't overwrite
+/// each other.
+fn emergency_validators_csv_path(protocol_version: &Version) -> String {
+    format!("emergency_validators-{}.csv", protocol_version)
 }
 
 impl From<&chainspec::UpgradePoint> for UpgradePoint {
@@ -85,20 +94,34 @@ impl From<&chainspec::UpgradePoint> for UpgradePoint {
             new_system_config: upgrade_point.new_system_config,
             new_deploy_config: upgrade_point.new_deploy_config,
             new_validator_slots: upgrade_point.new_validator_slots,
+            emergency_validators_path: upgrade_point.emergency_validators.as_ref().map(|_| {
+                External::path(emergency_validators_csv_path(
+                    &upgrade_point.protocol_version,
+                ))
+            }),
         }
     }
 }
 
 impl UpgradePoint {
-    fn into_chainspec_upgrade_point<P: AsRef<Path>>(self, _root: P) -> chainspec::UpgradePoint {
-        chainspec::UpgradePoint {
+    fn into_chainspec_upgrade_point<P: AsRef<Path>>(
+        self,
+        root: P,
+    ) -> Result<chainspec::UpgradePoint, Error> {
+        let emergency_validators = self
+            .emergency_validators_path
+            .map(|path| path.load(root))
+            .transpose()
+            .map_err(Error::LoadEmergencyValidators)?;
+        Ok(chainspec::UpgradePoint {
             activation_point: self.activation_point,
             protocol_version: self.protocol_version,
             new_wasm_config: self.new_wasm_config,
             new_system_config: self.new_system_config,
             new_deploy_config: self.new_deploy_config,
             new_validator_slots: self.new_validator_slots,
-        }
+            emergency_validators,
+        })
     }
 }
 
@@ -189,8 +212,42 @@ pub(super) fn parse_toml<P: AsRef<Path>>(chainspec_path: P) -> Result<chainspec:
     let chainspec_upgrades = chainspec.upgrade.unwrap_or_default();
     let mut upgrades = Vec::with_capacity(chainspec_upgrades.len());
     for upgrade_point in chainspec_upgrades.into_iter() {
-        upgrades.push(upgrade_point.into_chainspec_upgrade_point(root));
+        upgrades.push(upgrade_point.into_chainspec_upgrade_point(root)?);
     }
 
-    Ok(chainspec::Chainspec { genesis, upgrades })
+    let chainspec = chainspec::Chainspec { genesis, upgrades };
+    chainspec.validate_emergency_validators()?;
+    Ok(chainspec)
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{PublicKey, U512};
+
+    use super::*;
+    use crate::testing::TestRng;
+
+    #[test]
+    fn should_use_distinct_emergency_validators_paths_per_upgrade() {
+        let mut rng = TestRng::new();
+        let paths: Vec<_> = (1..=2)
+            .map(|minor| {
+                let mut upgrade_point = chainspec::UpgradePoint::random(&mut rng);
+                upgrade_point.protocol_version = Version::new(2, minor, 0);
+                upgrade_point.emergency_validators = Some(
+                    chainspec::EmergencyValidators::new(vec![(
+                        PublicKey::random(&mut rng),
+                        U512::one(),
+                    )])
+                    .unwrap(),
+                );
+                UpgradePoint::from(&upgrade_point)
+                    .emergency_validators_path
+                    .expect("should have emergency validators path")
+            })
+            .collect();
+
+        assert_eq!(paths[0], External::path("emergency_validators-2.1.0.csv"));
+        assert_eq!(paths[1], External::path("emergency_validators-2.2.0.csv"));
+    }
 }
@@ -1,7 +1,7 @@
 use thiserror::Error;
 use uint::FromDecStrErr;
 
-use casper_types::account::ACCOUNT_HASH_LENGTH;
+use casper_types::{account::ACCOUNT_HASH_LENGTH, PublicKey};
 
 use crate::utils::{LoadError, ReadFileError};
 
@@ -62,6 +62,19 @@ pub enum Error {
     /// Error loading the genesis accounts.
     #[error("could not load genesis accounts: {0}")]
     LoadGenesisAccounts(LoadError<GenesisLoadError>),
+
+    /// Error loading the emergency validator set of an upgrade point.
+    #[error("could not load emergency validators: {0}")]
+    LoadEmergencyValidators(LoadError<EmergencyValidatorsError>),
+
+    /// The emergency validator set of an upgrade point is invalid.
+    #[error("invalid emergency validators activated at height {height}: {error}")]
+    InvalidEmergencyValidators {
+        /// The activation height of the upgrade point.
+        height: u64,
+        /// The problem with the validator set.
+        error: EmergencyValidatorsError,
+    },
 }
 
 /// Error loading genesis accounts file.
@@ -87,3 +100,40 @@ pub enum GenesisLoadError {
     #[error("crypto module error: {0}")]
     Crypto(#[from] crate::crypto::Error),
 }
+
+/// Error loading or validating an emergency validator set.
+#[derive(Debug, Error)]
+pub enum EmergencyValidatorsError {
+    /// Error while decoding the validators from CSV format.
+    #[error("decoding from CSV error: {0}")]
+    DecodingFromCsv(#[from] csv::Error),
+
+    /// The set contains no validators.
+    #[error("the validator set is empty")]
+    Empty,
+
+    /// A validator is listed more than once.
+    #[error("validator {0} is listed more than once")]
+    DuplicateValidator(PublicKey),
+
+    /// A validator has a weight of zero.
+    #[error("validator {0} has a weight of zero")]
+    ZeroWeight(PublicKey),
+
+    /// The sum of the weights overflows.
+    #[error("the total weight overflows")]
+    TotalWeightOverflow,
+
+    /// The set has more validators than there are slots.
+    #[error("{count} validators exceed the {slots} validator slots")]
+    TooManyValidators {
+        /// The number of validators in the set.
+        count: usize,
+        /// The number of validator slots in effect at the activation point.
+        slots: u32,
+    },
+
+    /// The set would replace the genesis validators.
+    #[error("the validator set can not be activated at genesis")]
+    AtGenesis,
+}
@@ -13,6 +13,7 @@ mod tests;
 mod traits;
 
 use std::{
+    collections::BTreeMap,
     convert::Infallible,
     fmt::{self, Debug, Display, Formatter},
     time::Duration,
@@ -24,7 +25,7 @@ use hex_fmt::HexFmt;
 use serde::{Deserialize, Serialize};
 use tracing::error;
 
-use casper_types::PublicKey;
+use casper_types::{PublicKey, U512};
 
 use crate::{
     components::Component,
@@ -57,6 +58,13 @@ pub enum ConsensusMessage {
     EvidenceRequest { era_id: EraId, pub_key: PublicKey },
 }
 
+/// Capability to pass finalized blocks on for execution, see `EffectBuilder::execute_block`.
+///
+/// Only the consensus component can create it, as only blocks finalized by consensus may be
+/// executed without checking their state root hash.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BlockExecutionCapability(());
+
 /// An ID to distinguish different timers. What they are used for is specific to each consensus
 /// protocol implementation.
 #[derive(DataSize, Clone, Copy, Debug, Eq, PartialEq)]
@@ -112,11 +120,17 @@ pub enum Event<I> {
         booking_block_hash: Result<BlockHash, u64>,
         /// Ok(seed) if the key block was found, Err(height) if not
         key_block_seed: Result<Digest, u64>,
+        /// The new era's validator weights as computed by our own auction contract, if available
+        auction_validator_weights: Option<BTreeMap<PublicKey, U512>>,
     },
     /// An event instructing us to shutdown if the latest era received no votes
     Shutdown,
     /// An event fired when the joiner reactor transitions into validator.
     FinishedJoining(Timestamp),
+    /// A scheduled event to check whether we are possibly partitioned from the other validators.
+    CheckPartition,
+    /// A scheduled event to check whether the other validators are citing our own units.
+    CheckParticipation,
 }
 
 impl Debug for ConsensusMessage {
@@ -213,6 +227,10 @@ impl<I: Debug> Display for Event<I> {
             Event::FinishedJoining(timestamp) => {
                 write!(f, "The node finished joining the network at {}", timestamp)
             }
+            Event::CheckPartition => write!(f, "Check whether the node is possibly partitioned"),
+            Event::CheckParticipation => {
+                write!(f, "Check whether our own units are being cited")
+            }
         }
     }
 }
@@ -293,6 +311,7 @@ where
                 block_header,
                 booking_block_hash,
                 key_block_seed,
+                auction_validator_weights,
             } => {
                 let booking_block_hash = booking_block_hash.unwrap_or_else(|height| {
                     error!(
@@ -310,15 +329,25 @@ where
                     );
                     panic!("couldn't get the seed from the key block");
                 });
-                handling_es.handle_create_new_era(*block_header, booking_block_hash, key_block_seed)
+                handling_es.handle_create_new_era(
+                    *block_header,
+                    booking_block_hash,
+                    key_block_seed,
+                    auction_validator_weights,
+                )
             }
             Event::Shutdown => handling_es.shutdown_if_necessary(),
             Event::FinishedJoining(timestamp) => handling_es.finished_joining(timestamp),
+            Event::CheckPartition => handling_es.check_partition(),
+            Event::CheckParticipation => handling_es.check_participation(),
             Event::ConsensusRequest(requests::ConsensusRequest::IsBondedValidator(
                 era_id,
                 pk,
                 responder,
             )) => handling_es.is_bonded_validator(era_id, pk, responder),
+            Event::ConsensusRequest(requests::ConsensusRequest::CurrentValidators(responder)) => {
+                handling_es.current_validators(responder)
+            }
         }
     }
 }
@@ -6,9 +6,10 @@ use serde::{Deserialize, Serialize};
 use casper_types::SecretKey;
 
 use crate::{
-    components::chainspec_loader::{HighwayConfig, UpgradePoint},
+    components::chainspec_loader::{EmergencyValidators, HighwayConfig, UpgradePoint},
+    crypto::hash::Digest,
     types::{TimeDiff, Timestamp},
-    utils::External,
+    utils::{ConfigValidator, External},
     Chainspec,
 };
 
@@ -20,9 +21,33 @@ pub struct Config {
     /// Path to secret key file.
     pub secret_key_path: External<SecretKey>,
     /// Path to the folder where unit hash files will be stored.
+    ///
+    /// The files are verified at startup, consolidated per era once we stopped voting in it and
+    /// deleted once the era is no longer retained.
     pub unit_hashes_folder: PathBuf,
     /// The duration for which incoming vertices with missing dependencies are kept in a queue.
     pub pending_vertex_timeout: TimeDiff,
+    /// The maximum number of vertices with missing dependencies or from the future kept in the
+    /// queues of an era.
+    pub max_pending_vertices_per_era: usize,
+    /// The maximum number of such vertices from a single peer kept in the queues of an era.
+    pub max_pending_vertices_per_peer: usize,
+    /// How long ago a validator's latest unit may have been created for it to count as connected.
+    pub partition_window: TimeDiff,
+    /// The percentage of the other validators' weight that must be connected. Below it, the node
+    /// reports that it is possibly partitioned from the network.
+    pub partition_threshold_percent: u8,
+    /// The percentage of the other validators' weight that must cite our recent units while we are
+    /// validating. Below it, the node reports that its participation is degraded.
+    pub participation_threshold_percent: u8,
+    /// Whether to send our consensus messages only to the peers validating in their era, rather
+    /// than to all peers.
+    ///
+    /// Non-validating peers then only catch up with consensus when they connect to a peer.
+    pub validator_only_broadcast: bool,
+    /// Confirmations of the emergency validator sets in the chainspec.
+    #[serde(default)]
+    pub emergency_validators: Vec<EmergencyValidatorsConfirmation>,
 }
 
 impl Default for Config {
@@ -31,10 +56,87 @@ impl Default for Config {
             secret_key_path: External::Missing,
             unit_hashes_folder: Default::default(),
             pending_vertex_timeout: "10sec".parse().unwrap(),
+            max_pending_vertices_per_era: 20_000,
+            max_pending_vertices_per_peer: 10_000,
+            partition_window: "5min".parse().unwrap(),
+            partition_threshold_percent: 67,
+            participation_threshold_percent: 67,
+            validator_only_broadcast: false,
+            emergency_validators: vec![],
         }
     }
 }
 
+impl Config {
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        match &self.secret_key_path {
+            External::Path(path) => validator.ensure_file("secret_key_path", path),
+            External::Loaded(_) => (),
+            External::Missing => validator.violation("secret_key_path", "must be set"),
+        }
+        validator.ensure_writable_dir("unit_hashes_folder", &self.unit_hashes_folder);
+        validator.ensure_non_zero(
+            "max_pending_vertices_per_era",
+            self.max_pending_vertices_per_era,
+        );
+        validator.ensure_non_zero(
+            "max_pending_vertices_per_peer",
+            self.max_pending_vertices_per_peer,
+        );
+        validator.ensure_non_zero("partition_window", self.partition_window);
+        validator.ensure(
+            self.partition_threshold_percent <= 100,
+            "partition_threshold_percent",
+            "must not be greater than 100",
+        );
+        validator.ensure(
+            self.participation_threshold_percent <= 100,
+            "participation_threshold_percent",
+            "must not be greater than 100",
+        );
+        for confirmation in &self.emergency_validators {
+            validator.ensure(
+                confirmation.replace_auction_validators,
+                "emergency_validators.replace_auction_validators",
+                "must be true",
+            );
+        }
+    }
+}
+
+/// The operator's confirmation of an emergency validator set in the chainspec.
+///
+/// Every field has to match the set for it to count as confirmed, so that a node doesn't accept a
+/// replaced validator set it wasn't prepared for.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct EmergencyValidatorsConfirmation {
+    /// The activation height of the upgrade point replacing the validators.
+    pub activation_height: u64,
+    /// The number of validators in the replacement set.
+    pub validator_count: usize,
+    /// The hash of the replacement set.
+    pub validators_hash: Digest,
+    /// Must be `true`, acknowledging that the auction's validators are replaced.
+    pub replace_auction_validators: bool,
+}
+
+impl EmergencyValidatorsConfirmation {
+    /// Returns whether this confirms `validators`, activated at `activation_height`.
+    pub(crate) fn confirms(
+        &self,
+        activation_height: u64,
+        validators: &EmergencyValidators,
+    ) -> bool {
+        self.replace_auction_validators
+            && self.activation_height == activation_height
+            && self.validator_count == validators.weights().len()
+            && self.validators_hash == validators.hash()
+    }
+}
+
 /// Consensus protocol configuration.
 #[derive(DataSize, Debug)]
 pub(crate) struct ProtocolConfig {
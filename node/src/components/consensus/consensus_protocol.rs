@@ -107,6 +107,9 @@ pub(crate) enum ProtocolOutcome<I, C: Context> {
     DoppelgangerDetected,
     /// We want to disconnect from a sender of invalid data.
     Disconnect(I),
+    /// A pending vertex received from the given peer was evicted because the limits for pending
+    /// vertices were reached.
+    EvictedPendingVertex(I),
 }
 
 /// An API for a single instance of the consensus.
@@ -190,6 +193,17 @@ pub(crate) trait ConsensusProtocol<I, C: Context> {
     /// Returns whether this instance of a protocol is an active validator.
     fn is_active(&self) -> bool;
 
+    /// Returns the percentage of the other validators' weight from which we have seen messages
+    /// created at or after `since`, or `None` if there are no other validators.
+    fn recently_active_weight_percent(&self, since: Timestamp) -> Option<u64>;
+
+    /// Returns the percentage of the other validators' weight whose latest message cites a message
+    /// of ours created at or after `since`, or `None` if we are not an active validator.
+    fn own_messages_cited_weight_percent(&self, since: Timestamp) -> Option<u64>;
+
+    /// Returns the creation time of our latest message, or `None` if we have not created any.
+    fn latest_own_message_timestamp(&self) -> Option<Timestamp>;
+
     /// Returns the instance ID of this instance.
     fn instance_id(&self) -> &C::InstanceId;
 }
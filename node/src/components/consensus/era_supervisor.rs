@@ -6,15 +6,14 @@
 //! Most importantly, it doesn't care about what messages it's forwarding.
 
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     convert::TryInto,
     fmt::{self, Debug, Formatter},
-    path::PathBuf,
     rc::Rc,
     time::Duration,
 };
 
-use anyhow::Error;
+use anyhow::{bail, Error};
 use blake2::{
     digest::{Update, VariableOutput},
     VarBlake2b,
@@ -23,36 +22,50 @@ use datasize::DataSize;
 use itertools::Itertools;
 use prometheus::Registry;
 use rand::Rng;
-use tracing::{info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
-use casper_types::{AsymmetricType, PublicKey, SecretKey, U512};
+use casper_types::{ProtocolVersion, PublicKey, SecretKey, U512};
 
 use crate::{
-    components::consensus::{
-        candidate_block::CandidateBlock,
-        cl_context::{ClContext, Keypair},
-        consensus_protocol::{
-            BlockContext, ConsensusProtocol, EraEnd, FinalizedBlock as CpFinalizedBlock,
-            ProtocolOutcome,
+    components::{
+        chainspec_loader::EmergencyValidators,
+        consensus::{
+            candidate_block::CandidateBlock,
+            cl_context::{ClContext, Keypair},
+            consensus_protocol::{
+                BlockContext, ConsensusProtocol, EraEnd, FinalizedBlock as CpFinalizedBlock,
+                ProtocolOutcome,
+            },
+            metrics::ConsensusMetrics,
+            traits::NodeIdT,
+            ActionId, BlockExecutionCapability, Config, ConsensusMessage, Event, ReactorEventT,
+            TimerId,
         },
-        metrics::ConsensusMetrics,
-        traits::NodeIdT,
-        ActionId, Config, ConsensusMessage, Event, ReactorEventT, TimerId,
+        contract_runtime::ValidatorWeightsByEraIdRequest,
     },
     crypto::hash::Digest,
     effect::{EffectBuilder, EffectExt, Effects, Responder},
     fatal,
     types::{
-        BlockHash, BlockHeader, BlockLike, FinalitySignature, FinalizedBlock, ProtoBlock, Timestamp,
+        BlockHash, BlockHeader, BlockLike, FinalitySignature, FinalizedBlock, ProtoBlock,
+        SignedAttestation, SoftwareAttestation, Timestamp,
     },
     utils::WithDir,
     NodeRng,
 };
 
 pub use self::era::{Era, EraId};
+use self::unit_hash_store::UnitHashStore;
 use crate::components::consensus::config::ProtocolConfig;
 
 mod era;
+mod unit_hash_store;
+
+/// The interval at which we check whether we are possibly partitioned from the other validators.
+const PARTITION_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The interval at which we check whether the other validators are citing our own units.
+const PARTICIPATION_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
 type ConsensusConstructor<I> = dyn Fn(
     Digest,                                       // the era's unique instance ID
@@ -101,8 +114,25 @@ pub struct EraSupervisor<I> {
     metrics: ConsensusMetrics,
     // TODO: discuss this quick fix
     finished_joining: bool,
-    /// The path to the folder where unit hash files will be stored.
-    unit_hashes_folder: PathBuf,
+    /// The files recording the hashes of our latest units.
+    unit_hash_store: UnitHashStore,
+    /// Whether the last partition check found too few validators to be recently active.
+    possibly_partitioned: bool,
+    /// Whether the last participation check found too few validators to cite our recent units.
+    participation_degraded: bool,
+    /// The timestamp of the latest finalized block that we proposed ourselves.
+    last_own_block_finalized: Option<Timestamp>,
+    /// The eras whose validator set did not match the one computed by our own auction contract.
+    ///
+    /// We never propose in these, since our view of the validator set may be wrong and our blocks
+    /// could conflict with the canonical ones. We still vote, as a validator of the canonical set.
+    refused_eras: HashSet<EraId>,
+    /// The height of the highest executed block handled, i.e. added to the linear chain.
+    highest_executed_block_height: Option<u64>,
+    /// Whether we have stopped proposing blocks because the node is shutting down.
+    proposing_stopped: bool,
+    /// The number of proto blocks requested from the block proposer and not yet proposed.
+    pending_proposals: usize,
 }
 
 impl<I> Debug for EraSupervisor<I> {
@@ -129,8 +159,10 @@ where
         new_consensus: Box<ConsensusConstructor<I>>,
         mut rng: &mut NodeRng,
     ) -> Result<(Self, Effects<Event<I>>), Error> {
-        let unit_hashes_folder = config.with_dir(config.value().unit_hashes_folder.clone());
+        let unit_hash_store =
+            UnitHashStore::new(config.with_dir(config.value().unit_hashes_folder.clone()))?;
         let (root, config) = config.into_parts();
+        check_emergency_validators(&config, &protocol_config)?;
         let secret_signing_key = Rc::new(config.secret_key_path.clone().load(root)?);
         let public_signing_key = PublicKey::from(secret_signing_key.as_ref());
         info!(our_id = %public_signing_key, "EraSupervisor pubkey",);
@@ -152,7 +184,14 @@ where
             next_block_height: 0,
             metrics,
             finished_joining: false,
-            unit_hashes_folder,
+            unit_hash_store,
+            possibly_partitioned: false,
+            participation_degraded: false,
+            last_own_block_finalized: None,
+            refused_eras: HashSet::new(),
+            highest_executed_block_height: None,
+            proposing_stopped: false,
+            pending_proposals: 0,
         };
 
         let results = era_supervisor.new_era(
@@ -198,6 +237,17 @@ where
             .saturating_sub(1)
     }
 
+    /// Returns the emergency validator set replacing the auction's validators in the era starting
+    /// at `start_height`, if the latest upgrade point activated by then has one.
+    fn emergency_validators(&self, start_height: u64) -> Option<&EmergencyValidators> {
+        self.protocol_config
+            .upgrades
+            .iter()
+            .take_while(|up| up.activation_point.height <= start_height)
+            .last()
+            .and_then(|up| up.emergency_validators.as_ref())
+    }
+
     fn key_block_height(&self, _era_id: EraId, start_height: u64) -> u64 {
         // the switch block of the previous era
         // TODO: consider defining the key block as a block further in the past
@@ -285,18 +335,21 @@ where
         );
 
         if should_activate {
-            let secret = Keypair::new(Rc::clone(&self.secret_signing_key), our_id);
-            let unit_hash_file = self.unit_hashes_folder.join(format!(
-                "unit_hash_{:?}_{}.dat",
-                instance_id,
-                self.public_signing_key.to_hex()
-            ));
-            outcomes.extend(consensus.activate_validator(
-                our_id,
-                secret,
-                timestamp,
-                Some(unit_hash_file),
-            ))
+            match self
+                .unit_hash_store
+                .prepare_unit_hash_file(era_id, &instance_id, &our_id)
+            {
+                Ok(unit_hash_file) => {
+                    let secret = Keypair::new(Rc::clone(&self.secret_signing_key), our_id);
+                    outcomes.extend(consensus.activate_validator(
+                        our_id,
+                        secret,
+                        timestamp,
+                        Some(unit_hash_file),
+                    ))
+                }
+                Err(err) => error!(era = era_id.0, %our_id, %err, "not voting"),
+            }
         }
 
         let era = Era::new(
@@ -313,7 +366,13 @@ where
         // the oldest bonded era could still receive blocks that refer to bonded_eras before that.
         if let Some(obsolete_era_id) = era_id.checked_sub(2 * self.bonded_eras + 1) {
             trace!(era = obsolete_era_id.0, "removing obsolete era");
-            self.active_eras.remove(&obsolete_era_id);
+            if let Some(obsolete_era) = self.active_eras.remove(&obsolete_era_id) {
+                let instance_id = obsolete_era.consensus.instance_id();
+                if let Err(err) = self.unit_hash_store.prune(obsolete_era_id, instance_id) {
+                    warn!(era = obsolete_era_id.0, %err, "failed to delete unit hash files");
+                }
+            }
+            self.refused_eras.remove(&obsolete_era_id);
         }
 
         outcomes
@@ -336,6 +395,38 @@ where
         &self.active_eras
     }
 
+    /// Returns our public signing key.
+    pub(crate) fn public_signing_key(&self) -> PublicKey {
+        self.public_signing_key
+    }
+
+    /// Signs an attestation of the software we run with our signing key.
+    pub(crate) fn sign_attestation(
+        &self,
+        attestation: SoftwareAttestation,
+        rng: &mut NodeRng,
+    ) -> SignedAttestation {
+        SignedAttestation::sign(attestation, &self.secret_signing_key, rng)
+    }
+
+    /// Returns the height of the highest executed block handled, if any.
+    #[cfg(feature = "debug-assertions")]
+    pub(crate) fn highest_executed_block_height(&self) -> Option<u64> {
+        self.highest_executed_block_height
+    }
+
+    /// Stops requesting new blocks to propose, e.g. because the node is shutting down.
+    ///
+    /// Proto blocks which have been requested already are still proposed.
+    pub(crate) fn stop_proposing(&mut self) {
+        self.proposing_stopped = true;
+    }
+
+    /// Returns whether any requested proto blocks have not been proposed yet.
+    pub(crate) fn has_pending_proposals(&self) -> bool {
+        self.pending_proposals > 0
+    }
+
     /// To be called when we transition from the joiner to the validator reactor.
     pub(crate) fn finished_joining(
         &mut self,
@@ -344,19 +435,29 @@ where
         self.finished_joining = true;
         let secret = Keypair::new(Rc::clone(&self.secret_signing_key), self.public_signing_key);
         let public_key = self.public_signing_key;
-        let unit_hashes_folder = self.unit_hashes_folder.clone();
+        let current_era = self.current_era;
+        let unit_hash_store = &self.unit_hash_store;
         self.active_eras
-            .get_mut(&self.current_era)
+            .get_mut(&current_era)
             .map(|era| {
                 if era.validators().contains_key(&public_key) {
                     let instance_id = *era.consensus.instance_id();
-                    let unit_hash_file = unit_hashes_folder.join(format!(
-                        "unit_hash_{:?}_{}.dat",
-                        instance_id,
-                        public_key.to_hex()
-                    ));
-                    era.consensus
-                        .activate_validator(public_key, secret, now, Some(unit_hash_file))
+                    match unit_hash_store.prepare_unit_hash_file(
+                        current_era,
+                        &instance_id,
+                        &public_key,
+                    ) {
+                        Ok(unit_hash_file) => era.consensus.activate_validator(
+                            public_key,
+                            secret,
+                            now,
+                            Some(unit_hash_file),
+                        ),
+                        Err(err) => {
+                            error!(era = current_era.0, %public_key, %err, "not voting");
+                            Vec::new()
+                        }
+                    }
                 } else {
                     Vec::new()
                 }
@@ -466,6 +567,8 @@ where
         proto_block: ProtoBlock,
         block_context: BlockContext,
     ) -> Effects<Event<I>> {
+        self.era_supervisor.pending_proposals =
+            self.era_supervisor.pending_proposals.saturating_sub(1);
         if !self.era_supervisor.is_bonded(era_id) {
             warn!(era = era_id.0, "new proto block in outdated era");
             return Effects::new();
@@ -504,6 +607,10 @@ where
             None
         };
         let mut effects = responder.respond(maybe_fin_sig).ignore();
+        self.era_supervisor.highest_executed_block_height = self
+            .era_supervisor
+            .highest_executed_block_height
+            .max(Some(block_header.height()));
         if era_id < self.era_supervisor.current_era {
             trace!(era = era_id.0, "executed block in old era");
             return effects;
@@ -516,18 +623,29 @@ where
                 .era_supervisor
                 .key_block_height(new_era_id, block_header.height() + 1);
             let booking_block_height = self.era_supervisor.booking_block_height(new_era_id);
+            let auction_request = ValidatorWeightsByEraIdRequest::new(
+                (*block_header.state_root_hash()).into(),
+                new_era_id,
+                ProtocolVersion::V1_0_0,
+            );
             let effect = self
                 .effect_builder
-                .create_new_era(booking_block_height, key_block_height)
-                .event(move |(booking_block, key_block)| Event::CreateNewEra {
-                    block_header: Box::new(block_header),
-                    booking_block_hash: booking_block
-                        .map_or_else(|| Err(booking_block_height), |block| Ok(*block.hash())),
-                    key_block_seed: key_block.map_or_else(
-                        || Err(key_block_height),
-                        |block| Ok(block.header().accumulated_seed()),
-                    ),
-                });
+                .create_new_era(booking_block_height, key_block_height, auction_request)
+                .event(
+                    move |(booking_block, key_block, auction_validators)| Event::CreateNewEra {
+                        block_header: Box::new(block_header),
+                        booking_block_hash: booking_block
+                            .map_or_else(|| Err(booking_block_height), |block| Ok(*block.hash())),
+                        key_block_seed: key_block.map_or_else(
+                            || Err(key_block_height),
+                            |block| Ok(block.header().accumulated_seed()),
+                        ),
+                        auction_validator_weights: auction_validators.unwrap_or_else(|error| {
+                            error!(%error, era = new_era_id.0, "could not get auction results");
+                            None
+                        }),
+                    },
+                );
             effects.extend(effect);
         } else {
             // if it's not a switch block, we can already declare it handled
@@ -556,6 +674,14 @@ where
         if faulty_num == old_faulty_num {
             info!(era = era_id.0, "stop voting in era");
             era.consensus.deactivate_validator();
+            let instance_id = era.consensus.instance_id();
+            if let Err(err) = self
+                .era_supervisor
+                .unit_hash_store
+                .consolidate(era_id, instance_id)
+            {
+                warn!(era = era_id.0, %err, "failed to consolidate unit hash files");
+            }
             Effects::new()
         } else {
             let deactivate_era = move |_| Event::DeactivateEra {
@@ -572,6 +698,7 @@ where
         block_header: BlockHeader,
         booking_block_hash: BlockHash,
         key_block_seed: Digest,
+        auction_validator_weights: Option<BTreeMap<PublicKey, U512>>,
     ) -> Effects<Event<I>> {
         let (era_end, next_era_validators_weights) = match (
             block_header.era_end(),
@@ -590,17 +717,53 @@ where
         };
         let newly_slashed = era_end.equivocators.clone();
         let era_id = block_header.era_id().successor();
+        let start_height = block_header.height() + 1;
         info!(era = era_id.0, "era created");
+        let emergency_validators = self
+            .era_supervisor
+            .emergency_validators(start_height)
+            .map(EmergencyValidators::weights)
+            .cloned();
+        if let Some(validators) = &emergency_validators {
+            warn!(
+                era = era_id.0,
+                ?validators,
+                "EMERGENCY: using the validator set from the chainspec instead of the auction results"
+            );
+        }
+        // Dry run of the validator set transition: if the switch block disagrees with what our own
+        // auction contract computed, something is misconfigured and we must not propose. This
+        // doesn't apply while the auction's validators are replaced.
+        let mismatches = match (&emergency_validators, &auction_validator_weights) {
+            (Some(_), _) => vec![],
+            (None, Some(auction_weights)) => {
+                validator_set_mismatches(auction_weights, next_era_validators_weights)
+            }
+            (None, None) => vec!["no validator weights computed by the local auction".to_string()],
+        };
+        if !mismatches.is_empty() {
+            error!(
+                era = era_id.0,
+                ?mismatches,
+                "CRITICAL: upcoming validator set does not match the local auction results; \
+                refusing to propose in this era; check the chainspec and the global state"
+            );
+            let _ = self.era_supervisor.refused_eras.insert(era_id);
+        }
+        self.era_supervisor
+            .metrics
+            .validator_set_mismatch
+            .set(i64::from(!mismatches.is_empty()));
         let seed = EraSupervisor::<I>::era_seed(booking_block_hash, key_block_seed);
         trace!(%seed, "the seed for {}: {}", era_id, seed);
         let results = self.era_supervisor.new_era(
             era_id,
             Timestamp::now(), // TODO: This should be passed in.
-            next_era_validators_weights.clone(),
+            emergency_validators.unwrap_or_else(|| next_era_validators_weights.clone()),
             newly_slashed,
             seed,
             block_header.timestamp(),
-            block_header.height() + 1,
+            start_height,
             *block_header.state_root_hash(),
         );
         let mut effects = self.handle_consensus_results(era_id, results);
@@ -692,10 +855,24 @@ where
                 self.disconnect(sender)
             }
             ProtocolOutcome::CreatedGossipMessage(out_msg) => {
-                // TODO: we'll want to gossip instead of broadcast here
-                self.effect_builder
-                    .broadcast_message(era_id.message(out_msg).into())
-                    .ignore()
+                let message = era_id.message(out_msg);
+                let validators = self
+                    .era_supervisor
+                    .active_eras
+                    .get(&era_id)
+                    .filter(|_| self.era_supervisor.config.validator_only_broadcast)
+                    .map(|era| era.validators().keys().copied().collect());
+                match validators {
+                    Some(validators) => self
+                        .effect_builder
+                        .broadcast_message_to_validators(message.into(), validators)
+                        .ignore(),
+                    // TODO: we'll want to gossip instead of broadcast here
+                    None => self
+                        .effect_builder
+                        .broadcast_message(message.into())
+                        .ignore(),
+                }
             }
             ProtocolOutcome::CreatedTargetedMessage(out_msg, to) => self
                 .effect_builder
@@ -719,6 +896,19 @@ where
                 block_context,
                 past_values,
             } => {
+                if self.era_supervisor.proposing_stopped {
+                    debug!(era = era_id.0, "not proposing a block, shutting down");
+                    return Effects::new();
+                }
+                if self.era_supervisor.refused_eras.contains(&era_id) {
+                    error!(
+                        era = era_id.0,
+                        "not proposing a block; validator set does not match the local auction \
+                        results"
+                    );
+                    return Effects::new();
+                }
+                self.era_supervisor.pending_proposals += 1;
                 let past_deploys = past_values
                     .iter()
                     .flat_map(|candidate| BlockLike::deploys(candidate.proto_block()))
@@ -745,6 +935,9 @@ where
                 equivocators,
                 proposer,
             }) => {
+                if proposer == self.era_supervisor.public_signing_key {
+                    self.era_supervisor.last_own_block_finalized = Some(timestamp);
+                }
                 let era = self.era_supervisor.active_eras.get_mut(&era_id).unwrap();
                 era.add_accusations(&equivocators);
                 era.add_accusations(value.accusations());
@@ -766,10 +959,10 @@ where
                 self.era_supervisor
                     .metrics
                     .finalized_block(&finalized_block);
-                // Announce the finalized proto block.
+                // Publish the finalized block to its subscribers.
                 let mut effects = self
                     .effect_builder
-                    .announce_finalized_block(finalized_block.clone())
+                    .publish(finalized_block.clone())
                     .ignore();
                 self.era_supervisor.next_block_height = finalized_block.height() + 1;
                 if finalized_block.era_end().is_some() {
@@ -784,7 +977,11 @@ where
                     effects.extend(self.effect_builder.set_timeout(delay).event(deactivate_era));
                 }
                 // Request execution of the finalized block.
-                effects.extend(self.effect_builder.execute_block(finalized_block).ignore());
+                effects.extend(
+                    self.effect_builder
+                        .execute_block(BlockExecutionCapability(()), finalized_block)
+                        .ignore(),
+                );
                 effects
             }
             ProtocolOutcome::ValidateConsensusValue(sender, candidate_block, timestamp) => {
@@ -850,8 +1047,25 @@ where
                     })
                 })
                 .collect(),
-            ProtocolOutcome::WeAreFaulty => Default::default(),
-            ProtocolOutcome::DoppelgangerDetected => Default::default(),
+            ProtocolOutcome::WeAreFaulty => {
+                error!(era = era_id.0, "this validator equivocated");
+                self.effect_builder
+                    .announce_equivocation_risk(era_id, false)
+                    .ignore()
+            }
+            ProtocolOutcome::DoppelgangerDetected => {
+                error!(
+                    era = era_id.0,
+                    "another node is running with this validator's key"
+                );
+                self.effect_builder
+                    .announce_equivocation_risk(era_id, true)
+                    .ignore()
+            }
+            ProtocolOutcome::EvictedPendingVertex(_) => {
+                self.era_supervisor.metrics.evicted_pending_vertices.inc();
+                Effects::new()
+            }
         }
     }
 
@@ -875,7 +1089,166 @@ where
 
     pub(crate) fn finished_joining(&mut self, now: Timestamp) -> Effects<Event<I>> {
         let results = self.era_supervisor.finished_joining(now);
-        self.handle_consensus_results(self.era_supervisor.current_era, results)
+        let mut effects = self.handle_consensus_results(self.era_supervisor.current_era, results);
+        // Give the other validators a full window to be heard from before the first check.
+        let window = self.era_supervisor.config.partition_window;
+        effects.extend(
+            self.effect_builder
+                .set_timeout(window.into())
+                .event(|_| Event::CheckPartition),
+        );
+        effects.extend(
+            self.effect_builder
+                .set_timeout(window.into())
+                .event(|_| Event::CheckParticipation),
+        );
+        effects
+    }
+
+    /// Checks whether too few of the other validators were recently active in the current era, and
+    /// announces it if that changed since the previous check.
+    pub(super) fn check_partition(&mut self) -> Effects<Event<I>> {
+        let mut effects = self
+            .effect_builder
+            .set_timeout(PARTITION_CHECK_INTERVAL)
+            .event(|_| Event::CheckPartition);
+
+        let config = &self.era_supervisor.config;
+        let era_id = self.era_supervisor.current_era;
+        let era = match self.era_supervisor.active_eras.get(&era_id) {
+            Some(era) => era,
+            None => return effects,
+        };
+        let now = Timestamp::now();
+        // Nobody creates units before the era starts, so only judge once a full window has passed.
+        if now < era.start_time + config.partition_window {
+            return effects;
+        }
+        let active_weight_percent = match era
+            .consensus
+            .recently_active_weight_percent(now - config.partition_window)
+        {
+            Some(active_weight_percent) => active_weight_percent,
+            None => return effects,
+        };
+        let possibly_partitioned =
+            active_weight_percent < u64::from(config.partition_threshold_percent);
+
+        let metrics = &self.era_supervisor.metrics;
+        metrics
+            .active_validator_weight_percent
+            .set(active_weight_percent as i64);
+        metrics
+            .possibly_partitioned
+            .set(i64::from(possibly_partitioned));
+
+        if possibly_partitioned == self.era_supervisor.possibly_partitioned {
+            return effects;
+        }
+        self.era_supervisor.possibly_partitioned = possibly_partitioned;
+        if possibly_partitioned {
+            warn!(
+                %era_id,
+                active_weight_percent,
+                "too few validators recently active; possibly partitioned from the network"
+            );
+        } else {
+            info!(
+                %era_id,
+                active_weight_percent,
+                "enough validators recently active; no longer partitioned from the network"
+            );
+        }
+        effects.extend(
+            self.effect_builder
+                .announce_partition_status(era_id, active_weight_percent, possibly_partitioned)
+                .ignore(),
+        );
+        effects
+    }
+
+    /// Checks whether too few of the other validators cite our recent units in the current era,
+    /// and announces it if that changed since the previous check.
+    ///
+    /// This only applies while we are an active validator in the current era.
+    pub(super) fn check_participation(&mut self) -> Effects<Event<I>> {
+        let mut effects = self
+            .effect_builder
+            .set_timeout(PARTICIPATION_CHECK_INTERVAL)
+            .event(|_| Event::CheckParticipation);
+
+        let config = &self.era_supervisor.config;
+        let era_id = self.era_supervisor.current_era;
+        let era = match self.era_supervisor.active_eras.get(&era_id) {
+            Some(era) => era,
+            None => return effects,
+        };
+        let now = Timestamp::now();
+        if now < era.start_time + config.partition_window {
+            return effects;
+        }
+        let since = now - config.partition_window;
+        let cited_weight_percent = match era.consensus.own_messages_cited_weight_percent(since) {
+            Some(cited_weight_percent) => cited_weight_percent,
+            None => return effects,
+        };
+        let participation_degraded =
+            cited_weight_percent < u64::from(config.participation_threshold_percent);
+
+        let metrics = &self.era_supervisor.metrics;
+        metrics
+            .own_units_cited_weight_percent
+            .set(cited_weight_percent as i64);
+        metrics
+            .participation_degraded
+            .set(i64::from(participation_degraded));
+
+        if participation_degraded == self.era_supervisor.participation_degraded {
+            return effects;
+        }
+        self.era_supervisor.participation_degraded = participation_degraded;
+        let last_own_block_finalized = self.era_supervisor.last_own_block_finalized;
+        if participation_degraded {
+            match era.consensus.latest_own_message_timestamp() {
+                Some(timestamp) if timestamp >= since => warn!(
+                    %era_id,
+                    cited_weight_percent,
+                    latest_own_unit = %timestamp,
+                    ?last_own_block_finalized,
+                    "our recent units are not being cited by other validators; check that the \
+                    system clock is synchronized and that the node is well connected to its peers"
+                ),
+                Some(timestamp) => warn!(
+                    %era_id,
+                    cited_weight_percent,
+                    latest_own_unit = %timestamp,
+                    ?last_own_block_finalized,
+                    "we have not created any units recently; check the system clock, that the \
+                    validator secret key matches our bonded public key, and that the unit hash \
+                    file is not shared with another node running the same key"
+                ),
+                None => warn!(
+                    %era_id,
+                    cited_weight_percent,
+                    ?last_own_block_finalized,
+                    "we have not created any units in this era; check the system clock, that the \
+                    validator secret key matches our bonded public key, and that the unit hash \
+                    file is not shared with another node running the same key"
+                ),
+            }
+        } else {
+            info!(
+                %era_id,
+                cited_weight_percent,
+                "our recent units are being cited by enough validators; participation recovered"
+            );
+        }
+        effects.extend(
+            self.effect_builder
+                .announce_participation_status(era_id, cited_weight_percent, participation_degraded)
+                .ignore(),
+        );
+        effects
     }
 
     /// Returns whether validator is bonded in an era.
@@ -893,6 +1266,20 @@ where
         responder.respond(is_bonded).ignore()
     }
 
+    /// Returns the validators of the current era.
+    pub(super) fn current_validators(
+        &self,
+        responder: Responder<BTreeSet<PublicKey>>,
+    ) -> Effects<Event<I>> {
+        let era_supervisor = &self.era_supervisor;
+        let validators = era_supervisor
+            .active_eras
+            .get(&era_supervisor.current_era)
+            .map(|era| era.validators().keys().copied().collect())
+            .unwrap_or_default();
+        responder.respond(validators).ignore()
+    }
+
     fn disconnect(&self, sender: I) -> Effects<Event<I>> {
         self.effect_builder
             .announce_disconnect_from_peer(sender)
@@ -900,6 +1287,96 @@ where
     }
 }
 
+/// Returns the differences between the validator weights computed by the local auction contract
+/// and the ones in a switch block, as well as any weights the switch block should not contain.
+///
+/// The result is empty if the switch block's validator set is as expected.
+fn validator_set_mismatches(
+    auction_weights: &BTreeMap<PublicKey, U512>,
+    block_weights: &BTreeMap<PublicKey, U512>,
+) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if block_weights.is_empty() {
+        mismatches.push("empty validator set".to_string());
+    }
+    for (public_key, weight) in block_weights {
+        if weight.is_zero() {
+            mismatches.push(format!("{}: zero weight", public_key));
+        }
+        match auction_weights.get(public_key) {
+            None => mismatches.push(format!("{}: not a validator in the auction", public_key)),
+            Some(auction_weight) if auction_weight != weight => mismatches.push(format!(
+                "{}: weight {}, auction weight {}",
+                public_key, weight, auction_weight
+            )),
+            Some(_) => (),
+        }
+    }
+    for public_key in auction_weights.keys() {
+        if !block_weights.contains_key(public_key) {
+            mismatches.push(format!("{}: missing from the validator set", public_key));
+        }
+    }
+    mismatches
+}
+
+/// Checks that every emergency validator set in the chainspec is confirmed in the config.
+fn check_emergency_validators(
+    config: &Config,
+    protocol_config: &ProtocolConfig,
+) -> Result<(), Error> {
+    for upgrade_point in &protocol_config.upgrades {
+        let validators = match &upgrade_point.emergency_validators {
+            Some(validators) => validators,
+            None => continue,
+        };
+        let activation_height = upgrade_point.activation_point.height;
+        let validator_count = validators.weights().len();
+        let validators_hash = validators.hash();
+        if !config
+            .emergency_validators
+            .iter()
+            .any(|confirmation| confirmation.confirms(activation_height, validators))
+        {
+            error!(
+                %activation_height,
+                %validator_count,
+                %validators_hash,
+                "EMERGENCY: the chainspec replaces the validator set, but the consensus config \
+                doesn't confirm it; review the set and add a matching entry to \
+                consensus.emergency_validators"
+            );
+            bail!(
+                "unconfirmed emergency validator set activated at height {}",
+                activation_height
+            );
+        }
+        warn!(
+            %activation_height,
+            %validator_count,
+            %validators_hash,
+            "EMERGENCY: replacing the auction's validators from the activation height on, as \
+            confirmed in the consensus config"
+        );
+    }
+    for confirmation in &config.emergency_validators {
+        let is_in_chainspec = protocol_config.upgrades.iter().any(|up| {
+            up.emergency_validators
+                .as_ref()
+                .map_or(false, |validators| {
+                    confirmation.confirms(up.activation_point.height, validators)
+                })
+        });
+        if !is_in_chainspec {
+            warn!(
+                activation_height = confirmation.activation_height,
+                "confirmed emergency validator set is not in the chainspec"
+            );
+        }
+    }
+    Ok(())
+}
+
 /// Computes the instance ID for an era, given the state root hash, block height and chainspec.
 fn instance_id(
     protocol_config: &ProtocolConfig,
@@ -919,6 +1396,9 @@ fn instance_id(
         .take_while(|up| up.activation_point.height <= block_height)
     {
         hasher.update(upgrade_point.activation_point.height.to_le_bytes());
+        if let Some(emergency_validators) = &upgrade_point.emergency_validators {
+            hasher.update(emergency_validators.hash());
+        }
     }
 
     hasher.finalize_variable(|slice| {
@@ -926,3 +1406,35 @@ fn instance_id(
     });
     result.into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_validator_set_mismatches() {
+        let alice = PublicKey::from(&SecretKey::ed25519([1; SecretKey::ED25519_LENGTH]));
+        let bob = PublicKey::from(&SecretKey::ed25519([2; SecretKey::ED25519_LENGTH]));
+        let carol = PublicKey::from(&SecretKey::ed25519([3; SecretKey::ED25519_LENGTH]));
+        let auction_weights: BTreeMap<_, _> = vec![(alice, U512::from(10)), (bob, U512::from(20))]
+            .into_iter()
+            .collect();
+        assert!(validator_set_mismatches(&auction_weights, &auction_weights).is_empty());
+        assert_eq!(
+            validator_set_mismatches(&auction_weights, &BTreeMap::new()).len(),
+            3
+        );
+
+        let block_weights = vec![(alice, U512::from(11)), (carol, U512::zero())]
+            .into_iter()
+            .collect();
+        let mismatches = validator_set_mismatches(&auction_weights, &block_weights);
+        assert_eq!(mismatches.len(), 4);
+        assert!(mismatches
+            .iter()
+            .any(|m| m.ends_with("weight 11, auction weight 10")));
+        assert!(mismatches
+            .iter()
+            .any(|m| m.ends_with("missing from the validator set")));
+    }
+}
@@ -0,0 +1,372 @@
+//! Files recording the hashes of our latest units.
+//!
+//! While we are voting in an era, the active validator keeps the hash of the latest unit we
+//! created in a file of its own, so that we never equivocate after a restart. Once we stopped
+//! voting in an era, the files of the era are consolidated into a single compact file carrying a
+//! checksum, and the files of eras older than the evidence retention window are deleted, so the
+//! folder does not grow indefinitely.
+//!
+//! All files are verified when the node starts. A corrupt file is a fatal error rather than being
+//! ignored, as forgetting our latest unit in an era we are still voting in could make us
+//! equivocate.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use datasize::DataSize;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, info};
+
+use casper_types::{AsymmetricType, PublicKey};
+
+use super::EraId;
+use crate::crypto::hash::{self, Digest};
+
+/// Prefix of the files written by the active validator, one per era and validator key.
+const UNIT_HASH_FILE_PREFIX: &str = "unit_hash_";
+/// Prefix of the consolidated files, one per era.
+const ERA_FILE_PREFIX: &str = "unit_hashes_era_";
+/// Extension of all unit hash files.
+const EXTENSION: &str = ".dat";
+
+/// An error accessing the unit hash files.
+#[derive(Debug, Error)]
+pub(crate) enum Error {
+    /// Failure to read or write a file.
+    #[error("failed to access unit hash file `{}`: {}", .0.display(), .1)]
+    Io(PathBuf, io::Error),
+    /// A file failed verification.
+    #[error(
+        "unit hash file `{}` is corrupt: {}; removing it could make this node equivocate if it \
+         is still voting in that era",
+        .0.display(),
+        .1
+    )]
+    Corrupt(PathBuf, String),
+}
+
+/// The hashes of our latest units in an era, as stored in a consolidated file.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+struct EraUnitHashes {
+    /// The instance ID of the era's consensus protocol.
+    instance_id: Digest,
+    /// The hash of our latest unit, by the validator key it was created with.
+    last_units: BTreeMap<PublicKey, Digest>,
+}
+
+/// The folder holding the unit hash files.
+#[derive(DataSize, Debug)]
+pub(crate) struct UnitHashStore {
+    folder: PathBuf,
+}
+
+impl UnitHashStore {
+    /// Creates a unit hash store in `folder`, verifying all files in it.
+    pub(crate) fn new(folder: PathBuf) -> Result<Self, Error> {
+        let store = UnitHashStore { folder };
+        store.verify()?;
+        Ok(store)
+    }
+
+    /// Returns the path of the file the active validator writes our latest unit in an era to.
+    ///
+    /// File names use lowercase hex, so that the files written by earlier versions are found.
+    pub(crate) fn unit_hash_file(&self, instance_id: &Digest, public_key: &PublicKey) -> PathBuf {
+        self.folder.join(format!(
+            "{}{:x}_{}{}",
+            UNIT_HASH_FILE_PREFIX,
+            instance_id,
+            public_key.to_hex().to_lowercase(),
+            EXTENSION
+        ))
+    }
+
+    /// Returns the path of the file the active validator should use in `era_id`.
+    ///
+    /// If the era has been consolidated already, our latest unit in it is written back to the
+    /// validator's own file first.
+    pub(crate) fn prepare_unit_hash_file(
+        &self,
+        era_id: EraId,
+        instance_id: &Digest,
+        public_key: &PublicKey,
+    ) -> Result<PathBuf, Error> {
+        let path = self.unit_hash_file(instance_id, public_key);
+        if path.exists() {
+            return Ok(path);
+        }
+        let last_unit = match self.read_era_file(era_id)? {
+            Some(era) if era.instance_id == *instance_id => era.last_units.get(public_key).copied(),
+            _ => None,
+        };
+        if let Some(last_unit) = last_unit {
+            info!(
+                era = era_id.0,
+                "restoring unit hash file of consolidated era"
+            );
+            let bytes = serde_json::to_vec(&last_unit).expect("should serialize a digest to JSON");
+            write_atomically(&path, &bytes)?;
+        }
+        Ok(path)
+    }
+
+    /// Consolidates the files of the active validator in `era_id` into the era's file.
+    ///
+    /// Must only be called once we stopped voting in the era. Returns the number of files
+    /// consolidated.
+    pub(crate) fn consolidate(&self, era_id: EraId, instance_id: &Digest) -> Result<usize, Error> {
+        let files = self.unit_hash_files(instance_id)?;
+        if files.is_empty() {
+            return Ok(0);
+        }
+        let mut era = self.read_era_file(era_id)?.unwrap_or_default();
+        era.instance_id = *instance_id;
+        for (path, public_key) in &files {
+            era.last_units
+                .insert(*public_key, read_unit_hash_file(path)?);
+        }
+
+        let payload = bincode::serialize(&era).expect("should serialize unit hashes");
+        let mut bytes = hash::hash(&payload).as_ref().to_vec();
+        bytes.extend(payload);
+        write_atomically(&self.era_file(era_id), &bytes)?;
+
+        for (path, _) in &files {
+            fs::remove_file(path).map_err(|err| Error::Io(path.clone(), err))?;
+        }
+        debug!(
+            era = era_id.0,
+            files = files.len(),
+            "consolidated unit hash files"
+        );
+        Ok(files.len())
+    }
+
+    /// Deletes the files of `obsolete_era_id` and of all eras before it.
+    ///
+    /// The files of the active validator in the obsolete era are deleted as well, in case the era
+    /// was never consolidated. Returns the number of files deleted.
+    pub(crate) fn prune(
+        &self,
+        obsolete_era_id: EraId,
+        instance_id: &Digest,
+    ) -> Result<usize, Error> {
+        let mut obsolete: Vec<PathBuf> = self
+            .unit_hash_files(instance_id)?
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        for path in self.files()? {
+            if let Some(era_id) = parse_era_file_name(&path) {
+                if era_id <= obsolete_era_id {
+                    obsolete.push(path);
+                }
+            }
+        }
+        for path in &obsolete {
+            fs::remove_file(path).map_err(|err| Error::Io(path.clone(), err))?;
+        }
+        if !obsolete.is_empty() {
+            debug!(
+                era = obsolete_era_id.0,
+                files = obsolete.len(),
+                "deleted unit hash files of obsolete eras"
+            );
+        }
+        Ok(obsolete.len())
+    }
+
+    /// Verifies all unit hash files, returning the number of files checked.
+    fn verify(&self) -> Result<usize, Error> {
+        let mut verified = 0;
+        for path in self.files()? {
+            if let Some(era_id) = parse_era_file_name(&path) {
+                self.read_era_file(era_id)?;
+            } else if parse_unit_hash_file_name(&path).is_some() {
+                read_unit_hash_file(&path)?;
+            } else {
+                continue;
+            }
+            verified += 1;
+        }
+        info!(verified, folder = %self.folder.display(), "verified unit hash files");
+        Ok(verified)
+    }
+
+    /// Returns the path of the consolidated file of `era_id`.
+    fn era_file(&self, era_id: EraId) -> PathBuf {
+        self.folder
+            .join(format!("{}{}{}", ERA_FILE_PREFIX, era_id.0, EXTENSION))
+    }
+
+    /// Reads and verifies the consolidated file of `era_id`, if present.
+    fn read_era_file(&self, era_id: EraId) -> Result<Option<EraUnitHashes>, Error> {
+        let path = self.era_file(era_id);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::Io(path, err)),
+        };
+        if bytes.len() < Digest::LENGTH {
+            return Err(Error::Corrupt(path, "truncated".to_string()));
+        }
+        let (checksum, payload) = bytes.split_at(Digest::LENGTH);
+        if hash::hash(payload).as_ref() != checksum {
+            return Err(Error::Corrupt(path, "checksum mismatch".to_string()));
+        }
+        bincode::deserialize(payload)
+            .map(Some)
+            .map_err(|err| Error::Corrupt(path, err.to_string()))
+    }
+
+    /// Returns the files of the active validator in the era with the given instance ID, together
+    /// with the validator key they were written for.
+    fn unit_hash_files(&self, instance_id: &Digest) -> Result<Vec<(PathBuf, PublicKey)>, Error> {
+        let instance_hex = format!("{:x}", instance_id);
+        Ok(self
+            .files()?
+            .into_iter()
+            .filter_map(|path| {
+                let (file_instance_hex, public_key) = parse_unit_hash_file_name(&path)?;
+                if file_instance_hex == instance_hex {
+                    Some((path, public_key))
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the paths of all files in the folder, none if it doesn't exist yet.
+    fn files(&self) -> Result<Vec<PathBuf>, Error> {
+        let entries = match fs::read_dir(&self.folder) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(Error::Io(self.folder.clone(), err)),
+        };
+        let mut paths = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|err| Error::Io(self.folder.clone(), err))?;
+            paths.push(entry.path());
+        }
+        Ok(paths)
+    }
+}
+
+/// Returns the era of a consolidated file, or `None` if `path` is not one.
+fn parse_era_file_name(path: &Path) -> Option<EraId> {
+    let name = path.file_name()?.to_str()?;
+    let era_id = name
+        .strip_prefix(ERA_FILE_PREFIX)?
+        .strip_suffix(EXTENSION)?
+        .parse()
+        .ok()?;
+    Some(EraId(era_id))
+}
+
+/// Returns the instance ID in hex and the validator key of a file of the active validator, or
+/// `None` if `path` is not one.
+fn parse_unit_hash_file_name(path: &Path) -> Option<(String, PublicKey)> {
+    let name = path.file_name()?.to_str()?;
+    let mut parts = name
+        .strip_prefix(UNIT_HASH_FILE_PREFIX)?
+        .strip_suffix(EXTENSION)?
+        .splitn(2, '_');
+    let instance_hex = parts.next()?;
+    let public_key = PublicKey::from_hex(parts.next()?).ok()?;
+    Some((instance_hex.to_string(), public_key))
+}
+
+/// Reads the unit hash from a file of the active validator.
+fn read_unit_hash_file(path: &Path) -> Result<Digest, Error> {
+    let bytes = fs::read(path).map_err(|err| Error::Io(path.to_owned(), err))?;
+    serde_json::from_slice(&bytes).map_err(|err| Error::Corrupt(path.to_owned(), err.to_string()))
+}
+
+/// Writes a file by renaming a temporary one, so that a crash never leaves it half written.
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    fs::write(&tmp_path, bytes).map_err(|err| Error::Io(tmp_path.clone(), err))?;
+    fs::rename(&tmp_path, path).map_err(|err| Error::Io(path.to_owned(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::SecretKey;
+
+    use super::*;
+
+    fn public_key(seed: u8) -> PublicKey {
+        PublicKey::from(&SecretKey::ed25519([seed; SecretKey::ED25519_LENGTH]))
+    }
+
+    fn write_unit_hash(store: &UnitHashStore, instance_id: &Digest, key: &PublicKey) -> Digest {
+        let unit_hash = hash::hash(key.to_hex());
+        fs::write(
+            store.unit_hash_file(instance_id, key),
+            serde_json::to_vec(&unit_hash).unwrap(),
+        )
+        .unwrap();
+        unit_hash
+    }
+
+    #[test]
+    fn should_consolidate_restore_and_prune_eras() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = UnitHashStore::new(tmp.path().to_path_buf()).unwrap();
+        let instance_id = hash::hash("era 3");
+        let (key_a, key_b) = (public_key(1), public_key(2));
+        let unit_a = write_unit_hash(&store, &instance_id, &key_a);
+        write_unit_hash(&store, &instance_id, &key_b);
+
+        assert_eq!(store.consolidate(EraId(3), &instance_id).unwrap(), 2);
+        assert_eq!(store.files().unwrap(), vec![store.era_file(EraId(3))]);
+        let store = UnitHashStore::new(tmp.path().to_path_buf()).unwrap();
+
+        let path = store
+            .prepare_unit_hash_file(EraId(3), &instance_id, &key_a)
+            .unwrap();
+        assert_eq!(read_unit_hash_file(&path).unwrap(), unit_a);
+        // A different era with the same number, e.g. after an emergency restart, is not restored.
+        let other_instance_id = hash::hash("another era 3");
+        let path = store
+            .prepare_unit_hash_file(EraId(3), &other_instance_id, &key_a)
+            .unwrap();
+        assert!(!path.exists());
+
+        assert_eq!(store.prune(EraId(2), &hash::hash("era 2")).unwrap(), 0);
+        assert_eq!(store.prune(EraId(3), &instance_id).unwrap(), 2);
+        assert!(store.files().unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_refuse_corrupt_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = UnitHashStore::new(tmp.path().to_path_buf()).unwrap();
+        let instance_id = hash::hash("era 0");
+        write_unit_hash(&store, &instance_id, &public_key(1));
+        store.consolidate(EraId(0), &instance_id).unwrap();
+
+        let path = store.era_file(EraId(0));
+        let mut bytes = fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        fs::write(&path, bytes).unwrap();
+        assert!(matches!(
+            UnitHashStore::new(tmp.path().to_path_buf()),
+            Err(Error::Corrupt(..))
+        ));
+
+        fs::remove_file(&path).unwrap();
+        fs::write(store.unit_hash_file(&instance_id, &public_key(2)), b"{").unwrap();
+        assert!(matches!(
+            UnitHashStore::new(tmp.path().to_path_buf()),
+            Err(Error::Corrupt(..))
+        ));
+    }
+}
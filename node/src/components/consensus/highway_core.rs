@@ -39,6 +39,7 @@ pub(crate) mod state;
 pub(crate) mod validators;
 
 mod endorsement;
+pub(crate) mod equivocation;
 mod evidence;
 #[cfg(test)]
 pub(crate) mod highway_testing;
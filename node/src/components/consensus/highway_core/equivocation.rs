@@ -0,0 +1,84 @@
+//! Detection of equivocations.
+//!
+//! A validator equivocates if it signs two different units with the same sequence number in the
+//! same protocol instance: honest validators only ever extend their own latest unit, so such a pair
+//! of units is proof of a fault. `check_equivocation` decides whether two signed units constitute
+//! such proof. It depends on nothing but the units, the instance ID and the validator set, so it
+//! can be used to validate evidence independently of any protocol state.
+
+#[cfg(test)]
+pub(crate) mod test_vectors;
+
+use super::{evidence::EvidenceError, highway::SignedWireUnit, validators::Validators};
+use crate::components::consensus::traits::Context;
+
+/// Checks whether the two units are an equivocation by a validator in `validators`.
+///
+/// Returns `Ok(())` if both units were validly signed by the same validator for the given
+/// instance and have the same sequence number, but are not identical. The order of the units is
+/// irrelevant.
+pub(crate) fn check_equivocation<C: Context>(
+    unit1: &SignedWireUnit<C>,
+    unit2: &SignedWireUnit<C>,
+    instance_id: &C::InstanceId,
+    validators: &Validators<C::ValidatorId>,
+) -> Result<(), EvidenceError> {
+    let wunit1 = unit1.wire_unit();
+    let wunit2 = unit2.wire_unit();
+    let v_id = validators
+        .id(wunit1.creator)
+        .ok_or(EvidenceError::UnknownPerpetrator)?;
+    if wunit1.creator != wunit2.creator {
+        return Err(EvidenceError::EquivocationDifferentCreators);
+    }
+    if wunit1.seq_number != wunit2.seq_number {
+        return Err(EvidenceError::EquivocationDifferentSeqNumbers);
+    }
+    if wunit1.instance_id != *instance_id || wunit2.instance_id != *instance_id {
+        return Err(EvidenceError::EquivocationInstanceId);
+    }
+    if unit1 == unit2 {
+        return Err(EvidenceError::EquivocationSameUnit);
+    }
+    if !C::verify_signature(&unit1.hash(), v_id, &unit1.signature)
+        || !C::verify_signature(&unit2.hash(), v_id, &unit2.signature)
+    {
+        return Err(EvidenceError::Signature);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_equivocation, test_vectors};
+
+    #[test]
+    fn should_match_test_vectors() {
+        let mut rng = crate::new_rng();
+        let validators = test_vectors::validators();
+        for vector in test_vectors::test_vectors(&mut rng) {
+            assert_eq!(
+                vector.expected,
+                check_equivocation(
+                    &vector.unit1,
+                    &vector.unit2,
+                    &test_vectors::INSTANCE_ID,
+                    &validators
+                ),
+                "{}",
+                vector.description
+            );
+            assert_eq!(
+                vector.expected,
+                check_equivocation(
+                    &vector.unit2,
+                    &vector.unit1,
+                    &test_vectors::INSTANCE_ID,
+                    &validators
+                ),
+                "{} (units swapped)",
+                vector.description
+            );
+        }
+    }
+}
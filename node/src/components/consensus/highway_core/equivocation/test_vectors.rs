@@ -0,0 +1,167 @@
+//! Test vectors for equivocation detection.
+//!
+//! Each vector is a pair of signed units together with the result `check_equivocation` must return
+//! for them, with the validator set from `validators` and instance ID `INSTANCE_ID`. The vectors
+//! use `TestContext`, whose signature of a hash is the hash plus the signer's ID, so they can be
+//! reproduced without any cryptographic library.
+
+use std::{collections::BTreeSet, iter::FromIterator};
+
+use crate::{
+    components::consensus::highway_core::{
+        evidence::EvidenceError,
+        highway::{SignedWireUnit, WireUnit},
+        highway_testing::TEST_INSTANCE_ID,
+        state::{tests::*, Panorama},
+        validators::{ValidatorIndex, Validators},
+    },
+    types::Timestamp,
+    NodeRng,
+};
+
+/// The instance ID the vectors are checked against.
+pub(crate) const INSTANCE_ID: u64 = TEST_INSTANCE_ID;
+
+/// A pair of units and whether they constitute an equivocation.
+pub(crate) struct TestVector {
+    /// What the vector tests.
+    pub(crate) description: &'static str,
+    pub(crate) unit1: SignedWireUnit<TestContext>,
+    pub(crate) unit2: SignedWireUnit<TestContext>,
+    /// The expected result of `check_equivocation`.
+    pub(crate) expected: Result<(), EvidenceError>,
+}
+
+/// Returns the validator set the vectors are checked against: Alice, Bob and Carol.
+pub(crate) fn validators() -> Validators<u32> {
+    let vid_weights: Vec<(u32, u64)> = vec![(ALICE_SEC, ALICE), (BOB_SEC, BOB), (CAROL_SEC, CAROL)]
+        .into_iter()
+        .map(|(sk, vid)| (sk.0, WEIGHTS[vid.0 as usize].0))
+        .collect();
+    Validators::from_iter(vid_weights)
+}
+
+/// Returns Carol's first unit, proposing `value`.
+fn wire_unit(value: u32) -> WireUnit<TestContext> {
+    WireUnit {
+        panorama: Panorama::new(WEIGHTS.len()),
+        creator: CAROL,
+        instance_id: INSTANCE_ID,
+        value: Some(value),
+        seq_number: 0,
+        timestamp: Timestamp::zero(),
+        round_exp: 4,
+        endorsed: BTreeSet::new(),
+    }
+}
+
+fn sign(
+    wunit: WireUnit<TestContext>,
+    secret: &TestSecret,
+    rng: &mut NodeRng,
+) -> SignedWireUnit<TestContext> {
+    SignedWireUnit::new(wunit.into_hashed(), secret, rng)
+}
+
+/// Returns the test vectors.
+pub(crate) fn test_vectors(rng: &mut NodeRng) -> Vec<TestVector> {
+    let mut vectors = Vec::new();
+    let mut push = |description, unit1, unit2, expected| {
+        vectors.push(TestVector {
+            description,
+            unit1,
+            unit2,
+            expected,
+        })
+    };
+
+    push(
+        "two values with the same sequence number",
+        sign(wire_unit(0), &CAROL_SEC, rng),
+        sign(wire_unit(1), &CAROL_SEC, rng),
+        Ok(()),
+    );
+
+    let mut later = wire_unit(0);
+    later.timestamp = Timestamp::from(1);
+    push(
+        "the same value at different times with the same sequence number",
+        sign(wire_unit(0), &CAROL_SEC, rng),
+        sign(later, &CAROL_SEC, rng),
+        Ok(()),
+    );
+
+    let mut ballot = wire_unit(0);
+    ballot.value = None;
+    push(
+        "a proposal and a ballot with the same sequence number",
+        sign(wire_unit(0), &CAROL_SEC, rng),
+        sign(ballot, &CAROL_SEC, rng),
+        Ok(()),
+    );
+
+    push(
+        "the same unit twice",
+        sign(wire_unit(0), &CAROL_SEC, rng),
+        sign(wire_unit(0), &CAROL_SEC, rng),
+        Err(EvidenceError::EquivocationSameUnit),
+    );
+
+    let mut successor = wire_unit(1);
+    successor.seq_number = 1;
+    push(
+        "two units with different sequence numbers",
+        sign(wire_unit(0), &CAROL_SEC, rng),
+        sign(successor, &CAROL_SEC, rng),
+        Err(EvidenceError::EquivocationDifferentSeqNumbers),
+    );
+
+    let mut bobs = wire_unit(1);
+    bobs.creator = BOB;
+    push(
+        "two units by different creators",
+        sign(wire_unit(0), &CAROL_SEC, rng),
+        sign(bobs, &BOB_SEC, rng),
+        Err(EvidenceError::EquivocationDifferentCreators),
+    );
+
+    let mut other_instance = wire_unit(1);
+    other_instance.instance_id = INSTANCE_ID + 1;
+    push(
+        "one unit from a different protocol instance",
+        sign(wire_unit(0), &CAROL_SEC, rng),
+        sign(other_instance, &CAROL_SEC, rng),
+        Err(EvidenceError::EquivocationInstanceId),
+    );
+
+    push(
+        "one unit signed by a different validator",
+        sign(wire_unit(0), &CAROL_SEC, rng),
+        sign(wire_unit(1), &BOB_SEC, rng),
+        Err(EvidenceError::Signature),
+    );
+
+    let unit = sign(wire_unit(0), &CAROL_SEC, rng);
+    let mut forged = sign(wire_unit(1), &CAROL_SEC, rng);
+    forged.signature = unit.signature;
+    push(
+        "one unit carrying the other unit's signature",
+        unit,
+        forged,
+        Err(EvidenceError::Signature),
+    );
+
+    let unknown_creator = |value| {
+        let mut wunit = wire_unit(value);
+        wunit.creator = ValidatorIndex(WEIGHTS.len() as u32);
+        wunit
+    };
+    push(
+        "two units by a creator who is not a validator",
+        sign(unknown_creator(0), &CAROL_SEC, rng),
+        sign(unknown_creator(1), &CAROL_SEC, rng),
+        Err(EvidenceError::UnknownPerpetrator),
+    );
+
+    vectors
+}
@@ -7,8 +7,8 @@ use thiserror::Error;
 use super::validators::ValidatorIndex;
 use crate::components::consensus::{
     highway_core::{
-        endorsement::SignedEndorsement, highway::SignedWireUnit, state::State,
-        validators::Validators,
+        endorsement::SignedEndorsement, equivocation::check_equivocation, highway::SignedWireUnit,
+        state::State, validators::Validators,
     },
     traits::Context,
 };
@@ -77,7 +77,7 @@ impl<C: Context> Evidence<C> {
     /// "Validation" can mean different things for different type of evidence.
     ///
     /// - For an equivocation, it checks whether the creators, sequence numbers and instance IDs of
-    /// the two units are the same.
+    /// the two units are the same, see `check_equivocation`.
     pub(crate) fn validate(
         &self,
         validators: &Validators<C::ValidatorId>,
@@ -86,7 +86,7 @@ impl<C: Context> Evidence<C> {
     ) -> Result<(), EvidenceError> {
         match self {
             Evidence::Equivocation(unit1, unit2) => {
-                Self::validate_equivocation(unit1, unit2, instance_id, validators)
+                check_equivocation(unit1, unit2, instance_id, validators)
             }
             Evidence::Endorsements {
                 endorsement1,
@@ -112,7 +112,7 @@ impl<C: Context> Evidence<C> {
                         return Err(EvidenceError::EndorsementInvalidSwimlane);
                     }
                 }
-                Self::validate_equivocation(
+                check_equivocation(
                     unit1,
                     swimlane2.last().unwrap_or(unit2),
                     instance_id,
@@ -127,35 +127,4 @@ impl<C: Context> Evidence<C> {
             }
         }
     }
-
-    fn validate_equivocation(
-        unit1: &SignedWireUnit<C>,
-        unit2: &SignedWireUnit<C>,
-        instance_id: &C::InstanceId,
-        validators: &Validators<C::ValidatorId>,
-    ) -> Result<(), EvidenceError> {
-        let wunit1 = unit1.wire_unit();
-        let wunit2 = unit2.wire_unit();
-        let v_id = validators
-            .id(wunit1.creator)
-            .ok_or(EvidenceError::UnknownPerpetrator)?;
-        if wunit1.creator != wunit2.creator {
-            return Err(EvidenceError::EquivocationDifferentCreators);
-        }
-        if wunit1.seq_number != wunit2.seq_number {
-            return Err(EvidenceError::EquivocationDifferentSeqNumbers);
-        }
-        if wunit1.instance_id != *instance_id || wunit2.instance_id != *instance_id {
-            return Err(EvidenceError::EquivocationInstanceId);
-        }
-        if unit1 == unit2 {
-            return Err(EvidenceError::EquivocationSameUnit);
-        }
-        if !C::verify_signature(&unit1.hash(), v_id, &unit1.signature)
-            || !C::verify_signature(&unit2.hash(), v_id, &unit2.signature)
-        {
-            return Err(EvidenceError::Signature);
-        }
-        Ok(())
-    }
 }
@@ -16,7 +16,7 @@ use crate::{
         highway_core::{
             active_validator::{ActiveValidator, Effect},
             evidence::EvidenceError,
-            state::{Fault, State, UnitError},
+            state::{Fault, State, Unit, UnitError},
             validators::{Validator, Validators},
         },
         traits::Context,
@@ -600,6 +600,65 @@ impl<C: Context> Highway<C> {
         self.active_validator.is_some()
     }
 
+    /// Returns the percentage of the other validators' total weight whose latest unit we know of
+    /// was created at or after `since`, or `None` if there are no other validators with weight.
+    ///
+    /// Our own units are not counted: we always see them, even if we are cut off from everyone
+    /// else.
+    pub(crate) fn recently_active_weight_percent(&self, since: Timestamp) -> Option<u64> {
+        self.other_validators_weight_percent(|unit| unit.timestamp >= since)
+    }
+
+    /// Returns the percentage of the other validators' total weight whose latest unit cites a unit
+    /// of ours created at or after `since`.
+    ///
+    /// Returns `None` if we are not an active validator or there are no other validators with
+    /// weight.
+    pub(crate) fn own_units_cited_weight_percent(&self, since: Timestamp) -> Option<u64> {
+        let our_idx = self.active_validator.as_ref()?.vidx;
+        self.other_validators_weight_percent(|unit| {
+            unit.panorama[our_idx]
+                .correct()
+                .map_or(false, |hash| self.state.unit(hash).timestamp >= since)
+        })
+    }
+
+    /// Returns the timestamp of our latest unit, or `None` if we are not an active validator or
+    /// have not created any units yet.
+    pub(crate) fn latest_own_unit_timestamp(&self) -> Option<Timestamp> {
+        let our_idx = self.active_validator.as_ref()?.vidx;
+        let hash = self.state.panorama()[our_idx].correct()?;
+        Some(self.state.unit(hash).timestamp)
+    }
+
+    /// Returns the percentage of the total weight of validators other than us whose latest unit
+    /// satisfies `pred`, or `None` if there are no other validators with weight.
+    fn other_validators_weight_percent<F>(&self, pred: F) -> Option<u64>
+    where
+        F: Fn(&Unit<C>) -> bool,
+    {
+        let our_idx = self.active_validator.as_ref().map(|av| av.vidx);
+        let others = || {
+            self.state
+                .weights()
+                .enumerate()
+                .filter(move |(vidx, _)| Some(*vidx) != our_idx)
+        };
+        let total: u128 = others().map(|(_, weight)| u128::from(weight.0)).sum();
+        if total == 0 {
+            return None;
+        }
+        let matching: u128 = others()
+            .filter(|(vidx, _)| {
+                self.state.panorama()[*vidx]
+                    .correct()
+                    .map_or(false, |hash| pred(self.state.unit(hash)))
+            })
+            .map(|(_, weight)| u128::from(weight.0))
+            .sum();
+        Some((matching * 100 / total) as u64)
+    }
+
     /// Returns the instance ID of this Highway instance.
     pub(crate) fn instance_id(&self) -> &C::InstanceId {
         &self.instance_id
@@ -752,6 +811,33 @@ pub(crate) mod tests {
         Ok(())
     }
 
+    #[test]
+    fn recently_active_weight() -> Result<(), AddUnitError<TestContext>> {
+        let mut state = State::new_test(WEIGHTS, 0);
+        let mut rng = crate::new_rng();
+
+        // Alice's unit has timestamp 0, Bob's timestamp 1. Carol has not created any units.
+        let a = add_unit!(state, rng, ALICE, None; N, N, N)?;
+        add_unit!(state, rng, BOB, None; a, N, N)?;
+
+        let highway = Highway {
+            instance_id: TEST_INSTANCE_ID,
+            validators: test_validators(),
+            state,
+            active_validator: None,
+        };
+
+        // The total weight is 12: Alice has 3, Bob 4.
+        assert_eq!(Some(58), highway.recently_active_weight_percent(0.into()));
+        assert_eq!(Some(33), highway.recently_active_weight_percent(1.into()));
+        assert_eq!(Some(0), highway.recently_active_weight_percent(2.into()));
+
+        // We are not a validator ourselves, so none of the units are ours.
+        assert_eq!(None, highway.own_units_cited_weight_percent(0.into()));
+        assert_eq!(None, highway.latest_own_unit_timestamp());
+        Ok(())
+    }
+
     #[test]
     fn invalid_evidence() {
         let mut rng = crate::new_rng();
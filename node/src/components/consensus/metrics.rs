@@ -13,6 +13,18 @@ pub struct ConsensusMetrics {
     time_of_last_proposed_block: Gauge,
     /// The Current era.
     pub current_era: IntGauge,
+    /// Percentage of the other validators' weight we recently received messages from.
+    pub active_validator_weight_percent: IntGauge,
+    /// 1 if too few validators were recently active, i.e. we are possibly partitioned, else 0.
+    pub possibly_partitioned: IntGauge,
+    /// Percentage of the other validators' weight citing our recent units.
+    pub own_units_cited_weight_percent: IntGauge,
+    /// 1 if too few validators cite our recent units, i.e. our participation is degraded, else 0.
+    pub participation_degraded: IntGauge,
+    /// 1 if the current era's validator set does not match the local auction results, else 0.
+    pub validator_set_mismatch: IntGauge,
+    /// Number of pending vertices evicted because the pending vertex limits were reached.
+    pub evicted_pending_vertices: IntCounter,
     /// registry component.
     registry: Registry,
 }
@@ -30,14 +42,50 @@ impl ConsensusMetrics {
             "timestamp of the most recently accepted proto block",
         )?;
         let current_era = IntGauge::new("current_era", "The current era")?;
+        let active_validator_weight_percent = IntGauge::new(
+            "active_validator_weight_percent",
+            "percentage of the other validators' weight from which recent messages were received",
+        )?;
+        let possibly_partitioned = IntGauge::new(
+            "possibly_partitioned",
+            "1 if too few validators were recently active and the node may be partitioned, else 0",
+        )?;
+        let own_units_cited_weight_percent = IntGauge::new(
+            "own_units_cited_weight_percent",
+            "percentage of the other validators' weight whose latest messages cite our recent units",
+        )?;
+        let participation_degraded = IntGauge::new(
+            "participation_degraded",
+            "1 if too few validators cite our recent units and our participation is degraded, else 0",
+        )?;
+        let validator_set_mismatch = IntGauge::new(
+            "validator_set_mismatch",
+            "1 if the current era's validator set does not match the local auction results, else 0",
+        )?;
+        let evicted_pending_vertices = IntCounter::new(
+            "evicted_pending_vertices",
+            "the number of pending vertices evicted because the pending vertex limits were reached",
+        )?;
         registry.register(Box::new(finalization_time.clone()))?;
         registry.register(Box::new(finalized_block_count.clone()))?;
         registry.register(Box::new(current_era.clone()))?;
+        registry.register(Box::new(active_validator_weight_percent.clone()))?;
+        registry.register(Box::new(possibly_partitioned.clone()))?;
+        registry.register(Box::new(own_units_cited_weight_percent.clone()))?;
+        registry.register(Box::new(participation_degraded.clone()))?;
+        registry.register(Box::new(validator_set_mismatch.clone()))?;
+        registry.register(Box::new(evicted_pending_vertices.clone()))?;
         Ok(ConsensusMetrics {
             finalization_time,
             finalized_block_count,
             time_of_last_proposed_block,
             current_era,
+            active_validator_weight_percent,
+            possibly_partitioned,
+            own_units_cited_weight_percent,
+            participation_degraded,
+            validator_set_mismatch,
+            evicted_pending_vertices,
             registry: registry.clone(),
         })
     }
@@ -67,5 +115,23 @@ impl Drop for ConsensusMetrics {
         self.registry
             .unregister(Box::new(self.current_era.clone()))
             .expect("did not expect deregistering current era to fail");
+        self.registry
+            .unregister(Box::new(self.active_validator_weight_percent.clone()))
+            .expect("did not expect deregistering active validator weight to fail");
+        self.registry
+            .unregister(Box::new(self.possibly_partitioned.clone()))
+            .expect("did not expect deregistering possibly partitioned to fail");
+        self.registry
+            .unregister(Box::new(self.own_units_cited_weight_percent.clone()))
+            .expect("did not expect deregistering own units cited weight to fail");
+        self.registry
+            .unregister(Box::new(self.participation_degraded.clone()))
+            .expect("did not expect deregistering participation degraded to fail");
+        self.registry
+            .unregister(Box::new(self.validator_set_mismatch.clone()))
+            .expect("did not expect deregistering validator set mismatch to fail");
+        self.registry
+            .unregister(Box::new(self.evicted_pending_vertices.clone()))
+            .expect("did not expect deregistering evicted pending vertices to fail");
     }
 }
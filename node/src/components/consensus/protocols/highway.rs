@@ -34,6 +34,7 @@ use crate::{
         ActionId, TimerId,
     },
     types::Timestamp,
+    utils::bounded,
     NodeRng,
 };
 
@@ -164,7 +165,11 @@ impl<I: NodeIdT, C: Context + 'static> HighwayProtocol<I, C> {
                 max_round_exp,
                 start_timestamp,
             ),
-            synchronizer: Synchronizer::new(config.pending_vertex_timeout),
+            synchronizer: Synchronizer::new(
+                config.pending_vertex_timeout,
+                config.max_pending_vertices_per_era,
+                config.max_pending_vertices_per_peer,
+            ),
         });
         (hw_proto, outcomes)
     }
@@ -248,8 +253,10 @@ impl<I: NodeIdT, C: Context + 'static> HighwayProtocol<I, C> {
         // dependency from the sender.
         if let Some(dep) = self.highway.missing_dependency(pending_vertex.pvv()) {
             let sender = pending_vertex.sender().clone();
-            self.synchronizer
-                .add_missing_dependency(dep.clone(), pending_vertex);
+            outcomes.extend(
+                self.synchronizer
+                    .add_missing_dependency(dep.clone(), pending_vertex),
+            );
             let msg = HighwayMessage::RequestDependency(dep);
             let ser_msg = bincode::serialize(&msg).expect("should serialize message");
             outcomes.push(ProtocolOutcome::CreatedTargetedMessage(ser_msg, sender));
@@ -399,7 +406,7 @@ where
         evidence_only: bool,
         _rng: &mut NodeRng,
     ) -> ProtocolOutcomes<I, C> {
-        match bincode::deserialize(msg.as_slice()) {
+        match bounded::from_bincode(msg.as_slice(), bounded::Limits::NETWORK) {
             Err(err) => vec![ProtocolOutcome::InvalidIncomingMessage(
                 msg,
                 sender,
@@ -458,10 +465,12 @@ where
                     Some(timestamp) if timestamp > now => {
                         // If it's not from an equivocator and from the future, add to queue
                         trace!("received a vertex from the future; storing for later");
-                        self.synchronizer
+                        let mut outcomes = self
+                            .synchronizer
                             .store_vertex_for_addition_later(timestamp, sender, pvv);
                         let timer_id = TIMER_ID_VERTEX_WITH_FUTURE_TIMESTAMP;
-                        vec![ProtocolOutcome::ScheduleTimer(timestamp, timer_id)]
+                        outcomes.push(ProtocolOutcome::ScheduleTimer(timestamp, timer_id));
+                        outcomes
                     }
                     _ => {
                         // If it's not from an equivocator or it is a transitive dependency, add the
@@ -718,4 +727,16 @@ where
     fn instance_id(&self) -> &C::InstanceId {
         self.highway.instance_id()
     }
+
+    fn recently_active_weight_percent(&self, since: Timestamp) -> Option<u64> {
+        self.highway.recently_active_weight_percent(since)
+    }
+
+    fn own_messages_cited_weight_percent(&self, since: Timestamp) -> Option<u64> {
+        self.highway.own_units_cited_weight_percent(since)
+    }
+
+    fn latest_own_message_timestamp(&self) -> Option<Timestamp> {
+        self.highway.latest_own_unit_timestamp()
+    }
 }
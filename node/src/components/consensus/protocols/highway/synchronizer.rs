@@ -1,10 +1,12 @@
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Debug,
+    iter,
 };
 
 use datasize::DataSize;
 use itertools::Itertools;
+use tracing::debug;
 
 use crate::{
     components::consensus::{
@@ -83,16 +85,30 @@ where
     vertices_to_be_added: Vec<PendingVertex<I, C>>,
     /// The duration for which incoming vertices with missing dependencies are kept in a queue.
     pending_vertex_timeout: TimeDiff,
+    /// The maximum number of vertices in `vertex_deps` and `vertices_to_be_added_later`.
+    max_pending_vertices: usize,
+    /// The maximum number of vertices from a single peer in `vertex_deps` and
+    /// `vertices_to_be_added_later`.
+    max_pending_vertices_per_peer: usize,
+    /// The number of vertices in `vertex_deps` and `vertices_to_be_added_later`, by sender.
+    pending_by_sender: HashMap<I, usize>,
 }
 
 impl<I: NodeIdT, C: Context + 'static> Synchronizer<I, C> {
-    /// Creates a new synchronizer with the specified timeout for pending vertices.
-    pub(crate) fn new(pending_vertex_timeout: TimeDiff) -> Self {
+    /// Creates a new synchronizer with the specified timeout and limits for pending vertices.
+    pub(crate) fn new(
+        pending_vertex_timeout: TimeDiff,
+        max_pending_vertices: usize,
+        max_pending_vertices_per_peer: usize,
+    ) -> Self {
         Synchronizer {
             vertex_deps: BTreeMap::new(),
             vertices_to_be_added_later: BTreeMap::new(),
             vertices_to_be_added: Vec::new(),
             pending_vertex_timeout,
+            max_pending_vertices,
+            max_pending_vertices_per_peer,
+            pending_by_sender: HashMap::new(),
         }
     }
 
@@ -100,22 +116,28 @@ impl<I: NodeIdT, C: Context + 'static> Synchronizer<I, C> {
     pub(crate) fn purge_vertices(&mut self) {
         let timeout = self.pending_vertex_timeout;
         self.vertices_to_be_added.retain(|pv| !pv.expired(timeout));
-        Self::remove_expired(&mut self.vertices_to_be_added_later, timeout);
-        Self::remove_expired(&mut self.vertex_deps, timeout);
+        let mut senders = Self::remove_expired(&mut self.vertices_to_be_added_later, timeout);
+        senders.extend(Self::remove_expired(&mut self.vertex_deps, timeout));
+        self.untrack(senders);
     }
 
     /// Store a (pre-validated) vertex which will be added later.  This creates a timer to be sent
     /// to the reactor. The vertex be added using `Self::add_vertices` when that timer goes off.
+    ///
+    /// If this exceeds the limits for pending vertices, another one is evicted.
     pub(crate) fn store_vertex_for_addition_later(
         &mut self,
         future_timestamp: Timestamp,
         sender: I,
         pvv: PreValidatedVertex<C>,
-    ) {
+    ) -> ProtocolOutcomes<I, C> {
+        let outcomes = self.make_room_for(&sender);
+        *self.pending_by_sender.entry(sender.clone()).or_default() += 1;
         self.vertices_to_be_added_later
             .entry(future_timestamp)
             .or_default()
             .push(PendingVertex::new(sender, pvv));
+        outcomes
     }
 
     /// Schedules calls to `add_vertex` on any vertices in `vertices_to_be_added_later` which are
@@ -136,6 +158,7 @@ impl<I: NodeIdT, C: Context + 'static> Synchronizer<I, C> {
             if let Some(vertices_to_add) =
                 self.vertices_to_be_added_later.remove(&past_due_timestamp)
             {
+                self.untrack(vertices_to_add.iter().map(|pv| pv.sender.clone()));
                 results.extend(self.schedule_add_vertices(vertices_to_add))
             }
         }
@@ -169,6 +192,7 @@ impl<I: NodeIdT, C: Context + 'static> Synchronizer<I, C> {
             .into_iter()
             .flat_map(|dep| self.vertex_deps.remove(&dep).unwrap())
             .collect_vec();
+        self.untrack(pvs.iter().map(|pv| pv.sender.clone()));
         self.schedule_add_vertices(pvs)
     }
 
@@ -197,8 +221,17 @@ impl<I: NodeIdT, C: Context + 'static> Synchronizer<I, C> {
     }
 
     /// Adds a vertex with a known missing dependency to the queue.
-    pub(crate) fn add_missing_dependency(&mut self, dep: Dependency<C>, pv: PendingVertex<I, C>) {
-        self.vertex_deps.entry(dep).or_default().push(pv)
+    ///
+    /// If this exceeds the limits for pending vertices, another one is evicted.
+    pub(crate) fn add_missing_dependency(
+        &mut self,
+        dep: Dependency<C>,
+        pv: PendingVertex<I, C>,
+    ) -> ProtocolOutcomes<I, C> {
+        let outcomes = self.make_room_for(&pv.sender);
+        *self.pending_by_sender.entry(pv.sender.clone()).or_default() += 1;
+        self.vertex_deps.entry(dep).or_default().push(pv);
+        outcomes
     }
 
     /// Returns `true` if no vertices are in the queues.
@@ -241,7 +274,7 @@ impl<I: NodeIdT, C: Context + 'static> Synchronizer<I, C> {
         vertices: Vec<Dependency<C>>,
     ) -> (Vec<Dependency<C>>, HashSet<I>) {
         // collect the vertices that depend on the ones we got in the argument and their senders
-        vertices
+        let dropped = vertices
             .into_iter()
             // filtering by is_unit, so that we don't drop vertices depending on invalid evidence
             // or endorsements - we can still get valid ones from someone else and eventually
@@ -249,17 +282,120 @@ impl<I: NodeIdT, C: Context + 'static> Synchronizer<I, C> {
             .filter(|dep| dep.is_unit())
             .flat_map(|vertex| self.vertex_deps.remove(&vertex))
             .flatten()
+            .collect_vec();
+        self.untrack(dropped.iter().map(|pv| pv.sender.clone()));
+        dropped
+            .into_iter()
             .map(|pv| (pv.pvv.inner().id(), pv.sender))
             .unzip()
     }
 
-    /// Removes all expired entries from a `BTreeMap` of `Vec`s.
+    /// Evicts a pending vertex if adding one from `sender` would exceed the limits.
+    ///
+    /// If `sender` has reached the limit per peer, its own oldest vertex is evicted. Otherwise, if
+    /// the total limit is reached, the oldest vertex of the peer with the most pending vertices is
+    /// evicted, so that a peer flooding us with unresolvable vertices doesn't displace others'.
+    fn make_room_for(&mut self, sender: &I) -> ProtocolOutcomes<I, C> {
+        let sender_count = self.pending_by_sender.get(sender).copied().unwrap_or(0);
+        let peer = if sender_count >= self.max_pending_vertices_per_peer {
+            sender.clone()
+        } else if self.pending_by_sender.values().sum::<usize>() >= self.max_pending_vertices {
+            match self
+                .pending_by_sender
+                .iter()
+                .max_by_key(|(_, count)| **count)
+            {
+                Some((peer, _)) => peer.clone(),
+                None => return vec![],
+            }
+        } else {
+            return vec![];
+        };
+        match self.evict_oldest_from(&peer) {
+            Some(pv) => {
+                debug!(%peer, vertex_id = ?pv.vertex().id(), "evicted pending vertex");
+                vec![ProtocolOutcome::EvictedPendingVertex(peer)]
+            }
+            None => vec![],
+        }
+    }
+
+    /// Removes and returns the oldest vertex received from `peer` in `vertex_deps` and
+    /// `vertices_to_be_added_later`.
+    fn evict_oldest_from(&mut self, peer: &I) -> Option<PendingVertex<I, C>> {
+        let oldest_dep = Self::find_oldest_from(&self.vertex_deps, peer);
+        let oldest_later = Self::find_oldest_from(&self.vertices_to_be_added_later, peer);
+        let pv = match (oldest_dep, oldest_later) {
+            (Some((dep, index, dep_time)), Some((_, _, later_time))) if dep_time <= later_time => {
+                Self::remove_at(&mut self.vertex_deps, &dep, index)
+            }
+            (Some((dep, index, _)), None) => Self::remove_at(&mut self.vertex_deps, &dep, index),
+            (_, Some((timestamp, index, _))) => {
+                Self::remove_at(&mut self.vertices_to_be_added_later, &timestamp, index)
+            }
+            (None, None) => return None,
+        };
+        self.untrack(iter::once(pv.sender.clone()));
+        Some(pv)
+    }
+
+    /// Returns the key, index and receipt time of the oldest vertex received from `peer`.
+    fn find_oldest_from<T: Ord + Clone>(
+        map: &BTreeMap<T, Vec<PendingVertex<I, C>>>,
+        peer: &I,
+    ) -> Option<(T, usize, Timestamp)> {
+        map.iter()
+            .flat_map(|(key, pvs)| {
+                pvs.iter()
+                    .enumerate()
+                    .filter(|(_, pv)| pv.sender == *peer)
+                    .map(move |(index, pv)| (key, index, pv.time_received))
+            })
+            .min_by_key(|(_, _, time_received)| *time_received)
+            .map(|(key, index, time_received)| (key.clone(), index, time_received))
+    }
+
+    /// Removes the vertex at `index` of the entry with the given key, and the entry if empty.
+    fn remove_at<T: Ord>(
+        map: &mut BTreeMap<T, Vec<PendingVertex<I, C>>>,
+        key: &T,
+        index: usize,
+    ) -> PendingVertex<I, C> {
+        let pvs = map.get_mut(key).expect("entry should exist");
+        let pv = pvs.remove(index);
+        if pvs.is_empty() {
+            map.remove(key);
+        }
+        pv
+    }
+
+    /// Stops counting vertices from the given senders as pending.
+    fn untrack<T: IntoIterator<Item = I>>(&mut self, senders: T) {
+        for sender in senders {
+            if let Some(count) = self.pending_by_sender.get_mut(&sender) {
+                *count -= 1;
+                if *count == 0 {
+                    self.pending_by_sender.remove(&sender);
+                }
+            }
+        }
+    }
+
+    /// Removes all expired entries from a `BTreeMap` of `Vec`s, and returns their senders.
     fn remove_expired<T: Ord + Clone>(
         map: &mut BTreeMap<T, Vec<PendingVertex<I, C>>>,
         timeout: TimeDiff,
-    ) {
+    ) -> Vec<I> {
+        let mut senders = Vec::new();
         for pvs in map.values_mut() {
-            pvs.retain(|pv| !pv.expired(timeout));
+            pvs.retain(|pv| {
+                if pv.expired(timeout) {
+                    senders.push(pv.sender.clone());
+                    false
+                } else {
+                    true
+                }
+            });
         }
         let keys = map
             .iter()
@@ -269,5 +405,6 @@ impl<I: NodeIdT, C: Context + 'static> Synchronizer<I, C> {
         for key in keys {
             map.remove(&key);
         }
+        senders
     }
 }
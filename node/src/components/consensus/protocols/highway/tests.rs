@@ -1,4 +1,4 @@
-use std::{collections::BTreeSet, rc::Rc};
+use std::{collections::BTreeSet, rc::Rc, time::Duration};
 
 use datasize::DataSize;
 use derive_more::Display;
@@ -54,10 +54,39 @@ where
 
 const INSTANCE_ID_DATA: &[u8; 1] = &[123u8; 1];
 
+/// Returns a new consensus `Config` suitable for tests.
+fn new_test_config() -> Config {
+    Config {
+        secret_key_path: Default::default(),
+        unit_hashes_folder: Default::default(),
+        pending_vertex_timeout: "1min".parse().unwrap(),
+        max_pending_vertices_per_era: 20_000,
+        max_pending_vertices_per_peer: 10_000,
+        partition_window: "5min".parse().unwrap(),
+        partition_threshold_percent: 67,
+        participation_threshold_percent: 67,
+        validator_only_broadcast: false,
+        emergency_validators: vec![],
+    }
+}
+
 pub(crate) fn new_test_highway_protocol<I1, I2, T>(
     weights: I1,
     init_slashed: I2,
 ) -> Box<dyn ConsensusProtocol<NodeId, ClContext>>
+where
+    I1: IntoIterator<Item = (PublicKey, T)>,
+    I2: IntoIterator<Item = PublicKey>,
+    T: Into<U512>,
+{
+    new_test_highway_protocol_with_config(weights, init_slashed, new_test_config())
+}
+
+fn new_test_highway_protocol_with_config<I1, I2, T>(
+    weights: I1,
+    init_slashed: I2,
+    config: Config,
+) -> Box<dyn ConsensusProtocol<NodeId, ClContext>>
 where
     I1: IntoIterator<Item = (PublicKey, T)>,
     I2: IntoIterator<Item = PublicKey>,
@@ -68,11 +97,6 @@ where
         .map(|(pk, w)| (pk, w.into()))
         .collect::<Vec<_>>();
     let chainspec = new_test_chainspec(weights.clone());
-    let config = Config {
-        secret_key_path: Default::default(),
-        unit_hashes_folder: Default::default(),
-        pending_vertex_timeout: "1min".parse().unwrap(),
-    };
     let (hw_proto, outcomes) = HighwayProtocol::<NodeId, ClContext>::new_boxed(
         ClContext::hash(INSTANCE_ID_DATA),
         weights.into_iter().collect(),
@@ -264,3 +288,63 @@ fn detect_doppelganger() {
     }
     panic!("failed to return DoppelgangerDetected effect");
 }
+
+#[test]
+fn evict_pending_vertices_over_limit() {
+    let creator: ValidatorIndex = ValidatorIndex(0);
+    let validators = vec![(*ALICE_PUBLIC_KEY, 100)];
+    let state: State<ClContext> = new_test_state(validators.iter().map(|(_pk, w)| *w), 0);
+    let mut rng = TestRng::new();
+    let alice_keypair: Keypair = Keypair::from(Rc::new(ALICE_SECRET_KEY.clone()));
+    let config = Config {
+        max_pending_vertices_per_era: 3,
+        max_pending_vertices_per_peer: 2,
+        ..new_test_config()
+    };
+    let mut highway_protocol = new_test_highway_protocol_with_config(validators, vec![], config);
+
+    // Units with timestamps in the near future are kept in a queue until they are due.
+    let mut future_unit_msg = |seconds: u64| {
+        let panorama: Panorama<ClContext> = Panorama::from(vec![N]);
+        let wunit: WireUnit<ClContext> = WireUnit {
+            seq_number: panorama.next_seq_num(&state, creator),
+            panorama,
+            creator,
+            instance_id: ClContext::hash(INSTANCE_ID_DATA),
+            value: Some(CandidateBlock::new(
+                ProtoBlock::new(vec![], vec![], false),
+                vec![],
+            )),
+            timestamp: Timestamp::now() + Duration::from_secs(seconds).into(),
+            round_exp: 14,
+            endorsed: BTreeSet::new(),
+        };
+        let highway_message: HighwayMessage<ClContext> = HighwayMessage::NewVertex(Vertex::Unit(
+            SignedWireUnit::new(wunit.into_hashed(), &alice_keypair, &mut rng),
+        ));
+        bincode::serialize(&highway_message).unwrap()
+    };
+    let messages: Vec<_> = (1..=5)
+        .map(|seconds| future_unit_msg(seconds * 5))
+        .collect();
+    let mut rng = TestRng::new();
+    let mut handle = |sender: NodeId, msg: &Vec<u8>| {
+        highway_protocol
+            .handle_message(sender, msg.clone(), false, &mut rng)
+            .into_iter()
+            .filter_map(|outcome| match outcome {
+                ProtocolOutcome::EvictedPendingVertex(peer) => Some(peer),
+                ProtocolOutcome::ScheduleTimer(_, _) => None,
+                outcome => panic!("Unexpected outcome: {:?}", outcome),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert!(handle(NodeId(1), &messages[0]).is_empty());
+    assert!(handle(NodeId(1), &messages[1]).is_empty());
+    // The third vertex from the same peer exceeds the limit per peer.
+    assert_eq!(handle(NodeId(1), &messages[2]), vec![NodeId(1)]);
+    assert!(handle(NodeId(2), &messages[3]).is_empty());
+    // The total limit is reached: the peer with the most pending vertices loses one.
+    assert_eq!(handle(NodeId(3), &messages[4]), vec![NodeId(1)]);
+}
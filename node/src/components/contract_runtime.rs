@@ -1,8 +1,11 @@
 //! Contract Runtime component.
 mod config;
+mod conformance;
+mod durability;
 mod types;
 
-pub use config::Config;
+pub use config::{CommitSync, Config};
+pub use conformance::{run_fixtures, Error as ConformanceError, FixtureOutcome};
 pub use types::{EraValidatorsRequest, ValidatorWeightsByEraIdRequest};
 
 use std::{
@@ -37,15 +40,18 @@ use casper_types::{auction::ValidatorWeights, ProtocolVersion};
 use crate::{
     components::Component,
     effect::{requests::ContractRuntimeRequest, EffectBuilder, EffectExt, Effects},
-    utils::WithDir,
+    utils::{resource_usage, WithDir},
     Chainspec, NodeRng, StorageConfig,
 };
+use durability::CommitSyncer;
 
 /// The contract runtime components.
 #[derive(DataSize)]
 pub struct ContractRuntime {
     engine_state: Arc<EngineState<LmdbGlobalState>>,
     metrics: Arc<ContractRuntimeMetrics>,
+    #[data_size(skip)]
+    syncer: Arc<CommitSyncer>,
 }
 
 impl Debug for ContractRuntime {
@@ -84,6 +90,7 @@ pub struct ContractRuntimeMetrics {
     missing_trie_keys: Histogram,
     put_trie: Histogram,
     read_trie: Histogram,
+    sync: Histogram,
 }
 
 /// Value of upper bound of histogram.
@@ -118,6 +125,8 @@ const PUT_TRIE_NAME: &str = "contract_runtime_put_trie";
 const PUT_TRIE_HELP: &str = "tracking run of engine_state.put_trie in seconds.";
 const MISSING_TRIE_KEYS_NAME: &str = "contract_runtime_missing_trie_keys";
 const MISSING_TRIE_KEYS_HELP: &str = "tracking run of engine_state.missing_trie_keys in seconds.";
+const SYNC_NAME: &str = "contract_runtime_sync";
+const SYNC_HELP: &str = "tracking flushes of the global state store to disk in seconds.";
 
 /// Create prometheus Histogram and register.
 fn register_histogram_metric(
@@ -136,6 +145,15 @@ fn register_histogram_metric(
     Ok(histogram)
 }
 
+/// Runs `f` on the blocking thread pool, attributing its resource usage to the contract runtime.
+fn spawn_blocking_attributed<F, T>(f: F) -> task::JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    task::spawn_blocking(move || resource_usage::measure_blocking("contract_runtime", f))
+}
+
 impl ContractRuntimeMetrics {
     /// Constructor of metrics which creates and registers metrics objects for use.
     fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
@@ -175,6 +193,7 @@ impl ContractRuntimeMetrics {
                 MISSING_TRIE_KEYS_NAME,
                 MISSING_TRIE_KEYS_HELP,
             )?,
+            sync: register_histogram_metric(registry, SYNC_NAME, SYNC_HELP)?,
         })
     }
 }
@@ -209,6 +228,7 @@ where
                 responder,
             }) => {
                 let result = self.commit_genesis(chainspec);
+                self.syncer.sync_now();
                 responder.respond(result).ignore()
             }
             Event::Request(ContractRuntimeRequest::Execute {
@@ -220,7 +240,7 @@ where
                 let metrics = Arc::clone(&self.metrics);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let execution_result =
                             engine_state.run_execute(correlation_id, *execute_request);
@@ -242,9 +262,10 @@ where
                 trace!(?state_root_hash, ?effects, "commit");
                 let engine_state = Arc::clone(&self.engine_state);
                 let metrics = Arc::clone(&self.metrics);
+                let syncer = Arc::clone(&self.syncer);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let apply_result = engine_state.apply_effect(
                             correlation_id,
@@ -252,6 +273,7 @@ where
                             effects,
                         );
                         metrics.apply_effect.observe(start.elapsed().as_secs_f64());
+                        syncer.block_committed();
                         apply_result
                     })
                     .await
@@ -268,14 +290,16 @@ where
                 trace!(?upgrade_config, "upgrade");
                 let engine_state = Arc::clone(&self.engine_state);
                 let metrics = Arc::clone(&self.metrics);
+                let syncer = Arc::clone(&self.syncer);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let result = engine_state.commit_upgrade(correlation_id, *upgrade_config);
                         metrics
                             .commit_upgrade
                             .observe(start.elapsed().as_secs_f64());
+                        syncer.sync_now();
                         result
                     })
                     .await
@@ -294,7 +318,7 @@ where
                 let metrics = Arc::clone(&self.metrics);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let result = engine_state.run_query(correlation_id, query_request);
                         metrics.run_query.observe(start.elapsed().as_secs_f64());
@@ -316,7 +340,7 @@ where
                 let metrics = Arc::clone(&self.metrics);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let result = engine_state.get_purse_balance(
                             correlation_id,
@@ -347,7 +371,7 @@ where
                     GetEraValidatorsRequest::new(state_root_hash.into(), protocol_version);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let era_validators =
                             engine_state.get_era_validators(correlation_id, request);
@@ -376,7 +400,7 @@ where
                 // requested.
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let era_validators =
                             engine_state.get_era_validators(correlation_id, request.into());
@@ -403,7 +427,7 @@ where
                 // requested.
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let era_id = request.era_id().into();
                         let era_validators =
@@ -438,7 +462,7 @@ where
                 let metrics = Arc::clone(&self.metrics);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let result = engine_state.commit_step(correlation_id, step_request);
                         metrics.get_balance.observe(start.elapsed().as_secs_f64());
@@ -460,7 +484,7 @@ where
                 let metrics = Arc::clone(&self.metrics);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let result = engine_state.read_trie(correlation_id, trie_key);
                         metrics.read_trie.observe(start.elapsed().as_secs_f64());
@@ -486,7 +510,7 @@ where
                 let metrics = Arc::clone(&self.metrics);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let result = engine_state
                             .put_trie_and_find_missing_descendant_trie_keys(correlation_id, &*trie);
@@ -509,7 +533,7 @@ where
                 let metrics = Arc::clone(&self.metrics);
                 async move {
                     let correlation_id = CorrelationId::new();
-                    let result = task::spawn_blocking(move || {
+                    let result = spawn_blocking_attributed(move || {
                         let start = Instant::now();
                         let result = engine_state.missing_trie_keys(correlation_id, trie_key);
                         metrics.read_trie.observe(start.elapsed().as_secs_f64());
@@ -544,10 +568,12 @@ impl ContractRuntime {
         registry: &Registry,
     ) -> Result<Self, ConfigError> {
         let path = storage_config.with_dir(storage_config.value().path.clone());
-        let environment = Arc::new(LmdbEnvironment::new(
+        let commit_sync = contract_runtime_config.commit_sync();
+        let environment = Arc::new(LmdbEnvironment::with_flags(
             path.as_path(),
             contract_runtime_config.max_global_state_size(),
             contract_runtime_config.max_readers(),
+            durability::environment_flags(commit_sync),
         )?);
 
         let trie_store = Arc::new(LmdbTrieStore::new(
@@ -562,15 +588,22 @@ impl ContractRuntime {
             DatabaseFlags::empty(),
         )?);
 
+        let metrics = Arc::new(ContractRuntimeMetrics::new(registry)?);
+        let syncer = Arc::new(CommitSyncer::new(
+            Arc::clone(&environment),
+            commit_sync,
+            metrics.sync.clone(),
+        ));
+
         let global_state = LmdbGlobalState::empty(environment, trie_store, protocol_data_store)?;
         let engine_config = EngineConfig::new();
 
         let engine_state = Arc::new(EngineState::new(global_state, engine_config));
 
-        let metrics = Arc::new(ContractRuntimeMetrics::new(registry)?);
         Ok(ContractRuntime {
             engine_state,
             metrics,
+            syncer,
         })
     }
 
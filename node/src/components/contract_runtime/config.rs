@@ -1,11 +1,40 @@
+use std::time::Duration;
+
 use datasize::DataSize;
 use serde::{Deserialize, Serialize};
 
 use casper_execution_engine::shared::utils;
 
+use crate::utils::ConfigValidator;
+
 const DEFAULT_MAX_GLOBAL_STATE_SIZE: usize = 805_306_368_000; // 750 GiB
 const DEFAULT_MAX_READERS: u32 = 512;
 
+/// When commits to the global state store are flushed to disk, see `durability`.
+#[derive(Clone, Copy, DataSize, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub enum CommitSync {
+    /// Every commit is flushed before it completes.
+    EveryBlock,
+    /// Commits are flushed once `blocks` of them have not been flushed yet.
+    EveryNBlocks {
+        /// The number of block commits flushed together.
+        blocks: u64,
+    },
+    /// Commits are flushed once `interval` has passed since the last flush.
+    Timed {
+        /// The minimum time between two flushes, in milliseconds.
+        #[serde(with = "crate::utils::milliseconds")]
+        interval: Duration,
+    },
+}
+
+impl Default for CommitSync {
+    fn default() -> Self {
+        CommitSync::EveryBlock
+    }
+}
+
 /// Contract runtime configuration.
 #[derive(Clone, Copy, DataSize, Debug, Deserialize, Serialize)]
 // Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
@@ -21,6 +50,10 @@ pub struct Config {
     ///
     /// Defaults to 512.
     max_readers: Option<u32>,
+    /// When commits to the global state store are flushed to disk.
+    ///
+    /// Defaults to flushing every block.
+    commit_sync: Option<CommitSync>,
 }
 
 impl Config {
@@ -35,6 +68,25 @@ impl Config {
     pub(crate) fn max_readers(&self) -> u32 {
         self.max_readers.unwrap_or(DEFAULT_MAX_READERS)
     }
+
+    pub(crate) fn commit_sync(&self) -> CommitSync {
+        self.commit_sync.unwrap_or_default()
+    }
+
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        validator.ensure_non_zero("max_global_state_size", self.max_global_state_size());
+        validator.ensure_non_zero("max_readers", self.max_readers());
+        match self.commit_sync() {
+            CommitSync::EveryBlock => (),
+            CommitSync::EveryNBlocks { blocks } => {
+                validator.ensure_non_zero("commit_sync.blocks", blocks)
+            }
+            CommitSync::Timed { interval } => {
+                validator.ensure_non_zero("commit_sync.interval", interval)
+            }
+        }
+    }
 }
 
 impl Default for Config {
@@ -42,6 +94,7 @@ impl Default for Config {
         Config {
             max_global_state_size: Some(DEFAULT_MAX_GLOBAL_STATE_SIZE),
             max_readers: Some(DEFAULT_MAX_READERS),
+            commit_sync: Some(CommitSync::default()),
         }
     }
 }
@@ -0,0 +1,386 @@
+//! Conformance fixtures for deterministic execution.
+//!
+//! A fixture is a JSON file containing a deploy and the result of executing it, see `Fixture`.
+//! Running a directory of fixtures executes each deploy against the global state right after
+//! genesis and compares the results, so that changes to the execution engine can be checked
+//! against a frozen corpus of fixtures created with a previous version.
+//!
+//! Every fixture is executed on its own, with the deploy's timestamp as the block time and its
+//! account as the proposer. Deploys must therefore be signed by accounts created at genesis.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use prometheus::Registry;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use casper_execution_engine::{
+    core::engine_state::{
+        deploy_item::DeployItem, execute_request::ExecuteRequest, genesis::GenesisResult,
+    },
+    shared::newtypes::{Blake2bHash, CorrelationId},
+};
+use casper_types::{ExecutionResult, ProtocolVersion, Transform};
+
+use super::{Config, ContractRuntime};
+use crate::{
+    components::chainspec_loader::{self, Chainspec},
+    types::Deploy,
+    utils::{Loadable, WithDir},
+    StorageConfig,
+};
+
+/// Error running conformance fixtures.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Error loading the chainspec.
+    #[error("error loading chainspec: {0}")]
+    LoadChainspec(chainspec_loader::Error),
+
+    /// Error creating the temporary directory for the global state.
+    #[error("error creating temporary directory: {0}")]
+    TempDir(io::Error),
+
+    /// Error setting up the contract runtime.
+    #[error(transparent)]
+    ContractRuntime(#[from] super::ConfigError),
+
+    /// Genesis did not succeed.
+    #[error("genesis failed: {0}")]
+    Genesis(String),
+
+    /// Error listing the fixtures.
+    #[error("error reading fixtures from {path}: {error}")]
+    ReadFixtures {
+        /// The directory path.
+        path: String,
+        /// The IO error.
+        error: io::Error,
+    },
+
+    /// Error reading a fixture file.
+    #[error("error reading fixture {path}: {error}")]
+    ReadFixture {
+        /// The file path.
+        path: String,
+        /// The IO error.
+        error: io::Error,
+    },
+
+    /// Error decoding a fixture file.
+    #[error("error decoding fixture {path}: {error}")]
+    DecodeFixture {
+        /// The file path.
+        path: String,
+        /// The JSON error.
+        error: serde_json::Error,
+    },
+
+    /// Error writing a fixture file.
+    #[error("error writing fixture {path}: {error}")]
+    WriteFixture {
+        /// The file path.
+        path: String,
+        /// The IO error.
+        error: io::Error,
+    },
+}
+
+/// A conformance fixture, as stored in its file.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct Fixture {
+    /// What the fixture covers, informational only.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    description: String,
+    /// The deploy to execute.
+    deploy: Deploy,
+    /// The expected result, including the transforms and the cost.
+    ///
+    /// May be left out for new fixtures, to be filled in by running with `update`.
+    #[serde(default)]
+    expected: Option<ExecutionResult>,
+}
+
+/// The outcome of running a single fixture.
+#[derive(Debug)]
+pub struct FixtureOutcome {
+    /// The fixture's file name.
+    pub name: String,
+    /// The differences between the expected and the actual result, empty if it conforms.
+    pub diffs: Vec<String>,
+    /// Whether the fixture's expected result has been replaced by the actual one.
+    pub updated: bool,
+}
+
+/// Runs all fixtures, i.e. JSON files, in `fixtures_dir` against the global state resulting from
+/// genesis with the chainspec at `chainspec_path`.
+///
+/// If `update` is set, the expected result of every fixture which does not conform is replaced by
+/// the actual one.
+pub fn run_fixtures(
+    chainspec_path: &Path,
+    fixtures_dir: &Path,
+    update: bool,
+) -> Result<Vec<FixtureOutcome>, Error> {
+    let chainspec = Chainspec::from_file(chainspec_path).map_err(Error::LoadChainspec)?;
+    let protocol_version = ProtocolVersion::from_parts(
+        chainspec.genesis.protocol_version.major as u32,
+        chainspec.genesis.protocol_version.minor as u32,
+        chainspec.genesis.protocol_version.patch as u32,
+    );
+
+    let tempdir = tempfile::tempdir().map_err(Error::TempDir)?;
+    let mut storage_config = StorageConfig::default();
+    storage_config.path = tempdir.path().to_path_buf();
+    let contract_runtime = ContractRuntime::new(
+        WithDir::new(tempdir.path(), storage_config),
+        &Config::default(),
+        &Registry::new(),
+    )?;
+    let genesis_state_hash = match contract_runtime.commit_genesis(Box::new(chainspec)) {
+        Ok(GenesisResult::Success {
+            post_state_hash, ..
+        }) => post_state_hash,
+        Ok(result) => return Err(Error::Genesis(result.to_string())),
+        Err(error) => return Err(Error::Genesis(error.to_string())),
+    };
+
+    let mut outcomes = Vec::new();
+    for path in fixture_paths(fixtures_dir)? {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut fixture = read_fixture(&path)?;
+        let diffs = match execute(
+            &contract_runtime,
+            genesis_state_hash,
+            protocol_version,
+            &fixture.deploy,
+        ) {
+            Ok(actual) => {
+                let diffs = match &fixture.expected {
+                    Some(expected) => diff(expected, &actual),
+                    None => vec!["no expected result".to_string()],
+                };
+                if update && !diffs.is_empty() {
+                    fixture.expected = Some(actual);
+                    write_fixture(&path, &fixture)?;
+                    outcomes.push(FixtureOutcome {
+                        name,
+                        diffs,
+                        updated: true,
+                    });
+                    continue;
+                }
+                diffs
+            }
+            Err(error) => vec![error],
+        };
+        outcomes.push(FixtureOutcome {
+            name,
+            diffs,
+            updated: false,
+        });
+    }
+    Ok(outcomes)
+}
+
+/// Returns the paths of all JSON files in `dir`, sorted by name.
+fn fixture_paths(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let read_error = |error| Error::ReadFixtures {
+        path: dir.display().to_string(),
+        error,
+    };
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).map_err(read_error)? {
+        let path = entry.map_err(read_error)?.path();
+        if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn read_fixture(path: &Path) -> Result<Fixture, Error> {
+    let data = fs::read(path).map_err(|error| Error::ReadFixture {
+        path: path.display().to_string(),
+        error,
+    })?;
+    serde_json::from_slice(&data).map_err(|error| Error::DecodeFixture {
+        path: path.display().to_string(),
+        error,
+    })
+}
+
+fn write_fixture(path: &Path, fixture: &Fixture) -> Result<(), Error> {
+    let mut data = serde_json::to_vec_pretty(fixture).expect("should serialize fixture");
+    data.push(b'\n');
+    fs::write(path, data).map_err(|error| Error::WriteFixture {
+        path: path.display().to_string(),
+        error,
+    })
+}
+
+/// Executes a deploy against the given state, returning the result or why there is none.
+fn execute(
+    contract_runtime: &ContractRuntime,
+    state_hash: Blake2bHash,
+    protocol_version: ProtocolVersion,
+    deploy: &Deploy,
+) -> Result<ExecutionResult, String> {
+    let execute_request = ExecuteRequest::new(
+        state_hash,
+        deploy.header().timestamp().millis(),
+        vec![Ok(DeployItem::from(deploy.clone()))],
+        protocol_version,
+        *deploy.header().account(),
+    );
+    let results = contract_runtime
+        .engine_state
+        .run_execute(CorrelationId::new(), execute_request)
+        .map_err(|error| format!("execution failed: {:?}", error))?;
+    match results.front() {
+        Some(result) if results.len() == 1 => Ok(ExecutionResult::from(result)),
+        _ => Err(format!(
+            "expected one execution result, got {}",
+            results.len()
+        )),
+    }
+}
+
+/// Returns the differences between an expected and an actual execution result.
+fn diff(expected: &ExecutionResult, actual: &ExecutionResult) -> Vec<String> {
+    if expected == actual {
+        return Vec::new();
+    }
+    let (expected_parts, actual_parts) = (Parts::of(expected), Parts::of(actual));
+    let mut diffs = Vec::new();
+    if expected_parts.error_message != actual_parts.error_message {
+        diffs.push(format!(
+            "error: expected {:?}, got {:?}",
+            expected_parts.error_message, actual_parts.error_message
+        ));
+    }
+    if expected_parts.cost != actual_parts.cost {
+        diffs.push(format!(
+            "cost: expected {}, got {}",
+            expected_parts.cost, actual_parts.cost
+        ));
+    }
+    for (key, expected_transform) in &expected_parts.transforms {
+        match actual_parts.transforms.get(key) {
+            None => diffs.push(format!(
+                "{}: expected {:?}, got none",
+                key, expected_transform
+            )),
+            Some(actual_transform) if actual_transform != expected_transform => {
+                diffs.push(format!(
+                    "{}: expected {:?}, got {:?}",
+                    key, expected_transform, actual_transform
+                ))
+            }
+            Some(_) => (),
+        }
+    }
+    for (key, actual_transform) in &actual_parts.transforms {
+        if !expected_parts.transforms.contains_key(key) {
+            diffs.push(format!(
+                "{}: expected none, got {:?}",
+                key, actual_transform
+            ));
+        }
+    }
+    // Any other difference, e.g. in the order of the transforms, the operations or the transfers.
+    if diffs.is_empty() {
+        diffs.push(format!("expected {:?}, got {:?}", expected, actual));
+    }
+    diffs
+}
+
+/// The parts of an execution result which are compared individually.
+struct Parts<'a> {
+    error_message: Option<&'a str>,
+    cost: String,
+    transforms: BTreeMap<&'a str, &'a Transform>,
+}
+
+impl<'a> Parts<'a> {
+    fn of(result: &'a ExecutionResult) -> Self {
+        let (effect, cost, error_message) = match result {
+            ExecutionResult::Failure {
+                effect,
+                cost,
+                error_message,
+                ..
+            } => (effect, cost, Some(error_message.as_str())),
+            ExecutionResult::Success { effect, cost, .. } => (effect, cost, None),
+        };
+        Parts {
+            error_message,
+            cost: cost.to_string(),
+            transforms: effect
+                .transforms
+                .iter()
+                .map(|entry| (entry.key.as_str(), &entry.transform))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{ExecutionEffect, TransformEntry, U512};
+
+    use super::*;
+
+    fn result(cost: u64, transforms: Vec<(&str, Transform)>) -> ExecutionResult {
+        ExecutionResult::Success {
+            effect: ExecutionEffect {
+                operations: Vec::new(),
+                transforms: transforms
+                    .into_iter()
+                    .map(|(key, transform)| TransformEntry {
+                        key: key.to_string(),
+                        transform,
+                    })
+                    .collect(),
+            },
+            transfers: Vec::new(),
+            events: Vec::new(),
+            cost: U512::from(cost),
+        }
+    }
+
+    #[test]
+    fn should_report_differing_cost_and_transforms() {
+        let expected = result(
+            100,
+            vec![
+                ("hash-01", Transform::Identity),
+                ("hash-02", Transform::AddInt32(1)),
+            ],
+        );
+        assert!(diff(&expected, &expected).is_empty());
+
+        let actual = result(
+            120,
+            vec![
+                ("hash-02", Transform::AddInt32(2)),
+                ("hash-03", Transform::Identity),
+            ],
+        );
+        let diffs = diff(&expected, &actual);
+        assert_eq!(diffs.len(), 4);
+        assert_eq!(diffs[0], "cost: expected 100, got 120");
+        assert!(diffs[1].starts_with("hash-01: expected Identity, got none"));
+        assert!(diffs[2].starts_with("hash-02: expected AddInt32(1), got AddInt32(2)"));
+        assert!(diffs[3].starts_with("hash-03: expected none"));
+    }
+}
@@ -0,0 +1,151 @@
+//! Flushing of commits to the global state store according to the `CommitSync` policy.
+//!
+//! With the default policy, `CommitSync::EveryBlock`, LMDB flushes every transaction to disk
+//! before it completes. With the other policies, the store is opened with `NO_SYNC`, and commits
+//! only reach the operating system's page cache until the next flush, which writes all of them to
+//! disk at once.
+//!
+//! Crash recovery contract: LMDB never corrupts the store by skipping flushes, as long as the
+//! file system preserves the order of writes. If the process crashes, nothing is lost, as the page
+//! cache survives it. If the operating system crashes or the machine loses power, the store reverts
+//! to its state at the last flush: the commits of fewer than `blocks` blocks, respectively those
+//! made during the last `interval` before the latest commit, are lost. The blocks themselves are
+//! always flushed by the storage component, so after restarting, the global state of the latest
+//! blocks may be missing; it is fetched from peers while joining, like any other missing state.
+//!
+//! Genesis and upgrades are flushed right away regardless of the policy, as is everything not yet
+//! flushed when the contract runtime is dropped on shutdown.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use lmdb::EnvironmentFlags;
+use prometheus::Histogram;
+use tracing::error;
+
+use casper_execution_engine::storage::transaction_source::lmdb::LmdbEnvironment;
+
+use super::config::CommitSync;
+
+/// Returns the flags to open the global state store with for `policy`.
+pub(super) fn environment_flags(policy: CommitSync) -> EnvironmentFlags {
+    match policy {
+        CommitSync::EveryBlock => EnvironmentFlags::empty(),
+        CommitSync::EveryNBlocks { .. } | CommitSync::Timed { .. } => EnvironmentFlags::NO_SYNC,
+    }
+}
+
+/// Returns whether the commits not flushed yet are due to be flushed.
+fn is_due(policy: CommitSync, unsynced_commits: u64, since_sync: Duration) -> bool {
+    match policy {
+        CommitSync::EveryBlock => false,
+        CommitSync::EveryNBlocks { blocks } => unsynced_commits >= blocks,
+        CommitSync::Timed { interval } => unsynced_commits > 0 && since_sync >= interval,
+    }
+}
+
+#[derive(Debug)]
+struct SyncState {
+    unsynced_commits: u64,
+    last_sync: Instant,
+}
+
+/// Flushes the commits to the global state store.
+#[derive(Debug)]
+pub(super) struct CommitSyncer {
+    environment: Arc<LmdbEnvironment>,
+    policy: CommitSync,
+    state: Mutex<SyncState>,
+    sync_duration: Histogram,
+}
+
+impl CommitSyncer {
+    pub(super) fn new(
+        environment: Arc<LmdbEnvironment>,
+        policy: CommitSync,
+        sync_duration: Histogram,
+    ) -> Self {
+        CommitSyncer {
+            environment,
+            policy,
+            state: Mutex::new(SyncState {
+                unsynced_commits: 0,
+                last_sync: Instant::now(),
+            }),
+            sync_duration,
+        }
+    }
+
+    /// Records the commit of a block, flushing it along with all earlier ones if due.
+    ///
+    /// Blocks the calling thread while flushing.
+    pub(super) fn block_committed(&self) {
+        let mut state = self.state.lock().expect("commit sync lock poisoned");
+        state.unsynced_commits += 1;
+        if is_due(
+            self.policy,
+            state.unsynced_commits,
+            state.last_sync.elapsed(),
+        ) {
+            self.sync(&mut state);
+        }
+    }
+
+    /// Flushes all commits right away, unless every commit is flushed anyway.
+    ///
+    /// Blocks the calling thread while flushing.
+    pub(super) fn sync_now(&self) {
+        if self.policy != CommitSync::EveryBlock {
+            let mut state = self.state.lock().expect("commit sync lock poisoned");
+            self.sync(&mut state);
+        }
+    }
+
+    fn sync(&self, state: &mut SyncState) {
+        let start = Instant::now();
+        match self.environment.env().sync(true) {
+            Ok(()) => {
+                state.unsynced_commits = 0;
+                state.last_sync = Instant::now();
+            }
+            // The commits are retried with the next flush.
+            Err(error) => error!(
+                %error,
+                unsynced_commits = state.unsynced_commits,
+                "failed to flush global state to disk"
+            ),
+        }
+        self.sync_duration.observe(start.elapsed().as_secs_f64());
+    }
+}
+
+impl Drop for CommitSyncer {
+    fn drop(&mut self) {
+        self.sync_now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_flush_when_due() {
+        let second = Duration::from_secs(1);
+        assert!(!is_due(CommitSync::EveryBlock, 100, second));
+
+        let every_3 = CommitSync::EveryNBlocks { blocks: 3 };
+        assert!(!is_due(every_3, 2, second));
+        assert!(is_due(every_3, 3, Duration::from_secs(0)));
+
+        let timed = CommitSync::Timed { interval: second };
+        assert!(!is_due(timed, 5, Duration::from_millis(999)));
+        assert!(is_due(timed, 1, second));
+        assert!(!is_due(timed, 0, second * 10));
+
+        assert!(environment_flags(CommitSync::EveryBlock).is_empty());
+        assert!(environment_flags(timed).contains(EnvironmentFlags::NO_SYNC));
+    }
+}
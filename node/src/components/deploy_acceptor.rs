@@ -1,12 +1,13 @@
 mod config;
 mod event;
+mod policy;
 
 use std::{collections::HashMap, convert::Infallible, fmt::Debug};
 
 use semver::Version;
 use serde::Serialize;
 use thiserror::Error;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use super::chainspec_loader::DeployConfig;
 use crate::{
@@ -16,7 +17,7 @@ use crate::{
         requests::{ContractRuntimeRequest, StorageRequest},
         EffectBuilder, EffectExt, Effects,
     },
-    types::{Deploy, DeployValidationFailure, NodeId},
+    types::{Deploy, DeployValidationFailure, NodeId, TimeDiff, Timestamp},
     utils::Source,
     NodeRng,
 };
@@ -25,6 +26,7 @@ use casper_types::Key;
 use crate::effect::Responder;
 pub use config::Config;
 pub use event::Event;
+use policy::DeployPolicy;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -37,6 +39,17 @@ pub enum Error {
     /// A deploy was sent from account with insufficient balance.
     #[error("insufficient balance")]
     InsufficientBalance,
+    /// Deploys from clients are not accepted while the node is running out of disk space.
+    #[error("deploy acceptance paused, the node is running out of disk space")]
+    AcceptancePaused,
+    /// A deploy from a client violates a policy configured by the node operator.
+    #[error("deploy rejected by {policy} policy: {reason}")]
+    RejectedByPolicy {
+        /// The name of the violated policy.
+        policy: &'static str,
+        /// Why the deploy violates the policy.
+        reason: String,
+    },
 }
 
 /// A helper trait constraining `DeployAcceptor` compatible reactor events.
@@ -62,6 +75,7 @@ impl<REv> ReactorEventT for REv where
 pub struct DeployAcceptorChainspec {
     chain_name: String,
     deploy_config: DeployConfig,
+    protocol_version: Version,
 }
 
 impl From<Chainspec> for DeployAcceptorChainspec {
@@ -69,7 +83,44 @@ impl From<Chainspec> for DeployAcceptorChainspec {
         DeployAcceptorChainspec {
             chain_name: c.genesis.name,
             deploy_config: c.genesis.deploy_config,
+            protocol_version: c.latest_protocol_version(),
+        }
+    }
+}
+
+/// Limits the number of deploys each peer may submit over the network within a fixed window.
+#[derive(Debug)]
+struct PeerSubmissionLimiter {
+    max_submissions: u32,
+    window: TimeDiff,
+    /// The start of the current window and the number of submissions within it, per peer.
+    submissions: HashMap<NodeId, (Timestamp, u32)>,
+}
+
+impl PeerSubmissionLimiter {
+    fn new(max_submissions: u32, window: TimeDiff) -> Self {
+        PeerSubmissionLimiter {
+            max_submissions,
+            window,
+            submissions: HashMap::new(),
+        }
+    }
+
+    /// Records a submission by `peer` at `now`, returning `false` if it exceeds the peer's limit.
+    fn try_submit(&mut self, peer: NodeId, now: Timestamp) -> bool {
+        if self.max_submissions == 0 {
+            return false;
+        }
+        // Forget peers whose window has ended, so that disconnected peers don't accumulate.
+        let window = self.window;
+        self.submissions
+            .retain(|_, (window_start, _)| now.saturating_sub(*window_start) < window);
+        let (_, count) = self.submissions.entry(peer).or_insert((now, 0));
+        if *count >= self.max_submissions {
+            return false;
         }
+        *count += 1;
+        true
     }
 }
 
@@ -82,16 +133,59 @@ impl From<Chainspec> for DeployAcceptorChainspec {
 pub struct DeployAcceptor {
     cached_deploy_configs: HashMap<Version, DeployAcceptorChainspec>,
     verify_accounts: bool,
+    peer_submissions: PeerSubmissionLimiter,
+    /// Whether deploys from clients are rejected.
+    paused: bool,
+    /// The policies deploys from clients are checked against.
+    policies: Vec<Box<dyn DeployPolicy>>,
 }
 
 impl DeployAcceptor {
     pub(crate) fn new(config: Config) -> Self {
+        let policies = policy::from_config(&config);
         DeployAcceptor {
             cached_deploy_configs: HashMap::new(),
             verify_accounts: config.verify_accounts(),
+            peer_submissions: PeerSubmissionLimiter::new(
+                config.max_peer_submissions(),
+                config.peer_submission_window(),
+            ),
+            paused: false,
+            policies,
         }
     }
 
+    /// Returns the first configured policy `deploy` violates, if any.
+    fn policy_violation(&self, deploy: &Deploy) -> Option<Error> {
+        self.policies.iter().find_map(|policy| {
+            policy
+                .check(deploy)
+                .err()
+                .map(|reason| Error::RejectedByPolicy {
+                    policy: policy.name(),
+                    reason,
+                })
+        })
+    }
+
+    /// Handles a `Deploy` submitted by a peer over the network.
+    ///
+    /// Unlike deploys fetched or gossiped from peers, it is validated as a client's deploy would
+    /// be, including the account verification, subject to the peer's submission limit.
+    fn submitted_by_peer<REv: ReactorEventT>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        deploy: Box<Deploy>,
+        sender: NodeId,
+    ) -> Effects<Event> {
+        if !self.peer_submissions.try_submit(sender, Timestamp::now()) {
+            warn!(deploy_hash = %deploy.id(), %sender, "dropping deploy submission from peer");
+            return Effects::new();
+        }
+        info!(deploy_hash = %deploy.id(), %sender, "received deploy submission from peer");
+        self.accept(effect_builder, deploy, Source::Client, None)
+    }
+
     /// Handles receiving a new `Deploy` from a peer or client.
     /// In the case of a peer, there should be no responder and the variant should be `None`
     /// In the case of a client, there should be a responder to communicate the validity of the
@@ -103,6 +197,14 @@ impl DeployAcceptor {
         source: Source<NodeId>,
         responder: Option<Responder<Result<(), Error>>>,
     ) -> Effects<Event> {
+        // Deploys from peers are still accepted, as they may be needed to validate blocks.
+        if self.paused && source.from_client() {
+            debug!(deploy_hash = %deploy.id(), "rejecting deploy, acceptance is paused");
+            return match responder {
+                Some(responder) => responder.respond(Err(Error::AcceptancePaused)).ignore(),
+                None => Effects::new(),
+            };
+        }
         // TODO - where to get version from?
         let chainspec_version = Version::new(1, 0, 0);
         let cached_config = self.cached_deploy_configs.get(&chainspec_version).cloned();
@@ -201,8 +303,13 @@ impl DeployAcceptor {
     ) -> Effects<Event> {
         let mut cloned_deploy = deploy.clone();
         let mut effects = Effects::new();
-        let is_acceptable =
-            cloned_deploy.is_acceptable(chainspec.chain_name, chainspec.deploy_config);
+        let is_acceptable = cloned_deploy
+            .is_acceptable(
+                chainspec.chain_name,
+                chainspec.deploy_config,
+                Timestamp::now(),
+            )
+            .and_then(|()| cloned_deploy.check_protocol_version(&chainspec.protocol_version));
         if let Err(error) = is_acceptable {
             // The client has submitted an invalid deploy. Return an error to the RPC component via
             // the responder.
@@ -217,6 +324,22 @@ impl DeployAcceptor {
             return effects;
         }
 
+        // Deploys from peers are exempt, as they may be needed to validate blocks.
+        if source.from_client() {
+            if let Some(error) = self.policy_violation(&deploy) {
+                info!(deploy_hash = %deploy.id(), %error, "rejecting deploy");
+                if let Some(responder) = maybe_responder {
+                    effects.extend(responder.respond(Err(error)).ignore());
+                }
+                effects.extend(
+                    effect_builder
+                        .announce_invalid_deploy(deploy, source)
+                        .ignore(),
+                );
+                return effects;
+            }
+        }
+
         let account_key = deploy.header().account().to_account_hash().into();
 
         // skip account verification if deploy not received from client or node is configured to
@@ -262,9 +385,13 @@ impl DeployAcceptor {
         is_new: bool,
     ) -> Effects<Event> {
         if is_new {
-            return effect_builder
-                .announce_new_deploy_accepted(deploy, source)
-                .ignore();
+            let mut effects = effect_builder.publish((*deploy).clone()).ignore();
+            effects.extend(
+                effect_builder
+                    .announce_new_deploy_accepted(deploy, source)
+                    .ignore(),
+            );
+            return effects;
         }
         Effects::new()
     }
@@ -287,6 +414,16 @@ impl<REv: ReactorEventT> Component<REv> for DeployAcceptor {
                 source,
                 responder,
             } => self.accept(effect_builder, deploy, source, responder),
+            Event::SubmittedByPeer { deploy, sender } => {
+                self.submitted_by_peer(effect_builder, deploy, sender)
+            }
+            Event::SetPaused { paused } => {
+                if paused != self.paused {
+                    info!(paused, "changing whether deploys from clients are accepted");
+                }
+                self.paused = paused;
+                Effects::new()
+            }
             Event::GetChainspecResult {
                 deploy,
                 source,
@@ -324,3 +461,31 @@ impl<REv: ReactorEventT> Component<REv> for DeployAcceptor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::TestRng;
+
+    #[test]
+    fn should_limit_peer_submissions_per_window() {
+        let mut rng = TestRng::new();
+        let (peer, other_peer) = (NodeId::random(&mut rng), NodeId::random(&mut rng));
+        let window = TimeDiff::from(Duration::from_secs(60));
+        let mut limiter = PeerSubmissionLimiter::new(2, window);
+
+        let start = Timestamp::now();
+        assert!(limiter.try_submit(peer.clone(), start));
+        assert!(limiter.try_submit(peer.clone(), start));
+        assert!(!limiter.try_submit(peer.clone(), start));
+        // Other peers have their own limit.
+        assert!(limiter.try_submit(other_peer, start));
+
+        assert!(limiter.try_submit(peer.clone(), start + window));
+
+        let mut disabled = PeerSubmissionLimiter::new(0, window);
+        assert!(!disabled.try_submit(peer, start));
+    }
+}
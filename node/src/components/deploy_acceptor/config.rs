@@ -1,28 +1,94 @@
+use std::str::FromStr;
+
+use casper_types::{AsymmetricType, PublicKey};
 use datasize::DataSize;
 use serde::{Deserialize, Serialize};
 
+use super::policy;
+use crate::{types::TimeDiff, utils::ConfigValidator};
+
+const DEFAULT_MAX_PEER_SUBMISSIONS: u32 = 20;
+const DEFAULT_PEER_SUBMISSION_WINDOW: &str = "1minute";
+
 /// Configuration options for fetching.
-#[derive(Copy, Clone, DataSize, Debug, Deserialize, Serialize)]
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
 pub struct Config {
     verify_accounts: bool,
+    /// The maximum number of deploys a single peer may submit over the network per
+    /// `peer_submission_window`. Zero disables submissions from peers.
+    max_peer_submissions: u32,
+    /// The window over which submissions from a peer are counted.
+    peer_submission_window: TimeDiff,
+    /// The minimum payment amount in motes of deploys from clients. Zero disables the check.
+    pub(super) minimum_payment: u64,
+    /// Hex-encoded hashes of contracts and contract packages deploys from clients must not call.
+    pub(super) banned_contracts: Vec<String>,
+    /// Hex-encoded public keys of the only accounts deploys from clients are accepted from. Empty
+    /// to accept deploys from any account.
+    pub(super) allowed_accounts: Vec<String>,
 }
 
 impl Config {
     /// Constructor for deploy_acceptor config.
     pub fn new(verify_accounts: bool) -> Self {
-        Config { verify_accounts }
+        Config {
+            verify_accounts,
+            ..Config::default()
+        }
     }
 
     /// Get verify_accounts setting.
     pub(crate) fn verify_accounts(&self) -> bool {
         self.verify_accounts
     }
+
+    /// Get max_peer_submissions setting.
+    pub(crate) fn max_peer_submissions(&self) -> u32 {
+        self.max_peer_submissions
+    }
+
+    /// Get peer_submission_window setting.
+    pub(crate) fn peer_submission_window(&self) -> TimeDiff {
+        self.peer_submission_window
+    }
+
+    /// Returns whether any deploy policy is enabled.
+    pub(crate) fn has_policies(&self) -> bool {
+        self.minimum_payment > 0
+            || !self.banned_contracts.is_empty()
+            || !self.allowed_accounts.is_empty()
+    }
+
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        for hex in &self.banned_contracts {
+            if let Err(error) = policy::parse_contract_hash(hex) {
+                validator.violation(
+                    "banned_contracts",
+                    format!("invalid contract hash {}: {}", hex, error),
+                );
+            }
+        }
+        for hex in &self.allowed_accounts {
+            if let Err(error) = PublicKey::from_hex(hex) {
+                validator.violation(
+                    "allowed_accounts",
+                    format!("invalid public key {}: {}", hex, error),
+                );
+            }
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
             verify_accounts: true,
+            max_peer_submissions: DEFAULT_MAX_PEER_SUBMISSIONS,
+            peer_submission_window: TimeDiff::from_str(DEFAULT_PEER_SUBMISSION_WINDOW).unwrap(),
+            minimum_payment: 0,
+            banned_contracts: Vec::new(),
+            allowed_accounts: Vec::new(),
         }
     }
 }
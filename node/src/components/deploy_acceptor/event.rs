@@ -7,7 +7,7 @@ use super::{DeployAcceptorChainspec, Source};
 use crate::{
     components::deploy_acceptor::Error,
     effect::{announcements::RpcServerAnnouncement, Responder},
-    types::{Deploy, NodeId},
+    types::{Deploy, DeployHash, NodeId},
 };
 use casper_types::Key;
 
@@ -20,6 +20,10 @@ pub enum Event {
         source: Source<NodeId>,
         responder: Option<Responder<Result<(), Error>>>,
     },
+    /// A `Deploy` submitted by a peer over the network, as a client would through the RPC server.
+    SubmittedByPeer { deploy: Box<Deploy>, sender: NodeId },
+    /// Deploys from clients are to be rejected, or accepted again.
+    SetPaused { paused: bool },
     /// The result of getting the chainspec from the storage component.
     GetChainspecResult {
         deploy: Box<Deploy>,
@@ -44,6 +48,20 @@ pub enum Event {
     },
 }
 
+impl Event {
+    /// Returns the hash of the deploy the event concerns, if any.
+    pub(crate) fn deploy_hash(&self) -> Option<DeployHash> {
+        match self {
+            Event::Accept { deploy, .. }
+            | Event::SubmittedByPeer { deploy, .. }
+            | Event::GetChainspecResult { deploy, .. }
+            | Event::PutToStorageResult { deploy, .. }
+            | Event::AccountVerificationResult { deploy, .. } => Some(*deploy.id()),
+            Event::SetPaused { .. } => None,
+        }
+    }
+}
+
 impl From<RpcServerAnnouncement> for Event {
     fn from(announcement: RpcServerAnnouncement) -> Self {
         match announcement {
@@ -62,6 +80,10 @@ impl Display for Event {
             Event::Accept { deploy, source, .. } => {
                 write!(formatter, "accept {} from {}", deploy.id(), source)
             }
+            Event::SubmittedByPeer { deploy, sender } => {
+                write!(formatter, "{} submitted by {}", deploy.id(), sender)
+            }
+            Event::SetPaused { paused } => write!(formatter, "set paused: {}", paused),
             Event::GetChainspecResult {
                 chainspec_version,
                 maybe_chainspec,
@@ -0,0 +1,247 @@
+//! Policies operators can enable on the deploys clients submit.
+//!
+//! Policies are checked after a deploy passed validation against the chainspec, before it is
+//! stored and gossiped. They only apply to deploys from clients and peer submissions: deploys
+//! gossiped or fetched from peers are accepted regardless, as they may be needed to validate
+//! blocks proposed by validators with other policies.
+
+use std::{collections::HashSet, convert::TryInto, fmt::Debug};
+
+use tracing::warn;
+
+use casper_execution_engine::core::engine_state::executable_deploy_item::ExecutableDeployItem;
+use casper_types::{standard_payment::ARG_AMOUNT, AsymmetricType, PublicKey, U512};
+
+use super::Config;
+use crate::types::Deploy;
+
+/// A check of the deploys clients submit.
+pub(crate) trait DeployPolicy: Debug + Send {
+    /// Returns the name of the policy, reported along with rejected deploys.
+    fn name(&self) -> &'static str;
+
+    /// Returns why `deploy` violates the policy, if it does.
+    fn check(&self, deploy: &Deploy) -> Result<(), String>;
+}
+
+/// Rejects deploys paying less than a minimum amount.
+///
+/// Native transfers are exempt, as their cost is fixed by the chainspec.
+#[derive(Debug)]
+struct MinimumPayment {
+    amount: U512,
+}
+
+impl DeployPolicy for MinimumPayment {
+    fn name(&self) -> &'static str {
+        "minimum_payment"
+    }
+
+    fn check(&self, deploy: &Deploy) -> Result<(), String> {
+        if deploy.session().is_transfer() {
+            return Ok(());
+        }
+        let amount = deploy
+            .payment()
+            .args()
+            .get(ARG_AMOUNT)
+            .and_then(|value| value.clone().into_t::<U512>().ok())
+            .ok_or_else(|| "payment has no amount".to_string())?;
+        if amount < self.amount {
+            return Err(format!(
+                "payment of {} is below minimum of {}",
+                amount, self.amount
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Rejects deploys calling one of a set of stored contracts or contract packages by hash.
+///
+/// Contracts called by name cannot be resolved without reading global state, so they are not
+/// checked.
+#[derive(Debug)]
+struct BannedContracts {
+    hashes: HashSet<[u8; 32]>,
+}
+
+impl BannedContracts {
+    /// Returns the hash of the contract or contract package `item` calls by hash, if any.
+    fn called_hash(item: &ExecutableDeployItem) -> Option<[u8; 32]> {
+        match item {
+            ExecutableDeployItem::StoredContractByHash { hash, .. } => Some(hash.value()),
+            ExecutableDeployItem::StoredVersionedContractByHash { hash, .. } => Some(hash.value()),
+            _ => None,
+        }
+    }
+}
+
+impl DeployPolicy for BannedContracts {
+    fn name(&self) -> &'static str {
+        "banned_contracts"
+    }
+
+    fn check(&self, deploy: &Deploy) -> Result<(), String> {
+        for item in &[deploy.payment(), deploy.session()] {
+            if let Some(hash) = Self::called_hash(item) {
+                if self.hashes.contains(&hash) {
+                    return Err(format!("calls banned contract {}", hex::encode(hash)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rejects deploys from accounts not on an allowlist, e.g. on private networks.
+#[derive(Debug)]
+struct AccountAllowlist {
+    accounts: HashSet<PublicKey>,
+}
+
+impl DeployPolicy for AccountAllowlist {
+    fn name(&self) -> &'static str {
+        "account_allowlist"
+    }
+
+    fn check(&self, deploy: &Deploy) -> Result<(), String> {
+        let account = deploy.header().account();
+        if self.accounts.contains(account) {
+            Ok(())
+        } else {
+            Err(format!("account {} is not on the allowlist", account))
+        }
+    }
+}
+
+/// Parses a hex-encoded 32 byte contract or contract package hash.
+pub(super) fn parse_contract_hash(hex: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(hex).map_err(|error| error.to_string())?;
+    bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| format!("expected 32 bytes, got {}", bytes.len()))
+}
+
+/// Returns the policies enabled in `config`.
+///
+/// Invalid entries are skipped with a warning; they are reported as errors when validating the
+/// config at startup.
+pub(super) fn from_config(config: &Config) -> Vec<Box<dyn DeployPolicy>> {
+    let mut policies: Vec<Box<dyn DeployPolicy>> = Vec::new();
+    if config.minimum_payment > 0 {
+        policies.push(Box::new(MinimumPayment {
+            amount: U512::from(config.minimum_payment),
+        }));
+    }
+    if !config.banned_contracts.is_empty() {
+        let hashes = config
+            .banned_contracts
+            .iter()
+            .filter_map(|hex| match parse_contract_hash(hex) {
+                Ok(hash) => Some(hash),
+                Err(error) => {
+                    warn!(%hex, %error, "ignoring invalid banned contract hash");
+                    None
+                }
+            })
+            .collect();
+        policies.push(Box::new(BannedContracts { hashes }));
+    }
+    if !config.allowed_accounts.is_empty() {
+        let accounts = config
+            .allowed_accounts
+            .iter()
+            .filter_map(|hex| match PublicKey::from_hex(hex) {
+                Ok(public_key) => Some(public_key),
+                Err(error) => {
+                    warn!(%hex, %error, "ignoring invalid allowed account");
+                    None
+                }
+            })
+            .collect();
+        policies.push(Box::new(AccountAllowlist { accounts }));
+    }
+    policies
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use casper_types::{runtime_args, ContractHash, RuntimeArgs, SecretKey};
+
+    use super::*;
+    use crate::{
+        crypto::AsymmetricKeyExt,
+        testing::TestRng,
+        types::{TimeDiff, Timestamp},
+    };
+
+    fn deploy(
+        secret_key: &SecretKey,
+        payment_amount: u64,
+        session: ExecutableDeployItem,
+        rng: &mut TestRng,
+    ) -> Deploy {
+        let payment = ExecutableDeployItem::ModuleBytes {
+            module_bytes: Default::default(),
+            args: runtime_args! { ARG_AMOUNT => U512::from(payment_amount) },
+        };
+        Deploy::new(
+            Timestamp::now(),
+            TimeDiff::from(Duration::from_secs(60)),
+            1,
+            vec![],
+            "casper-example".to_string(),
+            payment,
+            session,
+            secret_key,
+            rng,
+        )
+    }
+
+    #[test]
+    fn should_reject_deploys_violating_policies() {
+        let mut rng = TestRng::new();
+        let secret_key = SecretKey::random(&mut rng);
+        let other_secret_key = SecretKey::random(&mut rng);
+        let banned = [7u8; 32];
+        let config = Config {
+            minimum_payment: 1_000,
+            banned_contracts: vec![hex::encode(banned)],
+            allowed_accounts: vec![PublicKey::from(&secret_key).to_hex()],
+            ..Default::default()
+        };
+        let policies = from_config(&config);
+        let violations = |deploy: &Deploy| -> Vec<&'static str> {
+            policies
+                .iter()
+                .filter(|policy| policy.check(deploy).is_err())
+                .map(|policy| policy.name())
+                .collect()
+        };
+
+        let module_bytes = || ExecutableDeployItem::ModuleBytes {
+            module_bytes: Default::default(),
+            args: RuntimeArgs::new(),
+        };
+        let acceptable = deploy(&secret_key, 1_000, module_bytes(), &mut rng);
+        assert!(violations(&acceptable).is_empty());
+
+        let underpaid = deploy(&secret_key, 999, module_bytes(), &mut rng);
+        assert_eq!(violations(&underpaid), vec!["minimum_payment"]);
+
+        let calls_banned = ExecutableDeployItem::StoredContractByHash {
+            hash: ContractHash::new(banned),
+            entry_point: "call".to_string(),
+            args: RuntimeArgs::new(),
+        };
+        let calls_banned = deploy(&secret_key, 1_000, calls_banned, &mut rng);
+        assert_eq!(violations(&calls_banned), vec!["banned_contracts"]);
+
+        let unknown_account = deploy(&other_secret_key, 1_000, module_bytes(), &mut rng);
+        assert_eq!(violations(&unknown_account), vec!["account_allowlist"]);
+    }
+}
@@ -0,0 +1,283 @@
+//! Disk space monitoring.
+//!
+//! The disk monitor checks the space available on the disk holding the storage periodically and
+//! whenever a block has been added, and degrades the node in stages as the space runs out, so
+//! that the node stops well before LMDB runs out of space in the middle of a write transaction:
+//!
+//! * below `disable_archival_below_mib`, historical blocks are no longer served to peers,
+//! * below `pause_deploys_below_mib`, deploys submitted by clients are rejected as well,
+//! * below `halt_below_mib`, the node shuts down cleanly, flushing the storage.
+//!
+//! Each stage includes the restrictions of the earlier ones. Every change of stage is announced
+//! and reported in status responses; the first two stages are lifted again once space was freed.
+
+mod config;
+
+use std::{
+    convert::Infallible,
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
+
+use datasize::DataSize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::task;
+use tracing::{error, info, warn};
+
+use crate::{
+    components::Component,
+    effect::{
+        announcements::DiskMonitorAnnouncement, EffectBuilder, EffectExt, EffectOptionExt, Effects,
+    },
+    utils, NodeRng,
+};
+pub use config::Config;
+
+/// How far the node is degraded due to a lack of disk space.
+#[derive(
+    Clone, Copy, DataSize, Debug, Deserialize, Eq, JsonSchema, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskSpaceStage {
+    /// Enough disk space is available.
+    Normal,
+    /// Historical blocks are not served to peers.
+    ArchivalDisabled,
+    /// Historical blocks are not served and deploys from clients are rejected.
+    DeploysPaused,
+    /// The node is shutting down.
+    Halted,
+}
+
+impl Default for DiskSpaceStage {
+    fn default() -> Self {
+        DiskSpaceStage::Normal
+    }
+}
+
+impl Display for DiskSpaceStage {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DiskSpaceStage::Normal => "normal",
+            DiskSpaceStage::ArchivalDisabled => "archival disabled",
+            DiskSpaceStage::DeploysPaused => "deploys paused",
+            DiskSpaceStage::Halted => "halted",
+        };
+        formatter.write_str(name)
+    }
+}
+
+/// A disk monitor event.
+#[derive(Debug)]
+pub(crate) enum Event {
+    /// The available disk space is due to be checked.
+    CheckTimer,
+    /// A block has been added, taking up disk space.
+    BlockAdded,
+    /// The space available on the disk holding the storage has been determined.
+    DiskSpace {
+        /// The available space in bytes.
+        available_bytes: u64,
+    },
+}
+
+impl Display for Event {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::CheckTimer => write!(formatter, "check timer"),
+            Event::BlockAdded => write!(formatter, "block added"),
+            Event::DiskSpace { available_bytes } => {
+                write!(formatter, "{} bytes available", available_bytes)
+            }
+        }
+    }
+}
+
+/// A helper trait constraining `DiskMonitor` compatible reactor events.
+pub(crate) trait ReactorEventT: From<Event> + From<DiskMonitorAnnouncement> + Send {}
+
+impl<REv> ReactorEventT for REv where REv: From<Event> + From<DiskMonitorAnnouncement> + Send {}
+
+/// The disk monitor component.
+#[derive(DataSize, Debug)]
+pub(crate) struct DiskMonitor {
+    config: Config,
+    /// Directory of the storage, whose disk is checked.
+    storage_path: PathBuf,
+    /// The stage the node is currently in.
+    stage: DiskSpaceStage,
+}
+
+impl DiskMonitor {
+    /// Creates a new disk monitor, scheduling the first check right away.
+    pub(crate) fn new<REv: From<Event> + Send + 'static>(
+        config: Config,
+        storage_path: &Path,
+        effect_builder: EffectBuilder<REv>,
+    ) -> (Self, Effects<Event>) {
+        let effects = if config.is_enabled() {
+            effect_builder.immediately().event(|_| Event::CheckTimer)
+        } else {
+            info!("all disk space thresholds are zero, disk space monitoring is disabled");
+            Effects::new()
+        };
+        let disk_monitor = DiskMonitor {
+            config,
+            storage_path: storage_path.to_owned(),
+            stage: DiskSpaceStage::Normal,
+        };
+        (disk_monitor, effects)
+    }
+
+    /// Returns the stage the node is currently in.
+    pub(crate) fn stage(&self) -> DiskSpaceStage {
+        self.stage
+    }
+
+    /// Determines the available disk space without blocking the reactor.
+    fn check(&self) -> Effects<Event> {
+        if !self.config.is_enabled() {
+            return Effects::new();
+        }
+        let path = self.storage_path.clone();
+        async move {
+            // `statvfs` may block, e.g. on network file systems.
+            let result = task::spawn_blocking(move || utils::disk_space(&path))
+                .await
+                .expect("should run");
+            match result {
+                Ok(disk_space) => Some(disk_space.available_bytes),
+                Err(error) => {
+                    warn!(%error, "could not determine available disk space");
+                    None
+                }
+            }
+        }
+        .map_some(|available_bytes| Event::DiskSpace { available_bytes })
+    }
+
+    /// Records the available disk space, returning the new stage if it changed.
+    fn update_stage(&mut self, available_bytes: u64) -> Option<DiskSpaceStage> {
+        // There is no way back once the node is shutting down.
+        if self.stage == DiskSpaceStage::Halted {
+            return None;
+        }
+        let stage = self.config.stage(available_bytes);
+        if stage == self.stage {
+            return None;
+        }
+        if stage > self.stage {
+            warn!(%stage, available_bytes, path = %self.storage_path.display(), "running out of disk space");
+        } else {
+            info!(%stage, available_bytes, "disk space freed");
+        }
+        self.stage = stage;
+        Some(stage)
+    }
+}
+
+impl<REv: ReactorEventT + 'static> Component<REv> for DiskMonitor {
+    type Event = Event;
+    type ConstructionError = Infallible;
+
+    fn handle_event(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        _rng: &mut NodeRng,
+        event: Self::Event,
+    ) -> Effects<Self::Event> {
+        match event {
+            Event::CheckTimer => {
+                let mut effects = effect_builder
+                    .set_timeout(self.config.check_interval)
+                    .event(|_| Event::CheckTimer);
+                effects.extend(self.check());
+                effects
+            }
+            Event::BlockAdded => self.check(),
+            Event::DiskSpace { available_bytes } => {
+                let stage = match self.update_stage(available_bytes) {
+                    Some(stage) => stage,
+                    None => return Effects::new(),
+                };
+                if stage == DiskSpaceStage::Halted {
+                    error!(
+                        available_bytes,
+                        "too little disk space left to write to the storage safely, shutting down"
+                    );
+                    crate::TERMINATION_REQUESTED.store(true, Ordering::SeqCst);
+                }
+                effect_builder.announce_disk_space_stage(stage).ignore()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn disk_monitor() -> DiskMonitor {
+        DiskMonitor {
+            config: Config {
+                disable_archival_below_mib: 300,
+                pause_deploys_below_mib: 200,
+                halt_below_mib: 100,
+                ..Config::default()
+            },
+            storage_path: PathBuf::from("/var/lib/casper"),
+            stage: DiskSpaceStage::Normal,
+        }
+    }
+
+    #[test]
+    fn should_degrade_and_recover_in_stages() {
+        let mut disk_monitor = disk_monitor();
+        assert_eq!(disk_monitor.update_stage(400 * MIB), None);
+        assert_eq!(
+            disk_monitor.update_stage(250 * MIB),
+            Some(DiskSpaceStage::ArchivalDisabled)
+        );
+        assert_eq!(disk_monitor.update_stage(260 * MIB), None);
+        assert_eq!(
+            disk_monitor.update_stage(150 * MIB),
+            Some(DiskSpaceStage::DeploysPaused)
+        );
+        assert_eq!(
+            disk_monitor.update_stage(350 * MIB),
+            Some(DiskSpaceStage::Normal)
+        );
+        assert_eq!(
+            disk_monitor.update_stage(50 * MIB),
+            Some(DiskSpaceStage::Halted)
+        );
+        // Once halted, freeing space does not bring the node back.
+        assert_eq!(disk_monitor.update_stage(400 * MIB), None);
+        assert_eq!(disk_monitor.stage(), DiskSpaceStage::Halted);
+    }
+
+    #[test]
+    fn should_skip_disabled_stages() {
+        let config = Config {
+            disable_archival_below_mib: 0,
+            pause_deploys_below_mib: 0,
+            halt_below_mib: 100,
+            ..Config::default()
+        };
+        assert_eq!(config.stage(150 * MIB), DiskSpaceStage::Normal);
+        assert_eq!(config.stage(50 * MIB), DiskSpaceStage::Halted);
+
+        let config = Config {
+            disable_archival_below_mib: 0,
+            pause_deploys_below_mib: 0,
+            halt_below_mib: 0,
+            ..Config::default()
+        };
+        assert!(!config.is_enabled());
+        assert_eq!(config.stage(0), DiskSpaceStage::Normal);
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use datasize::DataSize;
+use serde::{Deserialize, Serialize};
+
+use super::DiskSpaceStage;
+use crate::utils::ConfigValidator;
+
+/// Number of bytes in a mebibyte.
+const MIB: u64 = 1024 * 1024;
+
+/// Default interval between checks of the available disk space.
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default available disk space in MiB below which historical blocks are no longer served.
+const DEFAULT_DISABLE_ARCHIVAL_BELOW_MIB: u64 = 10 * 1024;
+
+/// Default available disk space in MiB below which deploys from clients are rejected.
+const DEFAULT_PAUSE_DEPLOYS_BELOW_MIB: u64 = 5 * 1024;
+
+/// Default available disk space in MiB below which the node shuts down.
+const DEFAULT_HALT_BELOW_MIB: u64 = 1024;
+
+/// Configuration of the disk space monitor.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval in milliseconds between checks of the space available on the disk holding the
+    /// storage. The space is also checked whenever a block has been added.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub check_interval: Duration,
+    /// Available disk space in MiB below which historical blocks are no longer served to peers.
+    /// Zero disables this stage.
+    pub disable_archival_below_mib: u64,
+    /// Available disk space in MiB below which deploys submitted by clients are rejected. Zero
+    /// disables this stage.
+    pub pause_deploys_below_mib: u64,
+    /// Available disk space in MiB below which the node shuts down cleanly. Zero disables this
+    /// stage.
+    pub halt_below_mib: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            check_interval: DEFAULT_CHECK_INTERVAL,
+            disable_archival_below_mib: DEFAULT_DISABLE_ARCHIVAL_BELOW_MIB,
+            pause_deploys_below_mib: DEFAULT_PAUSE_DEPLOYS_BELOW_MIB,
+            halt_below_mib: DEFAULT_HALT_BELOW_MIB,
+        }
+    }
+}
+
+impl Config {
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        validator.ensure_non_zero("check_interval", self.check_interval);
+        // Each enabled stage has to be entered with no more space left than the earlier ones.
+        let thresholds = [
+            (
+                "disable_archival_below_mib",
+                self.disable_archival_below_mib,
+            ),
+            ("pause_deploys_below_mib", self.pause_deploys_below_mib),
+            ("halt_below_mib", self.halt_below_mib),
+        ];
+        let mut previous: Option<(&str, u64)> = None;
+        for (name, threshold) in thresholds.iter().copied() {
+            if threshold == 0 {
+                continue;
+            }
+            if let Some((previous_name, previous_threshold)) = previous {
+                validator.ensure(
+                    threshold <= previous_threshold,
+                    name,
+                    format!("must not exceed {}", previous_name),
+                );
+            }
+            previous = Some((name, threshold));
+        }
+    }
+
+    /// Returns whether any of the stages is enabled.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.disable_archival_below_mib != 0
+            || self.pause_deploys_below_mib != 0
+            || self.halt_below_mib != 0
+    }
+
+    /// Returns the stage the node is in with `available_bytes` left on the disk.
+    pub(crate) fn stage(&self, available_bytes: u64) -> DiskSpaceStage {
+        let below = |threshold_mib: u64| available_bytes < threshold_mib.saturating_mul(MIB);
+        if below(self.halt_below_mib) {
+            DiskSpaceStage::Halted
+        } else if below(self.pause_deploys_below_mib) {
+            DiskSpaceStage::DeploysPaused
+        } else if below(self.disable_archival_below_mib) {
+            DiskSpaceStage::ArchivalDisabled
+        } else {
+            DiskSpaceStage::Normal
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! Per-era metrics.
+//!
+//! The era metrics component keeps a compact summary of every era while it is running: the
+//! finality latencies of its blocks, the number of deploys executed and the gas they used. Once the
+//! switch block of an era has been executed, the summary is completed with the number of connected
+//! peers and persisted as an `EraMetricsSnapshot`, to be queried later through the RPC server.
+//!
+//! Only blocks executed while the validator reactor is running are taken into account, so the
+//! snapshot of the era during which the node joined is marked as partial.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    fmt::{self, Display, Formatter},
+};
+
+use datasize::DataSize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use casper_types::{ExecutionResult, U512};
+
+use crate::{
+    components::{consensus::EraId, Component},
+    effect::{
+        requests::{NetworkInfoRequest, StateStoreRequest},
+        EffectBuilder, EffectExt, Effects,
+    },
+    types::{Block, DeployHash, DeployHeader, FinalizedBlock, NodeId, TimeDiff, Timestamp},
+    NodeRng,
+};
+
+/// A helper trait constraining `EraMetrics` compatible reactor events.
+pub(crate) trait ReactorEventT:
+    From<Event> + From<NetworkInfoRequest<NodeId>> + From<StateStoreRequest> + Send + 'static
+{
+}
+
+impl<REv> ReactorEventT for REv where
+    REv: From<Event> + From<NetworkInfoRequest<NodeId>> + From<StateStoreRequest> + Send + 'static
+{
+}
+
+/// Creates the key under which the snapshot of the given era is stored.
+pub(crate) fn create_storage_key(era_id: EraId) -> Vec<u8> {
+    format!("era_metrics:era_id={}", u64::from(era_id)).into()
+}
+
+/// Summary of the finality latencies of the blocks of an era, in milliseconds.
+///
+/// The finality latency of a block is the time between its timestamp and its finalization by this
+/// node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FinalityLatencies {
+    /// The number of blocks the latencies were measured for.
+    pub count: u64,
+    /// The lowest latency.
+    pub min_ms: u64,
+    /// The mean latency.
+    pub mean_ms: u64,
+    /// The highest latency.
+    pub max_ms: u64,
+}
+
+/// The metrics of a single era, as observed by this node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EraMetricsSnapshot {
+    /// The era.
+    pub era_id: EraId,
+    /// Whether the node only observed part of the era, e.g. because it joined during the era.
+    pub partial: bool,
+    /// The number of blocks executed, including the switch block.
+    pub block_count: u64,
+    /// The number of deploys executed.
+    pub deploy_count: u64,
+    /// The gas used by all executed deploys.
+    pub gas_used: U512,
+    /// The finality latencies, if any block of the era was finalized by this node.
+    pub finality_latencies: Option<FinalityLatencies>,
+    /// The number of connected peers at the end of the era.
+    pub peer_count: u64,
+}
+
+/// Era metrics component event.
+#[derive(Debug)]
+pub(crate) enum Event {
+    /// A block has been finalized by consensus.
+    Finalized {
+        /// The era of the block.
+        era_id: EraId,
+        /// The time between the block's timestamp and its finalization.
+        latency: TimeDiff,
+    },
+    /// A block has been executed.
+    BlockExecuted {
+        /// The era of the block.
+        era_id: EraId,
+        /// Whether the block is the switch block of its era.
+        switch_block: bool,
+        /// The number of deploys executed.
+        deploy_count: u64,
+        /// The gas used by the deploys.
+        gas_used: U512,
+    },
+}
+
+impl Event {
+    /// Creates the event for an executed block and the results of its deploys.
+    pub(crate) fn block_executed(
+        block: &Block,
+        execution_results: &HashMap<DeployHash, (DeployHeader, ExecutionResult)>,
+    ) -> Self {
+        let gas_used = execution_results
+            .values()
+            .map(|(_, execution_result)| match execution_result {
+                ExecutionResult::Failure { cost, .. } | ExecutionResult::Success { cost, .. } => {
+                    *cost
+                }
+            })
+            .fold(U512::zero(), |total, cost| total.saturating_add(cost));
+        Event::BlockExecuted {
+            era_id: block.header().era_id(),
+            switch_block: block.header().switch_block(),
+            deploy_count: execution_results.len() as u64,
+            gas_used,
+        }
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Finalized { era_id, latency } => {
+                write!(formatter, "block in {} finalized after {}", era_id, latency)
+            }
+            Event::BlockExecuted {
+                era_id,
+                switch_block,
+                deploy_count,
+                ..
+            } => write!(
+                formatter,
+                "{}block in {} with {} deploys executed",
+                if *switch_block { "switch " } else { "" },
+                era_id,
+                deploy_count
+            ),
+        }
+    }
+}
+
+/// The metrics collected for an era which has not ended yet.
+#[derive(DataSize, Debug, Default)]
+struct EraAccumulator {
+    /// Whether the switch block of the previous era has been executed by this component.
+    observed_from_start: bool,
+    block_count: u64,
+    deploy_count: u64,
+    gas_used: U512,
+    latency_count: u64,
+    latency_sum_ms: u64,
+    latency_min_ms: u64,
+    latency_max_ms: u64,
+}
+
+impl EraAccumulator {
+    fn add_latency(&mut self, latency: TimeDiff) {
+        let millis = latency.millis();
+        if self.latency_count == 0 {
+            self.latency_min_ms = millis;
+            self.latency_max_ms = millis;
+        } else {
+            self.latency_min_ms = self.latency_min_ms.min(millis);
+            self.latency_max_ms = self.latency_max_ms.max(millis);
+        }
+        self.latency_count += 1;
+        self.latency_sum_ms = self.latency_sum_ms.saturating_add(millis);
+    }
+
+    /// Returns the snapshot of the ended era, without the peer count.
+    fn into_snapshot(self, era_id: EraId) -> EraMetricsSnapshot {
+        let finality_latencies = if self.latency_count == 0 {
+            None
+        } else {
+            Some(FinalityLatencies {
+                count: self.latency_count,
+                min_ms: self.latency_min_ms,
+                mean_ms: self.latency_sum_ms / self.latency_count,
+                max_ms: self.latency_max_ms,
+            })
+        };
+        EraMetricsSnapshot {
+            era_id,
+            partial: !self.observed_from_start,
+            block_count: self.block_count,
+            deploy_count: self.deploy_count,
+            gas_used: self.gas_used,
+            finality_latencies,
+            peer_count: 0,
+        }
+    }
+}
+
+/// The era metrics component.
+#[derive(DataSize, Debug, Default)]
+pub(crate) struct EraMetrics {
+    /// The metrics of all eras not ended yet.
+    ///
+    /// Consensus may finalize blocks of the next era before the switch block of the current one
+    /// has been executed, so there can be more than one.
+    eras: BTreeMap<EraId, EraAccumulator>,
+}
+
+impl EraMetrics {
+    /// Creates a new era metrics component, subscribing to finalized blocks.
+    pub(crate) fn new<REv: ReactorEventT>(effect_builder: EffectBuilder<REv>) -> Self {
+        effect_builder.subscribe(|block: FinalizedBlock| Event::Finalized {
+            era_id: block.era_id(),
+            latency: Timestamp::now().saturating_sub(block.timestamp()),
+        });
+        EraMetrics::default()
+    }
+
+    /// Records an executed block, returning the snapshot of its era if it is a switch block.
+    fn block_executed(
+        &mut self,
+        era_id: EraId,
+        switch_block: bool,
+        deploy_count: u64,
+        gas_used: U512,
+    ) -> Option<EraMetricsSnapshot> {
+        let accumulator = self.eras.entry(era_id).or_default();
+        accumulator.block_count += 1;
+        accumulator.deploy_count += deploy_count;
+        accumulator.gas_used = accumulator.gas_used.saturating_add(gas_used);
+        if !switch_block {
+            return None;
+        }
+        let accumulator = self.eras.remove(&era_id).unwrap_or_default();
+        // Anything left over from earlier eras can't be completed anymore.
+        self.eras = self.eras.split_off(&era_id);
+        self.eras
+            .entry(era_id.successor())
+            .or_default()
+            .observed_from_start = true;
+        Some(accumulator.into_snapshot(era_id))
+    }
+}
+
+impl<REv: ReactorEventT> Component<REv> for EraMetrics {
+    type Event = Event;
+    type ConstructionError = Infallible;
+
+    fn handle_event(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        _rng: &mut NodeRng,
+        event: Self::Event,
+    ) -> Effects<Self::Event> {
+        match event {
+            Event::Finalized { era_id, latency } => {
+                self.eras.entry(era_id).or_default().add_latency(latency);
+                Effects::new()
+            }
+            Event::BlockExecuted {
+                era_id,
+                switch_block,
+                deploy_count,
+                gas_used,
+            } => match self.block_executed(era_id, switch_block, deploy_count, gas_used) {
+                Some(mut snapshot) => async move {
+                    snapshot.peer_count =
+                        effect_builder.network_peers::<NodeId>().await.len() as u64;
+                    info!(?snapshot, "era ended");
+                    if !effect_builder
+                        .save_state(create_storage_key(era_id).into(), snapshot)
+                        .await
+                    {
+                        warn!(%era_id, "could not persist era metrics");
+                    }
+                }
+                .ignore(),
+                None => Effects::new(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn should_summarize_era_on_switch_block() {
+        let era_id = EraId(1);
+        let mut era_metrics = EraMetrics::default();
+        era_metrics
+            .eras
+            .entry(era_id)
+            .or_default()
+            .add_latency(TimeDiff::from(Duration::from_millis(300)));
+        era_metrics
+            .eras
+            .entry(era_id)
+            .or_default()
+            .add_latency(TimeDiff::from(Duration::from_millis(100)));
+        // The next era's first block may be finalized before the switch block is executed.
+        era_metrics
+            .eras
+            .entry(era_id.successor())
+            .or_default()
+            .add_latency(TimeDiff::from(Duration::from_millis(50)));
+
+        assert!(era_metrics
+            .block_executed(era_id, false, 2, U512::from(10))
+            .is_none());
+        let snapshot = era_metrics
+            .block_executed(era_id, true, 1, U512::from(5))
+            .expect("should end era");
+        assert_eq!(
+            snapshot,
+            EraMetricsSnapshot {
+                era_id,
+                partial: true,
+                block_count: 2,
+                deploy_count: 3,
+                gas_used: U512::from(15),
+                finality_latencies: Some(FinalityLatencies {
+                    count: 2,
+                    min_ms: 100,
+                    mean_ms: 200,
+                    max_ms: 300,
+                }),
+                peer_count: 0,
+            }
+        );
+
+        let snapshot = era_metrics
+            .block_executed(era_id.successor(), true, 0, U512::zero())
+            .expect("should end era");
+        assert!(!snapshot.partial);
+        assert_eq!(
+            snapshot.finality_latencies.map(|latencies| latencies.count),
+            Some(1)
+        );
+    }
+}
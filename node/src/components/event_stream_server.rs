@@ -17,11 +17,15 @@
 //! unintended subscriber disconnects, if a disconnected subscriber re-subscribes before the buffer
 //! has advanced past their last received event.
 //!
+//! Subscribers may also pass a filter expression to only receive the events they are interested in,
+//! see the `filter` module.
+//!
 //! For details about the SSE model and a list of supported SSEs, see:
 //! <https://github.com/CasperLabs/ceps/blob/master/text/0009-client-api.md#rpcs>
 
 mod config;
 mod event;
+mod filter;
 mod http_server;
 mod sse_server;
 
@@ -30,6 +34,8 @@ use std::{convert::Infallible, fmt::Debug};
 use datasize::DataSize;
 use tokio::sync::mpsc::{self, UnboundedSender};
 
+use casper_types::ExecutionResult;
+
 use super::Component;
 use crate::{
     effect::{EffectBuilder, Effects},
@@ -103,15 +109,31 @@ where
                 deploy_header,
                 block_hash,
                 execution_result,
-            } => self.broadcast(SseData::DeployProcessed {
-                deploy_hash: Box::new(deploy_hash),
-                account: *deploy_header.account(),
-                timestamp: deploy_header.timestamp(),
-                ttl: deploy_header.ttl(),
-                dependencies: deploy_header.dependencies().clone(),
-                block_hash: Box::new(block_hash),
-                execution_result,
-            }),
+            } => {
+                // Events emitted by contracts are additionally sent individually, so subscribers
+                // can filter for them without inspecting every execution result.
+                let contract_events = match &*execution_result {
+                    ExecutionResult::Success { events, .. } => events.clone(),
+                    ExecutionResult::Failure { .. } => vec![],
+                };
+                let mut effects = self.broadcast(SseData::DeployProcessed {
+                    deploy_hash: Box::new(deploy_hash),
+                    account: *deploy_header.account(),
+                    timestamp: deploy_header.timestamp(),
+                    ttl: deploy_header.ttl(),
+                    dependencies: deploy_header.dependencies().clone(),
+                    block_hash: Box::new(block_hash),
+                    execution_result,
+                });
+                for event in contract_events {
+                    effects.extend(self.broadcast(SseData::ContractEvent {
+                        deploy_hash: Box::new(deploy_hash),
+                        block_hash: Box::new(block_hash),
+                        event: Box::new(event),
+                    }));
+                }
+                effects
+            }
             Event::Fault {
                 era_id,
                 public_key,
@@ -0,0 +1,325 @@
+//! Server-side filtering of the event stream.
+//!
+//! A subscriber may pass a filter expression in the `filter` query parameter, e.g.
+//! `/events?filter=event:deploy_processed|block_added,account:01a3...`, in which case only the
+//! events matching it are sent.
+//!
+//! An expression is a comma-separated list of clauses, all of which must match. Each clause has the
+//! form `<key>:<value>[|<value>...]` and matches if any of its values does. The supported keys are:
+//!
+//! * `event`: the event type, one of `block_added`, `deploy_processed`, `fault`,
+//!   `finality_signature` and `contract_event`
+//! * `account`: a hex-encoded public key, matching deploys sent from that account as well as blocks
+//!   proposed, faults committed and finality signatures created by that validator
+//! * `contract`: a hex-encoded contract hash, optionally prefixed with `hash-`, matching deploys
+//!   whose execution read or wrote that contract and events emitted by that contract
+//! * `era`: an era ID, matching blocks, faults and finality signatures of that era
+//!
+//! The initial `ApiVersion` event is always sent, irrespective of the filter.
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use casper_types::{AsymmetricType, ExecutionEffect, ExecutionResult, PublicKey};
+
+use super::SseData;
+use crate::components::consensus::EraId;
+
+/// The prefix of a formatted `Key::Hash`.
+const HASH_PREFIX: &str = "hash-";
+
+/// Error parsing an event stream filter expression.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(super) enum FilterError {
+    /// A clause is not of the form `<key>:<values>`.
+    #[error("invalid clause '{0}', expected '<key>:<value>[|<value>...]'")]
+    InvalidClause(String),
+    /// A clause uses an unknown key.
+    #[error("unknown filter key '{0}'")]
+    UnknownKey(String),
+    /// A value could not be parsed for its key.
+    #[error("invalid value '{value}' for filter key '{key}'")]
+    InvalidValue { key: &'static str, value: String },
+}
+
+/// A type of event the subscriber is interested in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum EventType {
+    BlockAdded,
+    DeployProcessed,
+    Fault,
+    FinalitySignature,
+    ContractEvent,
+}
+
+impl FromStr for EventType {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "block_added" => Ok(EventType::BlockAdded),
+            "deploy_processed" => Ok(EventType::DeployProcessed),
+            "fault" => Ok(EventType::Fault),
+            "finality_signature" => Ok(EventType::FinalitySignature),
+            "contract_event" => Ok(EventType::ContractEvent),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A single clause of a filter expression, matching if any of its values match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Clause {
+    Event(Vec<EventType>),
+    Account(Vec<PublicKey>),
+    /// Formatted `Key::Hash`es of contracts.
+    Contract(Vec<String>),
+    Era(Vec<EraId>),
+}
+
+impl Clause {
+    fn matches(&self, data: &SseData) -> bool {
+        match self {
+            Clause::Event(types) => event_type(data).map_or(false, |t| types.contains(&t)),
+            Clause::Account(keys) => account(data).map_or(false, |key| keys.contains(key)),
+            Clause::Contract(keys) => match data {
+                SseData::DeployProcessed {
+                    execution_result, ..
+                } => {
+                    let effect = match &**execution_result {
+                        ExecutionResult::Success { effect, .. }
+                        | ExecutionResult::Failure { effect, .. } => effect,
+                    };
+                    touches_any(effect, keys)
+                }
+                SseData::ContractEvent { event, .. } => keys.contains(&event.emitter),
+                _ => false,
+            },
+            Clause::Era(era_ids) => era_id(data).map_or(false, |era_id| era_ids.contains(&era_id)),
+        }
+    }
+}
+
+/// A parsed filter expression.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct EventFilter {
+    clauses: Vec<Clause>,
+}
+
+impl EventFilter {
+    /// Returns whether the event should be sent to the subscriber.
+    pub(super) fn matches(&self, data: &SseData) -> bool {
+        if let SseData::ApiVersion(_) = data {
+            return true;
+        }
+        self.clauses.iter().all(|clause| clause.matches(data))
+    }
+}
+
+impl FromStr for EventFilter {
+    type Err = FilterError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let clauses = input
+            .split(',')
+            .map(str::trim)
+            .filter(|clause| !clause.is_empty())
+            .map(parse_clause)
+            .collect::<Result<_, _>>()?;
+        Ok(EventFilter { clauses })
+    }
+}
+
+fn parse_clause(clause: &str) -> Result<Clause, FilterError> {
+    let mut parts = clause.splitn(2, ':');
+    let key = parts.next().unwrap_or_default().trim();
+    let values: Vec<&str> = match parts.next() {
+        Some(values) => values.split('|').map(str::trim).collect(),
+        None => return Err(FilterError::InvalidClause(clause.to_string())),
+    };
+    if values.iter().any(|value| value.is_empty()) {
+        return Err(FilterError::InvalidClause(clause.to_string()));
+    }
+
+    match key {
+        "event" => parse_values("event", &values, |value| value.parse().ok()).map(Clause::Event),
+        "account" => parse_values("account", &values, |value| PublicKey::from_hex(value).ok())
+            .map(Clause::Account),
+        "contract" => parse_values("contract", &values, |value| {
+            let hex = value.strip_prefix(HASH_PREFIX).unwrap_or(value);
+            match base16::decode(hex) {
+                Ok(bytes) if bytes.len() == 32 => {
+                    Some(format!("{}{}", HASH_PREFIX, base16::encode_lower(&bytes)))
+                }
+                _ => None,
+            }
+        })
+        .map(Clause::Contract),
+        "era" => {
+            parse_values("era", &values, |value| value.parse().ok().map(EraId)).map(Clause::Era)
+        }
+        _ => Err(FilterError::UnknownKey(key.to_string())),
+    }
+}
+
+fn parse_values<T, F>(key: &'static str, values: &[&str], parse: F) -> Result<Vec<T>, FilterError>
+where
+    F: Fn(&str) -> Option<T>,
+{
+    values
+        .iter()
+        .map(|value| {
+            parse(value).ok_or_else(|| FilterError::InvalidValue {
+                key,
+                value: value.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn event_type(data: &SseData) -> Option<EventType> {
+    match data {
+        SseData::ApiVersion(_) => None,
+        SseData::BlockAdded { .. } => Some(EventType::BlockAdded),
+        SseData::DeployProcessed { .. } => Some(EventType::DeployProcessed),
+        SseData::Fault { .. } => Some(EventType::Fault),
+        SseData::FinalitySignature(_) => Some(EventType::FinalitySignature),
+        SseData::ContractEvent { .. } => Some(EventType::ContractEvent),
+    }
+}
+
+fn account(data: &SseData) -> Option<&PublicKey> {
+    match data {
+        SseData::ApiVersion(_) | SseData::ContractEvent { .. } => None,
+        SseData::BlockAdded { block_header, .. } => Some(block_header.proposer()),
+        SseData::DeployProcessed { account, .. } => Some(account),
+        SseData::Fault { public_key, .. } => Some(public_key),
+        SseData::FinalitySignature(signature) => Some(&signature.public_key),
+    }
+}
+
+fn era_id(data: &SseData) -> Option<EraId> {
+    match data {
+        SseData::ApiVersion(_)
+        | SseData::DeployProcessed { .. }
+        | SseData::ContractEvent { .. } => None,
+        SseData::BlockAdded { block_header, .. } => Some(block_header.era_id()),
+        SseData::Fault { era_id, .. } => Some(*era_id),
+        SseData::FinalitySignature(signature) => Some(signature.era_id),
+    }
+}
+
+/// Returns whether any operation or transform of the effect is on one of the given keys.
+fn touches_any(effect: &ExecutionEffect, keys: &[String]) -> bool {
+    effect
+        .operations
+        .iter()
+        .map(|operation| &operation.key)
+        .chain(effect.transforms.iter().map(|entry| &entry.key))
+        .any(|key| keys.contains(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use casper_types::{SecretKey, Transform, TransformEntry, U512};
+
+    use super::*;
+    use crate::{
+        crypto::{hash::Digest, AsymmetricKeyExt},
+        types::{BlockHash, DeployHash, TimeDiff, Timestamp},
+    };
+
+    fn deploy_processed(account: PublicKey, touched_key: &str) -> SseData {
+        SseData::DeployProcessed {
+            deploy_hash: Box::new(DeployHash::default()),
+            account,
+            timestamp: Timestamp::zero(),
+            ttl: TimeDiff::from(Duration::from_secs(1)),
+            dependencies: vec![],
+            block_hash: Box::new(BlockHash::new(Digest::default())),
+            execution_result: Box::new(ExecutionResult::Success {
+                effect: ExecutionEffect {
+                    operations: vec![],
+                    transforms: vec![TransformEntry {
+                        key: touched_key.to_string(),
+                        transform: Transform::Identity,
+                    }],
+                },
+                transfers: vec![],
+                events: vec![],
+                cost: U512::zero(),
+            }),
+        }
+    }
+
+    fn fault(public_key: PublicKey, era_id: u64) -> SseData {
+        SseData::Fault {
+            era_id: EraId(era_id),
+            public_key,
+            timestamp: Timestamp::zero(),
+        }
+    }
+
+    #[test]
+    fn should_match_all_clauses() {
+        let mut rng = crate::new_rng();
+        let public_key = PublicKey::from(&SecretKey::random(&mut rng));
+        let filter: EventFilter = format!(
+            "event:fault|block_added, era:3, account:{}",
+            public_key.to_hex()
+        )
+        .parse()
+        .unwrap();
+
+        assert!(filter.matches(&fault(public_key, 3)));
+        assert!(!filter.matches(&fault(public_key, 4)));
+        assert!(!filter.matches(&fault(PublicKey::from(&SecretKey::random(&mut rng)), 3)));
+        assert!(!filter.matches(&deploy_processed(public_key, "hash-00")));
+    }
+
+    #[test]
+    fn should_match_contract() {
+        let mut rng = crate::new_rng();
+        let public_key = PublicKey::from(&SecretKey::random(&mut rng));
+        let hex = "ab".repeat(32);
+        let filter: EventFilter = format!("contract:{}", hex).parse().unwrap();
+
+        let touched = format!("{}{}", HASH_PREFIX, hex);
+        assert!(filter.matches(&deploy_processed(public_key, &touched)));
+        assert!(!filter.matches(&deploy_processed(public_key, "hash-00")));
+        assert!(!filter.matches(&fault(public_key, 0)));
+    }
+
+    #[test]
+    fn should_always_match_api_version() {
+        let filter: EventFilter = "event:fault".parse().unwrap();
+        assert!(filter.matches(&SseData::ApiVersion(semver::Version::new(1, 0, 0))));
+        assert!(EventFilter::default().matches(&fault(
+            PublicKey::from(&SecretKey::random(&mut crate::new_rng())),
+            0
+        )));
+    }
+
+    #[test]
+    fn should_reject_invalid_expressions() {
+        assert_eq!(
+            "event".parse::<EventFilter>(),
+            Err(FilterError::InvalidClause("event".to_string()))
+        );
+        assert_eq!(
+            "height:5".parse::<EventFilter>(),
+            Err(FilterError::UnknownKey("height".to_string()))
+        );
+        assert_eq!(
+            "era:five".parse::<EventFilter>(),
+            Err(FilterError::InvalidValue {
+                key: "era",
+                value: "five".to_string()
+            })
+        );
+        assert!("contract:hash-00".parse::<EventFilter>().is_err());
+    }
+}
@@ -1,7 +1,8 @@
 //! Types and functions used by the http server to manage the event-stream.
 
 use datasize::DataSize;
-use futures::{Stream, StreamExt};
+use futures::{future, Stream, StreamExt};
+use http::StatusCode;
 use once_cell::sync::Lazy;
 use semver::Version;
 use serde::{Deserialize, Serialize};
@@ -12,12 +13,14 @@ use tokio::sync::{
 use tracing::{error, info, trace};
 use warp::{
     filters::BoxedFilter,
+    reply::{self, Response},
     sse::{self, ServerSentEvent as WarpServerSentEvent},
     Filter, Reply,
 };
 
-use casper_types::{ExecutionResult, PublicKey};
+use casper_types::{ContractEvent, ExecutionResult, PublicKey};
 
+use super::filter::EventFilter;
 use crate::{
     components::{consensus::EraId, CLIENT_API_VERSION},
     types::{BlockHash, BlockHeader, DeployHash, FinalitySignature, TimeDiff, Timestamp},
@@ -66,6 +69,14 @@ pub enum SseData {
     },
     /// New finality signature received.
     FinalitySignature(Box<FinalitySignature>),
+    /// An event emitted by a contract during the successful execution of the given deploy, sent
+    /// after the corresponding `DeployProcessed` event.
+    ContractEvent {
+        deploy_hash: Box<DeployHash>,
+        block_hash: Box<BlockHash>,
+        #[data_size(skip)]
+        event: Box<ContractEvent>,
+    },
 }
 
 /// The components of a single SSE.
@@ -102,6 +113,8 @@ pub(super) struct NewSubscriberInfo {
 #[derive(Deserialize, Debug)]
 struct Query {
     start_from: Option<Id>,
+    /// An expression restricting the events sent to the client, see `EventFilter`.
+    filter: Option<String>,
 }
 
 /// Creates the message-passing channels required to run the event-stream server and the warp filter
@@ -111,7 +124,7 @@ pub(super) fn create_channels_and_filter(
 ) -> (
     broadcast::Sender<BroadcastChannelMessage>,
     mpsc::UnboundedReceiver<NewSubscriberInfo>,
-    BoxedFilter<(impl Reply,)>,
+    BoxedFilter<(Response,)>,
 ) {
     // Create a channel to broadcast new events to all subscribed clients' streams.
     let (broadcaster, _) = broadcast::channel(broadcast_channel_size);
@@ -124,6 +137,15 @@ pub(super) fn create_channels_and_filter(
     let filter = warp::get()
         .and(warp::path(SSE_API_PATH))
         .and(warp::query().map(move |query: Query| {
+            let event_filter = match query.filter.as_deref().map(str::parse).transpose() {
+                Ok(event_filter) => event_filter.unwrap_or_default(),
+                Err(error) => {
+                    info!(%error, "rejecting event stream subscription with invalid filter");
+                    return reply::with_status(error.to_string(), StatusCode::BAD_REQUEST)
+                        .into_response();
+                }
+            };
+
             // Create a channel for the client's handler to receive the stream of initial events.
             let (initial_events_sender, initial_events_receiver) = mpsc::unbounded_channel();
 
@@ -146,7 +168,9 @@ pub(super) fn create_channels_and_filter(
             sse::reply(sse::keep_alive().stream(stream_to_client(
                 initial_events_receiver,
                 ongoing_events_receiver,
+                event_filter,
             )))
+            .into_response()
         }))
         .boxed();
 
@@ -164,13 +188,25 @@ pub(super) fn create_channels_and_filter(
 /// either the client disconnects, or the server shuts down (indicated by sending a `Shutdown`
 /// variant via the channel).  This channel will receive all SSEs created from the moment the client
 /// subscribed to the server's event stream.
+///
+/// Events from either channel which don't match `event_filter` are skipped.
 fn stream_to_client(
     initial_events: mpsc::UnboundedReceiver<ServerSentEvent>,
     ongoing_events: broadcast::Receiver<BroadcastChannelMessage>,
+    event_filter: EventFilter,
 ) -> impl Stream<Item = Result<impl WarpServerSentEvent, RecvError>> + 'static {
     initial_events
         .map(|event| Ok(BroadcastChannelMessage::ServerSentEvent(event)))
         .chain(ongoing_events)
+        .filter(move |result| {
+            let wanted = match result {
+                Ok(BroadcastChannelMessage::ServerSentEvent(event)) => {
+                    event_filter.matches(&event.data)
+                }
+                Ok(BroadcastChannelMessage::Shutdown) | Err(_) => true,
+            };
+            future::ready(wanted)
+        })
         .map(|result| {
             trace!(?result);
             match result {
@@ -180,7 +216,8 @@ fn stream_to_client(
                         (Some(id), &SseData::BlockAdded { .. })
                         | (Some(id), &SseData::DeployProcessed { .. })
                         | (Some(id), &SseData::FinalitySignature(_))
-                        | (Some(id), &SseData::Fault { .. }) => {
+                        | (Some(id), &SseData::Fault { .. })
+                        | (Some(id), &SseData::ContractEvent { .. }) => {
                             Ok((sse::id(id), sse::json(event.data)).boxed())
                         }
                         _ => unreachable!("only ApiVersion may have no event ID"),
@@ -1,18 +1,21 @@
 mod config;
 mod event;
+mod peer_selection;
 mod tests;
 
-use std::{collections::HashMap, convert::Infallible, fmt::Debug, time::Duration};
+use std::{collections::HashMap, fmt::Debug, time::Duration};
 
 use datasize::DataSize;
+use prometheus::Registry;
 use smallvec::smallvec;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 use casper_execution_engine::shared::newtypes::Blake2bHash;
 
 use crate::{
     components::{fetcher::event::FetchResponder, Component},
     effect::{
+        peer_requests::PeerRequests,
         requests::{ContractRuntimeRequest, LinearChainRequest, NetworkRequest, StorageRequest},
         EffectBuilder, EffectExt, Effects,
     },
@@ -26,6 +29,7 @@ use casper_execution_engine::{shared::stored_value::StoredValue, storage::trie::
 use casper_types::Key;
 pub use config::Config;
 pub use event::{Event, FetchResult};
+use peer_selection::PeerSelection;
 
 /// A helper trait constraining `Fetcher` compatible reactor events.
 pub trait ReactorEventT<T>:
@@ -60,7 +64,11 @@ where
 pub trait ItemFetcher<T: Item + 'static> {
     fn responders(&mut self) -> &mut HashMap<T::Id, HashMap<NodeId, Vec<FetchResponder<T>>>>;
 
-    fn peer_timeout(&self) -> Duration;
+    /// The requests to peers awaiting a response.
+    fn requests(&mut self) -> &mut PeerRequests<T::Id>;
+
+    /// The stats of the peers and the peers left to ask for items.
+    fn peer_selection(&mut self) -> &mut PeerSelection<T::Id>;
 
     /// We've been asked to fetch the item by another component of this node.  We'll try to get it
     /// from our own storage component first, and if that fails, we'll send a request to `peer` for
@@ -85,6 +93,42 @@ pub trait ItemFetcher<T: Item + 'static> {
         self.get_from_storage(effect_builder, id, peer)
     }
 
+    /// We've been asked to fetch the item by another component of this node, from any of `peers`.
+    /// We'll try our own storage first, and if that fails, we'll ask the peer with the best score,
+    /// see `peer_selection`, trying the others in turn if it doesn't deliver.
+    fn fetch_from_any<REv: ReactorEventT<T>>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        id: T::Id,
+        peers: Vec<NodeId>,
+        responder: FetchResponder<T>,
+    ) -> Effects<Event<T>> {
+        match self.choose_peer(id, peers) {
+            Some(peer) => self.fetch(effect_builder, id, peer, responder),
+            None => responder.respond(None).ignore(),
+        }
+    }
+
+    /// Chooses the best of `candidates` to ask for the item, remembering the others.
+    fn choose_peer(&mut self, id: T::Id, candidates: Vec<NodeId>) -> Option<NodeId> {
+        let candidates = candidates
+            .into_iter()
+            .map(|peer| {
+                let queue_depth = self.requests().queue_depth(&peer);
+                (peer, queue_depth)
+            })
+            .collect();
+        self.peer_selection().choose(id, candidates)
+    }
+
+    /// Checks the integrity of an item received from a peer.
+    ///
+    /// Items which other components validate before handing them to the fetcher, like deploys, see
+    /// `Event::RejectedRemotely`, are not checked again.
+    fn validate(_item: &T) -> Result<(), String> {
+        Ok(())
+    }
+
     // Handles attempting to get the item from storage.
     fn get_from_storage<REv: ReactorEventT<T>>(
         &mut self,
@@ -105,24 +149,60 @@ pub trait ItemFetcher<T: Item + 'static> {
 
     /// Handles the `Err` case for a `Result` of attempting to get the item from the storage
     /// component.
+    ///
+    /// If as many requests to peers are pending as currently allowed, the request is deferred. A
+    /// banned peer is not asked at all.
     fn failed_to_get_from_storage<REv: ReactorEventT<T>>(
         &mut self,
         effect_builder: EffectBuilder<REv>,
         id: T::Id,
         peer: NodeId,
     ) -> Effects<Event<T>> {
-        match Message::new_get_request::<T>(&id) {
-            Ok(message) => {
-                let mut effects = effect_builder.send_message(peer.clone(), message).ignore();
-
-                effects.extend(
-                    effect_builder
-                        .set_timeout(self.peer_timeout())
-                        .event(move |_| Event::TimeoutPeer { id, peer }),
-                );
+        if self.peer_selection().is_banned(&peer) {
+            debug!(?id, %peer, "not asking banned peer");
+            return self.failed_to_get_from_peer(effect_builder, id, peer);
+        }
+        if self.requests().is_busy(effect_builder) {
+            debug!(?id, %peer, "deferring request, too many pending");
+            self.requests().defer(peer, id);
+            return Effects::new();
+        }
+        self.request_from_peer(effect_builder, id, peer)
+    }
 
-                effects
+    /// Sends the deferred requests which can be sent now that fewer are pending.
+    ///
+    /// Requests nobody is waiting for anymore are skipped.
+    fn send_deferred<REv: ReactorEventT<T>>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+    ) -> Effects<Event<T>> {
+        let mut effects = Effects::new();
+        while let Some((peer, id)) = self.requests().next_deferred(effect_builder) {
+            let is_awaited = self
+                .responders()
+                .get(&id)
+                .map_or(false, |responders| responders.contains_key(&peer));
+            if is_awaited {
+                effects.extend(self.request_from_peer(effect_builder, id, peer));
             }
+        }
+        effects
+    }
+
+    /// Sends a request for the item to `peer`.
+    fn request_from_peer<REv: ReactorEventT<T>>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        id: T::Id,
+        peer: NodeId,
+    ) -> Effects<Event<T>> {
+        match Message::new_get_request::<T>(&id) {
+            Ok(message) => self
+                .requests()
+                .send(effect_builder, peer, id, message, |request_id| {
+                    Event::TimeoutPeer { request_id }
+                }),
             Err(error) => {
                 error!("failed to construct get request: {}", error);
                 self.signal(id, None, peer)
@@ -130,6 +210,56 @@ pub trait ItemFetcher<T: Item + 'static> {
         }
     }
 
+    /// Handles `peer` failing to deliver the item, because it doesn't have it or timed out.
+    ///
+    /// If other peers which may have the item are left, the responders waiting for it from `peer`
+    /// are moved over to the best of them, which is asked next. Otherwise, they are signalled with
+    /// `None`.
+    fn failed_to_get_from_peer<REv: ReactorEventT<T>>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        id: T::Id,
+        peer: NodeId,
+    ) -> Effects<Event<T>> {
+        self.peer_selection().record_failure(&peer);
+        let remaining = self.peer_selection().take_remaining(id, &peer);
+        let waiting = self
+            .responders()
+            .get(&id)
+            .map_or(false, |responders| responders.contains_key(&peer));
+        if !waiting {
+            // Nobody is waiting for the item from `peer` anymore, so no next peer is chosen.
+            return Effects::new();
+        }
+        let next_peer = match self.choose_peer(id, remaining) {
+            Some(next_peer) => next_peer,
+            None => return self.signal(id, None, peer),
+        };
+        if let Some(responders) = self.responders().get_mut(&id) {
+            let moved = responders.remove(&peer).unwrap_or_default();
+            responders
+                .entry(next_peer.clone())
+                .or_default()
+                .extend(moved);
+        }
+        debug!(?id, %peer, %next_peer, "asking next peer for item");
+        self.failed_to_get_from_storage(effect_builder, id, next_peer)
+    }
+
+    /// Handles `peer` responding with an invalid item, by banning it and asking the next peer, if
+    /// any.
+    fn rejected_from_peer<REv: ReactorEventT<T>>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        id: T::Id,
+        peer: NodeId,
+    ) -> Effects<Event<T>> {
+        warn!(?id, %peer, "peer responded with an invalid item, banning it");
+        self.requests().responded(&peer, &id);
+        self.peer_selection().ban(&peer);
+        self.failed_to_get_from_peer(effect_builder, id, peer)
+    }
+
     /// Handles signalling responders with the item or `None`.
     fn signal(
         &mut self,
@@ -141,6 +271,7 @@ pub trait ItemFetcher<T: Item + 'static> {
         let mut all_responders = self.responders().remove(&id).unwrap_or_default();
         match result {
             Some(ret) => {
+                self.peer_selection().fetched(&id);
                 // signal all responders waiting for this item
                 for (_, responders) in all_responders {
                     for responder in responders {
@@ -170,16 +301,31 @@ pub struct Fetcher<T>
 where
     T: Item + 'static,
 {
-    get_from_peer_timeout: Duration,
+    requests: PeerRequests<T::Id>,
     responders: HashMap<T::Id, HashMap<NodeId, Vec<FetchResponder<T>>>>,
+    peer_selection: PeerSelection<T::Id>,
 }
 
 impl<T: Item> Fetcher<T> {
-    pub(crate) fn new(config: Config) -> Self {
-        Fetcher {
-            get_from_peer_timeout: Duration::from_secs(config.get_from_peer_timeout()),
+    /// Constructs a new fetcher component.
+    ///
+    /// Must be supplied with a name, which should be a snake-case identifier to disambiguate the
+    /// specific fetcher from other potentially present fetchers.
+    pub(crate) fn new(
+        name: &str,
+        config: Config,
+        registry: &Registry,
+    ) -> Result<Self, prometheus::Error> {
+        Ok(Fetcher {
+            requests: PeerRequests::new(
+                name,
+                Duration::from_secs(config.get_from_peer_timeout()),
+                config.max_parallel_requests(),
+                registry,
+            )?,
             responders: HashMap::new(),
-        }
+            peer_selection: PeerSelection::new((&config).into()),
+        })
     }
 }
 
@@ -190,8 +336,12 @@ impl ItemFetcher<Deploy> for Fetcher<Deploy> {
         &mut self.responders
     }
 
-    fn peer_timeout(&self) -> Duration {
-        self.get_from_peer_timeout
+    fn requests(&mut self) -> &mut PeerRequests<DeployHash> {
+        &mut self.requests
+    }
+
+    fn peer_selection(&mut self) -> &mut PeerSelection<DeployHash> {
+        &mut self.peer_selection
     }
 
     /// Gets a `Deploy` from the storage component.
@@ -218,8 +368,16 @@ impl ItemFetcher<Block> for Fetcher<Block> {
         &mut self.responders
     }
 
-    fn peer_timeout(&self) -> Duration {
-        self.get_from_peer_timeout
+    fn requests(&mut self) -> &mut PeerRequests<BlockHash> {
+        &mut self.requests
+    }
+
+    fn peer_selection(&mut self) -> &mut PeerSelection<BlockHash> {
+        &mut self.peer_selection
+    }
+
+    fn validate(item: &Block) -> Result<(), String> {
+        item.verify().map_err(|error| error.to_string())
     }
 
     fn get_from_storage<REv: ReactorEventT<Block>>(
@@ -245,8 +403,19 @@ impl ItemFetcher<BlockByHeight> for Fetcher<BlockByHeight> {
         &mut self.responders
     }
 
-    fn peer_timeout(&self) -> Duration {
-        self.get_from_peer_timeout
+    fn requests(&mut self) -> &mut PeerRequests<u64> {
+        &mut self.requests
+    }
+
+    fn peer_selection(&mut self) -> &mut PeerSelection<u64> {
+        &mut self.peer_selection
+    }
+
+    fn validate(item: &BlockByHeight) -> Result<(), String> {
+        match item {
+            BlockByHeight::Absent(_) => Ok(()),
+            BlockByHeight::Block(block) => block.verify().map_err(|error| error.to_string()),
+        }
     }
 
     fn get_from_storage<REv: ReactorEventT<BlockByHeight>>(
@@ -274,8 +443,12 @@ impl ItemFetcher<GlobalStorageTrie> for Fetcher<GlobalStorageTrie> {
         &mut self.responders
     }
 
-    fn peer_timeout(&self) -> Duration {
-        self.get_from_peer_timeout
+    fn requests(&mut self) -> &mut PeerRequests<Blake2bHash> {
+        &mut self.requests
+    }
+
+    fn peer_selection(&mut self) -> &mut PeerSelection<Blake2bHash> {
+        &mut self.peer_selection
     }
 
     fn get_from_storage<REv: ReactorEventT<GlobalStorageTrie>>(
@@ -301,7 +474,7 @@ where
     REv: ReactorEventT<T>,
 {
     type Event = Event<T>;
-    type ConstructionError = Infallible;
+    type ConstructionError = prometheus::Error;
 
     fn handle_event(
         &mut self,
@@ -316,6 +489,11 @@ where
                 peer,
                 responder,
             } => self.fetch(effect_builder, id, peer, responder),
+            Event::FetchFromAny {
+                id,
+                peers,
+                responder,
+            } => self.fetch_from_any(effect_builder, id, peers, responder),
             Event::GetFromStorageResult {
                 id,
                 peer,
@@ -326,21 +504,53 @@ where
             },
             Event::GotRemotely { item, source } => {
                 match source {
-                    Source::Peer(peer) => self.signal(
-                        item.id(),
-                        Some(FetchResult::FromPeer(item, peer.clone())),
-                        peer,
-                    ),
+                    Source::Peer(peer) => {
+                        let id = item.id();
+                        if let Err(error) = Self::validate(&item) {
+                            debug!(?id, %peer, %error, "invalid item");
+                            let mut effects = self.rejected_from_peer(effect_builder, id, peer);
+                            effects.extend(self.send_deferred(effect_builder));
+                            return effects;
+                        }
+                        if let Some(latency) = self.requests().responded(&peer, &id) {
+                            self.peer_selection().record_success(&peer, latency);
+                        }
+                        // Requests for the item to other peers don't need answering anymore.
+                        self.requests().cancel_all(&id);
+                        let mut effects =
+                            self.signal(id, Some(FetchResult::FromPeer(item, peer.clone())), peer);
+                        effects.extend(self.send_deferred(effect_builder));
+                        effects
+                    }
                     Source::Client => {
                         // TODO - we could possibly also handle this case
                         Effects::new()
                     }
                 }
             }
-            // We do nothing in the case of having an incoming deploy rejected.
-            Event::RejectedRemotely { .. } => Effects::new(),
-            Event::AbsentRemotely { id, peer } => self.signal(id, None, peer),
-            Event::TimeoutPeer { id, peer } => self.signal(id, None, peer),
+            Event::RejectedRemotely { item, source } => match source {
+                Source::Peer(peer) => {
+                    let mut effects = self.rejected_from_peer(effect_builder, item.id(), peer);
+                    effects.extend(self.send_deferred(effect_builder));
+                    effects
+                }
+                // We do nothing in the case of having a deploy from a client rejected.
+                Source::Client => Effects::new(),
+            },
+            Event::AbsentRemotely { id, peer } => {
+                self.requests().responded(&peer, &id);
+                let mut effects = self.failed_to_get_from_peer(effect_builder, id, peer);
+                effects.extend(self.send_deferred(effect_builder));
+                effects
+            }
+            Event::TimeoutPeer { request_id } => match self.requests().timed_out(request_id) {
+                Some((peer, id)) => {
+                    let mut effects = self.failed_to_get_from_peer(effect_builder, id, peer);
+                    effects.extend(self.send_deferred(effect_builder));
+                    effects
+                }
+                None => Effects::new(),
+            },
         }
     }
 }
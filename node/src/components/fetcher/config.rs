@@ -1,24 +1,76 @@
 use datasize::DataSize;
 use serde::{Deserialize, Serialize};
 
+use crate::utils::ConfigValidator;
+
 const DEFAULT_GET_FROM_PEER_TIMEOUT_SECS: u64 = 3;
+const DEFAULT_MAX_PARALLEL_REQUESTS: usize = 100;
+const DEFAULT_LATENCY_WEIGHT: f64 = 1.0;
+const DEFAULT_SUCCESS_RATE_WEIGHT: f64 = 5.0;
+const DEFAULT_QUEUE_DEPTH_WEIGHT: f64 = 0.1;
 
 /// Configuration options for fetching.
 #[derive(Copy, Clone, DataSize, Debug, Deserialize, Serialize)]
 pub struct Config {
     get_from_peer_timeout: u64,
+    /// Maximum number of requests to peers awaiting a response at a time, further ones are
+    /// deferred. Lowered automatically while the node is under load.
+    max_parallel_requests: usize,
+    /// Weight of a peer's average response time in seconds in its score, see `peer_selection`.
+    latency_weight: f64,
+    /// Weight of the share of recent requests a peer answered with the item in its score.
+    success_rate_weight: f64,
+    /// Weight of the number of requests queued to a peer in its score.
+    queue_depth_weight: f64,
 }
 
 impl Config {
     pub(crate) fn get_from_peer_timeout(&self) -> u64 {
         self.get_from_peer_timeout
     }
+
+    pub(crate) fn max_parallel_requests(&self) -> usize {
+        self.max_parallel_requests
+    }
+
+    pub(crate) fn latency_weight(&self) -> f64 {
+        self.latency_weight
+    }
+
+    pub(crate) fn success_rate_weight(&self) -> f64 {
+        self.success_rate_weight
+    }
+
+    pub(crate) fn queue_depth_weight(&self) -> f64 {
+        self.queue_depth_weight
+    }
+
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        validator.ensure_non_zero("get_from_peer_timeout", self.get_from_peer_timeout);
+        validator.ensure_non_zero("max_parallel_requests", self.max_parallel_requests);
+        for (field, weight) in &[
+            ("latency_weight", self.latency_weight),
+            ("success_rate_weight", self.success_rate_weight),
+            ("queue_depth_weight", self.queue_depth_weight),
+        ] {
+            validator.ensure(
+                weight.is_finite() && *weight >= 0.0,
+                field,
+                "must be a non-negative number",
+            );
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
             get_from_peer_timeout: DEFAULT_GET_FROM_PEER_TIMEOUT_SECS,
+            max_parallel_requests: DEFAULT_MAX_PARALLEL_REQUESTS,
+            latency_weight: DEFAULT_LATENCY_WEIGHT,
+            success_rate_weight: DEFAULT_SUCCESS_RATE_WEIGHT,
+            queue_depth_weight: DEFAULT_QUEUE_DEPTH_WEIGHT,
         }
     }
 }
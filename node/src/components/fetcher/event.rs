@@ -5,7 +5,10 @@ use serde::Serialize;
 
 use super::Item;
 use crate::{
-    effect::{announcements::DeployAcceptorAnnouncement, requests::FetcherRequest, Responder},
+    effect::{
+        announcements::DeployAcceptorAnnouncement, peer_requests::RequestId,
+        requests::FetcherRequest, Responder,
+    },
     types::{Deploy, NodeId},
     utils::Source,
 };
@@ -27,6 +30,12 @@ pub enum Event<T: Item> {
         peer: NodeId,
         responder: FetchResponder<T>,
     },
+    /// The initiating event to fetch an item by its id from the best of several peers.
+    FetchFromAny {
+        id: T::Id,
+        peers: Vec<NodeId>,
+        responder: FetchResponder<T>,
+    },
     /// The result of the `Fetcher` getting a item from the storage component.  If the
     /// result is `None`, the item should be requested from the peer.
     GetFromStorageResult {
@@ -48,8 +57,24 @@ pub enum Event<T: Item> {
     },
     /// An item was not available on the remote peer.
     AbsentRemotely { id: T::Id, peer: NodeId },
-    /// The timeout has elapsed and we should clean up state.
-    TimeoutPeer { id: T::Id, peer: NodeId },
+    /// The timeout of a request to a peer has elapsed and we should clean up state.
+    TimeoutPeer { request_id: RequestId },
+}
+
+impl<T: Item> Event<T> {
+    /// Returns the ID of the item the event concerns, if any.
+    pub(crate) fn item_id(&self) -> Option<T::Id> {
+        match self {
+            Event::Fetch { id, .. }
+            | Event::FetchFromAny { id, .. }
+            | Event::GetFromStorageResult { id, .. }
+            | Event::AbsentRemotely { id, .. } => Some(*id),
+            Event::GotRemotely { item, .. } | Event::RejectedRemotely { item, .. } => {
+                Some(item.id())
+            }
+            Event::TimeoutPeer { .. } => None,
+        }
+    }
 }
 
 impl<T: Item> From<FetcherRequest<NodeId, T>> for Event<T> {
@@ -64,6 +89,15 @@ impl<T: Item> From<FetcherRequest<NodeId, T>> for Event<T> {
                 peer,
                 responder,
             },
+            FetcherRequest::FetchFromAny {
+                id,
+                peers,
+                responder,
+            } => Event::FetchFromAny {
+                id,
+                peers,
+                responder,
+            },
         }
     }
 }
@@ -93,6 +127,12 @@ impl<T: Item> Display for Event<T> {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Event::Fetch { id, .. } => write!(formatter, "request to fetch item at hash {}", id),
+            Event::FetchFromAny { id, peers, .. } => write!(
+                formatter,
+                "request to fetch item at hash {} from any of {} peers",
+                id,
+                peers.len()
+            ),
             Event::GetFromStorageResult { id, maybe_item, .. } => {
                 if maybe_item.is_some() {
                     write!(formatter, "got {} from storage", id)
@@ -109,11 +149,9 @@ impl<T: Item> Display for Event<T> {
                 item.id(),
                 source
             ),
-            Event::TimeoutPeer { id, peer } => write!(
-                formatter,
-                "check get from peer timeout for {} with {}",
-                id, peer
-            ),
+            Event::TimeoutPeer { request_id } => {
+                write!(formatter, "check get from peer timeout of {}", request_id)
+            }
             Event::AbsentRemotely { id, peer } => {
                 write!(formatter, "Item {} was not available on {}", id, peer)
             }
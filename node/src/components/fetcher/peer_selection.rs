@@ -0,0 +1,284 @@
+//! Choice of the peer to fetch an item from.
+//!
+//! If several peers can serve an item, see `Event::FetchFromAny`, the fetcher asks the one with the
+//! highest score first, and the remaining ones in the order of their scores if it fails to deliver.
+//! The score of a peer rises with the share of recent requests it answered with the item, and falls
+//! with the average time its responses took and the number of requests to it which are still
+//! queued, each weighted as configured, see `score`.
+//!
+//! Peers the fetcher has not requested anything from yet are assumed to answer every request
+//! immediately, so they are tried out as soon as they are candidates. Peers which responded with an
+//! invalid item are banned, i.e. never asked for items again.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    hash::Hash,
+};
+
+use datasize::DataSize;
+
+use super::Config;
+use crate::types::{NodeId, TimeDiff};
+
+/// The weight of the latest outcome in the averages of a peer's success rate and latency.
+const SMOOTHING_FACTOR: f64 = 0.2;
+
+/// The weights of the measures making up the score of a peer.
+#[derive(Clone, Copy, DataSize, Debug, PartialEq)]
+pub(super) struct ScoringWeights {
+    /// Weight of the average latency, per second.
+    pub(super) latency: f64,
+    /// Weight of the success rate, between 0 and 1.
+    pub(super) success_rate: f64,
+    /// Weight of the queue depth, per queued request.
+    pub(super) queue_depth: f64,
+}
+
+impl From<&Config> for ScoringWeights {
+    fn from(config: &Config) -> Self {
+        ScoringWeights {
+            latency: config.latency_weight(),
+            success_rate: config.success_rate_weight(),
+            queue_depth: config.queue_depth_weight(),
+        }
+    }
+}
+
+/// The measured quality of a peer, as exponentially weighted moving averages.
+#[derive(Clone, Copy, DataSize, Debug, PartialEq)]
+pub(super) struct PeerStats {
+    /// Average time a response took, in seconds.
+    pub(super) latency_secs: f64,
+    /// Share of requests answered with the item.
+    pub(super) success_rate: f64,
+}
+
+impl Default for PeerStats {
+    fn default() -> Self {
+        PeerStats {
+            latency_secs: 0.0,
+            success_rate: 1.0,
+        }
+    }
+}
+
+impl PeerStats {
+    fn record(&mut self, success: bool, latency_secs: Option<f64>) {
+        let outcome = if success { 1.0 } else { 0.0 };
+        self.success_rate += SMOOTHING_FACTOR * (outcome - self.success_rate);
+        if let Some(latency_secs) = latency_secs {
+            self.latency_secs += SMOOTHING_FACTOR * (latency_secs - self.latency_secs);
+        }
+    }
+}
+
+/// Returns the score of a peer with the given stats and `queue_depth` requests queued to it.
+pub(super) fn score(weights: &ScoringWeights, stats: &PeerStats, queue_depth: usize) -> f64 {
+    weights.success_rate * stats.success_rate
+        - weights.latency * stats.latency_secs
+        - weights.queue_depth * queue_depth as f64
+}
+
+/// The stats of the peers, and the peers left to ask for the items being fetched.
+#[derive(DataSize, Debug)]
+pub(crate) struct PeerSelection<K>
+where
+    K: Copy + Eq + Hash + Debug,
+{
+    weights: ScoringWeights,
+    stats: HashMap<NodeId, PeerStats>,
+    /// The peers not asked yet for an item, by the item and the peer currently asked.
+    remaining: HashMap<(K, NodeId), Vec<NodeId>>,
+    /// The peers which responded with an invalid item.
+    banned: HashSet<NodeId>,
+}
+
+impl<K> PeerSelection<K>
+where
+    K: Copy + Eq + Hash + Debug,
+{
+    pub(super) fn new(weights: ScoringWeights) -> Self {
+        PeerSelection {
+            weights,
+            stats: HashMap::new(),
+            remaining: HashMap::new(),
+            banned: HashSet::new(),
+        }
+    }
+
+    /// Records that `peer` delivered an item after `latency`.
+    pub(super) fn record_success(&mut self, peer: &NodeId, latency: TimeDiff) {
+        let latency_secs = latency.millis() as f64 / 1000.0;
+        self.stats
+            .entry(peer.clone())
+            .or_default()
+            .record(true, Some(latency_secs));
+    }
+
+    /// Records that `peer` did not deliver an item, because it does not have it or timed out.
+    pub(super) fn record_failure(&mut self, peer: &NodeId) {
+        self.stats
+            .entry(peer.clone())
+            .or_default()
+            .record(false, None);
+    }
+
+    /// Bans `peer` for responding with an invalid item.
+    pub(super) fn ban(&mut self, peer: &NodeId) {
+        let _ = self.banned.insert(peer.clone());
+    }
+
+    /// Returns whether `peer` is banned.
+    pub(super) fn is_banned(&self, peer: &NodeId) -> bool {
+        self.banned.contains(peer)
+    }
+
+    /// Chooses the peer with the highest score among `candidates` which are not banned, given with
+    /// their queue depths, to ask for the item `key`, remembering the others in case it fails.
+    pub(super) fn choose(
+        &mut self,
+        key: K,
+        mut candidates: Vec<(NodeId, usize)>,
+    ) -> Option<NodeId> {
+        let banned = &self.banned;
+        candidates.retain(|(peer, _)| !banned.contains(peer));
+        let weights = self.weights;
+        let stats = &self.stats;
+        let score_of = |(peer, queue_depth): &(NodeId, usize)| {
+            let peer_stats = stats.get(peer).copied().unwrap_or_default();
+            score(&weights, &peer_stats, *queue_depth)
+        };
+        let best = (0..candidates.len()).max_by(|&left, &right| {
+            score_of(&candidates[left])
+                .partial_cmp(&score_of(&candidates[right]))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+        let (peer, _) = candidates.swap_remove(best);
+        if !candidates.is_empty() {
+            let others = candidates.into_iter().map(|(peer, _)| peer).collect();
+            let _ = self.remaining.insert((key, peer.clone()), others);
+        }
+        Some(peer)
+    }
+
+    /// Returns the peers not asked yet for the item `key`, after `failed_peer` did not deliver it.
+    pub(super) fn take_remaining(&mut self, key: K, failed_peer: &NodeId) -> Vec<NodeId> {
+        self.remaining
+            .remove(&(key, failed_peer.clone()))
+            .unwrap_or_default()
+    }
+
+    /// Forgets the peers left to ask for the item `key`, once it has been fetched.
+    pub(super) fn fetched(&mut self, key: &K) {
+        self.remaining
+            .retain(|(remaining_key, _), _| remaining_key != key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestRng;
+
+    const WEIGHTS: ScoringWeights = ScoringWeights {
+        latency: 1.0,
+        success_rate: 10.0,
+        queue_depth: 0.5,
+    };
+
+    #[test]
+    fn should_score_by_weighted_measures() {
+        let stats = PeerStats {
+            latency_secs: 2.0,
+            success_rate: 0.5,
+        };
+        assert!((score(&WEIGHTS, &stats, 4) - 1.0).abs() < 1e-9);
+
+        // A reliable peer is preferred over a fast one failing half the time...
+        let reliable = PeerStats {
+            latency_secs: 1.0,
+            success_rate: 1.0,
+        };
+        let fast = PeerStats {
+            latency_secs: 0.1,
+            success_rate: 0.5,
+        };
+        assert!(score(&WEIGHTS, &reliable, 0) > score(&WEIGHTS, &fast, 0));
+        // ...unless it is flooded with requests.
+        assert!(score(&WEIGHTS, &reliable, 20) < score(&WEIGHTS, &fast, 0));
+
+        // Without weights, all peers are equal.
+        let unweighted = ScoringWeights {
+            latency: 0.0,
+            success_rate: 0.0,
+            queue_depth: 0.0,
+        };
+        assert!(score(&unweighted, &reliable, 20).abs() < 1e-9);
+        assert!(score(&unweighted, &fast, 0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn should_move_averages_towards_outcomes() {
+        let mut stats = PeerStats::default();
+        stats.record(false, None);
+        assert!((stats.success_rate - 0.8).abs() < 1e-9);
+        assert!(stats.latency_secs.abs() < 1e-9);
+        stats.record(true, Some(1.0));
+        assert!((stats.success_rate - 0.84).abs() < 1e-9);
+        assert!((stats.latency_secs - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn should_choose_best_peer_and_keep_others() {
+        let mut rng = TestRng::new();
+        let (slow, failing, unknown) = (
+            NodeId::random(&mut rng),
+            NodeId::random(&mut rng),
+            NodeId::random(&mut rng),
+        );
+        let mut selection = PeerSelection::<u64>::new(WEIGHTS);
+        selection.record_success(&slow, TimeDiff::from(3_000));
+        selection.record_failure(&failing);
+        selection.record_failure(&failing);
+
+        let candidates = vec![
+            (slow.clone(), 0),
+            (failing.clone(), 0),
+            (unknown.clone(), 0),
+        ];
+        assert_eq!(
+            selection.choose(1, candidates.clone()),
+            Some(unknown.clone())
+        );
+        let mut remaining = selection.take_remaining(1, &unknown);
+        remaining.sort();
+        let mut expected = vec![slow.clone(), failing.clone()];
+        expected.sort();
+        assert_eq!(remaining, expected);
+        assert!(selection.take_remaining(1, &unknown).is_empty());
+
+        // A deep queue outweighs being untried.
+        let candidates = vec![(slow.clone(), 0), (failing, 0), (unknown, 10)];
+        assert_eq!(selection.choose(2, candidates), Some(slow));
+        selection.fetched(&2);
+        assert!(selection.remaining.is_empty());
+
+        assert_eq!(selection.choose(3, Vec::new()), None);
+    }
+
+    #[test]
+    fn should_not_choose_banned_peers() {
+        let mut rng = TestRng::new();
+        let (banned, other) = (NodeId::random(&mut rng), NodeId::random(&mut rng));
+        let mut selection = PeerSelection::<u64>::new(WEIGHTS);
+        selection.ban(&banned);
+        assert!(selection.is_banned(&banned));
+        assert!(!selection.is_banned(&other));
+
+        let candidates = vec![(banned.clone(), 0), (other.clone(), 10)];
+        assert_eq!(selection.choose(1, candidates), Some(other.clone()));
+        assert!(selection.take_remaining(1, &other).is_empty());
+        assert_eq!(selection.choose(2, vec![(banned, 0)]), None);
+    }
+}
@@ -17,16 +17,18 @@ use crate::{
         Responder,
     },
     protocol::Message,
-    reactor::{Reactor as ReactorTrait, Runner},
+    reactor::{QueueKind, Reactor as ReactorTrait, Runner},
     testing::{
+        malicious_peer::{Behavior, Corruptible, MaliciousPeer},
         network::{Network, NetworkedReactor},
         ConditionCheckReactor, TestRng,
     },
-    types::{Deploy, DeployHash, NodeId},
+    types::{Deploy, DeployHash, NodeId, Tag},
     utils::{Loadable, WithDir},
 };
 
 const TIMEOUT: Duration = Duration::from_secs(1);
+const QUIET_FOR: Duration = Duration::from_millis(50);
 
 /// Error type returned by the test reactor.
 #[derive(Debug, Error)]
@@ -72,12 +74,14 @@ reactor!(Reactor {
         network = infallible InMemoryNetwork::<Message>(event_queue, rng);
         storage = Storage(&WithDir::new(cfg.temp_dir.path(), cfg.storage_config));
         deploy_acceptor = infallible DeployAcceptor(cfg.deploy_acceptor_config);
-        deploy_fetcher = infallible Fetcher::<Deploy>(cfg.fetcher_config);
+        deploy_fetcher = Fetcher::<Deploy>("deploy_fetcher", cfg.fetcher_config, registry);
+        block_fetcher = Fetcher::<Block>("block_fetcher", cfg.fetcher_config, registry);
     }
 
     events: {
         network = Event<Message>;
         deploy_fetcher = Event<Deploy>;
+        block_fetcher = Event<Block>;
     }
 
     requests: {
@@ -112,7 +116,10 @@ impl Reactor {
         // announcements.
         match network_announcement {
             NetworkAnnouncement::MessageReceived { sender, payload } => match payload {
-                Message::GetRequest { serialized_id, .. } => {
+                Message::GetRequest {
+                    tag: Tag::Deploy,
+                    serialized_id,
+                } => {
                     let deploy_hash = match bincode::deserialize(&serialized_id) {
                         Ok(hash) => hash,
                         Err(error) => {
@@ -144,8 +151,40 @@ impl Reactor {
                     }
                 }
 
+                Message::GetRequest {
+                    tag: Tag::Block,
+                    serialized_id,
+                } => {
+                    let block_hash = match bincode::deserialize(&serialized_id) {
+                        Ok(hash) => hash,
+                        Err(error) => {
+                            error!(
+                                "failed to decode {:?} from {}: {}",
+                                serialized_id, sender, error
+                            );
+                            return Effects::new();
+                        }
+                    };
+
+                    effect_builder
+                        .get_block_from_storage(block_hash)
+                        .then(move |maybe_block| async move {
+                            match maybe_block.map(|block| Message::new_get_response(&block)) {
+                                Some(Ok(message)) => {
+                                    effect_builder.send_message(sender, message).await
+                                }
+                                Some(Err(error)) => {
+                                    error!("failed to create get-response: {}", error)
+                                }
+                                None => debug!("failed to get {} for {}", block_hash, sender),
+                            }
+                        })
+                        .ignore()
+                }
+
                 Message::GetResponse {
-                    serialized_item, ..
+                    tag: Tag::Deploy,
+                    serialized_item,
                 } => {
                     let deploy = match bincode::deserialize(&serialized_item) {
                         Ok(deploy) => Box::new(deploy),
@@ -165,6 +204,27 @@ impl Reactor {
                         }),
                     )
                 }
+                Message::GetResponse {
+                    tag: Tag::Block,
+                    serialized_item,
+                } => {
+                    let block = match bincode::deserialize(&serialized_item) {
+                        Ok(block) => Box::new(block),
+                        Err(error) => {
+                            error!("failed to decode block from {}: {}", sender, error);
+                            return Effects::new();
+                        }
+                    };
+
+                    self.dispatch_event(
+                        effect_builder,
+                        rng,
+                        ReactorEvent::BlockFetcher(Event::GotRemotely {
+                            item: block,
+                            source: Source::Peer(sender),
+                        }),
+                    )
+                }
                 msg => panic!("should not get {}", msg),
             },
             ann => panic!("should not received any network announcements: {:?}", ann),
@@ -180,6 +240,23 @@ impl NetworkedReactor for Reactor {
     }
 }
 
+/// Gives access to the fetcher of items of type `T`.
+trait HasFetcher<T: Item> {
+    fn fetcher(&self) -> &Fetcher<T>;
+}
+
+impl HasFetcher<Deploy> for Reactor {
+    fn fetcher(&self) -> &Fetcher<Deploy> {
+        &self.deploy_fetcher
+    }
+}
+
+impl HasFetcher<Block> for Reactor {
+    fn fetcher(&self) -> &Fetcher<Block> {
+        &self.block_fetcher
+    }
+}
+
 fn announce_deploy_received(
     deploy: Deploy,
     responder: Option<Responder<Result<(), deploy_acceptor::Error>>>,
@@ -191,7 +268,9 @@ fn announce_deploy_received(
     }
 }
 
-type FetchedDeployResult = Arc<Mutex<(bool, Option<FetchResult<Deploy, NodeId>>)>>;
+type FetchedResult<T> = Arc<Mutex<(bool, Option<FetchResult<T, NodeId>>)>>;
+
+type FetchedDeployResult = FetchedResult<Deploy>;
 
 fn fetch_deploy(
     deploy_hash: DeployHash,
@@ -210,6 +289,36 @@ fn fetch_deploy(
     }
 }
 
+/// Fetches the item `id` from the best of `peers`, by sending the request to the fetcher directly.
+fn fetch_from_any<T>(
+    id: T::Id,
+    peers: Vec<NodeId>,
+    fetched: FetchedResult<T>,
+) -> impl FnOnce(EffectBuilder<ReactorEvent>) -> Effects<ReactorEvent>
+where
+    T: Item + 'static,
+    <T as Item>::Id: 'static,
+    ReactorEvent: From<Event<T>>,
+{
+    move |effect_builder: EffectBuilder<ReactorEvent>| {
+        effect_builder
+            .make_request(
+                |responder| Event::<T>::FetchFromAny {
+                    id,
+                    peers,
+                    responder,
+                },
+                QueueKind::Regular,
+            )
+            .then(move |maybe_item| async move {
+                let mut result = fetched.lock().unwrap();
+                result.0 = true;
+                result.1 = maybe_item;
+            })
+            .ignore()
+    }
+}
+
 /// Store a deploy on a target node.
 async fn store_deploy(
     deploy: &Deploy,
@@ -240,6 +349,22 @@ async fn store_deploy(
         .await;
 }
 
+/// Store a block on a target node.
+async fn store_block(
+    block: &Block,
+    node_id: &NodeId,
+    network: &mut Network<Reactor>,
+    rng: &mut TestRng,
+) {
+    let block = Box::new(block.clone());
+    network
+        .process_injected_effect_on(node_id, |effect_builder| {
+            effect_builder.put_block_to_storage(block).ignore()
+        })
+        .await;
+    network.settle(rng, QUIET_FOR, TIMEOUT).await;
+}
+
 async fn assert_settled(
     node_id: &NodeId,
     deploy_hash: DeployHash,
@@ -450,3 +575,231 @@ async fn should_timeout_fetch_from_peer() {
 
     NetworkController::<Message>::remove_active();
 }
+
+/// Returns the fetcher of items of type `T` on the given node.
+fn fetcher_on<'a, T>(network: &'a Network<Reactor>, node_id: &NodeId) -> &'a Fetcher<T>
+where
+    T: Item,
+    Reactor: HasFetcher<T>,
+{
+    HasFetcher::<T>::fetcher(network.nodes()[node_id].reactor().inner())
+}
+
+/// Has the requesting node fetch `item` from either a malicious peer or the holding node, asking
+/// the malicious peer first.
+///
+/// A malicious peer serving a corrupted item must be banned and the holding node asked right away.
+/// Otherwise the holding node must only be asked once the request to the malicious peer timed out.
+/// Either way, the fetch must complete with the item from the holding node.
+async fn fetch_via_malicious_peer<T>(
+    network: &mut Network<Reactor>,
+    rng: &mut TestRng,
+    requesting_node: &NodeId,
+    holding_node: &NodeId,
+    malicious_peer: &MaliciousPeer,
+    item: T,
+) where
+    T: Corruptible + PartialEq + 'static,
+    <T as Item>::Id: 'static,
+    ReactorEvent: From<Event<T>>,
+    Reactor: HasFetcher<T>,
+{
+    let behavior = malicious_peer.behavior();
+    let malicious_node_id = malicious_peer.node_id().clone();
+
+    // Neither peer has been asked for anything yet, so they score equally and the last one is asked
+    // first.
+    let fetched = Arc::new(Mutex::new((false, None)));
+    network
+        .process_injected_effect_on(
+            requesting_node,
+            fetch_from_any(
+                item.id(),
+                vec![holding_node.clone(), malicious_node_id.clone()],
+                Arc::clone(&fetched),
+            ),
+        )
+        .await;
+
+    // Crank until the request has been sent to the malicious peer.
+    let request_recipient = malicious_node_id.clone();
+    network
+        .crank_until(
+            requesting_node,
+            rng,
+            move |event: &ReactorEvent| match event {
+                ReactorEvent::NetworkRequest(NetworkRequest::SendMessage {
+                    dest,
+                    payload: Message::GetRequest { .. },
+                    ..
+                }) => *dest == request_recipient,
+                _ => false,
+            },
+            TIMEOUT,
+        )
+        .await;
+
+    // Deliver whatever the malicious peer responds with.
+    if let Some(response) = malicious_peer.respond_to_get_request(&item) {
+        let sender = malicious_node_id.clone();
+        network
+            .process_injected_effect_on(requesting_node, |effect_builder| {
+                effect_builder
+                    .announce_message_received(sender, response)
+                    .ignore()
+            })
+            .await;
+    }
+
+    if behavior != Behavior::ServeCorrupted {
+        network.settle(rng, QUIET_FOR, TIMEOUT).await;
+
+        // No response may have been accepted, and the holding node not been asked, before the
+        // timeout.
+        assert!(
+            !fetched.lock().unwrap().0,
+            "{:?}: fetch should not have completed",
+            behavior
+        );
+        assert_eq!(
+            fetcher_on::<T>(network, requesting_node)
+                .requests
+                .queue_depth(holding_node),
+            0,
+            "{:?}: holding node should not have been asked yet",
+            behavior
+        );
+
+        // Advance time.
+        let secs_to_advance = Config::default().get_from_peer_timeout();
+        time::pause();
+        time::advance(Duration::from_secs(secs_to_advance + 10)).await;
+        time::resume();
+    }
+
+    // A corrupted item has the holding node asked right away, without waiting for the timeout.
+    let has_responded = |_nodes: &HashMap<NodeId, Runner<ConditionCheckReactor<Reactor>>>| {
+        fetched.lock().unwrap().0
+    };
+    network.settle_on(rng, has_responded, TIMEOUT).await;
+
+    assert_eq!(
+        fetcher_on::<T>(network, requesting_node)
+            .peer_selection
+            .is_banned(&malicious_node_id),
+        behavior == Behavior::ServeCorrupted,
+        "{:?}: only a peer serving a corrupted item should be banned",
+        behavior
+    );
+    let expected_result = Some(FetchResult::FromPeer(Box::new(item), holding_node.clone()));
+    assert_eq!(fetched.lock().unwrap().1, expected_result);
+}
+
+/// Has the requesting node fetch a deploy, see `fetch_via_malicious_peer`.
+async fn fetch_deploy_via_malicious_peer(behavior: Behavior) {
+    const NETWORK_SIZE: usize = 2;
+
+    NetworkController::<Message>::create_active();
+    let (mut network, mut rng, node_ids) = {
+        let mut network = Network::<Reactor>::new();
+        let mut rng = TestRng::new();
+        let node_ids = network.add_nodes(&mut rng, NETWORK_SIZE).await;
+        (network, rng, node_ids)
+    };
+
+    // Create a random deploy.
+    let deploy = Deploy::random(&mut rng);
+    let deploy_hash = *deploy.id();
+
+    let holding_node = node_ids[0].clone();
+    let requesting_node = node_ids[1].clone();
+    let malicious_peer = MaliciousPeer::new(&mut rng, behavior);
+
+    // Store deploy on holding node.
+    store_deploy(&deploy, &holding_node, &mut network, None, &mut rng).await;
+
+    fetch_via_malicious_peer(
+        &mut network,
+        &mut rng,
+        &requesting_node,
+        &holding_node,
+        &malicious_peer,
+        deploy.clone(),
+    )
+    .await;
+
+    // Only the deploy from the holding node should have been stored.
+    let stored_deploy = network.nodes()[&requesting_node]
+        .reactor()
+        .inner()
+        .storage
+        .get_deploy_by_hash(deploy_hash);
+    assert_eq!(stored_deploy, Some(deploy));
+
+    NetworkController::<Message>::remove_active();
+}
+
+/// Has the requesting node fetch a block, see `fetch_via_malicious_peer`.
+async fn fetch_block_via_malicious_peer(behavior: Behavior) {
+    const NETWORK_SIZE: usize = 2;
+
+    NetworkController::<Message>::create_active();
+    let (mut network, mut rng, node_ids) = {
+        let mut network = Network::<Reactor>::new();
+        let mut rng = TestRng::new();
+        let node_ids = network.add_nodes(&mut rng, NETWORK_SIZE).await;
+        (network, rng, node_ids)
+    };
+
+    // Create a random block.
+    let block = Block::random(&mut rng);
+
+    let holding_node = node_ids[0].clone();
+    let requesting_node = node_ids[1].clone();
+    let malicious_peer = MaliciousPeer::new(&mut rng, behavior);
+
+    // Store block on holding node.
+    store_block(&block, &holding_node, &mut network, &mut rng).await;
+
+    fetch_via_malicious_peer(
+        &mut network,
+        &mut rng,
+        &requesting_node,
+        &holding_node,
+        &malicious_peer,
+        block,
+    )
+    .await;
+
+    NetworkController::<Message>::remove_active();
+}
+
+#[tokio::test]
+async fn should_reject_corrupted_deploy_from_peer() {
+    fetch_deploy_via_malicious_peer(Behavior::ServeCorrupted).await
+}
+
+#[tokio::test]
+async fn should_timeout_stalled_transfer_from_peer() {
+    fetch_deploy_via_malicious_peer(Behavior::StallMidTransfer).await
+}
+
+#[tokio::test]
+async fn should_timeout_peer_never_providing_deploy() {
+    fetch_deploy_via_malicious_peer(Behavior::NeverProvide).await
+}
+
+#[tokio::test]
+async fn should_reject_corrupted_block_from_peer() {
+    fetch_block_via_malicious_peer(Behavior::ServeCorrupted).await
+}
+
+#[tokio::test]
+async fn should_timeout_stalled_block_transfer_from_peer() {
+    fetch_block_via_malicious_peer(Behavior::StallMidTransfer).await
+}
+
+#[tokio::test]
+async fn should_timeout_peer_never_providing_block() {
+    fetch_block_via_malicious_peer(Behavior::NeverProvide).await
+}
@@ -193,6 +193,9 @@ impl<T: Item + 'static, REv: ReactorEventT<T>> Gossiper<T, REv> {
     }
 
     /// Gossips the given item ID to `count` random peers excluding the indicated ones.
+    ///
+    /// Under load, fewer peers are chosen. The table still counts `count` as requested, so the
+    /// shortfall is released from its in-flight count once the network reports back.
     fn gossip(
         &mut self,
         effect_builder: EffectBuilder<REv>,
@@ -202,7 +205,11 @@ impl<T: Item + 'static, REv: ReactorEventT<T>> Gossiper<T, REv> {
     ) -> Effects<Event<T>> {
         let message = Message::Gossip(item_id);
         effect_builder
-            .gossip_message(message, count, exclude_peers)
+            .gossip_message(
+                message,
+                effect_builder.scale_concurrency(count),
+                exclude_peers,
+            )
             .event(move |peers| Event::GossipedTo {
                 item_id,
                 requested_count: count,
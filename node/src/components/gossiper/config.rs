@@ -7,6 +7,7 @@ use tracing::error;
 
 #[cfg(test)]
 use super::Error;
+use crate::utils::ConfigValidator;
 
 const DEFAULT_INFECTION_TARGET: u8 = 3;
 const DEFAULT_SATURATION_LIMIT_PERCENT: u8 = 80;
@@ -14,11 +15,11 @@ pub(super) const MAX_SATURATION_LIMIT_PERCENT: u8 = 99;
 pub(super) const DEFAULT_FINISHED_ENTRY_DURATION_SECS: u64 = 60;
 const DEFAULT_GOSSIP_REQUEST_TIMEOUT_SECS: u64 = 10;
 const DEFAULT_GET_REMAINDER_TIMEOUT_SECS: u64 = 60;
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 const SMALL_TIMEOUTS_FINISHED_ENTRY_DURATION_SECS: u64 = 2;
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 const SMALL_TIMEOUTS_GOSSIP_REQUEST_TIMEOUT_SECS: u64 = 1;
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 const SMALL_TIMEOUTS_GET_REMAINDER_TIMEOUT_SECS: u64 = 1;
 
 /// Configuration options for gossiping.
@@ -69,7 +70,7 @@ impl Config {
         })
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub(crate) fn new_with_small_timeouts() -> Self {
         Config {
             finished_entry_duration_secs: SMALL_TIMEOUTS_FINISHED_ENTRY_DURATION_SECS,
@@ -98,6 +99,25 @@ impl Config {
     pub(crate) fn get_remainder_timeout_secs(&self) -> u64 {
         self.get_remainder_timeout_secs
     }
+
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        validator.ensure_non_zero("infection_target", self.infection_target);
+        validator.ensure_non_zero(
+            "gossip_request_timeout_secs",
+            self.gossip_request_timeout_secs,
+        );
+        validator.ensure_non_zero(
+            "get_remainder_timeout_secs",
+            self.get_remainder_timeout_secs,
+        );
+        // Otherwise entries may be forgotten while responses to gossip requests are still due.
+        validator.ensure(
+            self.finished_entry_duration_secs >= self.gossip_request_timeout_secs,
+            "finished_entry_duration_secs",
+            "must not be less than `gossip_request_timeout_secs`",
+        );
+    }
 }
 
 impl Default for Config {
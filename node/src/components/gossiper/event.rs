@@ -42,6 +42,22 @@ pub enum Event<T: Item> {
     },
 }
 
+impl<T: Item> Event<T> {
+    /// Returns the ID of the item the event concerns.
+    pub(crate) fn item_id(&self) -> T::Id {
+        match self {
+            Event::ItemReceived { item_id, .. }
+            | Event::GossipedTo { item_id, .. }
+            | Event::CheckGossipTimeout { item_id, .. }
+            | Event::CheckGetFromPeerTimeout { item_id, .. }
+            | Event::GetFromHolderResult { item_id, .. } => *item_id,
+            Event::MessageReceived { message, .. } => match message {
+                Message::Gossip(item_id) | Message::GossipResponse { item_id, .. } => *item_id,
+            },
+        }
+    }
+}
+
 impl<T: Item> Display for Event<T> {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
         match self {
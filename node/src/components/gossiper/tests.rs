@@ -36,6 +36,7 @@ use crate::{
     protocol::Message as NodeMessage,
     reactor::{self, EventQueueHandle, Runner},
     testing::{
+        malicious_peer::{Behavior, MaliciousPeer},
         network::{Network, NetworkedReactor},
         ConditionCheckReactor, TestRng,
     },
@@ -150,7 +151,7 @@ impl reactor::Reactor for Reactor {
 
         let (storage_config, storage_tempdir) = storage::Config::default_for_tests();
         let storage_withdir = WithDir::new(storage_tempdir.path(), storage_config);
-        let storage = Storage::new(&storage_withdir).unwrap();
+        let storage = Storage::new(&storage_withdir, registry).unwrap();
 
         let contract_runtime_config = contract_runtime::Config::default();
         let contract_runtime =
@@ -405,6 +406,55 @@ async fn should_gossip() {
     }
 }
 
+#[tokio::test]
+async fn should_send_deploy_to_peer_without_get_requests() {
+    const TIMEOUT: Duration = Duration::from_secs(2);
+    const QUIET_FOR: Duration = Duration::from_millis(50);
+
+    NetworkController::<NodeMessage>::create_active();
+    let tap = NetworkController::<NodeMessage>::tap();
+    let mut network = Network::<Reactor>::new();
+    let mut rng = crate::new_rng();
+    let node_ids = network.add_nodes(&mut rng, 2).await;
+
+    // Give the deploy to node 0 only.
+    let deploy = Box::new(Deploy::random(&mut rng));
+    network
+        .process_injected_effect_on(&node_ids[0], announce_deploy_received(deploy, None))
+        .await;
+
+    // Node 0 gossips the deploy, then sends it to node 1 in response.
+    let (holder, peer) = (node_ids[0].clone(), node_ids[1].clone());
+    tap.expect_eventually(
+        &mut network,
+        &mut rng,
+        "deploy sent from node 0 to node 1",
+        TIMEOUT,
+        |messages| {
+            messages.iter().any(|message| {
+                message.sender == holder
+                    && message.recipient == peer
+                    && matches!(
+                        message.payload,
+                        NodeMessage::GetResponse {
+                            tag: Tag::Deploy,
+                            ..
+                        }
+                    )
+            })
+        },
+    )
+    .await;
+    network.settle(&mut rng, QUIET_FOR, TIMEOUT).await;
+
+    // Gossip responses replace get requests, which are only sent once a holder timed out.
+    tap.expect_no_messages_of_type("get requests", |message| {
+        matches!(message.payload, NodeMessage::GetRequest { .. })
+    });
+
+    NetworkController::<NodeMessage>::remove_active();
+}
+
 #[tokio::test]
 async fn should_get_from_alternate_source() {
     const NETWORK_SIZE: usize = 3;
@@ -553,3 +603,107 @@ async fn should_timeout_gossip_response() {
 
     NetworkController::<NodeMessage>::remove_active();
 }
+
+#[tokio::test]
+async fn should_get_from_honest_holder_if_advertiser_misbehaves() {
+    const NETWORK_SIZE: usize = 2;
+    const POLL_DURATION: Duration = Duration::from_millis(10);
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    let mut rng = crate::new_rng();
+
+    for behavior in Behavior::ALL.iter() {
+        NetworkController::<NodeMessage>::create_active();
+        let mut network = Network::<Reactor>::new();
+
+        // Add `NETWORK_SIZE` nodes: node 0 is the victim, node 1 an honest holder.
+        let node_ids = network.add_nodes(&mut rng, NETWORK_SIZE).await;
+
+        // Create random deploy.
+        let deploy = Box::new(Deploy::random(&mut rng));
+        let deploy_id = *deploy.id();
+
+        // Have the malicious peer gossip the deploy to node 0.
+        let malicious_peer = MaliciousPeer::new(&mut rng, *behavior);
+        let sender = malicious_peer.node_id().clone();
+        network
+            .process_injected_effect_on(&node_ids[0], |effect_builder| {
+                effect_builder
+                    .announce_message_received(
+                        sender,
+                        NodeMessage::DeployGossiper(Message::Gossip(deploy_id)),
+                    )
+                    .ignore()
+            })
+            .await;
+
+        // Run node 0 until it has asked the malicious peer for the deploy.
+        let malicious_node_id = malicious_peer.node_id().clone();
+        let sent_gossip_response = move |event: &Event| -> bool {
+            match event {
+                Event::NetworkRequest(NetworkRequest::SendMessage {
+                    dest,
+                    payload:
+                        NodeMessage::DeployGossiper(Message::GossipResponse {
+                            is_already_held: false,
+                            ..
+                        }),
+                    ..
+                }) => *dest == malicious_node_id,
+                _ => false,
+            }
+        };
+        network
+            .crank_until(&node_ids[0], &mut rng, sent_gossip_response, TIMEOUT)
+            .await;
+
+        // Deliver whatever the malicious peer responds with.
+        if let Some(response) = malicious_peer.respond_to_get_request(&*deploy) {
+            let sender = malicious_peer.node_id().clone();
+            network
+                .process_injected_effect_on(&node_ids[0], |effect_builder| {
+                    effect_builder
+                        .announce_message_received(sender, response)
+                        .ignore()
+                })
+                .await;
+        }
+
+        // Give the deploy to node 1 to be gossiped.  Node 0 will keep waiting for the malicious
+        // peer, recording node 1 as an alternative holder.
+        network
+            .process_injected_effect_on(
+                &node_ids[1],
+                announce_deploy_received(deploy.clone(), None),
+            )
+            .await;
+        network.settle(&mut rng, POLL_DURATION, TIMEOUT).await;
+
+        let deploy_held = |nodes: &HashMap<NodeId, Runner<ConditionCheckReactor<Reactor>>>| {
+            let runner = nodes.get(&node_ids[0]).unwrap();
+            runner
+                .reactor()
+                .inner()
+                .storage
+                .get_deploy_by_hash(deploy_id)
+                .map(|retrieved_deploy| retrieved_deploy == *deploy)
+                .unwrap_or_default()
+        };
+        assert!(
+            !deploy_held(network.nodes()),
+            "{:?}: node 0 should not hold the deploy yet",
+            malicious_peer.behavior()
+        );
+
+        // Advance time to trigger node 0's timeout causing it to request the deploy from node 1.
+        let secs_to_advance = Config::default().get_remainder_timeout_secs();
+        time::pause();
+        time::advance(Duration::from_secs(secs_to_advance)).await;
+        time::resume();
+        debug!("advanced time by {} secs", secs_to_advance);
+
+        network.settle_on(&mut rng, deploy_held, TIMEOUT).await;
+
+        NetworkController::<NodeMessage>::remove_active();
+    }
+}
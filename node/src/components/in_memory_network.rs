@@ -278,6 +278,13 @@
 //!
 //! # }); // end of tokio::block_on
 //! ```
+//!
+//! # Tapping messages
+//!
+//! `NetworkController::tap` starts recording all messages sent on the active network. The returned
+//! `MessageTap` can then be used to assert on the traffic between nodes, see the `tap` module.
+
+mod tap;
 
 use std::{
     any::Any,
@@ -296,15 +303,16 @@ use tracing::{debug, error, info, warn};
 use crate::{
     components::Component,
     effect::{
-        announcements::NetworkAnnouncement, requests::NetworkRequest, EffectBuilder, EffectExt,
-        Effects,
+        announcements::NetworkAnnouncement,
+        requests::{DeliveryStatus, NetworkRequest},
+        EffectBuilder, EffectExt, Effects,
     },
     logging,
     reactor::{EventQueueHandle, QueueKind},
-    testing::TestRng,
     types::NodeId,
     NodeRng,
 };
+pub use tap::{MessageTap, TappedMessage};
 
 /// A network.
 type Network<P> = Arc<RwLock<HashMap<NodeId, mpsc::UnboundedSender<(NodeId, P)>>>>;
@@ -338,6 +346,9 @@ thread_local! {
 pub struct NetworkController<P> {
     /// Channels for network communication.
     nodes: Network<P>,
+
+    /// Recorder of the messages sent, shared by all nodes.
+    tap: MessageTap<P>,
 }
 
 impl<P> NetworkController<P>
@@ -349,6 +360,7 @@ where
         let _ = logging::init();
         NetworkController {
             nodes: Default::default(),
+            tap: Default::default(),
         }
     }
 
@@ -382,6 +394,29 @@ where
         );
     }
 
+    /// Starts recording the messages sent on the active network, returning the recorder.
+    ///
+    /// Calling it again returns the same recorder, without clearing it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no active network or the active network is not of the correct message
+    /// type.
+    pub fn tap() -> MessageTap<P> {
+        ACTIVE_NETWORK.with(|active_network| {
+            let tap = active_network
+                .borrow()
+                .as_ref()
+                .expect("tried to tap without active network set")
+                .downcast_ref::<Self>()
+                .expect("active network has wrong message type")
+                .tap
+                .clone();
+            tap.enable();
+            tap
+        })
+    }
+
     /// Creates an in-memory network component on the active network.
     ///
     /// # Panics
@@ -390,7 +425,7 @@ where
     /// network is not of the correct message type.
     pub fn create_node<REv>(
         event_queue: EventQueueHandle<REv>,
-        rng: &mut TestRng,
+        rng: &mut NodeRng,
     ) -> InMemoryNetwork<P>
     where
         REv: From<NetworkAnnouncement<NodeId, P>> + Send,
@@ -433,12 +468,17 @@ where
     pub(crate) fn create_node_local<REv>(
         &self,
         event_queue: EventQueueHandle<REv>,
-        rng: &mut TestRng,
+        rng: &mut NodeRng,
     ) -> InMemoryNetwork<P>
     where
         REv: From<NetworkAnnouncement<NodeId, P>> + Send,
     {
-        InMemoryNetwork::new_with_data(event_queue, NodeId::random(rng), self.nodes.clone())
+        InMemoryNetwork::new_with_data(
+            event_queue,
+            NodeId::random(rng),
+            self.nodes.clone(),
+            self.tap.clone(),
+        )
     }
 }
 
@@ -450,6 +490,9 @@ pub struct InMemoryNetwork<P> {
 
     /// The nodes map, contains the incoming channel for each virtual node.
     nodes: Network<P>,
+
+    /// Recorder of the messages sent.
+    tap: MessageTap<P>,
 }
 
 impl<P> InMemoryNetwork<P>
@@ -471,6 +514,7 @@ where
         event_queue: EventQueueHandle<REv>,
         node_id: NodeId,
         nodes: Network<P>,
+        tap: MessageTap<P>,
     ) -> Self
     where
         REv: From<NetworkAnnouncement<NodeId, P>> + Send,
@@ -486,7 +530,11 @@ where
 
         tokio::spawn(receiver_task(event_queue, receiver));
 
-        InMemoryNetwork { node_id, nodes }
+        InMemoryNetwork {
+            node_id,
+            nodes,
+            tap,
+        }
     }
 
     /// Returns this node's ID.
@@ -498,28 +546,40 @@ where
 
 impl<P> InMemoryNetwork<P>
 where
-    P: Display,
+    P: Display + Clone,
 {
     /// Internal helper, sends a payload to a node, ignoring but logging all errors.
+    ///
+    /// Returns whether the payload was handed to the recipient.
     fn send(
         &self,
         nodes: &HashMap<NodeId, mpsc::UnboundedSender<(NodeId, P)>>,
         dest: NodeId,
         payload: P,
-    ) {
+    ) -> bool {
         if dest == self.node_id {
             panic!("can't send message to self");
         }
 
         match nodes.get(&dest) {
             Some(sender) => {
+                self.tap.record(|| TappedMessage {
+                    sender: self.node_id.clone(),
+                    recipient: dest.clone(),
+                    payload: payload.clone(),
+                });
                 if let Err(SendError((_, msg))) = sender.send((self.node_id.clone(), payload)) {
                     warn!(%dest, %msg, "could not send message (send error)");
 
                     // We do nothing else, the message is just dropped.
+                    return false;
                 }
+                true
+            }
+            None => {
+                info!(%dest, %payload, "dropping message to non-existent recipient");
+                false
             }
-            None => info!(%dest, %payload, "dropping message to non-existent recipient"),
         }
     }
 }
@@ -555,7 +615,32 @@ where
 
                 responder.respond(()).ignore()
             }
-            NetworkRequest::Broadcast { payload, responder } => {
+            NetworkRequest::SendMessageConfirmed {
+                dest,
+                payload,
+                responder,
+            } => {
+                if dest == self.node_id {
+                    panic!("can't send message to self");
+                }
+
+                // Handing the message to the recipient is as good as writing it to a connection.
+                let status = match self.nodes.read() {
+                    Ok(guard) if self.send(&guard, dest, payload) => DeliveryStatus::Written,
+                    Ok(_) => DeliveryStatus::Dropped,
+                    Err(_) => {
+                        error!("network lock has been poisoned");
+                        DeliveryStatus::Dropped
+                    }
+                };
+
+                responder.respond(status).ignore()
+            }
+            // The in-memory network does not know which nodes are validators.
+            NetworkRequest::Broadcast { payload, responder }
+            | NetworkRequest::BroadcastToValidators {
+                payload, responder, ..
+            } => {
                 if let Ok(guard) = self.nodes.read() {
                     for dest in guard.keys().filter(|&node_id| node_id != &self.node_id) {
                         self.send(&guard, dest.clone(), payload.clone());
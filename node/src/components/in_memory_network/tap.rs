@@ -0,0 +1,199 @@
+//! Recording of the messages sent over an in-memory network, and assertions on them.
+//!
+//! A tap is obtained from the active network through `NetworkController::tap`, after which every
+//! message sent to a node is recorded, so tests can state what should and should not have been
+//! sent, e.g.
+//!
+//! ```ignore
+//! let tap = NetworkController::<Message>::tap();
+//! // ...
+//! tap.expect_eventually(&mut network, &mut rng, "deploy sent", TIMEOUT, |messages| {
+//!     messages.iter().any(|message| matches!(message.payload, Message::GetResponse { .. }))
+//! })
+//! .await;
+//! tap.expect_no_messages_of_type("get requests", |message| {
+//!     matches!(message.payload, Message::GetRequest { .. })
+//! });
+//! ```
+
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    reactor::Reactor,
+    testing::network::{Network, NetworkedReactor},
+    types::NodeId,
+    NodeRng,
+};
+
+/// Maximum number of recorded messages listed when an expectation fails.
+const MAX_LISTED_MESSAGES: usize = 50;
+
+/// A message sent to a node of the in-memory network.
+#[derive(Clone, Debug)]
+pub struct TappedMessage<P> {
+    /// The node sending the message.
+    pub sender: NodeId,
+    /// The node the message was sent to.
+    pub recipient: NodeId,
+    /// The message itself.
+    pub payload: P,
+}
+
+impl<P: Display> Display for TappedMessage<P> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{} -> {}: {}",
+            self.sender, self.recipient, self.payload
+        )
+    }
+}
+
+/// Records the messages sent between the nodes of an in-memory network once enabled.
+pub struct MessageTap<P> {
+    /// The messages recorded so far, oldest first, `None` while the tap is disabled.
+    messages: Arc<Mutex<Option<Vec<TappedMessage<P>>>>>,
+}
+
+impl<P> Clone for MessageTap<P> {
+    fn clone(&self) -> Self {
+        MessageTap {
+            messages: Arc::clone(&self.messages),
+        }
+    }
+}
+
+impl<P> Default for MessageTap<P> {
+    fn default() -> Self {
+        MessageTap {
+            messages: Default::default(),
+        }
+    }
+}
+
+impl<P> Debug for MessageTap<P> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self.lock().as_ref() {
+            Some(messages) => write!(formatter, "MessageTap({} messages)", messages.len()),
+            None => write!(formatter, "MessageTap(disabled)"),
+        }
+    }
+}
+
+impl<P> MessageTap<P> {
+    /// Starts recording messages, if not recording already.
+    pub(super) fn enable(&self) {
+        self.lock().get_or_insert_with(Vec::new);
+    }
+
+    /// Records the message created by `message`, if enabled.
+    pub(super) fn record<F: FnOnce() -> TappedMessage<P>>(&self, message: F) {
+        if let Some(messages) = self.lock().as_mut() {
+            messages.push(message());
+        }
+    }
+
+    /// Forgets all messages recorded so far.
+    pub fn clear(&self) {
+        if let Some(messages) = self.lock().as_mut() {
+            messages.clear();
+        }
+    }
+
+    /// Returns the result of `inspect` on the messages recorded so far, oldest first.
+    pub fn inspect<T, F: FnOnce(&[TappedMessage<P>]) -> T>(&self, inspect: F) -> T {
+        inspect(self.lock().as_deref().unwrap_or_default())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Vec<TappedMessage<P>>>> {
+        self.messages.lock().expect("message tap lock poisoned")
+    }
+}
+
+impl<P: Clone> MessageTap<P> {
+    /// Returns the messages recorded so far, oldest first.
+    pub fn messages(&self) -> Vec<TappedMessage<P>> {
+        self.inspect(<[_]>::to_vec)
+    }
+}
+
+impl<P: Display> MessageTap<P> {
+    /// Asserts that no recorded message is of the type `is_of_type` selects.
+    ///
+    /// # Panics
+    ///
+    /// Panics listing the offending messages if there are any.
+    pub fn expect_no_messages_of_type<F>(&self, description: &str, is_of_type: F)
+    where
+        F: Fn(&TappedMessage<P>) -> bool,
+    {
+        let offending = self.inspect(|messages| {
+            let offending: Vec<_> = messages
+                .iter()
+                .filter(|message| is_of_type(message))
+                .collect();
+            if offending.is_empty() {
+                None
+            } else {
+                Some(listing(&offending))
+            }
+        });
+        if let Some(offending) = offending {
+            panic!("expected no {}, but got:\n{}", description, offending);
+        }
+    }
+
+    /// Runs every node of `network` until `condition` holds for the recorded messages.
+    ///
+    /// # Panics
+    ///
+    /// Panics listing the recorded messages if the `condition` is not reached inside of `within`.
+    pub async fn expect_eventually<R, F>(
+        &self,
+        network: &mut Network<R>,
+        rng: &mut NodeRng,
+        description: &str,
+        within: Duration,
+        condition: F,
+    ) where
+        R: Reactor + NetworkedReactor,
+        R::Config: Default,
+        <R as Reactor>::Error: Debug,
+        R::Event: Serialize,
+        R::Error: From<prometheus::Error>,
+        F: Fn(&[TappedMessage<P>]) -> bool,
+    {
+        let reached = network
+            .try_settle_on(rng, |_| self.inspect(&condition), within)
+            .await;
+        if !reached {
+            let recorded = self.inspect(|messages| listing(&messages.iter().collect::<Vec<_>>()));
+            panic!(
+                "expected {} within {:?}, but only got:\n{}",
+                description, within, recorded
+            );
+        }
+    }
+}
+
+/// Lists `messages` one per line, eliding all but the last `MAX_LISTED_MESSAGES`.
+fn listing<P: Display>(messages: &[&TappedMessage<P>]) -> String {
+    let elided = messages.len().saturating_sub(MAX_LISTED_MESSAGES);
+    let mut listing = if elided > 0 {
+        format!("  ... {} earlier messages\n", elided)
+    } else if messages.is_empty() {
+        "  no messages\n".to_string()
+    } else {
+        String::new()
+    };
+    for message in &messages[elided..] {
+        listing.push_str(&format!("  {}\n", message));
+    }
+    listing
+}
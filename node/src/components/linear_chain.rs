@@ -12,7 +12,7 @@ use tracing::{debug, error, info, warn};
 
 use casper_types::{ExecutionResult, ProtocolVersion, PublicKey, SemVer, Signature};
 
-use super::{consensus::EraId, Component};
+use super::{block_executor::BlockSigningCapability, consensus::EraId, Component};
 use crate::{
     effect::{
         announcements::LinearChainAnnouncement,
@@ -44,13 +44,6 @@ pub enum Event<I> {
     /// A linear chain request issued by another node in the network.
     #[from]
     Request(LinearChainRequest<I>),
-    /// New linear chain block has been produced.
-    LinearChainBlock {
-        /// The block.
-        block: Box<Block>,
-        /// The deploys' execution results.
-        execution_results: HashMap<DeployHash, ExecutionResult>,
-    },
     /// A continuation for `GetBlock` scenario.
     GetBlockResult(BlockHash, Option<Box<Block>>, I),
     /// A continuation for `BlockAtHeight` scenario.
@@ -66,6 +59,8 @@ pub enum Event<I> {
         block: Box<Block>,
         /// The deploys' execution results.
         execution_results: HashMap<DeployHash, ExecutionResult>,
+        /// Capability to have consensus sign the block.
+        signing_capability: BlockSigningCapability,
     },
     /// The result of requesting a block from storage to add a finality signature to it.
     GetBlockForFinalitySignaturesResult(Box<FinalitySignature>, Option<Box<Block>>),
@@ -80,9 +75,6 @@ impl<I: Display> Display for Event<I> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Event::Request(req) => write!(f, "linear chain request: {}", req),
-            Event::LinearChainBlock { block, .. } => {
-                write!(f, "linear chain new block: {}", block.hash())
-            }
             Event::GetBlockResult(block_hash, maybe_block, peer) => write!(
                 f,
                 "linear chain get-block for {} from {} found: {}",
@@ -379,10 +371,11 @@ where
                     }
                 },
             },
-            Event::LinearChainBlock {
+            Event::Request(LinearChainRequest::ExecutedBlock {
                 block,
                 execution_results,
-            } => {
+                signing_capability,
+            }) => {
                 let (block, mut effects) =
                     self.collect_pending_finality_signatures(*block, effect_builder);
                 // Cache the block as we expect more finality signatures to arrive soon.
@@ -392,6 +385,7 @@ where
                     move |_| Event::PutBlockResult {
                         block,
                         execution_results,
+                        signing_capability,
                     },
                 ));
                 effects
@@ -399,6 +393,7 @@ where
             Event::PutBlockResult {
                 block,
                 execution_results,
+                signing_capability,
             } => {
                 self.latest_block = Some(*block.clone());
 
@@ -412,7 +407,7 @@ where
                     .ignore();
                 effects.extend(
                     effect_builder
-                        .handle_linear_chain_block(block_header.clone())
+                        .handle_linear_chain_block(signing_capability, block_header.clone())
                         .map_some(move |fs| Event::FinalitySignatureReceived(Box::new(fs))),
                 );
                 effects.extend(
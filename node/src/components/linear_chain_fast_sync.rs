@@ -458,8 +458,11 @@ where
                         // Reset used peers so we can download next block with the full set.
                         self.peers.reset(rng);
                         // Execute block
+                        let expected_state_root_hash = *block_header.state_root_hash();
                         let finalized_block: FinalizedBlock = (*block_header).into();
-                        effect_builder.execute_block(finalized_block).ignore()
+                        effect_builder
+                            .execute_received_block(finalized_block, expected_state_root_hash)
+                            .ignore()
                     }
                     event::DeploysResult::NotFound(block_header, peer) => {
                         let block_hash = block_header.hash();
@@ -4,9 +4,12 @@
 //!
 //! Steps are:
 //! 1. Fetch blocks up to initial, trusted hash (blocks are downloaded starting from trusted hash up
-//! until Genesis).
-//! 2. Fetch deploys of the lowest height block.
-//! 3. Execute that block.
+//! until Genesis). Every block is verified as it arrives, i.e. its hashes and the signatures
+//! attached to it are checked.
+//! 2. Fetch deploys of the lowest height blocks, with up to `max_parallel_deploy_fetches` blocks in
+//! flight at once.
+//! 3. Execute the lowest block as soon as its deploys are available, while deploys of the following
+//! blocks are still being fetched.
 //! 4. Repeat steps 2-3 until trusted hash is reached.
 //! 5. Transition to `SyncingDescendants` state.
 //! 6. Fetch child block of highest block.
@@ -22,14 +25,24 @@
 //! execution is interleaved. If we had downloaded the whole chain, and then deploys, and then
 //! execute (as we do in the first, SynchronizeTrustedHash, phase) it would have taken more time and
 //! we might miss more eras.
+//!
+//! Downloading the chain up to the trusted hash is inherently sequential, since only a block tells
+//! us the hash of its parent. Once it completes, deploy fetching and execution form a pipeline:
+//! fetching deploys is bound by network bandwidth and can run for many blocks concurrently, while
+//! execution is bound by CPU and has to follow the order of the chain. Each stage reports its
+//! progress through separate metrics.
 
+mod config;
 mod event;
 mod metrics;
 mod peers;
 mod state;
 mod traits;
 
-use std::{collections::BTreeMap, convert::Infallible, fmt::Display, mem};
+#[cfg(test)]
+mod tests;
+
+use std::{collections::BTreeMap, convert::Infallible, fmt::Display, mem, time::Instant};
 
 use datasize::DataSize;
 use prometheus::Registry;
@@ -42,9 +55,10 @@ use self::event::{BlockByHashResult, DeploysResult};
 use super::{fetcher::FetchResult, Component};
 use crate::{
     effect::{EffectBuilder, EffectExt, EffectOptionExt, Effects},
-    types::{BlockByHeight, BlockHash, BlockHeader, FinalizedBlock},
+    types::{Block, BlockByHeight, BlockHash, BlockHeader, FinalitySignature, FinalizedBlock},
     NodeRng,
 };
+pub use config::Config;
 use event::BlockByHeightResult;
 pub use event::Event;
 pub use metrics::LinearChainSyncMetrics;
@@ -54,7 +68,11 @@ pub use traits::ReactorEventT;
 
 #[derive(DataSize, Debug)]
 pub(crate) struct LinearChainSync<I> {
+    config: Config,
     peers: PeersState<I>,
+    /// The peers asked for the deploys of each block being fetched while syncing up to the trusted
+    /// hash, keyed by block height.
+    deploy_fetch_peers: BTreeMap<u64, Vec<I>>,
     state: State,
     #[data_size(skip)]
     metrics: LinearChainSyncMetrics,
@@ -62,6 +80,7 @@ pub(crate) struct LinearChainSync<I> {
 
 impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
     pub fn new(
+        config: Config,
         registry: &Registry,
         init_hash: Option<BlockHash>,
         genesis_validator_weights: BTreeMap<PublicKey, U512>,
@@ -70,7 +89,9 @@ impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
             State::sync_trusted_hash(init_hash, genesis_validator_weights)
         });
         Ok(LinearChainSync {
+            config,
             peers: PeersState::new(),
+            deploy_fetch_peers: BTreeMap::new(),
             state,
             metrics: LinearChainSyncMetrics::new(registry)?,
         })
@@ -101,6 +122,7 @@ impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
         REv: ReactorEventT<I>,
     {
         self.peers.reset(rng);
+        self.metrics.header_downloaded();
         self.state.block_downloaded(block_header);
         self.add_block(block_header.clone());
         match &self.state {
@@ -118,7 +140,7 @@ impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
             State::SyncingDescendants { .. } => {
                 // When synchronizing descendants, we want to download block and execute it
                 // before trying to download the next block in linear chain.
-                self.fetch_next_block_deploys(effect_builder)
+                self.fetch_next_block_deploys(effect_builder, rng)
             }
         }
     }
@@ -155,6 +177,7 @@ impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
                 highest_block_seen,
                 ref latest_block,
                 ref mut validator_weights,
+                ref mut executing,
                 ..
             } if highest_block_seen != block_height => {
                 match latest_block.as_ref() {
@@ -169,8 +192,10 @@ impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
                 {
                     *validator_weights = validator_weights_for_new_era.clone();
                 }
+                *executing = false;
                 self.state = curr_state;
-                self.fetch_next_block_deploys(effect_builder)
+                self.metrics.block_executed();
+                self.advance_pipeline(effect_builder, rng)
             }
             // Otherwise transition to State::SyncingDescendants
             State::SyncingTrustedHash {
@@ -189,6 +214,8 @@ impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
                     None => panic!("Unexpected block execution results."),
                 }
                 info!(%block_height, "Finished synchronizing linear chain up until trusted hash.");
+                self.metrics.block_executed();
+                self.metrics.set_pipeline_depths(0, 0);
                 let peer = self.peers.random_unsafe();
                 // Kick off syncing trusted hash descendants.
                 self.state = State::sync_descendants(trusted_hash, block_header, validator_weights);
@@ -210,6 +237,7 @@ impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
                     }
                 }
                 self.state = curr_state;
+                self.metrics.block_executed();
                 self.fetch_next_block(effect_builder, rng, &block_header)
             }
         }
@@ -219,43 +247,159 @@ impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
     fn fetch_next_block_deploys<REv>(
         &mut self,
         effect_builder: EffectBuilder<REv>,
+        rng: &mut NodeRng,
     ) -> Effects<Event<I>>
     where
         I: Send + 'static,
         REv: ReactorEventT<I>,
     {
-        let peer = self.peers.random_unsafe();
+        if let State::SyncingTrustedHash { .. } = self.state {
+            return self.advance_pipeline(effect_builder, rng);
+        }
 
-        let next_block = match &mut self.state {
-            State::None | State::Done => {
+        let next_block = match &self.state {
+            State::None | State::Done | State::SyncingTrustedHash { .. } => {
                 panic!("Tried fetching next block when in {:?} state.", self.state)
             }
+            State::SyncingDescendants { latest_block, .. } => (**latest_block).clone(),
+        };
+
+        let peer = self.peers.random_unsafe();
+        self.metrics.reset_start_time();
+        fetch_block_deploys(effect_builder, peer, next_block)
+    }
+
+    /// Advances the deploy fetching and execution pipeline used while syncing up to the trusted
+    /// hash.
+    ///
+    /// Starts executing the lowest block if its deploys are available and no block with a lower
+    /// height is still being fetched, then tops up deploy fetches to the configured limit.
+    fn advance_pipeline<REv>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        rng: &mut NodeRng,
+    ) -> Effects<Event<I>>
+    where
+        I: Send + 'static,
+        REv: ReactorEventT<I>,
+    {
+        let max_deploy_fetches = self.config.max_parallel_deploy_fetches();
+        let mut effects = Effects::new();
+        let mut to_fetch = Vec::new();
+
+        match &mut self.state {
             State::SyncingTrustedHash {
                 linear_chain,
                 latest_block,
+                deploy_fetches,
+                awaiting_execution,
+                executing,
                 ..
-            } => match linear_chain.pop() {
-                None => None,
-                Some(block) => {
-                    // Update `latest_block` so that we can verify whether result of execution
-                    // matches the expected value.
-                    latest_block.replace(block.clone());
-                    Some(block)
+            } => {
+                let lowest_fetch = deploy_fetches.keys().next().copied();
+                let next_height = awaiting_execution
+                    .keys()
+                    .next()
+                    .copied()
+                    .filter(|height| lowest_fetch.map_or(true, |fetch| *height < fetch));
+                if let (false, Some(height)) = (*executing, next_height) {
+                    if let Some(block_header) = awaiting_execution.remove(&height) {
+                        // Update `latest_block` so that we can verify whether result of execution
+                        // matches the expected value.
+                        latest_block.replace(block_header.clone());
+                        *executing = true;
+                        let expected_state_root_hash = *block_header.state_root_hash();
+                        let finalized_block: FinalizedBlock = block_header.into();
+                        effects.extend(
+                            effect_builder
+                                .execute_received_block(finalized_block, expected_state_root_hash)
+                                .ignore(),
+                        );
+                    }
                 }
-            },
-            State::SyncingDescendants { latest_block, .. } => Some((**latest_block).clone()),
-        };
 
-        next_block.map_or_else(
-            || {
-                warn!("tried fetching next block deploys when there was no block.");
-                Effects::new()
-            },
-            |block| {
-                self.metrics.reset_start_time();
-                fetch_block_deploys(effect_builder, peer, block)
-            },
-        )
+                while deploy_fetches.len() < max_deploy_fetches {
+                    match linear_chain.pop() {
+                        Some(block_header) => {
+                            deploy_fetches.insert(block_header.height(), Instant::now());
+                            to_fetch.push(block_header);
+                        }
+                        None => break,
+                    }
+                }
+
+                self.metrics
+                    .set_pipeline_depths(deploy_fetches.len(), awaiting_execution.len());
+            }
+            State::None | State::Done | State::SyncingDescendants { .. } => {
+                warn!("tried advancing pipeline outside of trusted hash sync");
+                return Effects::new();
+            }
+        }
+
+        for block_header in to_fetch {
+            effects.extend(self.fetch_deploys_from_untried_peer(effect_builder, rng, block_header));
+        }
+        effects
+    }
+
+    /// Fetches the deploys of a block from a random peer which has not been asked for them yet,
+    /// while syncing up to the trusted hash.
+    ///
+    /// Panics if every peer has been asked already.
+    fn fetch_deploys_from_untried_peer<REv>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        rng: &mut NodeRng,
+        block_header: BlockHeader,
+    ) -> Effects<Event<I>>
+    where
+        I: Send + 'static,
+        REv: ReactorEventT<I>,
+    {
+        let tried = self
+            .deploy_fetch_peers
+            .entry(block_header.height())
+            .or_default();
+        match self.peers.random_excluding(rng, tried) {
+            Some(peer) => {
+                tried.push(peer.clone());
+                fetch_block_deploys(effect_builder, peer, block_header)
+            }
+            None => {
+                let block_hash = block_header.hash();
+                error!(%block_hash, "could not download deploys from linear chain block.");
+                panic!("Failed to download linear chain deploys.")
+            }
+        }
+    }
+
+    /// Records that the deploys of a block have been fetched while syncing up to the trusted hash.
+    fn deploys_fetched<REv>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        rng: &mut NodeRng,
+        block_header: BlockHeader,
+    ) -> Effects<Event<I>>
+    where
+        I: Send + 'static,
+        REv: ReactorEventT<I>,
+    {
+        if let State::SyncingTrustedHash {
+            deploy_fetches,
+            awaiting_execution,
+            ..
+        } = &mut self.state
+        {
+            let height = block_header.height();
+            self.deploy_fetch_peers.remove(&height);
+            match deploy_fetches.remove(&height) {
+                Some(start) => self.metrics.observe_get_deploys_since(start),
+                None => warn!(%height, "received deploys for a block that was not being fetched"),
+            }
+            awaiting_execution.insert(height, block_header);
+        }
+        self.advance_pipeline(effect_builder, rng)
     }
 
     fn fetch_next_block<REv>(
@@ -269,14 +413,15 @@ impl<I: Clone + PartialEq + 'static> LinearChainSync<I> {
         REv: ReactorEventT<I>,
     {
         self.peers.reset(rng);
-        let peer = self.peers.random_unsafe();
         match self.state {
             State::SyncingTrustedHash { .. } => {
+                // Let the fetcher choose the best of all peers.
                 let parent_hash = *block_header.parent_hash();
                 self.metrics.reset_start_time();
-                fetch_block_by_hash(effect_builder, peer, parent_hash)
+                fetch_block_by_hash(effect_builder, self.peers.untried(), parent_hash)
             }
             State::SyncingDescendants { .. } => {
+                let peer = self.peers.random_unsafe();
                 let next_height = block_header.height() + 1;
                 self.metrics.reset_start_time();
                 fetch_block_at_height(effect_builder, peer, next_height)
@@ -330,7 +475,7 @@ where
                         trace!(?trusted_hash, "start synchronization");
                         // Start synchronization.
                         self.metrics.reset_start_time();
-                        fetch_block_by_hash(effect_builder, init_peer, trusted_hash)
+                        fetch_block_by_hash(effect_builder, vec![init_peer], trusted_hash)
                     }
                 }
             }
@@ -368,6 +513,18 @@ where
                     BlockByHeightResult::FromPeer(block, peer) => {
                         self.metrics.observe_get_block_by_height();
                         trace!(%block_height, %peer, "linear chain block downloaded from a peer");
+                        if let Err(error) = verify_block(&block) {
+                            warn!(%peer, %error, "invalid block");
+                            self.peers.ban(&peer);
+                            return self.handle_event(
+                                effect_builder,
+                                rng,
+                                Event::GetBlockHeightResult(
+                                    block_height,
+                                    BlockByHeightResult::Absent(peer),
+                                ),
+                            );
+                        }
                         if block.height() != block_height
                             || *block.header().parent_hash() != self.latest_block().unwrap().hash()
                         {
@@ -397,20 +554,20 @@ where
             }
             Event::GetBlockHashResult(block_hash, fetch_result) => {
                 match fetch_result {
-                    BlockByHashResult::Absent(peer) => {
+                    BlockByHashResult::Absent(peers) => {
                         self.metrics.observe_get_block_by_hash();
-                        trace!(%block_hash, %peer, "failed to download block by hash. Trying next peer");
-                        self.peers.failure(&peer);
-                        match self.peers.random() {
-                            None => {
-                                error!(%block_hash, "Could not download linear block from any of the peers.");
-                                panic!("Failed to download linear chain.")
-                            }
-                            Some(peer) => {
-                                self.metrics.reset_start_time();
-                                fetch_block_by_hash(effect_builder, peer, block_hash)
-                            }
+                        trace!(%block_hash, tried = peers.len(), "failed to download block by hash. Trying remaining peers");
+                        for peer in &peers {
+                            self.peers.failure(peer);
+                            self.peers.tried(peer);
                         }
+                        let remaining = self.peers.untried();
+                        if remaining.is_empty() {
+                            error!(%block_hash, "Could not download linear block from any of the peers.");
+                            panic!("Failed to download linear chain.")
+                        }
+                        self.metrics.reset_start_time();
+                        fetch_block_by_hash(effect_builder, remaining, block_hash)
                     }
                     BlockByHashResult::FromStorage(block) => {
                         // We shouldn't get invalid data from the storage.
@@ -439,7 +596,19 @@ where
                                 rng,
                                 Event::GetBlockHashResult(
                                     block_hash,
-                                    BlockByHashResult::Absent(peer),
+                                    BlockByHashResult::Absent(vec![peer]),
+                                ),
+                            );
+                        }
+                        if let Err(error) = verify_block(&block) {
+                            warn!(%peer, %error, "invalid block");
+                            self.peers.ban(&peer);
+                            return self.handle_event(
+                                effect_builder,
+                                rng,
+                                Event::GetBlockHashResult(
+                                    block_hash,
+                                    BlockByHashResult::Absent(vec![peer]),
                                 ),
                             );
                         }
@@ -449,21 +618,37 @@ where
                 }
             }
             Event::GetDeploysResult(fetch_result) => {
-                self.metrics.observe_get_deploys();
                 match fetch_result {
                     event::DeploysResult::Found(block_header) => {
                         let block_hash = block_header.hash();
                         trace!(%block_hash, "deploys for linear chain block found");
+                        if let State::SyncingTrustedHash { .. } = self.state {
+                            return self.deploys_fetched(effect_builder, rng, *block_header);
+                        }
+                        self.metrics.observe_get_deploys();
                         // Reset used peers so we can download next block with the full set.
                         self.peers.reset(rng);
                         // Execute block
+                        let expected_state_root_hash = *block_header.state_root_hash();
                         let finalized_block: FinalizedBlock = (*block_header).into();
-                        effect_builder.execute_block(finalized_block).ignore()
+                        effect_builder
+                            .execute_received_block(finalized_block, expected_state_root_hash)
+                            .ignore()
                     }
                     event::DeploysResult::NotFound(block_header, peer) => {
                         let block_hash = block_header.hash();
                         trace!(%block_hash, %peer, "deploy for linear chain block not found. Trying next peer");
                         self.peers.failure(&peer);
+                        if let State::SyncingTrustedHash { .. } = self.state {
+                            // Other blocks are fetched concurrently, so only the peers tried for
+                            // this block are skipped.
+                            return self.fetch_deploys_from_untried_peer(
+                                effect_builder,
+                                rng,
+                                *block_header,
+                            );
+                        }
+                        self.metrics.observe_get_deploys();
                         match self.peers.random() {
                             None => {
                                 error!(%block_hash,
@@ -479,9 +664,9 @@ where
                 }
             }
             Event::StartDownloadingDeploys => {
-                // Start downloading deploys from the first block of the linear chain.
+                // Start downloading deploys from the first blocks of the linear chain.
                 self.peers.reset(rng);
-                self.fetch_next_block_deploys(effect_builder)
+                self.fetch_next_block_deploys(effect_builder, rng)
             }
             Event::NewPeerConnected(peer_id) => {
                 trace!(%peer_id, "new peer connected");
@@ -510,6 +695,24 @@ where
     }
 }
 
+/// Verifies the integrity of a block received from a peer, including the finality signatures
+/// attached to it.
+fn verify_block(block: &Block) -> Result<(), String> {
+    block.verify().map_err(|error| error.to_string())?;
+    for (public_key, signature) in block.proofs() {
+        let finality_signature = FinalitySignature {
+            block_hash: *block.hash(),
+            era_id: block.header().era_id(),
+            signature: *signature,
+            public_key: *public_key,
+        };
+        finality_signature
+            .verify()
+            .map_err(|error| format!("invalid finality signature by {}: {}", public_key, error))?;
+    }
+    Ok(())
+}
+
 fn fetch_block_deploys<I: Clone + Send + 'static, REv>(
     effect_builder: EffectBuilder<REv>,
     peer: I,
@@ -530,26 +733,29 @@ where
         })
 }
 
+/// Fetches the block with `block_hash` from the best of `peers`, see `Fetcher`.
 fn fetch_block_by_hash<I: Clone + Send + 'static, REv>(
     effect_builder: EffectBuilder<REv>,
-    peer: I,
+    peers: Vec<I>,
     block_hash: BlockHash,
 ) -> Effects<Event<I>>
 where
     REv: ReactorEventT<I>,
 {
-    let cloned = peer.clone();
-    effect_builder.fetch_block(block_hash, peer).map_or_else(
-        move |fetch_result| match fetch_result {
-            FetchResult::FromStorage(block) => {
-                Event::GetBlockHashResult(block_hash, BlockByHashResult::FromStorage(block))
-            }
-            FetchResult::FromPeer(block, peer) => {
-                Event::GetBlockHashResult(block_hash, BlockByHashResult::FromPeer(block, peer))
-            }
-        },
-        move || Event::GetBlockHashResult(block_hash, BlockByHashResult::Absent(cloned)),
-    )
+    let cloned = peers.clone();
+    effect_builder
+        .fetch_block_from_any(block_hash, peers)
+        .map_or_else(
+            move |fetch_result| match fetch_result {
+                FetchResult::FromStorage(block) => {
+                    Event::GetBlockHashResult(block_hash, BlockByHashResult::FromStorage(block))
+                }
+                FetchResult::FromPeer(block, peer) => {
+                    Event::GetBlockHashResult(block_hash, BlockByHashResult::FromPeer(block, peer))
+                }
+            },
+            move || Event::GetBlockHashResult(block_hash, BlockByHashResult::Absent(cloned)),
+        )
 }
 
 fn fetch_block_at_height<I: Send + Clone + 'static, REv>(
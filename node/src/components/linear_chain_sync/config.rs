@@ -0,0 +1,28 @@
+use datasize::DataSize;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_MAX_PARALLEL_DEPLOY_FETCHES: usize = 8;
+
+/// Configuration options for linear chain synchronization.
+#[derive(Copy, Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Maximum number of blocks whose deploys are fetched concurrently ahead of execution.
+    max_parallel_deploy_fetches: usize,
+}
+
+impl Config {
+    pub(crate) fn max_parallel_deploy_fetches(&self) -> usize {
+        // Zero would stall the pipeline, so at least one fetch is always allowed.
+        self.max_parallel_deploy_fetches.max(1)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_parallel_deploy_fetches: DEFAULT_MAX_PARALLEL_DEPLOY_FETCHES,
+        }
+    }
+}
@@ -20,7 +20,8 @@ pub enum DeploysResult<I> {
 
 #[derive(Debug)]
 pub enum BlockByHashResult<I> {
-    Absent(I),
+    /// None of the peers asked delivered a valid block.
+    Absent(Vec<I>),
     FromStorage(Box<Block>),
     FromPeer(Box<Block>, I),
 }
@@ -1,12 +1,20 @@
 use std::time::Instant;
 
-use prometheus::{Histogram, HistogramOpts, Registry};
+use prometheus::{Histogram, HistogramOpts, IntCounter, IntGauge, Registry};
 
 #[derive(Debug)]
 pub struct LinearChainSyncMetrics {
     get_block_by_hash: Histogram,
     get_block_by_height: Histogram,
     get_deploys: Histogram,
+    /// Number of linear chain headers downloaded and verified.
+    headers_downloaded: IntCounter,
+    /// Number of blocks whose deploys are currently being fetched.
+    deploy_fetches_in_flight: IntGauge,
+    /// Number of blocks with all deploys available, waiting for their turn to be executed.
+    blocks_awaiting_execution: IntGauge,
+    /// Number of linear chain blocks executed.
+    blocks_executed: IntCounter,
     request_start: Instant,
 }
 
@@ -16,6 +24,17 @@ const GET_BLOCK_BY_HEIGHT: &str = "linear_chain_sync_get_block_by_height";
 const GET_BLOCK_BY_HEIGHT_HELP: &str = "histogram of linear_chain_sync get_block_by_height request";
 const GET_DEPLOYS: &str = "linear_chain_sync_get_deploys";
 const GET_DEPLOYS_HELP: &str = "histogram of linear_chain_sync get_deploys request";
+const HEADERS_DOWNLOADED: &str = "linear_chain_sync_headers_downloaded";
+const HEADERS_DOWNLOADED_HELP: &str =
+    "number of linear chain block headers downloaded and verified";
+const DEPLOY_FETCHES_IN_FLIGHT: &str = "linear_chain_sync_deploy_fetches_in_flight";
+const DEPLOY_FETCHES_IN_FLIGHT_HELP: &str =
+    "number of blocks whose deploys are currently being fetched";
+const BLOCKS_AWAITING_EXECUTION: &str = "linear_chain_sync_blocks_awaiting_execution";
+const BLOCKS_AWAITING_EXECUTION_HELP: &str =
+    "number of blocks with all deploys fetched, waiting to be executed";
+const BLOCKS_EXECUTED: &str = "linear_chain_sync_blocks_executed";
+const BLOCKS_EXECUTED_HELP: &str = "number of linear chain blocks executed";
 
 /// Value of upper bound of histogram.
 const EXPONENTIAL_BUCKET_START: f64 = 0.01;
@@ -43,6 +62,17 @@ fn register_histogram_metric(
 
 impl LinearChainSyncMetrics {
     pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let headers_downloaded = IntCounter::new(HEADERS_DOWNLOADED, HEADERS_DOWNLOADED_HELP)?;
+        let deploy_fetches_in_flight =
+            IntGauge::new(DEPLOY_FETCHES_IN_FLIGHT, DEPLOY_FETCHES_IN_FLIGHT_HELP)?;
+        let blocks_awaiting_execution =
+            IntGauge::new(BLOCKS_AWAITING_EXECUTION, BLOCKS_AWAITING_EXECUTION_HELP)?;
+        let blocks_executed = IntCounter::new(BLOCKS_EXECUTED, BLOCKS_EXECUTED_HELP)?;
+        registry.register(Box::new(headers_downloaded.clone()))?;
+        registry.register(Box::new(deploy_fetches_in_flight.clone()))?;
+        registry.register(Box::new(blocks_awaiting_execution.clone()))?;
+        registry.register(Box::new(blocks_executed.clone()))?;
+
         Ok(LinearChainSyncMetrics {
             get_block_by_hash: register_histogram_metric(
                 registry,
@@ -55,6 +85,10 @@ impl LinearChainSyncMetrics {
                 GET_BLOCK_BY_HEIGHT_HELP,
             )?,
             get_deploys: register_histogram_metric(registry, GET_DEPLOYS, GET_DEPLOYS_HELP)?,
+            headers_downloaded,
+            deploy_fetches_in_flight,
+            blocks_awaiting_execution,
+            blocks_executed,
             request_start: Instant::now(),
         })
     }
@@ -77,4 +111,30 @@ impl LinearChainSyncMetrics {
         self.get_deploys
             .observe(self.request_start.elapsed().as_secs_f64());
     }
+
+    /// Observes the duration of a deploy fetch started at `start`.
+    ///
+    /// Used for fetches running in parallel, which cannot share a single request start time.
+    pub fn observe_get_deploys_since(&mut self, start: Instant) {
+        self.get_deploys.observe(start.elapsed().as_secs_f64());
+    }
+
+    pub fn header_downloaded(&mut self) {
+        self.headers_downloaded.inc();
+    }
+
+    pub fn block_executed(&mut self) {
+        self.blocks_executed.inc();
+    }
+
+    pub fn set_pipeline_depths(
+        &mut self,
+        deploy_fetches_in_flight: usize,
+        awaiting_execution: usize,
+    ) {
+        self.deploy_fetches_in_flight
+            .set(deploy_fetches_in_flight as i64);
+        self.blocks_awaiting_execution
+            .set(awaiting_execution as i64);
+    }
 }
@@ -1,7 +1,10 @@
 use std::collections::VecDeque;
 
 use datasize::DataSize;
-use rand::{seq::SliceRandom, Rng};
+use rand::{
+    seq::{IteratorRandom, SliceRandom},
+    Rng,
+};
 
 #[derive(DataSize, Debug)]
 pub struct PeersState<I> {
@@ -44,6 +47,19 @@ impl<I: Clone + PartialEq + 'static> PeersState<I> {
         }
     }
 
+    /// Returns a random peer not contained in `excluded`, regardless of `peers_to_try`.
+    pub(crate) fn random_excluding<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        excluded: &[I],
+    ) -> Option<I> {
+        self.peers
+            .iter()
+            .filter(|peer| !excluded.contains(peer))
+            .choose(rng)
+            .cloned()
+    }
+
     /// Unsafe version of `random_peer`.
     /// Panics if no peer is available for querying.
     pub(crate) fn random_unsafe(&mut self) -> I {
@@ -57,6 +73,16 @@ impl<I: Clone + PartialEq + 'static> PeersState<I> {
         self.succ_peers.retain(|p| p != peer);
     }
 
+    /// Returns the peers not tried yet since the last `reset`.
+    pub(crate) fn untried(&self) -> Vec<I> {
+        self.peers_to_try.clone()
+    }
+
+    /// Marks `peer` as tried, without handing it out.
+    pub(crate) fn tried(&mut self, peer: &I) {
+        self.peers_to_try.retain(|p| p != peer);
+    }
+
     /// Returns whether known peer set is empty.
     pub(crate) fn is_empty(&self) -> bool {
         self.peers.is_empty()
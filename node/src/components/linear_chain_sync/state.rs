@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, fmt::Display};
+use std::{collections::BTreeMap, fmt::Display, time::Instant};
 
 use datasize::DataSize;
 
@@ -19,11 +19,18 @@ pub enum State {
         /// Chain of downloaded blocks from the linear chain.
         /// We will `pop()` when executing blocks.
         linear_chain: Vec<BlockHeader>,
-        /// The most recent block we started to execute. This is updated whenever we start
-        /// downloading deploys for the next block to be executed.
+        /// The most recent block we started to execute.
         latest_block: Box<Option<BlockHeader>>,
         /// The weights of the validators for latest block being added.
         validator_weights: BTreeMap<PublicKey, U512>,
+        /// Heights of the blocks whose deploys are currently being fetched, along with the time
+        /// the fetch was started.
+        #[data_size(skip)]
+        deploy_fetches: BTreeMap<u64, Instant>,
+        /// Blocks whose deploys have been fetched, waiting to be executed in order of height.
+        awaiting_execution: BTreeMap<u64, BlockHeader>,
+        /// Whether a block is currently being executed.
+        executing: bool,
     },
     /// Synchronizing the descendants of the trusted hash.
     SyncingDescendants {
@@ -71,6 +78,9 @@ impl State {
             linear_chain: Vec::new(),
             latest_block: Box::new(None),
             validator_weights,
+            deploy_fetches: BTreeMap::new(),
+            awaiting_execution: BTreeMap::new(),
+            executing: false,
         }
     }
 
@@ -0,0 +1,206 @@
+use derive_more::From;
+
+use super::*;
+use crate::{
+    effect::requests::{
+        BlockExecutorRequest, BlockValidationRequest, FetcherRequest, StorageRequest,
+    },
+    testing::{ComponentHarness, TestRng},
+    types::{Block, BlockByHeight, NodeId},
+};
+
+/// The number of blocks below the trusted hash in the test chains.
+const CHAIN_LENGTH: u64 = 10;
+
+#[derive(Debug, From)]
+enum ReactorEvent {
+    Storage(StorageRequest),
+    BlockFetcher(FetcherRequest<NodeId, Block>),
+    BlockByHeightFetcher(FetcherRequest<NodeId, BlockByHeight>),
+    BlockValidator(BlockValidationRequest<BlockHeader, NodeId>),
+    BlockExecutor(BlockExecutorRequest),
+}
+
+/// Creates a synchronizer which has downloaded a chain of `CHAIN_LENGTH` blocks up to the trusted
+/// hash and knows `peer_count` peers, along with the block headers in order of height.
+fn synchronizer_with_chain(
+    rng: &mut TestRng,
+    peer_count: usize,
+) -> (LinearChainSync<NodeId>, Vec<BlockHeader>) {
+    let headers: Vec<BlockHeader> = (0..CHAIN_LENGTH)
+        .map(|height| {
+            let mut block = Block::random(rng);
+            block.set_height(height);
+            block.take_header()
+        })
+        .collect();
+    let trusted_hash = headers.last().unwrap().hash();
+
+    let mut sync = LinearChainSync::new(
+        Config::default(),
+        &Registry::new(),
+        Some(trusted_hash),
+        BTreeMap::new(),
+    )
+    .unwrap();
+    for _ in 0..peer_count {
+        sync.peers.push(NodeId::random(rng));
+    }
+    match &mut sync.state {
+        State::SyncingTrustedHash {
+            linear_chain,
+            highest_block_seen,
+            ..
+        } => {
+            // The chain is downloaded from the trusted hash down, so the lowest block comes last.
+            *linear_chain = headers.iter().rev().cloned().collect();
+            *highest_block_seen = CHAIN_LENGTH - 1;
+        }
+        state => panic!("unexpected state {}", state),
+    }
+    (sync, headers)
+}
+
+fn deploy_fetch_heights(sync: &LinearChainSync<NodeId>) -> Vec<u64> {
+    match &sync.state {
+        State::SyncingTrustedHash { deploy_fetches, .. } => {
+            deploy_fetches.keys().copied().collect()
+        }
+        state => panic!("unexpected state {}", state),
+    }
+}
+
+fn executing_block(sync: &LinearChainSync<NodeId>) -> Option<&BlockHeader> {
+    match &sync.state {
+        State::SyncingTrustedHash {
+            latest_block,
+            executing: true,
+            ..
+        } => Option::as_ref(&*latest_block),
+        _ => None,
+    }
+}
+
+fn deploys_found(header: &BlockHeader) -> Event<NodeId> {
+    Event::GetDeploysResult(DeploysResult::Found(Box::new(header.clone())))
+}
+
+#[test]
+fn should_fetch_deploys_of_several_blocks_in_parallel() {
+    let mut harness = ComponentHarness::<ReactorEvent>::builder().build();
+    let (mut sync, _) = synchronizer_with_chain(&mut harness.rng, 3);
+    let max_fetches = Config::default().max_parallel_deploy_fetches();
+
+    let effects = sync.handle_event(
+        harness.effect_builder,
+        &mut harness.rng,
+        Event::StartDownloadingDeploys,
+    );
+
+    assert_eq!(effects.len(), max_fetches);
+    assert_eq!(
+        deploy_fetch_heights(&sync),
+        (0..max_fetches as u64).collect::<Vec<_>>()
+    );
+    assert!(sync
+        .deploy_fetch_peers
+        .values()
+        .all(|tried| tried.len() == 1));
+    assert!(executing_block(&sync).is_none());
+}
+
+#[test]
+fn should_execute_blocks_in_order_of_height() {
+    let mut harness = ComponentHarness::<ReactorEvent>::builder().build();
+    let (mut sync, headers) = synchronizer_with_chain(&mut harness.rng, 3);
+    sync.handle_event(
+        harness.effect_builder,
+        &mut harness.rng,
+        Event::StartDownloadingDeploys,
+    );
+
+    // Deploys of a later block arriving first only free up a slot for another fetch.
+    let effects = sync.handle_event(
+        harness.effect_builder,
+        &mut harness.rng,
+        deploys_found(&headers[1]),
+    );
+    assert_eq!(effects.len(), 1);
+    assert!(executing_block(&sync).is_none());
+    assert!(!sync.deploy_fetch_peers.contains_key(&1));
+
+    // Once the lowest block's deploys are in, it is executed while the next fetch starts.
+    let effects = sync.handle_event(
+        harness.effect_builder,
+        &mut harness.rng,
+        deploys_found(&headers[0]),
+    );
+    assert_eq!(effects.len(), 2);
+    assert_eq!(executing_block(&sync), Some(&headers[0]));
+
+    // The next block waits until the executing one has been handled.
+    let effects = sync.handle_event(
+        harness.effect_builder,
+        &mut harness.rng,
+        deploys_found(&headers[2]),
+    );
+    assert!(effects.is_empty());
+    assert_eq!(executing_block(&sync), Some(&headers[0]));
+
+    sync.handle_event(
+        harness.effect_builder,
+        &mut harness.rng,
+        Event::BlockHandled(Box::new(headers[0].clone())),
+    );
+    assert_eq!(executing_block(&sync), Some(&headers[1]));
+}
+
+#[test]
+fn should_retry_deploys_not_found_with_untried_peers() {
+    let mut harness = ComponentHarness::<ReactorEvent>::builder().build();
+    // Fewer peers than blocks fetched in parallel, so every peer is asked for several blocks.
+    let (mut sync, headers) = synchronizer_with_chain(&mut harness.rng, 2);
+    sync.handle_event(
+        harness.effect_builder,
+        &mut harness.rng,
+        Event::StartDownloadingDeploys,
+    );
+
+    let peer = sync.deploy_fetch_peers[&0][0].clone();
+    let effects = sync.handle_event(
+        harness.effect_builder,
+        &mut harness.rng,
+        Event::GetDeploysResult(DeploysResult::NotFound(Box::new(headers[0].clone()), peer)),
+    );
+
+    assert_eq!(effects.len(), 1);
+    let tried = &sync.deploy_fetch_peers[&0];
+    assert_eq!(tried.len(), 2);
+    assert_ne!(tried[0], tried[1]);
+    // The fetches of the other blocks are unaffected.
+    assert_eq!(
+        sync.deploy_fetch_peers.len(),
+        Config::default().max_parallel_deploy_fetches()
+    );
+}
+
+#[test]
+#[should_panic(expected = "Failed to download linear chain deploys.")]
+fn should_give_up_on_deploys_not_found_on_any_peer() {
+    let mut harness = ComponentHarness::<ReactorEvent>::builder().build();
+    let (mut sync, headers) = synchronizer_with_chain(&mut harness.rng, 2);
+    sync.handle_event(
+        harness.effect_builder,
+        &mut harness.rng,
+        Event::StartDownloadingDeploys,
+    );
+
+    for _ in 0..2 {
+        let peer = sync.deploy_fetch_peers[&0].last().unwrap().clone();
+        sync.handle_event(
+            harness.effect_builder,
+            &mut harness.rng,
+            Event::GetDeploysResult(DeploysResult::NotFound(Box::new(headers[0].clone()), peer)),
+        );
+    }
+}
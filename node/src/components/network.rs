@@ -59,13 +59,13 @@ use crate::{
     components::{chainspec_loader::Chainspec, Component},
     effect::{
         announcements::NetworkAnnouncement,
-        requests::{NetworkInfoRequest, NetworkRequest},
+        requests::{DeliveryStatus, NetworkInfoRequest, NetworkRequest},
         EffectBuilder, EffectExt, Effects,
     },
     fatal,
     reactor::{EventQueueHandle, Finalize, QueueKind},
-    types::NodeId,
-    utils::DisplayIter,
+    types::{NodeId, PeerSample, ProtocolVersionHistogram},
+    utils::{bounded, DisplayIter},
     NodeRng,
 };
 
@@ -346,7 +346,9 @@ impl<REv: ReactorEventT<P>, P: PayloadT> Network<REv, P> {
     }
 
     /// Queues a message to be sent to a specific node.
-    fn send_message(&self, destination: NodeId, payload: P) {
+    ///
+    /// Returns whether the message was queued.
+    fn send_message(&self, destination: NodeId, payload: P) -> bool {
         let outgoing_message = match OneWayOutgoingMessage::new(
             destination,
             &payload,
@@ -355,12 +357,14 @@ impl<REv: ReactorEventT<P>, P: PayloadT> Network<REv, P> {
             Ok(msg) => msg,
             Err(error) => {
                 warn!(%error, %payload, "{}: failed to construct outgoing message", self.our_id);
-                return;
+                return false;
             }
         };
         if let Err(error) = self.one_way_message_sender.send(outgoing_message) {
             warn!(%error, "{}: dropped outgoing message, server has shut down", self.our_id);
+            return false;
         }
+        true
     }
 
     /// Queues a message to be sent to all nodes.
@@ -421,7 +425,6 @@ impl<REv: ReactorEventT<P>, P: PayloadT> Network<REv, P> {
     }
 
     /// Returns the node id of this network node.
-    #[cfg(test)]
     pub(crate) fn node_id(&self) -> NodeId {
         self.our_id.clone()
     }
@@ -606,7 +609,7 @@ async fn handle_one_way_messaging_event<REv: ReactorEventT<P>, P: PayloadT>(
             // We've received a one-way request from a peer: announce it via the reactor on the
             // `NetworkIncoming` queue.
             let sender = NodeId::from(peer);
-            match bincode::deserialize::<P>(&request) {
+            match bounded::from_bincode::<P>(&request, bounded::Limits::NETWORK) {
                 Ok(payload) => {
                     debug!(%sender, %payload, "{}: incoming one-way message received", our_id(swarm));
                     event_queue
@@ -679,7 +682,7 @@ async fn handle_gossip_event<REv: ReactorEventT<P>, P: PayloadT>(
                     return;
                 }
             };
-            match bincode::deserialize::<P>(&message.data) {
+            match bounded::from_bincode::<P>(&message.data, bounded::Limits::NETWORK) {
                 Ok(payload) => {
                     debug!(%sender, %payload, "{}: libp2p gossiped message received", our_id(swarm));
                     event_queue
@@ -912,12 +915,39 @@ impl<REv: ReactorEventT<P>, P: PayloadT> Component<REv> for Network<REv, P> {
                 self.send_message(dest, payload);
                 responder.respond(()).ignore()
             }
+            Event::NetworkRequest {
+                request:
+                    NetworkRequest::SendMessageConfirmed {
+                        dest,
+                        payload,
+                        responder,
+                    },
+            } => {
+                // The swarm does not report when a message has been written, so a queued message
+                // is assumed to be.
+                let status = if self.send_message(dest, payload) {
+                    DeliveryStatus::Written
+                } else {
+                    DeliveryStatus::Dropped
+                };
+                responder.respond(status).ignore()
+            }
             Event::NetworkRequest {
                 request: NetworkRequest::Broadcast { payload, responder },
             } => {
                 self.gossip_message(payload);
                 responder.respond(()).ignore()
             }
+            Event::NetworkRequest {
+                request:
+                    NetworkRequest::BroadcastToValidators {
+                        payload, responder, ..
+                    },
+            } => {
+                // Peers are not known to be validators or not, so the message goes to all of them.
+                self.gossip_message(payload);
+                responder.respond(()).ignore()
+            }
             Event::NetworkRequest {
                 request:
                     NetworkRequest::Gossip {
@@ -942,6 +972,43 @@ impl<REv: ReactorEventT<P>, P: PayloadT> Component<REv> for Network<REv, P> {
                     .collect();
                 responder.respond(peers).ignore()
             }
+            Event::NetworkInfoRequest {
+                info_request: NetworkInfoRequest::GetPeerProtocolVersions { responder },
+            } => {
+                // Peers do not advertise their protocol version over libp2p.
+                let versions = ProtocolVersionHistogram::new(self.peers.keys().map(|_| None));
+                responder.respond(versions).ignore()
+            }
+            Event::NetworkInfoRequest {
+                info_request: NetworkInfoRequest::GetImportedBans { responder },
+            } => {
+                // Signed ban lists are only supported by the small network.
+                responder.respond(Vec::new()).ignore()
+            }
+            Event::NetworkInfoRequest {
+                info_request: NetworkInfoRequest::GetAttestations { responder },
+            } => {
+                // Attestations are only exchanged by the small network.
+                responder.respond(Vec::new()).ignore()
+            }
+            Event::NetworkInfoRequest {
+                info_request: NetworkInfoRequest::GetRequestAudit { responder },
+            } => {
+                // Requests are only audited by the small network.
+                responder.respond(Vec::new()).ignore()
+            }
+            Event::NetworkInfoRequest {
+                info_request: NetworkInfoRequest::GetNetworkTime { responder },
+            } => {
+                // Clocks of peers are only sampled by the small network.
+                responder.respond(None).ignore()
+            }
+            Event::NetworkInfoRequest {
+                info_request: NetworkInfoRequest::SamplePeers { responder, .. },
+            } => {
+                // Connection metadata of peers is only tracked by the small network.
+                responder.respond(PeerSample::default()).ignore()
+            }
         }
     }
 }
@@ -0,0 +1,558 @@
+//! Operator notifications.
+//!
+//! The notifier posts a JSON notification to every configured webhook when an event occurs which
+//! requires the attention of the node operator:
+//!
+//! * an equivocation of a validator has been detected,
+//! * our own validator is at risk of equivocating, because it already did or because another node
+//!   is running with its key,
+//! * the activation point of an upgrade is approaching,
+//! * the disk holding the storage is almost full,
+//! * no block has been added for a long time, i.e. finality is stalled.
+//!
+//! The body of a notification is an object holding the name of the chain, a timestamp and the
+//! fields of the alert, with the kind of alert given by its `event` field. If a key is configured,
+//! the body is signed with HMAC-SHA256 and the signature sent as `sha256=<hex>` in the
+//! `X-Casper-Signature` header, so the receiver can check it was sent by the node.
+//!
+//! Failed deliveries are retried with exponential backoff. Conditions which persist, like a full
+//! disk, are only notified once until they cleared.
+
+mod config;
+mod webhook;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Debug, Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use datasize::DataSize;
+use semver::Version;
+use serde::Serialize;
+use thiserror::Error;
+use tokio::{task, time};
+use tracing::{debug, error, info, warn};
+
+use casper_types::PublicKey;
+
+use crate::{
+    components::{chainspec_loader::Chainspec, consensus::EraId, Component},
+    effect::{EffectBuilder, EffectExt, EffectOptionExt, Effects, RetryPolicy},
+    types::{TimeDiff, Timestamp},
+    utils::{self, ReadFileError},
+    NodeRng,
+};
+pub use config::Config;
+use webhook::DeliveryError;
+
+/// Upper bound for the delay between retries of a failed delivery.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Error constructing the notifier.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The HMAC key could not be read.
+    #[error("could not load HMAC key: {0}")]
+    LoadHmacKey(#[from] ReadFileError),
+}
+
+/// Notifier component event.
+#[derive(Debug)]
+pub(crate) enum Event {
+    /// The disk usage and finality are due to be checked.
+    CheckTimer,
+    /// The usage of the disk holding the storage has been determined.
+    DiskUsage {
+        /// The used share of the disk in percent.
+        used_percent: u8,
+    },
+    /// A block has been added to the linear chain.
+    BlockAdded {
+        /// The height of the block.
+        height: u64,
+    },
+    /// An equivocation has been detected.
+    Fault {
+        /// The era in which the equivocation was detected.
+        era_id: EraId,
+        /// The public key of the equivocator.
+        public_key: Box<PublicKey>,
+    },
+    /// Our own validator is at risk of equivocating.
+    EquivocationRisk {
+        /// The era in which the risk was detected.
+        era_id: EraId,
+        /// Whether another node is running with our validator key.
+        doppelganger: bool,
+    },
+}
+
+impl Display for Event {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::CheckTimer => write!(formatter, "check timer"),
+            Event::DiskUsage { used_percent } => write!(formatter, "disk {}% used", used_percent),
+            Event::BlockAdded { height } => write!(formatter, "block {} added", height),
+            Event::Fault { era_id, public_key } => {
+                write!(formatter, "fault by {} in {}", public_key, era_id)
+            }
+            Event::EquivocationRisk {
+                era_id,
+                doppelganger,
+            } => write!(
+                formatter,
+                "equivocation risk in {}, doppelganger: {}",
+                era_id, doppelganger
+            ),
+        }
+    }
+}
+
+/// A condition the node operator is notified of.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum Alert {
+    /// An equivocation of a validator has been detected.
+    FaultDetected {
+        era_id: EraId,
+        public_key: PublicKey,
+    },
+    /// Our own validator equivocated, or another node is running with its key.
+    OwnEquivocationRisk { era_id: EraId, doppelganger: bool },
+    /// The activation point of an upgrade is approaching.
+    UpgradeApproaching {
+        activation_height: u64,
+        protocol_version: Version,
+        current_height: u64,
+    },
+    /// The disk holding the storage is almost full.
+    DiskAlmostFull { path: PathBuf, used_percent: u8 },
+    /// No block has been added for a long time.
+    FinalityStall {
+        last_block_height: Option<u64>,
+        stalled_for_secs: u64,
+    },
+}
+
+/// The key notifications are signed with, kept out of debug output.
+#[derive(DataSize)]
+struct HmacKey(Vec<u8>);
+
+impl Debug for HmacKey {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "HmacKey(..)")
+    }
+}
+
+/// The body of a notification.
+#[derive(Serialize)]
+struct Notification<'a> {
+    chain_name: &'a str,
+    timestamp: Timestamp,
+    #[serde(flatten)]
+    alert: &'a Alert,
+}
+
+/// The notifier component.
+#[derive(DataSize, Debug)]
+pub(crate) struct Notifier {
+    config: Config,
+    chain_name: String,
+    /// Key the notifications are signed with, if any.
+    hmac_key: Option<HmacKey>,
+    /// Directory of the storage, whose disk is checked.
+    storage_path: PathBuf,
+    /// Upgrades not notified yet, by activation height.
+    #[data_size(skip)]
+    pending_upgrades: BTreeMap<u64, Version>,
+    /// Eras in which our own equivocation risk has been notified.
+    equivocation_risk_eras: BTreeSet<EraId>,
+    /// Whether the disk has been notified as almost full, and not dropped below the threshold
+    /// since.
+    disk_almost_full: bool,
+    /// The height of the latest block added, if any.
+    last_block_height: Option<u64>,
+    /// When the latest block was added, or the notifier was created if none was.
+    last_block_time: Timestamp,
+    /// Whether finality has been notified as stalled since the latest block was added.
+    finality_stalled: bool,
+}
+
+impl Notifier {
+    /// Creates a new notifier, scheduling the first check.
+    ///
+    /// The `hmac_key_path` of `config` is expected to be resolved already, see
+    /// `Config::resolve_path`.
+    pub(crate) fn new<REv: From<Event> + Send + 'static>(
+        config: Config,
+        chainspec: &Chainspec,
+        storage_path: &Path,
+        effect_builder: EffectBuilder<REv>,
+    ) -> Result<(Self, Effects<Event>), Error> {
+        let hmac_key = if config.hmac_key_path.is_empty() {
+            None
+        } else {
+            Some(HmacKey(utils::read_file(&config.hmac_key_path)?))
+        };
+        let pending_upgrades = chainspec
+            .upgrades
+            .iter()
+            .map(|upgrade| {
+                (
+                    upgrade.activation_point.height,
+                    upgrade.protocol_version.clone(),
+                )
+            })
+            .collect();
+        let effects = if config.webhook_urls.is_empty() {
+            info!("no webhooks configured, operator notifications are disabled");
+            Effects::new()
+        } else {
+            effect_builder
+                .set_timeout(config.check_interval)
+                .event(|_| Event::CheckTimer)
+        };
+        let notifier = Notifier {
+            config,
+            chain_name: chainspec.genesis.name.clone(),
+            hmac_key,
+            storage_path: storage_path.to_owned(),
+            pending_upgrades,
+            equivocation_risk_eras: BTreeSet::new(),
+            disk_almost_full: false,
+            last_block_height: None,
+            last_block_time: Timestamp::now(),
+            finality_stalled: false,
+        };
+        Ok((notifier, effects))
+    }
+
+    /// Records an added block, returning the alerts for upgrades coming into range.
+    fn block_added(&mut self, height: u64) -> Vec<Alert> {
+        self.last_block_height = Some(height);
+        self.last_block_time = Timestamp::now();
+        self.finality_stalled = false;
+
+        // Upgrades activating at or before the current height are not approaching, but past.
+        self.pending_upgrades = self.pending_upgrades.split_off(&(height + 1));
+        let warning_height = height.saturating_add(self.config.upgrade_warning_blocks);
+        let in_range: Vec<u64> = self
+            .pending_upgrades
+            .range(..=warning_height)
+            .map(|(activation_height, _)| *activation_height)
+            .collect();
+        in_range
+            .into_iter()
+            .filter_map(|activation_height| {
+                self.pending_upgrades
+                    .remove(&activation_height)
+                    .map(|protocol_version| Alert::UpgradeApproaching {
+                        activation_height,
+                        protocol_version,
+                        current_height: height,
+                    })
+            })
+            .collect()
+    }
+
+    /// Checks whether finality is stalled at `now`, returning the alert if it just became so.
+    fn check_finality(&mut self, now: Timestamp) -> Option<Alert> {
+        let stalled_for = now.saturating_sub(self.last_block_time);
+        if self.finality_stalled
+            || stalled_for < TimeDiff::from(self.config.finality_stall_threshold)
+        {
+            return None;
+        }
+        self.finality_stalled = true;
+        Some(Alert::FinalityStall {
+            last_block_height: self.last_block_height,
+            stalled_for_secs: stalled_for.millis() / 1000,
+        })
+    }
+
+    /// Records the disk usage, returning the alert if the disk just became almost full.
+    fn check_disk_usage(&mut self, used_percent: u8) -> Option<Alert> {
+        let almost_full = used_percent >= self.config.disk_usage_threshold_percent;
+        let newly_full = almost_full && !self.disk_almost_full;
+        self.disk_almost_full = almost_full;
+        if newly_full {
+            Some(Alert::DiskAlmostFull {
+                path: self.storage_path.clone(),
+                used_percent,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Posts `alert` to all webhooks.
+    fn notify<REv: Send + 'static>(
+        &self,
+        effect_builder: EffectBuilder<REv>,
+        alert: Alert,
+    ) -> Effects<Event> {
+        warn!(?alert, "notifying operator");
+        if self.config.webhook_urls.is_empty() {
+            return Effects::new();
+        }
+        let notification = Notification {
+            chain_name: &self.chain_name,
+            timestamp: Timestamp::now(),
+            alert: &alert,
+        };
+        let body = match serde_json::to_vec(&notification) {
+            Ok(body) => Arc::new(body),
+            Err(error) => {
+                error!(%error, "could not serialize notification");
+                return Effects::new();
+            }
+        };
+        let signature = match &self.hmac_key {
+            Some(HmacKey(key)) => match webhook::sign(key, &body) {
+                Ok(signature) => Some(Arc::new(signature)),
+                Err(error) => {
+                    error!(%error, "could not sign notification");
+                    return Effects::new();
+                }
+            },
+            None => None,
+        };
+        let policy = RetryPolicy {
+            initial_delay: self.config.retry_base_delay,
+            max_delay: MAX_RETRY_DELAY,
+            max_attempts: self.config.max_retries.saturating_add(1),
+        };
+        let request_timeout = self.config.request_timeout;
+
+        self.config
+            .webhook_urls
+            .iter()
+            .cloned()
+            .flat_map(|url| {
+                let body = Arc::clone(&body);
+                let signature = signature.clone();
+                async move {
+                    let (url_ref, body_ref) = (url.as_str(), body.as_slice());
+                    let signature_ref = signature.as_deref().map(String::as_str);
+                    let result = effect_builder
+                        .retry_with_backoff("webhook notification", policy, move || {
+                            deliver(url_ref, body_ref, signature_ref, request_timeout)
+                        })
+                        .await;
+                    match result {
+                        Ok(()) => debug!(%url, "delivered notification"),
+                        Err(error) => warn!(%url, %error, "could not deliver notification"),
+                    }
+                }
+                .ignore()
+            })
+            .collect()
+    }
+}
+
+/// Makes a single attempt of posting a notification to `url`, within `request_timeout`.
+async fn deliver(
+    url: &str,
+    body: &[u8],
+    signature: Option<&str>,
+    request_timeout: Duration,
+) -> Result<(), DeliveryError> {
+    match time::timeout(request_timeout, webhook::post(url, body, signature)).await {
+        Ok(result) => result,
+        Err(_) => Err(DeliveryError::Timeout),
+    }
+}
+
+/// Returns the used share of the file system holding `path` in percent.
+fn disk_usage_percent(path: &Path) -> io::Result<u8> {
+    let disk_space = utils::disk_space(path)?;
+    Ok(used_percent(
+        disk_space.total_bytes,
+        disk_space.available_bytes,
+    ))
+}
+
+/// Returns the share of `total` not available in percent.
+fn used_percent(total: u64, available: u64) -> u8 {
+    if total == 0 {
+        return 0;
+    }
+    let used = u128::from(total.saturating_sub(available));
+    (used * 100 / u128::from(total)) as u8
+}
+
+impl<REv: From<Event> + Send + 'static> Component<REv> for Notifier {
+    type Event = Event;
+    type ConstructionError = Error;
+
+    fn handle_event(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        _rng: &mut NodeRng,
+        event: Self::Event,
+    ) -> Effects<Self::Event> {
+        match event {
+            Event::CheckTimer => {
+                let mut effects = effect_builder
+                    .set_timeout(self.config.check_interval)
+                    .event(|_| Event::CheckTimer);
+                if let Some(alert) = self.check_finality(Timestamp::now()) {
+                    effects.extend(self.notify(effect_builder, alert));
+                }
+                let path = self.storage_path.clone();
+                effects.extend(
+                    async move {
+                        // `statvfs` may block, e.g. on network file systems.
+                        let result = task::spawn_blocking(move || disk_usage_percent(&path))
+                            .await
+                            .expect("should run");
+                        match result {
+                            Ok(used_percent) => Some(used_percent),
+                            Err(error) => {
+                                warn!(%error, "could not determine disk usage");
+                                None
+                            }
+                        }
+                    }
+                    .map_some(|used_percent| Event::DiskUsage { used_percent }),
+                );
+                effects
+            }
+            Event::DiskUsage { used_percent } => match self.check_disk_usage(used_percent) {
+                Some(alert) => self.notify(effect_builder, alert),
+                None => Effects::new(),
+            },
+            Event::BlockAdded { height } => self
+                .block_added(height)
+                .into_iter()
+                .flat_map(|alert| self.notify(effect_builder, alert))
+                .collect(),
+            Event::Fault { era_id, public_key } => self.notify(
+                effect_builder,
+                Alert::FaultDetected {
+                    era_id,
+                    public_key: *public_key,
+                },
+            ),
+            Event::EquivocationRisk {
+                era_id,
+                doppelganger,
+            } => {
+                if !self.equivocation_risk_eras.insert(era_id) {
+                    return Effects::new();
+                }
+                self.notify(
+                    effect_builder,
+                    Alert::OwnEquivocationRisk {
+                        era_id,
+                        doppelganger,
+                    },
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notifier() -> Notifier {
+        let mut pending_upgrades = BTreeMap::new();
+        pending_upgrades.insert(500, Version::new(1, 1, 0));
+        pending_upgrades.insert(800, Version::new(1, 2, 0));
+        Notifier {
+            config: Config::default(),
+            chain_name: "casper-example".to_string(),
+            hmac_key: None,
+            storage_path: PathBuf::from("/var/lib/casper"),
+            pending_upgrades,
+            equivocation_risk_eras: BTreeSet::new(),
+            disk_almost_full: false,
+            last_block_height: None,
+            last_block_time: Timestamp::now(),
+            finality_stalled: false,
+        }
+    }
+
+    #[test]
+    fn should_notify_approaching_upgrades_once() {
+        let mut notifier = notifier();
+        assert!(notifier.block_added(399).is_empty());
+        assert_eq!(
+            notifier.block_added(400),
+            vec![Alert::UpgradeApproaching {
+                activation_height: 500,
+                protocol_version: Version::new(1, 1, 0),
+                current_height: 400,
+            }]
+        );
+        assert!(notifier.block_added(401).is_empty());
+        // An upgrade whose activation point has passed already is not notified.
+        assert!(notifier.block_added(900).is_empty());
+        assert!(notifier.pending_upgrades.is_empty());
+    }
+
+    #[test]
+    fn should_notify_persisting_conditions_once_until_cleared() {
+        let mut notifier = notifier();
+        assert!(notifier.check_disk_usage(89).is_none());
+        assert!(notifier.check_disk_usage(90).is_some());
+        assert!(notifier.check_disk_usage(95).is_none());
+        assert!(notifier.check_disk_usage(80).is_none());
+        assert!(notifier.check_disk_usage(91).is_some());
+
+        let threshold = TimeDiff::from(notifier.config.finality_stall_threshold);
+        let start = notifier.last_block_time;
+        assert!(notifier.check_finality(start).is_none());
+        assert_eq!(
+            notifier.check_finality(start + threshold),
+            Some(Alert::FinalityStall {
+                last_block_height: None,
+                stalled_for_secs: threshold.millis() / 1000,
+            })
+        );
+        assert!(notifier
+            .check_finality(start + threshold + threshold)
+            .is_none());
+        notifier.block_added(7);
+        assert!(notifier.check_finality(Timestamp::now()).is_none());
+        assert!(matches!(
+            notifier.check_finality(Timestamp::now() + threshold),
+            Some(Alert::FinalityStall {
+                last_block_height: Some(7),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn should_compute_used_percent() {
+        assert_eq!(used_percent(0, 0), 0);
+        assert_eq!(used_percent(1_000, 1_000), 0);
+        assert_eq!(used_percent(1_000, 95), 90);
+        assert_eq!(used_percent(u64::MAX, 0), 100);
+    }
+
+    #[test]
+    fn should_serialize_notification_with_event_tag() {
+        let alert = Alert::DiskAlmostFull {
+            path: PathBuf::from("/var/lib/casper"),
+            used_percent: 93,
+        };
+        let notification = Notification {
+            chain_name: "casper-example",
+            timestamp: Timestamp::zero(),
+            alert: &alert,
+        };
+        let json: serde_json::Value = serde_json::to_value(&notification).unwrap();
+        assert_eq!(json["event"], "disk_almost_full");
+        assert_eq!(json["chain_name"], "casper-example");
+        assert_eq!(json["used_percent"], 93);
+        assert_eq!(json["path"], "/var/lib/casper");
+    }
+}
@@ -0,0 +1,114 @@
+use std::{path::Path, time::Duration};
+
+use datasize::DataSize;
+use http::Uri;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::ConfigValidator;
+
+/// Default number of times a failed delivery is retried.
+const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// Default delay before retrying a failed delivery for the first time.
+const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// Default time allowed for a single delivery attempt.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default number of blocks before an upgrade's activation point it is announced as approaching.
+const DEFAULT_UPGRADE_WARNING_BLOCKS: u64 = 100;
+
+/// Default usage of the storage disk in percent above which it is considered almost full.
+const DEFAULT_DISK_USAGE_THRESHOLD_PERCENT: u8 = 90;
+
+/// Default time without a new block after which finality is considered stalled.
+const DEFAULT_FINALITY_STALL_THRESHOLD: Duration = Duration::from_secs(600);
+
+/// Default interval between checks of the disk usage and of finality stalls.
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Configuration of the notifications sent to the node operator.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// URLs notifications are posted to as JSON. No notifications are sent if empty.
+    pub webhook_urls: Vec<String>,
+    /// File containing the key the notifications are signed with using HMAC-SHA256, with the
+    /// signature sent in the `X-Casper-Signature` header. Notifications are not signed if empty.
+    ///
+    /// Relative paths are resolved against the config directory.
+    pub hmac_key_path: String,
+    /// Number of times a failed delivery is retried before giving up.
+    pub max_retries: u32,
+    /// Time in milliseconds to wait before retrying a failed delivery for the first time. Doubled
+    /// on every further retry.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub retry_base_delay: Duration,
+    /// Time in milliseconds allowed for a single delivery attempt.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub request_timeout: Duration,
+    /// Number of blocks before the activation point of an upgrade it is notified as approaching.
+    pub upgrade_warning_blocks: u64,
+    /// Usage of the disk holding the storage in percent above which it is notified as almost
+    /// full.
+    pub disk_usage_threshold_percent: u8,
+    /// Time in milliseconds without a new block after which finality is notified as stalled.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub finality_stall_threshold: Duration,
+    /// Interval in milliseconds between checks of the disk usage and of finality stalls.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub check_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            webhook_urls: Vec::new(),
+            hmac_key_path: String::new(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_base_delay: DEFAULT_RETRY_BASE_DELAY,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            upgrade_warning_blocks: DEFAULT_UPGRADE_WARNING_BLOCKS,
+            disk_usage_threshold_percent: DEFAULT_DISK_USAGE_THRESHOLD_PERCENT,
+            finality_stall_threshold: DEFAULT_FINALITY_STALL_THRESHOLD,
+            check_interval: DEFAULT_CHECK_INTERVAL,
+        }
+    }
+}
+
+impl Config {
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        for url in &self.webhook_urls {
+            match url.parse::<Uri>() {
+                Ok(uri) => validator.ensure(
+                    matches!(uri.scheme_str(), Some("http") | Some("https"))
+                        && uri.host().is_some(),
+                    "webhook_urls",
+                    format!("{} is not an http or https URL", url),
+                ),
+                Err(error) => {
+                    validator.violation("webhook_urls", format!("invalid URL {}: {}", url, error))
+                }
+            }
+        }
+        if !self.hmac_key_path.is_empty() {
+            validator.ensure_file("hmac_key_path", Path::new(&self.hmac_key_path));
+        }
+        validator.ensure_non_zero("request_timeout", self.request_timeout);
+        validator.ensure_non_zero("check_interval", self.check_interval);
+        validator.ensure(
+            self.disk_usage_threshold_percent <= 100,
+            "disk_usage_threshold_percent",
+            "must not exceed 100",
+        );
+    }
+
+    /// Resolves a relative `hmac_key_path` against `root`.
+    pub(crate) fn resolve_path(&mut self, root: &Path) {
+        if !self.hmac_key_path.is_empty() {
+            self.hmac_key_path = root.join(&self.hmac_key_path).display().to_string();
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Delivery of notifications to webhooks.
+//!
+//! Every delivery attempt opens a new connection, as notifications are rare enough for keeping
+//! connections alive not to be worth it.
+
+use std::io;
+
+use http::{
+    header::{CONTENT_TYPE, HOST},
+    uri::InvalidUri,
+    Request, StatusCode, Uri,
+};
+use hyper::Body;
+use openssl::{
+    error::ErrorStack,
+    hash::MessageDigest,
+    pkey::PKey,
+    sign::Signer,
+    ssl::{SslConnector, SslMethod},
+};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    task,
+};
+use tracing::debug;
+
+use crate::utils::{self, ResolveAddressError};
+
+/// Header carrying the HMAC-SHA256 signature of the body.
+pub(super) const SIGNATURE_HEADER: &str = "X-Casper-Signature";
+
+/// Error delivering a notification to a webhook.
+#[derive(Debug, Error)]
+pub(super) enum DeliveryError {
+    /// The webhook URL could not be parsed.
+    #[error("invalid URL: {0}")]
+    InvalidUrl(#[from] InvalidUri),
+    /// The webhook URL is neither http nor https, or lacks a host.
+    #[error("unsupported URL, expected http(s)://host[:port]/path")]
+    UnsupportedUrl,
+    /// The host of the webhook could not be resolved.
+    #[error("{0}")]
+    Resolve(ResolveAddressError),
+    /// The TCP connection to the webhook failed.
+    #[error("connection failed: {0}")]
+    Connect(#[source] io::Error),
+    /// The TLS connector could not be set up.
+    #[error("TLS setup failed: {0}")]
+    Tls(#[from] ErrorStack),
+    /// The TLS handshake with the webhook failed.
+    #[error("TLS handshake failed: {0}")]
+    TlsHandshake(String),
+    /// The request could not be built.
+    #[error("invalid request: {0}")]
+    Request(#[from] http::Error),
+    /// The HTTP exchange failed.
+    #[error("HTTP error: {0}")]
+    Http(#[from] hyper::Error),
+    /// The webhook did not respond with a success status.
+    #[error("responded with status {0}")]
+    Status(StatusCode),
+    /// The delivery did not finish in time.
+    #[error("timed out")]
+    Timeout,
+}
+
+/// Computes the hex encoded HMAC-SHA256 signature of `body` with `key`.
+pub(super) fn sign(key: &[u8], body: &[u8]) -> Result<String, ErrorStack> {
+    let key = PKey::hmac(key)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(body)?;
+    Ok(hex::encode(signer.sign_to_vec()?))
+}
+
+/// Posts `body` as JSON to `url`, with `signature` in the `SIGNATURE_HEADER` if given.
+pub(super) async fn post(
+    url: &str,
+    body: &[u8],
+    signature: Option<&str>,
+) -> Result<(), DeliveryError> {
+    let uri: Uri = url.parse()?;
+    let https = match uri.scheme_str() {
+        Some("https") => true,
+        Some("http") => false,
+        _ => return Err(DeliveryError::UnsupportedUrl),
+    };
+    let (host, authority) = match (uri.host(), uri.authority()) {
+        (Some(host), Some(authority)) => (host.to_string(), authority.as_str().to_string()),
+        _ => return Err(DeliveryError::UnsupportedUrl),
+    };
+    let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+
+    let mut builder = Request::post(uri.path_and_query().map_or("/", |path| path.as_str()))
+        .header(HOST, authority)
+        .header(CONTENT_TYPE, "application/json");
+    if let Some(signature) = signature {
+        builder = builder.header(SIGNATURE_HEADER, format!("sha256={}", signature));
+    }
+    let request = builder.body(Body::from(body.to_vec()))?;
+
+    // Name resolution blocks, so it must not run on the reactor's threads.
+    let address = format!("{}:{}", host, port);
+    let socket_address = task::spawn_blocking(move || utils::resolve_address(&address))
+        .await
+        .expect("should run")
+        .map_err(DeliveryError::Resolve)?;
+    let stream = TcpStream::connect(socket_address)
+        .await
+        .map_err(DeliveryError::Connect)?;
+
+    let status = if https {
+        let config = SslConnector::builder(SslMethod::tls())?
+            .build()
+            .configure()?;
+        let tls_stream = tokio_openssl::connect(config, &host, stream)
+            .await
+            .map_err(|error| DeliveryError::TlsHandshake(error.to_string()))?;
+        send(tls_stream, request).await?
+    } else {
+        send(stream, request).await?
+    };
+
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(DeliveryError::Status(status))
+    }
+}
+
+/// Sends `request` over `io`, returning the status of the response.
+async fn send<T>(io: T, request: Request<Body>) -> Result<StatusCode, DeliveryError>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, connection) = hyper::client::conn::handshake(io).await?;
+    tokio::spawn(async move {
+        if let Err(error) = connection.await {
+            debug!(%error, "webhook connection closed with error");
+        }
+    });
+    let response = sender.send_request(request).await?;
+    Ok(response.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_sign_with_hmac_sha256() {
+        // Test case 2 of RFC 4231.
+        assert_eq!(
+            sign(b"Jefe", b"what do ya want for nothing?").unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -17,6 +17,11 @@
 //!     example: curl -X GET 'http://<ip>:8888/status'
 //! /metrics : time series data collected from the internals of the node being queried.
 //!     example: curl -X GET 'http://<ip>:8888/metrics'
+//!
+//! The server only starts listening once storage and the chainspec loader have been initialized.
+//! While the joiner is still synchronizing the linear chain, the status of the node would be
+//! misleading, so `/status` is answered with a `503 Service Unavailable` and a JSON body stating
+//! that the node is still syncing. Metrics are available throughout.
 
 mod config;
 mod event;
@@ -32,12 +37,13 @@ use tracing::{debug, error, warn};
 
 use super::Component;
 use crate::{
+    components::disk_monitor::DiskSpaceStage,
     effect::{
         requests::{ChainspecLoaderRequest, MetricsRequest, NetworkInfoRequest, StorageRequest},
         EffectBuilder, EffectExt, Effects,
     },
     reactor::Finalize,
-    types::{NodeId, StatusFeed},
+    types::{FeatureFlags, MaintenanceConfig, NodeId, StatusFeed},
     utils::{self, ListeningError},
     NodeRng,
 };
@@ -58,6 +64,15 @@ pub trait ReactorEventT:
 {
 }
 
+/// Whether the node is ready to answer queries about its state.
+#[derive(Clone, Copy, DataSize, Debug, Eq, PartialEq)]
+pub(crate) enum Readiness {
+    /// The node is still joining the network and synchronizing the linear chain.
+    Syncing,
+    /// The node has joined the network.
+    Ready,
+}
+
 impl<REv> ReactorEventT for REv where
     REv: From<Event>
         + From<RestRequest<NodeId>>
@@ -73,14 +88,27 @@ impl<REv> ReactorEventT for REv where
 #[derive(DataSize, Debug)]
 pub(crate) struct RestServer {
     /// When the message is sent, it signals the server loop to exit cleanly.
-    shutdown_sender: oneshot::Sender<()>,
+    shutdown_sender: Option<oneshot::Sender<()>>,
     /// The task handle which will only join once the server loop has exited.
     server_join_handle: Option<JoinHandle<()>>,
+    /// Scheduled maintenance windows, reported in status responses.
+    maintenance: MaintenanceConfig,
+    /// Feature flags of this node, reported in status responses.
+    features: FeatureFlags,
+    /// Whether consensus last reported that the node is possibly partitioned from the network.
+    possibly_partitioned: bool,
+    /// Whether consensus last reported that too few validators cite our recent units.
+    participation_degraded: bool,
+    /// The stage the disk monitor last reported.
+    disk_space_stage: DiskSpaceStage,
 }
 
 impl RestServer {
     pub(crate) fn new<REv>(
         config: Config,
+        maintenance: MaintenanceConfig,
+        features: FeatureFlags,
+        readiness: Readiness,
         effect_builder: EffectBuilder<REv>,
     ) -> Result<Self, ListeningError>
     where
@@ -89,14 +117,36 @@ impl RestServer {
         let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
 
         let builder = utils::start_listening(&config.address)?;
-        let server_join_handle =
-            tokio::spawn(http_server::run(builder, effect_builder, shutdown_receiver));
+        let server_join_handle = tokio::spawn(http_server::run(
+            builder,
+            effect_builder,
+            readiness,
+            shutdown_receiver,
+        ));
 
         Ok(RestServer {
-            shutdown_sender,
+            shutdown_sender: Some(shutdown_sender),
             server_join_handle: Some(server_join_handle),
+            maintenance,
+            features,
+            possibly_partitioned: false,
+            participation_degraded: false,
+            disk_space_stage: DiskSpaceStage::Normal,
         })
     }
+
+    /// Stops accepting new connections, letting the server exit once all pending requests have
+    /// been answered.
+    pub(crate) fn stop(&mut self) {
+        if let Some(shutdown_sender) = self.shutdown_sender.take() {
+            let _ = shutdown_sender.send(());
+        }
+    }
+
+    /// Returns whether the server has exited since it was stopped.
+    pub(crate) fn has_stopped(&mut self) -> bool {
+        utils::has_exited(&mut self.server_join_handle)
+    }
 }
 
 impl<REv> Component<REv> for RestServer
@@ -113,16 +163,34 @@ where
         event: Self::Event,
     ) -> Effects<Self::Event> {
         match event {
-            Event::RestRequest(RestRequest::GetStatus { responder }) => async move {
-                let (last_added_block, peers, chainspec_info) = join!(
-                    effect_builder.get_highest_block_from_storage(),
-                    effect_builder.network_peers(),
-                    effect_builder.get_chainspec_info()
-                );
-                let status_feed = StatusFeed::new(last_added_block, peers, chainspec_info);
-                responder.respond(status_feed).await;
+            Event::RestRequest(RestRequest::GetStatus { responder }) => {
+                let in_maintenance = self.maintenance.is_active();
+                let features = self.features.clone();
+                let possibly_partitioned = self.possibly_partitioned;
+                let participation_degraded = self.participation_degraded;
+                let disk_space_stage = self.disk_space_stage;
+                let components = effect_builder.get_component_stats();
+                async move {
+                    let (last_added_block, peers, chainspec_info) = join!(
+                        effect_builder.get_highest_block_from_storage(),
+                        effect_builder.network_peers(),
+                        effect_builder.get_chainspec_info()
+                    );
+                    let status_feed = StatusFeed::new(
+                        last_added_block,
+                        peers,
+                        chainspec_info,
+                        in_maintenance,
+                        features,
+                        possibly_partitioned,
+                        participation_degraded,
+                        disk_space_stage,
+                        components,
+                    );
+                    responder.respond(status_feed).await;
+                }
+                .ignore()
             }
-            .ignore(),
             Event::RestRequest(RestRequest::GetMetrics { responder }) => effect_builder
                 .get_metrics()
                 .event(move |text| Event::GetMetricsResult {
@@ -133,6 +201,22 @@ where
                 text,
                 main_responder,
             } => main_responder.respond(text).ignore(),
+            Event::PartitionStatus {
+                possibly_partitioned,
+            } => {
+                self.possibly_partitioned = possibly_partitioned;
+                Effects::new()
+            }
+            Event::ParticipationStatus {
+                participation_degraded,
+            } => {
+                self.participation_degraded = participation_degraded;
+                Effects::new()
+            }
+            Event::DiskSpaceStatus { stage } => {
+                self.disk_space_stage = stage;
+                Effects::new()
+            }
         }
     }
 }
@@ -140,7 +224,7 @@ where
 impl Finalize for RestServer {
     fn finalize(mut self) -> BoxFuture<'static, ()> {
         async {
-            let _ = self.shutdown_sender.send(());
+            self.stop();
 
             // Wait for the server to exit cleanly.
             if let Some(join_handle) = self.server_join_handle.take() {
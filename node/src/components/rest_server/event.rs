@@ -3,6 +3,7 @@ use std::fmt::{self, Display, Formatter};
 use derive_more::From;
 
 use crate::{
+    components::disk_monitor::DiskSpaceStage,
     effect::{requests::RestRequest, Responder},
     types::NodeId,
 };
@@ -15,6 +16,15 @@ pub enum Event {
         text: Option<String>,
         main_responder: Responder<Option<String>>,
     },
+    PartitionStatus {
+        possibly_partitioned: bool,
+    },
+    ParticipationStatus {
+        participation_degraded: bool,
+    },
+    DiskSpaceStatus {
+        stage: DiskSpaceStage,
+    },
 }
 
 impl Display for Event {
@@ -25,6 +35,21 @@ impl Display for Event {
                 Some(txt) => write!(formatter, "get metrics ({} bytes)", txt.len()),
                 None => write!(formatter, "get metrics (failed)"),
             },
+            Event::PartitionStatus {
+                possibly_partitioned,
+            } => write!(
+                formatter,
+                "partition status: possibly partitioned = {}",
+                possibly_partitioned
+            ),
+            Event::ParticipationStatus {
+                participation_degraded,
+            } => write!(
+                formatter,
+                "participation status: participation degraded = {}",
+                participation_degraded
+            ),
+            Event::DiskSpaceStatus { stage } => write!(formatter, "disk space status: {}", stage),
         }
     }
 }
@@ -1,6 +1,7 @@
 use futures::FutureExt;
 use http::Response;
 use hyper::Body;
+use serde::Serialize;
 use tracing::warn;
 use warp::{
     filters::BoxedFilter,
@@ -10,7 +11,7 @@ use warp::{
     Filter,
 };
 
-use super::ReactorEventT;
+use super::{ReactorEventT, Readiness};
 use crate::{
     components::CLIENT_API_VERSION,
     effect::{requests::RestRequest, EffectBuilder},
@@ -24,22 +25,43 @@ pub const STATUS_API_PATH: &str = "status";
 /// The metrics URL path.
 pub const METRICS_API_PATH: &str = "metrics";
 
+/// The body of responses to queries the node can't answer yet.
+#[derive(Serialize)]
+struct NotReadyResult {
+    /// The stage of startup the node is in.
+    status: &'static str,
+    /// A human readable explanation.
+    message: &'static str,
+}
+
+/// Replies with `503 Service Unavailable`, as the node is still syncing.
+fn syncing_reply() -> Response<Body> {
+    let body = NotReadyResult {
+        status: "syncing",
+        message: "the node is still joining the network and synchronizing the linear chain",
+    };
+    reply::with_status(reply::json(&body), StatusCode::SERVICE_UNAVAILABLE).into_response()
+}
+
 pub(super) fn create_status_filter<REv: ReactorEventT>(
     effect_builder: EffectBuilder<REv>,
+    readiness: Readiness,
 ) -> BoxedFilter<(Response<Body>,)> {
     warp::get()
         .and(warp::path(STATUS_API_PATH))
-        .and_then(move || {
-            effect_builder
+        .and_then(move || async move {
+            if readiness == Readiness::Syncing {
+                return Ok::<_, Rejection>(syncing_reply());
+            }
+            let status_feed = effect_builder
                 .make_request(
                     |responder| RestRequest::GetStatus { responder },
                     QueueKind::Api,
                 )
-                .map(|status_feed| {
-                    let mut body = GetStatusResult::from(status_feed);
-                    body.set_api_version(CLIENT_API_VERSION.clone());
-                    Ok::<_, Rejection>(reply::json(&body).into_response())
-                })
+                .await;
+            let mut body = GetStatusResult::from(status_feed);
+            body.set_api_version(CLIENT_API_VERSION.clone());
+            Ok(reply::json(&body).into_response())
         })
         .boxed()
 }
@@ -6,7 +6,7 @@ use tokio::sync::oneshot;
 use tracing::{info, warn};
 use warp::Filter;
 
-use super::{filters, ReactorEventT};
+use super::{filters, ReactorEventT, Readiness};
 use crate::effect::EffectBuilder;
 
 /// Run the REST HTTP server.
@@ -15,10 +15,11 @@ use crate::effect::EffectBuilder;
 pub(super) async fn run<REv: ReactorEventT>(
     builder: Builder<AddrIncoming>,
     effect_builder: EffectBuilder<REv>,
+    readiness: Readiness,
     shutdown_receiver: oneshot::Receiver<()>,
 ) {
     // REST filters.
-    let rest_status = filters::create_status_filter(effect_builder);
+    let rest_status = filters::create_status_filter(effect_builder, readiness);
     let rest_metrics = filters::create_metrics_filter(effect_builder);
 
     let service = warp_json_rpc::service(rest_status.or(rest_metrics));
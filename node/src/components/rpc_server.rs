@@ -19,34 +19,38 @@ mod event;
 mod http_server;
 pub mod rpcs;
 
-use std::{convert::Infallible, fmt::Debug};
+use std::fmt::Debug;
 
 use datasize::DataSize;
 use futures::join;
+use tokio::{sync::oneshot, task::JoinHandle};
 
 use casper_execution_engine::{
     core::engine_state::{
-        self, BalanceRequest, BalanceResult, GetEraValidatorsError, QueryRequest, QueryResult,
+        self, deploy_item::DeployItem, execute_request::ExecuteRequest, BalanceRequest,
+        BalanceResult, GetEraValidatorsError, QueryRequest, QueryResult,
     },
     storage::protocol_data::ProtocolData,
 };
-use casper_types::{auction::EraValidators, Key, ProtocolVersion, URef};
+use casper_types::{auction::EraValidators, ExecutionResult, Key, ProtocolVersion, URef};
 
 use self::rpcs::chain::BlockIdentifier;
 
 use super::Component;
 use crate::{
-    components::contract_runtime::EraValidatorsRequest,
+    components::{
+        contract_runtime::EraValidatorsRequest, disk_monitor::DiskSpaceStage, era_metrics,
+    },
     crypto::hash::Digest,
     effect::{
         announcements::RpcServerAnnouncement,
         requests::{
-            ChainspecLoaderRequest, ContractRuntimeRequest, LinearChainRequest, MetricsRequest,
-            NetworkInfoRequest, RpcRequest, StorageRequest,
+            ChainspecLoaderRequest, ConsensusRequest, ContractRuntimeRequest, LinearChainRequest,
+            MetricsRequest, NetworkInfoRequest, RpcRequest, StateStoreRequest, StorageRequest,
         },
         EffectBuilder, EffectExt, Effects, Responder,
     },
-    types::{NodeId, StatusFeed},
+    types::{Block, Deploy, FeatureFlags, MaintenanceConfig, NodeId, StatusFeed, Timestamp},
     utils::{self, ListeningError},
     NodeRng,
 };
@@ -60,10 +64,12 @@ pub trait ReactorEventT:
     + From<RpcRequest<NodeId>>
     + From<RpcServerAnnouncement>
     + From<ChainspecLoaderRequest>
+    + From<ConsensusRequest>
     + From<ContractRuntimeRequest>
     + From<LinearChainRequest<NodeId>>
     + From<MetricsRequest>
     + From<NetworkInfoRequest<NodeId>>
+    + From<StateStoreRequest>
     + From<StorageRequest>
     + Send
 {
@@ -74,31 +80,89 @@ impl<REv> ReactorEventT for REv where
         + From<RpcRequest<NodeId>>
         + From<RpcServerAnnouncement>
         + From<ChainspecLoaderRequest>
+        + From<ConsensusRequest>
         + From<ContractRuntimeRequest>
         + From<LinearChainRequest<NodeId>>
         + From<MetricsRequest>
         + From<NetworkInfoRequest<NodeId>>
+        + From<StateStoreRequest>
         + From<StorageRequest>
         + Send
         + 'static
 {
 }
 
+/// Capability to execute deploys without committing their effects, see
+/// `EffectBuilder::speculatively_execute`.
+///
+/// Only the RPC server can create it, as speculative execution is offered to clients only and would
+/// otherwise compete with block execution from inside the node.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SpeculativeExecutionCapability(());
+
 #[derive(DataSize, Debug)]
-pub(crate) struct RpcServer {}
+pub struct RpcServer {
+    /// When the message is sent, it signals the server loop to exit cleanly.
+    shutdown_sender: Option<oneshot::Sender<()>>,
+    /// The task handle which will only join once the server loop has exited.
+    server_join_handle: Option<JoinHandle<()>>,
+    /// Scheduled maintenance windows, reported in status responses.
+    maintenance: MaintenanceConfig,
+    /// Feature flags of this node, reported in status responses.
+    features: FeatureFlags,
+    /// Whether consensus last reported that the node is possibly partitioned from the network.
+    possibly_partitioned: bool,
+    /// Whether consensus last reported that too few validators cite our recent units.
+    participation_degraded: bool,
+    /// The stage the disk monitor last reported.
+    disk_space_stage: DiskSpaceStage,
+}
 
 impl RpcServer {
     pub(crate) fn new<REv>(
         config: Config,
+        maintenance: MaintenanceConfig,
+        features: FeatureFlags,
         effect_builder: EffectBuilder<REv>,
     ) -> Result<Self, ListeningError>
     where
         REv: ReactorEventT,
     {
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+
         let builder = utils::start_listening(&config.address)?;
-        tokio::spawn(http_server::run(builder, effect_builder));
+        let server_join_handle = if config.read_only {
+            tokio::spawn(http_server::run_read_only(
+                builder,
+                effect_builder,
+                shutdown_receiver,
+            ))
+        } else {
+            tokio::spawn(http_server::run(builder, effect_builder, shutdown_receiver))
+        };
+
+        Ok(RpcServer {
+            shutdown_sender: Some(shutdown_sender),
+            server_join_handle: Some(server_join_handle),
+            maintenance,
+            features,
+            possibly_partitioned: false,
+            participation_degraded: false,
+            disk_space_stage: DiskSpaceStage::Normal,
+        })
+    }
+
+    /// Stops accepting new connections, letting the server exit once all pending requests have
+    /// been answered.
+    pub(crate) fn stop(&mut self) {
+        if let Some(shutdown_sender) = self.shutdown_sender.take() {
+            let _ = shutdown_sender.send(());
+        }
+    }
 
-        Ok(RpcServer {})
+    /// Returns whether the server has exited since it was stopped.
+    pub(crate) fn has_stopped(&mut self) -> bool {
+        utils::has_exited(&mut self.server_join_handle)
     }
 }
 
@@ -165,6 +229,41 @@ impl RpcServer {
                 main_responder: responder,
             })
     }
+
+    fn handle_speculative_exec<REv: ReactorEventT>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        block: Block,
+        deploy: Deploy,
+        responder: Responder<Result<ExecutionResult, engine_state::RootNotFound>>,
+    ) -> Effects<Event> {
+        let mut deploy_item = DeployItem::from(deploy);
+        // Clients simulate deploys before signing them, so an unsigned deploy is executed as if
+        // signed by its account. As nothing is committed, this reveals no more than a query would.
+        if deploy_item.authorization_keys.is_empty() {
+            deploy_item.authorization_keys.insert(deploy_item.address);
+        }
+        let execute_request = ExecuteRequest::new(
+            (*block.header().state_root_hash()).into(),
+            Timestamp::now().millis(),
+            vec![Ok(deploy_item)],
+            ProtocolVersion::V1_0_0,
+            *block.header().proposer(),
+        );
+        async move {
+            let result = effect_builder
+                .speculatively_execute(SpeculativeExecutionCapability(()), execute_request)
+                .await
+                .map(|execution_results| {
+                    let execution_result = execution_results
+                        .front()
+                        .expect("should be one exec result per deploy");
+                    ExecutionResult::from(execution_result)
+                });
+            responder.respond(result).await;
+        }
+        .ignore()
+    }
 }
 
 impl<REv> Component<REv> for RpcServer
@@ -172,7 +271,7 @@ where
     REv: ReactorEventT,
 {
     type Event = Event;
-    type ConstructionError = Infallible;
+    type ConstructionError = ListeningError;
 
     fn handle_event(
         &mut self,
@@ -256,22 +355,83 @@ where
                     result: Box::new(result),
                     main_responder: responder,
                 }),
+            Event::RpcRequest(RpcRequest::SpeculativeExec {
+                block,
+                deploy,
+                responder,
+            }) => self.handle_speculative_exec(effect_builder, *block, *deploy, responder),
             Event::RpcRequest(RpcRequest::GetPeers { responder }) => effect_builder
                 .network_peers()
                 .event(move |peers| Event::GetPeersResult {
                     peers,
                     main_responder: responder,
                 }),
-            Event::RpcRequest(RpcRequest::GetStatus { responder }) => async move {
-                let (last_added_block, peers, chainspec_info) = join!(
-                    effect_builder.get_highest_block_from_storage(),
-                    effect_builder.network_peers(),
+            Event::RpcRequest(RpcRequest::GetPeerProtocolVersions { responder }) => async move {
+                let (peer_versions, chainspec_info) = join!(
+                    effect_builder.network_peer_protocol_versions(),
                     effect_builder.get_chainspec_info()
                 );
-                let status_feed = StatusFeed::new(last_added_block, peers, chainspec_info);
-                responder.respond(status_feed).await;
+                responder
+                    .respond((chainspec_info.protocol_version().clone(), peer_versions))
+                    .await;
+            }
+            .ignore(),
+            Event::RpcRequest(RpcRequest::GetImportedBans { responder }) => async move {
+                let bans = effect_builder.network_imported_bans().await;
+                responder.respond(bans).await;
+            }
+            .ignore(),
+            Event::RpcRequest(RpcRequest::GetValidatorAttestations { responder }) => async move {
+                let validators = effect_builder.current_validators().await;
+                let attestations = effect_builder.network_attestations(validators).await;
+                responder.respond(attestations).await;
+            }
+            .ignore(),
+            Event::RpcRequest(RpcRequest::GetPeerRequestAudit { responder }) => async move {
+                let audit = effect_builder.network_request_audit().await;
+                responder.respond(audit).await;
             }
             .ignore(),
+            Event::RpcRequest(RpcRequest::SamplePeers { count, responder }) => async move {
+                let sample = effect_builder.network_peer_sample(count).await;
+                responder.respond(sample).await;
+            }
+            .ignore(),
+            Event::RpcRequest(RpcRequest::GetEraMetrics { era_id, responder }) => async move {
+                let snapshot = effect_builder
+                    .load_state(era_metrics::create_storage_key(era_id).into())
+                    .await;
+                responder.respond(snapshot).await;
+            }
+            .ignore(),
+            Event::RpcRequest(RpcRequest::GetStatus { responder }) => {
+                let in_maintenance = self.maintenance.is_active();
+                let features = self.features.clone();
+                let possibly_partitioned = self.possibly_partitioned;
+                let participation_degraded = self.participation_degraded;
+                let disk_space_stage = self.disk_space_stage;
+                let components = effect_builder.get_component_stats();
+                async move {
+                    let (last_added_block, peers, chainspec_info) = join!(
+                        effect_builder.get_highest_block_from_storage(),
+                        effect_builder.network_peers(),
+                        effect_builder.get_chainspec_info()
+                    );
+                    let status_feed = StatusFeed::new(
+                        last_added_block,
+                        peers,
+                        chainspec_info,
+                        in_maintenance,
+                        features,
+                        possibly_partitioned,
+                        participation_degraded,
+                        disk_space_stage,
+                        components,
+                    );
+                    responder.respond(status_feed).await;
+                }
+                .ignore()
+            }
             Event::RpcRequest(RpcRequest::GetMetrics { responder }) => effect_builder
                 .get_metrics()
                 .event(move |text| Event::GetMetricsResult {
@@ -317,6 +477,22 @@ where
                 text,
                 main_responder,
             } => main_responder.respond(text).ignore(),
+            Event::PartitionStatus {
+                possibly_partitioned,
+            } => {
+                self.possibly_partitioned = possibly_partitioned;
+                Effects::new()
+            }
+            Event::ParticipationStatus {
+                participation_degraded,
+            } => {
+                self.participation_degraded = participation_degraded;
+                Effects::new()
+            }
+            Event::DiskSpaceStatus { stage } => {
+                self.disk_space_stage = stage;
+                Effects::new()
+            }
         }
     }
 }
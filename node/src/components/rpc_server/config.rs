@@ -13,6 +13,9 @@ const DEFAULT_ADDRESS: &str = "0.0.0.0:0";
 pub struct Config {
     /// Address to bind JSON-RPC HTTP server to.
     pub address: String,
+    /// Whether to only serve the RPCs answered from storage, set by the read replica reactor.
+    #[serde(skip)]
+    pub(crate) read_only: bool,
 }
 
 impl Config {
@@ -20,6 +23,15 @@ impl Config {
     pub fn new() -> Self {
         Config {
             address: DEFAULT_ADDRESS.to_string(),
+            read_only: false,
+        }
+    }
+
+    /// Creates a configuration for a read replica's server, see `http_server::run_read_only`.
+    pub(crate) fn read_only(address: String) -> Self {
+        Config {
+            address,
+            read_only: true,
         }
     }
 }
@@ -12,6 +12,7 @@ use casper_execution_engine::{
 use casper_types::{auction::EraValidators, Transfer};
 
 use crate::{
+    components::disk_monitor::DiskSpaceStage,
     effect::{requests::RpcRequest, Responder},
     rpcs::chain::BlockIdentifier,
     types::{Block, BlockHash, Deploy, DeployHash, DeployMetadata, NodeId},
@@ -56,6 +57,15 @@ pub enum Event {
         text: Option<String>,
         main_responder: Responder<Option<String>>,
     },
+    PartitionStatus {
+        possibly_partitioned: bool,
+    },
+    ParticipationStatus {
+        participation_degraded: bool,
+    },
+    DiskSpaceStatus {
+        stage: DiskSpaceStage,
+    },
     GetBalanceResult {
         result: Result<BalanceResult, engine_state::Error>,
         main_responder: Responder<Result<BalanceResult, engine_state::Error>>,
@@ -108,6 +118,21 @@ impl Display for Event {
                 Some(txt) => write!(formatter, "get metrics ({} bytes)", txt.len()),
                 None => write!(formatter, "get metrics (failed)"),
             },
+            Event::PartitionStatus {
+                possibly_partitioned,
+            } => write!(
+                formatter,
+                "partition status: possibly partitioned = {}",
+                possibly_partitioned
+            ),
+            Event::ParticipationStatus {
+                participation_degraded,
+            } => write!(
+                formatter,
+                "participation status: participation degraded = {}",
+                participation_degraded
+            ),
+            Event::DiskSpaceStatus { stage } => write!(formatter, "disk space status: {}", stage),
         }
     }
 }
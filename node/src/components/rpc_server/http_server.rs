@@ -1,56 +1,137 @@
-use std::convert::Infallible;
+use std::{convert::Infallible, fmt::Display};
 
 use futures::future::{self};
-use hyper::server::{conn::AddrIncoming, Builder};
+use http::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Request, Response, StatusCode,
+};
+use hyper::{
+    body::HttpBody,
+    server::{conn::AddrIncoming, Builder},
+    service::Service,
+    Body,
+};
 use tokio::sync::oneshot;
 use tracing::{info, trace};
-use warp::Filter;
+use warp::{reply::Reply, Filter, Rejection};
 
 use super::{
     rpcs::{self, RpcWithOptionalParamsExt, RpcWithParamsExt, RpcWithoutParamsExt},
     ReactorEventT,
 };
-use crate::effect::EffectBuilder;
+use crate::{effect::EffectBuilder, utils::bounded};
+
+/// JSON-RPC error code of a request which is not a valid request object.
+const INVALID_REQUEST_CODE: i64 = -32600;
 
 /// Run the JSON-RPC server.
+///
+/// A message received on `shutdown_receiver` will cause the server to exit cleanly.
 pub(super) async fn run<REv: ReactorEventT>(
     builder: Builder<AddrIncoming>,
     effect_builder: EffectBuilder<REv>,
+    shutdown_receiver: oneshot::Receiver<()>,
 ) {
     // RPC filters.
     let rpc_put_deploy = rpcs::account::PutDeploy::create_filter(effect_builder);
+    let rpc_speculative_exec = rpcs::account::SpeculativeExec::create_filter(effect_builder);
     let rpc_get_block = rpcs::chain::GetBlock::create_filter(effect_builder);
     let rpc_get_block_transfers = rpcs::chain::GetBlockTransfers::create_filter(effect_builder);
+    let rpc_get_recent_blocks = rpcs::chain::GetRecentBlocks::create_filter(effect_builder);
     let rpc_get_state_root_hash = rpcs::chain::GetStateRootHash::create_filter(effect_builder);
     let rpc_get_item = rpcs::state::GetItem::create_filter(effect_builder);
     let rpc_get_balance = rpcs::state::GetBalance::create_filter(effect_builder);
+    let rpc_get_account_balance_at_height =
+        rpcs::state::GetAccountBalanceAtHeight::create_filter(effect_builder);
     let rpc_get_deploy = rpcs::info::GetDeploy::create_filter(effect_builder);
     let rpc_get_peers = rpcs::info::GetPeers::create_filter(effect_builder);
+    let rpc_get_peer_versions = rpcs::info::GetPeerVersions::create_filter(effect_builder);
+    let rpc_get_imported_bans = rpcs::info::GetImportedBans::create_filter(effect_builder);
+    let rpc_get_validator_attestations =
+        rpcs::info::GetValidatorAttestations::create_filter(effect_builder);
+    let rpc_get_peer_request_audit = rpcs::info::GetPeerRequestAudit::create_filter(effect_builder);
+    let rpc_sample_peers = rpcs::info::SamplePeers::create_filter(effect_builder);
+    let rpc_get_era_metrics = rpcs::info::GetEraMetrics::create_filter(effect_builder);
     let rpc_get_status = rpcs::info::GetStatus::create_filter(effect_builder);
     let rpc_get_era_info = rpcs::chain::GetEraInfoBySwitchBlock::create_filter(effect_builder);
     let rpc_get_auction_info = rpcs::state::GetAuctionInfo::create_filter(effect_builder);
     let rpc_get_rpcs = rpcs::docs::ListRpcs::create_filter(effect_builder);
 
-    let service = warp_json_rpc::service(
+    serve(
+        builder,
         rpc_put_deploy
+            .or(rpc_speculative_exec)
             .or(rpc_get_block)
             .or(rpc_get_block_transfers)
+            .or(rpc_get_recent_blocks)
             .or(rpc_get_state_root_hash)
             .or(rpc_get_item)
             .or(rpc_get_balance)
+            .or(rpc_get_account_balance_at_height)
             .or(rpc_get_deploy)
             .or(rpc_get_peers)
+            .or(rpc_get_peer_versions)
+            .or(rpc_get_imported_bans)
+            .or(rpc_get_validator_attestations)
+            .or(rpc_get_peer_request_audit)
+            .or(rpc_sample_peers)
+            .or(rpc_get_era_metrics)
             .or(rpc_get_status)
             .or(rpc_get_era_info)
             .or(rpc_get_auction_info)
             .or(rpc_get_rpcs),
-    );
+        shutdown_receiver,
+    )
+    .await
+}
 
-    // Start the server, passing a oneshot receiver to allow the server to be shut down gracefully.
-    let make_svc =
-        hyper::service::make_service_fn(move |_| future::ok::<_, Infallible>(service.clone()));
-    let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+/// Run the JSON-RPC server of a read replica.
+///
+/// Only the RPCs answered from storage alone are served, so a read replica need not route any
+/// requests but storage requests.
+pub(super) async fn run_read_only<REv: ReactorEventT>(
+    builder: Builder<AddrIncoming>,
+    effect_builder: EffectBuilder<REv>,
+    shutdown_receiver: oneshot::Receiver<()>,
+) {
+    let rpc_get_block = rpcs::chain::GetBlock::create_filter(effect_builder);
+    let rpc_get_block_transfers = rpcs::chain::GetBlockTransfers::create_filter(effect_builder);
+    let rpc_get_recent_blocks = rpcs::chain::GetRecentBlocks::create_filter(effect_builder);
+    let rpc_get_state_root_hash = rpcs::chain::GetStateRootHash::create_filter(effect_builder);
+    let rpc_get_deploy = rpcs::info::GetDeploy::create_filter(effect_builder);
+    let rpc_get_era_metrics = rpcs::info::GetEraMetrics::create_filter(effect_builder);
+
+    serve(
+        builder,
+        rpc_get_block
+            .or(rpc_get_block_transfers)
+            .or(rpc_get_recent_blocks)
+            .or(rpc_get_state_root_hash)
+            .or(rpc_get_deploy)
+            .or(rpc_get_era_metrics),
+        shutdown_receiver,
+    )
+    .await
+}
 
+/// Serves the given RPC filters until the server is shut down.
+async fn serve<F>(
+    builder: Builder<AddrIncoming>,
+    filters: F,
+    shutdown_receiver: oneshot::Receiver<()>,
+) where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    let service = warp_json_rpc::service(filters);
+
+    // Start the server, passing a oneshot receiver to allow the server to be shut down gracefully.
+    let make_svc = hyper::service::make_service_fn(move |_| {
+        let service = service.clone();
+        future::ok::<_, Infallible>(hyper::service::service_fn(move |request| {
+            call_bounded(service.clone(), request)
+        }))
+    });
     let server = builder.serve(make_svc);
     info!(address = %server.local_addr(), "started JSON-RPC server");
 
@@ -62,8 +143,95 @@ pub(super) async fn run<REv: ReactorEventT>(
 
     let _ = server_joiner.await;
 
-    // Shut down the server.
-    let _ = shutdown_sender.send(());
-
     trace!("JSON-RPC server stopped");
 }
+
+/// Reads the body of `request` within `bounded::Limits::RPC`, then hands it on to `service`.
+///
+/// `warp_json_rpc` reads and parses whole request bodies before any filter sees them, so oversized
+/// or too deeply nested requests are answered with an error here instead.
+async fn call_bounded<S>(mut service: S, request: Request<Body>) -> Result<Response<Body>, S::Error>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+{
+    let limits = bounded::Limits::RPC;
+    let (parts, mut body) = request.into_parts();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(error) => return Ok(invalid_request(StatusCode::BAD_REQUEST, error)),
+        };
+        if (bytes.len() + chunk.len()) as u64 > limits.max_allocation {
+            return Ok(invalid_request(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("request exceeds {} bytes", limits.max_allocation),
+            ));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    // Malformed requests are left to `warp_json_rpc` to answer as usual.
+    if let Err(bounded::Error::LimitExceeded(exceeded)) =
+        bounded::from_json::<serde_json::Value>(&bytes, limits)
+    {
+        return Ok(invalid_request(StatusCode::BAD_REQUEST, exceeded));
+    }
+    future::poll_fn(|cx| service.poll_ready(cx)).await?;
+    service
+        .call(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+/// Returns a JSON-RPC error response for a request rejected before being parsed.
+fn invalid_request<M: Display>(status: StatusCode, message: M) -> Response<Body> {
+    let error = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": {
+            "code": INVALID_REQUEST_CODE,
+            "message": message.to_string(),
+        },
+    });
+    let mut response = Response::new(Body::from(error.to_string()));
+    *response.status_mut() = status;
+    let _ = response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn status_of(body: Vec<u8>) -> StatusCode {
+        let service = hyper::service::service_fn(|_request: Request<Body>| {
+            future::ok::<_, Infallible>(Response::new(Body::empty()))
+        });
+        let request = Request::post("/rpc").body(Body::from(body)).unwrap();
+        call_bounded(service, request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn should_reject_requests_exceeding_limits() {
+        let request = br#"{"jsonrpc":"2.0","id":1,"method":"info_get_status"}"#.to_vec();
+        assert_eq!(status_of(request).await, StatusCode::OK);
+
+        let depth = bounded::Limits::RPC.max_depth + 1;
+        let nested = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"chain_get_block","params":{}{}}}"#,
+            "[".repeat(depth),
+            "]".repeat(depth)
+        );
+        assert_eq!(
+            status_of(nested.into_bytes()).await,
+            StatusCode::BAD_REQUEST
+        );
+
+        let oversized = vec![b' '; bounded::Limits::RPC.max_allocation as usize + 1];
+        assert_eq!(status_of(oversized).await, StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Invalid JSON is answered by `warp_json_rpc` as usual.
+        assert_eq!(status_of(b"not json".to_vec()).await, StatusCode::OK);
+    }
+}
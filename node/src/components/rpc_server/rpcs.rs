@@ -10,20 +10,23 @@ pub mod state;
 
 use std::str;
 
-use futures::{future::BoxFuture, TryFutureExt};
+use futures::{
+    future::{self, BoxFuture},
+    TryFutureExt,
+};
 use http::Response;
 use hyper::Body;
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use warp::{
     filters::BoxedFilter,
     reject::{self, Reject},
-    Filter,
+    Filter, Rejection,
 };
 use warp_json_rpc::{filters, Builder};
 
 use super::{ReactorEventT, RpcRequest};
-use crate::effect::EffectBuilder;
+use crate::{effect::EffectBuilder, utils::bounded};
 use docs::DocExample;
 
 /// The URL path.
@@ -43,6 +46,15 @@ enum ErrorCode {
     GetBalanceFailed = 32006,
     GetBalanceFailedToExecute = 32007,
     InvalidDeploy = 32008,
+    BlockNotRetained = 32009,
+    NoSuchAccount = 32010,
+    InvalidRecentBlocksCount = 32011,
+    DeployTtlOutOfBounds = 32012,
+    DeployTimestampInFuture = 32013,
+    NoSuchEraMetrics = 32014,
+    DeployAcceptancePaused = 32015,
+    SpeculativeExecFailed = 32016,
+    DeployRejectedByPolicy = 32017,
 }
 
 #[derive(Debug)]
@@ -56,6 +68,17 @@ impl From<anyhow::Error> for Error {
     }
 }
 
+/// Extracts the JSON-RPC request's "params", deserialized within `bounded::Limits::RPC`.
+fn bounded_params<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    filters::params::<serde_json::Value>().and_then(|params| {
+        future::ready(
+            bounded::from_json_value(params, bounded::Limits::RPC)
+                .map_err(|error| reject::custom(Error(format!("invalid params: {}", error)))),
+        )
+    })
+}
+
 /// A JSON-RPC requiring the "params" field to be present.
 pub trait RpcWithParams {
     /// The JSON-RPC "method" name.
@@ -87,7 +110,7 @@ pub(super) trait RpcWithParamsExt: RpcWithParams {
         warp::path(RPC_API_PATH)
             .and(filters::json_rpc())
             .and(filters::method(Self::METHOD))
-            .and(filters::params::<Self::RequestParams>())
+            .and(bounded_params::<Self::RequestParams>())
             .and_then(
                 move |response_builder: Builder, params: Self::RequestParams| {
                     Self::handle_request(effect_builder, response_builder, params)
@@ -173,7 +196,7 @@ pub(super) trait RpcWithOptionalParamsExt: RpcWithOptionalParams {
         let with_params = warp::path(RPC_API_PATH)
             .and(filters::json_rpc())
             .and(filters::method(Self::METHOD))
-            .and(filters::params::<Self::OptionalRequestParams>())
+            .and(bounded_params::<Self::OptionalRequestParams>())
             .and_then(
                 move |response_builder: Builder, params: Self::OptionalRequestParams| {
                     Self::handle_request(effect_builder, response_builder, Some(params))
@@ -15,12 +15,18 @@ use serde::{Deserialize, Serialize};
 use tracing::info;
 use warp_json_rpc::Builder;
 
-use super::{docs::DocExample, Error, ReactorEventT, RpcRequest, RpcWithParams, RpcWithParamsExt};
+use casper_types::ExecutionResult;
+
+use super::{
+    chain::{self, BlockIdentifier},
+    docs::DocExample,
+    Error, ReactorEventT, RpcRequest, RpcWithParams, RpcWithParamsExt,
+};
 use crate::{
-    components::{rpc_server::rpcs::ErrorCode, CLIENT_API_VERSION},
+    components::{deploy_acceptor, rpc_server::rpcs::ErrorCode, CLIENT_API_VERSION},
     effect::EffectBuilder,
     reactor::QueueKind,
-    types::{Deploy, DeployHash},
+    types::{Block, BlockHash, Deploy, DeployHash, DeployValidationFailure},
 };
 
 static PUT_DEPLOY_PARAMS: Lazy<PutDeployParams> = Lazy::new(|| PutDeployParams {
@@ -30,6 +36,15 @@ static PUT_DEPLOY_RESULT: Lazy<PutDeployResult> = Lazy::new(|| PutDeployResult {
     api_version: CLIENT_API_VERSION.clone(),
     deploy_hash: *Deploy::doc_example().id(),
 });
+static SPECULATIVE_EXEC_PARAMS: Lazy<SpeculativeExecParams> = Lazy::new(|| SpeculativeExecParams {
+    deploy: Deploy::doc_example().clone(),
+    block_identifier: Some(BlockIdentifier::Hash(*Block::doc_example().hash())),
+});
+static SPECULATIVE_EXEC_RESULT: Lazy<SpeculativeExecResult> = Lazy::new(|| SpeculativeExecResult {
+    api_version: CLIENT_API_VERSION.clone(),
+    block_hash: *Block::doc_example().hash(),
+    execution_result: ExecutionResult::example().clone(),
+});
 
 /// Params for "account_put_deploy" RPC request.
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
@@ -108,8 +123,27 @@ impl RpcWithParamsExt for PutDeploy {
                         %error,
                         "the deploy submitted by the client was invalid",
                     );
+                    let error_code = match &error {
+                        deploy_acceptor::Error::InvalidDeploy(failure) => match failure {
+                            DeployValidationFailure::ExcessiveTimeToLive { .. }
+                            | DeployValidationFailure::InsufficientTimeToLive { .. } => {
+                                ErrorCode::DeployTtlOutOfBounds
+                            }
+                            DeployValidationFailure::TimestampInFuture { .. } => {
+                                ErrorCode::DeployTimestampInFuture
+                            }
+                            _ => ErrorCode::InvalidDeploy,
+                        },
+                        deploy_acceptor::Error::AcceptancePaused => {
+                            ErrorCode::DeployAcceptancePaused
+                        }
+                        deploy_acceptor::Error::RejectedByPolicy { .. } => {
+                            ErrorCode::DeployRejectedByPolicy
+                        }
+                        _ => ErrorCode::InvalidDeploy,
+                    };
                     Ok(response_builder.error(warp_json_rpc::Error::custom(
-                        ErrorCode::InvalidDeploy as i64,
+                        error_code as i64,
                         error.to_string(),
                     ))?)
                 }
@@ -118,3 +152,110 @@ impl RpcWithParamsExt for PutDeploy {
         .boxed()
     }
 }
+
+/// Params for "account_speculative_exec" RPC request.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SpeculativeExecParams {
+    /// The `Deploy`, which need not be signed yet.
+    pub deploy: Deploy,
+    /// The block on top of whose global state the deploy is executed, the latest if not given.
+    pub block_identifier: Option<BlockIdentifier>,
+}
+
+impl DocExample for SpeculativeExecParams {
+    fn doc_example() -> &'static Self {
+        &*SPECULATIVE_EXEC_PARAMS
+    }
+}
+
+/// Result for "account_speculative_exec" RPC response.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SpeculativeExecResult {
+    /// The RPC API version.
+    #[schemars(with = "String")]
+    pub api_version: Version,
+    /// The hash of the block the deploy was executed on top of.
+    pub block_hash: BlockHash,
+    /// The would-be execution result: the cost, the transforms of global state and the transfers.
+    pub execution_result: ExecutionResult,
+}
+
+impl DocExample for SpeculativeExecResult {
+    fn doc_example() -> &'static Self {
+        &*SPECULATIVE_EXEC_RESULT
+    }
+}
+
+/// "account_speculative_exec" RPC
+///
+/// Executes a deploy on top of the global state of a block without committing its effects, so
+/// clients can show what the deploy would change before signing and sending it. An unsigned
+/// deploy is executed as if signed by its account.
+pub struct SpeculativeExec {}
+
+impl RpcWithParams for SpeculativeExec {
+    const METHOD: &'static str = "account_speculative_exec";
+    type RequestParams = SpeculativeExecParams;
+    type ResponseResult = SpeculativeExecResult;
+}
+
+impl RpcWithParamsExt for SpeculativeExec {
+    fn handle_request<REv: ReactorEventT>(
+        effect_builder: EffectBuilder<REv>,
+        response_builder: Builder,
+        params: Self::RequestParams,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        async move {
+            let block = match chain::get_block(params.block_identifier, effect_builder).await {
+                Ok(Some(block)) => block,
+                Ok(None) => {
+                    let error_msg = "no block has been added yet".to_string();
+                    info!("{}", error_msg);
+                    return Ok(response_builder.error(warp_json_rpc::Error::custom(
+                        ErrorCode::NoSuchBlock as i64,
+                        error_msg,
+                    ))?);
+                }
+                Err(error) => return Ok(response_builder.error(error)?),
+            };
+            let block_hash = *block.hash();
+            let deploy_hash = *params.deploy.id();
+
+            let exec_result = effect_builder
+                .make_request(
+                    |responder| RpcRequest::SpeculativeExec {
+                        block: Box::new(block),
+                        deploy: Box::new(params.deploy),
+                        responder,
+                    },
+                    QueueKind::Api,
+                )
+                .await;
+
+            match exec_result {
+                Ok(execution_result) => {
+                    let result = Self::ResponseResult {
+                        api_version: CLIENT_API_VERSION.clone(),
+                        block_hash,
+                        execution_result,
+                    };
+                    Ok(response_builder.success(result)?)
+                }
+                Err(error) => {
+                    let error_msg = format!(
+                        "failed to speculatively execute {} on top of {}: {:?}",
+                        deploy_hash, block_hash, error
+                    );
+                    info!("{}", error_msg);
+                    Ok(response_builder.error(warp_json_rpc::Error::custom(
+                        ErrorCode::SpeculativeExecFailed as i64,
+                        error_msg,
+                    ))?)
+                }
+            }
+        }
+        .boxed()
+    }
+}
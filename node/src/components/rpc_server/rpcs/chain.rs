@@ -17,23 +17,28 @@ use serde::{Deserialize, Serialize};
 use tracing::info;
 use warp_json_rpc::Builder;
 
-use casper_types::{Key, Transfer};
+use casper_types::{Key, PublicKey, Transfer};
 
 use super::{
     docs::DocExample, Error, ErrorCode, ReactorEventT, RpcRequest, RpcWithOptionalParams,
     RpcWithOptionalParamsExt,
 };
 use crate::{
-    components::CLIENT_API_VERSION,
+    components::{consensus::EraId, CLIENT_API_VERSION},
     crypto::hash::Digest,
     effect::EffectBuilder,
     reactor::QueueKind,
     rpcs::common::{self},
-    types::{Block, BlockHash, Item, JsonBlock},
+    types::{Block, BlockHash, Item, JsonBlock, Timestamp},
 };
 pub use era_summary::EraSummary;
 use era_summary::ERA_SUMMARY;
 
+/// The number of blocks returned by "chain_get_recent_blocks" if no count is given.
+const DEFAULT_RECENT_BLOCKS_COUNT: u64 = 10;
+/// The maximum number of blocks returned by a single "chain_get_recent_blocks" request.
+const MAX_RECENT_BLOCKS_COUNT: u64 = 100;
+
 static GET_BLOCK_PARAMS: Lazy<GetBlockParams> = Lazy::new(|| GetBlockParams {
     block_identifier: BlockIdentifier::Hash(Block::doc_example().id()),
 });
@@ -51,6 +56,15 @@ static GET_BLOCK_TRANSFERS_RESULT: Lazy<GetBlockTransfersResult> =
         block_hash: Some(Block::doc_example().id()),
         transfers: Some(vec![Transfer::default()]),
     });
+static GET_RECENT_BLOCKS_PARAMS: Lazy<GetRecentBlocksParams> =
+    Lazy::new(|| GetRecentBlocksParams {
+        count: DEFAULT_RECENT_BLOCKS_COUNT,
+    });
+static GET_RECENT_BLOCKS_RESULT: Lazy<GetRecentBlocksResult> =
+    Lazy::new(|| GetRecentBlocksResult {
+        api_version: CLIENT_API_VERSION.clone(),
+        blocks: vec![BlockSummary::from(Block::doc_example())],
+    });
 static GET_STATE_ROOT_HASH_PARAMS: Lazy<GetStateRootHashParams> =
     Lazy::new(|| GetStateRootHashParams {
         block_identifier: BlockIdentifier::Height(Block::doc_example().header().height()),
@@ -240,6 +254,129 @@ impl RpcWithOptionalParamsExt for GetBlockTransfers {
     }
 }
 
+/// Params for "chain_get_recent_blocks" RPC request.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetRecentBlocksParams {
+    /// The number of blocks to return, at most 100.
+    pub count: u64,
+}
+
+impl DocExample for GetRecentBlocksParams {
+    fn doc_example() -> &'static Self {
+        &*GET_RECENT_BLOCKS_PARAMS
+    }
+}
+
+/// A compact summary of a block, as returned by "chain_get_recent_blocks".
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BlockSummary {
+    /// The block hash.
+    pub hash: BlockHash,
+    /// The height of the block.
+    pub height: u64,
+    /// The era in which the block was created.
+    pub era_id: EraId,
+    /// The validator who proposed the block.
+    pub proposer: PublicKey,
+    /// The number of non-transfer deploys included in the block.
+    pub deploy_count: usize,
+    /// The number of transfers included in the block.
+    pub transfer_count: usize,
+    /// The block's timestamp.
+    pub timestamp: Timestamp,
+}
+
+impl From<&Block> for BlockSummary {
+    fn from(block: &Block) -> Self {
+        let header = block.header();
+        BlockSummary {
+            hash: *block.hash(),
+            height: header.height(),
+            era_id: header.era_id(),
+            proposer: *header.proposer(),
+            deploy_count: header.deploy_hashes().len(),
+            transfer_count: header.transfer_hashes().len(),
+            timestamp: header.timestamp(),
+        }
+    }
+}
+
+/// Result for "chain_get_recent_blocks" RPC response.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetRecentBlocksResult {
+    /// The RPC API version.
+    #[schemars(with = "String")]
+    pub api_version: Version,
+    /// Summaries of the most recent blocks, highest first.
+    pub blocks: Vec<BlockSummary>,
+}
+
+impl DocExample for GetRecentBlocksResult {
+    fn doc_example() -> &'static Self {
+        &*GET_RECENT_BLOCKS_RESULT
+    }
+}
+
+/// "chain_get_recent_blocks" RPC.
+pub struct GetRecentBlocks {}
+
+impl RpcWithOptionalParams for GetRecentBlocks {
+    const METHOD: &'static str = "chain_get_recent_blocks";
+    type OptionalRequestParams = GetRecentBlocksParams;
+    type ResponseResult = GetRecentBlocksResult;
+}
+
+impl RpcWithOptionalParamsExt for GetRecentBlocks {
+    fn handle_request<REv: ReactorEventT>(
+        effect_builder: EffectBuilder<REv>,
+        response_builder: Builder,
+        maybe_params: Option<Self::OptionalRequestParams>,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        async move {
+            let count = maybe_params.map_or(DEFAULT_RECENT_BLOCKS_COUNT, |params| params.count);
+            if count > MAX_RECENT_BLOCKS_COUNT {
+                let error_msg = format!(
+                    "requested {} blocks, but at most {} can be returned",
+                    count, MAX_RECENT_BLOCKS_COUNT
+                );
+                info!("{}", error_msg);
+                return Ok(response_builder.error(warp_json_rpc::Error::custom(
+                    ErrorCode::InvalidRecentBlocksCount as i64,
+                    error_msg,
+                ))?);
+            }
+
+            // Walk down the linear chain from the highest block.
+            let mut blocks = Vec::new();
+            let mut maybe_block_id = None;
+            while (blocks.len() as u64) < count {
+                let block = match get_block(maybe_block_id, effect_builder).await {
+                    Ok(Some(block)) => block,
+                    // There are no blocks in the linear chain yet.
+                    Ok(None) => break,
+                    // A block below the highest one is missing, the list would be incomplete.
+                    Err(error) => return Ok(response_builder.error(error)?),
+                };
+                blocks.push(BlockSummary::from(&block));
+                match block.height().checked_sub(1) {
+                    Some(height) => maybe_block_id = Some(BlockIdentifier::Height(height)),
+                    None => break,
+                }
+            }
+
+            let result = Self::ResponseResult {
+                api_version: CLIENT_API_VERSION.clone(),
+                blocks,
+            };
+            Ok(response_builder.success(result)?)
+        }
+        .boxed()
+    }
+}
+
 /// Params for "chain_get_state_root_hash" RPC request.
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
@@ -422,7 +559,7 @@ impl RpcWithOptionalParamsExt for GetEraInfoBySwitchBlock {
     }
 }
 
-async fn get_block<REv: ReactorEventT>(
+pub(super) async fn get_block<REv: ReactorEventT>(
     maybe_id: Option<BlockIdentifier>,
     effect_builder: EffectBuilder<REv>,
 ) -> Result<Option<Block>, warp_json_rpc::Error> {
@@ -18,10 +18,13 @@ use serde_json::{json, Value};
 use warp_json_rpc::Builder;
 
 use super::{
-    account::PutDeploy,
-    chain::{GetBlock, GetBlockTransfers, GetStateRootHash},
-    info::{GetDeploy, GetPeers, GetStatus},
-    state::{GetAuctionInfo, GetBalance, GetItem},
+    account::{PutDeploy, SpeculativeExec},
+    chain::{GetBlock, GetBlockTransfers, GetRecentBlocks, GetStateRootHash},
+    info::{
+        GetDeploy, GetEraMetrics, GetImportedBans, GetPeerRequestAudit, GetPeerVersions, GetPeers,
+        GetStatus, GetValidatorAttestations, SamplePeers,
+    },
+    state::{GetAccountBalanceAtHeight, GetAuctionInfo, GetBalance, GetItem},
     Error, ReactorEventT, RpcWithOptionalParams, RpcWithParams, RpcWithoutParams,
     RpcWithoutParamsExt,
 };
@@ -66,18 +69,45 @@ static OPEN_RPC_SCHEMA: Lazy<OpenRpcSchema> = Lazy::new(|| {
     };
 
     schema.push_with_params::<PutDeploy>("receives a Deploy to be executed by the network");
+    schema.push_with_params::<SpeculativeExec>(
+        "returns the effects a Deploy would have if executed now, without committing them",
+    );
     schema.push_with_params::<GetDeploy>("returns a Deploy from the network");
     schema.push_without_params::<GetPeers>("returns a list of peers connected to the node");
     schema.push_without_params::<GetStatus>("returns the current status of the node");
+    schema.push_with_optional_params::<GetPeerVersions>(
+        "returns the protocol versions of connected peers and their readiness for an upgrade",
+    );
+    schema.push_without_params::<GetImportedBans>(
+        "returns the peer bans imported from trusted signed ban lists",
+    );
+    schema.push_without_params::<GetValidatorAttestations>(
+        "returns the software attestations signed by validators, grouped by attested software",
+    );
+    schema.push_without_params::<GetPeerRequestAudit>(
+        "returns the data requests served to each peer, with the quotas applied to them",
+    );
+    schema.push_with_optional_params::<SamplePeers>(
+        "returns a bounded, uniformly random sample of the peers connected to the node",
+    );
+    schema.push_with_params::<GetEraMetrics>(
+        "returns the metrics snapshot persisted by this node at the end of an era",
+    );
     schema.push_with_optional_params::<GetBlock>("returns a Block from the network");
     schema.push_with_optional_params::<GetBlockTransfers>(
         "returns all transfers for a Block from the network",
     );
+    schema.push_with_optional_params::<GetRecentBlocks>(
+        "returns summaries of the most recent Blocks, highest first",
+    );
     schema.push_with_optional_params::<GetStateRootHash>(
         "returns a state root hash at a given Block",
     );
     schema.push_with_params::<GetItem>("returns a stored value from the network");
     schema.push_with_params::<GetBalance>("returns a purse's balance from the network");
+    schema.push_with_params::<GetAccountBalanceAtHeight>(
+        "returns an account's main purse balance and stakes as of a retained historical Block",
+    );
     schema.push_with_optional_params::<GetEraInfoBySwitchBlock>(
         "returns an EraInfo from the network",
     );
@@ -3,7 +3,11 @@
 // TODO - remove once schemars stops causing warning.
 #![allow(clippy::field_reassign_with_default)]
 
-use std::str;
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr},
+    str,
+};
 
 use futures::{future::BoxFuture, FutureExt};
 use http::Response;
@@ -15,17 +19,28 @@ use serde::{Deserialize, Serialize};
 use tracing::info;
 use warp_json_rpc::Builder;
 
-use casper_types::ExecutionResult;
+use casper_types::{ExecutionResult, PublicKey, SecretKey, U512};
 
 use super::{
-    docs::DocExample, Error, ErrorCode, ReactorEventT, RpcRequest, RpcWithParams, RpcWithParamsExt,
-    RpcWithoutParams, RpcWithoutParamsExt,
+    docs::DocExample, Error, ErrorCode, ReactorEventT, RpcRequest, RpcWithOptionalParams,
+    RpcWithOptionalParamsExt, RpcWithParams, RpcWithParamsExt, RpcWithoutParams,
+    RpcWithoutParamsExt,
 };
 use crate::{
-    components::CLIENT_API_VERSION,
+    components::{
+        consensus::EraId,
+        era_metrics::{EraMetricsSnapshot, FinalityLatencies},
+        CLIENT_API_VERSION,
+    },
+    crypto::AsymmetricKeyExt,
     effect::EffectBuilder,
     reactor::QueueKind,
-    types::{Block, BlockHash, Deploy, DeployHash, GetStatusResult, Item, PeersMap},
+    types::{
+        BanEntry, BanTarget, Block, BlockHash, Deploy, DeployHash, GetStatusResult, ImportedBan,
+        Item, NodeId, PeerDirection, PeerRequestAudit, PeersMap, ProtocolVersionHistogram,
+        RequestClass, RequestClassAudit, SampledPeer, SignedAttestation, SoftwareAttestation,
+        Timestamp,
+    },
 };
 
 static GET_DEPLOY_PARAMS: Lazy<GetDeployParams> = Lazy::new(|| GetDeployParams {
@@ -43,6 +58,86 @@ static GET_PEERS_RESULT: Lazy<GetPeersResult> = Lazy::new(|| GetPeersResult {
     api_version: CLIENT_API_VERSION.clone(),
     peers: GetStatusResult::doc_example().peers.clone(),
 });
+static GET_PEER_VERSIONS_PARAMS: Lazy<GetPeerVersionsParams> =
+    Lazy::new(|| GetPeerVersionsParams {
+        target_version: Version::new(1, 1, 0),
+    });
+static GET_PEER_VERSIONS_RESULT: Lazy<GetPeerVersionsResult> = Lazy::new(|| {
+    let our_version = Version::new(1, 1, 0);
+    let old_version = Version::new(1, 0, 0);
+    let peer_versions =
+        ProtocolVersionHistogram::new(vec![Some(&our_version), Some(&old_version), None]);
+    GetPeerVersionsResult::new(our_version.clone(), peer_versions, our_version)
+});
+static GET_IMPORTED_BANS_RESULT: Lazy<GetImportedBansResult> =
+    Lazy::new(|| GetImportedBansResult {
+        api_version: CLIENT_API_VERSION.clone(),
+        bans: vec![ImportedBan {
+            issuer: PublicKey::from(SecretKey::doc_example()),
+            entry: BanEntry {
+                target: BanTarget::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+                reason: "connection flooding".to_string(),
+                expires: Some(*Timestamp::doc_example()),
+            },
+            enforced: true,
+        }],
+    });
+static GET_VALIDATOR_ATTESTATIONS_RESULT: Lazy<GetValidatorAttestationsResult> = Lazy::new(|| {
+    GetValidatorAttestationsResult::new(vec![SignedAttestation::doc_example().clone()])
+});
+static GET_PEER_REQUEST_AUDIT_RESULT: Lazy<GetPeerRequestAuditResult> =
+    Lazy::new(|| GetPeerRequestAuditResult {
+        api_version: CLIENT_API_VERSION.clone(),
+        peers: vec![PeerRequestAudit {
+            node_id: NodeId::doc_example().to_string(),
+            classes: vec![
+                RequestClassAudit {
+                    class: RequestClass::Deploy,
+                    in_window: 12,
+                    quota: None,
+                    served: 1_204,
+                    throttled: 0,
+                },
+                RequestClassAudit {
+                    class: RequestClass::Block,
+                    in_window: 600,
+                    quota: Some(600),
+                    served: 48_377,
+                    throttled: 9_520,
+                },
+            ],
+        }],
+    });
+static SAMPLE_PEERS_PARAMS: Lazy<SamplePeersParams> = Lazy::new(|| SamplePeersParams { count: 1 });
+static SAMPLE_PEERS_RESULT: Lazy<SamplePeersResult> = Lazy::new(|| SamplePeersResult {
+    api_version: CLIENT_API_VERSION.clone(),
+    total_peers: 24,
+    peers: vec![SampledPeer {
+        node_id: NodeId::doc_example().to_string(),
+        address: Some("34.203.11.2:35000".to_string()),
+        direction: PeerDirection::Both,
+        connected_secs: 8_412,
+    }],
+});
+static GET_ERA_METRICS_PARAMS: Lazy<GetEraMetricsParams> =
+    Lazy::new(|| GetEraMetricsParams { era_id: EraId(3) });
+static GET_ERA_METRICS_RESULT: Lazy<GetEraMetricsResult> = Lazy::new(|| GetEraMetricsResult {
+    api_version: CLIENT_API_VERSION.clone(),
+    era_metrics: EraMetricsSnapshot {
+        era_id: EraId(3),
+        partial: false,
+        block_count: 120,
+        deploy_count: 854,
+        gas_used: U512::from(2_456_000_000_u64),
+        finality_latencies: Some(FinalityLatencies {
+            count: 120,
+            min_ms: 12_753,
+            mean_ms: 16_384,
+            max_ms: 32_768,
+        }),
+        peer_count: 24,
+    },
+});
 
 /// Params for "info_get_deploy" RPC request.
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
@@ -194,6 +289,450 @@ impl RpcWithoutParamsExt for GetPeers {
     }
 }
 
+/// Params for "info_get_peer_versions" RPC request.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetPeerVersionsParams {
+    /// The protocol version to report upgrade readiness for.
+    #[schemars(with = "String")]
+    pub target_version: Version,
+}
+
+impl DocExample for GetPeerVersionsParams {
+    fn doc_example() -> &'static Self {
+        &*GET_PEER_VERSIONS_PARAMS
+    }
+}
+
+/// How many of the connected peers run a protocol version at or above a target version.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpgradeReadiness {
+    /// The protocol version readiness is reported for.
+    #[schemars(with = "String")]
+    pub target_version: Version,
+    /// The number of peers advertising the target version or a later one.
+    pub ready_peers: u64,
+    /// The total number of peers which completed the handshake.
+    pub total_peers: u64,
+    /// The percentage of peers advertising the target version or a later one, if there are peers.
+    pub ready_percent: Option<u64>,
+}
+
+/// Result for "info_get_peer_versions" RPC response.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetPeerVersionsResult {
+    /// The RPC API version.
+    #[schemars(with = "String")]
+    pub api_version: Version,
+    /// The latest protocol version supported by this node's chainspec.
+    #[schemars(with = "String")]
+    pub our_version: Version,
+    /// The number of connected peers per protocol version advertised in their handshake.
+    pub peer_versions: ProtocolVersionHistogram,
+    /// The upgrade readiness of the connected peers.
+    pub upgrade_readiness: UpgradeReadiness,
+}
+
+impl GetPeerVersionsResult {
+    fn new(
+        our_version: Version,
+        peer_versions: ProtocolVersionHistogram,
+        target_version: Version,
+    ) -> Self {
+        let upgrade_readiness = UpgradeReadiness {
+            ready_peers: peer_versions.count_at_least(&target_version),
+            total_peers: peer_versions.total(),
+            ready_percent: peer_versions.percent_at_least(&target_version),
+            target_version,
+        };
+        GetPeerVersionsResult {
+            api_version: CLIENT_API_VERSION.clone(),
+            our_version,
+            peer_versions,
+            upgrade_readiness,
+        }
+    }
+}
+
+impl DocExample for GetPeerVersionsResult {
+    fn doc_example() -> &'static Self {
+        &*GET_PEER_VERSIONS_RESULT
+    }
+}
+
+/// "info_get_peer_versions" RPC.
+pub struct GetPeerVersions {}
+
+impl RpcWithOptionalParams for GetPeerVersions {
+    const METHOD: &'static str = "info_get_peer_versions";
+    type OptionalRequestParams = GetPeerVersionsParams;
+    type ResponseResult = GetPeerVersionsResult;
+}
+
+impl RpcWithOptionalParamsExt for GetPeerVersions {
+    fn handle_request<REv: ReactorEventT>(
+        effect_builder: EffectBuilder<REv>,
+        response_builder: Builder,
+        maybe_params: Option<Self::OptionalRequestParams>,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        async move {
+            let (our_version, peer_versions) = effect_builder
+                .make_request(
+                    |responder| RpcRequest::GetPeerProtocolVersions { responder },
+                    QueueKind::Api,
+                )
+                .await;
+
+            // Without an explicit target, report how many peers have caught up with us.
+            let target_version = maybe_params
+                .map(|params| params.target_version)
+                .unwrap_or_else(|| our_version.clone());
+            let result = Self::ResponseResult::new(our_version, peer_versions, target_version);
+            Ok(response_builder.success(result)?)
+        }
+        .boxed()
+    }
+}
+
+/// Result for "info_get_imported_bans" RPC response.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetImportedBansResult {
+    /// The RPC API version.
+    #[schemars(with = "String")]
+    pub api_version: Version,
+    /// The bans from trusted signed ban lists which are currently in effect.
+    pub bans: Vec<ImportedBan>,
+}
+
+impl DocExample for GetImportedBansResult {
+    fn doc_example() -> &'static Self {
+        &*GET_IMPORTED_BANS_RESULT
+    }
+}
+
+/// "info_get_imported_bans" RPC.
+pub struct GetImportedBans {}
+
+impl RpcWithoutParams for GetImportedBans {
+    const METHOD: &'static str = "info_get_imported_bans";
+    type ResponseResult = GetImportedBansResult;
+}
+
+impl RpcWithoutParamsExt for GetImportedBans {
+    fn handle_request<REv: ReactorEventT>(
+        effect_builder: EffectBuilder<REv>,
+        response_builder: Builder,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        async move {
+            let bans = effect_builder
+                .make_request(
+                    |responder| RpcRequest::GetImportedBans { responder },
+                    QueueKind::Api,
+                )
+                .await;
+
+            let result = Self::ResponseResult {
+                api_version: CLIENT_API_VERSION.clone(),
+                bans,
+            };
+            Ok(response_builder.success(result)?)
+        }
+        .boxed()
+    }
+}
+
+/// The validators attesting to running the same software.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AttestedSoftware {
+    /// The attested software.
+    pub software: SoftwareAttestation,
+    /// The validators attesting to running it.
+    pub validators: Vec<PublicKey>,
+}
+
+/// Result for "info_get_validator_attestations" RPC response.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetValidatorAttestationsResult {
+    /// The RPC API version.
+    #[schemars(with = "String")]
+    pub api_version: Version,
+    /// The latest signed attestation of every validator of the current era known to the node.
+    pub attestations: Vec<SignedAttestation>,
+    /// The attested software, with the validators running it.
+    ///
+    /// The stake behind each can be looked up in the bids returned by "state_get_auction_info".
+    pub software: Vec<AttestedSoftware>,
+}
+
+impl GetValidatorAttestationsResult {
+    pub(crate) fn new(attestations: Vec<SignedAttestation>) -> Self {
+        let mut validators_by_software: BTreeMap<SoftwareAttestation, Vec<PublicKey>> =
+            BTreeMap::new();
+        for attestation in &attestations {
+            validators_by_software
+                .entry(attestation.attestation().clone())
+                .or_default()
+                .push(*attestation.validator());
+        }
+        let software = validators_by_software
+            .into_iter()
+            .map(|(software, validators)| AttestedSoftware {
+                software,
+                validators,
+            })
+            .collect();
+        GetValidatorAttestationsResult {
+            api_version: CLIENT_API_VERSION.clone(),
+            attestations,
+            software,
+        }
+    }
+}
+
+impl DocExample for GetValidatorAttestationsResult {
+    fn doc_example() -> &'static Self {
+        &*GET_VALIDATOR_ATTESTATIONS_RESULT
+    }
+}
+
+/// "info_get_validator_attestations" RPC.
+pub struct GetValidatorAttestations {}
+
+impl RpcWithoutParams for GetValidatorAttestations {
+    const METHOD: &'static str = "info_get_validator_attestations";
+    type ResponseResult = GetValidatorAttestationsResult;
+}
+
+impl RpcWithoutParamsExt for GetValidatorAttestations {
+    fn handle_request<REv: ReactorEventT>(
+        effect_builder: EffectBuilder<REv>,
+        response_builder: Builder,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        async move {
+            let attestations = effect_builder
+                .make_request(
+                    |responder| RpcRequest::GetValidatorAttestations { responder },
+                    QueueKind::Api,
+                )
+                .await;
+
+            let result = Self::ResponseResult::new(attestations);
+            Ok(response_builder.success(result)?)
+        }
+        .boxed()
+    }
+}
+
+/// Result for "info_get_peer_request_audit" RPC response.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetPeerRequestAuditResult {
+    /// The RPC API version.
+    #[schemars(with = "String")]
+    pub api_version: Version,
+    /// The data requests sent by peers, busiest peers first.
+    pub peers: Vec<PeerRequestAudit>,
+}
+
+impl DocExample for GetPeerRequestAuditResult {
+    fn doc_example() -> &'static Self {
+        &*GET_PEER_REQUEST_AUDIT_RESULT
+    }
+}
+
+/// "info_get_peer_request_audit" RPC.
+pub struct GetPeerRequestAudit {}
+
+impl RpcWithoutParams for GetPeerRequestAudit {
+    const METHOD: &'static str = "info_get_peer_request_audit";
+    type ResponseResult = GetPeerRequestAuditResult;
+}
+
+impl RpcWithoutParamsExt for GetPeerRequestAudit {
+    fn handle_request<REv: ReactorEventT>(
+        effect_builder: EffectBuilder<REv>,
+        response_builder: Builder,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        async move {
+            let peers = effect_builder
+                .make_request(
+                    |responder| RpcRequest::GetPeerRequestAudit { responder },
+                    QueueKind::Api,
+                )
+                .await;
+
+            let result = Self::ResponseResult {
+                api_version: CLIENT_API_VERSION.clone(),
+                peers,
+            };
+            Ok(response_builder.success(result)?)
+        }
+        .boxed()
+    }
+}
+
+/// The number of peers sampled by "info_sample_peers" if no count is given.
+const DEFAULT_PEER_SAMPLE_SIZE: u32 = 16;
+
+/// The maximum number of peers sampled by "info_sample_peers".
+const MAX_PEER_SAMPLE_SIZE: u32 = 64;
+
+/// Params for "info_sample_peers" RPC request.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SamplePeersParams {
+    /// The number of peers to sample, capped at 64.
+    pub count: u32,
+}
+
+impl DocExample for SamplePeersParams {
+    fn doc_example() -> &'static Self {
+        &*SAMPLE_PEERS_PARAMS
+    }
+}
+
+/// Result for "info_sample_peers" RPC response.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SamplePeersResult {
+    /// The RPC API version.
+    #[schemars(with = "String")]
+    pub api_version: Version,
+    /// The number of connected peers the sample was drawn from.
+    pub total_peers: u64,
+    /// The sampled peers, with the listening address only given if it is publicly routable.
+    pub peers: Vec<SampledPeer>,
+}
+
+impl DocExample for SamplePeersResult {
+    fn doc_example() -> &'static Self {
+        &*SAMPLE_PEERS_RESULT
+    }
+}
+
+/// "info_sample_peers" RPC.
+pub struct SamplePeers {}
+
+impl RpcWithOptionalParams for SamplePeers {
+    const METHOD: &'static str = "info_sample_peers";
+    type OptionalRequestParams = SamplePeersParams;
+    type ResponseResult = SamplePeersResult;
+}
+
+impl RpcWithOptionalParamsExt for SamplePeers {
+    fn handle_request<REv: ReactorEventT>(
+        effect_builder: EffectBuilder<REv>,
+        response_builder: Builder,
+        maybe_params: Option<Self::OptionalRequestParams>,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        async move {
+            let count = maybe_params
+                .map_or(DEFAULT_PEER_SAMPLE_SIZE, |params| params.count)
+                .min(MAX_PEER_SAMPLE_SIZE);
+            let sample = effect_builder
+                .make_request(
+                    |responder| RpcRequest::SamplePeers {
+                        count: count as usize,
+                        responder,
+                    },
+                    QueueKind::Api,
+                )
+                .await;
+
+            let result = Self::ResponseResult {
+                api_version: CLIENT_API_VERSION.clone(),
+                total_peers: sample.total_peers,
+                peers: sample.peers,
+            };
+            Ok(response_builder.success(result)?)
+        }
+        .boxed()
+    }
+}
+
+/// Params for "info_get_era_metrics" RPC request.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetEraMetricsParams {
+    /// The era to return the metrics of.
+    pub era_id: EraId,
+}
+
+impl DocExample for GetEraMetricsParams {
+    fn doc_example() -> &'static Self {
+        &*GET_ERA_METRICS_PARAMS
+    }
+}
+
+/// Result for "info_get_era_metrics" RPC response.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetEraMetricsResult {
+    /// The RPC API version.
+    #[schemars(with = "String")]
+    pub api_version: Version,
+    /// The metrics of the era, as observed by this node.
+    pub era_metrics: EraMetricsSnapshot,
+}
+
+impl DocExample for GetEraMetricsResult {
+    fn doc_example() -> &'static Self {
+        &*GET_ERA_METRICS_RESULT
+    }
+}
+
+/// "info_get_era_metrics" RPC.
+pub struct GetEraMetrics {}
+
+impl RpcWithParams for GetEraMetrics {
+    const METHOD: &'static str = "info_get_era_metrics";
+    type RequestParams = GetEraMetricsParams;
+    type ResponseResult = GetEraMetricsResult;
+}
+
+impl RpcWithParamsExt for GetEraMetrics {
+    fn handle_request<REv: ReactorEventT>(
+        effect_builder: EffectBuilder<REv>,
+        response_builder: Builder,
+        params: Self::RequestParams,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        async move {
+            let maybe_era_metrics = effect_builder
+                .make_request(
+                    |responder| RpcRequest::GetEraMetrics {
+                        era_id: params.era_id,
+                        responder,
+                    },
+                    QueueKind::Api,
+                )
+                .await;
+
+            let era_metrics = match maybe_era_metrics {
+                Some(era_metrics) => era_metrics,
+                None => {
+                    info!("no metrics persisted for {}", params.era_id);
+                    return Ok(response_builder.error(warp_json_rpc::Error::custom(
+                        ErrorCode::NoSuchEraMetrics as i64,
+                        "era metrics not known",
+                    ))?);
+                }
+            };
+
+            let result = Self::ResponseResult {
+                api_version: CLIENT_API_VERSION.clone(),
+                era_metrics,
+            };
+            Ok(response_builder.success(result)?)
+        }
+        .boxed()
+    }
+}
+
 /// "info_get_status" RPC.
 pub struct GetStatus {}
 
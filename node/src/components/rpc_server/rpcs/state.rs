@@ -19,7 +19,10 @@ use casper_execution_engine::{
     core::engine_state::{BalanceResult, QueryResult},
     storage::protocol_data::ProtocolData,
 };
-use casper_types::{bytesrepr::ToBytes, CLValue, Key, ProtocolVersion, URef, U512};
+use casper_types::{
+    auction::Bids, bytesrepr::ToBytes, AccessRights, CLValue, Key, ProtocolVersion, PublicKey,
+    SecretKey, URef, U512,
+};
 
 use super::{
     docs::DocExample, Error, ErrorCode, ReactorEventT, RpcRequest, RpcWithParams, RpcWithParamsExt,
@@ -30,12 +33,13 @@ use crate::{
     effect::EffectBuilder,
     reactor::QueueKind,
     rpcs::{
+        chain::BlockIdentifier,
         common::{self, MERKLE_PROOF},
         RpcWithoutParams, RpcWithoutParamsExt,
     },
     types::{
         json_compatibility::{AuctionState, StoredValue},
-        Block,
+        Block, BlockHash,
     },
 };
 
@@ -59,6 +63,25 @@ static GET_BALANCE_RESULT: Lazy<GetBalanceResult> = Lazy::new(|| GetBalanceResul
     balance_value: U512::from(123_456),
     merkle_proof: MERKLE_PROOF.clone(),
 });
+static GET_ACCOUNT_BALANCE_AT_HEIGHT_PARAMS: Lazy<GetAccountBalanceAtHeightParams> =
+    Lazy::new(|| GetAccountBalanceAtHeightParams {
+        public_key: SecretKey::ed25519([42; SecretKey::ED25519_LENGTH]).into(),
+        block_height: Block::doc_example().header().height(),
+    });
+static GET_ACCOUNT_BALANCE_AT_HEIGHT_RESULT: Lazy<GetAccountBalanceAtHeightResult> =
+    Lazy::new(|| GetAccountBalanceAtHeightResult {
+        api_version: CLIENT_API_VERSION.clone(),
+        block_hash: *Block::doc_example().hash(),
+        state_root_hash: *Block::doc_example().header().state_root_hash(),
+        main_purse: URef::new([9; 32], AccessRights::READ_ADD_WRITE).to_formatted_string(),
+        balance_value: U512::from(123_456),
+        merkle_proof: MERKLE_PROOF.clone(),
+        staking_positions: vec![StakingPosition {
+            validator_public_key: SecretKey::ed25519([42; SecretKey::ED25519_LENGTH]).into(),
+            staked_amount: U512::from(10),
+            is_delegation: false,
+        }],
+    });
 static GET_AUCTION_INFO_RESULT: Lazy<GetAuctionInfoResult> = Lazy::new(|| GetAuctionInfoResult {
     api_version: CLIENT_API_VERSION.clone(),
     auction_state: AuctionState::doc_example().clone(),
@@ -232,56 +255,208 @@ impl RpcWithParamsExt for GetBalance {
             };
 
             // Get the balance.
-            let balance_result = effect_builder
+            let (balance_value, merkle_proof) =
+                match get_balance(effect_builder, params.state_root_hash, purse_uref).await {
+                    Ok(balance) => balance,
+                    Err(error) => return Ok(response_builder.error(error)?),
+                };
+
+            // Return the result.
+            let result = Self::ResponseResult {
+                api_version: CLIENT_API_VERSION.clone(),
+                balance_value,
+                merkle_proof,
+            };
+            Ok(response_builder.success(result)?)
+        }
+        .boxed()
+    }
+}
+
+/// Params for "state_get_account_balance_at_height" RPC request.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetAccountBalanceAtHeightParams {
+    /// The public key of the account.
+    pub public_key: PublicKey,
+    /// The height of the block at which to query the balance.
+    pub block_height: u64,
+}
+
+impl DocExample for GetAccountBalanceAtHeightParams {
+    fn doc_example() -> &'static Self {
+        &*GET_ACCOUNT_BALANCE_AT_HEIGHT_PARAMS
+    }
+}
+
+/// A stake held by an account at a validator.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StakingPosition {
+    /// The public key of the validator the stake is held at.
+    pub validator_public_key: PublicKey,
+    /// The staked amount.
+    pub staked_amount: U512,
+    /// Whether the stake is delegated, as opposed to being the validator's own bid.
+    pub is_delegation: bool,
+}
+
+/// Result for "state_get_account_balance_at_height" RPC response.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetAccountBalanceAtHeightResult {
+    /// The RPC API version.
+    #[schemars(with = "String")]
+    pub api_version: Version,
+    /// The hash of the block at the requested height.
+    pub block_hash: BlockHash,
+    /// The state root hash of the block at the requested height.
+    pub state_root_hash: Digest,
+    /// The account's main purse as formatted URef.
+    pub main_purse: String,
+    /// The balance of the main purse.
+    pub balance_value: U512,
+    /// The merkle proof of the balance.
+    pub merkle_proof: String,
+    /// The stakes held by the account, both as a validator and as a delegator.
+    pub staking_positions: Vec<StakingPosition>,
+}
+
+impl DocExample for GetAccountBalanceAtHeightResult {
+    fn doc_example() -> &'static Self {
+        &*GET_ACCOUNT_BALANCE_AT_HEIGHT_RESULT
+    }
+}
+
+/// "state_get_account_balance_at_height" RPC.
+pub struct GetAccountBalanceAtHeight {}
+
+impl RpcWithParams for GetAccountBalanceAtHeight {
+    const METHOD: &'static str = "state_get_account_balance_at_height";
+    type RequestParams = GetAccountBalanceAtHeightParams;
+    type ResponseResult = GetAccountBalanceAtHeightResult;
+}
+
+impl RpcWithParamsExt for GetAccountBalanceAtHeight {
+    fn handle_request<REv: ReactorEventT>(
+        effect_builder: EffectBuilder<REv>,
+        response_builder: Builder,
+        params: Self::RequestParams,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        async move {
+            let block_height = params.block_height;
+            let maybe_block = effect_builder
                 .make_request(
-                    |responder| RpcRequest::GetBalance {
-                        state_root_hash: params.state_root_hash,
-                        purse_uref,
+                    |responder| RpcRequest::GetBlock {
+                        maybe_id: Some(BlockIdentifier::Height(block_height)),
                         responder,
                     },
                     QueueKind::Api,
                 )
                 .await;
 
-            let (balance_value, purse_proof, balance_proof) = match balance_result {
-                Ok(BalanceResult::Success {
-                    motes,
-                    purse_proof,
-                    balance_proof,
-                }) => (motes, purse_proof, balance_proof),
-                Ok(balance_result) => {
-                    let error_msg = format!("get-balance failed: {:?}", balance_result);
+            let block = match maybe_block {
+                Some(block) => block,
+                None => {
+                    // Tell apart heights the chain has not reached yet from ones this node no
+                    // longer holds.
+                    let maybe_highest_block = effect_builder
+                        .make_request(
+                            |responder| RpcRequest::GetBlock {
+                                maybe_id: None,
+                                responder,
+                            },
+                            QueueKind::Api,
+                        )
+                        .await;
+                    let (error_code, error_msg) = match maybe_highest_block {
+                        Some(highest_block) if highest_block.header().height() > block_height => (
+                            ErrorCode::BlockNotRetained,
+                            format!(
+                                "block at height {} is no longer retained by this node, lowest \
+                                 heights may have been pruned",
+                                block_height
+                            ),
+                        ),
+                        _ => (
+                            ErrorCode::NoSuchBlock,
+                            format!("no block at height {} known yet", block_height),
+                        ),
+                    };
+                    info!("{}", error_msg);
+                    return Ok(response_builder
+                        .error(warp_json_rpc::Error::custom(error_code as i64, error_msg))?);
+                }
+            };
+            let block_hash = *block.hash();
+            let state_root_hash = *block.header().state_root_hash();
+
+            // Look up the account's main purse in the block's global state.
+            let account_key = Key::Account(params.public_key.to_account_hash());
+            let query_result = effect_builder
+                .make_request(
+                    |responder| RpcRequest::QueryGlobalState {
+                        state_root_hash,
+                        base_key: account_key,
+                        path: vec![],
+                        responder,
+                    },
+                    QueueKind::Api,
+                )
+                .await;
+
+            let main_purse = match query_result {
+                Ok(QueryResult::Success { value, .. }) => match value.as_account() {
+                    Some(account) => account.main_purse(),
+                    None => {
+                        let error_msg = format!("{} is not an account", account_key);
+                        info!("{}", error_msg);
+                        return Ok(response_builder.error(warp_json_rpc::Error::custom(
+                            ErrorCode::QueryFailed as i64,
+                            error_msg,
+                        ))?);
+                    }
+                },
+                Ok(query_result) => {
+                    let error_msg = format!(
+                        "no account {} at height {}: {:?}",
+                        account_key, block_height, query_result
+                    );
                     info!("{}", error_msg);
                     return Ok(response_builder.error(warp_json_rpc::Error::custom(
-                        ErrorCode::GetBalanceFailed as i64,
+                        ErrorCode::NoSuchAccount as i64,
                         error_msg,
                     ))?);
                 }
                 Err(error) => {
-                    let error_msg = format!("get-balance failed to execute: {}", error);
+                    let error_msg = format!("state query failed to execute: {:?}", error);
                     info!("{}", error_msg);
                     return Ok(response_builder.error(warp_json_rpc::Error::custom(
-                        ErrorCode::GetBalanceFailedToExecute as i64,
+                        ErrorCode::QueryFailedToExecute as i64,
                         error_msg,
                     ))?);
                 }
             };
 
-            let proof_bytes = match (*purse_proof, *balance_proof).to_bytes() {
-                Ok(proof_bytes) => proof_bytes,
-                Err(error) => {
-                    info!("failed to encode stored value: {}", error);
-                    return Ok(response_builder.error(warp_json_rpc::Error::INTERNAL_ERROR)?);
-                }
-            };
+            let (balance_value, merkle_proof) =
+                match get_balance(effect_builder, state_root_hash, main_purse).await {
+                    Ok(balance) => balance,
+                    Err(error) => return Ok(response_builder.error(error)?),
+                };
 
-            let merkle_proof = hex::encode(proof_bytes);
+            let staking_positions = match get_bids(effect_builder, state_root_hash).await {
+                Ok(bids) => staking_positions(&params.public_key, &bids),
+                Err(error) => return Ok(response_builder.error(error)?),
+            };
 
-            // Return the result.
             let result = Self::ResponseResult {
                 api_version: CLIENT_API_VERSION.clone(),
+                block_hash,
+                state_root_hash,
+                main_purse: main_purse.to_formatted_string(),
                 balance_value,
                 merkle_proof,
+                staking_positions,
             };
             Ok(response_builder.success(result)?)
         }
@@ -346,52 +521,12 @@ impl RpcWithoutParamsExt for GetAuctionInfo {
             };
 
             let protocol_version = ProtocolVersion::V1_0_0;
-            let protocol_version_result = effect_builder
-                .make_request(
-                    |responder| RpcRequest::QueryProtocolData {
-                        protocol_version,
-                        responder,
-                    },
-                    QueueKind::Api,
-                )
-                .await;
-
-            let protocol_data = {
-                if let Ok(Some(protocol_data)) = protocol_version_result {
-                    protocol_data
-                } else {
-                    Box::new(ProtocolData::default())
-                }
-            };
-
-            // auction contract key
-            let base_key = protocol_data.auction().into();
-            // bids named key in auction contract
-            let path = vec![casper_types::auction::BIDS_KEY.to_string()];
             // the global state hash of the last block
             let state_root_hash = *block.header().state_root_hash();
             // the block height of the last added block
             let block_height = block.header().height();
 
-            let query_result = effect_builder
-                .make_request(
-                    |responder| RpcRequest::QueryGlobalState {
-                        state_root_hash,
-                        base_key,
-                        path,
-                        responder,
-                    },
-                    QueueKind::Api,
-                )
-                .await;
-
-            let bids = if let Ok(QueryResult::Success { value, .. }) = query_result {
-                value
-                    .as_cl_value()
-                    .and_then(|cl_value| cl_value.to_owned().into_t().ok())
-            } else {
-                None
-            };
+            let bids = get_bids(effect_builder, state_root_hash).await.ok();
 
             let era_validators_result = effect_builder
                 .make_request(
@@ -414,3 +549,146 @@ impl RpcWithoutParamsExt for GetAuctionInfo {
         .boxed()
     }
 }
+
+/// Queries the balance of a purse, returning it along with the hex-encoded merkle proof.
+async fn get_balance<REv: ReactorEventT>(
+    effect_builder: EffectBuilder<REv>,
+    state_root_hash: Digest,
+    purse_uref: URef,
+) -> Result<(U512, String), warp_json_rpc::Error> {
+    let balance_result = effect_builder
+        .make_request(
+            |responder| RpcRequest::GetBalance {
+                state_root_hash,
+                purse_uref,
+                responder,
+            },
+            QueueKind::Api,
+        )
+        .await;
+
+    let (balance_value, purse_proof, balance_proof) = match balance_result {
+        Ok(BalanceResult::Success {
+            motes,
+            purse_proof,
+            balance_proof,
+        }) => (motes, purse_proof, balance_proof),
+        Ok(balance_result) => {
+            let error_msg = format!("get-balance failed: {:?}", balance_result);
+            info!("{}", error_msg);
+            return Err(warp_json_rpc::Error::custom(
+                ErrorCode::GetBalanceFailed as i64,
+                error_msg,
+            ));
+        }
+        Err(error) => {
+            let error_msg = format!("get-balance failed to execute: {}", error);
+            info!("{}", error_msg);
+            return Err(warp_json_rpc::Error::custom(
+                ErrorCode::GetBalanceFailedToExecute as i64,
+                error_msg,
+            ));
+        }
+    };
+
+    let proof_bytes = match (*purse_proof, *balance_proof).to_bytes() {
+        Ok(proof_bytes) => proof_bytes,
+        Err(error) => {
+            info!("failed to encode stored value: {}", error);
+            return Err(warp_json_rpc::Error::INTERNAL_ERROR);
+        }
+    };
+
+    Ok((balance_value, hex::encode(proof_bytes)))
+}
+
+/// Reads the bids from the auction contract at the given state root hash.
+async fn get_bids<REv: ReactorEventT>(
+    effect_builder: EffectBuilder<REv>,
+    state_root_hash: Digest,
+) -> Result<Bids, warp_json_rpc::Error> {
+    let protocol_version = ProtocolVersion::V1_0_0;
+    let protocol_version_result = effect_builder
+        .make_request(
+            |responder| RpcRequest::QueryProtocolData {
+                protocol_version,
+                responder,
+            },
+            QueueKind::Api,
+        )
+        .await;
+
+    let protocol_data = {
+        if let Ok(Some(protocol_data)) = protocol_version_result {
+            protocol_data
+        } else {
+            Box::new(ProtocolData::default())
+        }
+    };
+
+    // auction contract key
+    let base_key = protocol_data.auction().into();
+    // bids named key in auction contract
+    let path = vec![casper_types::auction::BIDS_KEY.to_string()];
+
+    let query_result = effect_builder
+        .make_request(
+            |responder| RpcRequest::QueryGlobalState {
+                state_root_hash,
+                base_key,
+                path,
+                responder,
+            },
+            QueueKind::Api,
+        )
+        .await;
+
+    match query_result {
+        Ok(QueryResult::Success { value, .. }) => value
+            .as_cl_value()
+            .and_then(|cl_value| cl_value.to_owned().into_t().ok())
+            .ok_or_else(|| {
+                let error_msg = "get-bids failed: stored bids are not a valid bids map".to_string();
+                info!("{}", error_msg);
+                warp_json_rpc::Error::custom(ErrorCode::QueryFailed as i64, error_msg)
+            }),
+        Ok(query_result) => {
+            let error_msg = format!("get-bids failed: {:?}", query_result);
+            info!("{}", error_msg);
+            Err(warp_json_rpc::Error::custom(
+                ErrorCode::QueryFailed as i64,
+                error_msg,
+            ))
+        }
+        Err(error) => {
+            let error_msg = format!("get-bids failed to execute: {:?}", error);
+            info!("{}", error_msg);
+            Err(warp_json_rpc::Error::custom(
+                ErrorCode::QueryFailedToExecute as i64,
+                error_msg,
+            ))
+        }
+    }
+}
+
+/// Collects the stakes held by `public_key`, both its own bid and its delegations.
+fn staking_positions(public_key: &PublicKey, bids: &Bids) -> Vec<StakingPosition> {
+    let mut positions = Vec::new();
+    for (validator_public_key, bid) in bids {
+        if validator_public_key == public_key {
+            positions.push(StakingPosition {
+                validator_public_key: *validator_public_key,
+                staked_amount: *bid.staked_amount(),
+                is_delegation: false,
+            });
+        }
+        if let Some(delegator) = bid.delegators().get(public_key) {
+            positions.push(StakingPosition {
+                validator_public_key: *validator_public_key,
+                staked_amount: *delegator.staked_amount(),
+                is_delegation: true,
+            });
+        }
+    }
+    positions
+}
@@ -12,6 +12,11 @@
 //! connected to the correct node and sends its own certificate during the TLS handshake,
 //! establishing identity.
 //!
+//! # Transports
+//!
+//! Connections are made over the transport selected in the configuration, see the `transport`
+//! module. Everything above the transport handshake is transport-agnostic.
+//!
 //! # Messages and payloads
 //!
 //! The network itself is best-effort, during regular operation, no messages should be lost.
@@ -30,15 +35,50 @@
 //!
 //! On losing an incoming or outgoing connection for a given peer, the other connection is closed.
 //! No explicit reconnect is attempted. Instead, if the peer is still online, the normal gossiping
-//! process will cause both peers to connect again.
+//! process will cause both peers to connect again. After failed attempts to connect to an address,
+//! further attempts are backed off exponentially, see `reconnect`.
+//!
+//! After a network flap, a peer may connect to us again while its old connections have not been
+//! noticed to be dead yet. Every connection is therefore tagged with a generation: a new incoming
+//! connection from the same node ID supersedes and closes the old one, and makes us reconnect our
+//! outgoing connection as well. A new outgoing connection takes over the messages still queued for
+//! the one it supersedes. Closing a superseded connection does not affect its successor.
+//!
+//! # Planned disconnects
+//!
+//! Before deliberately dropping a peer, e.g. when shutting down, a node sends it a `Goodbye`
+//! message stating the reason and keeps the outgoing connection open for a short drain period, so
+//! responses to the peer's in-flight requests are still delivered. The peer does the same on its
+//! side and holds off reconnecting for a while, instead of treating the disconnect as a failure.
+//!
+//! # Streaming
+//!
+//! Payloads carrying large blobs, like tries, are sent to peers supporting it as a stream of
+//! chunks rather than as a single message, see `streaming`.
+//!
+//! # Network time
+//!
+//! After the handshake and on every gossip round, a node asks its peers for their current time.
+//! Correcting the answers for round-trip times yields an estimate of how far its clock is off from
+//! the median clock of its peers, which is logged if it exceeds the maximum tolerated clock skew.
 
+mod attestations;
+mod ban_list;
+mod chain_info;
 mod config;
 mod error;
 mod event;
 mod gossiped_address;
 mod message;
+mod metrics;
+mod outgoing_state;
+mod reconnect;
+mod request_audit;
+mod streaming;
 #[cfg(test)]
 mod tests;
+mod transport;
+mod validator_keys;
 
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
@@ -46,64 +86,140 @@ use std::{
     env,
     fmt::{self, Debug, Display, Formatter},
     io,
-    net::{SocketAddr, TcpListener},
+    marker::PhantomData,
+    net::SocketAddr,
+    pin::Pin,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use anyhow::Context;
+use bytes::{Bytes, BytesMut};
 use datasize::DataSize;
 use futures::{
-    future::{select, BoxFuture, Either},
+    future::{self, select, BoxFuture, Either},
     stream::{SplitSink, SplitStream},
     FutureExt, SinkExt, StreamExt,
 };
-use openssl::pkey;
-use pkey::{PKey, Private};
+use openssl::ssl::SslAcceptor;
+use prometheus::{IntCounter, IntCounterVec, Registry};
 use rand::seq::IteratorRandom;
+use semver::Version;
 use serde::{de::DeserializeOwned, Serialize};
 use tokio::{
-    net::TcpStream,
     sync::{
-        mpsc::{self, UnboundedReceiver, UnboundedSender},
-        watch,
+        mpsc::{self, error::TryRecvError, UnboundedReceiver, UnboundedSender},
+        oneshot, watch,
     },
     task::JoinHandle,
 };
-use tokio_openssl::SslStream;
 use tokio_serde::{formats::SymmetricalMessagePack, SymmetricallyFramed};
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
 use tracing::{debug, error, info, trace, warn};
 
-use self::error::Result;
-pub(crate) use self::{event::Event, gossiped_address::GossipedAddress, message::Message};
+use casper_types::PublicKey;
+
+use self::{
+    attestations::Attestations,
+    ban_list::ImportedBans,
+    error::Result,
+    message::{DisconnectReason, HandshakeEncoding},
+    metrics::{NetworkMetrics, PeerTraffic},
+    outgoing_state::{OutgoingState, OutgoingStates},
+    reconnect::ReconnectBackoff,
+    request_audit::RequestAudit,
+    streaming::{StreamAssembler, StreamHasher},
+    transport::{Connector, IncomingStream, Listener, Transport},
+    validator_keys::ValidatorKeys,
+};
+pub(crate) use self::{
+    attestations::ATTESTATION_FEATURE,
+    chain_info::ChainInfo,
+    event::Event,
+    gossiped_address::GossipedAddress,
+    message::Message,
+    request_audit::RequestPayload,
+    streaming::{LargePayload, STREAMING_FEATURE},
+    validator_keys::VALIDATOR_KEY_FEATURE,
+};
 use crate::{
     components::{network::ENABLE_SMALL_NET_ENV_VAR, Component},
-    crypto::hash::Digest,
     effect::{
         announcements::NetworkAnnouncement,
-        requests::{NetworkInfoRequest, NetworkRequest},
+        requests::{DeliveryStatus, NetworkInfoRequest, NetworkRequest},
         EffectBuilder, EffectExt, EffectResultExt, Effects,
     },
     fatal,
     reactor::{EventQueueHandle, Finalize, QueueKind},
-    tls::{self, TlsCert},
-    types::NodeId,
-    utils, NodeRng,
+    tls::{self, TlsCert, TlsConnector},
+    types::{
+        FeatureFlags, ImportedBan, NetworkTimeEstimate, NodeId, PeerDirection, PeerRequestAudit,
+        PeerSample, ProtocolVersionHistogram, SampledPeer, SignedAttestation, SoftwareAttestation,
+        TimeSample, Timestamp,
+    },
+    utils::{self, resource_usage::AttributeExt},
+    NodeRng,
 };
 pub use config::Config;
 pub use error::Error;
+pub use transport::TransportKind;
 
 const MAX_ASYMMETRIC_CONNECTION_SEEN: u16 = 3;
 
+/// Time the server waits before accepting further connections after running out of local
+/// resources, e.g. file descriptors.
+const ACCEPT_RESOURCE_EXHAUSTION_DELAY: Duration = Duration::from_millis(100);
+
+/// Handed to the message sender of a superseded outgoing connection, to pass on the messages still
+/// queued for it.
+type Handover<P> = oneshot::Sender<Vec<QueuedMessage<P>>>;
+
+/// A message queued for an outgoing connection.
+struct QueuedMessage<P> {
+    message: Message<P>,
+    /// Notified once the message has been written or failed to be. If the message is dropped
+    /// instead, the notifier is dropped along with it.
+    delivery: Option<oneshot::Sender<DeliveryStatus>>,
+    /// The bytes split off the payload of the message, to be streamed after it.
+    stream: Option<Vec<u8>>,
+}
+
+impl<P: Debug> Debug for QueuedMessage<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The streamed bytes can be megabytes in size, so only their length is shown.
+        f.debug_struct("QueuedMessage")
+            .field("message", &self.message)
+            .field("delivery", &self.delivery)
+            .field("stream_len", &self.stream.as_ref().map(Vec::len))
+            .finish()
+    }
+}
+
+impl<P> From<Message<P>> for QueuedMessage<P> {
+    fn from(message: Message<P>) -> Self {
+        QueuedMessage {
+            message,
+            delivery: None,
+            stream: None,
+        }
+    }
+}
+
 #[derive(DataSize, Debug)]
 pub(crate) struct OutgoingConnection<P> {
     #[data_size(skip)] // Unfortunately, there is no way to inspect an `UnboundedSender`.
-    sender: UnboundedSender<Message<P>>,
+    sender: UnboundedSender<QueuedMessage<P>>,
     peer_address: SocketAddr,
+    /// The generation of the connection, see `SmallNetwork::last_generation`.
+    generation: u64,
+    /// When the connection was established.
+    #[data_size(skip)]
+    established: Instant,
+    /// Signals the message sender that the connection has been superseded.
+    #[data_size(skip)]
+    supersede: oneshot::Sender<Handover<P>>,
 
     // for keeping track of connection asymmetry, tracking the number of times we've seen this
     // connection be asymmetric.
@@ -113,12 +229,31 @@ pub(crate) struct OutgoingConnection<P> {
 #[derive(DataSize, Debug)]
 pub(crate) struct IncomingConnection {
     peer_address: SocketAddr,
+    /// The generation of the connection, see `SmallNetwork::last_generation`.
+    generation: u64,
+    /// When the connection was established.
+    #[data_size(skip)]
+    established: Instant,
+    /// Signals the message reader that the connection has been superseded.
+    #[data_size(skip)]
+    supersede: oneshot::Sender<()>,
 
     // for keeping track of connection asymmetry, tracking the number of times we've seen this
     // connection be asymmetric.
     times_seen_asymmetric: u16,
 }
 
+/// A planned disconnect announced by a peer in a `Goodbye` message.
+#[derive(Debug)]
+struct Farewell {
+    /// The reason given by the peer.
+    reason: DisconnectReason,
+    /// The peer's public listening address, if we were connected to it.
+    peer_address: Option<SocketAddr>,
+    /// When the goodbye was received.
+    received: Instant,
+}
+
 #[derive(DataSize)]
 pub(crate) struct SmallNetwork<REv, P>
 where
@@ -126,8 +261,12 @@ where
 {
     /// Server certificate.
     certificate: Arc<TlsCert>,
-    /// Server secret key.
-    secret_key: Arc<PKey<Private>>,
+    /// TLS acceptor shared by all incoming connections, so that sessions can be resumed.
+    #[data_size(skip)]
+    tls_acceptor: Arc<SslAcceptor>,
+    /// Connector shared by all outgoing connections, e.g. keeping TLS sessions to resume.
+    #[data_size(skip)]
+    connector: Connector,
     /// Our public listening address.
     public_address: SocketAddr,
     /// Our node ID,
@@ -141,17 +280,58 @@ where
     incoming: HashMap<NodeId, IncomingConnection>,
     /// Outgoing network connections' messages.
     outgoing: HashMap<NodeId, OutgoingConnection<P>>,
+    /// The generation of the most recently established connection, incoming or outgoing.
+    ///
+    /// Events about closed connections carry the generation, so that closing a connection which
+    /// has been superseded by a new one to the same peer does not remove the new one.
+    last_generation: u64,
+    /// Senders of outgoing connections which are kept open for the drain period after saying
+    /// goodbye.
+    #[data_size(skip)]
+    draining: HashMap<NodeId, UnboundedSender<QueuedMessage<P>>>,
+    /// Planned disconnects announced by peers.
+    #[data_size(skip)]
+    farewells: HashMap<NodeId, Farewell>,
+    /// Held by every message sender, so shutting down can wait for them to finish.
+    #[data_size(skip)]
+    drain_guard: Option<UnboundedSender<()>>,
+    /// Dropped on shutdown, starting the deadline of the message senders to send everything
+    /// queued.
+    #[data_size(skip)]
+    drain_sender: Option<watch::Sender<()>>,
+    /// A clone of the receiver is passed to every message sender.
+    #[data_size(skip)]
+    drain_signal: watch::Receiver<()>,
+    /// Time the message senders are given to send everything queued on shutdown.
+    shutdown_drain_timeout: Duration,
+    /// Size of the chunks large payloads are streamed in, zero if streaming is disabled.
+    stream_chunk_size: usize,
+    /// Peers which advertised in their handshake that they can receive streams.
+    streaming_peers: HashSet<NodeId>,
+    /// The public key this node validates with, announced to peers supporting it.
+    validator_key: Option<PublicKey>,
+    /// The validator keys announced by peers.
+    validator_keys: ValidatorKeys,
+    /// Our software attestation and the ones sent by peers.
+    attestations: Attestations,
+    /// The data requests sent by peers, and their quotas.
+    request_audit: RequestAudit,
+    /// Closed once the drain guard and all its clones have been dropped.
+    #[data_size(skip)]
+    drain_receiver: UnboundedReceiver<()>,
 
     /// List of addresses which this node will avoid connecting to.
     blocklist: HashSet<SocketAddr>,
+    /// Bans imported from the signed ban lists of trusted operators.
+    imported_bans: ImportedBans,
 
     /// Pending outgoing connections: ones for which we are currently trying to make a connection.
     pending: HashSet<SocketAddr>,
     /// The interval between each fresh round of gossiping the node's public listening address.
     gossip_interval: Duration,
-    /// The hash of the chainspec.  We only remain connected to peers with the same
-    /// `genesis_config_hash` as us.
-    genesis_config_hash: Digest,
+    /// The chain we are on. We only remain connected to peers on the same chain, see `ChainInfo`.
+    #[data_size(skip)]
+    chain_info: ChainInfo,
     /// Channel signaling a shutdown of the small network.
     // Note: This channel is closed when `SmallNetwork` is dropped, signalling the receivers that
     // they should cease operation.
@@ -165,11 +345,60 @@ where
     is_stopped: Arc<AtomicBool>,
     /// Join handle for the server thread.
     server_join_handle: Option<JoinHandle<()>>,
+    /// Maximum clock skew tolerated between us and a peer.
+    max_clock_skew: Duration,
+    /// Whether to drop connections to peers exceeding `max_clock_skew`.
+    reject_clock_skew: bool,
+    /// Our feature flags, sent to peers in the handshake.
+    features: FeatureFlags,
+    /// Time a connection is kept open after saying goodbye.
+    goodbye_drain_period: Duration,
+    /// Time to wait before reconnecting to a peer which said goodbye.
+    reconnect_delay_after_goodbye: Duration,
+    /// Time allowed for a peer to complete the TLS and protocol handshakes.
+    handshake_timeout: Duration,
+    /// Time allowed for an outgoing connection attempt.
+    connection_timeout: Duration,
+    /// Maximum size of a single message sent to or received from a peer.
+    max_message_size: u32,
+    /// Delays before reconnecting to addresses which connection attempts failed to.
+    reconnect_backoff: ReconnectBackoff,
+    /// The protocol versions advertised in the handshakes of connected peers.
+    #[data_size(skip)]
+    peer_protocol_versions: HashMap<NodeId, Option<Version>>,
+    /// The handshake encodings used for peers running older versions, all other peers are sent the
+    /// current encoding. Kept across reconnects.
+    handshake_encodings: HashMap<NodeId, HandshakeEncoding>,
+    /// The encodings of previous versions which peers on our chain may still run, newest first.
+    supported_handshake_ancestors: Vec<HandshakeEncoding>,
+    /// Peers whose outgoing connection is to be re-established right away once closed, as it was
+    /// established with a handshake encoding they cannot decode.
+    downgrade_retries: HashSet<NodeId>,
+    /// The latest clock offset measured for each connected peer.
+    #[data_size(skip)]
+    time_samples: HashMap<NodeId, TimeSample>,
+    /// Whether we have warned that our clock is off from the network time.
+    clock_error_reported: bool,
+    /// The state of the outgoing connection to each address, exported as metrics.
+    outgoing_states: OutgoingStates,
+    /// Whether to log every change of `outgoing_states`.
+    log_outgoing_state_changes: bool,
+    /// Network metrics.
+    #[data_size(skip)]
+    metrics: NetworkMetrics,
 }
 
 impl<REv, P> SmallNetwork<REv, P>
 where
-    P: Serialize + DeserializeOwned + Clone + Debug + Display + Send + 'static,
+    P: Serialize
+        + DeserializeOwned
+        + Clone
+        + Debug
+        + Display
+        + LargePayload
+        + RequestPayload
+        + Send
+        + 'static,
     REv: Send + From<Event<P>> + From<NetworkAnnouncement<NodeId, P>>,
 {
     /// Creates a new small network component instance.
@@ -180,7 +409,10 @@ where
     pub(crate) fn new(
         event_queue: EventQueueHandle<REv>,
         cfg: Config,
-        genesis_config_hash: Digest,
+        registry: &Registry,
+        chain_info: ChainInfo,
+        features: FeatureFlags,
+        validator_key: Option<PublicKey>,
         notify: bool,
     ) -> Result<(SmallNetwork<REv, P>, Effects<Event<P>>)> {
         // Assert we have at least one known address in the config.
@@ -194,44 +426,102 @@ where
 
         // First, we generate the TLS keys.
         let (cert, secret_key) = tls::generate_node_cert().map_err(Error::CertificateGeneration)?;
+        let tls_options = cfg.tls_options();
+        let tls_acceptor = Arc::new(
+            tls::create_tls_acceptor(&cert, &secret_key, &tls_options)
+                .map_err(Error::AcceptorCreation)?,
+        );
+        let tls_connector = TlsConnector::new(&cert, &secret_key, &tls_options)
+            .map_err(Error::ConnectorCreation)?;
         let certificate = Arc::new(tls::validate_cert(cert).map_err(Error::OwnCertificateInvalid)?);
         let our_id = NodeId::from(certificate.public_key_fingerprint());
+        let metrics = NetworkMetrics::new(registry)?;
+        let imported_bans = ImportedBans::from_config(&cfg);
+        let (drain_guard, drain_receiver) = mpsc::unbounded_channel();
+        let (drain_sender, drain_signal) = watch::channel(());
+        let supported_handshake_ancestors =
+            HandshakeEncoding::supported_ancestors(&chain_info.protocol_version);
 
         // If the env var "CASPER_ENABLE_LEGACY_NET" is not defined, exit without starting the
         // server.
         if env::var(ENABLE_SMALL_NET_ENV_VAR).is_err() {
             let model = SmallNetwork {
                 certificate,
-                secret_key: Arc::new(secret_key),
+                tls_acceptor,
+                // Without a listener no connections are made, so the connector is never used.
+                connector: Connector::Tcp(tls_connector),
                 public_address,
                 our_id,
                 is_bootstrap_node: false,
                 event_queue,
                 incoming: HashMap::new(),
                 outgoing: HashMap::new(),
+                last_generation: 0,
+                draining: HashMap::new(),
+                farewells: HashMap::new(),
+                drain_guard: Some(drain_guard),
+                drain_sender: Some(drain_sender),
+                drain_signal,
+                shutdown_drain_timeout: cfg.shutdown_drain_timeout,
+                stream_chunk_size: cfg.stream_chunk_size as usize,
+                streaming_peers: HashSet::new(),
+                validator_key,
+                validator_keys: ValidatorKeys::default(),
+                attestations: Attestations::default(),
+                request_audit: RequestAudit::new(&cfg),
+                drain_receiver,
                 pending: HashSet::new(),
                 blocklist: HashSet::new(),
+                imported_bans,
                 gossip_interval: cfg.gossip_interval,
-                genesis_config_hash,
+                chain_info,
+                outgoing_states: OutgoingStates::default(),
+                log_outgoing_state_changes: cfg.log_outgoing_state_changes,
                 shutdown_sender: None,
                 shutdown_receiver: watch::channel(()).1,
                 server_join_handle: None,
                 is_stopped: Arc::new(AtomicBool::new(true)),
+                max_clock_skew: cfg.max_clock_skew,
+                reject_clock_skew: cfg.reject_clock_skew,
+                goodbye_drain_period: cfg.goodbye_drain_period,
+                reconnect_delay_after_goodbye: cfg.reconnect_delay_after_goodbye,
+                handshake_timeout: cfg.handshake_timeout,
+                connection_timeout: cfg.connection_timeout,
+                max_message_size: cfg.max_message_size,
+                reconnect_backoff: ReconnectBackoff::new(
+                    cfg.reconnect_base_delay,
+                    cfg.reconnect_max_delay,
+                ),
+                features,
+                peer_protocol_versions: HashMap::new(),
+                handshake_encodings: HashMap::new(),
+                supported_handshake_ancestors,
+                downgrade_retries: HashSet::new(),
+                time_samples: HashMap::new(),
+                clock_error_reported: false,
+                metrics,
             };
             return Ok((model, Effects::new()));
         }
 
         // We can now create a listener.
         let bind_address = utils::resolve_address(&cfg.bind_address).map_err(Error::ResolveAddr)?;
-        let listener = TcpListener::bind(bind_address)
-            .map_err(|error| Error::ListenerCreation(error, bind_address))?;
+        let listener = Listener::bind(
+            cfg.transport,
+            bind_address,
+            certificate.as_x509(),
+            &secret_key,
+            &tls_options,
+        )?;
+        let connector = listener.connector(tls_connector);
 
         // Once the port has been bound, we can notify systemd if instructed to do so.
         if notify {
             if cfg.systemd_support {
                 if sd_notify::booted().map_err(Error::SystemD)? {
                     info!("notifying systemd that the network is ready to receive connections");
-                    sd_notify::notify(true, &[sd_notify::NotifyState::Ready])
+                    // Keep the notification socket in the environment for the watchdog.
+                    sd_notify::notify(false, &[sd_notify::NotifyState::Ready])
                         .map_err(Error::SystemD)?;
                 } else {
                     warn!("systemd_support enabled but not booted with systemd, ignoring");
@@ -240,7 +530,7 @@ where
                 debug!("systemd_support disabled, not notifying");
             }
         }
-        let local_address = listener.local_addr().map_err(Error::ListenerAddr)?;
+        let local_address = listener.local_addr()?;
 
         // Substitute the actually bound port if set to 0.
         if public_address.port() == 0 {
@@ -250,34 +540,72 @@ where
         // Run the server task.
         // We spawn it ourselves instead of through an effect to get a hold of the join handle,
         // which we need to shutdown cleanly later on.
-        info!(%local_address, %public_address, "{}: starting server background task", our_id);
+        info!(%local_address, %public_address, transport=%cfg.transport, "{}: starting server background task", our_id);
         let (server_shutdown_sender, server_shutdown_receiver) = watch::channel(());
         let shutdown_receiver = server_shutdown_receiver.clone();
         let server_join_handle = tokio::spawn(server_task(
             event_queue,
-            tokio::net::TcpListener::from_std(listener).map_err(Error::ListenerConversion)?,
+            listener,
             server_shutdown_receiver,
             our_id,
+            metrics.accept_resource_exhaustion.clone(),
         ));
 
         let our_id = NodeId::from(certificate.public_key_fingerprint());
         let mut model = SmallNetwork {
             certificate,
-            secret_key: Arc::new(secret_key),
+            tls_acceptor,
+            connector,
             public_address,
             our_id,
             is_bootstrap_node: false,
             event_queue,
             incoming: HashMap::new(),
             outgoing: HashMap::new(),
+            last_generation: 0,
+            draining: HashMap::new(),
+            farewells: HashMap::new(),
+            drain_guard: Some(drain_guard),
+            drain_sender: Some(drain_sender),
+            drain_signal,
+            shutdown_drain_timeout: cfg.shutdown_drain_timeout,
+            stream_chunk_size: cfg.stream_chunk_size as usize,
+            streaming_peers: HashSet::new(),
+            validator_key,
+            validator_keys: ValidatorKeys::default(),
+            attestations: Attestations::default(),
+            request_audit: RequestAudit::new(&cfg),
+            drain_receiver,
             pending: HashSet::new(),
             blocklist: HashSet::new(),
+            imported_bans,
             gossip_interval: cfg.gossip_interval,
-            genesis_config_hash,
+            chain_info,
+            outgoing_states: OutgoingStates::default(),
+            log_outgoing_state_changes: cfg.log_outgoing_state_changes,
             shutdown_sender: Some(server_shutdown_sender),
             shutdown_receiver,
             server_join_handle: Some(server_join_handle),
             is_stopped: Arc::new(AtomicBool::new(false)),
+            max_clock_skew: cfg.max_clock_skew,
+            reject_clock_skew: cfg.reject_clock_skew,
+            goodbye_drain_period: cfg.goodbye_drain_period,
+            reconnect_delay_after_goodbye: cfg.reconnect_delay_after_goodbye,
+            handshake_timeout: cfg.handshake_timeout,
+            connection_timeout: cfg.connection_timeout,
+            max_message_size: cfg.max_message_size,
+            reconnect_backoff: ReconnectBackoff::new(
+                cfg.reconnect_base_delay,
+                cfg.reconnect_max_delay,
+            ),
+            features,
+            peer_protocol_versions: HashMap::new(),
+            handshake_encodings: HashMap::new(),
+            supported_handshake_ancestors,
+            downgrade_retries: HashSet::new(),
+            time_samples: HashMap::new(),
+            clock_error_reported: false,
+            metrics,
         };
 
         // Bootstrap process.
@@ -287,14 +615,17 @@ where
             match utils::resolve_address(address) {
                 Ok(known_address) => {
                     model.pending.insert(known_address);
+                    model.set_outgoing_state(known_address, Some(OutgoingState::Connecting));
 
                     // We successfully resolved an address, add an effect to connect to it.
                     effects.extend(
                         connect_outgoing(
                             known_address,
                             Arc::clone(&model.certificate),
-                            Arc::clone(&model.secret_key),
+                            model.connector.clone(),
+                            model.metrics.tls_handshakes.clone(),
                             Arc::clone(&model.is_stopped),
+                            model.connection_timeout,
                         )
                         .result(
                             move |(peer_id, transport)| Event::OutgoingEstablished {
@@ -330,6 +661,53 @@ where
         Ok((model, effects))
     }
 
+    /// Returns the generation for a newly established connection.
+    fn next_generation(&mut self) -> u64 {
+        self.last_generation += 1;
+        self.last_generation
+    }
+
+    /// Returns the handshake encoding to use for a peer.
+    fn handshake_encoding(&self, peer_id: &NodeId) -> HandshakeEncoding {
+        self.handshake_encodings
+            .get(peer_id)
+            .copied()
+            .unwrap_or(HandshakeEncoding::CURRENT)
+    }
+
+    /// Returns whether the peer understands time requests, i.e. has sent us a handshake carrying a
+    /// timestamp.
+    fn knows_time_requests(&self, peer_id: &NodeId) -> bool {
+        self.peer_protocol_versions.contains_key(peer_id)
+            && self.handshake_encoding(peer_id) >= HandshakeEncoding::Timestamped
+    }
+
+    /// Creates a new handshake message for a peer, stamped with the current time.
+    fn handshake(&self, peer_id: &NodeId) -> Message<P> {
+        let encoding = self.handshake_encoding(peer_id);
+        let timestamp = if encoding >= HandshakeEncoding::Timestamped {
+            Some(Timestamp::now())
+        } else {
+            None
+        };
+        let features = if encoding >= HandshakeEncoding::WithFeatures {
+            Some(self.features.clone())
+        } else {
+            None
+        };
+        let protocol_version = if encoding >= HandshakeEncoding::WithProtocolVersion {
+            Some(self.chain_info.protocol_version.clone())
+        } else {
+            None
+        };
+        Message::Handshake {
+            genesis_config_hash: self.chain_info.chainspec_hash,
+            timestamp,
+            features,
+            protocol_version,
+        }
+    }
+
     /// Queues a message to be sent to all nodes.
     fn broadcast_message(&self, msg: Message<P>) {
         for peer_id in self.outgoing.keys() {
@@ -337,6 +715,15 @@ where
         }
     }
 
+    /// Queues a message to be sent to the nodes which may validate with one of `validators`.
+    fn broadcast_message_to_validators(&self, msg: Message<P>, validators: &HashSet<PublicKey>) {
+        for peer_id in self.outgoing.keys() {
+            if self.validator_keys.is_in_scope(peer_id, validators) {
+                self.send_message(peer_id.clone(), msg.clone());
+            }
+        }
+    }
+
     /// Queues a message to `count` random nodes on the network.
     fn gossip_message(
         &self,
@@ -372,9 +759,44 @@ where
 
     /// Queues a message to be sent to a specific node.
     fn send_message(&self, dest: NodeId, msg: Message<P>) {
-        // Try to send the message.
-        if let Some(connection) = self.outgoing.get(&dest) {
-            if let Err(msg) = connection.sender.send(msg) {
+        self.queue_message(dest, msg.into());
+    }
+
+    /// Queues a message to be sent to a specific node, returning a receiver for the outcome.
+    ///
+    /// The receiver is cancelled if the message is dropped without being written.
+    fn send_message_confirmed(
+        &self,
+        dest: NodeId,
+        msg: Message<P>,
+    ) -> oneshot::Receiver<DeliveryStatus> {
+        let (delivery, delivered) = oneshot::channel();
+        self.queue_message(
+            dest,
+            QueuedMessage {
+                message: msg,
+                delivery: Some(delivery),
+                stream: None,
+            },
+        );
+        delivered
+    }
+
+    /// Queues a message, along with its delivery notifier if any, to be sent to a specific node.
+    ///
+    /// The bytes of a large payload are split off to be streamed if the node supports it.
+    fn queue_message(&self, dest: NodeId, mut msg: QueuedMessage<P>) {
+        if self.streaming_peers.contains(&dest) {
+            msg.stream = streaming::split_stream(&mut msg.message, self.stream_chunk_size);
+        }
+        // Try to send the message, falling back to a connection which is being drained.
+        let sender = self
+            .outgoing
+            .get(&dest)
+            .map(|connection| &connection.sender)
+            .or_else(|| self.draining.get(&dest));
+        if let Some(sender) = sender {
+            if let Err(msg) = sender.send(msg) {
                 // We lost the connection, but that fact has not reached us yet.
                 warn!(our_id=%self.our_id, %dest, ?msg, "dropped outgoing message, lost connection");
             }
@@ -398,58 +820,97 @@ where
                     debug!(
                         our_id=%self.our_id,
                         %peer_address,
-                        local_address=?transport.get_ref().local_addr(),
+                        local_address=?transport.local_addr(),
                         "connected incoming to ourself - closing connection"
                     );
                     return Effects::new();
                 }
 
                 // If the peer has already disconnected, allow the connection to drop.
-                if let Err(error) = transport.get_ref().peer_addr() {
+                if let Err(error) = transport.peer_addr() {
                     debug!(
                         our_id=%self.our_id,
                         %peer_address,
-                        local_address=?transport.get_ref().local_addr(),
+                        local_address=?transport.local_addr(),
                         %error,
                         "incoming connection dropped",
                     );
                     return Effects::new();
                 }
 
+                if self
+                    .imported_bans
+                    .should_refuse(Some(&peer_id), peer_address.ip())
+                {
+                    return Effects::new();
+                }
+
                 debug!(our_id=%self.our_id, %peer_id, %peer_address, "established incoming connection");
+                // A peer which said goodbye is back, so there is no need to hold off reconnecting.
+                let _ = self.farewells.remove(&peer_id);
                 // The sink is only used to send a single handshake message, then dropped.
-                let (mut sink, stream) = framed::<P>(transport).split();
-                let handshake = Message::Handshake {
-                    genesis_config_hash: self.genesis_config_hash,
-                };
+                let traffic = self.metrics.peer_traffic(&peer_id);
+                let (mut sink, stream) =
+                    framed::<P>(transport, traffic, self.max_message_size).split();
+                let handshake = self.handshake(&peer_id);
                 let mut effects = async move {
                     let _ = sink.send(handshake).await;
                 }
                 .ignore::<Event<P>>();
 
-                let _ = self.incoming.insert(
+                let generation = self.next_generation();
+                let (supersede, superseded) = oneshot::channel();
+                let previous = self.incoming.insert(
                     peer_id.clone(),
                     IncomingConnection {
                         peer_address,
+                        generation,
+                        established: Instant::now(),
+                        supersede,
                         times_seen_asymmetric: 0,
                     },
                 );
 
-                // If the connection is now complete, announce the new peer before starting reader.
-                effects.extend(self.check_connection_complete(effect_builder, peer_id.clone()));
+                match previous {
+                    Some(previous) => {
+                        info!(our_id=%self.our_id, %peer_id, %peer_address, previous_address=%previous.peer_address, "new incoming connection supersedes existing one");
+                        let _ = previous.supersede.send(());
+                        // Our outgoing connection is likely affected by the same flap, so replace
+                        // it as well.
+                        if let Some(outgoing_address) = self
+                            .outgoing
+                            .get(&peer_id)
+                            .map(|outgoing| outgoing.peer_address)
+                        {
+                            if !self.pending.contains(&outgoing_address) {
+                                effects.extend(self.connect(outgoing_address));
+                            }
+                        }
+                    }
+                    None => {
+                        // If the connection is now complete, announce the new peer before starting
+                        // reader.
+                        effects.extend(
+                            self.check_connection_complete(effect_builder, peer_id.clone()),
+                        );
+                    }
+                }
 
                 effects.extend(
                     message_reader(
                         self.event_queue,
                         stream,
                         self.shutdown_receiver.clone(),
+                        superseded,
                         self.our_id.clone(),
                         peer_id.clone(),
                     )
+                    .attribute_to("small_network::reader")
                     .event(move |result| Event::IncomingClosed {
                         result,
                         peer_id,
                         peer_address,
+                        generation,
                     }),
                 );
 
@@ -470,10 +931,7 @@ where
         transport: Transport,
     ) -> Effects<Event<P>> {
         // This connection is send-only, we only use the sink.
-        let peer_address = transport
-            .get_ref()
-            .peer_addr()
-            .expect("should have peer address");
+        let peer_address = transport.peer_addr().expect("should have peer address");
 
         if !self.pending.remove(&peer_address) {
             info!(
@@ -481,49 +939,93 @@ where
                 %peer_address,
                 "this peer's incoming connection has dropped, so don't establish an outgoing"
             );
+            self.set_outgoing_state(peer_address, None);
             return Effects::new();
         }
+        self.reconnect_backoff.succeeded(peer_address);
 
         // If we have connected to ourself, allow the connection to drop.
         if peer_id == self.our_id {
             self.is_bootstrap_node = true;
             debug!(
                 our_id=%self.our_id,
-                peer_address=?transport.get_ref().peer_addr(),
-                local_address=?transport.get_ref().local_addr(),
+                peer_address=?transport.peer_addr(),
+                local_address=?transport.local_addr(),
                 "connected outgoing to ourself - closing connection",
             );
+            self.set_outgoing_state(peer_address, None);
             return Effects::new();
         }
 
+        if self
+            .imported_bans
+            .should_refuse(Some(&peer_id), peer_address.ip())
+        {
+            self.set_outgoing_state(peer_address, None);
+            return Effects::new();
+        }
+
+        let drain_guard = match self.drain_guard.clone() {
+            Some(drain_guard) => drain_guard,
+            None => {
+                debug!(our_id=%self.our_id, %peer_id, %peer_address, "shutting down, not setting up outgoing connection");
+                self.set_outgoing_state(peer_address, None);
+                return Effects::new();
+            }
+        };
+
         // The stream is only used to receive a single handshake message and then dropped.
-        let (sink, stream) = framed::<P>(transport).split();
+        let traffic = self.metrics.peer_traffic(&peer_id);
+        let (sink, stream) = framed::<P>(transport, traffic, self.max_message_size).split();
         debug!(our_id=%self.our_id, %peer_id, %peer_address, "established outgoing connection");
 
         let (sender, receiver) = mpsc::unbounded_channel();
+        let generation = self.next_generation();
+        let (supersede, superseded) = oneshot::channel();
         let connection = OutgoingConnection {
             peer_address,
             sender,
+            generation,
+            established: Instant::now(),
+            supersede,
             times_seen_asymmetric: 0,
         };
-        if self.outgoing.insert(peer_id.clone(), connection).is_some() {
-            // We assume that for a reconnect to have happened, the outgoing entry must have
-            // been either non-existent yet or cleaned up by the handler of the connection
-            // closing event. If this is not the case, an assumed invariant has been violated.
-            error!(our_id=%self.our_id, %peer_id, "did not expect leftover channel in outgoing map");
-        }
-
-        let mut effects = self.check_connection_complete(effect_builder, peer_id.clone());
 
-        let handshake = Message::Handshake {
-            genesis_config_hash: self.genesis_config_hash,
+        let mut effects = Effects::new();
+        self.set_outgoing_state(peer_address, Some(OutgoingState::Connected));
+        let handed_over = match self.outgoing.insert(peer_id.clone(), connection) {
+            Some(previous) => {
+                info!(our_id=%self.our_id, %peer_id, %peer_address, previous_address=%previous.peer_address, "new outgoing connection supersedes existing one");
+                let (handover, handed_over) = oneshot::channel();
+                let _ = previous.supersede.send(handover);
+                Some(handed_over)
+            }
+            None => {
+                effects.extend(self.check_connection_complete(effect_builder, peer_id.clone()));
+                None
+            }
         };
+
+        let handshake = self.handshake(&peer_id);
         let peer_id_cloned = peer_id.clone();
         effects.extend(
-            message_sender(receiver, sink, handshake).event(move |result| Event::OutgoingFailed {
+            message_sender(
+                receiver,
+                sink,
+                handshake,
+                handed_over,
+                superseded,
+                self.drain_signal.clone(),
+                self.shutdown_drain_timeout,
+                self.stream_chunk_size,
+                drain_guard,
+            )
+            .attribute_to("small_network::writer")
+            .event(move |result| Event::OutgoingFailed {
                 peer_id: Some(peer_id),
                 peer_address,
                 error: result.err().map(Into::into),
+                generation,
             }),
         );
         effects.extend(
@@ -533,6 +1035,8 @@ where
                 self.our_id.clone(),
                 peer_id_cloned,
                 peer_address,
+                generation,
+                self.handshake_timeout,
             )
             .ignore::<Event<P>>(),
         );
@@ -543,14 +1047,31 @@ where
     fn handle_outgoing_lost(
         &mut self,
         effect_builder: EffectBuilder<REv>,
+        rng: &mut NodeRng,
         peer_id: Option<NodeId>,
         peer_address: SocketAddr,
         error: Option<Error>,
+        generation: u64,
     ) -> Effects<Event<P>> {
+        if let Some(peer_id) = &peer_id {
+            if !self.is_current_outgoing(peer_id, generation) {
+                debug!(our_id=%self.our_id, %peer_id, %peer_address, generation, "superseded outgoing connection closed");
+                return Effects::new();
+            }
+        }
+
         let _ = self.pending.remove(&peer_address);
 
         if let Some(peer_id) = peer_id {
-            if let Some(err) = error {
+            if self.downgrade_retries.remove(&peer_id) {
+                info!(our_id=%self.our_id, %peer_id, %peer_address, encoding=%self.handshake_encoding(&peer_id), "reconnecting with older handshake encoding");
+                let mut effects = self.remove(effect_builder, &peer_id, false);
+                effects.extend(self.connect_to_peer_if_required(peer_address));
+                return effects;
+            }
+            if let Some(farewell) = self.farewells.get(&peer_id) {
+                info!(our_id=%self.our_id, %peer_id, %peer_address, reason=%farewell.reason, "outgoing connection closed after peer said goodbye");
+            } else if let Some(err) = error {
                 warn!(our_id=%self.our_id, %peer_id, %peer_address, %err, "outgoing connection failed");
             } else {
                 warn!(our_id=%self.our_id, %peer_id, %peer_address, "outgoing connection closed");
@@ -561,14 +1082,67 @@ where
         // If we don't have the node ID passed in here, it was never added as an
         // outgoing connection, hence no need to call `self.remove()`.
         if let Some(err) = error {
-            warn!(our_id=%self.our_id, %peer_address, %err, "outgoing connection failed");
+            self.connect_failed(rng, peer_address, &err);
         } else {
             warn!(our_id=%self.our_id, %peer_address, "outgoing connection closed");
+            self.set_outgoing_state(peer_address, None);
         }
 
         Effects::new()
     }
 
+    /// Backs off reconnecting to `peer_address` after a failed connection attempt.
+    ///
+    /// Only the first of consecutive failures is logged as a warning, to not flood the logs while a
+    /// peer is down.
+    fn connect_failed(&mut self, rng: &mut NodeRng, peer_address: SocketAddr, err: &Error) {
+        let (failures, delay) = self
+            .reconnect_backoff
+            .failed(peer_address, Instant::now(), rng);
+        let retry_in_ms = delay.as_millis() as u64;
+        if failures == 1 {
+            warn!(our_id=%self.our_id, %peer_address, %err, retry_in_ms, "outgoing connection failed");
+        } else {
+            debug!(our_id=%self.our_id, %peer_address, %err, failures, retry_in_ms, "outgoing connection failed again");
+        }
+        self.set_outgoing_state(peer_address, Some(OutgoingState::Waiting));
+    }
+
+    /// Sets the state of the outgoing connection to `peer_address`, recording the change if any.
+    fn set_outgoing_state(&mut self, peer_address: SocketAddr, state: Option<OutgoingState>) {
+        let transition = match self.outgoing_states.set(peer_address, state) {
+            Some(transition) => transition,
+            None => return,
+        };
+        self.metrics
+            .record_outgoing_transition(transition, &self.outgoing_states);
+        if self.log_outgoing_state_changes {
+            info!(
+                our_id=%self.our_id,
+                %peer_address,
+                from=OutgoingState::name(transition.from),
+                to=OutgoingState::name(transition.to),
+                "outgoing connection state changed"
+            );
+        }
+    }
+
+    /// Returns whether an outgoing connection to the peer of the given generation, if any, has not
+    /// been superseded.
+    fn is_current_outgoing(&self, peer_id: &NodeId, generation: u64) -> bool {
+        self.outgoing
+            .get(peer_id)
+            .map_or(true, |outgoing| outgoing.generation == generation)
+    }
+
+    /// Returns whether an incoming connection from the peer of the given generation, if any, has
+    /// not been superseded.
+    fn is_current_incoming(&self, peer_id: &NodeId, generation: u64) -> bool {
+        self.incoming
+            .get(peer_id)
+            .map_or(true, |incoming| incoming.generation == generation)
+    }
+
     fn remove(
         &mut self,
         effect_builder: EffectBuilder<REv>,
@@ -577,18 +1151,82 @@ where
     ) -> Effects<Event<P>> {
         if let Some(incoming) = self.incoming.remove(&peer_id) {
             trace!(our_id=%self.our_id, %peer_id, "removing peer from the incoming connections");
-            let _ = self.pending.remove(&incoming.peer_address);
+            if self.pending.remove(&incoming.peer_address) {
+                self.set_outgoing_state(incoming.peer_address, None);
+            }
         }
         if let Some(outgoing) = self.outgoing.remove(&peer_id) {
             trace!(our_id=%self.our_id, %peer_id, "removing peer from the outgoing connections");
             if add_to_blocklist {
                 info!(our_id=%self.our_id, %peer_id, "blacklisting peer");
                 self.blocklist.insert(outgoing.peer_address);
+                self.set_outgoing_state(outgoing.peer_address, Some(OutgoingState::Blocked));
+            } else {
+                self.set_outgoing_state(outgoing.peer_address, None);
             }
         }
+        let _ = self.streaming_peers.remove(peer_id);
+        self.validator_keys.remove(peer_id);
+        self.attestations.remove(peer_id);
+        if self.peer_protocol_versions.remove(peer_id).is_some() {
+            self.metrics
+                .set_peer_protocol_versions(&self.peer_protocol_versions());
+        }
+        if self.time_samples.remove(peer_id).is_some() {
+            self.update_network_time();
+        }
+        self.metrics.remove_peer(peer_id);
         self.terminate_if_isolated(effect_builder)
     }
 
+    /// Says goodbye to a peer and removes it.
+    ///
+    /// The outgoing connection is kept open for the drain period, so messages still queued for the
+    /// peer and responses to its in-flight requests are delivered.
+    fn say_goodbye(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        peer_id: &NodeId,
+        reason: DisconnectReason,
+        add_to_blocklist: bool,
+    ) -> Effects<Event<P>> {
+        let mut effects = Effects::new();
+        if let Some(outgoing) = self.outgoing.get(peer_id) {
+            debug!(our_id=%self.our_id, %peer_id, %reason, "saying goodbye to peer");
+            let _ = outgoing.sender.send(Message::Goodbye { reason }.into());
+            effects.extend(self.start_draining(effect_builder, peer_id));
+        }
+        effects.extend(self.remove(effect_builder, peer_id, add_to_blocklist));
+        effects
+    }
+
+    /// Keeps the outgoing connection to a peer open for the drain period, even once the peer has
+    /// been removed.
+    fn start_draining(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        peer_id: &NodeId,
+    ) -> Effects<Event<P>> {
+        let sender = match self.outgoing.get(peer_id) {
+            Some(outgoing) => outgoing.sender.clone(),
+            None => return Effects::new(),
+        };
+        let _ = self.draining.insert(peer_id.clone(), sender);
+        let peer_id = peer_id.clone();
+        effect_builder
+            .set_timeout(self.goodbye_drain_period)
+            .event(move |_| Event::DrainPeriodElapsed { peer_id })
+    }
+
+    /// Returns whether the peer at `peer_address` said goodbye recently enough that we should not
+    /// reconnect to it yet.
+    fn is_holding_off(&self, peer_address: SocketAddr) -> bool {
+        self.farewells.values().any(|farewell| {
+            farewell.peer_address == Some(peer_address)
+                && farewell.received.elapsed() < self.reconnect_delay_after_goodbye
+        })
+    }
+
     /// Gossips our public listening address, and schedules the next such gossip round.
     fn gossip_our_address(&mut self, effect_builder: EffectBuilder<REv>) -> Effects<Event<P>> {
         let our_address = GossipedAddress::new(self.public_address);
@@ -634,7 +1272,12 @@ where
         }
         let mut effects = Effects::new();
         for node_id in remove {
-            effects.extend(self.remove(effect_builder, &node_id, true));
+            effects.extend(self.say_goodbye(
+                effect_builder,
+                &node_id,
+                DisconnectReason::Rebalancing,
+                true,
+            ));
         }
         effects
     }
@@ -652,56 +1295,285 @@ where
         match msg {
             Message::Handshake {
                 genesis_config_hash,
+                timestamp,
+                features,
+                protocol_version,
             } => {
-                if genesis_config_hash != self.genesis_config_hash {
+                if let Err(error) = self
+                    .chain_info
+                    .is_compatible_with(&genesis_config_hash, protocol_version.as_ref())
+                {
                     info!(
                         our_id=%self.our_id,
                         %peer_id,
-                        our_hash=?self.genesis_config_hash,
+                        our_hash=?self.chain_info.chainspec_hash,
                         their_hash=?genesis_config_hash,
-                        "dropping connection due to genesis config hash mismatch"
+                        %error,
+                        "dropping connection to peer on another chain"
+                    );
+                    return self.say_goodbye(
+                        effect_builder,
+                        &peer_id,
+                        DisconnectReason::Incompatible,
+                        false,
+                    );
+                }
+                let skewed = timestamp.map_or(false, |timestamp| {
+                    !self.check_clock_skew(&peer_id, timestamp)
+                });
+                if skewed {
+                    return self.say_goodbye(
+                        effect_builder,
+                        &peer_id,
+                        DisconnectReason::Incompatible,
+                        false,
+                    );
+                }
+                let peer_encoding =
+                    HandshakeEncoding::of_handshake(&timestamp, &features, &protocol_version);
+                if let Some(encoding) = self
+                    .handshake_encoding(&peer_id)
+                    .downgrade_for(peer_encoding, &self.supported_handshake_ancestors)
+                {
+                    // The peer runs an older version and will drop the connections we sent our
+                    // handshake on. Retry the outgoing one once it is closed.
+                    info!(our_id=%self.our_id, %peer_id, %peer_encoding, %encoding, "peer uses an older handshake encoding, downgrading");
+                    let _ = self.handshake_encodings.insert(peer_id.clone(), encoding);
+                    if self.outgoing.contains_key(&peer_id) {
+                        let _ = self.downgrade_retries.insert(peer_id.clone());
+                    }
+                } else if peer_encoding == HandshakeEncoding::CURRENT {
+                    // The peer has been upgraded.
+                    let _ = self.handshake_encodings.remove(&peer_id);
+                }
+                let features = features.unwrap_or_default();
+                debug!(our_id=%self.our_id, %peer_id, %features, "peer feature flags");
+                // The handshake timestamp is skewed by transit times, so take a proper sample.
+                // Peers not sending a timestamp do not know time requests either.
+                if timestamp.is_some() {
+                    self.send_message(
+                        peer_id.clone(),
+                        Message::TimeRequest {
+                            origin: Timestamp::now(),
+                        },
                     );
-                    return self.remove(effect_builder, &peer_id, false);
                 }
+                if features.is_enabled(STREAMING_FEATURE) == Some(true) {
+                    let _ = self.streaming_peers.insert(peer_id.clone());
+                }
+                if features.is_enabled(VALIDATOR_KEY_FEATURE) == Some(true) {
+                    self.send_message(
+                        peer_id.clone(),
+                        Message::ValidatorKey {
+                            public_key: self.validator_key,
+                        },
+                    );
+                }
+                if features.is_enabled(ATTESTATION_FEATURE) == Some(true) {
+                    self.attestations.add_recipient(peer_id.clone());
+                    if let Some(attestation) = self.attestations.ours() {
+                        let attestation = Box::new(attestation.clone());
+                        self.send_message(peer_id.clone(), Message::Attestation { attestation });
+                    }
+                }
+                self.peer_protocol_versions
+                    .insert(peer_id, protocol_version);
+                self.metrics
+                    .set_peer_protocol_versions(&self.peer_protocol_versions());
                 Effects::new()
             }
-            Message::Payload(payload) => effect_builder
-                .announce_message_received(peer_id, payload)
-                .ignore(),
+            Message::TimeRequest { origin } => {
+                // Time spent in our event queue is not excluded and widens the bounds of the
+                // sample; this only makes it less precise, not wrong.
+                let now = Timestamp::now();
+                self.send_message(
+                    peer_id,
+                    Message::TimeResponse {
+                        origin,
+                        received: now,
+                        sent: now,
+                    },
+                );
+                Effects::new()
+            }
+            Message::TimeResponse {
+                origin,
+                received,
+                sent,
+            } => {
+                let sample = TimeSample::from_exchange(origin, received, sent, Timestamp::now());
+                trace!(our_id=%self.our_id, %peer_id, ?sample, "peer time sampled");
+                let _ = self.time_samples.insert(peer_id, sample);
+                self.update_network_time();
+                Effects::new()
+            }
+            Message::Goodbye { reason } => {
+                info!(our_id=%self.our_id, %peer_id, %reason, "peer said goodbye");
+                let farewell = Farewell {
+                    reason,
+                    peer_address: self
+                        .outgoing
+                        .get(&peer_id)
+                        .map(|connection| connection.peer_address),
+                    received: Instant::now(),
+                };
+                let _ = self.farewells.insert(peer_id.clone(), farewell);
+                // The peer is draining its side, so keep ours open for responses to it as well.
+                let mut effects = self.start_draining(effect_builder, &peer_id);
+                effects.extend(self.remove(effect_builder, &peer_id, false));
+                effects
+            }
+            Message::Payload(payload) => {
+                if let Some(class) = payload.request_class() {
+                    if !self.request_audit.admit(&peer_id, class, Instant::now()) {
+                        debug!(our_id=%self.our_id, %peer_id, %class, "peer exceeded request quota, dropping request");
+                        return Effects::new();
+                    }
+                }
+                effect_builder
+                    .announce_message_received(peer_id, payload)
+                    .ignore()
+            }
+            Message::ValidatorKey { public_key } => {
+                debug!(our_id=%self.our_id, %peer_id, ?public_key, "peer announced validator key");
+                self.validator_keys.announce(peer_id, public_key);
+                Effects::new()
+            }
+            Message::Attestation { attestation } => {
+                if let Err(error) = attestation.verify() {
+                    warn!(our_id=%self.our_id, %peer_id, %error, "ignoring invalid attestation");
+                    return Effects::new();
+                }
+                let created = attestation.created();
+                if !attestations::is_fresh(created, Timestamp::now(), self.max_clock_skew) {
+                    warn!(our_id=%self.our_id, %peer_id, %created, "ignoring stale attestation");
+                    return Effects::new();
+                }
+                debug!(our_id=%self.our_id, %peer_id, ?attestation, "peer sent attestation");
+                self.attestations.insert(peer_id, *attestation);
+                Effects::new()
+            }
+            Message::StreamStart { .. }
+            | Message::StreamChunk { .. }
+            | Message::StreamEnd { .. }
+            | Message::StreamCancel { .. } => {
+                // Streams are reassembled by the message reader, so these never get here.
+                warn!(our_id=%self.our_id, %peer_id, %msg, "unexpected stream message");
+                Effects::new()
+            }
+        }
+    }
+
+    /// Measures the clock skew of a peer from the timestamp in its handshake.
+    ///
+    /// The measurement includes the time the handshake spent in transit and in our event queue, so
+    /// it is an upper bound for a peer whose clock is ahead of ours. Returns `false` if the
+    /// connection should be dropped.
+    fn check_clock_skew(&self, peer_id: &NodeId, their_timestamp: Timestamp) -> bool {
+        let skew_ms = their_timestamp.millis() as i64 - Timestamp::now().millis() as i64;
+        self.metrics.set_peer_clock_skew(peer_id, skew_ms);
+
+        if skew_ms.abs() as u64 <= self.max_clock_skew.as_millis() as u64 {
+            return true;
+        }
+
+        // Skewed clocks break round timing in consensus, so this is worth shouting about.
+        warn!(
+            our_id=%self.our_id,
+            %peer_id,
+            skew_ms,
+            max_skew_ms=self.max_clock_skew.as_millis() as u64,
+            "CLOCK SKEW: peer's clock differs from ours by more than the configured maximum, \
+            check the time synchronization of this node"
+        );
+        !self.reject_clock_skew
+    }
+
+    /// Returns the estimated offset of our clock from the median clock of connected peers.
+    pub(crate) fn network_time(&self) -> Option<NetworkTimeEstimate> {
+        NetworkTimeEstimate::from_samples(self.time_samples.values())
+    }
+
+    /// Updates the network time metric after the samples changed, and warns once our clock is
+    /// measurably off from the network time.
+    fn update_network_time(&mut self) {
+        let estimate = match self.network_time() {
+            Some(estimate) => estimate,
+            None => return,
+        };
+        self.metrics.network_time_offset.set(estimate.offset_ms);
+
+        match estimate.local_clock_error(self.max_clock_skew.as_millis() as u64) {
+            Some(error_ms) if !self.clock_error_reported => {
+                self.clock_error_reported = true;
+                warn!(
+                    our_id=%self.our_id,
+                    error_ms,
+                    offset_ms=estimate.offset_ms,
+                    lower_bound_ms=estimate.lower_bound_ms,
+                    upper_bound_ms=estimate.upper_bound_ms,
+                    peers=estimate.peers,
+                    "CLOCK SKEW: our clock differs from the median clock of our peers by more than \
+                    the configured maximum, check the time synchronization of this node"
+                );
+            }
+            None if self.clock_error_reported => {
+                self.clock_error_reported = false;
+                info!(
+                    our_id=%self.our_id,
+                    offset_ms=estimate.offset_ms,
+                    "our clock is in sync with the median clock of our peers again"
+                );
+            }
+            _ => (),
         }
     }
 
     fn connect_to_peer_if_required(&mut self, peer_address: SocketAddr) -> Effects<Event<P>> {
         if self.pending.contains(&peer_address)
             || self.blocklist.contains(&peer_address)
+            || self.is_holding_off(peer_address)
+            || self
+                .reconnect_backoff
+                .is_backing_off(peer_address, Instant::now())
+            || self.imported_bans.should_refuse(None, peer_address.ip())
             || self
                 .outgoing
                 .iter()
                 .any(|(_peer_id, connection)| connection.peer_address == peer_address)
         {
-            // We're already trying to connect, are connected, or the connection is on the blocklist
-            // - do nothing.
+            // We're already trying to connect, are connected, the connection is on the blocklist,
+            // the peer recently said goodbye or our recent attempts failed - do nothing.
             Effects::new()
         } else {
             // We need to connect.
-            assert!(self.pending.insert(peer_address));
-            connect_outgoing(
-                peer_address,
-                Arc::clone(&self.certificate),
-                Arc::clone(&self.secret_key),
-                Arc::clone(&self.is_stopped),
-            )
-            .result(
-                move |(peer_id, transport)| Event::OutgoingEstablished { peer_id, transport },
-                move |error| Event::OutgoingFailed {
-                    peer_id: None,
-                    peer_address,
-                    error: Some(error),
-                },
-            )
+            self.connect(peer_address)
         }
     }
 
+    /// Connects to `peer_address`, which must not be pending already.
+    fn connect(&mut self, peer_address: SocketAddr) -> Effects<Event<P>> {
+        assert!(self.pending.insert(peer_address));
+        self.set_outgoing_state(peer_address, Some(OutgoingState::Connecting));
+        connect_outgoing(
+            peer_address,
+            Arc::clone(&self.certificate),
+            self.connector.clone(),
+            self.metrics.tls_handshakes.clone(),
+            Arc::clone(&self.is_stopped),
+            self.connection_timeout,
+        )
+        .result(
+            move |(peer_id, transport)| Event::OutgoingEstablished { peer_id, transport },
+            move |error| Event::OutgoingFailed {
+                peer_id: None,
+                peer_address,
+                error: Some(error),
+                generation: 0,
+            },
+        )
+    }
+
     /// Checks whether a connection has been established fully, i.e. with an incoming and outgoing
     /// connection.
     ///
@@ -713,6 +1585,7 @@ where
     ) -> Effects<Event<P>> {
         if self.outgoing.contains_key(&peer_id) && self.incoming.contains_key(&peer_id) {
             debug!(%peer_id, "connection to peer is now complete");
+            self.metrics.set_peer_connected(&peer_id);
             effect_builder.announce_new_peer(peer_id).ignore()
         } else {
             Effects::new()
@@ -753,6 +1626,56 @@ where
         ret
     }
 
+    /// Returns the distribution of protocol versions advertised by the connected peers.
+    pub(crate) fn peer_protocol_versions(&self) -> ProtocolVersionHistogram {
+        ProtocolVersionHistogram::new(
+            self.peer_protocol_versions
+                .values()
+                .map(|maybe_version| maybe_version.as_ref()),
+        )
+    }
+
+    /// Returns at most `count` connected peers, chosen uniformly at random.
+    pub(crate) fn sample_peers(&self, rng: &mut NodeRng, count: usize) -> PeerSample {
+        let now = Instant::now();
+        let peer_ids: HashSet<&NodeId> = self.outgoing.keys().chain(self.incoming.keys()).collect();
+        let total_peers = peer_ids.len() as u64;
+        let peers = peer_ids
+            .into_iter()
+            .choose_multiple(rng, count)
+            .into_iter()
+            .map(|peer_id| {
+                let outgoing = self.outgoing.get(peer_id);
+                let incoming = self.incoming.get(peer_id);
+                let direction = match (outgoing, incoming) {
+                    (Some(_), Some(_)) => PeerDirection::Both,
+                    (Some(_), None) => PeerDirection::Outgoing,
+                    (None, _) => PeerDirection::Incoming,
+                };
+                let established = outgoing
+                    .map(|connection| connection.established)
+                    .into_iter()
+                    .chain(incoming.map(|connection| connection.established))
+                    .min()
+                    .unwrap_or(now);
+                // Incoming connections come from an ephemeral port, so only outgoing ones tell the
+                // address the peer listens on.
+                SampledPeer::new(
+                    peer_id,
+                    outgoing.map(|connection| connection.peer_address),
+                    direction,
+                    now.saturating_duration_since(established),
+                )
+            })
+            .collect();
+        PeerSample { total_peers, peers }
+    }
+
+    /// Returns the bans imported from signed ban lists which are currently in effect.
+    pub(crate) fn imported_bans(&self) -> Vec<ImportedBan> {
+        self.imported_bans.active()
+    }
+
     /// Returns whether or not this node has been isolated.
     ///
     /// An isolated node has no chance of recovering a connection to the network and is not
@@ -761,12 +1684,72 @@ where
         self.pending.is_empty() && self.outgoing.is_empty() && self.incoming.is_empty()
     }
 
+    /// Returns the data requests sent by peers, busiest peers first.
+    pub(crate) fn request_audit(&self) -> Vec<PeerRequestAudit> {
+        self.request_audit.snapshot(Instant::now())
+    }
+
+    /// Sets the software attestation sent to peers connecting from now on, and sends it to the
+    /// connected peers supporting attestations.
+    pub(crate) fn set_attestation(&mut self, attestation: SignedAttestation) {
+        for peer_id in self.attestations.recipients() {
+            let attestation = Box::new(attestation.clone());
+            self.send_message(peer_id.clone(), Message::Attestation { attestation });
+        }
+        self.attestations.set_ours(attestation);
+    }
+
+    /// Returns the software our attestation is about if it is due to be re-signed, see
+    /// `set_attestation`.
+    pub(crate) fn stale_attestation(&self) -> Option<SoftwareAttestation> {
+        self.attestations.stale_ours(Timestamp::now())
+    }
+
     /// Returns the node id of this network node.
-    /// - Used in validator test.
-    #[cfg(test)]
     pub(crate) fn node_id(&self) -> NodeId {
         self.our_id.clone()
     }
+
+    /// Says goodbye to all peers and closes the outgoing connections once everything queued on
+    /// them has been sent, see `is_drained`.
+    ///
+    /// No new outgoing connections are set up afterwards, so further messages are dropped.
+    pub(crate) fn start_draining_all(&mut self) {
+        // Dropping the senders lets the message senders exit once they have sent everything
+        // queued.
+        for (_, outgoing) in self.outgoing.drain() {
+            let _ = outgoing.sender.send(
+                Message::Goodbye {
+                    reason: DisconnectReason::Shutdown,
+                }
+                .into(),
+            );
+        }
+        self.draining.clear();
+        drop(self.drain_sender.take());
+        drop(self.drain_guard.take());
+    }
+
+    /// Returns whether all message senders have exited since `start_draining_all` was called.
+    pub(crate) fn is_drained(&mut self) -> bool {
+        self.drain_guard.is_none()
+            && matches!(self.drain_receiver.try_recv(), Err(TryRecvError::Closed))
+    }
+
+    /// Closes the listener and makes any ongoing attempts to connect to peers fail.
+    pub(crate) fn close_listener(&mut self) {
+        // Close the shutdown socket, causing the server to exit.
+        drop(self.shutdown_sender.take());
+
+        // Set the flag to true, ensuring any ongoing attempts to establish outgoing TLS
+        // connections return errors.
+        self.is_stopped.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether the server has exited since `close_listener` was called.
+    pub(crate) fn is_listener_closed(&mut self) -> bool {
+        self.shutdown_sender.is_none() && utils::has_exited(&mut self.server_join_handle)
+    }
 }
 
 impl<REv, P> Finalize for SmallNetwork<REv, P>
@@ -776,12 +1759,16 @@ where
 {
     fn finalize(mut self) -> BoxFuture<'static, ()> {
         async move {
-            // Close the shutdown socket, causing the server to exit.
-            drop(self.shutdown_sender.take());
+            // Give the message senders their deadline to send everything queued.
+            self.start_draining_all();
+            if tokio::time::timeout(self.shutdown_drain_timeout, self.drain_receiver.recv())
+                .await
+                .is_err()
+            {
+                debug!(our_id=%self.our_id, "not all connections drained before shutdown");
+            }
 
-            // Set the flag to true, ensuring any ongoing attempts to establish outgoing TLS
-            // connections return errors.
-            self.is_stopped.store(true, Ordering::SeqCst);
+            self.close_listener();
 
             // Wait for the server to exit cleanly.
             if let Some(join_handle) = self.server_join_handle.take() {
@@ -800,7 +1787,15 @@ where
 impl<REv, P> Component<REv> for SmallNetwork<REv, P>
 where
     REv: Send + From<Event<P>> + From<NetworkAnnouncement<NodeId, P>>,
-    P: Serialize + DeserializeOwned + Clone + Debug + Display + Send + 'static,
+    P: Serialize
+        + DeserializeOwned
+        + Clone
+        + Debug
+        + Display
+        + LargePayload
+        + RequestPayload
+        + Send
+        + 'static,
 {
     type Event = Event<P>;
     type ConstructionError = Infallible;
@@ -816,7 +1811,7 @@ where
                 peer_address,
                 error,
             } => {
-                warn!(our_id=%self.our_id, %peer_address, %error, "connection to known node failed");
+                self.connect_failed(rng, peer_address, &error);
 
                 let was_removed = self.pending.remove(&peer_address);
                 assert!(
@@ -829,14 +1824,24 @@ where
                 stream,
                 peer_address,
             } => {
-                debug!(our_id=%self.our_id, %peer_address, "incoming connection, starting TLS handshake");
+                debug!(our_id=%self.our_id, %peer_address, "incoming connection, starting transport handshake");
 
-                setup_tls(stream, self.certificate.clone(), self.secret_key.clone())
-                    .boxed()
-                    .event(move |result| Event::IncomingHandshakeCompleted {
-                        result,
-                        peer_address,
-                    })
+                let handshake_timeout = self.handshake_timeout;
+                let accept = transport::accept(
+                    stream,
+                    Arc::clone(&self.tls_acceptor),
+                    self.metrics.tls_handshakes.clone(),
+                );
+                async move {
+                    tokio::time::timeout(handshake_timeout, accept)
+                        .await
+                        .unwrap_or(Err(Error::HandshakeTimeout))
+                }
+                .boxed()
+                .event(move |result| Event::IncomingHandshakeCompleted {
+                    result,
+                    peer_address,
+                })
             }
             Event::IncomingHandshakeCompleted {
                 result,
@@ -849,12 +1854,20 @@ where
                 result,
                 peer_id,
                 peer_address,
+                generation,
             } => {
-                match result {
-                    Ok(()) => {
+                if !self.is_current_incoming(&peer_id, generation) {
+                    debug!(our_id=%self.our_id, %peer_id, %peer_address, generation, "superseded incoming connection closed");
+                    return Effects::new();
+                }
+                match (result, self.farewells.get(&peer_id)) {
+                    (_, Some(farewell)) => {
+                        info!(our_id=%self.our_id, %peer_id, %peer_address, reason=%farewell.reason, "connection closed after peer said goodbye")
+                    }
+                    (Ok(()), None) => {
                         info!(our_id=%self.our_id, %peer_id, %peer_address, "connection closed",)
                     }
-                    Err(err) => {
+                    (Err(err), None) => {
                         warn!(our_id=%self.our_id, %peer_id, %peer_address, %err, "connection dropped")
                     }
                 }
@@ -867,7 +1880,15 @@ where
                 peer_id,
                 peer_address,
                 error,
-            } => self.handle_outgoing_lost(effect_builder, peer_id, peer_address, error),
+                generation,
+            } => self.handle_outgoing_lost(
+                effect_builder,
+                rng,
+                peer_id,
+                peer_address,
+                error,
+                generation,
+            ),
             Event::NetworkRequest {
                 req:
                     NetworkRequest::SendMessage {
@@ -880,6 +1901,21 @@ where
                 self.send_message(dest, Message::Payload(payload));
                 responder.respond(()).ignore()
             }
+            Event::NetworkRequest {
+                req:
+                    NetworkRequest::SendMessageConfirmed {
+                        dest,
+                        payload,
+                        responder,
+                    },
+            } => {
+                let delivered = self.send_message_confirmed(dest, Message::Payload(payload));
+                async move {
+                    let status = delivered.await.unwrap_or(DeliveryStatus::Dropped);
+                    responder.respond(status).await
+                }
+                .ignore()
+            }
             Event::NetworkRequest {
                 req: NetworkRequest::Broadcast { payload, responder },
             } => {
@@ -887,6 +1923,17 @@ where
                 self.broadcast_message(Message::Payload(payload));
                 responder.respond(()).ignore()
             }
+            Event::NetworkRequest {
+                req:
+                    NetworkRequest::BroadcastToValidators {
+                        payload,
+                        validators,
+                        responder,
+                    },
+            } => {
+                self.broadcast_message_to_validators(Message::Payload(payload), &validators);
+                responder.respond(()).ignore()
+            }
             Event::NetworkRequest {
                 req:
                     NetworkRequest::Gossip {
@@ -903,7 +1950,55 @@ where
             Event::NetworkInfoRequest {
                 req: NetworkInfoRequest::GetPeers { responder },
             } => responder.respond(self.peers()).ignore(),
+            Event::NetworkInfoRequest {
+                req: NetworkInfoRequest::GetPeerProtocolVersions { responder },
+            } => responder.respond(self.peer_protocol_versions()).ignore(),
+            Event::NetworkInfoRequest {
+                req: NetworkInfoRequest::GetImportedBans { responder },
+            } => responder.respond(self.imported_bans()).ignore(),
+            Event::NetworkInfoRequest {
+                req:
+                    NetworkInfoRequest::GetAttestations {
+                        validators,
+                        responder,
+                    },
+            } => responder
+                .respond(
+                    self.attestations
+                        .by_validator(&validators, Timestamp::now()),
+                )
+                .ignore(),
+            Event::NetworkInfoRequest {
+                req: NetworkInfoRequest::GetRequestAudit { responder },
+            } => responder.respond(self.request_audit()).ignore(),
+            Event::NetworkInfoRequest {
+                req: NetworkInfoRequest::GetNetworkTime { responder },
+            } => responder.respond(self.network_time()).ignore(),
+            Event::NetworkInfoRequest {
+                req: NetworkInfoRequest::SamplePeers { count, responder },
+            } => responder.respond(self.sample_peers(rng, count)).ignore(),
+            Event::DrainPeriodElapsed { peer_id } => {
+                if self.draining.remove(&peer_id).is_some() {
+                    debug!(our_id=%self.our_id, %peer_id, "closing drained connection");
+                }
+                Effects::new()
+            }
             Event::GossipOurAddress => {
+                let reconnect_delay = self.reconnect_delay_after_goodbye;
+                self.farewells
+                    .retain(|_, farewell| farewell.received.elapsed() < reconnect_delay);
+                // Resample the clocks of our peers on every gossip round. Peers running a version
+                // without time requests drop the connection on receiving one.
+                for peer_id in self.outgoing.keys() {
+                    if self.knows_time_requests(peer_id) {
+                        self.send_message(
+                            peer_id.clone(),
+                            Message::TimeRequest {
+                                origin: Timestamp::now(),
+                            },
+                        );
+                    }
+                }
                 let mut effects = self.gossip_our_address(effect_builder);
                 effects.extend(self.enforce_symmetric_connections(effect_builder));
                 effects
@@ -920,9 +2015,10 @@ where
 /// Never terminates.
 async fn server_task<P, REv>(
     event_queue: EventQueueHandle<REv>,
-    mut listener: tokio::net::TcpListener,
+    mut listener: Listener,
     mut shutdown_receiver: watch::Receiver<()>,
     our_id: NodeId,
+    accept_resource_exhaustion: IntCounter,
 ) where
     REv: From<Event<P>>,
 {
@@ -948,13 +2044,16 @@ async fn server_task<P, REv>(
                         .schedule(event, QueueKind::NetworkIncoming)
                         .await;
                 }
-                // TODO: Handle resource errors gracefully.
-                //       In general, two kinds of errors occur here: Local resource exhaustion,
-                //       which should be handled by waiting a few milliseconds, or remote connection
-                //       errors, which can be dropped immediately.
-                //
-                //       The code in its current state will consume 100% CPU if local resource
-                //       exhaustion happens, as no distinction is made and no delay introduced.
+                // Retrying right away after running out of local resources would spin, as the
+                // connection is still waiting in the queue. The resources are likely freed soon,
+                // so we wait a little instead.
+                Err(err) if is_local_resource_exhaustion(&err) => {
+                    accept_resource_exhaustion.inc();
+                    warn!(our_id=%cloned_our_id, %err, delay=?ACCEPT_RESOURCE_EXHAUSTION_DELAY, "out of local resources while accepting incoming connection, pausing");
+                    tokio::time::delay_for(ACCEPT_RESOURCE_EXHAUSTION_DELAY).await;
+                }
+                // Other errors are caused by the remote side, e.g. by closing the connection while
+                // it is waiting in the queue, and only affect that connection.
                 Err(err) => {
                     warn!(our_id=%cloned_our_id, %err, "dropping incoming connection during accept")
                 }
@@ -975,31 +2074,13 @@ async fn server_task<P, REv>(
     }
 }
 
-/// Server-side TLS handshake.
-///
-/// This function groups the TLS handshake into a convenient function, enabling the `?` operator.
-async fn setup_tls(
-    stream: TcpStream,
-    cert: Arc<TlsCert>,
-    secret_key: Arc<PKey<Private>>,
-) -> Result<(NodeId, Transport)> {
-    let tls_stream = tokio_openssl::accept(
-        &tls::create_tls_acceptor(&cert.as_x509().as_ref(), &secret_key.as_ref())
-            .map_err(Error::AcceptorCreation)?,
-        stream,
+/// Returns whether an error accepting a connection is caused by running out of local resources,
+/// rather than by the remote side.
+fn is_local_resource_exhaustion(err: &io::Error) -> bool {
+    matches!(
+        err.raw_os_error(),
+        Some(libc::EMFILE) | Some(libc::ENFILE) | Some(libc::ENOBUFS) | Some(libc::ENOMEM)
     )
-    .await?;
-
-    // We can now verify the certificate.
-    let peer_cert = tls_stream
-        .ssl()
-        .peer_certificate()
-        .ok_or(Error::NoClientCertificate)?;
-
-    Ok((
-        NodeId::from(tls::validate_cert(peer_cert)?.public_key_fingerprint()),
-        tls_stream,
-    ))
 }
 
 /// Network handshake reader for single handshake message received by outgoing connection.
@@ -1009,26 +2090,38 @@ async fn handshake_reader<REv, P>(
     our_id: NodeId,
     peer_id: NodeId,
     peer_address: SocketAddr,
+    generation: u64,
+    handshake_timeout: Duration,
 ) where
     P: DeserializeOwned + Send + Display,
     REv: From<Event<P>>,
 {
-    if let Some(Ok(msg @ Message::Handshake { .. })) = stream.next().await {
-        debug!(%our_id, %msg, %peer_id, "handshake received");
-        return event_queue
-            .schedule(
-                Event::IncomingMessage { peer_id, msg },
-                QueueKind::NetworkIncoming,
-            )
-            .await;
-    }
-    warn!(%our_id, %peer_id, "receiving handshake failed, closing connection");
+    let error = match tokio::time::timeout(handshake_timeout, stream.next()).await {
+        Ok(Some(Ok(msg @ Message::Handshake { .. }))) => {
+            debug!(%our_id, %msg, %peer_id, "handshake received");
+            return event_queue
+                .schedule(
+                    Event::IncomingMessage { peer_id, msg },
+                    QueueKind::NetworkIncoming,
+                )
+                .await;
+        }
+        Ok(_) => {
+            warn!(%our_id, %peer_id, "receiving handshake failed, closing connection");
+            None
+        }
+        Err(_) => {
+            warn!(%our_id, %peer_id, ?handshake_timeout, "no handshake received in time, closing connection");
+            Some(Error::HandshakeTimeout)
+        }
+    };
     event_queue
         .schedule(
             Event::OutgoingFailed {
                 peer_id: Some(peer_id),
                 peer_address,
-                error: None,
+                error,
+                generation,
             },
             QueueKind::Network,
         )
@@ -1037,24 +2130,34 @@ async fn handshake_reader<REv, P>(
 
 /// Network message reader.
 ///
-/// Schedules all received messages until the stream is closed or an error occurs.
+/// Schedules all received messages until the stream is closed, an error occurs or the connection
+/// is superseded. Streamed payloads are scheduled once complete, a malformed stream is treated like
+/// a message which failed to decode.
 async fn message_reader<REv, P>(
     event_queue: EventQueueHandle<REv>,
     mut stream: SplitStream<FramedTransport<P>>,
     mut shutdown_receiver: watch::Receiver<()>,
+    superseded: oneshot::Receiver<()>,
     our_id: NodeId,
     peer_id: NodeId,
 ) -> io::Result<()>
 where
-    P: DeserializeOwned + Send + Display,
+    P: DeserializeOwned + Send + Display + LargePayload,
     REv: From<Event<P>>,
 {
     let our_id_ref = &our_id;
     let peer_id_cloned = peer_id.clone();
     let read_messages = async move {
+        let mut streams = StreamAssembler::default();
         while let Some(msg_result) = stream.next().await {
+            let msg_result = msg_result.and_then(|msg| {
+                streams
+                    .receive(msg)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+            });
             match msg_result {
-                Ok(msg) => {
+                Ok(None) => (),
+                Ok(Some(msg)) => {
                     debug!(our_id=%our_id_ref, %msg, peer_id=%peer_id_cloned, "message received");
                     // We've received a message, push it to the reactor.
                     event_queue
@@ -1078,15 +2181,32 @@ where
 
     let shutdown_messages = async move { while shutdown_receiver.recv().await.is_some() {} };
 
-    // Now we can wait for either the `shutdown` channel's remote end to do be dropped or the
-    // while loop to terminate.
-    match select(Box::pin(shutdown_messages), Box::pin(read_messages)).await {
+    // The connection is only closed if actually superseded, not if merely removed.
+    let superseded = async move {
+        if superseded.await.is_err() {
+            future::pending::<()>().await
+        }
+    };
+
+    // Now we can wait for either the `shutdown` channel's remote end to do be dropped, the
+    // connection to be superseded or the while loop to terminate.
+    match select(
+        Box::pin(shutdown_messages),
+        select(Box::pin(superseded), Box::pin(read_messages)),
+    )
+    .await
+    {
         Either::Left(_) => info!(
             our_id=%our_id,
             %peer_id,
             "shutting down incoming connection message reader"
         ),
-        Either::Right(_) => (),
+        Either::Right((Either::Left(_), _)) => info!(
+            our_id=%our_id,
+            %peer_id,
+            "closing superseded incoming connection"
+        ),
+        Either::Right((Either::Right(_), _)) => (),
     }
 
     Ok(())
@@ -1097,80 +2217,291 @@ where
 /// Reads from a channel and sends all messages, until the stream is closed or an error occurs.
 ///
 /// Initially sends a handshake including the `genesis_config_hash` as a final handshake step.  If
-/// the recipient's `genesis_config_hash` doesn't match, the connection will be closed.
+/// the recipient finds us to be on another chain, see `ChainInfo`, the connection will be closed.
+///
+/// If the connection supersedes another one, the messages still queued for the latter are handed
+/// over through `handed_over` and sent first. Once superseded itself, the sender hands over its
+/// own queue and exits; a message it was just sending is lost with the connection.
+///
+/// Every message which has been written, or failed to be, is reported through its notifier.
+///
+/// Bytes split off a large payload are streamed in chunks of `stream_chunk_size` after it. A stream
+/// still being written once the node starts shutting down is cancelled.
+///
+/// Once the queue is closed and everything in it has been sent, the sink is closed. When the node
+/// shuts down, the closing of `drain_signal` starts a deadline of `drain_timeout`, after which the
+/// messages still queued are dropped rather than holding up the shutdown.
+///
+/// The `drain_guard` is held until the sender exits, see `SmallNetwork::finalize`.
+#[allow(clippy::too_many_arguments)]
 async fn message_sender<P>(
-    mut queue: UnboundedReceiver<Message<P>>,
+    queue: UnboundedReceiver<QueuedMessage<P>>,
     mut sink: SplitSink<FramedTransport<P>, Message<P>>,
     handshake: Message<P>,
+    handed_over: Option<oneshot::Receiver<Vec<QueuedMessage<P>>>>,
+    superseded: oneshot::Receiver<Handover<P>>,
+    mut drain_signal: watch::Receiver<()>,
+    drain_timeout: Duration,
+    stream_chunk_size: usize,
+    _drain_guard: UnboundedSender<()>,
 ) -> Result<()>
 where
     P: Serialize + Send,
 {
     sink.send(handshake).await.map_err(Error::MessageNotSent)?;
-    while let Some(payload) = queue.recv().await {
+
+    let writer = MessageWriter {
+        sink,
+        stream_chunk_size,
+        next_stream_id: 0,
+        drain_signal: drain_signal.clone(),
+    };
+    let drain_deadline = async move {
+        while drain_signal.recv().await.is_some() {}
+        tokio::time::delay_for(drain_timeout).await
+    };
+    let sending = send_queued_messages(queue, writer, handed_over, superseded);
+    match select(Box::pin(sending), Box::pin(drain_deadline)).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => {
+            debug!("drain deadline passed, dropping the messages still queued");
+            Ok(())
+        }
+    }
+}
+
+/// Sends the handed over messages and then those in the queue, until the queue is closed or the
+/// connection is superseded.
+async fn send_queued_messages<P>(
+    mut queue: UnboundedReceiver<QueuedMessage<P>>,
+    mut writer: MessageWriter<P>,
+    handed_over: Option<oneshot::Receiver<Vec<QueuedMessage<P>>>>,
+    superseded: oneshot::Receiver<Handover<P>>,
+) -> Result<()>
+where
+    P: Serialize + Send,
+{
+    // The connection is only superseded if actually told so, not if merely removed.
+    let mut superseded = Box::pin(async move {
+        match superseded.await {
+            Ok(handover) => handover,
+            Err(_) => future::pending().await,
+        }
+    });
+
+    if let Some(handed_over) = handed_over {
+        for payload in handed_over.await.unwrap_or_default() {
+            writer.write_queued(payload).await?;
+        }
+    }
+
+    loop {
+        // Being superseded takes precedence over sending further messages on this connection.
+        let next = match select(&mut superseded, Box::pin(queue.recv())).await {
+            Either::Left((handover, _)) => Either::Left(handover),
+            Either::Right((maybe_payload, _)) => Either::Right(maybe_payload),
+        };
+        let payload = match next {
+            Either::Left(handover) => {
+                hand_over(&mut queue, handover);
+                return Ok(());
+            }
+            Either::Right(Some(payload)) => payload,
+            Either::Right(None) => {
+                // Everything queued has been sent, so close the connection cleanly.
+                return writer.sink.close().await.map_err(Error::MessageNotSent);
+            }
+        };
+
         // We simply error-out if the sink fails, it means that our connection broke.
-        sink.send(payload).await.map_err(Error::MessageNotSent)?;
+        let sent = match select(&mut superseded, Box::pin(writer.write_queued(payload))).await {
+            Either::Left((handover, _)) => Err(handover),
+            Either::Right((result, _)) => Ok(result),
+        };
+        match sent {
+            Ok(result) => result?,
+            Err(handover) => {
+                hand_over(&mut queue, handover);
+                return Ok(());
+            }
+        }
     }
+}
 
-    Ok(())
+/// Writes queued messages to the sink of an outgoing connection.
+struct MessageWriter<P> {
+    sink: SplitSink<FramedTransport<P>, Message<P>>,
+    /// Size of the chunks split off bytes are streamed in.
+    stream_chunk_size: usize,
+    /// The ID of the next stream written.
+    next_stream_id: u64,
+    /// Closed once the node starts shutting down.
+    drain_signal: watch::Receiver<()>,
+}
+
+impl<P> MessageWriter<P>
+where
+    P: Serialize + Send,
+{
+    /// Writes a queued message, notifying the sender waiting for it of the outcome.
+    ///
+    /// A message whose stream is cancelled counts as written, as far as its notifier is concerned.
+    async fn write_queued(&mut self, queued: QueuedMessage<P>) -> Result<()> {
+        let result = match queued.stream {
+            Some(data) => self.write_stream(queued.message, data).await,
+            None => self.sink.send(queued.message).await,
+        };
+        if let Some(delivery) = queued.delivery {
+            let status = match result {
+                Ok(()) => DeliveryStatus::Written,
+                Err(_) => DeliveryStatus::Failed,
+            };
+            let _ = delivery.send(status);
+        }
+        result.map_err(Error::MessageNotSent)
+    }
+
+    /// Writes the payload of `message` as the start of a stream, followed by `data` in chunks.
+    async fn write_stream(&mut self, message: Message<P>, data: Vec<u8>) -> io::Result<()> {
+        let payload = match message {
+            Message::Payload(payload) => payload,
+            // Only payloads are split.
+            other => return self.sink.send(other).await,
+        };
+        let stream_id = self.next_stream_id;
+        self.next_stream_id += 1;
+        self.sink
+            .send(Message::StreamStart {
+                stream_id,
+                payload,
+                total_len: data.len() as u64,
+            })
+            .await?;
+
+        let mut hasher = StreamHasher::new();
+        for chunk in data.chunks(self.stream_chunk_size) {
+            if self.is_draining() {
+                debug!(stream_id, "shutting down, cancelling stream");
+                return self.sink.send(Message::StreamCancel { stream_id }).await;
+            }
+            hasher.update(chunk);
+            self.sink
+                .send(Message::StreamChunk {
+                    stream_id,
+                    data: chunk.to_vec(),
+                })
+                .await?;
+        }
+        self.sink
+            .send(Message::StreamEnd {
+                stream_id,
+                hash: hasher.finalize(),
+            })
+            .await
+    }
+
+    /// Returns whether the node has started shutting down, without waiting.
+    fn is_draining(&mut self) -> bool {
+        // The signal only ever yields its initial value before closing.
+        loop {
+            match self.drain_signal.recv().now_or_never() {
+                Some(Some(())) => continue,
+                Some(None) => return true,
+                None => return false,
+            }
+        }
+    }
 }
 
-/// Transport type alias for base encrypted connections.
-type Transport = SslStream<TcpStream>;
+/// Hands the messages still queued for a superseded outgoing connection over to its successor.
+fn hand_over<P>(queue: &mut UnboundedReceiver<QueuedMessage<P>>, handover: Handover<P>) {
+    let mut queued = Vec::new();
+    while let Ok(message) = queue.try_recv() {
+        queued.push(message);
+    }
+    let _ = handover.send(queued);
+}
 
 /// A framed transport for `Message`s.
 type FramedTransport<P> = SymmetricallyFramed<
     Framed<Transport, LengthDelimitedCodec>,
     Message<P>,
-    SymmetricalMessagePack<Message<P>>,
+    BoundedMessagePack<Message<P>>,
 >;
 
-/// Constructs a new framed transport on a stream.
-fn framed<P>(stream: Transport) -> FramedTransport<P> {
-    let length_delimited = Framed::new(stream, LengthDelimitedCodec::new());
+/// Constructs a new framed transport on a stream, counting its traffic in `traffic` and rejecting
+/// frames larger than `max_message_size`.
+fn framed<P>(stream: Transport, traffic: PeerTraffic, max_message_size: u32) -> FramedTransport<P> {
+    let codec = LengthDelimitedCodec::builder()
+        .max_frame_length(max_message_size as usize)
+        .new_codec();
+    let length_delimited = Framed::new(stream, codec);
     SymmetricallyFramed::new(
         length_delimited,
-        SymmetricalMessagePack::<Message<P>>::default(),
+        BoundedMessagePack {
+            traffic,
+            _phantom: PhantomData,
+        },
     )
 }
 
-/// Initiates a TLS connection to a remote address.
+/// The MessagePack format of `SymmetricalMessagePack`, decoding frames within
+/// `utils::bounded::Limits::NETWORK`.
+///
+/// Records the number and serialized size of the messages passing through it in the traffic
+/// counters of the peer.
+struct BoundedMessagePack<T> {
+    traffic: PeerTraffic,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Serialize> tokio_serde::Serializer<T> for BoundedMessagePack<T> {
+    type Error = io::Error;
+
+    fn serialize(self: Pin<&mut Self>, item: &T) -> io::Result<Bytes> {
+        let format = SymmetricalMessagePack::<T>::default();
+        futures::pin_mut!(format);
+        let serialized = tokio_serde::Serializer::serialize(format, item)?;
+        self.traffic.record_sent(serialized.len());
+        Ok(serialized)
+    }
+}
+
+impl<T: DeserializeOwned> tokio_serde::Deserializer<T> for BoundedMessagePack<T> {
+    type Error = io::Error;
+
+    fn deserialize(self: Pin<&mut Self>, src: &BytesMut) -> io::Result<T> {
+        self.traffic.record_received(src.len());
+        utils::bounded::from_msgpack(src, utils::bounded::Limits::NETWORK)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+/// Initiates a connection to a remote address over the given transport.
 async fn connect_outgoing(
     peer_address: SocketAddr,
     our_certificate: Arc<TlsCert>,
-    secret_key: Arc<PKey<Private>>,
+    connector: Connector,
+    tls_handshakes: IntCounterVec,
     server_is_stopped: Arc<AtomicBool>,
+    connection_timeout: Duration,
 ) -> Result<(NodeId, Transport)> {
-    let mut config = tls::create_tls_connector(&our_certificate.as_x509(), &secret_key)
-        .context("could not create TLS connector")?
-        .configure()
-        .map_err(Error::ConnectorConfiguration)?;
-    config.set_verify_hostname(false);
-
-    let stream = TcpStream::connect(peer_address)
-        .await
-        .context("TCP connection failed")?;
-
-    let tls_stream = tokio_openssl::connect(config, "this-will-not-be-checked.example.com", stream)
-        .await
-        .context("tls handshake failed")?;
-
-    let peer_cert = tls_stream
-        .ssl()
-        .peer_certificate()
-        .ok_or(Error::NoServerCertificate)?;
-
-    let peer_id = tls::validate_cert(peer_cert)?.public_key_fingerprint();
+    let (peer_id, transport) = tokio::time::timeout(
+        connection_timeout,
+        transport::connect(connector, peer_address, tls_handshakes),
+    )
+    .await
+    .map_err(|_| Error::ConnectionTimeout)??;
 
     if server_is_stopped.load(Ordering::SeqCst) {
         debug!(
             our_id=%our_certificate.public_key_fingerprint(),
             %peer_address,
-            "server stopped - aborting outgoing TLS connection"
+            "server stopped - aborting outgoing connection"
         );
         Err(Error::ServerStopped)
     } else {
-        Ok((NodeId::from(peer_id), tls_stream))
+        Ok((peer_id, transport))
     }
 }
 
@@ -1182,7 +2513,7 @@ where
         f.debug_struct("SmallNetwork")
             .field("our_id", &self.our_id)
             .field("certificate", &"<SSL cert>")
-            .field("secret_key", &"<hidden>")
+            .field("connector", &self.connector)
             .field("public_address", &self.public_address)
             .field("event_queue", &"<event_queue>")
             .field("incoming", &self.incoming)
@@ -0,0 +1,222 @@
+//! Software attestations of validators, as sent by the peers.
+//!
+//! Right after the handshake, validators configured to do so send peers supporting it an
+//! attestation of the software they run, signed with their validator key, see
+//! `Message::Attestation`. Attestations are only collected to be reported, so that the stake
+//! running a patched binary can be estimated during security incidents; they play no role in
+//! deciding whom to connect to.
+//!
+//! A peer may send an attestation signed by another validator, e.g. one it received itself, so
+//! attestations older than `MAX_ATTESTATION_AGE` are rejected to keep outdated ones from being
+//! replayed, and our own attestation is re-signed regularly, see `Attestations::stale_ours`.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    time::Duration,
+};
+
+use datasize::DataSize;
+
+use casper_types::PublicKey;
+
+use crate::types::{NodeId, SignedAttestation, SoftwareAttestation, TimeDiff, Timestamp};
+
+/// The feature flag advertising that a node understands `Message::Attestation`.
+pub(crate) const ATTESTATION_FEATURE: &str = "software_attestation";
+
+/// The maximum age of an attestation accepted from a peer.
+const MAX_ATTESTATION_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// The age after which our own attestation is re-signed, well before peers reject it.
+const REFRESH_ATTESTATION_AGE: Duration = Duration::from_secs(20 * 60);
+
+/// Returns whether an attestation `created` at the given time is neither too old nor from the
+/// future at `now`, tolerating `max_clock_skew`.
+pub(super) fn is_fresh(created: Timestamp, now: Timestamp, max_clock_skew: Duration) -> bool {
+    now.saturating_sub(created) <= TimeDiff::from(MAX_ATTESTATION_AGE)
+        && created.saturating_sub(now) <= TimeDiff::from(max_clock_skew)
+}
+
+/// Our own attestation, and the ones sent by the connected peers.
+#[derive(DataSize, Debug, Default)]
+pub(super) struct Attestations {
+    /// Our attestation, sent to peers supporting it, if we are configured to send one.
+    ours: Option<SignedAttestation>,
+    /// The verified attestations sent by the connected peers.
+    peers: HashMap<NodeId, SignedAttestation>,
+    /// The connected peers supporting attestations, which are sent ours whenever it is re-signed.
+    recipients: HashSet<NodeId>,
+}
+
+impl Attestations {
+    /// Sets our attestation, sent to peers connecting from now on.
+    pub(super) fn set_ours(&mut self, attestation: SignedAttestation) {
+        self.ours = Some(attestation);
+    }
+
+    /// Returns our attestation, if any.
+    pub(super) fn ours(&self) -> Option<&SignedAttestation> {
+        self.ours.as_ref()
+    }
+
+    /// Returns the software of our attestation if it is old enough to be re-signed at `now`.
+    pub(super) fn stale_ours(&self, now: Timestamp) -> Option<SoftwareAttestation> {
+        self.ours
+            .as_ref()
+            .filter(|ours| {
+                now.saturating_sub(ours.created()) >= TimeDiff::from(REFRESH_ATTESTATION_AGE)
+            })
+            .map(|ours| ours.attestation().clone())
+    }
+
+    /// Records the attestation `peer_id` sent, which must have been verified.
+    pub(super) fn insert(&mut self, peer_id: NodeId, attestation: SignedAttestation) {
+        let _ = self.peers.insert(peer_id, attestation);
+    }
+
+    /// Records that `peer_id` supports attestations.
+    pub(super) fn add_recipient(&mut self, peer_id: NodeId) {
+        let _ = self.recipients.insert(peer_id);
+    }
+
+    /// Returns the connected peers supporting attestations.
+    pub(super) fn recipients(&self) -> impl Iterator<Item = &NodeId> {
+        self.recipients.iter()
+    }
+
+    /// Forgets the attestation of a disconnected peer.
+    pub(super) fn remove(&mut self, peer_id: &NodeId) {
+        let _ = self.peers.remove(peer_id);
+        let _ = self.recipients.remove(peer_id);
+    }
+
+    /// Returns the latest attestation still fresh at `now` of every one of `validators` known,
+    /// including ours.
+    ///
+    /// Any peer can send an attestation signed by another validator, so several peers may have sent
+    /// one of the same validator.
+    pub(super) fn by_validator(
+        &self,
+        validators: &BTreeSet<PublicKey>,
+        now: Timestamp,
+    ) -> Vec<SignedAttestation> {
+        let mut latest: BTreeMap<PublicKey, &SignedAttestation> = BTreeMap::new();
+        let candidates = self
+            .ours
+            .iter()
+            .chain(self.peers.values())
+            .filter(|attestation| {
+                validators.contains(attestation.validator())
+                    && now.saturating_sub(attestation.created())
+                        <= TimeDiff::from(MAX_ATTESTATION_AGE)
+            });
+        for attestation in candidates {
+            let entry = latest
+                .entry(*attestation.validator())
+                .or_insert(attestation);
+            if attestation.created() > entry.created() {
+                *entry = attestation;
+            }
+        }
+        latest
+            .into_iter()
+            .map(|(_, attestation)| attestation.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::SecretKey;
+    use semver::Version;
+
+    use super::*;
+    use crate::crypto::{hash, AsymmetricKeyExt};
+
+    fn software() -> SoftwareAttestation {
+        SoftwareAttestation {
+            binary_hash: hash::hash(b"casper-node"),
+            protocol_version: Version::new(1, 0, 0),
+            chainspec_hash: hash::hash(b"chainspec"),
+        }
+    }
+
+    #[test]
+    fn should_report_one_attestation_per_validator() {
+        let mut rng = crate::new_rng();
+        let secret_key = SecretKey::random(&mut rng);
+        let attestation = SignedAttestation::sign(software(), &secret_key, &mut rng);
+        let validators = vec![*attestation.validator()].into_iter().collect();
+
+        let mut attestations = Attestations::default();
+        attestations.set_ours(attestation.clone());
+        attestations.insert(NodeId::random(&mut rng), attestation.clone());
+        assert_eq!(
+            attestations.by_validator(&validators, Timestamp::now()),
+            vec![attestation]
+        );
+    }
+
+    #[test]
+    fn should_only_report_current_validators() {
+        let mut rng = crate::new_rng();
+        let validator = SignedAttestation::sign(software(), &SecretKey::random(&mut rng), &mut rng);
+        let former = SignedAttestation::sign(software(), &SecretKey::random(&mut rng), &mut rng);
+        let validators = vec![*validator.validator()].into_iter().collect();
+
+        let mut attestations = Attestations::default();
+        attestations.insert(NodeId::random(&mut rng), validator.clone());
+        attestations.insert(NodeId::random(&mut rng), former);
+        assert_eq!(
+            attestations.by_validator(&validators, Timestamp::now()),
+            vec![validator]
+        );
+    }
+
+    #[test]
+    fn should_reject_stale_attestations() {
+        let mut rng = crate::new_rng();
+        let attestation =
+            SignedAttestation::sign(software(), &SecretKey::random(&mut rng), &mut rng);
+        let created = attestation.created();
+        let max_clock_skew = Duration::from_secs(5);
+        let max_age = TimeDiff::from(MAX_ATTESTATION_AGE);
+
+        assert!(is_fresh(created, created, max_clock_skew));
+        assert!(is_fresh(created, created + max_age, max_clock_skew));
+        assert!(!is_fresh(
+            created,
+            created + max_age + TimeDiff::from(1u64),
+            max_clock_skew
+        ));
+        assert!(!is_fresh(
+            created,
+            created - TimeDiff::from(Duration::from_secs(6)),
+            max_clock_skew
+        ));
+
+        let validators = vec![*attestation.validator()].into_iter().collect();
+        let mut attestations = Attestations::default();
+        attestations.insert(NodeId::random(&mut rng), attestation);
+        assert!(attestations
+            .by_validator(&validators, created + max_age + TimeDiff::from(1u64))
+            .is_empty());
+    }
+
+    #[test]
+    fn should_re_sign_our_attestation_once_due() {
+        let mut rng = crate::new_rng();
+        let attestation =
+            SignedAttestation::sign(software(), &SecretKey::random(&mut rng), &mut rng);
+        let created = attestation.created();
+
+        let mut attestations = Attestations::default();
+        assert_eq!(attestations.stale_ours(created), None);
+        attestations.set_ours(attestation);
+        assert_eq!(attestations.stale_ours(created), None);
+        assert_eq!(
+            attestations.stale_ours(created + TimeDiff::from(REFRESH_ATTESTATION_AGE)),
+            Some(software())
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Bans imported from signed ban lists of other operators.
+//!
+//! Cooperating operators share signed ban lists, see `SignedBanList`. A node only applies the
+//! entries of lists signed by one of its configured trusted issuers, and depending on the
+//! configured policy either refuses connections to and from the banned peers or merely logs them.
+
+use std::net::IpAddr;
+
+use casper_types::{AsymmetricType, PublicKey};
+use datasize::DataSize;
+use tracing::{info, warn};
+
+use super::Config;
+use crate::types::{BanEntry, BanListPolicy, ImportedBan, NodeId, SignedBanList, Timestamp};
+
+#[derive(DataSize, Debug, Default)]
+pub(super) struct ImportedBans {
+    /// How the imported bans are applied.
+    policy: BanListPolicy,
+    /// The entries of all verified ban lists from trusted issuers.
+    #[data_size(skip)]
+    bans: Vec<(PublicKey, BanEntry)>,
+}
+
+impl ImportedBans {
+    /// Imports the ban lists configured in `cfg`.
+    ///
+    /// Lists that cannot be read, fail verification or come from an untrusted issuer are skipped
+    /// with a warning, rather than preventing the node from starting.
+    pub(super) fn from_config(cfg: &Config) -> Self {
+        let trusted: Vec<PublicKey> = cfg
+            .trusted_ban_list_issuers
+            .iter()
+            .filter_map(|hex| match PublicKey::from_hex(hex) {
+                Ok(public_key) => Some(public_key),
+                Err(error) => {
+                    warn!(%hex, %error, "ignoring invalid trusted ban list issuer");
+                    None
+                }
+            })
+            .collect();
+
+        let mut bans = Vec::new();
+        for path in &cfg.ban_list_files {
+            let ban_list = match SignedBanList::from_file(path) {
+                Ok(ban_list) => ban_list,
+                Err(error) => {
+                    warn!(%path, %error, "skipping ban list");
+                    continue;
+                }
+            };
+            if !trusted.contains(ban_list.issuer()) {
+                warn!(%path, issuer = %ban_list.issuer(), "skipping ban list from untrusted issuer");
+                continue;
+            }
+            info!(
+                %path,
+                issuer = %ban_list.issuer(),
+                created = %ban_list.created(),
+                entries = ban_list.entries().len(),
+                policy = ?cfg.ban_list_policy,
+                "imported ban list"
+            );
+            let issuer = *ban_list.issuer();
+            bans.extend(
+                ban_list
+                    .entries()
+                    .iter()
+                    .map(|entry| (issuer, entry.clone())),
+            );
+        }
+
+        ImportedBans {
+            policy: cfg.ban_list_policy,
+            bans,
+        }
+    }
+
+    /// Checks whether a peer is banned, logging if it is.
+    ///
+    /// Returns `true` if the connection to the peer should be refused.
+    pub(super) fn should_refuse(&self, peer_id: Option<&NodeId>, peer_ip: IpAddr) -> bool {
+        let now = Timestamp::now();
+        let (issuer, entry) = match self
+            .bans
+            .iter()
+            .find(|(_, entry)| entry.is_active(now) && entry.target.matches(peer_id, peer_ip))
+        {
+            Some(ban) => ban,
+            None => return false,
+        };
+        match self.policy {
+            BanListPolicy::Enforce => {
+                info!(
+                    ?peer_id,
+                    %peer_ip,
+                    %issuer,
+                    reason = %entry.reason,
+                    "refusing connection to peer on imported ban list"
+                );
+                true
+            }
+            BanListPolicy::LogOnly => {
+                warn!(
+                    ?peer_id,
+                    %peer_ip,
+                    %issuer,
+                    reason = %entry.reason,
+                    "connecting to peer on imported ban list, as bans are not enforced"
+                );
+                false
+            }
+        }
+    }
+
+    /// Returns all bans currently in effect.
+    pub(super) fn active(&self) -> Vec<ImportedBan> {
+        let now = Timestamp::now();
+        let enforced = self.policy == BanListPolicy::Enforce;
+        self.bans
+            .iter()
+            .filter(|(_, entry)| entry.is_active(now))
+            .map(|(issuer, entry)| ImportedBan {
+                issuer: *issuer,
+                entry: entry.clone(),
+                enforced,
+            })
+            .collect()
+    }
+}
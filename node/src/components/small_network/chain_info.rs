@@ -0,0 +1,123 @@
+//! The chain a node is on, checked against the one of every peer during the handshake.
+//!
+//! Peers send the hash of their chainspec and the latest protocol version it supports. A peer is on
+//! our chain if it sends the hash of our chainspec, or if it runs an ancestor of it: our chainspec
+//! without the upgrades from the version the peer sent onwards, which is the case for a peer which
+//! has not been upgraded yet. Any other peer is on a forked or an outdated network.
+
+use semver::Version;
+
+use super::Error;
+use crate::{crypto::hash::Digest, Chainspec};
+
+/// The chainspec of a node, identified by its hash, along with the ones it descends from.
+#[derive(Clone, Debug)]
+pub(crate) struct ChainInfo {
+    /// The hash of the chainspec.
+    pub(super) chainspec_hash: Digest,
+    /// The latest protocol version the chainspec supports.
+    pub(super) protocol_version: Version,
+    /// The latest protocol versions and hashes of the ancestors of the chainspec, oldest first.
+    ancestors: Vec<(Version, Digest)>,
+}
+
+impl ChainInfo {
+    /// Creates the info of a chainspec without ancestors.
+    #[cfg(test)]
+    pub(crate) fn new(chainspec_hash: Digest, protocol_version: Version) -> Self {
+        ChainInfo {
+            chainspec_hash,
+            protocol_version,
+            ancestors: Vec::new(),
+        }
+    }
+
+    /// Creates the info of `chainspec`, its ancestors being the chainspec without its upgrades,
+    /// without all but the first one, and so on.
+    pub(crate) fn from_chainspec(chainspec: &Chainspec) -> Self {
+        let mut ancestor = chainspec.clone();
+        ancestor.upgrades.clear();
+        let mut ancestors = Vec::with_capacity(chainspec.upgrades.len());
+        for upgrade in &chainspec.upgrades {
+            ancestors.push((ancestor.latest_protocol_version(), ancestor.hash()));
+            ancestor.upgrades.push(upgrade.clone());
+        }
+        ChainInfo {
+            chainspec_hash: chainspec.hash(),
+            protocol_version: chainspec.latest_protocol_version(),
+            ancestors,
+        }
+    }
+
+    /// Checks whether a peer sending `chainspec_hash` and `protocol_version` in its handshake is on
+    /// our chain.
+    ///
+    /// Peers running an older version do not send a protocol version, so they are only compatible
+    /// if their chainspec is ours.
+    pub(super) fn is_compatible_with(
+        &self,
+        chainspec_hash: &Digest,
+        protocol_version: Option<&Version>,
+    ) -> Result<(), Error> {
+        let compatible = match protocol_version {
+            Some(version) if *version != self.protocol_version => {
+                self.ancestors
+                    .iter()
+                    .any(|(ancestor_version, ancestor_hash)| {
+                        ancestor_version == version && ancestor_hash == chainspec_hash
+                    })
+            }
+            _ => *chainspec_hash == self.chainspec_hash,
+        };
+        if compatible {
+            Ok(())
+        } else {
+            Err(Error::IncompatibleChain {
+                protocol_version: protocol_version.cloned(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestRng;
+
+    #[test]
+    fn should_accept_peers_on_our_chain_or_an_ancestor() {
+        let mut rng = TestRng::new();
+        let mut chainspec = Chainspec::random(&mut rng);
+        // Random upgrades may share a protocol version, which real ones cannot.
+        chainspec.upgrades.last_mut().unwrap().protocol_version = Version::new(20, 0, 0);
+        let info = ChainInfo::from_chainspec(&chainspec);
+        let current = chainspec.latest_protocol_version();
+        assert!(info
+            .is_compatible_with(&chainspec.hash(), Some(&current))
+            .is_ok());
+        assert!(info.is_compatible_with(&chainspec.hash(), None).is_ok());
+
+        // A peer which has not been upgraded to the latest version yet.
+        let mut outdated = chainspec.clone();
+        let _ = outdated.upgrades.pop();
+        let outdated_version = outdated.latest_protocol_version();
+        assert!(info
+            .is_compatible_with(&outdated.hash(), Some(&outdated_version))
+            .is_ok());
+        // Older peers not sending their version need to have our chainspec.
+        assert!(info.is_compatible_with(&outdated.hash(), None).is_err());
+
+        // A peer on a fork, with a different upgrade at the latest version.
+        let mut forked = chainspec.clone();
+        forked.upgrades.last_mut().unwrap().activation_point.height += 1;
+        match info.is_compatible_with(&forked.hash(), Some(&current)) {
+            Err(Error::IncompatibleChain { protocol_version }) => {
+                assert_eq!(protocol_version, Some(current))
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(info
+            .is_compatible_with(&forked.hash(), Some(&Version::new(99, 0, 0)))
+            .is_err());
+    }
+}
@@ -1,10 +1,20 @@
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 use std::net::{Ipv4Addr, SocketAddr};
-use std::time::Duration;
+use std::{path::Path, time::Duration};
 
 use datasize::DataSize;
 use serde::{Deserialize, Serialize};
 
+use casper_types::{AsymmetricType, PublicKey};
+
+use super::TransportKind;
+use crate::{
+    tls::{self, TlsOptions},
+    types::BanListPolicy,
+    utils::{bounded::Limits, ConfigValidator},
+    Chainspec,
+};
+
 /// Default binding address.
 ///
 /// Uses a fixed port per node, but binds on any interface.
@@ -18,6 +28,43 @@ const DEFAULT_PUBLIC_ADDRESS: &str = "127.0.0.1:0";
 /// Default interval for gossiping network addresses.
 const DEFAULT_GOSSIP_INTERVAL: Duration = Duration::from_secs(30);
 
+/// Default maximum tolerated clock skew between peers.
+const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_secs(5);
+
+/// Default time a connection is kept open after saying goodbye to a peer.
+const DEFAULT_GOODBYE_DRAIN_PERIOD: Duration = Duration::from_secs(2);
+
+/// Default time to wait before reconnecting to a peer which said goodbye.
+const DEFAULT_RECONNECT_DELAY_AFTER_GOODBYE: Duration = Duration::from_secs(60);
+
+/// Default time allowed for a peer to complete the TLS and protocol handshakes.
+const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Default time allowed for an outgoing connection attempt.
+const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time to wait before reconnecting to an address after a first failed attempt.
+const DEFAULT_RECONNECT_BASE_DELAY: Duration = Duration::from_secs(5);
+
+/// Default maximum time to wait before reconnecting to an address after failed attempts.
+const DEFAULT_RECONNECT_MAX_DELAY: Duration = Duration::from_secs(600);
+
+/// Default time given to sending the queued messages on shutdown.
+const DEFAULT_SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default size of the chunks large payloads are streamed in.
+const DEFAULT_STREAM_CHUNK_SIZE: u32 = 1024 * 1024;
+
+/// Maximum size of the chunks large payloads are streamed in, well within the default maximum
+/// message size.
+const MAX_STREAM_CHUNK_SIZE: u32 = 4 * 1024 * 1024;
+
+/// Default maximum size of a single message sent to or received from a peer.
+const DEFAULT_MAX_MESSAGE_SIZE: u32 = 24 * 1024 * 1024;
+
+/// Default length of the sliding window the request quotas apply to.
+const DEFAULT_REQUEST_QUOTA_WINDOW: Duration = Duration::from_secs(60);
+
 // Default values for networking configuration:
 impl Default for Config {
     fn default() -> Self {
@@ -27,6 +74,28 @@ impl Default for Config {
             known_addresses: Vec::new(),
             gossip_interval: DEFAULT_GOSSIP_INTERVAL,
             systemd_support: false,
+            transport: TransportKind::default(),
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+            reject_clock_skew: false,
+            ban_list_files: Vec::new(),
+            trusted_ban_list_issuers: Vec::new(),
+            ban_list_policy: BanListPolicy::default(),
+            goodbye_drain_period: DEFAULT_GOODBYE_DRAIN_PERIOD,
+            reconnect_delay_after_goodbye: DEFAULT_RECONNECT_DELAY_AFTER_GOODBYE,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
+            reconnect_base_delay: DEFAULT_RECONNECT_BASE_DELAY,
+            reconnect_max_delay: DEFAULT_RECONNECT_MAX_DELAY,
+            shutdown_drain_timeout: DEFAULT_SHUTDOWN_DRAIN_TIMEOUT,
+            stream_chunk_size: DEFAULT_STREAM_CHUNK_SIZE,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            log_outgoing_state_changes: false,
+            tls_cipher_suites: Vec::new(),
+            tls_session_resumption: true,
+            send_attestation: false,
+            request_quota_window: DEFAULT_REQUEST_QUOTA_WINDOW,
+            deploy_request_quota: 0,
+            block_request_quota: 0,
         }
     }
 }
@@ -49,17 +118,198 @@ pub struct Config {
     pub gossip_interval: Duration,
     /// Enable systemd startup notification.
     pub systemd_support: bool,
+    /// Transport used for connections to other nodes.
+    pub transport: TransportKind,
+    /// Maximum clock skew in milliseconds tolerated between us and a peer before warning.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub max_clock_skew: Duration,
+    /// Whether to drop connections to peers whose clock skew exceeds `max_clock_skew`.
+    pub reject_clock_skew: bool,
+    /// Files containing signed ban lists to import at startup.
+    ///
+    /// Relative paths are resolved against the directory of the config file.
+    pub ban_list_files: Vec<String>,
+    /// Hex-encoded public keys of the operators whose ban lists are applied.
+    pub trusted_ban_list_issuers: Vec<String>,
+    /// Whether bans from trusted ban lists are enforced or only logged.
+    pub ban_list_policy: BanListPolicy,
+    /// Time in milliseconds a connection is kept open after saying goodbye to a peer, so that
+    /// responses to its in-flight requests can still be sent.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub goodbye_drain_period: Duration,
+    /// Time in milliseconds to wait before reconnecting to a peer which said goodbye.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub reconnect_delay_after_goodbye: Duration,
+    /// Time in milliseconds allowed for a peer to complete the TLS and protocol handshakes before
+    /// the connection is dropped.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub handshake_timeout: Duration,
+    /// Time in milliseconds allowed for an outgoing connection attempt, from opening the TCP
+    /// connection to completing the TLS handshake.
+    ///
+    /// Must be greater than `handshake_timeout`, so that the peer is given all of it.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub connection_timeout: Duration,
+    /// Time in milliseconds to wait before reconnecting to an address after a first failed
+    /// attempt. Doubled on every further consecutive failure.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub reconnect_base_delay: Duration,
+    /// Maximum time in milliseconds to wait before reconnecting to an address after failed
+    /// attempts.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub reconnect_max_delay: Duration,
+    /// Time in milliseconds given to sending the messages still queued for peers on shutdown,
+    /// after which they are dropped.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub shutdown_drain_timeout: Duration,
+    /// Size in bytes of the chunks large payloads, like tries, are streamed to peers in.
+    ///
+    /// Payloads no larger than a chunk are sent as a single message. Zero disables streaming, and
+    /// tells peers not to stream to us either.
+    pub stream_chunk_size: u32,
+    /// Maximum size in bytes of a single message sent to or received from a peer.
+    ///
+    /// Must be large enough for a deploy of the maximum block size allowed by the chainspec.
+    pub max_message_size: u32,
+    /// Whether to log every change of the state of an outgoing connection, meant for small
+    /// networks.
+    pub log_outgoing_state_changes: bool,
+    /// TLS 1.3 cipher suites offered on connections to other nodes, in order of preference, e.g.
+    /// `TLS_AES_256_GCM_SHA384`.
+    ///
+    /// If empty, the defaults of the TLS stack are used. The QUIC transport does not support the
+    /// CCM cipher suites.
+    pub tls_cipher_suites: Vec<String>,
+    /// Whether TLS sessions are resumed when reconnecting to a peer, which avoids a full
+    /// handshake.
+    ///
+    /// High-security deployments may want to disable this, so that every connection performs a
+    /// full handshake.
+    pub tls_session_resumption: bool,
+    /// Whether a validator sends peers a signed attestation of the software it runs, i.e. the hash
+    /// of its binary, its protocol version and the hash of its chainspec.
+    pub send_attestation: bool,
+    /// Length in milliseconds of the sliding window the request quotas apply to.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub request_quota_window: Duration,
+    /// Maximum number of deploy requests served to a peer per window, zero for unlimited.
+    pub deploy_request_quota: u32,
+    /// Maximum number of block requests served to a peer per window, zero for unlimited.
+    ///
+    /// Joining nodes fetch many blocks while syncing, so this should be generous.
+    pub block_request_quota: u32,
 }
 
-#[cfg(test)]
+impl Config {
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        validator.ensure_address("bind_address", &self.bind_address);
+        validator.ensure_address("public_address", &self.public_address);
+        validator.ensure_non_zero("gossip_interval", self.gossip_interval);
+        validator.ensure_non_zero("handshake_timeout", self.handshake_timeout);
+        validator.ensure_non_zero("request_quota_window", self.request_quota_window);
+        validator.ensure(
+            self.handshake_timeout < self.connection_timeout,
+            "connection_timeout",
+            "must be greater than `handshake_timeout`",
+        );
+        validator.ensure(
+            self.stream_chunk_size <= MAX_STREAM_CHUNK_SIZE,
+            "stream_chunk_size",
+            format!("must not exceed {} bytes", MAX_STREAM_CHUNK_SIZE),
+        );
+        validator.ensure(
+            u64::from(self.max_message_size) <= Limits::NETWORK.max_allocation,
+            "max_message_size",
+            format!("must not exceed {} bytes", Limits::NETWORK.max_allocation),
+        );
+        validator.ensure(
+            self.stream_chunk_size < self.max_message_size,
+            "max_message_size",
+            "must be greater than `stream_chunk_size`",
+        );
+        validator.ensure(
+            self.reconnect_base_delay <= self.reconnect_max_delay,
+            "reconnect_max_delay",
+            "must not be less than `reconnect_base_delay`",
+        );
+        validator.ensure(
+            !self.reject_clock_skew || self.max_clock_skew > Duration::default(),
+            "max_clock_skew",
+            "must not be zero if `reject_clock_skew` is set",
+        );
+        for cipher_suite in &self.tls_cipher_suites {
+            validator.ensure(
+                tls::TLS13_CIPHER_SUITES.contains(&cipher_suite.as_str()),
+                "tls_cipher_suites",
+                format!("unknown TLS 1.3 cipher suite {}", cipher_suite),
+            );
+            validator.ensure(
+                !tls::TLS13_CIPHER_SUITES.contains(&cipher_suite.as_str())
+                    || self.transport.supports_cipher_suite(cipher_suite),
+                "tls_cipher_suites",
+                format!(
+                    "cipher suite {} is not supported by the {} transport",
+                    cipher_suite, self.transport
+                ),
+            );
+        }
+        for path in &self.ban_list_files {
+            validator.ensure_file("ban_list_files", Path::new(path));
+        }
+        for issuer in &self.trusted_ban_list_issuers {
+            if let Err(error) = PublicKey::from_hex(issuer) {
+                validator.violation(
+                    "trusted_ban_list_issuers",
+                    format!("invalid public key {}: {}", issuer, error),
+                );
+            }
+        }
+    }
+
+    /// Checks the configuration against the limits set by `chainspec`.
+    pub(crate) fn validate_against_chainspec(
+        &self,
+        chainspec: &Chainspec,
+        validator: &mut ConfigValidator,
+    ) {
+        let max_block_size = chainspec.max_block_size();
+        validator.ensure(
+            self.max_message_size >= max_block_size,
+            "max_message_size",
+            format!(
+                "must not be less than the chainspec's `max_block_size` of {} bytes",
+                max_block_size
+            ),
+        );
+    }
+
+    /// Returns the configured TLS parameters.
+    pub(crate) fn tls_options(&self) -> TlsOptions {
+        TlsOptions {
+            cipher_suites: self.tls_cipher_suites.clone(),
+            session_resumption: self.tls_session_resumption,
+        }
+    }
+
+    /// Resolves relative paths of `ban_list_files` against `root`.
+    pub(crate) fn resolve_ban_list_files(&mut self, root: &Path) {
+        for path in self.ban_list_files.iter_mut() {
+            let resolved = root.join(&*path).display().to_string();
+            *path = resolved;
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
 /// Reduced gossip interval for local testing.
 const DEFAULT_TEST_GOSSIP_INTERVAL: Duration = Duration::from_secs(1);
 
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 /// Address used to bind all local testing networking to by default.
 const TEST_BIND_INTERFACE: Ipv4Addr = Ipv4Addr::LOCALHOST;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 impl Config {
     /// Construct a configuration suitable for testing with no known address that binds to a
     /// specific address.
@@ -70,6 +320,28 @@ impl Config {
             known_addresses: vec![bind_address.to_string()],
             gossip_interval: DEFAULT_TEST_GOSSIP_INTERVAL,
             systemd_support: false,
+            transport: TransportKind::default(),
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+            reject_clock_skew: false,
+            ban_list_files: Vec::new(),
+            trusted_ban_list_issuers: Vec::new(),
+            ban_list_policy: BanListPolicy::default(),
+            goodbye_drain_period: DEFAULT_GOODBYE_DRAIN_PERIOD,
+            reconnect_delay_after_goodbye: DEFAULT_RECONNECT_DELAY_AFTER_GOODBYE,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
+            reconnect_base_delay: DEFAULT_RECONNECT_BASE_DELAY,
+            reconnect_max_delay: DEFAULT_RECONNECT_MAX_DELAY,
+            shutdown_drain_timeout: DEFAULT_SHUTDOWN_DRAIN_TIMEOUT,
+            stream_chunk_size: DEFAULT_STREAM_CHUNK_SIZE,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            log_outgoing_state_changes: false,
+            tls_cipher_suites: Vec::new(),
+            tls_session_resumption: true,
+            send_attestation: false,
+            request_quota_window: DEFAULT_REQUEST_QUOTA_WINDOW,
+            deploy_request_quota: 0,
+            block_request_quota: 0,
         }
     }
 
@@ -88,6 +360,117 @@ impl Config {
             ],
             gossip_interval: DEFAULT_TEST_GOSSIP_INTERVAL,
             systemd_support: false,
+            transport: TransportKind::default(),
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+            reject_clock_skew: false,
+            ban_list_files: Vec::new(),
+            trusted_ban_list_issuers: Vec::new(),
+            ban_list_policy: BanListPolicy::default(),
+            goodbye_drain_period: DEFAULT_GOODBYE_DRAIN_PERIOD,
+            reconnect_delay_after_goodbye: DEFAULT_RECONNECT_DELAY_AFTER_GOODBYE,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
+            reconnect_base_delay: DEFAULT_RECONNECT_BASE_DELAY,
+            reconnect_max_delay: DEFAULT_RECONNECT_MAX_DELAY,
+            shutdown_drain_timeout: DEFAULT_SHUTDOWN_DRAIN_TIMEOUT,
+            stream_chunk_size: DEFAULT_STREAM_CHUNK_SIZE,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            log_outgoing_state_changes: false,
+            tls_cipher_suites: Vec::new(),
+            tls_session_resumption: true,
+            send_attestation: false,
+            request_quota_window: DEFAULT_REQUEST_QUOTA_WINDOW,
+            deploy_request_quota: 0,
+            block_request_quota: 0,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Loadable;
+
+    /// Returns the fields of the violations of `check`.
+    fn violated_fields<F: FnOnce(&mut ConfigValidator)>(check: F) -> Vec<String> {
+        let mut validator = ConfigValidator::new(env!("CARGO_MANIFEST_DIR"));
+        validator.section("network");
+        check(&mut validator);
+        match validator.finish() {
+            Ok(()) => Vec::new(),
+            Err(error) => error
+                .violations
+                .into_iter()
+                .map(|violation| violation.field)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn should_accept_default_config() {
+        let config = Config::default();
+        let chainspec = Chainspec::from_resources("local/chainspec.toml");
+        assert!(violated_fields(|validator| {
+            config.validate(validator);
+            config.validate_against_chainspec(&chainspec, validator);
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn should_require_connection_timeout_above_handshake_timeout() {
+        let config = Config {
+            connection_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            ..Config::default()
+        };
+        assert_eq!(
+            violated_fields(|validator| config.validate(validator)),
+            vec!["network.connection_timeout"]
+        );
+    }
+
+    #[test]
+    fn should_require_max_message_size_within_limits() {
+        let config = Config {
+            max_message_size: DEFAULT_STREAM_CHUNK_SIZE,
+            ..Config::default()
+        };
+        assert_eq!(
+            violated_fields(|validator| config.validate(validator)),
+            vec!["network.max_message_size"]
+        );
+
+        let config = Config {
+            max_message_size: Limits::NETWORK.max_allocation as u32 + 1,
+            ..Config::default()
+        };
+        assert_eq!(
+            violated_fields(|validator| config.validate(validator)),
+            vec!["network.max_message_size"]
+        );
+    }
+
+    #[test]
+    fn should_require_max_message_size_to_fit_max_block_size() {
+        let config = Config::default();
+        let mut chainspec = Chainspec::from_resources("local/chainspec.toml");
+        chainspec.genesis.deploy_config.max_block_size = DEFAULT_MAX_MESSAGE_SIZE + 1;
+        assert_eq!(
+            violated_fields(|validator| config.validate_against_chainspec(&chainspec, validator)),
+            vec!["network.max_message_size"]
+        );
+    }
+
+    #[test]
+    fn should_reject_cipher_suites_unsupported_by_transport() {
+        let config = Config {
+            transport: TransportKind::Quic,
+            tls_cipher_suites: vec!["TLS_AES_128_CCM_SHA256".to_string()],
+            ..Config::default()
+        };
+        assert_eq!(
+            violated_fields(|validator| config.validate(validator)),
+            vec!["network.tls_cipher_suites"]
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use std::{io, net::SocketAddr, result, time::SystemTimeError};
 
 use openssl::error::ErrorStack;
+use quinn::{ConnectError, ConnectionError, EndpointError};
+use rustls::TLSError;
+use semver::Version;
 use serde::Serialize;
 use thiserror::Error;
 use tokio::net::TcpStream;
@@ -74,6 +77,13 @@ pub enum Error {
         #[source]
         ErrorStack,
     ),
+    /// Failed to create TLS connector.
+    #[error("failed to create connector")]
+    ConnectorCreation(
+        #[serde(skip_serializing)]
+        #[source]
+        ErrorStack,
+    ),
     /// Failed to create configuration for TLS connector.
     #[error("failed to configure connector")]
     ConnectorConfiguration(
@@ -118,4 +128,64 @@ pub enum Error {
     /// Server has stopped.
     #[error("failed to create outgoing connection as server has stopped")]
     ServerStopped,
+    /// The peer did not complete the handshake in time.
+    #[error("handshake timed out")]
+    HandshakeTimeout,
+    /// The connection to the peer could not be established in time.
+    #[error("connection attempt timed out")]
+    ConnectionTimeout,
+    /// The peer is on a forked or an outdated network, see `ChainInfo`.
+    #[error(
+        "peer's chainspec is incompatible with ours (peer protocol version {protocol_version:?})"
+    )]
+    IncompatibleChain {
+        /// The protocol version the peer sent in its handshake, if any.
+        protocol_version: Option<Version>,
+    },
+    /// Failed to register metrics.
+    #[error("could not register metrics: {0}")]
+    Metrics(
+        #[serde(skip_serializing)]
+        #[from]
+        prometheus::Error,
+    ),
+    /// Failed to configure the QUIC TLS stack.
+    #[error("failed to configure QUIC: {0}")]
+    QuicConfiguration(
+        #[serde(skip_serializing)]
+        #[source]
+        TLSError,
+    ),
+    /// Failed to create a QUIC endpoint.
+    #[error("failed to create QUIC endpoint on {1}")]
+    QuicEndpoint(
+        #[serde(skip_serializing)]
+        #[source]
+        EndpointError,
+        SocketAddr,
+    ),
+    /// Failed to initiate a QUIC connection.
+    #[error("failed to initiate QUIC connection: {0}")]
+    QuicConnect(
+        #[serde(skip_serializing)]
+        #[source]
+        ConnectError,
+    ),
+    /// A QUIC connection failed or was closed.
+    #[error("QUIC connection error: {0}")]
+    QuicConnection(
+        #[serde(skip_serializing)]
+        #[source]
+        ConnectionError,
+    ),
+    /// Failed to open the stream of a QUIC connection.
+    #[error("failed to open QUIC stream: {0}")]
+    QuicStream(
+        #[serde(skip_serializing)]
+        #[source]
+        io::Error,
+    ),
+    /// The peer did not present a transport certificate followed by its node certificate.
+    #[error("invalid certificate chain presented")]
+    InvalidCertificateChain,
 }
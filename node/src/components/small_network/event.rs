@@ -6,9 +6,8 @@ use std::{
 
 use derive_more::From;
 use serde::Serialize;
-use tokio::net::TcpStream;
 
-use super::{Error, GossipedAddress, Message, NodeId, Transport};
+use super::{Error, GossipedAddress, IncomingStream, Message, NodeId, Transport};
 use crate::effect::requests::{NetworkInfoRequest, NetworkRequest};
 
 #[derive(Debug, From, Serialize)]
@@ -18,13 +17,13 @@ pub enum Event<P> {
         peer_address: SocketAddr,
         error: Error,
     },
-    /// A new TCP connection has been established from an incoming connection.
+    /// A new connection has been established from an incoming connection.
     IncomingNew {
         #[serde(skip_serializing)]
-        stream: TcpStream,
+        stream: IncomingStream,
         peer_address: SocketAddr,
     },
-    /// The TLS handshake completed on the incoming connection.
+    /// The transport handshake completed on the incoming connection.
     IncomingHandshakeCompleted {
         #[serde(skip_serializing)]
         result: Result<(NodeId, Transport), Error>,
@@ -38,6 +37,8 @@ pub enum Event<P> {
         result: io::Result<()>,
         peer_id: NodeId,
         peer_address: SocketAddr,
+        /// The generation of the connection.
+        generation: u64,
     },
 
     /// A new outgoing connection was successfully established.
@@ -51,6 +52,8 @@ pub enum Event<P> {
         peer_id: Option<NodeId>,
         peer_address: SocketAddr,
         error: Option<Error>,
+        /// The generation of the connection, zero if it was never established.
+        generation: u64,
     },
 
     /// Incoming network request.
@@ -67,6 +70,9 @@ pub enum Event<P> {
         req: NetworkInfoRequest<NodeId>,
     },
 
+    /// The drain period of a connection we or the peer said goodbye on has elapsed.
+    DrainPeriodElapsed { peer_id: NodeId },
+
     /// The node should gossip its own public listening address.
     GossipOurAddress,
     /// We received a peer's public listening address via gossip.
@@ -110,6 +116,7 @@ impl<P: Display> Display for Event<P> {
                 peer_id: Some(node_id),
                 peer_address,
                 error,
+                ..
             } => write!(
                 f,
                 "failed outgoing {} {}: (is_err {})",
@@ -121,6 +128,7 @@ impl<P: Display> Display for Event<P> {
                 peer_id: None,
                 peer_address,
                 error,
+                ..
             } => write!(
                 f,
                 "failed outgoing {}: (is_err {})",
@@ -129,6 +137,9 @@ impl<P: Display> Display for Event<P> {
             ),
             Event::NetworkRequest { req } => write!(f, "request: {}", req),
             Event::NetworkInfoRequest { req } => write!(f, "request: {}", req),
+            Event::DrainPeriodElapsed { peer_id } => {
+                write!(f, "drain period for {} elapsed", peer_id)
+            }
             Event::GossipOurAddress => write!(f, "gossip our address"),
             Event::PeerAddressReceived(gossiped_address) => {
                 write!(f, "received gossiped peer address {}", gossiped_address)
@@ -1,13 +1,238 @@
 use std::fmt::{self, Debug, Display, Formatter};
 
+use datasize::DataSize;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 
-use crate::crypto::hash::Digest;
+use casper_types::PublicKey;
 
+use crate::{
+    crypto::hash::Digest,
+    types::{FeatureFlags, SignedAttestation, Timestamp},
+};
+
+/// A message sent between peers.
+///
+/// Variants are encoded by their index, so new ones must be appended to stay compatible with peers
+/// running older versions.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum Message<P> {
-    Handshake { genesis_config_hash: Digest },
+    Handshake {
+        /// Hash of the genesis config of the sender.
+        genesis_config_hash: Digest,
+        /// The sender's current time, used to detect clock skew between peers.
+        ///
+        /// `None` if the peer runs an older version which does not send it, or if the handshake
+        /// is encoded for such a peer. Must also be `None` if `features` is, as fields are
+        /// encoded by position.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timestamp: Option<Timestamp>,
+        /// The sender's feature flags, informational only apart from advertising support for
+        /// streaming, see `streaming`.
+        ///
+        /// `None` if the peer runs an older version which does not send them, or if the handshake
+        /// is encoded for such a peer.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        features: Option<FeatureFlags>,
+        /// The latest protocol version the sender's chainspec supports.
+        ///
+        /// `None` if the peer runs an older version which does not send it, or if the handshake
+        /// is encoded for such a peer. Must also be `None` if `features` is.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        protocol_version: Option<Version>,
+    },
     Payload(P),
+    /// Announces that the sender is about to close the connection on purpose.
+    ///
+    /// Allows the recipient to tell a planned disconnect apart from a failure.
+    Goodbye {
+        /// Why the sender is disconnecting.
+        reason: DisconnectReason,
+    },
+    /// Asks the recipient for its current time, to measure the offset between our clocks.
+    TimeRequest {
+        /// The sender's time when sending the request.
+        origin: Timestamp,
+    },
+    /// Answers a `TimeRequest`.
+    TimeResponse {
+        /// The `origin` of the request.
+        origin: Timestamp,
+        /// The sender's time when handling the request.
+        received: Timestamp,
+        /// The sender's time when sending the response.
+        sent: Timestamp,
+    },
+    /// Starts a stream of the bytes split off a large payload, see `streaming`.
+    StreamStart {
+        /// Identifies the stream among those sent on the connection.
+        stream_id: u64,
+        /// The payload, without the streamed bytes.
+        payload: P,
+        /// The total number of bytes streamed.
+        total_len: u64,
+    },
+    /// A chunk of the bytes of a stream.
+    StreamChunk {
+        stream_id: u64,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    },
+    /// Completes a stream.
+    StreamEnd {
+        stream_id: u64,
+        /// The hash of all bytes streamed.
+        hash: Digest,
+    },
+    /// Abandons a stream, the chunks sent so far are to be discarded.
+    StreamCancel {
+        stream_id: u64,
+    },
+    /// Announces the public key the sender validates with, or that it has none.
+    ///
+    /// Only sent to peers advertising `VALIDATOR_KEY_FEATURE`, right after the handshake. The key
+    /// is not proven: a peer claiming a key it does not hold merely receives the messages meant
+    /// for the validator as well.
+    ValidatorKey {
+        public_key: Option<PublicKey>,
+    },
+    /// Attests the software the sending validator runs.
+    ///
+    /// Only sent to peers advertising `ATTESTATION_FEATURE`, right after the handshake, and only
+    /// if the sender is configured to attest its software.
+    Attestation {
+        attestation: Box<SignedAttestation>,
+    },
+}
+
+/// An encoding of the handshake, i.e. the set of fields it contains.
+///
+/// Peers running older versions reject handshakes containing fields they do not know, so the
+/// fields added since are left out when handshaking with them. Variants are ordered from oldest to
+/// newest.
+#[derive(Copy, Clone, DataSize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HandshakeEncoding {
+    /// The genesis config hash only, the encoding of the first release.
+    GenesisConfigHash,
+    /// Adds the timestamp.
+    Timestamped,
+    /// Adds the feature flags.
+    WithFeatures,
+    /// Adds the protocol version, the encoding of this version.
+    WithProtocolVersion,
+}
+
+impl HandshakeEncoding {
+    /// The encoding of this version.
+    pub const CURRENT: HandshakeEncoding = HandshakeEncoding::WithProtocolVersion;
+
+    /// The encodings of previous versions, newest first.
+    const ANCESTORS: &'static [HandshakeEncoding] = &[
+        HandshakeEncoding::WithFeatures,
+        HandshakeEncoding::Timestamped,
+        HandshakeEncoding::GenesisConfigHash,
+    ];
+
+    /// Returns the latest protocol version supported by the versions sending this encoding, or
+    /// `None` for the current encoding.
+    fn last_protocol_version(self) -> Option<Version> {
+        match self {
+            HandshakeEncoding::GenesisConfigHash
+            | HandshakeEncoding::Timestamped
+            | HandshakeEncoding::WithFeatures => Some(Version::new(1, 0, 0)),
+            HandshakeEncoding::WithProtocolVersion => None,
+        }
+    }
+
+    /// Returns the encodings of previous versions which this version can still handshake with on a
+    /// chain supporting protocol versions up to `protocol_version`, newest first.
+    ///
+    /// Peers sending these encodings do not send their protocol version, so they are only on our
+    /// chain if their chainspec is ours. Once our chainspec supports a protocol version their
+    /// version does not, they are on an outdated network and their encoding is not supported
+    /// anymore.
+    pub(super) fn supported_ancestors(protocol_version: &Version) -> Vec<HandshakeEncoding> {
+        HandshakeEncoding::ANCESTORS
+            .iter()
+            .copied()
+            .filter(|ancestor| {
+                ancestor
+                    .last_protocol_version()
+                    .map_or(true, |last| *protocol_version <= last)
+            })
+            .collect()
+    }
+
+    /// Returns the encoding of a received handshake, based on the fields it contains.
+    pub(super) fn of_handshake(
+        timestamp: &Option<Timestamp>,
+        features: &Option<FeatureFlags>,
+        protocol_version: &Option<Version>,
+    ) -> Self {
+        match (timestamp, features, protocol_version) {
+            (_, _, Some(_)) => HandshakeEncoding::WithProtocolVersion,
+            (_, Some(_), None) => HandshakeEncoding::WithFeatures,
+            (Some(_), None, None) => HandshakeEncoding::Timestamped,
+            (None, None, None) => HandshakeEncoding::GenesisConfigHash,
+        }
+    }
+
+    /// Returns the encoding to retry with after sending a handshake in this encoding to a peer
+    /// which responded with `peer_encoding`, i.e. the newest of the `supported` ancestors the peer
+    /// can decode.
+    ///
+    /// Returns `None` if the peer can decode this encoding already, or no ancestor is supported.
+    pub(super) fn downgrade_for(
+        self,
+        peer_encoding: HandshakeEncoding,
+        supported: &[HandshakeEncoding],
+    ) -> Option<Self> {
+        if peer_encoding >= self {
+            return None;
+        }
+        supported
+            .iter()
+            .copied()
+            .find(|ancestor| *ancestor <= peer_encoding)
+    }
+}
+
+impl Display for HandshakeEncoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeEncoding::GenesisConfigHash => write!(f, "genesis config hash"),
+            HandshakeEncoding::Timestamped => write!(f, "timestamped"),
+            HandshakeEncoding::WithFeatures => write!(f, "with features"),
+            HandshakeEncoding::WithProtocolVersion => write!(f, "with protocol version"),
+        }
+    }
+}
+
+/// The reason for a planned disconnect, sent in a `Goodbye` message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DisconnectReason {
+    /// The sender is shedding connections to reduce its load.
+    ///
+    /// Not sent by this version yet, but understood, so that upcoming versions can send it.
+    Shedding,
+    /// The sender is dropping a connection it considers redundant, e.g. because it was only
+    /// established in one direction.
+    Rebalancing,
+    /// The sender is incompatible with the recipient, e.g. it runs a different chain.
+    Incompatible,
+    /// The sender is shutting down.
+    Shutdown,
+}
+
+impl Display for DisconnectReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DisconnectReason::Shedding => write!(f, "shedding"),
+            DisconnectReason::Rebalancing => write!(f, "rebalancing"),
+            DisconnectReason::Incompatible => write!(f, "incompatible"),
+            DisconnectReason::Shutdown => write!(f, "shutdown"),
+        }
+    }
 }
 
 impl<P: Display> Display for Message<P> {
@@ -15,8 +240,164 @@ impl<P: Display> Display for Message<P> {
         match self {
             Message::Handshake {
                 genesis_config_hash,
+                timestamp: Some(timestamp),
+                ..
+            } => write!(
+                f,
+                "handshake: {}, sent at {}",
+                genesis_config_hash, timestamp
+            ),
+            Message::Handshake {
+                genesis_config_hash,
+                timestamp: None,
+                ..
             } => write!(f, "handshake: {}", genesis_config_hash),
             Message::Payload(payload) => write!(f, "payload: {}", payload),
+            Message::Goodbye { reason } => write!(f, "goodbye: {}", reason),
+            Message::TimeRequest { origin } => write!(f, "time request, sent at {}", origin),
+            Message::TimeResponse { origin, sent, .. } => write!(
+                f,
+                "time response to request sent at {}, sent at {}",
+                origin, sent
+            ),
+            Message::StreamStart {
+                stream_id,
+                payload,
+                total_len,
+            } => write!(
+                f,
+                "stream {} of {} bytes: {}",
+                stream_id, total_len, payload
+            ),
+            Message::StreamChunk { stream_id, data } => {
+                write!(f, "chunk of {} bytes of stream {}", data.len(), stream_id)
+            }
+            Message::StreamEnd { stream_id, hash } => {
+                write!(f, "end of stream {}, hash {}", stream_id, hash)
+            }
+            Message::StreamCancel { stream_id } => write!(f, "cancel stream {}", stream_id),
+            Message::ValidatorKey {
+                public_key: Some(public_key),
+            } => write!(f, "validator key: {}", public_key),
+            Message::ValidatorKey { public_key: None } => write!(f, "validator key: none"),
+            Message::Attestation { attestation } => write!(
+                f,
+                "attestation of {}: {:?}",
+                attestation.validator(),
+                attestation.attestation()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+    use crate::{
+        crypto::hash,
+        utils::bounded::{self, Limits},
+    };
+
+    /// The messages of the version which handshakes with the genesis config hash only.
+    #[derive(Serialize)]
+    enum BaselineMessage<P> {
+        Handshake { genesis_config_hash: Digest },
+        Payload(P),
+    }
+
+    #[test]
+    fn should_decode_baseline_handshake() {
+        let genesis_config_hash = hash::hash(b"genesis config");
+        let encoded = rmp_serde::to_vec(&BaselineMessage::<()>::Handshake {
+            genesis_config_hash,
+        })
+        .unwrap();
+
+        match bounded::from_msgpack(&encoded, Limits::NETWORK).unwrap() {
+            Message::<()>::Handshake {
+                genesis_config_hash: decoded_hash,
+                timestamp,
+                features,
+                protocol_version,
+            } => {
+                assert_eq!(decoded_hash, genesis_config_hash);
+                assert_eq!(timestamp, None);
+                assert!(features.is_none());
+                assert_eq!(protocol_version, None);
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn should_decode_baseline_payload() {
+        let payload = "payload".to_string();
+        let encoded = rmp_serde::to_vec(&BaselineMessage::Payload(payload.clone())).unwrap();
+
+        match bounded::from_msgpack(&encoded, Limits::NETWORK).unwrap() {
+            Message::<String>::Payload(decoded_payload) => assert_eq!(decoded_payload, payload),
+            other => panic!("unexpected message {:?}", other),
         }
     }
+
+    #[test]
+    fn should_downgrade_to_newest_ancestor_known_to_peer() {
+        let current = HandshakeEncoding::CURRENT;
+        let supported = HandshakeEncoding::supported_ancestors(&Version::new(1, 0, 0));
+        assert_eq!(current.downgrade_for(current, &supported), None);
+        assert_eq!(
+            current.downgrade_for(HandshakeEncoding::WithFeatures, &supported),
+            Some(HandshakeEncoding::WithFeatures)
+        );
+        assert_eq!(
+            current.downgrade_for(HandshakeEncoding::Timestamped, &supported),
+            Some(HandshakeEncoding::Timestamped)
+        );
+        assert_eq!(
+            current.downgrade_for(HandshakeEncoding::GenesisConfigHash, &supported),
+            Some(HandshakeEncoding::GenesisConfigHash)
+        );
+        // Already downgraded far enough.
+        assert_eq!(
+            HandshakeEncoding::Timestamped
+                .downgrade_for(HandshakeEncoding::WithFeatures, &supported),
+            None
+        );
+    }
+
+    #[test]
+    fn should_not_support_ancestors_once_chainspec_is_upgraded_past_them() {
+        let supported = HandshakeEncoding::supported_ancestors(&Version::new(1, 1, 0));
+        assert!(supported.is_empty());
+        assert_eq!(
+            HandshakeEncoding::CURRENT
+                .downgrade_for(HandshakeEncoding::GenesisConfigHash, &supported),
+            None
+        );
+    }
+
+    #[test]
+    fn should_detect_encoding_of_handshake() {
+        let timestamp = Some(Timestamp::now());
+        let version = Some(Version::new(1, 0, 0));
+        let features = Some(FeatureFlags::default());
+        assert_eq!(
+            HandshakeEncoding::of_handshake(&timestamp, &features, &version),
+            HandshakeEncoding::WithProtocolVersion
+        );
+        assert_eq!(
+            HandshakeEncoding::of_handshake(&timestamp, &features, &None),
+            HandshakeEncoding::WithFeatures
+        );
+        assert_eq!(
+            HandshakeEncoding::of_handshake(&timestamp, &None, &None),
+            HandshakeEncoding::Timestamped
+        );
+        assert_eq!(
+            HandshakeEncoding::of_handshake(&None, &None, &None),
+            HandshakeEncoding::GenesisConfigHash
+        );
+    }
 }
@@ -0,0 +1,297 @@
+use prometheus::{IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry};
+
+use super::outgoing_state::{OutgoingState, OutgoingStates, Transition};
+use crate::types::{NodeId, ProtocolVersionHistogram, Timestamp};
+
+/// Metrics for the small network component.
+#[derive(Debug)]
+pub struct NetworkMetrics {
+    /// Clock skew of each connected peer relative to our clock, in milliseconds.
+    ///
+    /// Positive values indicate the peer's clock is ahead of ours.
+    pub(super) peer_clock_skew: IntGaugeVec,
+    /// Number of connected peers per advertised protocol version.
+    pub(super) peers_by_protocol_version: IntGaugeVec,
+    /// Estimated offset of the median clock of connected peers relative to ours, in milliseconds.
+    pub(super) network_time_offset: IntGauge,
+    /// Serialized size of the messages sent to each peer, in bytes.
+    peer_bytes_sent: IntCounterVec,
+    /// Serialized size of the messages received from each peer, in bytes.
+    peer_bytes_received: IntCounterVec,
+    /// Number of messages sent to each peer.
+    peer_messages_sent: IntCounterVec,
+    /// Number of messages received from each peer.
+    peer_messages_received: IntCounterVec,
+    /// Time at which the connection to each peer was completed, in seconds since the Unix epoch.
+    ///
+    /// The uptime of a connection is the current time minus this value.
+    peer_connected_since: IntGaugeVec,
+    /// Number of times accepting incoming connections was paused for lack of local resources.
+    pub(super) accept_resource_exhaustion: IntCounter,
+    /// Number of completed TLS handshakes, by direction and whether a session was resumed.
+    pub(super) tls_handshakes: IntCounterVec,
+    /// Number of addresses in each state of the outgoing connection, see `OutgoingState`.
+    outgoing_states: IntGaugeVec,
+    /// Number of changes of the state of an outgoing connection, by previous and new state.
+    outgoing_transitions: IntCounterVec,
+    /// Reference to the registry for unregistering.
+    registry: Registry,
+}
+
+/// Traffic counters of a single peer, updated by its connections as messages are encoded and
+/// decoded.
+#[derive(Clone, Debug)]
+pub(super) struct PeerTraffic {
+    bytes_sent: IntCounter,
+    bytes_received: IntCounter,
+    messages_sent: IntCounter,
+    messages_received: IntCounter,
+}
+
+impl PeerTraffic {
+    /// Records a message of `size` bytes sent to the peer.
+    pub(super) fn record_sent(&self, size: usize) {
+        self.bytes_sent.inc_by(size as u64);
+        self.messages_sent.inc();
+    }
+
+    /// Records a message of `size` bytes received from the peer.
+    pub(super) fn record_received(&self, size: usize) {
+        self.bytes_received.inc_by(size as u64);
+        self.messages_received.inc();
+    }
+}
+
+impl NetworkMetrics {
+    /// Creates a new instance of small network metrics.
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let peer_clock_skew = IntGaugeVec::new(
+            Opts::new(
+                "net_peer_clock_skew_ms",
+                "clock skew of a peer relative to our clock as measured during the handshake, in \
+                 milliseconds",
+            ),
+            &["peer"],
+        )?;
+
+        let peers_by_protocol_version = IntGaugeVec::new(
+            Opts::new(
+                "net_peers_by_protocol_version",
+                "number of connected peers advertising a protocol version in their handshake",
+            ),
+            &["version"],
+        )?;
+
+        let network_time_offset = IntGauge::new(
+            "net_network_time_offset_ms",
+            "estimated offset of the median clock of connected peers relative to our clock, \
+             corrected for round-trip times, in milliseconds",
+        )?;
+
+        let peer_bytes_sent = IntCounterVec::new(
+            Opts::new(
+                "net_peer_bytes_sent",
+                "serialized size of the messages sent to a peer, in bytes",
+            ),
+            &["peer"],
+        )?;
+        let peer_bytes_received = IntCounterVec::new(
+            Opts::new(
+                "net_peer_bytes_received",
+                "serialized size of the messages received from a peer, in bytes",
+            ),
+            &["peer"],
+        )?;
+        let peer_messages_sent = IntCounterVec::new(
+            Opts::new(
+                "net_peer_messages_sent",
+                "number of messages sent to a peer",
+            ),
+            &["peer"],
+        )?;
+        let peer_messages_received = IntCounterVec::new(
+            Opts::new(
+                "net_peer_messages_received",
+                "number of messages received from a peer",
+            ),
+            &["peer"],
+        )?;
+        let peer_connected_since = IntGaugeVec::new(
+            Opts::new(
+                "net_peer_connected_since_seconds",
+                "time at which the connection to a peer was completed, in seconds since the unix \
+                 epoch",
+            ),
+            &["peer"],
+        )?;
+        let accept_resource_exhaustion = IntCounter::new(
+            "net_accept_resource_exhaustion",
+            "number of times accepting incoming connections was paused for lack of local \
+             resources, e.g. file descriptors",
+        )?;
+        let tls_handshakes = IntCounterVec::new(
+            Opts::new(
+                "net_tls_handshakes",
+                "number of completed tls handshakes, by direction and whether they resumed an \
+                 earlier session or were full handshakes",
+            ),
+            &["direction", "kind"],
+        )?;
+
+        let outgoing_states = IntGaugeVec::new(
+            Opts::new(
+                "net_outgoing_states",
+                "number of addresses in each state of the outgoing connection to them",
+            ),
+            &["state"],
+        )?;
+        let outgoing_transitions = IntCounterVec::new(
+            Opts::new(
+                "net_outgoing_transitions",
+                "number of changes of the state of an outgoing connection, by previous and new \
+                 state",
+            ),
+            &["from", "to"],
+        )?;
+
+        registry.register(Box::new(peer_clock_skew.clone()))?;
+        registry.register(Box::new(peers_by_protocol_version.clone()))?;
+        registry.register(Box::new(network_time_offset.clone()))?;
+        registry.register(Box::new(peer_bytes_sent.clone()))?;
+        registry.register(Box::new(peer_bytes_received.clone()))?;
+        registry.register(Box::new(peer_messages_sent.clone()))?;
+        registry.register(Box::new(peer_messages_received.clone()))?;
+        registry.register(Box::new(peer_connected_since.clone()))?;
+        registry.register(Box::new(accept_resource_exhaustion.clone()))?;
+        registry.register(Box::new(tls_handshakes.clone()))?;
+        registry.register(Box::new(outgoing_states.clone()))?;
+        registry.register(Box::new(outgoing_transitions.clone()))?;
+
+        Ok(NetworkMetrics {
+            peer_clock_skew,
+            peers_by_protocol_version,
+            network_time_offset,
+            peer_bytes_sent,
+            peer_bytes_received,
+            peer_messages_sent,
+            peer_messages_received,
+            peer_connected_since,
+            accept_resource_exhaustion,
+            tls_handshakes,
+            outgoing_states,
+            outgoing_transitions,
+            registry: registry.clone(),
+        })
+    }
+
+    /// Returns the traffic counters of a peer, to be handed to its connections.
+    pub(super) fn peer_traffic(&self, peer_id: &NodeId) -> PeerTraffic {
+        let label = peer_id.to_string();
+        PeerTraffic {
+            bytes_sent: self.peer_bytes_sent.with_label_values(&[&label]),
+            bytes_received: self.peer_bytes_received.with_label_values(&[&label]),
+            messages_sent: self.peer_messages_sent.with_label_values(&[&label]),
+            messages_received: self.peer_messages_received.with_label_values(&[&label]),
+        }
+    }
+
+    /// Records that the connection to a peer is now complete.
+    pub(super) fn set_peer_connected(&self, peer_id: &NodeId) {
+        self.peer_connected_since
+            .with_label_values(&[&peer_id.to_string()])
+            .set((Timestamp::now().millis() / 1000) as i64);
+    }
+
+    /// Records the measured clock skew of a peer.
+    pub(super) fn set_peer_clock_skew(&self, peer_id: &NodeId, skew_ms: i64) {
+        self.peer_clock_skew
+            .with_label_values(&[&peer_id.to_string()])
+            .set(skew_ms);
+    }
+
+    /// Replaces the per-version peer counts with those of `histogram`.
+    pub(super) fn set_peer_protocol_versions(&self, histogram: &ProtocolVersionHistogram) {
+        // Versions no peer advertises anymore must not linger with a stale count.
+        self.peers_by_protocol_version.reset();
+        for (version, peers) in histogram.iter() {
+            self.peers_by_protocol_version
+                .with_label_values(&[&version.to_string()])
+                .set(peers as i64);
+        }
+        self.peers_by_protocol_version
+            .with_label_values(&["unknown"])
+            .set(histogram.unknown() as i64);
+    }
+
+    /// Records a change of the state of an outgoing connection, with `states` after it.
+    pub(super) fn record_outgoing_transition(
+        &self,
+        transition: Transition,
+        states: &OutgoingStates,
+    ) {
+        self.outgoing_transitions
+            .with_label_values(&[
+                OutgoingState::name(transition.from),
+                OutgoingState::name(transition.to),
+            ])
+            .inc();
+        for state in OutgoingState::ALL.iter() {
+            self.outgoing_states
+                .with_label_values(&[state.as_str()])
+                .set(states.count(*state) as i64);
+        }
+    }
+
+    /// Removes all per-peer metrics of a peer that is no longer connected.
+    pub(super) fn remove_peer(&self, peer_id: &NodeId) {
+        // Each removal fails if no value has been recorded for the peer yet, which is fine.
+        let label = peer_id.to_string();
+        let _ = self.peer_clock_skew.remove_label_values(&[&label]);
+        let _ = self.peer_bytes_sent.remove_label_values(&[&label]);
+        let _ = self.peer_bytes_received.remove_label_values(&[&label]);
+        let _ = self.peer_messages_sent.remove_label_values(&[&label]);
+        let _ = self.peer_messages_received.remove_label_values(&[&label]);
+        let _ = self.peer_connected_since.remove_label_values(&[&label]);
+    }
+}
+
+impl Drop for NetworkMetrics {
+    fn drop(&mut self) {
+        self.registry
+            .unregister(Box::new(self.peer_clock_skew.clone()))
+            .expect("did not expect deregistering peer_clock_skew to fail");
+        self.registry
+            .unregister(Box::new(self.peers_by_protocol_version.clone()))
+            .expect("did not expect deregistering peers_by_protocol_version to fail");
+        self.registry
+            .unregister(Box::new(self.network_time_offset.clone()))
+            .expect("did not expect deregistering network_time_offset to fail");
+        self.registry
+            .unregister(Box::new(self.peer_bytes_sent.clone()))
+            .expect("did not expect deregistering peer_bytes_sent to fail");
+        self.registry
+            .unregister(Box::new(self.peer_bytes_received.clone()))
+            .expect("did not expect deregistering peer_bytes_received to fail");
+        self.registry
+            .unregister(Box::new(self.peer_messages_sent.clone()))
+            .expect("did not expect deregistering peer_messages_sent to fail");
+        self.registry
+            .unregister(Box::new(self.peer_messages_received.clone()))
+            .expect("did not expect deregistering peer_messages_received to fail");
+        self.registry
+            .unregister(Box::new(self.peer_connected_since.clone()))
+            .expect("did not expect deregistering peer_connected_since to fail");
+        self.registry
+            .unregister(Box::new(self.accept_resource_exhaustion.clone()))
+            .expect("did not expect deregistering accept_resource_exhaustion to fail");
+        self.registry
+            .unregister(Box::new(self.tls_handshakes.clone()))
+            .expect("did not expect deregistering tls_handshakes to fail");
+        self.registry
+            .unregister(Box::new(self.outgoing_states.clone()))
+            .expect("did not expect deregistering outgoing_states to fail");
+        self.registry
+            .unregister(Box::new(self.outgoing_transitions.clone()))
+            .expect("did not expect deregistering outgoing_transitions to fail");
+    }
+}
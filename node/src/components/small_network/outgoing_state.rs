@@ -0,0 +1,134 @@
+//! The states of the outgoing connections, by address.
+//!
+//! The small network keeps what it knows about an address it connects to in several places: the
+//! pending connection attempts, the established connections, the blocklist and the reconnect
+//! backoff. For monitoring, these are summarized as a single state per address, see
+//! `OutgoingState`, updated whenever one of them changes. Addresses in none of them have no state.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    net::SocketAddr,
+};
+
+use datasize::DataSize;
+
+/// The state of the outgoing connection to an address.
+#[derive(Clone, Copy, DataSize, Debug, PartialEq, Eq, Hash)]
+pub(super) enum OutgoingState {
+    /// The last attempt to connect failed, the next one is held off.
+    Waiting,
+    /// An attempt to connect is in progress.
+    Connecting,
+    /// The connection is established.
+    Connected,
+    /// The address is on the blocklist.
+    Blocked,
+}
+
+impl OutgoingState {
+    /// All states, in the order the connection to an address usually goes through them.
+    pub(super) const ALL: [OutgoingState; 4] = [
+        OutgoingState::Waiting,
+        OutgoingState::Connecting,
+        OutgoingState::Connected,
+        OutgoingState::Blocked,
+    ];
+
+    /// Returns the name of the state, as used in metric labels.
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            OutgoingState::Waiting => "waiting",
+            OutgoingState::Connecting => "connecting",
+            OutgoingState::Connected => "connected",
+            OutgoingState::Blocked => "blocked",
+        }
+    }
+
+    /// Returns the name of `state`, or "none" for no state.
+    pub(super) fn name(state: Option<OutgoingState>) -> &'static str {
+        state.map_or("none", OutgoingState::as_str)
+    }
+}
+
+impl Display for OutgoingState {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+/// A change of the state of an address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct Transition {
+    pub(super) from: Option<OutgoingState>,
+    pub(super) to: Option<OutgoingState>,
+}
+
+/// The states of all addresses which have one.
+#[derive(DataSize, Debug, Default)]
+pub(super) struct OutgoingStates(HashMap<SocketAddr, OutgoingState>);
+
+impl OutgoingStates {
+    /// Sets the state of `address`, returning the transition if it changed.
+    pub(super) fn set(
+        &mut self,
+        address: SocketAddr,
+        state: Option<OutgoingState>,
+    ) -> Option<Transition> {
+        let from = match state {
+            Some(state) => self.0.insert(address, state),
+            None => self.0.remove(&address),
+        };
+        if from == state {
+            None
+        } else {
+            Some(Transition { from, to: state })
+        }
+    }
+
+    /// Returns the number of addresses in `state`.
+    pub(super) fn count(&self, state: OutgoingState) -> usize {
+        self.0.values().filter(|&&value| value == state).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_changes_only() {
+        let mut states = OutgoingStates::default();
+        let address: SocketAddr = "127.0.0.1:34553".parse().unwrap();
+        let other: SocketAddr = "127.0.0.2:34553".parse().unwrap();
+
+        assert_eq!(
+            states.set(address, Some(OutgoingState::Connecting)),
+            Some(Transition {
+                from: None,
+                to: Some(OutgoingState::Connecting),
+            })
+        );
+        assert_eq!(states.set(address, Some(OutgoingState::Connecting)), None);
+        assert_eq!(states.set(other, None), None);
+        let _ = states.set(other, Some(OutgoingState::Connecting));
+        assert_eq!(states.count(OutgoingState::Connecting), 2);
+
+        assert_eq!(
+            states.set(address, Some(OutgoingState::Waiting)),
+            Some(Transition {
+                from: Some(OutgoingState::Connecting),
+                to: Some(OutgoingState::Waiting),
+            })
+        );
+        assert_eq!(
+            states.set(other, None),
+            Some(Transition {
+                from: Some(OutgoingState::Connecting),
+                to: None,
+            })
+        );
+        assert_eq!(states.count(OutgoingState::Connecting), 0);
+        assert_eq!(states.count(OutgoingState::Waiting), 1);
+    }
+}
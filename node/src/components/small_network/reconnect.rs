@@ -0,0 +1,124 @@
+//! Backoff for reconnecting to addresses which outgoing connections failed to.
+//!
+//! On every gossip round a node learns the addresses of its peers again, and would try to connect
+//! to every one it is not connected to. Instead, after a failed attempt to connect to an address,
+//! the next attempt is held off for a delay which doubles with every consecutive failure up to a
+//! maximum. The delay is randomized, so nodes which lost the same peer do not all retry at once. A
+//! successful connection resets the delay.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use datasize::DataSize;
+use rand::Rng;
+
+/// The consecutive failed attempts to connect to an address.
+#[derive(DataSize, Debug)]
+struct Failures {
+    /// Number of consecutive failed attempts.
+    count: u32,
+    /// Time before which no further attempt is made.
+    #[data_size(skip)]
+    retry_after: Instant,
+}
+
+/// The delays before reconnecting to addresses, by address.
+#[derive(DataSize, Debug)]
+pub(super) struct ReconnectBackoff {
+    /// Delay after the first failed attempt, before randomization.
+    base_delay: Duration,
+    /// Upper bound of the delay.
+    max_delay: Duration,
+    /// The addresses the latest attempts to connect to failed.
+    failures: HashMap<SocketAddr, Failures>,
+}
+
+impl ReconnectBackoff {
+    pub(super) fn new(base_delay: Duration, max_delay: Duration) -> Self {
+        ReconnectBackoff {
+            base_delay,
+            max_delay,
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Records a failed attempt to connect to `address`.
+    ///
+    /// Returns the number of consecutive failures and the time until the next attempt.
+    pub(super) fn failed<R: Rng + ?Sized>(
+        &mut self,
+        address: SocketAddr,
+        now: Instant,
+        rng: &mut R,
+    ) -> (u32, Duration) {
+        let failures = self.failures.entry(address).or_insert(Failures {
+            count: 0,
+            retry_after: now,
+        });
+        failures.count = failures.count.saturating_add(1);
+
+        // Pick a delay between half and all of the exponential delay.
+        let delay = exponential_delay(self.base_delay, self.max_delay, failures.count);
+        let half_millis = delay.as_millis() as u64 / 2;
+        let delay = Duration::from_millis(half_millis + rng.gen_range(0, half_millis + 1));
+        failures.retry_after = now + delay;
+        (failures.count, delay)
+    }
+
+    /// Records a successful connection to `address`, resetting its delay.
+    pub(super) fn succeeded(&mut self, address: SocketAddr) {
+        let _ = self.failures.remove(&address);
+    }
+
+    /// Returns whether no attempt should be made to connect to `address` yet.
+    pub(super) fn is_backing_off(&self, address: SocketAddr, now: Instant) -> bool {
+        self.failures
+            .get(&address)
+            .map_or(false, |failures| now < failures.retry_after)
+    }
+}
+
+/// Returns `base_delay` doubled for every failure after the first, but at most `max_delay`.
+fn exponential_delay(base_delay: Duration, max_delay: Duration, failures: u32) -> Duration {
+    let factor = 1u32
+        .checked_shl(failures.saturating_sub(1))
+        .unwrap_or(u32::MAX);
+    base_delay
+        .checked_mul(factor)
+        .map_or(max_delay, |delay| delay.min(max_delay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestRng;
+
+    #[test]
+    fn should_back_off_exponentially_up_to_max_delay() {
+        let mut rng = TestRng::new();
+        let base_delay = Duration::from_secs(1);
+        let max_delay = Duration::from_secs(10);
+        let mut backoff = ReconnectBackoff::new(base_delay, max_delay);
+        let address: SocketAddr = "127.0.0.1:34553".parse().unwrap();
+        let now = Instant::now();
+        assert!(!backoff.is_backing_off(address, now));
+
+        for (attempt, expected_secs) in [1, 2, 4, 8, 10, 10].iter().enumerate() {
+            let (failures, delay) = backoff.failed(address, now, &mut rng);
+            assert_eq!(failures as usize, attempt + 1);
+            let expected = Duration::from_secs(*expected_secs);
+            assert!(delay >= expected / 2 && delay <= expected);
+            assert!(backoff.is_backing_off(address, now));
+            assert!(!backoff.is_backing_off(address, now + delay));
+        }
+        assert_eq!(exponential_delay(base_delay, max_delay, 100), max_delay);
+
+        backoff.succeeded(address);
+        assert!(!backoff.is_backing_off(address, now));
+        let (failures, _) = backoff.failed(address, now, &mut rng);
+        assert_eq!(failures, 1);
+    }
+}
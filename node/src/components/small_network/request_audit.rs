@@ -0,0 +1,200 @@
+//! Audit trail and quotas of the data requests peers send.
+//!
+//! Serving blocks and deploys costs disk reads and bandwidth, which a crawler can consume without
+//! contributing anything. Every data request is therefore counted per peer and cost class over a
+//! sliding window, and dropped if the peer exceeds the quota configured for the class. Quotas are
+//! disabled by default, since joining nodes legitimately fetch many blocks while syncing; the
+//! counts are reported regardless, so operators can spot freeloaders first.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use datasize::DataSize;
+
+use super::Config;
+use crate::types::{NodeId, PeerRequestAudit, RequestClass, RequestClassAudit};
+
+/// Maximum number of peers audited, beyond which peers without requests in the current window are
+/// forgotten.
+const MAX_AUDITED_PEERS: usize = 1024;
+
+/// A payload which may be a data request.
+pub(crate) trait RequestPayload {
+    /// Returns the cost class of the payload if it is a data request to be audited.
+    fn request_class(&self) -> Option<RequestClass>;
+}
+
+/// The requests of one class a peer sent.
+#[derive(DataSize, Debug, Default)]
+struct ClassLog {
+    /// When the requests served within the window were received, oldest first.
+    #[data_size(skip)]
+    recent: VecDeque<Instant>,
+    /// Requests served in total.
+    served: u64,
+    /// Requests dropped in total.
+    throttled: u64,
+}
+
+/// The data requests sent by every peer.
+#[derive(DataSize, Debug)]
+pub(super) struct RequestAudit {
+    /// Length of the sliding window quotas apply to.
+    window: Duration,
+    /// Maximum number of requests served per window and class. Unlimited classes are missing.
+    quotas: BTreeMap<RequestClass, u32>,
+    /// The requests per peer.
+    peers: HashMap<NodeId, BTreeMap<RequestClass, ClassLog>>,
+}
+
+impl RequestAudit {
+    /// Creates an audit with the window and quotas from the network configuration.
+    pub(super) fn new(cfg: &Config) -> Self {
+        let quotas = [
+            (RequestClass::Deploy, cfg.deploy_request_quota),
+            (RequestClass::Block, cfg.block_request_quota),
+        ]
+        .iter()
+        .filter(|(_, quota)| *quota > 0)
+        .copied()
+        .collect();
+        RequestAudit {
+            window: cfg.request_quota_window,
+            quotas,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Records a request of `class` from `peer_id` received at `now`, returning whether it is to be
+    /// served.
+    pub(super) fn admit(&mut self, peer_id: &NodeId, class: RequestClass, now: Instant) -> bool {
+        if !self.peers.contains_key(peer_id) && self.peers.len() >= MAX_AUDITED_PEERS {
+            self.forget_idle(now);
+        }
+        let window = self.window;
+        let log = self
+            .peers
+            .entry(peer_id.clone())
+            .or_default()
+            .entry(class)
+            .or_default();
+        while let Some(received) = log.recent.front() {
+            if now.saturating_duration_since(*received) < window {
+                break;
+            }
+            log.recent.pop_front();
+        }
+        match self.quotas.get(&class) {
+            Some(quota) if log.recent.len() >= *quota as usize => {
+                log.throttled += 1;
+                false
+            }
+            _ => {
+                log.recent.push_back(now);
+                log.served += 1;
+                true
+            }
+        }
+    }
+
+    /// Returns the requests of every audited peer at `now`, busiest peers first.
+    pub(super) fn snapshot(&self, now: Instant) -> Vec<PeerRequestAudit> {
+        let mut peers: Vec<_> = self
+            .peers
+            .iter()
+            .map(|(peer_id, classes)| PeerRequestAudit {
+                node_id: peer_id.to_string(),
+                classes: classes
+                    .iter()
+                    .map(|(class, log)| RequestClassAudit {
+                        class: *class,
+                        in_window: self.in_window(log, now) as u32,
+                        quota: self.quotas.get(class).copied(),
+                        served: log.served,
+                        throttled: log.throttled,
+                    })
+                    .collect(),
+            })
+            .collect();
+        peers.sort_by(|a, b| b.in_window().cmp(&a.in_window()));
+        peers
+    }
+
+    /// Returns the number of requests in `log` received within the window ending at `now`.
+    fn in_window(&self, log: &ClassLog, now: Instant) -> usize {
+        log.recent
+            .iter()
+            .filter(|received| now.saturating_duration_since(**received) < self.window)
+            .count()
+    }
+
+    /// Forgets the peers which sent no request within the window ending at `now`.
+    fn forget_idle(&mut self, now: Instant) {
+        let window = self.window;
+        self.peers.retain(|_, classes| {
+            classes.values().any(|log| {
+                log.recent.back().map_or(false, |received| {
+                    now.saturating_duration_since(*received) < window
+                })
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_throttle_requests_beyond_quota_within_window() {
+        let mut rng = crate::new_rng();
+        let peer_id = NodeId::random(&mut rng);
+        let cfg = Config {
+            request_quota_window: Duration::from_secs(10),
+            block_request_quota: 2,
+            ..Default::default()
+        };
+        let mut audit = RequestAudit::new(&cfg);
+        let start = Instant::now();
+
+        assert!(audit.admit(&peer_id, RequestClass::Block, start));
+        assert!(audit.admit(&peer_id, RequestClass::Block, start));
+        assert!(!audit.admit(&peer_id, RequestClass::Block, start));
+        // Deploys are not limited.
+        for _ in 0..10 {
+            assert!(audit.admit(&peer_id, RequestClass::Deploy, start));
+        }
+        // Once the window has passed, blocks are served again.
+        assert!(audit.admit(
+            &peer_id,
+            RequestClass::Block,
+            start + Duration::from_secs(10)
+        ));
+
+        let snapshot = audit.snapshot(start + Duration::from_secs(10));
+        assert_eq!(snapshot.len(), 1);
+        let classes = &snapshot[0].classes;
+        assert_eq!(
+            classes[0],
+            RequestClassAudit {
+                class: RequestClass::Deploy,
+                in_window: 0,
+                quota: None,
+                served: 10,
+                throttled: 0,
+            }
+        );
+        assert_eq!(
+            classes[1],
+            RequestClassAudit {
+                class: RequestClass::Block,
+                in_window: 1,
+                quota: Some(2),
+                served: 3,
+                throttled: 1,
+            }
+        );
+    }
+}
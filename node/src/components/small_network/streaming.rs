@@ -0,0 +1,331 @@
+//! Streaming of large payloads.
+//!
+//! Some payloads, like responses carrying a trie, can be tens of megabytes in size. Sent as a
+//! single message, such a payload is serialized into one frame on the sending side, and the whole
+//! frame is buffered before being decoded on the receiving side, while no other message can be
+//! sent on the connection.
+//!
+//! Instead, the bytes of a designated large payload, see `LargePayload`, are split off and sent
+//! after it as a stream of chunks: a `StreamStart` message carries the payload without the bytes,
+//! followed by `StreamChunk` messages and a final `StreamEnd` message with the hash of the bytes,
+//! which both sides compute incrementally as the chunks pass. The receiving side appends the chunks
+//! to the payload as they arrive, without buffering frames of the full size, and only hands the
+//! payload on once the hash has been checked.
+//!
+//! The sender may abandon a stream midway by sending `StreamCancel`, which makes the receiver
+//! discard the chunks received so far. This happens once the node starts shutting down, so the
+//! messages queued behind the stream are not held up by it.
+//!
+//! Streams are only sent to peers which advertise the `message_streaming` feature flag in their
+//! handshake, as older versions cannot decode the stream messages.
+
+use std::mem;
+
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
+use thiserror::Error;
+
+use super::Message;
+use crate::crypto::hash::Digest;
+
+/// Name of the feature flag advertising that a node can receive streams.
+pub(crate) const STREAMING_FEATURE: &str = "message_streaming";
+
+/// The maximum total length of a stream accepted from a peer.
+const MAX_STREAM_LENGTH: u64 = 128 * 1024 * 1024;
+
+/// A payload which may carry bytes large enough to be streamed.
+pub(crate) trait LargePayload {
+    /// Returns the bytes to be streamed if the payload is of a designated large payload type.
+    fn stream_data(&mut self) -> Option<&mut Vec<u8>>;
+}
+
+/// A stream which failed, making the connection it was received on unusable.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(super) enum StreamError {
+    /// A stream was announced to be longer than `MAX_STREAM_LENGTH`.
+    #[error("stream of {0} bytes exceeds the maximum length")]
+    TooLong(u64),
+    /// A message referred to a stream other than the current one.
+    #[error("message for unknown stream {0}")]
+    UnknownStream(u64),
+    /// The payload of a stream has no bytes to stream.
+    #[error("payload of stream {0} cannot be streamed")]
+    NotStreamable(u64),
+    /// The chunks received do not add up to the announced length.
+    #[error("received {received} bytes of a stream of {expected} bytes")]
+    LengthMismatch { expected: u64, received: u64 },
+    /// The hash of the chunks received does not match the one sent at the end.
+    #[error("hash mismatch of streamed bytes")]
+    HashMismatch,
+}
+
+/// Incrementally computes the hash of the bytes of a stream.
+pub(super) struct StreamHasher(VarBlake2b);
+
+impl StreamHasher {
+    pub(super) fn new() -> Self {
+        StreamHasher(VarBlake2b::new(Digest::LENGTH).expect("should create hasher"))
+    }
+
+    pub(super) fn update(&mut self, chunk: &[u8]) {
+        self.0.update(chunk);
+    }
+
+    /// Returns the hash of all bytes passed so far, equal to `crypto::hash::hash` of them.
+    pub(super) fn finalize(self) -> Digest {
+        let mut result = [0; Digest::LENGTH];
+        self.0
+            .finalize_variable(|slice| result.copy_from_slice(slice));
+        Digest::from(result)
+    }
+}
+
+/// Splits the bytes off a payload in `message` if there are more than `chunk_size` of them.
+///
+/// A `chunk_size` of zero disables streaming.
+pub(super) fn split_stream<P: LargePayload>(
+    message: &mut Message<P>,
+    chunk_size: usize,
+) -> Option<Vec<u8>> {
+    match message {
+        Message::Payload(payload) if chunk_size > 0 => match payload.stream_data() {
+            Some(data) if data.len() > chunk_size => Some(mem::take(data)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A stream being received.
+struct PartialStream<P> {
+    stream_id: u64,
+    payload: P,
+    total_len: u64,
+    data: Vec<u8>,
+    hasher: StreamHasher,
+}
+
+/// Reassembles the streams received on a single connection.
+///
+/// Stream messages are consumed, all other messages are passed through unchanged. As a sender
+/// writes a single stream at a time, a `StreamStart` discards a stream still in progress.
+pub(super) struct StreamAssembler<P> {
+    current: Option<PartialStream<P>>,
+}
+
+impl<P> Default for StreamAssembler<P> {
+    fn default() -> Self {
+        StreamAssembler { current: None }
+    }
+}
+
+impl<P: LargePayload> StreamAssembler<P> {
+    /// Processes a received message, returning the message to hand on, if any.
+    pub(super) fn receive(&mut self, msg: Message<P>) -> Result<Option<Message<P>>, StreamError> {
+        match msg {
+            Message::StreamStart {
+                stream_id,
+                payload,
+                total_len,
+            } => {
+                if total_len > MAX_STREAM_LENGTH {
+                    return Err(StreamError::TooLong(total_len));
+                }
+                // The announced length is untrusted, so nothing is allocated for it up front.
+                self.current = Some(PartialStream {
+                    stream_id,
+                    payload,
+                    total_len,
+                    data: Vec::new(),
+                    hasher: StreamHasher::new(),
+                });
+                Ok(None)
+            }
+            Message::StreamChunk { stream_id, data } => {
+                let stream = self.current_stream(stream_id)?;
+                let received = (stream.data.len() + data.len()) as u64;
+                if received > stream.total_len {
+                    return Err(StreamError::LengthMismatch {
+                        expected: stream.total_len,
+                        received,
+                    });
+                }
+                stream.hasher.update(&data);
+                stream.data.extend_from_slice(&data);
+                Ok(None)
+            }
+            Message::StreamEnd { stream_id, hash } => {
+                self.current_stream(stream_id)?;
+                let mut stream = self.current.take().expect("current stream should exist");
+                let received = stream.data.len() as u64;
+                if received != stream.total_len {
+                    return Err(StreamError::LengthMismatch {
+                        expected: stream.total_len,
+                        received,
+                    });
+                }
+                if stream.hasher.finalize() != hash {
+                    return Err(StreamError::HashMismatch);
+                }
+                match stream.payload.stream_data() {
+                    Some(data) => *data = stream.data,
+                    None => return Err(StreamError::NotStreamable(stream_id)),
+                }
+                Ok(Some(Message::Payload(stream.payload)))
+            }
+            Message::StreamCancel { stream_id } => {
+                self.current_stream(stream_id)?;
+                self.current = None;
+                Ok(None)
+            }
+            other => Ok(Some(other)),
+        }
+    }
+
+    fn current_stream(&mut self, stream_id: u64) -> Result<&mut PartialStream<P>, StreamError> {
+        match self.current.as_mut() {
+            Some(stream) if stream.stream_id == stream_id => Ok(stream),
+            _ => Err(StreamError::UnknownStream(stream_id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash;
+
+    #[derive(Debug, PartialEq)]
+    struct Blob(Vec<u8>);
+
+    impl LargePayload for Blob {
+        fn stream_data(&mut self) -> Option<&mut Vec<u8>> {
+            Some(&mut self.0)
+        }
+    }
+
+    fn chunks(
+        stream_id: u64,
+        data: &[u8],
+        chunk_size: usize,
+    ) -> impl Iterator<Item = Message<Blob>> + '_ {
+        data.chunks(chunk_size)
+            .map(move |chunk| Message::StreamChunk {
+                stream_id,
+                data: chunk.to_vec(),
+            })
+    }
+
+    #[test]
+    fn should_reassemble_split_payload() {
+        let data: Vec<u8> = (0..10_000u32).map(|byte| byte as u8).collect();
+        let mut message = Message::Payload(Blob(data.clone()));
+        assert!(split_stream(&mut message, data.len()).is_none());
+        assert!(split_stream(&mut message, 0).is_none());
+        let streamed = split_stream(&mut message, 1024).expect("should be streamed");
+        assert_eq!(streamed, data);
+        let header = match message {
+            Message::Payload(header) => header,
+            other => panic!("unexpected message {:?}", other),
+        };
+        assert!(header.0.is_empty());
+
+        let mut assembler = StreamAssembler::default();
+        let start = Message::StreamStart {
+            stream_id: 7,
+            payload: header,
+            total_len: streamed.len() as u64,
+        };
+        assert!(assembler.receive(start).unwrap().is_none());
+        let mut hasher = StreamHasher::new();
+        for chunk in chunks(7, &streamed, 1024) {
+            if let Message::StreamChunk { data, .. } = &chunk {
+                hasher.update(data);
+            }
+            assert!(assembler.receive(chunk).unwrap().is_none());
+        }
+        let hash = hasher.finalize();
+        assert_eq!(hash, hash::hash(&data));
+
+        match assembler.receive(Message::StreamEnd { stream_id: 7, hash }) {
+            Ok(Some(Message::Payload(blob))) => assert_eq!(blob, Blob(data)),
+            other => panic!("unexpected result {:?}", other),
+        }
+        // Other messages pass through.
+        assert!(matches!(
+            assembler.receive(Message::Payload(Blob(vec![1]))),
+            Ok(Some(Message::Payload(_)))
+        ));
+    }
+
+    #[test]
+    fn should_discard_cancelled_and_reject_corrupt_streams() {
+        let data = vec![42; 3_000];
+        let start = |stream_id| Message::StreamStart {
+            stream_id,
+            payload: Blob(Vec::new()),
+            total_len: data.len() as u64,
+        };
+
+        let mut assembler = StreamAssembler::default();
+        assembler.receive(start(1)).unwrap();
+        let mut partial = chunks(1, &data, 1_000);
+        assembler.receive(partial.next().unwrap()).unwrap();
+        assert!(assembler
+            .receive(Message::StreamCancel { stream_id: 1 })
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            assembler.receive(partial.next().unwrap()).unwrap_err(),
+            StreamError::UnknownStream(1)
+        );
+
+        let mut assembler = StreamAssembler::default();
+        assembler.receive(start(2)).unwrap();
+        for chunk in chunks(2, &data, 1_000) {
+            assembler.receive(chunk).unwrap();
+        }
+        assert_eq!(
+            assembler
+                .receive(Message::StreamEnd {
+                    stream_id: 2,
+                    hash: hash::hash(b"other data"),
+                })
+                .unwrap_err(),
+            StreamError::HashMismatch
+        );
+
+        let mut assembler = StreamAssembler::default();
+        assembler.receive(start(3)).unwrap();
+        for chunk in chunks(3, &data, 1_000) {
+            assembler.receive(chunk).unwrap();
+        }
+        assert_eq!(
+            assembler
+                .receive(Message::StreamChunk {
+                    stream_id: 3,
+                    data: vec![0],
+                })
+                .unwrap_err(),
+            StreamError::LengthMismatch {
+                expected: 3_000,
+                received: 3_001,
+            }
+        );
+
+        let mut assembler = StreamAssembler::<Blob>::default();
+        assert_eq!(
+            assembler
+                .receive(Message::StreamStart {
+                    stream_id: 4,
+                    payload: Blob(Vec::new()),
+                    total_len: MAX_STREAM_LENGTH + 1,
+                })
+                .unwrap_err(),
+            StreamError::TooLong(MAX_STREAM_LENGTH + 1)
+        );
+    }
+}
@@ -13,10 +13,14 @@ use std::{
 use derive_more::From;
 use pnet::datalink;
 use prometheus::Registry;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 
-use super::{Config, Event as SmallNetworkEvent, GossipedAddress, SmallNetwork};
+use super::{
+    ChainInfo, Config, DisconnectReason, Event as SmallNetworkEvent, GossipedAddress, LargePayload,
+    RequestPayload, SmallNetwork,
+};
 use crate::{
     components::{
         gossiper::{self, Gossiper},
@@ -36,7 +40,7 @@ use crate::{
         network::{Network, NetworkedReactor},
         ConditionCheckReactor,
     },
-    types::NodeId,
+    types::{FeatureFlags, NodeId, RequestClass},
     utils::Source,
     NodeRng,
 };
@@ -92,6 +96,18 @@ impl Display for Message {
     }
 }
 
+impl LargePayload for Message {
+    fn stream_data(&mut self) -> Option<&mut Vec<u8>> {
+        None
+    }
+}
+
+impl RequestPayload for Message {
+    fn request_class(&self) -> Option<RequestClass> {
+        None
+    }
+}
+
 /// Test reactor.
 ///
 /// Runs a single small network.
@@ -112,7 +128,15 @@ impl Reactor for TestReactor {
         event_queue: EventQueueHandle<Self::Event>,
         _rng: &mut NodeRng,
     ) -> anyhow::Result<(Self, Effects<Self::Event>)> {
-        let (net, effects) = SmallNetwork::new(event_queue, cfg, Digest::default(), false)?;
+        let (net, effects) = SmallNetwork::new(
+            event_queue,
+            cfg,
+            registry,
+            ChainInfo::new(Digest::default(), Version::new(1, 0, 0)),
+            FeatureFlags::default(),
+            None,
+            false,
+        )?;
         let gossiper_config = gossiper::Config::new_with_small_timeouts();
         let address_gossiper =
             Gossiper::new_for_complete_items("address_gossiper", gossiper_config, registry)?;
@@ -412,3 +436,67 @@ async fn check_varying_size_network_connects() {
         net.finalize().await;
     }
 }
+
+/// Check that a node shutting down says goodbye, so its peers do not treat it as a failure.
+#[tokio::test]
+async fn shutdown_is_announced_to_peers() {
+    // If the env var "CASPER_ENABLE_LEGACY_NET" is not defined, exit without running the test.
+    if env::var(ENABLE_SMALL_NET_ENV_VAR).is_err() {
+        return;
+    }
+
+    init_logging();
+
+    let mut rng = crate::new_rng();
+    let mut net = Network::new();
+    let first_node_port = testing::unused_port_on_localhost();
+
+    let _ = net
+        .add_node_with_config(
+            Config::default_local_net_first_node(first_node_port),
+            &mut rng,
+        )
+        .await
+        .unwrap();
+    let _ = net
+        .add_node_with_config(Config::default_local_net(first_node_port), &mut rng)
+        .await
+        .unwrap();
+    // Keep a third node, so neither remaining node is isolated once the leaving one is gone.
+    let (leaving_id, _) = net
+        .add_node_with_config(Config::default_local_net(first_node_port), &mut rng)
+        .await
+        .unwrap();
+
+    let blocklist = HashSet::new();
+    net.settle_on(
+        &mut rng,
+        |nodes| network_is_complete(&blocklist, nodes),
+        Duration::from_secs(10),
+    )
+    .await;
+
+    let leaving = net.remove_node(&leaving_id).unwrap();
+    leaving.into_inner().finalize().await;
+
+    net.settle_on(
+        &mut rng,
+        |nodes| {
+            nodes.values().all(|runner| {
+                let farewell = runner.reactor().inner().net.farewells.get(&leaving_id);
+                matches!(farewell, Some(farewell) if farewell.reason == DisconnectReason::Shutdown)
+            })
+        },
+        Duration::from_secs(10),
+    )
+    .await;
+
+    // The remaining nodes hold off reconnecting to the node which left.
+    for runner in net.nodes().values() {
+        let net = &runner.reactor().inner().net;
+        let farewell = &net.farewells[&leaving_id];
+        assert!(net.is_holding_off(farewell.peer_address.expect("should know address")));
+    }
+
+    net.finalize().await;
+}
@@ -0,0 +1,418 @@
+//! Transport abstraction for the small network.
+//!
+//! The small network is agnostic of the underlying transport, as long as it provides a reliable,
+//! ordered and bidirectional byte stream per connection and lets both ends present a certificate.
+//! After the transport-level handshake completes, the peer's certificate is checked using
+//! `tls::validate_cert` and the `NodeId` is derived from its public key fingerprint, regardless of
+//! which transport the connection was made over.
+//!
+//! The transport is selected through the `transport` setting in the network configuration:
+//!
+//! * `tcp`: TLS 1.3 over TCP, using OpenSSL. This is the default. Sessions are resumed when
+//!   reconnecting unless `tls_session_resumption` is disabled.
+//! * `quic`: QUIC, using `rustls`. Incoming and outgoing connections share a single UDP endpoint.
+//!   As `rustls` does not support node certificates, a transport certificate issued by the node
+//!   certificate is presented along with it, see the `quic` module.
+
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    io,
+    net::SocketAddr,
+    sync::Arc,
+};
+
+use anyhow::Context;
+use datasize::DataSize;
+use futures::StreamExt;
+use openssl::{
+    pkey::{PKeyRef, Private},
+    ssl::{SslAcceptor, SslRef},
+    x509::X509Ref,
+};
+use prometheus::IntCounterVec;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+};
+use tokio_openssl::SslStream;
+
+use super::{Error, Result};
+use crate::{
+    tls::{self, TlsConnector, TlsOptions},
+    types::NodeId,
+};
+
+mod quic;
+
+/// The kind of transport used for node-to-node connections.
+#[derive(Copy, Clone, DataSize, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// TLS 1.3 over TCP.
+    Tcp,
+    /// QUIC, which provides TLS 1.3 and connection migration as part of the protocol.
+    Quic,
+}
+
+impl TransportKind {
+    /// Returns whether the TLS 1.3 cipher suite with the given OpenSSL name can be used with this
+    /// transport.
+    pub(crate) fn supports_cipher_suite(&self, name: &str) -> bool {
+        match self {
+            TransportKind::Tcp => tls::TLS13_CIPHER_SUITES.contains(&name),
+            TransportKind::Quic => quic::cipher_suite(name).is_some(),
+        }
+    }
+}
+
+impl Default for TransportKind {
+    fn default() -> Self {
+        TransportKind::Tcp
+    }
+}
+
+impl Display for TransportKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportKind::Tcp => write!(f, "tcp"),
+            TransportKind::Quic => write!(f, "quic"),
+        }
+    }
+}
+
+/// An established and authenticated connection stream.
+pub(crate) trait TransportStream: AsyncRead + AsyncWrite + Debug + Send + Unpin {
+    /// Returns the address of the remote end of the connection.
+    fn peer_addr(&self) -> io::Result<SocketAddr>;
+
+    /// Returns the local address of the connection.
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+impl TransportStream for SslStream<TcpStream> {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().peer_addr()
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().local_addr()
+    }
+}
+
+/// Transport type for base encrypted connections.
+pub(crate) type Transport = Box<dyn TransportStream>;
+
+/// A listener accepting incoming connections for a specific transport.
+pub(super) enum Listener {
+    /// A TCP listener, connections will be wrapped in TLS.
+    Tcp(TcpListener),
+    /// A QUIC endpoint, also used for outgoing connections.
+    Quic {
+        /// The endpoint.
+        endpoint: quinn::Endpoint,
+        /// The incoming connections of the endpoint.
+        incoming: quinn::Incoming,
+        /// The address the endpoint is bound to.
+        local_address: SocketAddr,
+    },
+}
+
+/// An incoming connection that has not completed its handshake yet.
+pub(crate) enum IncomingStream {
+    /// An incoming TCP connection.
+    Tcp(TcpStream),
+    /// An incoming QUIC connection.
+    Quic {
+        /// The connection being established.
+        connecting: quinn::Connecting,
+        /// The address of the endpoint accepting it.
+        local_address: SocketAddr,
+    },
+}
+
+impl Debug for IncomingStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IncomingStream::Tcp(stream) => f.debug_tuple("Tcp").field(stream).finish(),
+            IncomingStream::Quic {
+                connecting,
+                local_address,
+            } => f
+                .debug_struct("Quic")
+                .field("peer_address", &connecting.remote_address())
+                .field("local_address", local_address)
+                .finish(),
+        }
+    }
+}
+
+/// Connector for outgoing connections over a specific transport.
+#[derive(Clone)]
+pub(super) enum Connector {
+    /// Connects over TCP, wrapping connections in TLS.
+    Tcp(TlsConnector),
+    /// Connects from the QUIC endpoint of the listener.
+    Quic(quinn::Endpoint),
+}
+
+impl Debug for Connector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Connector::Tcp(connector) => f.debug_tuple("Tcp").field(connector).finish(),
+            Connector::Quic(_) => f.debug_tuple("Quic").field(&"<endpoint>").finish(),
+        }
+    }
+}
+
+impl Listener {
+    /// Binds a new listener for the given transport.
+    ///
+    /// The node certificate `cert`, its `secret_key` and `tls_options` are only used by transports
+    /// not using OpenSSL, which configure their TLS stack on their own.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub(super) fn bind(
+        kind: TransportKind,
+        bind_address: SocketAddr,
+        cert: &X509Ref,
+        secret_key: &PKeyRef<Private>,
+        tls_options: &TlsOptions,
+    ) -> Result<Self> {
+        match kind {
+            TransportKind::Tcp => {
+                let listener = std::net::TcpListener::bind(bind_address)
+                    .map_err(|error| Error::ListenerCreation(error, bind_address))?;
+                Ok(Listener::Tcp(
+                    TcpListener::from_std(listener).map_err(Error::ListenerConversion)?,
+                ))
+            }
+            TransportKind::Quic => {
+                let (endpoint, incoming) = quic::bind(bind_address, cert, secret_key, tls_options)?;
+                let local_address = endpoint.local_addr().map_err(Error::ListenerAddr)?;
+                Ok(Listener::Quic {
+                    endpoint,
+                    incoming,
+                    local_address,
+                })
+            }
+        }
+    }
+
+    /// Returns the address the listener is actually bound to.
+    pub(super) fn local_addr(&self) -> Result<SocketAddr> {
+        match self {
+            Listener::Tcp(listener) => listener.local_addr().map_err(Error::ListenerAddr),
+            Listener::Quic { local_address, .. } => Ok(*local_address),
+        }
+    }
+
+    /// Returns the connector for outgoing connections over the transport of this listener.
+    ///
+    /// Over TCP, outgoing connections are made using `tls_connector`.
+    pub(super) fn connector(&self, tls_connector: TlsConnector) -> Connector {
+        match self {
+            Listener::Tcp(_) => Connector::Tcp(tls_connector),
+            Listener::Quic { endpoint, .. } => Connector::Quic(endpoint.clone()),
+        }
+    }
+
+    /// Waits for the next incoming connection.
+    pub(super) async fn accept(&mut self) -> io::Result<(IncomingStream, SocketAddr)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, peer_address) = listener.accept().await?;
+                Ok((IncomingStream::Tcp(stream), peer_address))
+            }
+            Listener::Quic {
+                incoming,
+                local_address,
+                ..
+            } => {
+                // The listener keeps the endpoint open, so there is always a next connection.
+                let connecting = incoming.next().await.ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotConnected, "QUIC endpoint closed")
+                })?;
+                let peer_address = connecting.remote_address();
+                Ok((
+                    IncomingStream::Quic {
+                        connecting,
+                        local_address: *local_address,
+                    },
+                    peer_address,
+                ))
+            }
+        }
+    }
+}
+
+/// Server-side handshake.
+///
+/// Completes the transport handshake on an incoming connection and validates the certificate
+/// presented by the client. TLS handshakes over TCP are counted in `handshakes`.
+pub(super) async fn accept(
+    stream: IncomingStream,
+    acceptor: Arc<SslAcceptor>,
+    handshakes: IntCounterVec,
+) -> Result<(NodeId, Transport)> {
+    match stream {
+        IncomingStream::Tcp(stream) => {
+            let tls_stream = tokio_openssl::accept(&acceptor, stream).await?;
+            record_tls_handshake(&handshakes, "incoming", tls_stream.ssl());
+
+            // We can now verify the certificate.
+            let peer_cert = tls_stream
+                .ssl()
+                .peer_certificate()
+                .ok_or(Error::NoClientCertificate)?;
+
+            Ok((
+                NodeId::from(tls::validate_cert(peer_cert)?.public_key_fingerprint()),
+                Box::new(tls_stream),
+            ))
+        }
+        IncomingStream::Quic {
+            connecting,
+            local_address,
+        } => quic::accept(connecting, local_address).await,
+    }
+}
+
+/// Client-side handshake.
+///
+/// Connects to a remote address using the transport of `connector` and validates the certificate
+/// presented by the server. TLS handshakes over TCP are counted in `handshakes`.
+pub(super) async fn connect(
+    connector: Connector,
+    peer_address: SocketAddr,
+    handshakes: IntCounterVec,
+) -> Result<(NodeId, Transport)> {
+    match connector {
+        Connector::Tcp(connector) => {
+            let mut config = connector
+                .configure(peer_address)
+                .map_err(Error::ConnectorConfiguration)?;
+            config.set_verify_hostname(false);
+
+            let stream = TcpStream::connect(peer_address)
+                .await
+                .context("TCP connection failed")?;
+
+            let tls_stream =
+                tokio_openssl::connect(config, "this-will-not-be-checked.example.com", stream)
+                    .await
+                    .context("tls handshake failed")?;
+            record_tls_handshake(&handshakes, "outgoing", tls_stream.ssl());
+
+            let peer_cert = tls_stream
+                .ssl()
+                .peer_certificate()
+                .ok_or(Error::NoServerCertificate)?;
+
+            let peer_id = tls::validate_cert(peer_cert)?.public_key_fingerprint();
+
+            Ok((NodeId::from(peer_id), Box::new(tls_stream)))
+        }
+        Connector::Quic(endpoint) => quic::connect(&endpoint, peer_address).await,
+    }
+}
+
+/// Counts a completed TLS handshake by direction and whether a session was resumed.
+fn record_tls_handshake(handshakes: &IntCounterVec, direction: &str, ssl: &SslRef) {
+    let kind = if ssl.session_reused() {
+        "resumed"
+    } else {
+        "full"
+    };
+    handshakes.with_label_values(&[direction, kind]).inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use prometheus::{IntCounterVec, Opts};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{accept, connect, Listener, TransportKind};
+    use crate::{
+        tls::{self, TlsConnector, TlsOptions},
+        types::NodeId,
+    };
+
+    #[test]
+    fn transport_kind_should_serialize_lowercase() {
+        assert_eq!(
+            toml::Value::try_from(TransportKind::Tcp).unwrap(),
+            toml::Value::String("tcp".to_string())
+        );
+        assert_eq!(
+            toml::Value::try_from(TransportKind::Quic).unwrap(),
+            toml::Value::String("quic".to_string())
+        );
+    }
+
+    #[test]
+    fn quic_should_not_support_ccm_cipher_suites() {
+        assert!(TransportKind::Quic.supports_cipher_suite("TLS_AES_256_GCM_SHA384"));
+        assert!(!TransportKind::Quic.supports_cipher_suite("TLS_AES_128_CCM_SHA256"));
+        assert!(TransportKind::Tcp.supports_cipher_suite("TLS_AES_128_CCM_SHA256"));
+    }
+
+    #[tokio::test]
+    async fn should_identify_nodes_connected_over_quic() {
+        let handshakes = IntCounterVec::new(
+            Opts::new("handshakes", "handshakes"),
+            &["direction", "kind"],
+        )
+        .unwrap();
+        let options = TlsOptions::default();
+        let bind = || {
+            let (cert, secret_key) = tls::generate_node_cert().unwrap();
+            let listener = Listener::bind(
+                TransportKind::Quic,
+                "127.0.0.1:0".parse().unwrap(),
+                &cert,
+                &secret_key,
+                &options,
+            )
+            .unwrap();
+            let connector =
+                listener.connector(TlsConnector::new(&cert, &secret_key, &options).unwrap());
+            let acceptor =
+                Arc::new(tls::create_tls_acceptor(&cert, &secret_key, &options).unwrap());
+            let node_id = NodeId::from(tls::validate_cert(cert).unwrap().public_key_fingerprint());
+            (listener, connector, acceptor, node_id)
+        };
+        let (mut server, _, server_acceptor, server_id) = bind();
+        let (_client, client_connector, _, client_id) = bind();
+        let server_address = server.local_addr().unwrap();
+
+        let server_handshakes = handshakes.clone();
+        let server_task = tokio::spawn(async move {
+            let (stream, _) = server.accept().await.unwrap();
+            let (peer_id, mut transport) = accept(stream, server_acceptor, server_handshakes)
+                .await
+                .unwrap();
+            let mut buf = [0; 4];
+            transport.read_exact(&mut buf).await.unwrap();
+            transport.write_all(&buf).await.unwrap();
+            transport.flush().await.unwrap();
+            // Keep the connection open until the client has read the reply.
+            let _ = transport.read(&mut buf).await;
+            peer_id
+        });
+
+        let (peer_id, mut transport) = connect(client_connector, server_address, handshakes)
+            .await
+            .unwrap();
+        assert_eq!(peer_id, server_id);
+        transport.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        transport.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        drop(transport);
+
+        assert_eq!(server_task.await.unwrap(), client_id);
+    }
+}
@@ -0,0 +1,339 @@
+//! QUIC transport.
+//!
+//! The available QUIC stacks rely on `rustls`, which does not support the P-521 curve of node
+//! certificates. Each node therefore presents a transport certificate on a supported curve, issued
+//! by its node key, followed by its node certificate, see `tls::generate_transport_cert`. Peers
+//! check the chain using `tls::validate_transport_cert`, so the `NodeId` is still derived from the
+//! node certificate, exactly as over TCP.
+//!
+//! Every connection carries a single bidirectional stream. As QUIC only announces a stream to the
+//! peer once data has been sent on it, the client opens it by sending `STREAM_OPENER`.
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::StreamExt;
+use openssl::{
+    pkey::{PKeyRef, Private},
+    x509::{X509Ref, X509},
+};
+use quinn::{Connecting, Connection, Endpoint, Incoming, NewConnection, RecvStream, SendStream};
+use rustls::{
+    ciphersuite, Certificate, ClientCertVerified, ClientCertVerifier, DistinguishedNames,
+    NoClientSessionStorage, NoServerSessionStorage, PrivateKey, ProtocolVersion, RootCertStore,
+    ServerCertVerified, ServerCertVerifier, SupportedCipherSuite, TLSError,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use webpki::{DNSName, DNSNameRef};
+
+use super::{
+    super::{Error, Result},
+    Transport, TransportStream,
+};
+use crate::{
+    tls::{self, TlsCert, TlsOptions},
+    types::NodeId,
+};
+
+/// ALPN protocol identifier of node-to-node connections.
+const ALPN_PROTOCOL: &[u8] = b"casper-node";
+
+/// Byte sent by the client to open the stream of a connection.
+const STREAM_OPENER: u8 = 1;
+
+/// Server name sent when connecting, peers are identified by their certificates instead.
+const SERVER_NAME: &str = "this-will-not-be-checked.example.com";
+
+/// Returns the `rustls` cipher suite with the given OpenSSL name, if `rustls` supports it.
+///
+/// The CCM cipher suites are not supported.
+pub(super) fn cipher_suite(name: &str) -> Option<&'static SupportedCipherSuite> {
+    match name {
+        "TLS_AES_256_GCM_SHA384" => Some(&ciphersuite::TLS13_AES_256_GCM_SHA384),
+        "TLS_CHACHA20_POLY1305_SHA256" => Some(&ciphersuite::TLS13_CHACHA20_POLY1305_SHA256),
+        "TLS_AES_128_GCM_SHA256" => Some(&ciphersuite::TLS13_AES_128_GCM_SHA256),
+        _ => None,
+    }
+}
+
+/// Binds an endpoint which accepts incoming connections and is used for outgoing ones.
+///
+/// Must be called from within a tokio runtime.
+pub(super) fn bind(
+    bind_address: SocketAddr,
+    cert: &X509Ref,
+    secret_key: &PKeyRef<Private>,
+    tls_options: &TlsOptions,
+) -> Result<(Endpoint, Incoming)> {
+    let (transport_cert, transport_key) =
+        tls::generate_transport_cert(cert, secret_key).map_err(Error::CertificateGeneration)?;
+    let cert_chain = vec![
+        Certificate(
+            transport_cert
+                .to_der()
+                .map_err(Error::CertificateGeneration)?,
+        ),
+        Certificate(cert.to_der().map_err(Error::CertificateGeneration)?),
+    ];
+    // `rustls` only accepts keys in PKCS #8 format.
+    let key_pem = transport_key
+        .private_key_to_pem_pkcs8()
+        .map_err(Error::CertificateGeneration)?;
+    let key = PrivateKey(
+        pem::parse(key_pem)
+            .expect("PEM encoded by OpenSSL should be valid")
+            .contents,
+    );
+    let cipher_suites: Vec<_> = if tls_options.cipher_suites.is_empty() {
+        rustls::ALL_CIPHERSUITES.to_vec()
+    } else {
+        tls_options
+            .cipher_suites
+            .iter()
+            .filter_map(|name| cipher_suite(name))
+            .collect()
+    };
+
+    let mut server_crypto = rustls::ServerConfig::new(Arc::new(NodeCertVerifier));
+    server_crypto.versions = vec![ProtocolVersion::TLSv1_3];
+    server_crypto.ciphersuites = cipher_suites.clone();
+    server_crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+    server_crypto
+        .set_single_cert(cert_chain.clone(), key.clone())
+        .map_err(Error::QuicConfiguration)?;
+    if !tls_options.session_resumption {
+        server_crypto.session_storage = Arc::new(NoServerSessionStorage {});
+    }
+    let mut server_config = quinn::ServerConfig::default();
+    server_config.crypto = Arc::new(server_crypto);
+
+    let mut client_crypto = rustls::ClientConfig::new();
+    client_crypto.versions = vec![ProtocolVersion::TLSv1_3];
+    client_crypto.ciphersuites = cipher_suites;
+    client_crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+    client_crypto
+        .dangerous()
+        .set_certificate_verifier(Arc::new(NodeCertVerifier));
+    client_crypto
+        .set_single_client_cert(cert_chain, key)
+        .map_err(Error::QuicConfiguration)?;
+    if !tls_options.session_resumption {
+        client_crypto.session_persistence = Arc::new(NoClientSessionStorage {});
+    }
+    let mut client_config = quinn::ClientConfig::default();
+    client_config.crypto = Arc::new(client_crypto);
+
+    let mut builder = Endpoint::builder();
+    builder.listen(server_config);
+    builder.default_client_config(client_config);
+    builder
+        .bind(&bind_address)
+        .map_err(|error| Error::QuicEndpoint(error, bind_address))
+}
+
+/// Server-side handshake, accepting the stream opened by the client.
+pub(super) async fn accept(
+    connecting: Connecting,
+    local_address: SocketAddr,
+) -> Result<(NodeId, Transport)> {
+    let NewConnection {
+        connection,
+        mut bi_streams,
+        ..
+    } = connecting.await.map_err(Error::QuicConnection)?;
+    let peer_id = peer_id(&connection, Error::NoClientCertificate)?;
+
+    let (send, mut recv) = bi_streams
+        .next()
+        .await
+        .ok_or_else(|| {
+            Error::QuicStream(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed before opening a stream",
+            ))
+        })?
+        .map_err(Error::QuicConnection)?;
+    let mut opener = [0; 1];
+    AsyncReadExt::read_exact(&mut recv, &mut opener)
+        .await
+        .map_err(Error::QuicStream)?;
+    if opener[0] != STREAM_OPENER {
+        return Err(Error::QuicStream(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected stream opener",
+        )));
+    }
+
+    Ok((
+        peer_id,
+        Box::new(QuicStream {
+            connection,
+            send,
+            recv,
+            local_address,
+        }),
+    ))
+}
+
+/// Client-side handshake, opening the stream of the connection.
+pub(super) async fn connect(
+    endpoint: &Endpoint,
+    peer_address: SocketAddr,
+) -> Result<(NodeId, Transport)> {
+    let local_address = endpoint.local_addr().map_err(Error::ListenerAddr)?;
+    let NewConnection { connection, .. } = endpoint
+        .connect(&peer_address, SERVER_NAME)
+        .map_err(Error::QuicConnect)?
+        .await
+        .map_err(Error::QuicConnection)?;
+    let peer_id = peer_id(&connection, Error::NoServerCertificate)?;
+
+    let (mut send, recv) = connection.open_bi().await.map_err(Error::QuicConnection)?;
+    AsyncWriteExt::write_all(&mut send, &[STREAM_OPENER])
+        .await
+        .map_err(Error::QuicStream)?;
+
+    Ok((
+        peer_id,
+        Box::new(QuicStream {
+            connection,
+            send,
+            recv,
+            local_address,
+        }),
+    ))
+}
+
+/// Returns the ID of the peer at the other end of an established connection, or
+/// `missing_certificate` if it presented none.
+fn peer_id(connection: &Connection, missing_certificate: Error) -> Result<NodeId> {
+    let peer_certificates = connection
+        .authentication_data()
+        .peer_certificates
+        .ok_or(missing_certificate)?;
+    let node_cert = validate_cert_chain(&peer_certificates)?;
+    Ok(NodeId::from(node_cert.public_key_fingerprint()))
+}
+
+/// Validates a certificate chain presented by a peer, returning its node certificate.
+fn validate_cert_chain(presented_certs: &[Certificate]) -> Result<TlsCert> {
+    match presented_certs {
+        [transport_cert, node_cert] => {
+            let transport_cert =
+                X509::from_der(&transport_cert.0).map_err(|_| Error::InvalidCertificateChain)?;
+            let node_cert =
+                X509::from_der(&node_cert.0).map_err(|_| Error::InvalidCertificateChain)?;
+            Ok(tls::validate_transport_cert(&transport_cert, node_cert)?)
+        }
+        _ => Err(Error::InvalidCertificateChain),
+    }
+}
+
+/// Verifies the certificate chains presented by peers during the handshake.
+///
+/// `rustls` checks that the peer holds the key of the transport certificate, the chain itself is
+/// checked here instead of against a set of roots.
+struct NodeCertVerifier;
+
+impl NodeCertVerifier {
+    fn verify(presented_certs: &[Certificate]) -> std::result::Result<(), TLSError> {
+        validate_cert_chain(presented_certs)
+            .map(|_| ())
+            .map_err(|error| TLSError::General(error.to_string()))
+    }
+}
+
+impl ServerCertVerifier for NodeCertVerifier {
+    fn verify_server_cert(
+        &self,
+        _roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        _dns_name: DNSNameRef,
+        _ocsp_response: &[u8],
+    ) -> std::result::Result<ServerCertVerified, TLSError> {
+        NodeCertVerifier::verify(presented_certs).map(|_| ServerCertVerified::assertion())
+    }
+}
+
+impl ClientCertVerifier for NodeCertVerifier {
+    fn client_auth_root_subjects(&self, _sni: Option<&DNSName>) -> Option<DistinguishedNames> {
+        Some(DistinguishedNames::new())
+    }
+
+    fn client_auth_mandatory(&self, _sni: Option<&DNSName>) -> Option<bool> {
+        Some(true)
+    }
+
+    fn verify_client_cert(
+        &self,
+        presented_certs: &[Certificate],
+        _sni: Option<&DNSName>,
+    ) -> std::result::Result<ClientCertVerified, TLSError> {
+        NodeCertVerifier::verify(presented_certs).map(|_| ClientCertVerified::assertion())
+    }
+}
+
+/// The stream of an established QUIC connection.
+pub(super) struct QuicStream {
+    /// The connection, which is closed once dropped along with its streams.
+    connection: Connection,
+    /// The sending half of the stream.
+    send: SendStream,
+    /// The receiving half of the stream.
+    recv: RecvStream,
+    /// The local address of the endpoint.
+    local_address: SocketAddr,
+}
+
+impl Debug for QuicStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuicStream")
+            .field("peer_address", &self.connection.remote_address())
+            .field("local_address", &self.local_address)
+            .finish()
+    }
+}
+
+impl AsyncRead for QuicStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncRead::poll_read(Pin::new(&mut self.recv), cx, buf)
+    }
+}
+
+impl AsyncWrite for QuicStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(Pin::new(&mut self.send), cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(Pin::new(&mut self.send), cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_shutdown(Pin::new(&mut self.send), cx)
+    }
+}
+
+impl TransportStream for QuicStream {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.connection.remote_address())
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_address)
+    }
+}
@@ -0,0 +1,75 @@
+//! The public keys the peers validate with, as announced by them.
+//!
+//! Right after the handshake, peers supporting it send the key they validate with, or that they
+//! have none, see `Message::ValidatorKey`. A message for the validators of an era is then sent to
+//! the peers which announced one of their keys, and to the peers which have not announced anything,
+//! since they may run an older version and be a validator nonetheless.
+
+use std::collections::{HashMap, HashSet};
+
+use datasize::DataSize;
+
+use casper_types::PublicKey;
+
+use crate::types::NodeId;
+
+/// The feature flag advertising that a node understands `Message::ValidatorKey`.
+pub(crate) const VALIDATOR_KEY_FEATURE: &str = "validator_key_announcement";
+
+/// The validator keys announced by the connected peers.
+#[derive(DataSize, Debug, Default)]
+pub(super) struct ValidatorKeys(HashMap<NodeId, Option<PublicKey>>);
+
+impl ValidatorKeys {
+    /// Records the key `peer_id` announced, `None` if it does not validate.
+    pub(super) fn announce(&mut self, peer_id: NodeId, public_key: Option<PublicKey>) {
+        let _ = self.0.insert(peer_id, public_key);
+    }
+
+    /// Forgets the announcement of a disconnected peer.
+    pub(super) fn remove(&mut self, peer_id: &NodeId) {
+        let _ = self.0.remove(peer_id);
+    }
+
+    /// Returns whether `peer_id` may validate with one of `validators`.
+    pub(super) fn is_in_scope(&self, peer_id: &NodeId, validators: &HashSet<PublicKey>) -> bool {
+        match self.0.get(peer_id) {
+            Some(Some(public_key)) => validators.contains(public_key),
+            Some(None) => false,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{crypto::AsymmetricKeyExt, testing::TestRng};
+
+    #[test]
+    fn should_include_validators_and_unannounced_peers() {
+        let mut rng = TestRng::new();
+        let validator = PublicKey::random(&mut rng);
+        let former_validator = PublicKey::random(&mut rng);
+        let validators: HashSet<_> = vec![validator].into_iter().collect();
+
+        let mut keys = ValidatorKeys::default();
+        let (peer_1, peer_2, peer_3, peer_4) = (
+            NodeId::random(&mut rng),
+            NodeId::random(&mut rng),
+            NodeId::random(&mut rng),
+            NodeId::random(&mut rng),
+        );
+        keys.announce(peer_1.clone(), Some(validator));
+        keys.announce(peer_2.clone(), Some(former_validator));
+        keys.announce(peer_3.clone(), None);
+
+        assert!(keys.is_in_scope(&peer_1, &validators));
+        assert!(!keys.is_in_scope(&peer_2, &validators));
+        assert!(!keys.is_in_scope(&peer_3, &validators));
+        assert!(keys.is_in_scope(&peer_4, &validators));
+
+        keys.remove(&peer_3);
+        assert!(keys.is_in_scope(&peer_3, &validators));
+    }
+}
@@ -7,9 +7,26 @@
 //! * storing and loading deploys,
 //! * [temporary until refactored] holding `DeployMetadata` for each deploy,
 //! * holding a read-only copy of the chainspec,
-//! * keeping an index of blocks by height and
+//! * keeping an index of blocks by height,
+//! * storing large artifacts in a content-addressable, reference counted blob store,
+//! * recovering updates spanning several databases which were interrupted by a crash and
 //! * [unimplemented] managing disk usage by pruning blocks and deploys from storage.
 //!
+//! ## Block bodies
+//!
+//! The deploy and transfer hashes of a block, its body, are stored in the blob store under the
+//! hash of their contents and only referenced from the block record, see `block_body`. Identical
+//! bodies, most commonly those of blocks without any deploys, are thus stored only once. Blocks
+//! written before bodies were stored separately are converted by a background migration like the
+//! one for compression, and can be read at any time.
+//!
+//! ## Compression
+//!
+//! If enabled in the configuration, blocks and deploys are stored zstd compressed. Records written
+//! before compression was enabled are compressed by a background migration, which processes a
+//! small batch of records at a time in between regular events. Compressed and uncompressed records
+//! can be read at any time.
+//!
 //! Any I/O performed by the component is done on the event handling thread, this is on purpose as
 //! the assumption is that caching by LMDB will offset any gains from offloading it onto a separate
 //! thread, while keeping the maximum event processing time reasonable.
@@ -25,64 +42,115 @@
 //!   block with a different block already existing at the same height causes a fatal error.
 //! * Storing a deploy or block that already exists (same hash) is fine and will silently be
 //!   accepted.
+//! * Storing a block or execution results is recorded in an intent log first, see `intent_log`, so
+//!   an update interrupted by a crash is completed when the storage is opened again.
 //!
 //! ## Indices
 //!
 //! The current implementation keeps only in-memory indices, which are not persisted, based upon the
 //! estimate that they are reasonably quick to rebuild on start-up and do not take up much memory.
 //!
+//! ## Read replicas
+//!
+//! Heavy API queries can be served by a separate process opening the same database read-only, see
+//! `crate::reactor::read_replica`, so that they do not compete with the node for the event loop.
+//! A read replica refuses to start unless the primary, i.e. the node, is running and holds a lock
+//! file next to the database, which it only does if `max_read_replicas` is configured. The lock
+//! file records the process ID of the primary, so a lock file left behind by a crashed primary is
+//! recognized.
+//!
+//! The replica reads records straight from LMDB, which always sees the latest committed data. Only
+//! the in-memory indices need to be kept up to date: the primary increments an epoch counter in a
+//! file next to the database whenever it stores a new block, logging the block by the epoch it
+//! started, and the replica indexes the blocks logged since its last refresh once it sees the
+//! counter change.
+//!
 //! ## Errors
 //!
 //! The storage component itself is panic free and in general reports three classes of errors:
 //! Corruption, temporary resource exhaustion and potential bugs.
 
+mod blob_store;
+mod block_body;
+mod intent_log;
 mod lmdb_ext;
+mod metrics;
 #[cfg(test)]
 mod tests;
 
-#[cfg(test)]
-use std::{collections::BTreeSet, convert::TryFrom};
 use std::{
-    collections::{btree_map::Entry, BTreeMap},
+    cmp,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    convert::TryFrom,
     fmt::{self, Display, Formatter},
-    fs, io,
-    path::PathBuf,
+    fs, io, mem,
+    ops::Range,
+    path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
 use datasize::DataSize;
 use derive_more::From;
 use lmdb::{
-    Cursor, Database, DatabaseFlags, Environment, EnvironmentFlags, Transaction, WriteFlags,
+    Cursor, Database, DatabaseFlags, Environment, EnvironmentFlags, RwTransaction, Transaction,
+    WriteFlags,
 };
+use prometheus::Registry;
+use semver::Version;
 use serde::{Deserialize, Serialize};
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 use tempfile::TempDir;
 use thiserror::Error;
-use tracing::info;
+use tracing::{debug, info, warn};
 
 use super::Component;
-#[cfg(test)]
-use crate::crypto::hash::Digest;
 use crate::{
     components::consensus::EraId,
+    crypto::hash::{self, Digest},
     effect::{
         requests::{StateStoreRequest, StorageRequest},
         EffectBuilder, EffectExt, Effects,
     },
     fatal,
-    types::{Block, BlockHash, Deploy, DeployHash, DeployMetadata},
-    utils::WithDir,
+    types::{Block, BlockHash, BlockHeader, Deploy, DeployHash, DeployMetadata},
+    utils::{ConfigValidator, WithDir},
     Chainspec, NodeRng,
 };
+use blob_store::BlobStore;
+use block_body::{MissingBlockBody, StoredBlock};
 use casper_types::{ExecutionResult, Transfer, Transform};
+use intent_log::{Intent, IntentLog};
 use lmdb_ext::{LmdbExtError, TransactionExt, WriteTransactionExt};
+use metrics::StorageMetrics;
 
 /// Filename for the LMDB database created by the Storage component.
 const STORAGE_DB_FILENAME: &str = "storage.lmdb";
+/// Filename of the lock file held by the primary while read replicas are enabled.
+const PRIMARY_LOCK_FILENAME: &str = "storage.primary.lock";
+/// Filename of the epoch counter written by the primary for read replicas.
+const EPOCH_FILENAME: &str = "storage.epoch";
+/// Filename of the marker recording the versions which have written to the storage.
+const VERSION_FILENAME: &str = "storage.version";
+
+/// Name of the migration to compressed blocks and deploys.
+const COMPRESSION_MIGRATION: &str = "compression";
+/// Name of the migration to blocks referring to their bodies in the blob store.
+const BLOCK_BODY_MIGRATION: &str = "block_body_dedup";
+/// Name of the migration to records starting with their format.
+const RECORD_FORMAT_MIGRATION: &str = "record_format";
+/// The storage migrations this node knows how to read.
+///
+/// Every migration changing the on-disk format must be added here, so that older nodes refuse to
+/// open a storage they cannot read.
+const KNOWN_MIGRATIONS: &[&str] = &[
+    COMPRESSION_MIGRATION,
+    BLOCK_BODY_MIGRATION,
+    RECORD_FORMAT_MIGRATION,
+];
 
 /// We can set this very low, as there is only a single reader/writer accessing the component at any
-/// one time.
+/// one time. Every read replica adds one more reader.
 const MAX_TRANSACTIONS: u32 = 1;
 
 /// One Gibibyte.
@@ -96,8 +164,21 @@ const DEFAULT_MAX_DEPLOY_STORE_SIZE: usize = 300 * GIB;
 const DEFAULT_MAX_DEPLOY_METADATA_STORE_SIZE: usize = 300 * GIB;
 /// Default max state store size.
 const DEFAULT_MAX_STATE_STORE_SIZE: usize = 10 * GIB;
+/// Default max blob store size.
+const DEFAULT_MAX_BLOB_STORE_SIZE: usize = 100 * GIB;
 /// Maximum number of allowed dbs.
-const MAX_DB_COUNT: u32 = 5;
+const MAX_DB_COUNT: u32 = 11;
+
+/// Number of records compressed in a single step of the compression migration.
+const COMPRESSION_MIGRATION_BATCH_SIZE: usize = 100;
+/// Delay between two steps of the compression migration, leaving room for other events.
+const COMPRESSION_MIGRATION_STEP_INTERVAL: Duration = Duration::from_millis(50);
+/// Number of blocks converted in a single step of the block body migration.
+const BLOCK_BODY_MIGRATION_BATCH_SIZE: usize = 100;
+/// Delay between two steps of the block body migration, leaving room for other events.
+const BLOCK_BODY_MIGRATION_STEP_INTERVAL: Duration = Duration::from_millis(50);
+/// Number of records rewritten in a single transaction of the record format migration.
+const RECORD_FORMAT_MIGRATION_BATCH_SIZE: usize = 1000;
 
 /// OS-specific lmdb flags.
 #[cfg(not(target_os = "macos"))]
@@ -117,6 +198,10 @@ pub enum Event {
     /// Incoming state storage request.
     #[from]
     StateStoreRequest(StateStoreRequest),
+    /// Compress the next batch of records written before compression was enabled.
+    CompressionMigrationStep,
+    /// Convert the next batch of blocks written before bodies were stored separately.
+    BlockBodyMigrationStep,
 }
 
 /// A storage component initialization error.
@@ -156,6 +241,112 @@ pub enum Error {
     /// LMDB error while operating.
     #[error("internal database error: {0}")]
     InternalStorage(#[from] LmdbExtError),
+    /// A read replica was started without a primary holding the lock file.
+    #[error("no primary holds the storage lock file `{}`", .0.display())]
+    PrimaryNotRunning(PathBuf),
+    /// A read replica was started with a lock file left behind by a primary which is not running.
+    #[error(
+        "storage lock file `{}` is stale, the primary with process ID {1} is not running",
+        .0.display()
+    )]
+    StalePrimaryLock(PathBuf, u32),
+    /// A primary was started while another one is running.
+    #[error(
+        "storage lock file `{}` is held by another running primary with process ID {1}",
+        .0.display()
+    )]
+    PrimaryRunning(PathBuf, u32),
+    /// A block logged for read replicas is not stored.
+    #[error("block {0} logged for read replicas is not stored")]
+    MissingLoggedBlock(BlockHash),
+    /// Found an entry in the block log which is not a block hash.
+    #[error("found corrupt block log entry for epoch {0}")]
+    CorruptBlockLog(u64),
+    /// Failure to create or remove the primary's lock file.
+    #[error("failed to access storage lock file `{}`: {}", .0.display(), .1)]
+    PrimaryLockFile(PathBuf, io::Error),
+    /// Failure to read or write the epoch counter.
+    #[error("failed to access storage epoch file `{}`: {}", .0.display(), .1)]
+    EpochFile(PathBuf, io::Error),
+    /// Failure to read or write the version marker.
+    #[error("failed to access storage version file `{}`: {}", .0.display(), .1)]
+    VersionFile(PathBuf, io::Error),
+    /// The storage was last written at a newer protocol version than this node supports.
+    #[error(
+        "refusing to downgrade: storage `{}` was last written at protocol version {stored}, but \
+         this node only supports protocol versions up to {supported}; run a node supporting \
+         protocol version {stored} or later",
+        .path.display()
+    )]
+    ProtocolDowngrade {
+        /// The path of the version marker.
+        path: PathBuf,
+        /// The protocol version recorded in the storage.
+        stored: Version,
+        /// The latest protocol version supported by this node.
+        supported: Version,
+    },
+    /// The storage contains a migration this node does not know.
+    #[error(
+        "refusing to downgrade: storage `{}` contains the migration `{migration}` unknown to this \
+         node; run casper-node {required} or later",
+        .path.display()
+    )]
+    UnknownMigration {
+        /// The path of the version marker.
+        path: PathBuf,
+        /// The name of the unknown migration.
+        migration: String,
+        /// The version of the newest node which has written to the storage.
+        required: Version,
+    },
+    /// Attempted to write to a read replica.
+    #[error("attempted to write to a read replica of the storage")]
+    ReadOnly,
+    /// A read replica was started before the primary finished migrating the record format.
+    #[error("records of database `{0}` are still being migrated by the primary")]
+    RecordFormatMigrationPending(&'static str),
+    /// Found a key in the deploy database which is not a deploy hash.
+    #[error("found corrupt deploy hash {} in database", hex::encode(.0))]
+    CorruptDeployHash(Vec<u8>),
+    /// Failure to register the storage metrics.
+    #[error("failed to register storage metrics: {0}")]
+    Metrics(#[from] prometheus::Error),
+}
+
+/// A failed storage write which may succeed if retried later, e.g. because the database ran out of
+/// space.
+///
+/// Storage reports these to the requester instead of treating them as fatal.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+#[error("transient storage write failure: {0}")]
+pub struct TransientWriteError(lmdb::Error);
+
+/// Separates transient write failures, which are reported to the requester, from all other errors.
+fn split_transient<T>(result: Result<T, Error>) -> Result<Result<T, TransientWriteError>, Error> {
+    match result {
+        Ok(value) => Ok(Ok(value)),
+        Err(Error::InternalStorage(LmdbExtError::ResourceExhausted(error))) => {
+            Ok(Err(TransientWriteError(error)))
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Maximum number of block headers returned for a single request of a scan over heights.
+pub const MAX_BLOCK_HEADER_BATCH: usize = 1000;
+
+/// A batch of the block headers of a scan over heights.
+///
+/// Scanning the chain in batches keeps only one batch in memory at a time, where loading all
+/// blocks at once would not fit for long chains.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeaderBatch {
+    /// The headers of the stored blocks, in order of height. Heights without a stored block are
+    /// skipped.
+    pub headers: Vec<BlockHeader>,
+    /// The height to resume the scan at, or `None` if no block is stored at the remaining heights.
+    pub next_height: Option<u64>,
 }
 
 // We wholesale wrap lmdb errors and treat them as internal errors here.
@@ -172,9 +363,12 @@ pub struct Storage {
     /// Environment holding LMDB databases.
     #[data_size(skip)]
     env: Environment,
-    /// The block database.
+    /// The database of blocks written before bodies were stored separately.
     #[data_size(skip)]
     block_db: Database,
+    /// The block database, holding blocks referring to their bodies in the blob store.
+    #[data_size(skip)]
+    block_header_db: Database,
     /// The deploy database.
     #[data_size(skip)]
     deploy_db: Database,
@@ -187,15 +381,76 @@ pub struct Storage {
     /// The state storage database.
     #[data_size(skip)]
     state_store_db: Database,
+    /// The hashes of the blocks stored while read replicas are enabled, by the epoch they started.
+    #[data_size(skip)]
+    block_log_db: Database,
+    /// The blob store.
+    #[data_size(skip)]
+    blob_store: BlobStore,
+    /// The intents of the updates spanning several databases being applied.
+    #[data_size(skip)]
+    intent_log: IntentLog,
     /// A map of block height to block ID.
     block_height_index: BTreeMap<u64, BlockHash>,
     /// A map of era ID to switch block ID.
     switch_block_era_id_index: BTreeMap<EraId, BlockHash>,
     /// Chainspec cache.
     chainspec_cache: Option<Arc<Chainspec>>,
+    /// Whether blocks and deploys are stored compressed.
+    enable_compression: bool,
+    /// Progress of the compression migration, if still running.
+    #[data_size(skip)]
+    compression_migration: Option<CompressionMigration>,
+    /// Whether blocks written before bodies were stored separately may be left to convert.
+    block_body_migration_pending: bool,
+    /// Whether the version marker is known to record blocks referring to their bodies.
+    block_body_format_recorded: bool,
+    /// The role of this instance with respect to read replicas.
+    replication: Replication,
+    /// Protocol upgrades not in effect at the highest stored block yet, by activation height.
+    pending_upgrades: BTreeMap<u64, Version>,
+    /// Metrics.
+    #[data_size(skip)]
+    metrics: StorageMetrics,
+}
+
+/// The role of a storage instance with respect to read replicas.
+#[derive(DataSize, Debug)]
+enum Replication {
+    /// Read replicas are not enabled.
+    Disabled,
+    /// This is the primary, having last written the given epoch.
+    Primary { epoch: u64 },
+    /// This is a read replica, with indices up to date as of the given epoch of the primary.
+    Replica { epoch: u64 },
 }
 
-impl<REv> Component<REv> for Storage {
+/// The versions which have written to a storage, persisted next to the database.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct VersionMarker {
+    /// The highest protocol version in effect when the storage was written.
+    protocol_version: Version,
+    /// The version of the newest node which has written to the storage.
+    node_version: Version,
+    /// The migrations of the on-disk format applied to the storage.
+    migrations: BTreeSet<String>,
+}
+
+/// Progress of the background task compressing records written before compression was enabled.
+#[derive(Debug)]
+struct CompressionMigration {
+    /// Databases still to be migrated, the first one is currently being processed.
+    pending_dbs: Vec<(&'static str, Database)>,
+    /// Key of the last record processed in the current database.
+    last_key: Option<Vec<u8>>,
+    /// Number of records compressed in the current database so far.
+    compressed: usize,
+}
+
+impl<REv> Component<REv> for Storage
+where
+    REv: Send,
+{
     type Event = Event;
     type ConstructionError = Error;
 
@@ -206,10 +461,16 @@ impl<REv> Component<REv> for Storage {
         event: Self::Event,
     ) -> Effects<Self::Event> {
         let result = match event {
-            Event::StorageRequest(req) => self.handle_storage_request::<REv>(req),
+            Event::StorageRequest(req) => self
+                .refresh_read_replica()
+                .and_then(|()| self.handle_storage_request::<REv>(req)),
             Event::StateStoreRequest(req) => {
                 self.handle_state_store_request::<REv>(effect_builder, req)
             }
+            Event::CompressionMigrationStep => {
+                self.handle_compression_migration_step(effect_builder)
+            }
+            Event::BlockBodyMigrationStep => self.handle_block_body_migration_step(effect_builder),
         };
 
         // Any error is turned into a fatal effect, the component itself does not panic. Note that
@@ -224,11 +485,29 @@ impl<REv> Component<REv> for Storage {
 
 impl Storage {
     /// Creates a new storage component.
-    pub(crate) fn new(cfg: &WithDir<Config>) -> Result<Self, Error> {
+    pub(crate) fn new(cfg: &WithDir<Config>, registry: &Registry) -> Result<Self, Error> {
         let config = cfg.value();
 
-        // Create the database directory.
         let root = cfg.with_dir(config.path.clone());
+        let lock_path = root.join(PRIMARY_LOCK_FILENAME);
+        let primary_pid = read_primary_lock(&lock_path)?;
+        if config.read_replica {
+            match primary_pid {
+                Some(pid) if is_process_running(pid) => (),
+                Some(pid) => return Err(Error::StalePrimaryLock(lock_path, pid)),
+                None => return Err(Error::PrimaryNotRunning(lock_path)),
+            }
+        } else if let Some(pid) = primary_pid {
+            if pid != std::process::id() && is_process_running(pid) {
+                return Err(Error::PrimaryRunning(lock_path, pid));
+            }
+            // The primary holding the lock crashed, so nobody else writes to the storage.
+            warn!(path = %lock_path.display(), pid, "removing stale storage lock file");
+            fs::remove_file(&lock_path)
+                .map_err(|err| Error::PrimaryLockFile(lock_path.clone(), err))?;
+        }
+
+        // Create the database directory.
         if !root.exists() {
             fs::create_dir_all(&root)
                 .map_err(|err| Error::CreateDatabaseDirectory(root.clone(), err))?;
@@ -238,67 +517,599 @@ impl Storage {
         let total_size = config
             .max_block_store_size
             .saturating_add(config.max_deploy_store_size)
-            .saturating_add(config.max_deploy_metadata_store_size);
+            .saturating_add(config.max_deploy_metadata_store_size)
+            .saturating_add(config.max_blob_store_size);
 
-        // Creates the environment and databases.
+        // Creates the environment and databases. The reader table is shared by all processes
+        // opening the database, so it must have room for the read replicas as well.
+        let flags = if config.read_replica {
+            EnvironmentFlags::READ_ONLY
+        } else {
+            OS_FLAGS
+        };
         let env = Environment::new()
             .set_flags(
-                OS_FLAGS |
+                flags |
                 // We manage our own directory.
                 EnvironmentFlags::NO_SUB_DIR
                 // Disable thread local storage, strongly suggested for operation with tokio.
                     | EnvironmentFlags::NO_TLS,
             )
-            .set_max_readers(MAX_TRANSACTIONS)
+            .set_max_readers(MAX_TRANSACTIONS + config.max_read_replicas)
             .set_max_dbs(MAX_DB_COUNT)
             .set_map_size(total_size)
             .open(&root.join(STORAGE_DB_FILENAME))?;
 
-        let block_db = env.create_db(Some("blocks"), DatabaseFlags::empty())?;
-        let deploy_db = env.create_db(Some("deploys"), DatabaseFlags::empty())?;
-        let deploy_metadata_db = env.create_db(Some("deploy_metadata"), DatabaseFlags::empty())?;
-        let transfer_db = env.create_db(Some("transfer"), DatabaseFlags::empty())?;
-        let state_store_db = env.create_db(Some("state_store"), DatabaseFlags::empty())?;
+        let open_db = |name| {
+            if config.read_replica {
+                env.open_db(Some(name))
+            } else {
+                env.create_db(Some(name), DatabaseFlags::empty())
+            }
+        };
+        let block_db = open_db("blocks")?;
+        let block_header_db = open_db("block_headers")?;
+        let deploy_db = open_db("deploys")?;
+        let deploy_metadata_db = open_db("deploy_metadata")?;
+        let transfer_db = open_db("transfer")?;
+        let state_store_db = open_db("state_store")?;
+        let meta_db = open_db("storage_meta")?;
+        let block_log_db = open_db("block_log")?;
+        let blob_store = if config.read_replica {
+            BlobStore::open(&env)?
+        } else {
+            BlobStore::new(&env)?
+        };
+        let intent_log = if config.read_replica {
+            IntentLog::open(&env)?
+        } else {
+            IntentLog::new(&env)?
+        };
+
+        // Records written before they started with their format must be rewritten before anything
+        // is read, since the two can't be told apart.
+        let legacy_dbs = [
+            ("blocks", block_db),
+            ("deploys", deploy_db),
+            ("deploy_metadata", deploy_metadata_db),
+            ("transfer", transfer_db),
+        ];
+        for &(db_name, db) in &legacy_dbs {
+            if config.read_replica {
+                if !is_record_format_migrated(&env, meta_db, db_name)? {
+                    return Err(Error::RecordFormatMigrationPending(db_name));
+                }
+            } else {
+                migrate_record_format(&env, meta_db, db_name, db)?;
+            }
+        }
+
+        let epoch_path = root.join(EPOCH_FILENAME);
+        let replication = if config.read_replica {
+            Replication::Replica {
+                epoch: read_epoch(&epoch_path)?,
+            }
+        } else if config.max_read_replicas > 0 {
+            fs::write(&lock_path, std::process::id().to_string())
+                .map_err(|err| Error::PrimaryLockFile(lock_path, err))?;
+            // Start a new epoch, so replicas reindex in case the database changed while we were
+            // not running.
+            let epoch = read_epoch(&epoch_path)? + 1;
+            write_epoch(&epoch_path, epoch)?;
+            Replication::Primary { epoch }
+        } else {
+            Replication::Disabled
+        };
 
         // We now need to restore the block-height index. Log messages allow timing here.
         info!("reindexing block store");
         let mut block_height_index = BTreeMap::new();
         let mut switch_block_era_id_index = BTreeMap::new();
-        let block_txn = env.begin_ro_txn()?;
-        let mut cursor = block_txn.open_ro_cursor(block_db)?;
-
-        // Note: `iter_start` has an undocumented panic if called on an empty database. We rely on
-        //       the iterator being at the start when created.
-        for (raw_key, raw_val) in cursor.iter() {
-            let block: Block = lmdb_ext::deserialize(raw_val)?;
-            // We use the opportunity for a small integrity check.
-            assert_eq!(
-                raw_key,
-                block.hash().as_ref(),
-                "found corrupt block in database"
-            );
-            insert_to_block_indices(
-                &mut block_height_index,
-                &mut switch_block_era_id_index,
-                &block,
-            )?;
-        }
+        reindex_blocks(
+            &env,
+            block_header_db,
+            block_db,
+            &mut block_height_index,
+            &mut switch_block_era_id_index,
+        )?;
         info!("block store reindexing complete");
-        drop(cursor);
-        drop(block_txn);
 
-        Ok(Storage {
+        let compression_migration = if config.enable_compression && !config.read_replica {
+            Some(CompressionMigration {
+                pending_dbs: vec![("block_headers", block_header_db), ("deploys", deploy_db)],
+                last_key: None,
+                compressed: 0,
+            })
+        } else {
+            None
+        };
+
+        let mut storage = Storage {
             root,
             env,
             block_db,
+            block_header_db,
             deploy_db,
             deploy_metadata_db,
             transfer_db,
             state_store_db,
+            block_log_db,
+            blob_store,
+            intent_log,
             block_height_index,
             switch_block_era_id_index,
             chainspec_cache: None,
-        })
+            enable_compression: config.enable_compression,
+            compression_migration,
+            block_body_migration_pending: !config.read_replica,
+            block_body_format_recorded: false,
+            replication,
+            pending_upgrades: BTreeMap::new(),
+            metrics: StorageMetrics::new(registry)?,
+        };
+        if !config.read_replica {
+            storage.recover_intents()?;
+        }
+        Ok(storage)
+    }
+
+    /// Replays the updates interrupted by a crash, rolling back those which can't be applied.
+    fn recover_intents(&mut self) -> Result<(), Error> {
+        let pending = self.intent_log.pending(&self.env)?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+        warn!(
+            count = pending.len(),
+            "recovering interrupted storage updates"
+        );
+        for (id, intent) in pending {
+            match self.apply_intent(&intent) {
+                Ok(_) => info!(%intent, "replayed interrupted storage update"),
+                Err(error) => warn!(%intent, %error, "rolled back interrupted storage update"),
+            }
+            self.intent_log.complete(&self.env, id)?;
+        }
+        Ok(())
+    }
+
+    /// Applies an update spanning several databases, keeping it in the intent log until it has
+    /// been applied completely.
+    ///
+    /// Returns `true` if anything was newly stored.
+    fn apply_logged(&mut self, intent: Intent) -> Result<bool, Error> {
+        self.ensure_writable()?;
+        let id = self.intent_log.record(&self.env, &intent)?;
+        let result = self.apply_intent(&intent);
+        self.intent_log.complete(&self.env, id)?;
+        result
+    }
+
+    /// Applies an update spanning several databases.
+    ///
+    /// Applying the same update again has no further effect. If applying fails, none of the
+    /// databases has been written to.
+    fn apply_intent(&mut self, intent: &Intent) -> Result<bool, Error> {
+        match intent {
+            Intent::PutBlock(block) => {
+                let outcome = self.put_block(block)?;
+                self.record_activated_upgrades(block.height())?;
+                Ok(outcome)
+            }
+            Intent::PutExecutionResults {
+                block_hash,
+                execution_results,
+            } => {
+                self.put_execution_results(block_hash, execution_results)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Checks that this node can safely run against the storage, then records its versions.
+    ///
+    /// Refuses to open a storage last written at a newer protocol version than the latest one in
+    /// `chainspec`, or containing a migration this node does not know, since an older node would
+    /// misinterpret or corrupt its contents. Otherwise the protocol version in effect at the
+    /// highest stored block is recorded, together with the node version and the migrations
+    /// enabled, and upgrades activated by blocks stored later on are recorded as they happen. Read
+    /// replicas only check, they never write the marker.
+    pub(crate) fn check_version(&mut self, chainspec: &Chainspec) -> Result<(), Error> {
+        let path = self.root.join(VERSION_FILENAME);
+        let node_version = Version::parse(env!("CARGO_PKG_VERSION"))
+            .expect("crate version should be a valid semver version");
+        let supported = chainspec.latest_protocol_version();
+
+        let marker = read_version_marker(&path)?;
+        if let Some(marker) = &marker {
+            if marker.protocol_version > supported {
+                return Err(Error::ProtocolDowngrade {
+                    path,
+                    stored: marker.protocol_version.clone(),
+                    supported,
+                });
+            }
+            if let Some(migration) = marker
+                .migrations
+                .iter()
+                .find(|migration| !KNOWN_MIGRATIONS.contains(&migration.as_str()))
+            {
+                return Err(Error::UnknownMigration {
+                    path,
+                    migration: migration.clone(),
+                    required: marker.node_version.clone(),
+                });
+            }
+        }
+
+        if let Replication::Replica { .. } = self.replication {
+            return Ok(());
+        }
+
+        let mut marker = marker.unwrap_or_else(|| VersionMarker {
+            protocol_version: chainspec.genesis.protocol_version.clone(),
+            node_version: node_version.clone(),
+            migrations: BTreeSet::new(),
+        });
+        marker.protocol_version = cmp::max(
+            marker.protocol_version,
+            self.active_protocol_version(chainspec),
+        );
+        marker.node_version = cmp::max(marker.node_version, node_version);
+        marker
+            .migrations
+            .insert(RECORD_FORMAT_MIGRATION.to_string());
+        if self.enable_compression {
+            marker.migrations.insert(COMPRESSION_MIGRATION.to_string());
+        }
+        // Only storages holding a block which refers to its body can't be read by older nodes.
+        if self.has_block_body_format()? {
+            marker.migrations.insert(BLOCK_BODY_MIGRATION.to_string());
+        }
+        self.block_body_format_recorded = marker.migrations.contains(BLOCK_BODY_MIGRATION);
+        write_version_marker(&path, &marker)?;
+
+        let highest_height = self.block_height_index.keys().next_back().copied();
+        self.pending_upgrades = chainspec
+            .upgrades
+            .iter()
+            .filter(|upgrade| Some(upgrade.activation_point.height) > highest_height)
+            .map(|upgrade| {
+                (
+                    upgrade.activation_point.height,
+                    upgrade.protocol_version.clone(),
+                )
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Returns whether any block is stored referring to its body.
+    fn has_block_body_format(&self) -> Result<bool, Error> {
+        let txn = self.env.begin_ro_txn()?;
+        let mut cursor = txn.open_ro_cursor(self.block_header_db)?;
+        // Note: `iter_start` has an undocumented panic if called on an empty database. We rely on
+        //       the iterator being at the start when created.
+        let has_block = cursor.iter().next().is_some();
+        drop(cursor);
+        txn.commit()?;
+        Ok(has_block)
+    }
+
+    /// Records in the version marker that blocks refer to their bodies, before the first such
+    /// block is written.
+    ///
+    /// Does nothing if the storage has no marker yet, it is written by `check_version` including
+    /// the migration if such blocks have been written by then.
+    fn record_block_body_format(&mut self) -> Result<(), Error> {
+        if self.block_body_format_recorded {
+            return Ok(());
+        }
+        let path = self.root.join(VERSION_FILENAME);
+        if let Some(mut marker) = read_version_marker(&path)? {
+            if marker.migrations.insert(BLOCK_BODY_MIGRATION.to_string()) {
+                info!("recording block body migration in storage");
+                write_version_marker(&path, &marker)?;
+            }
+            self.block_body_format_recorded = true;
+        }
+        Ok(())
+    }
+
+    /// Records the protocol version of the upgrades activated at or below `height` in the marker.
+    fn record_activated_upgrades(&mut self, height: u64) -> Result<(), Error> {
+        let not_activated = self.pending_upgrades.split_off(&(height + 1));
+        let activated = mem::replace(&mut self.pending_upgrades, not_activated);
+        let version = match activated.into_iter().map(|(_, version)| version).max() {
+            Some(version) => version,
+            None => return Ok(()),
+        };
+
+        let path = self.root.join(VERSION_FILENAME);
+        match read_version_marker(&path)? {
+            Some(mut marker) if marker.protocol_version < version => {
+                info!(%version, "recording activated protocol upgrade in storage");
+                marker.protocol_version = version;
+                write_version_marker(&path, &marker)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the protocol version in effect at the highest stored block.
+    fn active_protocol_version(&self, chainspec: &Chainspec) -> Version {
+        let highest_height = match self.block_height_index.keys().next_back() {
+            Some(height) => *height,
+            None => return chainspec.genesis.protocol_version.clone(),
+        };
+        chainspec
+            .upgrades
+            .iter()
+            .filter(|upgrade| upgrade.activation_point.height <= highest_height)
+            .map(|upgrade| upgrade.protocol_version.clone())
+            .chain(Some(chainspec.genesis.protocol_version.clone()))
+            .max()
+            .expect("should contain the genesis protocol version")
+    }
+
+    /// Returns the directory holding the database and the files next to it.
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns an error if this is a read replica.
+    fn ensure_writable(&self) -> Result<(), Error> {
+        match self.replication {
+            Replication::Replica { .. } => Err(Error::ReadOnly),
+            Replication::Disabled | Replication::Primary { .. } => Ok(()),
+        }
+    }
+
+    /// Indexes any blocks added by the primary if its epoch changed since the last refresh.
+    ///
+    /// Only the blocks logged for the epochs since the last refresh are indexed. All blocks are
+    /// reindexed if the primary restarted in the meantime, as it may have stored blocks without
+    /// logging them while it was running without read replicas.
+    ///
+    /// Does nothing unless this is a read replica.
+    fn refresh_read_replica(&mut self) -> Result<(), Error> {
+        let last_epoch = match self.replication {
+            Replication::Replica { epoch } => epoch,
+            Replication::Disabled | Replication::Primary { .. } => return Ok(()),
+        };
+        let epoch = read_epoch(&self.root.join(EPOCH_FILENAME))?;
+        if epoch == last_epoch {
+            return Ok(());
+        }
+        let block_count = self.block_height_index.len();
+        let mut txn = self.env.begin_ro_txn()?;
+        match logged_blocks(&txn, self.block_log_db, last_epoch, epoch)? {
+            Some(block_hashes) => {
+                for block_hash in &block_hashes {
+                    let block = self
+                        .get_single_block(&mut txn, block_hash)?
+                        .ok_or(Error::MissingLoggedBlock(*block_hash))?;
+                    insert_to_block_indices(
+                        &mut self.block_height_index,
+                        &mut self.switch_block_era_id_index,
+                        &block,
+                    )?;
+                }
+                txn.commit()?;
+            }
+            None => {
+                txn.commit()?;
+                reindex_blocks(
+                    &self.env,
+                    self.block_header_db,
+                    self.block_db,
+                    &mut self.block_height_index,
+                    &mut self.switch_block_era_id_index,
+                )?;
+            }
+        }
+        debug!(
+            epoch,
+            new_blocks = self.block_height_index.len() - block_count,
+            "refreshed read replica"
+        );
+        self.replication = Replication::Replica { epoch };
+        Ok(())
+    }
+
+    /// Starts the next epoch after a new block has been stored, if read replicas are enabled.
+    fn advance_epoch(&mut self) -> Result<(), Error> {
+        if let Replication::Primary { epoch } = &mut self.replication {
+            *epoch += 1;
+            write_epoch(&self.root.join(EPOCH_FILENAME), *epoch)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes all written data to disk.
+    ///
+    /// Does nothing on a read replica, which never writes.
+    pub(crate) fn flush(&self) -> Result<(), Error> {
+        if let Replication::Replica { .. } = self.replication {
+            return Ok(());
+        }
+        self.env.sync(true).map_err(LmdbExtError::from)?;
+        Ok(())
+    }
+
+    /// Returns an effect starting the background compression of records written before
+    /// compression was enabled.
+    ///
+    /// Returns no effect if compression is disabled or all records have been migrated already.
+    pub(crate) fn start_compression_migration<REv>(
+        &self,
+        effect_builder: EffectBuilder<REv>,
+    ) -> Effects<Event>
+    where
+        REv: Send,
+    {
+        if self.compression_migration.is_none() {
+            return Effects::new();
+        }
+        info!("starting compression of existing records");
+        effect_builder
+            .set_timeout(COMPRESSION_MIGRATION_STEP_INTERVAL)
+            .event(|_| Event::CompressionMigrationStep)
+    }
+
+    /// Compresses the next batch of uncompressed records and schedules the next step.
+    fn handle_compression_migration_step<REv>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+    ) -> Result<Effects<Event>, Error>
+    where
+        REv: Send,
+    {
+        let migration = match self.compression_migration.as_mut() {
+            Some(migration) => migration,
+            None => return Ok(Effects::new()),
+        };
+        let (db_name, db) = match migration.pending_dbs.first() {
+            Some(&pending) => pending,
+            None => {
+                info!("compression of existing records complete");
+                self.compression_migration = None;
+                return Ok(Effects::new());
+            }
+        };
+
+        let mut txn = self.env.begin_rw_txn()?;
+        let batch: Vec<(Vec<u8>, Vec<u8>)> = {
+            let mut cursor = txn.open_ro_cursor(db)?;
+            let last_key = migration.last_key.as_deref();
+            // Note: `iter_start` has an undocumented panic if called on an empty database. We rely
+            //       on the iterator being at the start when created.
+            let iter = match last_key {
+                Some(key) => cursor.iter_from(key),
+                None => cursor.iter(),
+            };
+            iter.filter(|&(raw_key, _)| Some(raw_key) != last_key)
+                .take(COMPRESSION_MIGRATION_BATCH_SIZE)
+                .map(|(raw_key, raw_val)| (raw_key.to_owned(), raw_val.to_owned()))
+                .collect()
+        };
+
+        for (raw_key, raw_val) in &batch {
+            if let Some(compressed) = lmdb_ext::compress(raw_val)? {
+                txn.put(db, raw_key, &compressed, WriteFlags::empty())?;
+                migration.compressed += 1;
+            }
+        }
+        txn.commit()?;
+
+        if batch.len() < COMPRESSION_MIGRATION_BATCH_SIZE {
+            info!(
+                db = db_name,
+                compressed = migration.compressed,
+                "finished compressing existing records"
+            );
+            migration.pending_dbs.remove(0);
+            migration.last_key = None;
+            migration.compressed = 0;
+        } else {
+            debug!(
+                db = db_name,
+                compressed = migration.compressed,
+                "compressing existing records"
+            );
+            migration.last_key = batch.last().map(|(raw_key, _)| raw_key.clone());
+        }
+
+        Ok(effect_builder
+            .set_timeout(COMPRESSION_MIGRATION_STEP_INTERVAL)
+            .event(|_| Event::CompressionMigrationStep))
+    }
+
+    /// Returns an effect starting the background conversion of blocks written before bodies were
+    /// stored separately.
+    ///
+    /// Returns no effect on a read replica.
+    pub(crate) fn start_block_body_migration<REv>(
+        &self,
+        effect_builder: EffectBuilder<REv>,
+    ) -> Effects<Event>
+    where
+        REv: Send,
+    {
+        if !self.block_body_migration_pending {
+            return Effects::new();
+        }
+        effect_builder
+            .set_timeout(BLOCK_BODY_MIGRATION_STEP_INTERVAL)
+            .event(|_| Event::BlockBodyMigrationStep)
+    }
+
+    /// Converts the next batch of blocks written before bodies were stored separately and
+    /// schedules the next step.
+    ///
+    /// Converted blocks are removed from the legacy block database, so every step starts at its
+    /// beginning.
+    fn handle_block_body_migration_step<REv>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+    ) -> Result<Effects<Event>, Error>
+    where
+        REv: Send,
+    {
+        if !self.block_body_migration_pending {
+            return Ok(Effects::new());
+        }
+
+        let batch: Vec<Block> = {
+            let txn = self.env.begin_ro_txn()?;
+            let mut cursor = txn.open_ro_cursor(self.block_db)?;
+            // Note: `iter_start` has an undocumented panic if called on an empty database. We rely
+            //       on the iterator being at the start when created.
+            let batch = cursor
+                .iter()
+                .take(BLOCK_BODY_MIGRATION_BATCH_SIZE)
+                .map(|(_, raw_val)| lmdb_ext::deserialize(raw_val))
+                .collect::<Result<_, _>>()?;
+            drop(cursor);
+            txn.commit()?;
+            batch
+        };
+        if !batch.is_empty() {
+            self.record_block_body_format()?;
+            let mut txn = self.env.begin_rw_txn()?;
+            for block in &batch {
+                self.put_block_record(&mut txn, block)?;
+            }
+            txn.commit()?;
+        }
+
+        if batch.len() < BLOCK_BODY_MIGRATION_BATCH_SIZE {
+            if !batch.is_empty() {
+                info!("finished storing block bodies separately");
+            }
+            self.block_body_migration_pending = false;
+            return Ok(Effects::new());
+        }
+        debug!(converted = batch.len(), "storing block bodies separately");
+        Ok(effect_builder
+            .set_timeout(BLOCK_BODY_MIGRATION_STEP_INTERVAL)
+            .event(|_| Event::BlockBodyMigrationStep))
+    }
+
+    /// Writes a block or deploy record, compressing it if enabled.
+    fn put_record<K: AsRef<[u8]>, V: Serialize>(
+        &self,
+        txn: &mut RwTransaction<'_>,
+        db: Database,
+        key: &K,
+        value: &V,
+        overwrite: bool,
+    ) -> Result<bool, LmdbExtError> {
+        if self.enable_compression {
+            txn.put_compressed_value(db, key, value, overwrite)
+        } else {
+            txn.put_value(db, key, value, overwrite)
+        }
     }
 
     /// Handles a state store request.
@@ -318,6 +1129,7 @@ impl Storage {
                 data,
                 responder,
             } => {
+                self.ensure_writable()?;
                 let mut txn = self.env.begin_rw_txn()?;
                 txn.put(self.state_store_db, &key, &data, WriteFlags::default())?;
                 txn.commit()?;
@@ -335,6 +1147,151 @@ impl Storage {
         }
     }
 
+    /// Writes a block to storage and adds it to the indices.
+    ///
+    /// Returns `true` if the block was newly stored.
+    fn put_block(&mut self, block: &Block) -> Result<bool, Error> {
+        self.ensure_writable()?;
+        self.record_block_body_format()?;
+        let mut txn = self.env.begin_rw_txn()?;
+        let outcome = self.put_block_record(&mut txn, block)?;
+        // Check the indices before committing, so a conflicting block is not stored.
+        check_block_indices(
+            &self.block_height_index,
+            &self.switch_block_era_id_index,
+            block,
+        )?;
+        if let (true, Replication::Primary { epoch }) = (outcome, &self.replication) {
+            // Logged with the epoch `advance_epoch` starts, so replicas index just this block.
+            txn.put(
+                self.block_log_db,
+                &(epoch + 1).to_be_bytes(),
+                block.hash(),
+                WriteFlags::empty(),
+            )?;
+        }
+        txn.commit()?;
+        insert_to_block_indices(
+            &mut self.block_height_index,
+            &mut self.switch_block_era_id_index,
+            block,
+        )?;
+        if outcome {
+            self.advance_epoch()?;
+        }
+        Ok(outcome)
+    }
+
+    /// Writes a block record referring to its body, storing the body unless it is stored already.
+    ///
+    /// A record of the block written before bodies were stored separately is removed. Always
+    /// returns `true`, as the block record is overwritten if present.
+    fn put_block_record(&self, txn: &mut RwTransaction<'_>, block: &Block) -> Result<bool, Error> {
+        let (block_without_body, body) = block_body::split(block);
+        let raw_body = lmdb_ext::serialize(&body)?;
+        let stored: Option<StoredBlock> = txn.get_value(self.block_header_db, block.hash())?;
+        let body_hash = match stored {
+            // The block holds a reference to its body already.
+            Some(stored) => stored.body_hash,
+            None => {
+                if self.blob_store.is_referenced(txn, &hash::hash(&raw_body))? {
+                    self.metrics.deduplicated_block_bodies.inc();
+                    self.metrics
+                        .block_body_bytes_saved
+                        .inc_by(raw_body.len() as u64);
+                }
+                self.blob_store.put(txn, &raw_body)?
+            }
+        };
+        let stored = StoredBlock::new(block_without_body, body_hash);
+        let outcome = self.put_record(txn, self.block_header_db, block.hash(), &stored, true)?;
+        match txn.del(self.block_db, block.hash(), None) {
+            Ok(()) | Err(lmdb::Error::NotFound) => (),
+            Err(err) => return Err(err.into()),
+        }
+        Ok(outcome)
+    }
+
+    /// Writes a deploy to storage.
+    ///
+    /// Returns `true` if the deploy was newly stored.
+    fn put_deploy(&mut self, deploy: &Deploy) -> Result<bool, Error> {
+        self.ensure_writable()?;
+        let mut txn = self.env.begin_rw_txn()?;
+        let outcome = self.put_record(&mut txn, self.deploy_db, deploy.id(), deploy, false)?;
+        txn.commit()?;
+        Ok(outcome)
+    }
+
+    /// Writes the execution results of a block to the metadata of its deploys, and the transfers
+    /// they made to the block's transfers.
+    fn put_execution_results(
+        &self,
+        block_hash: &BlockHash,
+        execution_results: &HashMap<DeployHash, ExecutionResult>,
+    ) -> Result<(), Error> {
+        let mut txn = self.env.begin_rw_txn()?;
+
+        let mut transfers: Vec<Transfer> = vec![];
+
+        for (deploy_hash, execution_result) in execution_results {
+            // The transfers are collected even for results stored already, since the transfers of
+            // the block are overwritten below.
+            if let ExecutionResult::Success { effect, .. } = execution_result {
+                for transform_entry in &effect.transforms {
+                    if let Transform::WriteTransfer(transfer) = &transform_entry.transform {
+                        transfers.push(*transfer);
+                    }
+                }
+            }
+
+            let mut metadata = self
+                .get_deploy_metadata(&mut txn, deploy_hash)?
+                .unwrap_or_default();
+
+            // If we have a previous execution result, we enforce that it is the same.
+            if let Some(prev) = metadata.execution_results.get(block_hash) {
+                if prev != execution_result {
+                    return Err(Error::DuplicateExecutionResult {
+                        deploy_hash: *deploy_hash,
+                        block_hash: *block_hash,
+                    });
+                }
+
+                // We can now skip adding, as the result is the same.
+                continue;
+            }
+
+            // TODO: this is currently done like this because rpc get_deploy returns the
+            // data, but the organization of deploy, block_hash, and
+            // execution_result is incorrectly represented. it should be
+            // inverted; for a given block_hash 0n deploys and each deploy has exactly 1
+            // result (aka deploy_metadata in this context).
+
+            // Update metadata and write back to db.
+            metadata
+                .execution_results
+                .insert(*block_hash, execution_result.clone());
+            let was_written =
+                txn.put_value(self.deploy_metadata_db, deploy_hash, &metadata, true)?;
+            assert!(
+                was_written,
+                "failed to write deploy metadata for block_hash {} deploy_hash {}",
+                block_hash, deploy_hash
+            );
+        }
+
+        let was_written = txn.put_value(self.transfer_db, block_hash, &transfers, true)?;
+        assert!(
+            was_written,
+            "failed to write transfers for block_hash {}",
+            block_hash
+        );
+
+        txn.commit()?;
+        Ok(())
+    }
+
     /// Handles a storage request.
     fn handle_storage_request<REv>(&mut self, req: StorageRequest) -> Result<Effects<Event>, Error>
     where
@@ -345,14 +1302,7 @@ impl Storage {
         // average the actual execution time will be very low.
         Ok(match req {
             StorageRequest::PutBlock { block, responder } => {
-                let mut txn = self.env.begin_rw_txn()?;
-                let outcome = txn.put_value(self.block_db, block.hash(), &block, true)?;
-                txn.commit()?;
-                insert_to_block_indices(
-                    &mut self.block_height_index,
-                    &mut self.switch_block_era_id_index,
-                    block.as_ref(),
-                )?;
+                let outcome = split_transient(self.apply_logged(Intent::PutBlock(block)))?;
                 responder.respond(outcome).ignore()
             }
             StorageRequest::GetBlock {
@@ -407,6 +1357,17 @@ impl Storage {
                         .map(|block| block.header().clone()),
                 )
                 .ignore(),
+            StorageRequest::GetBlockHeadersByHeight {
+                heights,
+                max_count,
+                responder,
+            } => responder
+                .respond(self.get_block_headers_by_height(
+                    &mut self.env.begin_ro_txn()?,
+                    heights,
+                    max_count,
+                )?)
+                .ignore(),
             StorageRequest::GetBlockTransfers {
                 block_hash,
                 responder,
@@ -414,9 +1375,7 @@ impl Storage {
                 .respond(self.get_transfers(&mut self.env.begin_ro_txn()?, &block_hash)?)
                 .ignore(),
             StorageRequest::PutDeploy { deploy, responder } => {
-                let mut txn = self.env.begin_rw_txn()?;
-                let outcome = txn.put_value(self.deploy_db, deploy.id(), &deploy, false)?;
-                txn.commit()?;
+                let outcome = split_transient(self.put_deploy(&deploy))?;
                 responder.respond(outcome).ignore()
             }
             StorageRequest::GetDeploys {
@@ -442,63 +1401,10 @@ impl Storage {
                 execution_results,
                 responder,
             } => {
-                let mut txn = self.env.begin_rw_txn()?;
-
-                let mut transfers: Vec<Transfer> = vec![];
-
-                for (deploy_hash, execution_result) in execution_results {
-                    let mut metadata = self
-                        .get_deploy_metadata(&mut txn, &deploy_hash)?
-                        .unwrap_or_default();
-
-                    // If we have a previous execution result, we enforce that it is the same.
-                    if let Some(prev) = metadata.execution_results.get(&block_hash) {
-                        if prev != &execution_result {
-                            return Err(Error::DuplicateExecutionResult {
-                                deploy_hash,
-                                block_hash,
-                            });
-                        }
-
-                        // We can now skip adding, as the result is the same.
-                        continue;
-                    }
-
-                    if let ExecutionResult::Success { effect, .. } = execution_result.clone() {
-                        for transform_entry in effect.transforms {
-                            if let Transform::WriteTransfer(transfer) = transform_entry.transform {
-                                transfers.push(transfer);
-                            }
-                        }
-                    }
-
-                    // TODO: this is currently done like this because rpc get_deploy returns the
-                    // data, but the organization of deploy, block_hash, and
-                    // execution_result is incorrectly represented. it should be
-                    // inverted; for a given block_hash 0n deploys and each deploy has exactly 1
-                    // result (aka deploy_metadata in this context).
-
-                    // Update metadata and write back to db.
-                    metadata
-                        .execution_results
-                        .insert(block_hash, execution_result);
-                    let was_written =
-                        txn.put_value(self.deploy_metadata_db, &deploy_hash, &metadata, true)?;
-                    assert!(
-                        was_written,
-                        "failed to write deploy metadata for block_hash {} deploy_hash {}",
-                        block_hash, deploy_hash
-                    );
-                }
-
-                let was_written = txn.put_value(self.transfer_db, &block_hash, &transfers, true)?;
-                assert!(
-                    was_written,
-                    "failed to write transfers for block_hash {}",
-                    block_hash
-                );
-
-                txn.commit()?;
+                self.apply_logged(Intent::PutExecutionResults {
+                    block_hash,
+                    execution_results,
+                })?;
                 responder.respond(()).ignore()
             }
             StorageRequest::GetDeployAndMetadata {
@@ -533,6 +1439,40 @@ impl Storage {
                 version: _version,
                 responder,
             } => responder.respond(self.chainspec_cache.clone()).ignore(),
+            StorageRequest::PutBlob { data, responder } => {
+                self.ensure_writable()?;
+                let mut txn = self.env.begin_rw_txn()?;
+                let blob_hash = self.blob_store.put(&mut txn, &data)?;
+                txn.commit()?;
+                responder.respond(blob_hash).ignore()
+            }
+            StorageRequest::GetBlob {
+                blob_hash,
+                responder,
+            } => responder
+                .respond(
+                    self.blob_store
+                        .get(&mut self.env.begin_ro_txn()?, &blob_hash)?,
+                )
+                .ignore(),
+            StorageRequest::ReleaseBlob {
+                blob_hash,
+                responder,
+            } => {
+                self.ensure_writable()?;
+                let mut txn = self.env.begin_rw_txn()?;
+                let remaining = self.blob_store.release(&mut txn, &blob_hash)?;
+                txn.commit()?;
+                responder.respond(remaining).ignore()
+            }
+            StorageRequest::CollectBlobGarbage { responder } => {
+                self.ensure_writable()?;
+                let mut txn = self.env.begin_rw_txn()?;
+                let deleted = self.blob_store.collect_garbage(&mut txn)?;
+                txn.commit()?;
+                info!(deleted, "collected unreferenced blobs");
+                responder.respond(deleted).ignore()
+            }
         })
     }
 
@@ -548,6 +1488,35 @@ impl Storage {
             .transpose()
     }
 
+    /// Retrieves the headers of the blocks stored at `heights`, at most `max_count` and no more
+    /// than `MAX_BLOCK_HEADER_BATCH` of them.
+    fn get_block_headers_by_height<Tx: Transaction>(
+        &self,
+        tx: &mut Tx,
+        heights: Range<u64>,
+        max_count: usize,
+    ) -> Result<BlockHeaderBatch, LmdbExtError> {
+        // `BTreeMap::range` panics on a decreasing range.
+        if heights.start >= heights.end {
+            return Ok(BlockHeaderBatch {
+                headers: Vec::new(),
+                next_height: None,
+            });
+        }
+        let max_count = cmp::min(cmp::max(max_count, 1), MAX_BLOCK_HEADER_BATCH);
+        let mut indexed = self.block_height_index.range(heights);
+        let mut headers = Vec::new();
+        for (_, block_hash) in indexed.by_ref().take(max_count) {
+            if let Some(block) = self.get_single_block(tx, block_hash)? {
+                headers.push(block.take_header());
+            }
+        }
+        Ok(BlockHeaderBatch {
+            headers,
+            next_height: indexed.next().map(|(height, _)| *height),
+        })
+    }
+
     /// Retrieves single switch block by era ID by looking it up in the index and returning it.
     fn get_switch_block_by_era_id<Tx: Transaction>(
         &self,
@@ -561,12 +1530,24 @@ impl Storage {
     }
 
     /// Retrieves a single block in a separate transaction from storage.
+    ///
+    /// Blocks written before bodies were stored separately are read as they are.
     fn get_single_block<Tx: Transaction>(
         &self,
         tx: &mut Tx,
         block_hash: &BlockHash,
     ) -> Result<Option<Block>, LmdbExtError> {
-        tx.get_value(self.block_db, &block_hash)
+        let stored: StoredBlock = match tx.get_value(self.block_header_db, block_hash)? {
+            Some(stored) => stored,
+            None => return tx.get_value(self.block_db, block_hash),
+        };
+        match self.blob_store.get(tx, &stored.body_hash)? {
+            Some(raw_body) => Ok(Some(stored.into_block(lmdb_ext::deserialize(&raw_body)?))),
+            None => Err(LmdbExtError::DataCorrupted(Box::new(MissingBlockBody {
+                block_hash: *block_hash,
+                body_hash: stored.body_hash,
+            }))),
+        }
     }
 
     /// Retrieves a set of deploys from storage.
@@ -606,12 +1587,258 @@ impl Storage {
     }
 }
 
-/// Inserts the relevant entries to the two indices.
+impl Drop for Storage {
+    fn drop(&mut self) {
+        if let Replication::Primary { .. } = self.replication {
+            let lock_path = self.root.join(PRIMARY_LOCK_FILENAME);
+            if let Err(err) = fs::remove_file(&lock_path) {
+                warn!(path = %lock_path.display(), %err, "could not remove storage lock file");
+            }
+        }
+    }
+}
+
+/// Reads the epoch counter written by the primary, which is zero if it was never written.
+fn read_epoch(path: &Path) -> Result<u64, Error> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents.trim().parse().map_err(|_| {
+            Error::EpochFile(
+                path.to_owned(),
+                io::Error::new(io::ErrorKind::InvalidData, "not an epoch counter"),
+            )
+        }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(Error::EpochFile(path.to_owned(), err)),
+    }
+}
+
+/// Writes the epoch counter, replacing the file atomically so replicas never read a partial value.
+fn write_epoch(path: &Path, epoch: u64) -> Result<(), Error> {
+    let tmp_path = path.with_extension("epoch.tmp");
+    fs::write(&tmp_path, epoch.to_string())
+        .and_then(|()| fs::rename(&tmp_path, path))
+        .map_err(|err| Error::EpochFile(path.to_owned(), err))
+}
+
+/// Returns the hashes of the blocks logged for the epochs after `last_epoch` up to `epoch`.
 ///
-/// If a duplicate entry is encountered, neither index is updated and an error is returned.
-fn insert_to_block_indices(
+/// Returns `None` if any of these epochs has no block logged, i.e. it was started by the primary
+/// restarting rather than by storing a block.
+fn logged_blocks<Tx: Transaction>(
+    txn: &Tx,
+    block_log_db: Database,
+    last_epoch: u64,
+    epoch: u64,
+) -> Result<Option<Vec<BlockHash>>, Error> {
+    let mut cursor = txn.open_ro_cursor(block_log_db)?;
+    let mut block_hashes = Vec::new();
+    let mut expected_epoch = last_epoch + 1;
+    for (raw_key, raw_val) in cursor.iter_from((last_epoch + 1).to_be_bytes()) {
+        if expected_epoch > epoch {
+            break;
+        }
+        if raw_key != expected_epoch.to_be_bytes() {
+            return Ok(None);
+        }
+        let block_hash = BlockHash::new(
+            Digest::try_from(raw_val).map_err(|_| Error::CorruptBlockLog(expected_epoch))?,
+        );
+        block_hashes.push(block_hash);
+        expected_epoch += 1;
+    }
+    Ok(if expected_epoch > epoch {
+        Some(block_hashes)
+    } else {
+        None
+    })
+}
+
+/// Reads the process ID of the primary from its lock file, returning `None` if there is none.
+fn read_primary_lock(path: &Path) -> Result<Option<u32>, Error> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents.trim().parse().map(Some).map_err(|_| {
+            Error::PrimaryLockFile(
+                path.to_owned(),
+                io::Error::new(io::ErrorKind::InvalidData, "not a process ID"),
+            )
+        }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::PrimaryLockFile(path.to_owned(), err)),
+    }
+}
+
+/// Returns whether a process with the given ID is running.
+fn is_process_running(pid: u32) -> bool {
+    let pid = match libc::pid_t::try_from(pid) {
+        // Zero and negative IDs address process groups rather than a single process.
+        Ok(pid) if pid > 0 => pid,
+        _ => return false,
+    };
+    // Signal 0 is not delivered, only the existence of the process and our permissions are checked.
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Reads the version marker, returning `None` if the storage has no marker yet.
+fn read_version_marker(path: &Path) -> Result<Option<VersionMarker>, Error> {
+    match fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents).map(Some).map_err(|err| {
+            Error::VersionFile(
+                path.to_owned(),
+                io::Error::new(io::ErrorKind::InvalidData, err),
+            )
+        }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::VersionFile(path.to_owned(), err)),
+    }
+}
+
+/// Writes the version marker, replacing the file atomically.
+fn write_version_marker(path: &Path, marker: &VersionMarker) -> Result<(), Error> {
+    let contents = toml::to_string(marker).map_err(|err| {
+        Error::VersionFile(
+            path.to_owned(),
+            io::Error::new(io::ErrorKind::InvalidData, err),
+        )
+    })?;
+    let tmp_path = path.with_extension("version.tmp");
+    fs::write(&tmp_path, contents)
+        .and_then(|()| fs::rename(&tmp_path, path))
+        .map_err(|err| Error::VersionFile(path.to_owned(), err))
+}
+
+/// Returns the key of the storage metadata recording the record format migration of `db_name`.
+///
+/// The value is empty once the migration is complete, otherwise it holds the key of the last
+/// record rewritten.
+fn record_format_progress_key(db_name: &str) -> String {
+    format!("record_format_progress/{}", db_name)
+}
+
+/// Returns whether all records of the database `db_name` start with their format.
+fn is_record_format_migrated(
+    env: &Environment,
+    meta_db: Database,
+    db_name: &str,
+) -> Result<bool, LmdbExtError> {
+    let txn = env.begin_ro_txn()?;
+    let migrated = match txn.get(meta_db, &record_format_progress_key(db_name)) {
+        Ok(last_key) => last_key.is_empty(),
+        Err(lmdb::Error::NotFound) => false,
+        Err(err) => return Err(err.into()),
+    };
+    txn.commit()?;
+    Ok(migrated)
+}
+
+/// Rewrites the records of `db` written before records started with their format.
+///
+/// Every batch is committed together with the key of its last record, so an interrupted migration
+/// resumes where it left off and never rewrites a record twice.
+fn migrate_record_format(
+    env: &Environment,
+    meta_db: Database,
+    db_name: &str,
+    db: Database,
+) -> Result<(), LmdbExtError> {
+    let progress_key = record_format_progress_key(db_name);
+    let mut migrated = 0;
+    loop {
+        let mut txn = env.begin_rw_txn()?;
+        let last_key = match txn.get(meta_db, &progress_key) {
+            // An empty key marks the migration as complete.
+            Ok([]) => return Ok(()),
+            Ok(last_key) => Some(last_key.to_owned()),
+            Err(lmdb::Error::NotFound) => None,
+            Err(err) => return Err(err.into()),
+        };
+        let batch: Vec<(Vec<u8>, Vec<u8>)> = {
+            let mut cursor = txn.open_ro_cursor(db)?;
+            // Note: `iter_start` has an undocumented panic if called on an empty database. We rely
+            //       on the iterator being at the start when created.
+            let iter = match last_key.as_deref() {
+                Some(key) => cursor.iter_from(key),
+                None => cursor.iter(),
+            };
+            iter.filter(|&(raw_key, _)| Some(raw_key) != last_key.as_deref())
+                .take(RECORD_FORMAT_MIGRATION_BATCH_SIZE)
+                .map(|(raw_key, raw_val)| (raw_key.to_owned(), raw_val.to_owned()))
+                .collect()
+        };
+        for (raw_key, raw_val) in &batch {
+            txn.put(
+                db,
+                raw_key,
+                &lmdb_ext::with_format(raw_val),
+                WriteFlags::empty(),
+            )?;
+        }
+        migrated += batch.len();
+        let complete = batch.len() < RECORD_FORMAT_MIGRATION_BATCH_SIZE;
+        let progress: &[u8] = match batch.last() {
+            Some((raw_key, _)) if !complete => raw_key,
+            _ => &[],
+        };
+        txn.put(meta_db, &progress_key, &progress, WriteFlags::empty())?;
+        txn.commit()?;
+        if complete {
+            if migrated > 0 {
+                info!(db = db_name, migrated, "migrated record format");
+            }
+            return Ok(());
+        }
+    }
+}
+
+/// Adds all blocks in the block databases not indexed yet to the two indices.
+///
+/// The bodies of the blocks are not needed for indexing, so blocks referring to their bodies are
+/// indexed without loading them.
+fn reindex_blocks(
+    env: &Environment,
+    block_header_db: Database,
+    legacy_block_db: Database,
     block_height_index: &mut BTreeMap<u64, BlockHash>,
     switch_block_era_id_index: &mut BTreeMap<EraId, BlockHash>,
+) -> Result<(), Error> {
+    let known: HashSet<Vec<u8>> = block_height_index
+        .values()
+        .map(|block_hash| block_hash.as_ref().to_vec())
+        .collect();
+    let block_txn = env.begin_ro_txn()?;
+
+    for &(db, is_legacy) in &[(block_header_db, false), (legacy_block_db, true)] {
+        let mut cursor = block_txn.open_ro_cursor(db)?;
+
+        // Note: `iter_start` has an undocumented panic if called on an empty database. We rely on
+        //       the iterator being at the start when created.
+        for (raw_key, raw_val) in cursor.iter() {
+            if known.contains(raw_key) {
+                continue;
+            }
+            let block: Block = if is_legacy {
+                lmdb_ext::deserialize(raw_val)?
+            } else {
+                lmdb_ext::deserialize::<StoredBlock>(raw_val)?.into_block_without_body()
+            };
+            // We use the opportunity for a small integrity check.
+            assert_eq!(
+                raw_key,
+                block.hash().as_ref(),
+                "found corrupt block in database"
+            );
+            insert_to_block_indices(block_height_index, switch_block_era_id_index, &block)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a block can be added to the two indices without conflicting with another block.
+fn check_block_indices(
+    block_height_index: &BTreeMap<u64, BlockHash>,
+    switch_block_era_id_index: &BTreeMap<EraId, BlockHash>,
     block: &Block,
 ) -> Result<(), Error> {
     if let Some(first) = block_height_index.get(&block.height()) {
@@ -625,22 +1852,33 @@ fn insert_to_block_indices(
     }
 
     if block.header().switch_block() {
-        match switch_block_era_id_index.entry(block.header().era_id()) {
-            Entry::Vacant(entry) => {
-                let _ = entry.insert(*block.hash());
-            }
-            Entry::Occupied(entry) => {
-                if entry.get() != block.hash() {
-                    return Err(Error::DuplicateEraIdIndex {
-                        era_id: block.header().era_id(),
-                        first: *entry.get(),
-                        second: *block.hash(),
-                    });
-                }
+        if let Some(first) = switch_block_era_id_index.get(&block.header().era_id()) {
+            if first != block.hash() {
+                return Err(Error::DuplicateEraIdIndex {
+                    era_id: block.header().era_id(),
+                    first: *first,
+                    second: *block.hash(),
+                });
             }
         }
     }
 
+    Ok(())
+}
+
+/// Inserts the relevant entries to the two indices.
+///
+/// If a duplicate entry is encountered, neither index is updated and an error is returned.
+fn insert_to_block_indices(
+    block_height_index: &mut BTreeMap<u64, BlockHash>,
+    switch_block_era_id_index: &mut BTreeMap<EraId, BlockHash>,
+    block: &Block,
+) -> Result<(), Error> {
+    check_block_indices(block_height_index, switch_block_era_id_index, block)?;
+
+    if block.header().switch_block() {
+        let _ = switch_block_era_id_index.insert(block.header().era_id(), *block.hash());
+    }
     let _ = block_height_index.insert(block.height(), *block.hash());
     Ok(())
 }
@@ -669,6 +1907,21 @@ pub struct Config {
     ///
     /// The size should be a multiple of the OS page size.
     max_state_store_size: usize,
+    /// The maximum size of the database to use for the blob store.
+    ///
+    /// The size should be a multiple of the OS page size.
+    max_blob_store_size: usize,
+    /// Whether to store blocks and deploys zstd compressed.
+    ///
+    /// Existing uncompressed records are compressed in the background once enabled.
+    enable_compression: bool,
+    /// The maximum number of read replicas which may open the database at the same time.
+    ///
+    /// If zero, read replicas are disabled.
+    max_read_replicas: u32,
+    /// Whether to open the database as a read replica, set by the read replica reactor.
+    #[serde(skip)]
+    read_replica: bool,
 }
 
 impl Default for Config {
@@ -680,14 +1933,49 @@ impl Default for Config {
             max_deploy_store_size: DEFAULT_MAX_DEPLOY_STORE_SIZE,
             max_deploy_metadata_store_size: DEFAULT_MAX_DEPLOY_METADATA_STORE_SIZE,
             max_state_store_size: DEFAULT_MAX_STATE_STORE_SIZE,
+            max_blob_store_size: DEFAULT_MAX_BLOB_STORE_SIZE,
+            enable_compression: false,
+            max_read_replicas: 0,
+            read_replica: false,
         }
     }
 }
 
 impl Config {
+    /// Returns whether blocks and deploys are stored compressed.
+    pub(crate) fn enable_compression(&self) -> bool {
+        self.enable_compression
+    }
+
+    /// Returns the maximum number of read replicas, zero if they are disabled.
+    pub(crate) fn max_read_replicas(&self) -> u32 {
+        self.max_read_replicas
+    }
+
+    /// Returns the configuration for a read replica of the storage configured by `self`.
+    pub(crate) fn into_read_replica(self) -> Self {
+        Config {
+            read_replica: true,
+            ..self
+        }
+    }
+
+    /// Checks the configuration for problems not caught while parsing.
+    pub(crate) fn validate(&self, validator: &mut ConfigValidator) {
+        validator.ensure_writable_dir("path", &self.path);
+        validator.ensure_non_zero("max_block_store_size", self.max_block_store_size);
+        validator.ensure_non_zero("max_deploy_store_size", self.max_deploy_store_size);
+        validator.ensure_non_zero(
+            "max_deploy_metadata_store_size",
+            self.max_deploy_metadata_store_size,
+        );
+        validator.ensure_non_zero("max_state_store_size", self.max_state_store_size);
+        validator.ensure_non_zero("max_blob_store_size", self.max_blob_store_size);
+    }
+
     /// Returns a default `Config` suitable for tests, along with a `TempDir` which must be kept
     /// alive for the duration of the test since its destructor removes the dir from the filesystem.
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub(crate) fn default_for_tests() -> (Self, TempDir) {
         let tempdir = tempfile::tempdir().expect("should get tempdir");
         let path = tempdir.path().join("lmdb");
@@ -705,7 +1993,69 @@ impl Display for Event {
         match self {
             Event::StorageRequest(req) => req.fmt(f),
             Event::StateStoreRequest(req) => req.fmt(f),
+            Event::CompressionMigrationStep => write!(f, "compression migration step"),
+            Event::BlockBodyMigrationStep => write!(f, "block body migration step"),
+        }
+    }
+}
+
+// Direct access for the deploy archive tooling, which runs against the storage of a stopped node.
+impl Storage {
+    /// Returns the hashes of all stored deploys, or of those included in the blocks of `era_id`.
+    pub(crate) fn read_deploy_hashes(
+        &self,
+        era_id: Option<EraId>,
+    ) -> Result<Vec<DeployHash>, Error> {
+        let mut txn = self.env.begin_ro_txn()?;
+        let era_id = match era_id {
+            Some(era_id) => era_id,
+            None => {
+                let mut cursor = txn.open_ro_cursor(self.deploy_db)?;
+                return cursor
+                    .iter()
+                    .map(|(raw_key, _)| {
+                        Digest::try_from(raw_key)
+                            .map(DeployHash::new)
+                            .map_err(|_| Error::CorruptDeployHash(raw_key.to_vec()))
+                    })
+                    .collect();
+            }
+        };
+        let mut deploy_hashes = Vec::new();
+        for block_hash in self.block_height_index.values() {
+            match self.get_single_block(&mut txn, block_hash)? {
+                Some(block) if block.header().era_id() == era_id => {
+                    deploy_hashes.extend(block.header().deploy_hashes());
+                    deploy_hashes.extend(block.header().transfer_hashes());
+                }
+                _ => (),
+            }
         }
+        Ok(deploy_hashes)
+    }
+
+    /// Reads a single deploy.
+    pub(crate) fn read_deploy(&self, deploy_hash: &DeployHash) -> Result<Option<Deploy>, Error> {
+        let mut txn = self.env.begin_ro_txn()?;
+        Ok(txn.get_value(self.deploy_db, deploy_hash)?)
+    }
+
+    /// Writes a deploy, returning `true` if it was newly stored.
+    pub(crate) fn write_deploy(&mut self, deploy: &Deploy) -> Result<bool, Error> {
+        self.put_deploy(deploy)
+    }
+}
+
+#[cfg(any(test, feature = "testing", feature = "debug-assertions"))]
+impl Storage {
+    /// Returns the height of the highest block stored, if any.
+    pub fn highest_block_height(&self) -> Option<u64> {
+        self.block_height_index.keys().next_back().copied()
+    }
+
+    /// Returns the hash of the block stored at `height`, if any.
+    pub fn block_hash_at_height(&self, height: u64) -> Option<BlockHash> {
+        self.block_height_index.get(&height).copied()
     }
 }
 
@@ -771,4 +2121,9 @@ impl Storage {
             })
             .collect()
     }
+
+    /// Returns the number of blocks in the height index.
+    pub fn block_count(&self) -> usize {
+        self.block_height_index.len()
+    }
 }
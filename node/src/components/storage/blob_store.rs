@@ -0,0 +1,153 @@
+//! Content-addressable blob store.
+//!
+//! Large artifacts such as wasm modules, chunked tries or upgrade packages are stored once under
+//! the hash of their contents, no matter how many deploys or blocks refer to them. Every blob
+//! carries a reference count, which is incremented each time the same bytes are put and decremented
+//! when a reference is released.
+//!
+//! Blobs whose reference count dropped to zero are not deleted right away, but only once garbage
+//! collection is run. This allows a blob to be released and put again cheaply, e.g. when a deploy
+//! is replaced by another one using the same module.
+
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+
+use super::lmdb_ext::{LmdbExtError, TransactionExt, WriteTransactionExt};
+use crate::crypto::hash::{self, Digest};
+
+/// Name of the database holding the blob contents.
+const BLOB_DB_NAME: &str = "blobs";
+/// Name of the database holding the blob reference counts.
+const BLOB_REFCOUNT_DB_NAME: &str = "blob_refcounts";
+
+/// Hash-addressed, reference counted store for large artifacts.
+#[derive(Debug)]
+pub(super) struct BlobStore {
+    /// The blob contents, keyed by their hash.
+    blob_db: Database,
+    /// The number of references held for every blob, keyed by the blob hash.
+    refcount_db: Database,
+}
+
+impl BlobStore {
+    /// Opens or creates the databases required for the blob store.
+    pub(super) fn new(env: &Environment) -> Result<Self, lmdb::Error> {
+        Ok(BlobStore {
+            blob_db: env.create_db(Some(BLOB_DB_NAME), DatabaseFlags::empty())?,
+            refcount_db: env.create_db(Some(BLOB_REFCOUNT_DB_NAME), DatabaseFlags::empty())?,
+        })
+    }
+
+    /// Opens the existing databases of the blob store, e.g. in a read-only environment.
+    pub(super) fn open(env: &Environment) -> Result<Self, lmdb::Error> {
+        Ok(BlobStore {
+            blob_db: env.open_db(Some(BLOB_DB_NAME))?,
+            refcount_db: env.open_db(Some(BLOB_REFCOUNT_DB_NAME))?,
+        })
+    }
+
+    /// Puts a blob into the store, adding a reference to it.
+    ///
+    /// The contents are only written if the blob is not stored already. Returns the hash of the
+    /// blob.
+    pub(super) fn put(
+        &self,
+        txn: &mut RwTransaction<'_>,
+        data: &[u8],
+    ) -> Result<Digest, LmdbExtError> {
+        let blob_hash = hash::hash(data);
+        let refcount = self.refcount(txn, &blob_hash)?;
+
+        // An unreferenced blob may still be present if it has not been garbage collected yet.
+        if refcount == 0 {
+            match txn.put(self.blob_db, &blob_hash, &data, WriteFlags::NO_OVERWRITE) {
+                Ok(()) | Err(lmdb::Error::KeyExist) => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        txn.put_value(self.refcount_db, &blob_hash, &(refcount + 1), true)?;
+
+        Ok(blob_hash)
+    }
+
+    /// Retrieves the contents of a blob.
+    pub(super) fn get<Tx: Transaction>(
+        &self,
+        txn: &mut Tx,
+        blob_hash: &Digest,
+    ) -> Result<Option<Vec<u8>>, LmdbExtError> {
+        match txn.get(self.blob_db, blob_hash) {
+            Ok(raw) => Ok(Some(raw.to_owned())),
+            Err(lmdb::Error::NotFound) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns whether a blob is stored and referenced at least once.
+    pub(super) fn is_referenced<Tx: Transaction>(
+        &self,
+        txn: &mut Tx,
+        blob_hash: &Digest,
+    ) -> Result<bool, LmdbExtError> {
+        Ok(self.refcount(txn, blob_hash)? > 0)
+    }
+
+    /// Releases a reference to a blob.
+    ///
+    /// Returns the number of remaining references, or `None` if the blob is not referenced at all.
+    pub(super) fn release(
+        &self,
+        txn: &mut RwTransaction<'_>,
+        blob_hash: &Digest,
+    ) -> Result<Option<u64>, LmdbExtError> {
+        let refcount = self.refcount(txn, blob_hash)?;
+        if refcount == 0 {
+            return Ok(None);
+        }
+
+        let remaining = refcount - 1;
+        txn.put_value(self.refcount_db, blob_hash, &remaining, true)?;
+        Ok(Some(remaining))
+    }
+
+    /// Deletes all blobs that are no longer referenced.
+    ///
+    /// Returns the number of blobs deleted.
+    pub(super) fn collect_garbage(
+        &self,
+        txn: &mut RwTransaction<'_>,
+    ) -> Result<usize, LmdbExtError> {
+        let mut unreferenced = Vec::new();
+        {
+            let mut cursor = txn.open_ro_cursor(self.refcount_db)?;
+            // Note: `iter_start` has an undocumented panic if called on an empty database. We rely
+            //       on the iterator being at the start when created.
+            for (raw_key, raw_val) in cursor.iter() {
+                let refcount: u64 = super::lmdb_ext::deserialize(raw_val)?;
+                if refcount == 0 {
+                    unreferenced.push(raw_key.to_owned());
+                }
+            }
+        }
+
+        for raw_key in &unreferenced {
+            match txn.del(self.blob_db, raw_key, None) {
+                Ok(()) | Err(lmdb::Error::NotFound) => (),
+                Err(err) => return Err(err.into()),
+            }
+            txn.del(self.refcount_db, raw_key, None)?;
+        }
+
+        Ok(unreferenced.len())
+    }
+
+    /// Returns the current reference count of a blob, zero if unknown.
+    fn refcount<Tx: Transaction>(
+        &self,
+        txn: &mut Tx,
+        blob_hash: &Digest,
+    ) -> Result<u64, LmdbExtError> {
+        Ok(txn
+            .get_value(self.refcount_db, blob_hash)?
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,70 @@
+//! Deduplicated storage of block bodies.
+//!
+//! The deploy and transfer hashes of a block are stored apart from the rest of it, in the blob
+//! store under the hash of their serialized form, and only referenced from the block record. Since
+//! many blocks include no deploys at all, and some include the very same ones, identical bodies
+//! are stored once no matter how many blocks refer to them.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    crypto::hash::Digest,
+    types::{Block, BlockHash, DeployHash},
+};
+
+/// The deploy and transfer hashes included in a block.
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct BlockBody {
+    deploy_hashes: Vec<DeployHash>,
+    transfer_hashes: Vec<DeployHash>,
+}
+
+/// A block stored without its body, referring to the body by its hash instead.
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct StoredBlock {
+    /// The block, with no deploy and transfer hashes.
+    block: Block,
+    /// The hash of the block's body in the blob store.
+    pub(super) body_hash: Digest,
+}
+
+/// The body a stored block refers to is missing from the blob store.
+#[derive(Debug, Error)]
+#[error("body {body_hash} of block {block_hash} is missing")]
+pub(super) struct MissingBlockBody {
+    pub(super) block_hash: BlockHash,
+    pub(super) body_hash: Digest,
+}
+
+/// Splits a block into the block without its body and the body.
+pub(super) fn split(block: &Block) -> (Block, BlockBody) {
+    let mut block = block.clone();
+    let (deploy_hashes, transfer_hashes) = block.take_deploy_and_transfer_hashes();
+    (
+        block,
+        BlockBody {
+            deploy_hashes,
+            transfer_hashes,
+        },
+    )
+}
+
+impl StoredBlock {
+    /// Creates a stored block from a block split off its body and the hash of the body.
+    pub(super) fn new(block: Block, body_hash: Digest) -> Self {
+        StoredBlock { block, body_hash }
+    }
+
+    /// Returns the block without its deploy and transfer hashes, e.g. for indexing.
+    pub(super) fn into_block_without_body(self) -> Block {
+        self.block
+    }
+
+    /// Reassembles the full block from the stored block and its body.
+    pub(super) fn into_block(self, body: BlockBody) -> Block {
+        let mut block = self.block;
+        block.restore_deploy_and_transfer_hashes(body.deploy_hashes, body.transfer_hashes);
+        block
+    }
+}
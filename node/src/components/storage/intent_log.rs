@@ -0,0 +1,138 @@
+//! Intent log for updates spanning several databases.
+//!
+//! A single LMDB transaction is atomic, but storing a block also updates the in-memory indices and
+//! the epoch file read by replicas, and execution results update the metadata of many deploys
+//! besides the transfers of the block. Before such an update is applied, it is recorded in full as
+//! an intent, which is only removed once the whole update has been applied.
+//!
+//! Intents still present on startup belong to updates interrupted by a crash. They are replayed,
+//! which is safe as applying an update again has no further effect. An intent which can't be
+//! applied is rolled back by discarding it, as none of its database writes was committed.
+
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fmt::{self, Display, Formatter},
+};
+
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, Transaction};
+use serde::{Deserialize, Serialize};
+
+use casper_types::ExecutionResult;
+
+use super::lmdb_ext::{self, LmdbExtError, WriteTransactionExt};
+use crate::types::{Block, BlockHash, DeployHash};
+
+/// Name of the database holding the intents.
+const INTENT_DB_NAME: &str = "intents";
+
+/// An update spanning several databases.
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) enum Intent {
+    /// Storing a block and adding it to the indices.
+    PutBlock(Box<Block>),
+    /// Storing the execution results of the deploys in a block.
+    PutExecutionResults {
+        block_hash: BlockHash,
+        execution_results: HashMap<DeployHash, ExecutionResult>,
+    },
+}
+
+impl Display for Intent {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Intent::PutBlock(block) => write!(formatter, "put block {}", block.hash()),
+            Intent::PutExecutionResults {
+                block_hash,
+                execution_results,
+            } => write!(
+                formatter,
+                "put {} execution results of block {}",
+                execution_results.len(),
+                block_hash
+            ),
+        }
+    }
+}
+
+/// The intents of the updates being applied, keyed by a sequence number.
+#[derive(Debug)]
+pub(super) struct IntentLog {
+    /// The intents, keyed by their big-endian sequence number.
+    db: Database,
+    /// The sequence number of the next intent.
+    next_id: u64,
+}
+
+impl IntentLog {
+    /// Opens or creates the database of the intent log.
+    pub(super) fn new(env: &Environment) -> Result<Self, LmdbExtError> {
+        let db = env.create_db(Some(INTENT_DB_NAME), DatabaseFlags::empty())?;
+        IntentLog::with_db(env, db)
+    }
+
+    /// Opens the existing database of the intent log, e.g. in a read-only environment.
+    pub(super) fn open(env: &Environment) -> Result<Self, LmdbExtError> {
+        let db = env.open_db(Some(INTENT_DB_NAME))?;
+        IntentLog::with_db(env, db)
+    }
+
+    fn with_db(env: &Environment, db: Database) -> Result<Self, LmdbExtError> {
+        let next_id = {
+            let txn = env.begin_ro_txn()?;
+            let mut cursor = txn.open_ro_cursor(db)?;
+            // Note: `iter_start` has an undocumented panic if called on an empty database. We rely
+            //       on the iterator being at the start when created.
+            cursor
+                .iter()
+                .last()
+                .map(|(raw_key, _)| decode_id(raw_key) + 1)
+                .unwrap_or_default()
+        };
+        Ok(IntentLog { db, next_id })
+    }
+
+    /// Records `intent` in a transaction of its own, returning its sequence number.
+    pub(super) fn record(
+        &mut self,
+        env: &Environment,
+        intent: &Intent,
+    ) -> Result<u64, LmdbExtError> {
+        let id = self.next_id;
+        let mut txn = env.begin_rw_txn()?;
+        txn.put_value(self.db, &id.to_be_bytes(), intent, true)?;
+        txn.commit()?;
+        self.next_id += 1;
+        Ok(id)
+    }
+
+    /// Removes the intent with sequence number `id`, once its update has been applied.
+    pub(super) fn complete(&self, env: &Environment, id: u64) -> Result<(), LmdbExtError> {
+        let mut txn = env.begin_rw_txn()?;
+        match txn.del(self.db, &id.to_be_bytes(), None) {
+            Ok(()) | Err(lmdb::Error::NotFound) => (),
+            Err(err) => return Err(err.into()),
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Returns the intents which have not been completed, in the order they were recorded.
+    pub(super) fn pending(&self, env: &Environment) -> Result<Vec<(u64, Intent)>, LmdbExtError> {
+        let txn = env.begin_ro_txn()?;
+        let mut cursor = txn.open_ro_cursor(self.db)?;
+        cursor
+            .iter()
+            .map(|(raw_key, raw_val)| Ok((decode_id(raw_key), lmdb_ext::deserialize(raw_val)?)))
+            .collect()
+    }
+}
+
+/// Decodes the sequence number of an intent from its key.
+fn decode_id(raw_key: &[u8]) -> u64 {
+    u64::from_be_bytes(
+        raw_key
+            .try_into()
+            .expect("intent keys should be 8 byte sequence numbers"),
+    )
+}
@@ -9,11 +9,52 @@
 //!
 //! Serialization errors are unified into a generic, type erased `std` error to allow for easy
 //! interchange of the serialization format if desired.
+//!
+//! ## Record format
+//!
+//! Every record starts with a single byte naming its `RecordFormat`, followed by the serialized
+//! value, optionally zstd compressed. Reading is transparent, any value may be either compressed or
+//! not. Records of a format unknown to this node, or failing to decompress, are reported as
+//! corrupted.
+//!
+//! Records written before the format byte was introduced are plain bincode. They are rewritten
+//! once when the storage is opened, see `with_format`.
 
 use lmdb::{Database, RwTransaction, Transaction, WriteFlags};
 use serde::{de::DeserializeOwned, Serialize};
 use thiserror::Error;
 
+/// The format of a record, stored as its first byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum RecordFormat {
+    /// A bincode serialized value.
+    Bincode = 0,
+    /// A zstd compressed, bincode serialized value.
+    ZstdBincode = 1,
+}
+
+impl RecordFormat {
+    /// Splits a raw record into its format and payload.
+    fn split(raw: &[u8]) -> Result<(RecordFormat, &[u8]), LmdbExtError> {
+        match raw.split_first() {
+            Some((&byte, payload)) if byte == RecordFormat::Bincode as u8 => {
+                Ok((RecordFormat::Bincode, payload))
+            }
+            Some((&byte, payload)) if byte == RecordFormat::ZstdBincode as u8 => {
+                Ok((RecordFormat::ZstdBincode, payload))
+            }
+            Some((&byte, _)) => Err(LmdbExtError::DataCorrupted(
+                format!("unknown record format {}", byte).into(),
+            )),
+            None => Err(LmdbExtError::DataCorrupted("empty record".into())),
+        }
+    }
+}
+
+/// zstd compression level used for records, 0 selects the zstd default.
+const COMPRESSION_LEVEL: i32 = 0;
+
 /// Error wrapper for lower-level storage errors.
 ///
 /// Used to classify storage errors, allowing more accurate reporting on potential issues and
@@ -94,6 +135,17 @@ pub(super) trait WriteTransactionExt {
         value: &V,
         overwrite: bool,
     ) -> Result<bool, LmdbExtError>;
+
+    /// Helper function to write a value to a database in compressed form.
+    ///
+    /// Behaves like `put_value` otherwise.
+    fn put_compressed_value<K: AsRef<[u8]>, V: Serialize>(
+        &mut self,
+        db: Database,
+        key: &K,
+        value: &V,
+        overwrite: bool,
+    ) -> Result<bool, LmdbExtError>;
 }
 
 impl<T> TransactionExt for T
@@ -123,31 +175,89 @@ impl WriteTransactionExt for RwTransaction<'_> {
         value: &V,
         overwrite: bool,
     ) -> Result<bool, LmdbExtError> {
-        let buffer = serialize(value)?;
-
-        let flags = if overwrite {
-            WriteFlags::empty()
-        } else {
-            WriteFlags::NO_OVERWRITE
-        };
-
-        match self.put(db, key, &buffer, flags) {
-            Ok(()) => Ok(true),
-            // If we did not add the value due to it already existing, just return `false`.
-            Err(lmdb::Error::KeyExist) => Ok(false),
-            Err(err) => Err(err.into()),
-        }
+        put_raw(self, db, key, &serialize(value)?, overwrite)
     }
+
+    fn put_compressed_value<K: AsRef<[u8]>, V: Serialize>(
+        &mut self,
+        db: Database,
+        key: &K,
+        value: &V,
+        overwrite: bool,
+    ) -> Result<bool, LmdbExtError> {
+        let raw = bincode::serialize(value).map_err(|err| LmdbExtError::Other(Box::new(err)))?;
+        put_raw(self, db, key, &compressed_record(&raw)?, overwrite)
+    }
+}
+
+/// Writes an already serialized buffer to a database.
+fn put_raw<K: AsRef<[u8]>>(
+    txn: &mut RwTransaction<'_>,
+    db: Database,
+    key: &K,
+    buffer: &[u8],
+    overwrite: bool,
+) -> Result<bool, LmdbExtError> {
+    let flags = if overwrite {
+        WriteFlags::empty()
+    } else {
+        WriteFlags::NO_OVERWRITE
+    };
+
+    match txn.put(db, key, &buffer, flags) {
+        Ok(()) => Ok(true),
+        // If we did not add the value due to it already existing, just return `false`.
+        Err(lmdb::Error::KeyExist) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Compresses a raw record, returning `None` if it is compressed already.
+pub(super) fn compress(raw: &[u8]) -> Result<Option<Vec<u8>>, LmdbExtError> {
+    match RecordFormat::split(raw)? {
+        (RecordFormat::Bincode, payload) => compressed_record(payload).map(Some),
+        (RecordFormat::ZstdBincode, _) => Ok(None),
+    }
+}
+
+/// Creates a compressed record from a bincode serialized value.
+fn compressed_record(payload: &[u8]) -> Result<Vec<u8>, LmdbExtError> {
+    let mut buffer = vec![RecordFormat::ZstdBincode as u8];
+    buffer.extend(
+        zstd::encode_all(payload, COMPRESSION_LEVEL)
+            .map_err(|err| LmdbExtError::Other(Box::new(err)))?,
+    );
+    Ok(buffer)
+}
+
+/// Converts a record written before the format byte was introduced into an uncompressed record.
+pub(super) fn with_format(legacy: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(legacy.len() + 1);
+    buffer.push(RecordFormat::Bincode as u8);
+    buffer.extend_from_slice(legacy);
+    buffer
 }
 
 /// Deserializes from a buffer.
 #[inline(always)]
 pub(super) fn deserialize<T: DeserializeOwned>(raw: &[u8]) -> Result<T, LmdbExtError> {
-    bincode::deserialize(raw).map_err(|err| LmdbExtError::DataCorrupted(Box::new(err)))
+    match RecordFormat::split(raw)? {
+        (RecordFormat::Bincode, payload) => bincode::deserialize(payload),
+        (RecordFormat::ZstdBincode, payload) => {
+            // A record failing to decompress is corrupted, it is never read as uncompressed.
+            let decompressed = zstd::decode_all(payload)
+                .map_err(|err| LmdbExtError::DataCorrupted(Box::new(err)))?;
+            bincode::deserialize(&decompressed)
+        }
+    }
+    .map_err(|err| LmdbExtError::DataCorrupted(Box::new(err)))
 }
 
 /// Serializes into a buffer.
 #[inline(always)]
 pub(super) fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, LmdbExtError> {
-    bincode::serialize(value).map_err(|err| LmdbExtError::Other(Box::new(err)))
+    let mut buffer = vec![RecordFormat::Bincode as u8];
+    bincode::serialize_into(&mut buffer, value)
+        .map_err(|err| LmdbExtError::Other(Box::new(err)))?;
+    Ok(buffer)
 }
@@ -0,0 +1,46 @@
+use prometheus::{IntCounter, Registry};
+
+/// Metrics for the storage component.
+#[derive(Debug)]
+pub(super) struct StorageMetrics {
+    /// Number of blocks stored whose body was stored already.
+    pub(super) deduplicated_block_bodies: IntCounter,
+    /// Number of bytes not written since the block body was stored already.
+    pub(super) block_body_bytes_saved: IntCounter,
+    /// Reference to the registry for unregistering.
+    registry: Registry,
+}
+
+impl StorageMetrics {
+    /// Creates a new instance of storage metrics.
+    pub(super) fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let deduplicated_block_bodies = IntCounter::new(
+            "storage_deduplicated_block_bodies",
+            "number of blocks stored whose body was stored already",
+        )?;
+        let block_body_bytes_saved = IntCounter::new(
+            "storage_block_body_bytes_saved",
+            "number of bytes saved by storing identical block bodies only once",
+        )?;
+
+        registry.register(Box::new(deduplicated_block_bodies.clone()))?;
+        registry.register(Box::new(block_body_bytes_saved.clone()))?;
+
+        Ok(StorageMetrics {
+            deduplicated_block_bodies,
+            block_body_bytes_saved,
+            registry: registry.clone(),
+        })
+    }
+}
+
+impl Drop for StorageMetrics {
+    fn drop(&mut self) {
+        self.registry
+            .unregister(Box::new(self.deduplicated_block_bodies.clone()))
+            .expect("did not expect deregistering deduplicated_block_bodies to fail");
+        self.registry
+            .unregister(Box::new(self.block_body_bytes_saved.clone()))
+            .expect("did not expect deregistering block_body_bytes_saved to fail");
+    }
+}
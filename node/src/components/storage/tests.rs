@@ -1,16 +1,24 @@
 //! Unit tests for the storage component.
 
-use std::{borrow::Cow, collections::HashMap, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, fs, ops::Range, sync::Arc};
 
+use lmdb::Transaction;
+use prometheus::Registry;
 use rand::{prelude::SliceRandom, Rng};
 use semver::Version;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use smallvec::smallvec;
 
-use casper_types::ExecutionResult;
+use casper_types::{ExecutionEffect, ExecutionResult, Transfer, Transform, TransformEntry, U512};
 
-use super::{Config, Storage};
+use super::{
+    intent_log::Intent,
+    lmdb_ext::{LmdbExtError, TransactionExt, WriteTransactionExt},
+    BlockHeaderBatch, Config, Event, Storage, TransientWriteError,
+};
 use crate::{
+    components::chainspec_loader::{ActivationPoint, UpgradePoint},
+    crypto::hash::Digest,
     effect::{
         requests::{StateStoreRequest, StorageRequest},
         Multiple,
@@ -29,6 +37,18 @@ use crate::{
 ///
 /// Panics if setting up the storage fixture fails.
 fn storage_fixture(harness: &mut ComponentHarness<()>) -> Storage {
+    storage_fixture_with_compression(harness, false)
+}
+
+/// Storage component test fixture with compression enabled or disabled.
+///
+/// # Panics
+///
+/// Panics if setting up the storage fixture fails.
+fn storage_fixture_with_compression(
+    harness: &mut ComponentHarness<()>,
+    enable_compression: bool,
+) -> Storage {
     const MIB: usize = 1024 * 1024;
 
     // Restrict all stores to 50 mibibytes, to catch issues before filling up the entire disk.
@@ -38,9 +58,13 @@ fn storage_fixture(harness: &mut ComponentHarness<()>) -> Storage {
         max_deploy_store_size: 50 * MIB,
         max_deploy_metadata_store_size: 50 * MIB,
         max_state_store_size: 50 * MIB,
+        max_blob_store_size: 50 * MIB,
+        enable_compression,
+        max_read_replicas: 0,
+        read_replica: false,
     };
 
-    Storage::new(&WithDir::new(harness.tmp.path(), cfg)).expect(
+    Storage::new(&WithDir::new(harness.tmp.path(), cfg), &Registry::new()).expect(
         "could not create storage component
     fixture",
     )
@@ -66,6 +90,25 @@ fn get_block_at_height(
     response
 }
 
+/// Requests a batch of block headers at a range of heights from a storage component.
+fn get_block_headers_by_height(
+    harness: &mut ComponentHarness<()>,
+    storage: &mut Storage,
+    heights: Range<u64>,
+    max_count: usize,
+) -> BlockHeaderBatch {
+    let response = harness.send_request(storage, move |responder| {
+        StorageRequest::GetBlockHeadersByHeight {
+            heights,
+            max_count,
+            responder,
+        }
+        .into()
+    });
+    assert!(harness.is_idle());
+    response
+}
+
 /// Loads a block from a storage component.
 fn get_block(
     harness: &mut ComponentHarness<()>,
@@ -163,7 +206,7 @@ fn put_block(harness: &mut ComponentHarness<()>, storage: &mut Storage, block: B
         StorageRequest::PutBlock { block, responder }.into()
     });
     assert!(harness.is_idle());
-    response
+    response.expect("should not fail to write block")
 }
 
 /// Stores the chainspec in a storage component.
@@ -188,7 +231,7 @@ fn put_deploy(
         StorageRequest::PutDeploy { deploy, responder }.into()
     });
     assert!(harness.is_idle());
-    response
+    response.expect("should not fail to write deploy")
 }
 
 /// Stores execution results in a storage component.
@@ -352,6 +395,35 @@ fn can_retrieve_block_by_height() {
     );
 }
 
+#[test]
+fn can_scan_block_headers_by_height_in_batches() {
+    let mut harness = ComponentHarness::default();
+    let mut storage = storage_fixture(&mut harness);
+
+    let blocks: Vec<_> = [2, 3, 5, 8]
+        .iter()
+        .map(|&height| random_block_at_height(&mut harness.rng, height))
+        .collect();
+    for block in &blocks {
+        put_block(&mut harness, &mut storage, block.clone());
+    }
+    let header = |index: usize| blocks[index].header().clone();
+
+    // Heights without blocks are skipped, and the scan resumes at the next stored block.
+    let batch = get_block_headers_by_height(&mut harness, &mut storage, 0..8, 2);
+    assert_eq!(batch.headers, vec![header(0), header(1)]);
+    assert_eq!(batch.next_height, Some(5));
+
+    // The end of the range is exclusive.
+    let batch = get_block_headers_by_height(&mut harness, &mut storage, 5..8, 2);
+    assert_eq!(batch.headers, vec![header(2)]);
+    assert_eq!(batch.next_height, None);
+
+    let batch = get_block_headers_by_height(&mut harness, &mut storage, 8..3, 2);
+    assert!(batch.headers.is_empty());
+    assert_eq!(batch.next_height, None);
+}
+
 #[test]
 #[should_panic(expected = "duplicate entries")]
 fn different_block_at_height_is_fatal() {
@@ -655,6 +727,48 @@ fn store_identical_execution_results() {
     put_execution_results(&mut harness, &mut storage, block_hash, exec_result);
 }
 
+#[test]
+fn storing_identical_execution_results_again_keeps_transfers() {
+    let mut harness = ComponentHarness::default();
+    let mut storage = storage_fixture(&mut harness);
+
+    let block_hash = BlockHash::random(&mut harness.rng);
+    let deploy_hash = DeployHash::random(&mut harness.rng);
+    let transfer = Transfer {
+        amount: U512::from(harness.rng.gen::<u64>()),
+        ..Transfer::default()
+    };
+    let execution_result = ExecutionResult::Success {
+        effect: ExecutionEffect {
+            operations: vec![],
+            transforms: vec![TransformEntry {
+                key: "transfer".to_string(),
+                transform: Transform::WriteTransfer(transfer),
+            }],
+        },
+        transfers: vec![],
+        events: vec![],
+        cost: U512::zero(),
+    };
+
+    let mut exec_result = HashMap::new();
+    exec_result.insert(deploy_hash, execution_result);
+    put_execution_results(&mut harness, &mut storage, block_hash, exec_result.clone());
+
+    // The transfers of the block are rewritten, so they must include those of known results.
+    put_execution_results(&mut harness, &mut storage, block_hash, exec_result);
+
+    let transfers = harness.send_request(&mut storage, |responder| {
+        StorageRequest::GetBlockTransfers {
+            block_hash,
+            responder,
+        }
+        .into()
+    });
+    assert!(harness.is_idle());
+    assert_eq!(transfers, Some(vec![transfer]));
+}
+
 #[test]
 fn store_and_load_chainspec() {
     let mut harness = ComponentHarness::default();
@@ -823,3 +937,665 @@ fn persist_blocks_deploys_and_deploy_metadata_across_instantiations() {
         *block
     );
 }
+
+/// Destroys the harness and storage component, then rebuilds them using the same directory.
+fn reopen_storage(harness: ComponentHarness<()>) -> (ComponentHarness<()>, Storage) {
+    let (on_disk, rng) = harness.into_parts();
+    let mut harness = ComponentHarness::builder()
+        .on_disk(on_disk)
+        .rng(rng)
+        .build();
+    let storage = storage_fixture(&mut harness);
+    (harness, storage)
+}
+
+#[test]
+fn interrupted_updates_are_replayed_on_startup() {
+    let mut harness = ComponentHarness::default();
+    let mut storage = storage_fixture(&mut harness);
+
+    let deploy = Deploy::random(&mut harness.rng);
+    let block = random_block_at_height(&mut harness.rng, 7);
+    let execution_result: ExecutionResult = harness.rng.gen();
+    put_deploy(&mut harness, &mut storage, Box::new(deploy.clone()));
+
+    // Record the updates without applying them, as if the node crashed right after.
+    let mut execution_results = HashMap::new();
+    execution_results.insert(*deploy.id(), execution_result.clone());
+    let intents = vec![
+        Intent::PutBlock(block.clone()),
+        Intent::PutExecutionResults {
+            block_hash: *block.hash(),
+            execution_results,
+        },
+    ];
+    for intent in &intents {
+        storage
+            .intent_log
+            .record(&storage.env, intent)
+            .expect("should record intent");
+    }
+    assert!(get_block(&mut harness, &mut storage, *block.hash()).is_none());
+    drop(storage);
+
+    let (mut harness, mut storage) = reopen_storage(harness);
+    assert_eq!(
+        get_block_at_height(&mut harness, &mut storage, 7).expect("block should be replayed"),
+        *block
+    );
+    let (_, deploy_metadata) = get_deploy_and_metadata(&mut harness, &mut storage, *deploy.id())
+        .expect("missing deploy we stored earlier");
+    assert_eq!(
+        deploy_metadata.execution_results[block.hash()],
+        execution_result
+    );
+    assert!(storage.intent_log.pending(&storage.env).unwrap().is_empty());
+}
+
+#[test]
+fn interrupted_update_which_cannot_be_applied_is_rolled_back() {
+    let mut harness = ComponentHarness::default();
+    let mut storage = storage_fixture(&mut harness);
+
+    let block = random_block_at_height(&mut harness.rng, 7);
+    let conflicting_block = random_block_at_height(&mut harness.rng, 7);
+    put_block(&mut harness, &mut storage, block.clone());
+    storage
+        .intent_log
+        .record(&storage.env, &Intent::PutBlock(conflicting_block.clone()))
+        .expect("should record intent");
+    drop(storage);
+
+    let (mut harness, mut storage) = reopen_storage(harness);
+    assert!(get_block(&mut harness, &mut storage, *conflicting_block.hash()).is_none());
+    assert_eq!(
+        get_block_at_height(&mut harness, &mut storage, 7).expect("block should be kept"),
+        *block
+    );
+    assert!(storage.intent_log.pending(&storage.env).unwrap().is_empty());
+}
+
+/// Stores a blob in the blob store of a storage component.
+fn put_blob(harness: &mut ComponentHarness<()>, storage: &mut Storage, data: Vec<u8>) -> Digest {
+    let response = harness.send_request(storage, move |responder| {
+        StorageRequest::PutBlob { data, responder }.into()
+    });
+    assert!(harness.is_idle());
+    response
+}
+
+/// Loads a blob from the blob store of a storage component.
+fn get_blob(
+    harness: &mut ComponentHarness<()>,
+    storage: &mut Storage,
+    blob_hash: Digest,
+) -> Option<Vec<u8>> {
+    let response = harness.send_request(storage, move |responder| {
+        StorageRequest::GetBlob {
+            blob_hash,
+            responder,
+        }
+        .into()
+    });
+    assert!(harness.is_idle());
+    response
+}
+
+/// Releases a reference to a blob in the blob store of a storage component.
+fn release_blob(
+    harness: &mut ComponentHarness<()>,
+    storage: &mut Storage,
+    blob_hash: Digest,
+) -> Option<u64> {
+    let response = harness.send_request(storage, move |responder| {
+        StorageRequest::ReleaseBlob {
+            blob_hash,
+            responder,
+        }
+        .into()
+    });
+    assert!(harness.is_idle());
+    response
+}
+
+/// Runs garbage collection on the blob store of a storage component.
+fn collect_blob_garbage(harness: &mut ComponentHarness<()>, storage: &mut Storage) -> usize {
+    let response = harness.send_request(storage, move |responder| {
+        StorageRequest::CollectBlobGarbage { responder }.into()
+    });
+    assert!(harness.is_idle());
+    response
+}
+
+#[test]
+fn blobs_are_deduplicated_and_garbage_collected() {
+    let mut harness = ComponentHarness::default();
+    let mut storage = storage_fixture(&mut harness);
+
+    let data: Vec<u8> = (0..4096).map(|_| harness.rng.gen()).collect();
+
+    // Storing the same bytes twice yields the same hash and two references.
+    let blob_hash = put_blob(&mut harness, &mut storage, data.clone());
+    assert_eq!(
+        put_blob(&mut harness, &mut storage, data.clone()),
+        blob_hash
+    );
+    assert_eq!(
+        get_blob(&mut harness, &mut storage, blob_hash),
+        Some(data.clone())
+    );
+
+    // A referenced blob survives garbage collection.
+    assert_eq!(release_blob(&mut harness, &mut storage, blob_hash), Some(1));
+    assert_eq!(collect_blob_garbage(&mut harness, &mut storage), 0);
+    assert_eq!(
+        get_blob(&mut harness, &mut storage, blob_hash),
+        Some(data.clone())
+    );
+
+    // Once the last reference is gone, the blob is only deleted by garbage collection.
+    assert_eq!(release_blob(&mut harness, &mut storage, blob_hash), Some(0));
+    assert_eq!(
+        get_blob(&mut harness, &mut storage, blob_hash),
+        Some(data.clone())
+    );
+    assert_eq!(collect_blob_garbage(&mut harness, &mut storage), 1);
+    assert_eq!(get_blob(&mut harness, &mut storage, blob_hash), None);
+    assert_eq!(release_blob(&mut harness, &mut storage, blob_hash), None);
+
+    // The blob can be stored again after being collected.
+    assert_eq!(
+        put_blob(&mut harness, &mut storage, data.clone()),
+        blob_hash
+    );
+    assert_eq!(get_blob(&mut harness, &mut storage, blob_hash), Some(data));
+}
+
+#[test]
+fn compressed_and_uncompressed_records_can_be_mixed() {
+    let mut harness = ComponentHarness::default();
+    let mut storage = storage_fixture_with_compression(&mut harness, false);
+
+    let uncompressed_block = Box::new(Block::random(&mut harness.rng));
+    put_block(&mut harness, &mut storage, uncompressed_block.clone());
+
+    // Reopen with compression enabled, the old block must still be readable.
+    let (on_disk, rng) = harness.into_parts();
+    let mut harness = ComponentHarness::builder()
+        .on_disk(on_disk)
+        .rng(rng)
+        .build();
+    let mut storage = storage_fixture_with_compression(&mut harness, true);
+
+    let compressed_block = Box::new(Block::random(&mut harness.rng));
+    put_block(&mut harness, &mut storage, compressed_block.clone());
+    assert_eq!(
+        get_block(&mut harness, &mut storage, *uncompressed_block.hash()).as_ref(),
+        Some(&*uncompressed_block)
+    );
+    assert_eq!(
+        get_block(&mut harness, &mut storage, *compressed_block.hash()).as_ref(),
+        Some(&*compressed_block)
+    );
+
+    // Disabling compression again must not make compressed records unreadable.
+    let (on_disk, rng) = harness.into_parts();
+    let mut harness = ComponentHarness::builder()
+        .on_disk(on_disk)
+        .rng(rng)
+        .build();
+    let mut storage = storage_fixture_with_compression(&mut harness, false);
+
+    assert_eq!(
+        get_block(&mut harness, &mut storage, *compressed_block.hash()).as_ref(),
+        Some(&*compressed_block)
+    );
+}
+
+#[test]
+fn full_database_is_reported_as_transient_write_failure() {
+    const KIB: usize = 1024;
+
+    let mut harness = ComponentHarness::default();
+    let cfg = Config {
+        path: harness.tmp.path().join("storage"),
+        max_block_store_size: 64 * KIB,
+        max_deploy_store_size: 64 * KIB,
+        max_deploy_metadata_store_size: 64 * KIB,
+        max_state_store_size: 64 * KIB,
+        max_blob_store_size: 64 * KIB,
+        enable_compression: false,
+        max_read_replicas: 0,
+        read_replica: false,
+    };
+    let mut storage = Storage::new(&WithDir::new(harness.tmp.path(), cfg), &Registry::new())
+        .expect("could not create storage component fixture");
+
+    // Fill the database until a write fails, which must not be treated as fatal.
+    let mut failure = None;
+    for _ in 0..10_000 {
+        let deploy = Box::new(Deploy::random(&mut harness.rng));
+        let response: Result<bool, TransientWriteError> = harness
+            .send_request(&mut storage, move |responder| {
+                StorageRequest::PutDeploy { deploy, responder }.into()
+            });
+        if let Err(error) = response {
+            failure = Some(error);
+            break;
+        }
+    }
+    assert!(failure.is_some(), "database should have filled up");
+    assert!(harness.is_idle());
+}
+
+#[test]
+fn read_replica_requires_primary_and_sees_its_blocks() {
+    let mut harness = ComponentHarness::default();
+    let path = harness.tmp.path().join("storage");
+    let cfg = Config {
+        path: path.clone(),
+        max_read_replicas: 1,
+        ..Default::default()
+    };
+    let replica_cfg = cfg.clone().into_read_replica();
+    let lock_path = path.join(super::PRIMARY_LOCK_FILENAME);
+    let epoch_path = path.join(super::EPOCH_FILENAME);
+
+    let mut primary = Storage::new(&WithDir::new(harness.tmp.path(), cfg), &Registry::new())
+        .expect("could not create primary storage");
+    assert!(lock_path.exists());
+    assert_eq!(super::read_epoch(&epoch_path).unwrap(), 1);
+
+    let block = Box::new(Block::random(&mut harness.rng));
+    assert!(put_block(&mut harness, &mut primary, block.clone()));
+    assert_eq!(super::read_epoch(&epoch_path).unwrap(), 2);
+
+    // LMDB must not be opened twice by the same process, so the primary's lock file is recreated
+    // after dropping it to simulate a primary still running.
+    drop(primary);
+    assert!(!lock_path.exists());
+    assert!(matches!(
+        Storage::new(
+            &WithDir::new(harness.tmp.path(), replica_cfg.clone()),
+            &Registry::new()
+        ),
+        Err(super::Error::PrimaryNotRunning(_))
+    ));
+    fs::write(&lock_path, std::process::id().to_string()).unwrap();
+
+    let mut replica = Storage::new(
+        &WithDir::new(harness.tmp.path(), replica_cfg),
+        &Registry::new(),
+    )
+    .expect("could not create read replica");
+    assert_eq!(
+        get_block(&mut harness, &mut replica, *block.hash()).as_ref(),
+        Some(&*block)
+    );
+    assert_eq!(
+        get_highest_block(&mut harness, &mut replica).as_ref(),
+        Some(&*block)
+    );
+}
+
+#[test]
+fn read_replica_refuses_stale_lock_file() {
+    let harness = ComponentHarness::default();
+    let path = harness.tmp.path().join("storage");
+    let cfg = Config {
+        path: path.clone(),
+        max_read_replicas: 1,
+        ..Default::default()
+    };
+    let replica_cfg = cfg.clone().into_read_replica();
+    let lock_path = path.join(super::PRIMARY_LOCK_FILENAME);
+
+    let primary = Storage::new(
+        &WithDir::new(harness.tmp.path(), cfg.clone()),
+        &Registry::new(),
+    )
+    .expect("could not create primary storage");
+    drop(primary);
+
+    // Left behind by a primary which crashed, no process has the maximum ID.
+    let dead_pid = i32::max_value().to_string();
+    fs::write(&lock_path, &dead_pid).unwrap();
+    assert!(matches!(
+        Storage::new(
+            &WithDir::new(harness.tmp.path(), replica_cfg),
+            &Registry::new()
+        ),
+        Err(super::Error::StalePrimaryLock(_, _))
+    ));
+
+    // A restarted primary takes over the stale lock file.
+    let primary = Storage::new(&WithDir::new(harness.tmp.path(), cfg), &Registry::new())
+        .expect("could not restart primary storage");
+    assert_eq!(
+        fs::read_to_string(&lock_path).unwrap(),
+        std::process::id().to_string()
+    );
+    drop(primary);
+}
+
+#[test]
+fn blocks_are_logged_by_epoch_for_read_replicas() {
+    let mut harness = ComponentHarness::default();
+    let cfg = Config {
+        path: harness.tmp.path().join("storage"),
+        max_read_replicas: 1,
+        ..Default::default()
+    };
+    let mut storage = Storage::new(&WithDir::new(harness.tmp.path(), cfg), &Registry::new())
+        .expect("could not create primary storage");
+
+    // The primary started epoch 1, every block stored starts the next one.
+    let blocks: Vec<Box<Block>> = (0..3)
+        .map(|height| random_block_at_height(&mut harness.rng, height))
+        .collect();
+    for block in &blocks {
+        assert!(put_block(&mut harness, &mut storage, block.clone()));
+    }
+
+    let txn = storage.env.begin_ro_txn().unwrap();
+    let logged = |last_epoch, epoch| {
+        super::logged_blocks(&txn, storage.block_log_db, last_epoch, epoch).unwrap()
+    };
+    assert_eq!(
+        logged(2, 4),
+        Some(vec![*blocks[1].hash(), *blocks[2].hash()])
+    );
+    assert_eq!(logged(4, 4), Some(vec![]));
+    // Epoch 1 was started by the primary starting up, so all blocks must be reindexed.
+    assert_eq!(logged(0, 4), None);
+    // Epochs not started yet have no blocks logged either.
+    assert_eq!(logged(3, 5), None);
+}
+
+#[test]
+fn should_refuse_storage_written_at_newer_protocol_version() {
+    let mut harness = ComponentHarness::default();
+    let old_version = Version::new(1, 0, 0);
+    let new_version = Version::new(2, 0, 0);
+
+    let mut old_chainspec = Chainspec::random(&mut harness.rng);
+    old_chainspec.genesis.protocol_version = old_version.clone();
+    old_chainspec.upgrades.clear();
+    let mut new_chainspec = old_chainspec.clone();
+    new_chainspec.upgrades.push(UpgradePoint {
+        activation_point: ActivationPoint { height: 1 },
+        protocol_version: new_version.clone(),
+        new_wasm_config: None,
+        new_system_config: None,
+        new_deploy_config: None,
+        new_validator_slots: None,
+        emergency_validators: None,
+    });
+
+    // The upgrade is not in effect before a block at its activation height has been stored.
+    let mut storage = storage_fixture(&mut harness);
+    storage.check_version(&new_chainspec).unwrap();
+    assert!(put_block(
+        &mut harness,
+        &mut storage,
+        random_block_at_height(&mut harness.rng, 0)
+    ));
+    drop(storage);
+    let mut storage = storage_fixture(&mut harness);
+    storage.check_version(&old_chainspec).unwrap();
+    drop(storage);
+
+    let mut storage = storage_fixture(&mut harness);
+    storage.check_version(&new_chainspec).unwrap();
+    assert!(put_block(
+        &mut harness,
+        &mut storage,
+        random_block_at_height(&mut harness.rng, 1)
+    ));
+    drop(storage);
+    let mut storage = storage_fixture(&mut harness);
+    match storage.check_version(&old_chainspec) {
+        Err(super::Error::ProtocolDowngrade {
+            stored, supported, ..
+        }) => {
+            assert_eq!(stored, new_version);
+            assert_eq!(supported, old_version);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+    storage.check_version(&new_chainspec).unwrap();
+
+    // Migrations of a newer node are refused as well.
+    let marker_path = harness
+        .tmp
+        .path()
+        .join("storage")
+        .join(super::VERSION_FILENAME);
+    let mut marker = super::read_version_marker(&marker_path).unwrap().unwrap();
+    marker.migrations.insert("from-the-future".to_string());
+    super::write_version_marker(&marker_path, &marker).unwrap();
+    assert!(matches!(
+        storage.check_version(&new_chainspec),
+        Err(super::Error::UnknownMigration { .. })
+    ));
+}
+
+#[test]
+fn identical_block_bodies_are_stored_once() {
+    let mut harness = ComponentHarness::default();
+    let mut storage = storage_fixture(&mut harness);
+
+    // Both blocks have empty bodies.
+    let blocks: Vec<Box<Block>> = (0..2)
+        .map(|height| {
+            let mut block = random_block_at_height(&mut harness.rng, height);
+            let _ = block.take_deploy_and_transfer_hashes();
+            block
+        })
+        .collect();
+    for block in &blocks {
+        assert!(put_block(&mut harness, &mut storage, block.clone()));
+    }
+    // Storing a block again must not count as storing its body again.
+    assert!(put_block(&mut harness, &mut storage, blocks[0].clone()));
+
+    assert_eq!(storage.metrics.deduplicated_block_bodies.get(), 1);
+    assert!(storage.metrics.block_body_bytes_saved.get() > 0);
+    for block in &blocks {
+        assert_eq!(
+            get_block(&mut harness, &mut storage, *block.hash()).as_ref(),
+            Some(&**block)
+        );
+    }
+}
+
+#[test]
+fn legacy_blocks_are_readable_and_migrated() {
+    let mut harness = ComponentHarness::default();
+    let mut storage = storage_fixture(&mut harness);
+
+    // Write blocks the way they were stored before bodies were stored separately.
+    let blocks: Vec<Box<Block>> = (0..3)
+        .map(|height| random_block_at_height(&mut harness.rng, height))
+        .collect();
+    let mut txn = storage.env.begin_rw_txn().unwrap();
+    for block in &blocks {
+        assert!(txn
+            .put_value(storage.block_db, block.hash(), &**block, true)
+            .unwrap());
+    }
+    txn.commit().unwrap();
+
+    // Reopen, so the legacy blocks are indexed.
+    drop(storage);
+    let mut storage = storage_fixture(&mut harness);
+    assert_eq!(storage.block_count(), 3);
+    assert_eq!(
+        get_block(&mut harness, &mut storage, *blocks[0].hash()).as_ref(),
+        Some(&*blocks[0])
+    );
+
+    let effects = harness.send_event(&mut storage, Event::BlockBodyMigrationStep);
+    assert!(effects.is_empty(), "migration should be complete");
+    let txn = storage.env.begin_ro_txn().unwrap();
+    assert!(matches!(
+        txn.get(storage.block_db, blocks[0].hash()),
+        Err(lmdb::Error::NotFound)
+    ));
+    drop(txn);
+
+    for block in &blocks {
+        assert_eq!(
+            get_block(&mut harness, &mut storage, *block.hash()).as_ref(),
+            Some(&**block)
+        );
+    }
+    assert_eq!(
+        get_highest_block(&mut harness, &mut storage).as_ref(),
+        Some(&*blocks[2])
+    );
+}
+
+#[test]
+fn block_body_migration_is_recorded_once_written_and_resumed_after_restart() {
+    let mut harness = ComponentHarness::default();
+    let chainspec = Chainspec::random(&mut harness.rng);
+    let marker_path = harness
+        .tmp
+        .path()
+        .join("storage")
+        .join(super::VERSION_FILENAME);
+    let has_migration = || {
+        super::read_version_marker(&marker_path)
+            .unwrap()
+            .unwrap()
+            .migrations
+            .contains(super::BLOCK_BODY_MIGRATION)
+    };
+
+    // Write more legacy blocks than a single migration step converts.
+    let mut storage = storage_fixture(&mut harness);
+    let blocks: Vec<Box<Block>> = (0..super::BLOCK_BODY_MIGRATION_BATCH_SIZE as u64 + 5)
+        .map(|height| random_block_at_height(&mut harness.rng, height))
+        .collect();
+    let mut txn = storage.env.begin_rw_txn().unwrap();
+    for block in &blocks {
+        assert!(txn
+            .put_value(storage.block_db, block.hash(), &**block, true)
+            .unwrap());
+    }
+    txn.commit().unwrap();
+    drop(storage);
+
+    // Nothing has been converted yet, so older nodes can still read the storage.
+    let mut storage = storage_fixture(&mut harness);
+    storage.check_version(&chainspec).unwrap();
+    assert!(!has_migration());
+
+    let effects = harness.send_event(&mut storage, Event::BlockBodyMigrationStep);
+    assert!(!effects.is_empty(), "migration should not be complete");
+    assert!(has_migration());
+
+    // Restart partway through the migration.
+    drop(storage);
+    let mut storage = storage_fixture(&mut harness);
+    storage.check_version(&chainspec).unwrap();
+    assert!(has_migration());
+    assert_eq!(storage.block_count(), blocks.len());
+    for block in &blocks {
+        assert_eq!(
+            get_block(&mut harness, &mut storage, *block.hash()).as_ref(),
+            Some(&**block)
+        );
+    }
+
+    let effects = harness.send_event(&mut storage, Event::BlockBodyMigrationStep);
+    assert!(effects.is_empty(), "migration should be complete");
+    let txn = storage.env.begin_ro_txn().unwrap();
+    for block in &blocks {
+        assert!(matches!(
+            txn.get(storage.block_db, block.hash()),
+            Err(lmdb::Error::NotFound)
+        ));
+    }
+    drop(txn);
+    for block in &blocks {
+        assert_eq!(
+            get_block(&mut harness, &mut storage, *block.hash()).as_ref(),
+            Some(&**block)
+        );
+    }
+}
+
+#[test]
+fn records_written_before_the_record_format_are_migrated_once() {
+    let mut harness = ComponentHarness::default();
+    let storage = storage_fixture(&mut harness);
+
+    // Write deploys the way they were stored before records started with their format, and forget
+    // that the deploy database has been migrated.
+    let deploys: Vec<Deploy> = (0..3).map(|_| Deploy::random(&mut harness.rng)).collect();
+    let meta_db = storage.env.open_db(Some("storage_meta")).unwrap();
+    let mut txn = storage.env.begin_rw_txn().unwrap();
+    for deploy in &deploys {
+        let legacy = bincode::serialize(deploy).unwrap();
+        txn.put(
+            storage.deploy_db,
+            deploy.id(),
+            &legacy,
+            lmdb::WriteFlags::empty(),
+        )
+        .unwrap();
+    }
+    txn.del(meta_db, &super::record_format_progress_key("deploys"), None)
+        .unwrap();
+    txn.commit().unwrap();
+    drop(storage);
+
+    // Reopening twice must neither leave the legacy records unreadable nor rewrite them again.
+    let (harness, storage) = reopen_storage(harness);
+    drop(storage);
+    let (mut harness, mut storage) = reopen_storage(harness);
+    let deploy_hashes = deploys.iter().map(|deploy| *deploy.id()).collect();
+    let loaded = get_deploys(&mut harness, &mut storage, deploy_hashes);
+    assert_eq!(loaded, deploys.into_iter().map(Some).collect::<Vec<_>>());
+}
+
+#[test]
+fn records_failing_to_decompress_or_of_unknown_format_are_corrupted() {
+    let mut harness = ComponentHarness::default();
+    let storage = storage_fixture(&mut harness);
+
+    let deploy = Deploy::random(&mut harness.rng);
+    let raw = bincode::serialize(&deploy).unwrap();
+    let mut txn = storage.env.begin_rw_txn().unwrap();
+    // The compressed format, but holding an uncompressed deploy.
+    let mut not_compressed = vec![1];
+    not_compressed.extend_from_slice(&raw);
+    txn.put(
+        storage.deploy_db,
+        &[1],
+        &not_compressed,
+        lmdb::WriteFlags::empty(),
+    )
+    .unwrap();
+    // A format unknown to this node.
+    let mut unknown_format = vec![0xff];
+    unknown_format.extend_from_slice(&raw);
+    txn.put(
+        storage.deploy_db,
+        &[2],
+        &unknown_format,
+        lmdb::WriteFlags::empty(),
+    )
+    .unwrap();
+    txn.commit().unwrap();
+
+    let mut txn = storage.env.begin_ro_txn().unwrap();
+    for key in &[[1u8], [2u8]] {
+        assert!(matches!(
+            txn.get_value::<_, Deploy>(storage.deploy_db, key),
+            Err(LmdbExtError::DataCorrupted(_))
+        ));
+    }
+}
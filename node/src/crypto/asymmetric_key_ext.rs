@@ -5,14 +5,14 @@ use std::path::Path;
 use derp::{Der, Tag};
 use once_cell::sync::Lazy;
 use pem::Pem;
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 use rand::{Rng, RngCore};
 use untrusted::Input;
 
 use casper_types::{AsymmetricType, PublicKey, SecretKey, ED25519_TAG, SECP256K1_TAG, SYSTEM_TAG};
 
-#[cfg(test)]
-use crate::testing::TestRng;
+#[cfg(any(test, feature = "testing"))]
+use crate::NodeRng;
 use crate::{crypto::Error, utils};
 
 // See https://tools.ietf.org/html/rfc8410#section-10.3
@@ -70,20 +70,20 @@ pub trait AsymmetricKeyExt: Sized {
     /// Duplicates a secret key.
     ///
     /// Only available for testing and named other than `clone` to prevent accidental use.
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     fn duplicate(&self) -> Self;
 
-    /// Generates a random instance using a `TestRng`.
-    #[cfg(test)]
-    fn random(rng: &mut TestRng) -> Self;
+    /// Generates a random instance.
+    #[cfg(any(test, feature = "testing"))]
+    fn random(rng: &mut NodeRng) -> Self;
 
-    /// Generates a random ed25519 instance using a `TestRng`.
-    #[cfg(test)]
-    fn random_ed25519(rng: &mut TestRng) -> Self;
+    /// Generates a random ed25519 instance.
+    #[cfg(any(test, feature = "testing"))]
+    fn random_ed25519(rng: &mut NodeRng) -> Self;
 
-    /// Generates a random secp256k1 instance using a `TestRng`.
-    #[cfg(test)]
-    fn random_secp256k1(rng: &mut TestRng) -> Self;
+    /// Generates a random secp256k1 instance.
+    #[cfg(any(test, feature = "testing"))]
+    fn random_secp256k1(rng: &mut NodeRng) -> Self;
 
     /// Returns an example value for documentation purposes.
     fn doc_example() -> &'static Self;
@@ -263,7 +263,7 @@ impl AsymmetricKeyExt for SecretKey {
         Ok(secret_key)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     fn duplicate(&self) -> Self {
         match self {
             SecretKey::System => SecretKey::System,
@@ -277,8 +277,8 @@ impl AsymmetricKeyExt for SecretKey {
         }
     }
 
-    #[cfg(test)]
-    fn random(rng: &mut TestRng) -> Self {
+    #[cfg(any(test, feature = "testing"))]
+    fn random(rng: &mut NodeRng) -> Self {
         if rng.gen() {
             Self::random_ed25519(rng)
         } else {
@@ -286,15 +286,15 @@ impl AsymmetricKeyExt for SecretKey {
         }
     }
 
-    #[cfg(test)]
-    fn random_ed25519(rng: &mut TestRng) -> Self {
+    #[cfg(any(test, feature = "testing"))]
+    fn random_ed25519(rng: &mut NodeRng) -> Self {
         let mut bytes = [0u8; Self::ED25519_LENGTH];
         rng.fill_bytes(&mut bytes[..]);
         SecretKey::ed25519(bytes)
     }
 
-    #[cfg(test)]
-    fn random_secp256k1(rng: &mut TestRng) -> Self {
+    #[cfg(any(test, feature = "testing"))]
+    fn random_secp256k1(rng: &mut NodeRng) -> Self {
         let mut bytes = [0u8; Self::SECP256K1_LENGTH];
         rng.fill_bytes(&mut bytes[..]);
         SecretKey::secp256k1(bytes)
@@ -430,7 +430,7 @@ impl AsymmetricKeyExt for PublicKey {
         Ok(public_key)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     fn duplicate(&self) -> Self {
         match self {
             PublicKey::System => PublicKey::System,
@@ -443,20 +443,20 @@ impl AsymmetricKeyExt for PublicKey {
         }
     }
 
-    #[cfg(test)]
-    fn random(rng: &mut TestRng) -> Self {
+    #[cfg(any(test, feature = "testing"))]
+    fn random(rng: &mut NodeRng) -> Self {
         let secret_key = SecretKey::random(rng);
         PublicKey::from(&secret_key)
     }
 
-    #[cfg(test)]
-    fn random_ed25519(rng: &mut TestRng) -> Self {
+    #[cfg(any(test, feature = "testing"))]
+    fn random_ed25519(rng: &mut NodeRng) -> Self {
         let secret_key = SecretKey::random_ed25519(rng);
         PublicKey::from(&secret_key)
     }
 
-    #[cfg(test)]
-    fn random_secp256k1(rng: &mut TestRng) -> Self {
+    #[cfg(any(test, feature = "testing"))]
+    fn random_secp256k1(rng: &mut NodeRng) -> Self {
         let secret_key = SecretKey::random_secp256k1(rng);
         PublicKey::from(&secret_key)
     }
@@ -14,7 +14,6 @@ use blake2::{
     VarBlake2b,
 };
 use datasize::DataSize;
-use hex_buffer_serde::{Hex, HexForm};
 use hex_fmt::HexFmt;
 #[cfg(test)]
 use rand::Rng;
@@ -22,7 +21,10 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use casper_execution_engine::shared::newtypes::Blake2bHash;
-use casper_types::bytesrepr::{self, FromBytes, ToBytes};
+use casper_types::{
+    bytesrepr::{self, FromBytes, ToBytes},
+    checksummed_hex::{self, ChecksummedHexFmt},
+};
 
 use super::Error;
 #[cfg(test)]
@@ -46,7 +48,7 @@ use crate::testing::TestRng;
 #[serde(deny_unknown_fields)]
 #[schemars(with = "String", description = "Hex-encoded hash digest.")]
 pub struct Digest(
-    #[serde(with = "HexForm::<[u8; Digest::LENGTH]>")]
+    #[serde(with = "checksummed_hex_form")]
     #[schemars(skip, with = "String")]
     [u8; Digest::LENGTH],
 );
@@ -65,10 +67,12 @@ impl Digest {
         self.0.to_vec()
     }
 
-    /// Returns a `Digest` parsed from a hex-encoded `Digest`.
+    /// Returns a `Digest` parsed from a hex-encoded `Digest`, either checksummed or in a single
+    /// case.
     pub fn from_hex<T: AsRef<[u8]>>(hex_input: T) -> Result<Self, Error> {
-        let mut inner = [0; Digest::LENGTH];
-        hex::decode_to_slice(hex_input, &mut inner)?;
+        let bytes = checksummed_hex::decode(hex_input)?;
+        let inner = <[u8; Digest::LENGTH]>::try_from(bytes.as_slice())
+            .map_err(|_| hex::FromHexError::InvalidStringLength)?;
         Ok(Digest(inner))
     }
 
@@ -101,13 +105,13 @@ impl TryFrom<&[u8]> for Digest {
 
 impl Debug for Digest {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
-        write!(formatter, "{}", HexFmt(&self.0))
+        write!(formatter, "{}", ChecksummedHexFmt(&self.0))
     }
 }
 
 impl Display for Digest {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
-        write!(formatter, "{:10}", HexFmt(&self.0))
+        write!(formatter, "{:10}", ChecksummedHexFmt(&self.0))
     }
 }
 impl LowerHex for Digest {
@@ -146,6 +150,39 @@ impl FromBytes for Digest {
     }
 }
 
+/// Serializes the digest as checksummed hex in human-readable formats, as raw bytes otherwise.
+mod checksummed_hex_form {
+    use std::convert::TryFrom;
+
+    use hex_buffer_serde::{Hex, HexForm};
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+    use casper_types::checksummed_hex;
+
+    use super::Digest;
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &[u8; Digest::LENGTH],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return checksummed_hex::encode(value).serialize(serializer);
+        }
+        HexForm::<[u8; Digest::LENGTH]>::serialize(value, serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<[u8; Digest::LENGTH], D::Error> {
+        if deserializer.is_human_readable() {
+            let hex_string = String::deserialize(deserializer)?;
+            let bytes = checksummed_hex::decode(&hex_string).map_err(D::Error::custom)?;
+            return <[u8; Digest::LENGTH]>::try_from(bytes.as_slice()).map_err(D::Error::custom);
+        }
+        HexForm::<[u8; Digest::LENGTH]>::deserialize(deserializer)
+    }
+}
+
 /// Returns the hash of `data`.
 pub fn hash<T: AsRef<[u8]>>(data: T) -> Digest {
     let mut result = [0; Digest::LENGTH];
@@ -196,7 +233,7 @@ mod test {
         ];
         for (known_input, expected_digest) in &inputs_and_digests {
             let known_input: &[u8] = known_input.as_ref();
-            assert_eq!(*expected_digest, format!("{:?}", hash(known_input)));
+            assert_eq!(*expected_digest, format!("{:x}", hash(known_input)));
         }
     }
 
@@ -234,6 +271,26 @@ mod test {
         );
     }
 
+    #[test]
+    fn should_display_and_parse_checksummed_hex() {
+        let hash = hash("abc");
+        let checksummed = format!("{:?}", hash);
+        assert_ne!(checksummed, format!("{:x}", hash));
+        assert_eq!(checksummed, checksummed_hex::encode(&hash));
+        assert_eq!(Digest::from_hex(&checksummed).unwrap(), hash);
+        assert_eq!(Digest::from_hex(format!("{:X}", hash)).unwrap(), hash);
+
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, format!("\"{}\"", checksummed));
+        assert_eq!(serde_json::from_str::<Digest>(&json).unwrap(), hash);
+
+        // Changing the case of a single letter invalidates the checksum.
+        let mut typo = checksummed.into_bytes();
+        let index = typo.iter().position(u8::is_ascii_alphabetic).unwrap();
+        typo[index] ^= 0x20;
+        assert!(Digest::from_hex(&typo).is_err());
+    }
+
     #[test]
     fn should_print_digest_lower_hex() {
         let hash = Digest([10u8; 32]);
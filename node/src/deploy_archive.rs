@@ -0,0 +1,357 @@
+//! Export and import of deploys between the storages of nodes on different networks.
+//!
+//! An archive is a portable file of the deploys of one node, e.g. of a production network, to be
+//! imported into the storage of another one, e.g. to stand up a staging network mirroring its
+//! traffic. It starts with `ARCHIVE_MAGIC`, the format version and the name of the chain the
+//! deploys were exported from, followed by the deploys, each prefixed with its length and encoded
+//! with `bytesrepr`, so an archive stays readable across node versions.
+//!
+//! Imported deploys are revalidated against the chainspec of the importing node as if they were
+//! received at the time they were created, so deploys the node would not have accepted, like those
+//! for a different chain or with invalid approvals, are rejected.
+//!
+//! Both export and import open the storage directly, so the node must not be running.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use prometheus::Registry;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use casper_types::{
+    bytesrepr::{self, ToBytes},
+    PublicKey,
+};
+
+use crate::{
+    components::{
+        chainspec_loader,
+        consensus::EraId,
+        storage::{self, Storage},
+    },
+    reactor::validator::Config,
+    types::Deploy,
+    utils::{LoadError, WithDir},
+};
+
+/// The first bytes of every deploy archive.
+const ARCHIVE_MAGIC: &[u8; 8] = b"CSPRDPLY";
+/// The version of the archive format written.
+const ARCHIVE_VERSION: u32 = 1;
+/// Maximum size of a single encoded deploy, well above the size of any acceptable deploy.
+const MAX_DEPLOY_SIZE: u32 = 64 * 1024 * 1024;
+
+/// Error exporting or importing deploys.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Error loading the chainspec.
+    #[error("error loading chainspec: {0}")]
+    LoadChainspec(LoadError<chainspec_loader::Error>),
+
+    /// Error opening or accessing the storage.
+    #[error("storage error: {0}")]
+    Storage(#[from] storage::Error),
+
+    /// Error reading or writing the archive file.
+    #[error("error accessing archive {path}: {error}")]
+    Archive {
+        /// The file path.
+        path: String,
+        /// The IO error.
+        error: io::Error,
+    },
+
+    /// The file is not a deploy archive, or one of a newer format.
+    #[error("{path} is not a deploy archive of version {}", ARCHIVE_VERSION)]
+    InvalidArchive {
+        /// The file path.
+        path: String,
+    },
+
+    /// A deploy in the archive could not be encoded or decoded.
+    #[error("error encoding deploy in archive {path}: {error}")]
+    Encoding {
+        /// The file path.
+        path: String,
+        /// The encoding error.
+        error: bytesrepr::Error,
+    },
+}
+
+/// Selects the deploys to export.
+#[derive(Clone, Debug, Default)]
+pub struct DeployFilter {
+    /// Only export the deploys included in blocks of this era.
+    pub era_id: Option<u64>,
+    /// Only export the deploys of this account.
+    pub account: Option<PublicKey>,
+}
+
+impl DeployFilter {
+    fn matches(&self, deploy: &Deploy) -> bool {
+        self.account
+            .as_ref()
+            .map_or(true, |account| deploy.header().account() == account)
+    }
+}
+
+/// The outcome of importing an archive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Number of deploys newly stored.
+    pub imported: usize,
+    /// Number of deploys which were stored already.
+    pub already_stored: usize,
+    /// Number of deploys which failed revalidation.
+    pub rejected: usize,
+}
+
+/// Exports the deploys selected by `filter` from the storage configured in `config` to an archive
+/// at `output`.
+///
+/// Returns the number of deploys exported.
+pub fn export_deploys(
+    config: WithDir<Config>,
+    filter: &DeployFilter,
+    output: &Path,
+) -> Result<usize, Error> {
+    let (root, config) = config.into_parts();
+    let chainspec = config
+        .node
+        .chainspec_config_path
+        .load(&root)
+        .map_err(Error::LoadChainspec)?;
+    let storage = Storage::new(&WithDir::new(root, config.storage), &Registry::new())?;
+
+    let file = File::create(output).map_err(|error| archive_error(output, error))?;
+    let mut writer = ArchiveWriter::new(BufWriter::new(file), &chainspec.genesis.name)
+        .map_err(|error| archive_error(output, error))?;
+    let mut exported = 0;
+    for deploy_hash in storage.read_deploy_hashes(filter.era_id.map(EraId))? {
+        let deploy = match storage.read_deploy(&deploy_hash)? {
+            Some(deploy) if filter.matches(&deploy) => deploy,
+            Some(_) => continue,
+            None => {
+                warn!(%deploy_hash, "deploy included in a block is missing from storage");
+                continue;
+            }
+        };
+        writer
+            .write(&deploy)
+            .map_err(|error| error.with_path(output))?;
+        exported += 1;
+    }
+    writer
+        .finish()
+        .map_err(|error| archive_error(output, error))?;
+    info!(exported, path = %output.display(), "exported deploys");
+    Ok(exported)
+}
+
+/// Imports the deploys of the archive at `input` into the storage configured in `config`,
+/// revalidating each against the configured chainspec.
+pub fn import_deploys(config: WithDir<Config>, input: &Path) -> Result<ImportSummary, Error> {
+    let (root, config) = config.into_parts();
+    let chainspec = config
+        .node
+        .chainspec_config_path
+        .load(&root)
+        .map_err(Error::LoadChainspec)?;
+    let mut storage = Storage::new(&WithDir::new(root, config.storage), &Registry::new())?;
+    storage.check_version(&chainspec)?;
+
+    let file = File::open(input).map_err(|error| archive_error(input, error))?;
+    let mut reader = ArchiveReader::new(BufReader::new(file)).map_err(|error| match error {
+        ArchiveError::Io(error) if error.kind() == io::ErrorKind::InvalidData => {
+            Error::InvalidArchive {
+                path: input.display().to_string(),
+            }
+        }
+        error => error.with_path(input),
+    })?;
+    if reader.chain_name != chainspec.genesis.name {
+        warn!(
+            archive_chain = %reader.chain_name,
+            chain = %chainspec.genesis.name,
+            "archive was exported from a different chain, its deploys will be rejected"
+        );
+    }
+
+    let protocol_version = chainspec.latest_protocol_version();
+    let mut summary = ImportSummary::default();
+    while let Some(mut deploy) = reader.read_next().map_err(|error| error.with_path(input))? {
+        let created = deploy.header().timestamp();
+        let is_acceptable = deploy
+            .is_acceptable(
+                chainspec.genesis.name.clone(),
+                chainspec.genesis.deploy_config,
+                created,
+            )
+            .and_then(|()| deploy.check_protocol_version(&protocol_version));
+        if let Err(error) = is_acceptable {
+            warn!(deploy_hash = %deploy.id(), %error, "rejected deploy from archive");
+            summary.rejected += 1;
+            continue;
+        }
+        if storage.write_deploy(&deploy)? {
+            summary.imported += 1;
+        } else {
+            summary.already_stored += 1;
+        }
+    }
+    storage.flush()?;
+    info!(?summary, path = %input.display(), "imported deploys");
+    Ok(summary)
+}
+
+fn archive_error(path: &Path, error: io::Error) -> Error {
+    Error::Archive {
+        path: path.display().to_string(),
+        error,
+    }
+}
+
+/// Error reading or writing an archive, before the path is attached.
+#[derive(Debug)]
+enum ArchiveError {
+    Io(io::Error),
+    Encoding(bytesrepr::Error),
+}
+
+impl ArchiveError {
+    fn with_path(self, path: &Path) -> Error {
+        match self {
+            ArchiveError::Io(error) => archive_error(path, error),
+            ArchiveError::Encoding(error) => Error::Encoding {
+                path: path.display().to_string(),
+                error,
+            },
+        }
+    }
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(error: io::Error) -> Self {
+        ArchiveError::Io(error)
+    }
+}
+
+/// Writes deploys in the archive format.
+struct ArchiveWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    /// Writes the header of an archive of deploys exported from `chain_name`.
+    fn new(mut writer: W, chain_name: &str) -> io::Result<Self> {
+        writer.write_all(ARCHIVE_MAGIC)?;
+        writer.write_all(&ARCHIVE_VERSION.to_le_bytes())?;
+        write_record(&mut writer, chain_name.as_bytes())?;
+        Ok(ArchiveWriter { writer })
+    }
+
+    fn write(&mut self, deploy: &Deploy) -> Result<(), ArchiveError> {
+        let bytes = deploy.to_bytes().map_err(ArchiveError::Encoding)?;
+        write_record(&mut self.writer, &bytes)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads deploys in the archive format.
+struct ArchiveReader<R> {
+    reader: R,
+    /// The name of the chain the deploys were exported from.
+    chain_name: String,
+}
+
+impl<R: Read> ArchiveReader<R> {
+    /// Reads the header of an archive, failing with `InvalidData` if it is not one.
+    fn new(mut reader: R) -> Result<Self, ArchiveError> {
+        let mut magic = [0; 8];
+        let mut version = [0; 4];
+        reader.read_exact(&mut magic)?;
+        reader.read_exact(&mut version)?;
+        if &magic != ARCHIVE_MAGIC || u32::from_le_bytes(version) != ARCHIVE_VERSION {
+            return Err(io::Error::from(io::ErrorKind::InvalidData).into());
+        }
+        let chain_name = read_record(&mut reader)?
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+        Ok(ArchiveReader { reader, chain_name })
+    }
+
+    /// Reads the next deploy, `None` at the end of the archive.
+    fn read_next(&mut self) -> Result<Option<Deploy>, ArchiveError> {
+        match read_record(&mut self.reader)? {
+            Some(bytes) => bytesrepr::deserialize(bytes)
+                .map(Some)
+                .map_err(ArchiveError::Encoding),
+            None => Ok(None),
+        }
+    }
+}
+
+fn write_record<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+/// Reads a length-prefixed record, `None` if the reader is at its end.
+fn read_record<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => (),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    }
+    let length = u32::from_le_bytes(length);
+    if length > MAX_DEPLOY_SIZE {
+        return Err(io::Error::from(io::ErrorKind::InvalidData));
+    }
+    let mut bytes = vec![0; length as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestRng;
+
+    #[test]
+    fn should_roundtrip_archive() {
+        let mut rng = TestRng::new();
+        let deploys = vec![Deploy::random(&mut rng), Deploy::random(&mut rng)];
+
+        let mut writer = ArchiveWriter::new(Vec::new(), "casper-example").unwrap();
+        for deploy in &deploys {
+            writer.write(deploy).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = ArchiveReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.chain_name, "casper-example");
+        assert_eq!(reader.read_next().unwrap().as_ref(), Some(&deploys[0]));
+        assert_eq!(reader.read_next().unwrap().as_ref(), Some(&deploys[1]));
+        assert!(reader.read_next().unwrap().is_none());
+
+        let mut corrupt = bytes;
+        corrupt[0] = b'X';
+        match ArchiveReader::new(corrupt.as_slice()) {
+            Err(ArchiveError::Io(error)) => assert_eq!(error.kind(), io::ErrorKind::InvalidData),
+            other => panic!(
+                "unexpected result {:?}",
+                other.map(|reader| reader.chain_name)
+            ),
+        }
+    }
+}
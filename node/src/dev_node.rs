@@ -0,0 +1,265 @@
+//! Single-node developer networks.
+//!
+//! For iterating on contracts locally, `casper-node validator --dev` runs a network consisting of
+//! just the node itself. Everything it needs is set up in a developer directory:
+//!
+//! * a validator key in `validator/` and keys of pre-funded accounts in `account-<N>/`, generated
+//!   on first use and reused afterwards, so clients can keep signing deploys with them,
+//! * `chainspec.toml` and `accounts.csv`, derived from the bundled production chainspec, but with
+//!   the generated keys as the only genesis accounts and rounds of about a second,
+//! * the storage, unit hashes and spillover files, which are wiped on every start, since each start
+//!   launches a new chain with a fresh genesis timestamp.
+//!
+//! The node only listens on and connects to a free port on localhost, while the API servers keep
+//! their configured addresses.
+
+use std::{
+    fs, io,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+};
+
+use casper_types::{PublicKey, SecretKey};
+use thiserror::Error;
+use toml::Value;
+
+use crate::{
+    crypto::{self, AsymmetricKeyExt},
+    keygen::{self, KeyAlgorithm, SECRET_KEY_PEM},
+    reactor::validator,
+    types::Timestamp,
+    utils::External,
+};
+
+/// The chainspec developer chains are derived from.
+const BASE_CHAINSPEC: &str = include_str!("../../resources/production/chainspec.toml");
+/// Name of developer chains.
+const DEV_CHAIN_NAME: &str = "casper-dev";
+/// Number of pre-funded accounts.
+pub const DEV_ACCOUNT_COUNT: usize = 5;
+/// Balance of each pre-funded account and of the validator, in motes.
+const DEV_ACCOUNT_BALANCE: &str = "1000000000000000000000000000";
+/// Amount bonded by the validator, in motes.
+const DEV_VALIDATOR_BOND: &str = "1000000000000000";
+/// Minimum round exponent, making rounds last about a second.
+const DEV_MINIMUM_ROUND_EXPONENT: i64 = 10;
+/// Era duration of developer chains.
+const DEV_ERA_DURATION: &str = "1minute";
+/// How long after setup genesis happens, to leave time for initializing the node.
+const DEV_GENESIS_DELAY_MILLIS: u64 = 10_000;
+
+/// Error setting up a developer network.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A file or directory could not be written or removed.
+    #[error("could not set up '{}': {error}", .path.display())]
+    Io {
+        /// The file or directory.
+        path: PathBuf,
+        /// The underlying OS error.
+        #[source]
+        error: io::Error,
+    },
+    /// A key could not be generated.
+    #[error(transparent)]
+    Keygen(#[from] keygen::Error),
+    /// An existing key could not be loaded.
+    #[error(transparent)]
+    Crypto(#[from] crypto::Error),
+}
+
+/// A pre-funded account of a developer network.
+#[derive(Debug)]
+pub struct DevAccount {
+    /// Path to the account's secret key.
+    pub secret_key_path: PathBuf,
+    /// The account's public key.
+    pub public_key: PublicKey,
+}
+
+/// The setup of a developer network.
+#[derive(Debug)]
+pub struct DevNode {
+    /// The public key of the single validator.
+    pub validator: PublicKey,
+    /// The pre-funded accounts.
+    pub accounts: Vec<DevAccount>,
+}
+
+/// Sets up a developer network in `dir`, and points `config` to it.
+pub fn setup_dev_node(config: &mut validator::Config, dir: &Path) -> Result<DevNode, Error> {
+    let validator_dir = dir.join("validator");
+    let validator = load_or_generate_key(&validator_dir)?;
+    let accounts = (1..=DEV_ACCOUNT_COUNT)
+        .map(|index| {
+            let account_dir = dir.join(format!("account-{}", index));
+            let public_key = load_or_generate_key(&account_dir)?;
+            Ok(DevAccount {
+                secret_key_path: account_dir.join(SECRET_KEY_PEM),
+                public_key,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut accounts_csv = format!(
+        "{},{},{}\n",
+        validator.to_hex(),
+        DEV_ACCOUNT_BALANCE,
+        DEV_VALIDATOR_BOND
+    );
+    for account in &accounts {
+        accounts_csv.push_str(&format!(
+            "{},{},0\n",
+            account.public_key.to_hex(),
+            DEV_ACCOUNT_BALANCE
+        ));
+    }
+    write(&dir.join("accounts.csv"), accounts_csv)?;
+
+    let genesis_timestamp = Timestamp::now() + DEV_GENESIS_DELAY_MILLIS.into();
+    let chainspec_path = dir.join("chainspec.toml");
+    write(&chainspec_path, dev_chainspec(genesis_timestamp))?;
+
+    let storage_path = dir.join("storage");
+    let unit_hashes_folder = dir.join("unit_hashes");
+    let spillover_path = dir.join("spillover");
+    for path in &[&storage_path, &unit_hashes_folder, &spillover_path] {
+        recreate_dir(path)?;
+    }
+
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, unused_port(dir)?)).to_string();
+
+    config.node.chainspec_config_path = External::path(chainspec_path);
+    config.node.trusted_hash = None;
+    config.node.queue_snapshot_path = None;
+    config.consensus.secret_key_path = External::path(validator_dir.join(SECRET_KEY_PEM));
+    config.consensus.unit_hashes_folder = unit_hashes_folder;
+    config.network.bind_address = address.clone();
+    config.network.public_address = address.clone();
+    config.network.known_addresses = vec![address];
+    config.network.systemd_support = false;
+    config.storage.path = storage_path;
+    config.event_queue_spillover.path = spillover_path.display().to_string();
+
+    Ok(DevNode {
+        validator,
+        accounts,
+    })
+}
+
+/// Returns the chainspec of a developer chain with the given genesis timestamp.
+fn dev_chainspec(genesis_timestamp: Timestamp) -> String {
+    let mut chainspec: Value =
+        toml::from_str(BASE_CHAINSPEC).expect("bundled chainspec should be valid TOML");
+    let table = chainspec
+        .as_table_mut()
+        .expect("bundled chainspec should be a table");
+    // A new chain has nothing to upgrade.
+    table.remove("upgrade");
+
+    let genesis = table["genesis"]
+        .as_table_mut()
+        .expect("bundled chainspec should have a genesis section");
+    genesis.insert("name".into(), DEV_CHAIN_NAME.into());
+    genesis.insert("timestamp".into(), genesis_timestamp.to_string().into());
+    genesis.insert("accounts_path".into(), "accounts.csv".into());
+    genesis.insert("locked_funds_period_millis".into(), 0.into());
+
+    let highway = table["highway"]
+        .as_table_mut()
+        .expect("bundled chainspec should have a highway section");
+    highway.insert("era_duration".into(), DEV_ERA_DURATION.into());
+    highway.insert(
+        "minimum_round_exponent".into(),
+        DEV_MINIMUM_ROUND_EXPONENT.into(),
+    );
+
+    format!(
+        "# Generated by `casper-node validator --dev`, and regenerated on every start.\n\n{}",
+        toml::to_string(&chainspec).expect("chainspec should serialize")
+    )
+}
+
+/// Loads the public key of the key pair in `dir`, generating the key pair if there is none.
+fn load_or_generate_key(dir: &Path) -> Result<PublicKey, Error> {
+    let secret_key_path = dir.join(SECRET_KEY_PEM);
+    if secret_key_path.exists() {
+        let secret_key = SecretKey::from_file(&secret_key_path)?;
+        return Ok(PublicKey::from(&secret_key));
+    }
+    Ok(keygen::generate_validator_keys(
+        dir,
+        KeyAlgorithm::Ed25519,
+        false,
+    )?)
+}
+
+/// Writes `contents` to the file at `path`.
+fn write(path: &Path, contents: String) -> Result<(), Error> {
+    fs::write(path, contents).map_err(|error| Error::Io {
+        path: path.to_owned(),
+        error,
+    })
+}
+
+/// Removes the directory at `path` if it exists, and creates it empty.
+fn recreate_dir(path: &Path) -> Result<(), Error> {
+    let io_error = |error| Error::Io {
+        path: path.to_owned(),
+        error,
+    };
+    if path.exists() {
+        fs::remove_dir_all(path).map_err(io_error)?;
+    }
+    fs::create_dir_all(path).map_err(io_error)
+}
+
+/// Returns a port on localhost no other socket is bound to.
+fn unused_port(dir: &Path) -> Result<u16, Error> {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|address| address.port())
+        .map_err(|error| Error::Io {
+            path: dir.to_owned(),
+            error,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utils::Loadable, Chainspec};
+
+    #[test]
+    fn should_set_up_loadable_dev_network_and_reuse_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = validator::Config::default();
+        let dev_node = setup_dev_node(&mut config, dir.path()).unwrap();
+
+        let chainspec = Chainspec::from_file(dir.path().join("chainspec.toml")).unwrap();
+        assert_eq!(chainspec.genesis.name, DEV_CHAIN_NAME);
+        assert!(chainspec.upgrades.is_empty());
+        assert_eq!(
+            chainspec.genesis.highway_config.minimum_round_exponent as i64,
+            DEV_MINIMUM_ROUND_EXPONENT
+        );
+        assert_eq!(chainspec.genesis.accounts.len(), DEV_ACCOUNT_COUNT + 1);
+        let validators: Vec<_> = chainspec
+            .genesis
+            .accounts
+            .iter()
+            .filter(|account| account.is_genesis_validator())
+            .map(|account| account.public_key())
+            .collect();
+        assert_eq!(validators, vec![Some(dev_node.validator)]);
+
+        // Restarting launches a new chain, but with the same keys.
+        fs::write(dir.path().join("storage").join("stale"), "").unwrap();
+        let restarted = setup_dev_node(&mut config, dir.path()).unwrap();
+        assert_eq!(restarted.validator, dev_node.validator);
+        for (account, restarted) in dev_node.accounts.iter().zip(&restarted.accounts) {
+            assert_eq!(account.public_key, restarted.public_key);
+        }
+        assert!(!dir.path().join("storage").join("stale").exists());
+    }
+}
@@ -57,27 +57,44 @@
 //!
 //! A request **must** have a `Responder` field, which a handler of a request **must** call at
 //! some point. Failing to do so will result in a resource leak.
+//!
+//! ## Capabilities
+//!
+//! Some requests must only ever be made by a single component, but any component with the
+//! necessary bounds on its reactor event could make them through its effect builder. The effect
+//! builder methods for these take a capability token, a type defined in the module of the component
+//! allowed to make the request, whose private field keeps any other module from creating it.
+//! Making such a request from the wrong component then fails to compile.
 
 pub mod announcements;
+pub(crate) mod peer_requests;
 pub mod requests;
+pub(crate) mod subscriptions;
 
 use std::{
     any::type_name,
     borrow::Cow,
-    collections::{BTreeMap, HashMap, HashSet},
+    cmp,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fmt::{self, Debug, Display, Formatter},
     future::Future,
+    ops::Range,
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use datasize::DataSize;
-use futures::{channel::oneshot, future::BoxFuture, FutureExt};
+use futures::{
+    channel::oneshot,
+    future::BoxFuture,
+    stream::{FuturesUnordered, StreamExt},
+    FutureExt,
+};
 use semver::Version;
 use serde::{de::DeserializeOwned, Serialize};
 use smallvec::{smallvec, SmallVec};
 use tokio::join;
-use tracing::{error, warn};
+use tracing::{error, trace, warn};
 
 use casper_execution_engine::{
     core::engine_state::{
@@ -96,37 +113,47 @@ use casper_execution_engine::{
     storage::{global_state::CommitResult, protocol_data::ProtocolData, trie::Trie},
 };
 use casper_types::{
-    auction::EraValidators, ExecutionResult, Key, ProtocolVersion, PublicKey, Transfer,
+    auction::{EraValidators, ValidatorWeights},
+    ExecutionResult, Key, ProtocolVersion, PublicKey, Transfer,
 };
 
+#[cfg(feature = "debug-assertions")]
+use crate::reactor::invariants::TrackedResponder;
 use crate::{
     components::{
+        block_executor::{BlockSigningCapability, DeployExecutionCapability},
         chainspec_loader::ChainspecInfo,
-        consensus::{BlockContext, EraId},
-        contract_runtime::EraValidatorsRequest,
+        consensus::{BlockContext, BlockExecutionCapability, EraId},
+        contract_runtime::{EraValidatorsRequest, ValidatorWeightsByEraIdRequest},
         deploy_acceptor,
+        disk_monitor::DiskSpaceStage,
         fetcher::FetchResult,
+        rpc_server::SpeculativeExecutionCapability,
         small_network::GossipedAddress,
+        storage::{BlockHeaderBatch, TransientWriteError},
     },
     crypto::hash::Digest,
     effect::requests::LinearChainRequest,
     reactor::{EventQueueHandle, QueueKind},
     types::{
-        Block, BlockByHeight, BlockHash, BlockHeader, BlockLike, Deploy, DeployHash, DeployHeader,
-        DeployMetadata, FinalitySignature, FinalizedBlock, Item, ProtoBlock, Timestamp,
+        Block, BlockByHeight, BlockHash, BlockHeader, BlockLike, ComponentStatus, Deploy,
+        DeployHash, DeployHeader, DeployMetadata, FinalitySignature, FinalizedBlock, ImportedBan,
+        Item, NetworkTimeEstimate, PeerRequestAudit, PeerSample, ProtoBlock,
+        ProtocolVersionHistogram, SignedAttestation, Timestamp,
     },
     utils::Source,
     Chainspec,
 };
 use announcements::{
     BlockExecutorAnnouncement, ConsensusAnnouncement, DeployAcceptorAnnouncement,
-    GossiperAnnouncement, LinearChainAnnouncement, NetworkAnnouncement, RpcServerAnnouncement,
+    DiskMonitorAnnouncement, GossiperAnnouncement, LinearChainAnnouncement, NetworkAnnouncement,
+    RpcServerAnnouncement,
 };
 use casper_execution_engine::core::engine_state::put_trie::InsertedTrieKeyAndMissingDescendants;
 use requests::{
     BlockExecutorRequest, BlockProposerRequest, BlockValidationRequest, ChainspecLoaderRequest,
-    ConsensusRequest, ContractRuntimeRequest, FetcherRequest, MetricsRequest, NetworkInfoRequest,
-    NetworkRequest, ProtoBlockRequest, StateStoreRequest, StorageRequest,
+    ConsensusRequest, ContractRuntimeRequest, DeliveryStatus, FetcherRequest, MetricsRequest,
+    NetworkInfoRequest, NetworkRequest, ProtoBlockRequest, StateStoreRequest, StorageRequest,
 };
 
 /// A pinned, boxed future that produces one or more events.
@@ -143,16 +170,47 @@ pub type Effects<Ev> = Multiple<Effect<Ev>>;
 /// the same size as an empty vec, which is two pointers.
 pub type Multiple<T> = SmallVec<[T; 2]>;
 
+/// How often and how fast an operation which may fail transiently is retried.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct RetryPolicy {
+    /// Delay before the first retry.
+    pub(crate) initial_delay: Duration,
+    /// Upper bound for the delay between retries, which doubles after every failed attempt.
+    pub(crate) max_delay: Duration,
+    /// Number of attempts, including the first one, before giving up.
+    pub(crate) max_attempts: u32,
+}
+
+/// Retry policy for storage writes.
+///
+/// Gives a resize of the database map or an operator freeing up disk space roughly a minute before
+/// the node gives up.
+const STORAGE_WRITE_RETRY_POLICY: RetryPolicy = RetryPolicy {
+    initial_delay: Duration::from_millis(100),
+    max_delay: Duration::from_secs(10),
+    max_attempts: 12,
+};
+
 /// A responder satisfying a request.
 #[must_use]
 #[derive(DataSize)]
-pub struct Responder<T>(Option<oneshot::Sender<T>>);
+pub struct Responder<T> {
+    sender: Option<oneshot::Sender<T>>,
+    /// Tracks how long the responder has been waiting to be responded to.
+    #[cfg(feature = "debug-assertions")]
+    #[data_size(skip)]
+    _tracked: TrackedResponder,
+}
 
 impl<T: 'static + Send> Responder<T> {
     /// Creates a new `Responder`.
     #[inline]
     fn new(sender: oneshot::Sender<T>) -> Self {
-        Responder(Some(sender))
+        Responder {
+            sender: Some(sender),
+            #[cfg(feature = "debug-assertions")]
+            _tracked: TrackedResponder::new::<T>(),
+        }
     }
 
     /// Helper method for tests.
@@ -169,7 +227,7 @@ impl<T: 'static + Send> Responder<T> {
 impl<T> Responder<T> {
     /// Send `data` to the origin of the request.
     pub async fn respond(mut self, data: T) {
-        if let Some(sender) = self.0.take() {
+        if let Some(sender) = self.sender.take() {
             if sender.send(data).is_err() {
                 error!("could not send response to request down oneshot channel");
             }
@@ -193,7 +251,7 @@ impl<T> Display for Responder<T> {
 
 impl<T> Drop for Responder<T> {
     fn drop(&mut self) {
-        if self.0.is_some() {
+        if self.sender.is_some() {
             // This is usually a very serious error, as another component will now be stuck.
             error!(
                 "{} dropped without being responded to --- \
@@ -429,6 +487,53 @@ impl<REv> EffectBuilder<REv> {
         async {} // The compiler will complain about an incorrect return value otherwise.
     }
 
+    /// Gets the per-component event handling statistics of the reactor.
+    ///
+    /// Unlike most other effects, this reads the statistics directly instead of making a request.
+    pub(crate) fn get_component_stats(self) -> Vec<ComponentStatus> {
+        self.0.component_stats().snapshot()
+    }
+
+    /// Scales a configured concurrency to the current load of the node.
+    ///
+    /// Under load, the result is less than `nominal`, but never less than one unless `nominal` is
+    /// zero. Like `get_component_stats`, this does not make a request.
+    pub(crate) fn scale_concurrency(self, nominal: usize) -> usize {
+        self.0.load_controller().scale(nominal)
+    }
+
+    /// Publishes a value to all components subscribed to its type, see `subscribe`.
+    ///
+    /// Each subscriber receives the value as an event scheduled on the regular queue, in the same
+    /// way as an announcement, see `crate::effect::subscriptions`.
+    pub(crate) async fn publish<T>(self, value: T)
+    where
+        T: Clone + Send + 'static,
+    {
+        let events = self.0.subscriptions().publish(value);
+        trace!(subscribers = events.len(), "published {}", type_name::<T>());
+        for event in events {
+            self.0.schedule(event, QueueKind::Regular).await;
+        }
+    }
+
+    /// Subscribes to all values of type `T` published from now on, see `publish`.
+    ///
+    /// Every value published is turned into an event by `map`. The subscription lasts until the
+    /// runner of the reactor has been dropped, e.g. after transitioning from the joiner to the
+    /// validator reactor.
+    pub fn subscribe<T, Ev, F>(self, map: F)
+    where
+        T: 'static,
+        REv: From<Ev>,
+        Ev: 'static,
+        F: Fn(T) -> Ev + Send + 'static,
+    {
+        self.0
+            .subscriptions()
+            .subscribe(move |value| REv::from(map(value)));
+    }
+
     /// Sets a timeout.
     pub(crate) async fn set_timeout(self, timeout: Duration) -> Duration {
         let then = Instant::now();
@@ -436,6 +541,66 @@ impl<REv> EffectBuilder<REv> {
         Instant::now() - then
     }
 
+    /// Runs `operation` until it succeeds, waiting with exponential backoff between attempts.
+    ///
+    /// Returns the last error if all attempts allowed by `policy` failed.
+    pub(crate) async fn retry_with_backoff<T, E, F, Fut>(
+        self,
+        description: &str,
+        policy: RetryPolicy,
+        mut operation: F,
+    ) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Display,
+    {
+        let mut delay = policy.initial_delay;
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(error) if attempt < policy.max_attempts => {
+                    warn!(%error, attempt, ?delay, "{} failed, retrying", description);
+                    self.set_timeout(delay).await;
+                    delay = cmp::min(delay * 2, policy.max_delay);
+                    attempt += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Makes a storage write request, retrying transient failures.
+    ///
+    /// Escalates to a fatal error once the retries are exhausted.
+    async fn write_to_storage<F>(self, description: &str, make_request: F) -> bool
+    where
+        REv: From<StorageRequest>,
+        F: Fn(Responder<Result<bool, TransientWriteError>>) -> StorageRequest,
+    {
+        let result = self
+            .retry_with_backoff(description, STORAGE_WRITE_RETRY_POLICY, || {
+                self.make_request(&make_request, QueueKind::Regular)
+            })
+            .await;
+        match result {
+            Ok(is_new) => is_new,
+            Err(error) => {
+                self.fatal(
+                    file!(),
+                    line!(),
+                    format!(
+                        "{} failed after {} attempts: {}",
+                        description, STORAGE_WRITE_RETRY_POLICY.max_attempts, error
+                    ),
+                )
+                .await;
+                false
+            }
+        }
+    }
+
     /// Retrieve a snapshot of the nodes current metrics formatted as string.
     ///
     /// If an error occurred producing the metrics, `None` is returned.
@@ -481,6 +646,26 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
+    /// Sends a network message, returning whether it was delivered.
+    ///
+    /// Unlike `send_message`, this only returns once the message has been written to the
+    /// connection to the peer, or is known not to be. A message which has been written is still
+    /// not guaranteed to be received, but one reported as failed or dropped is worth resending.
+    pub(crate) async fn send_message_confirmed<I, P>(self, dest: I, payload: P) -> DeliveryStatus
+    where
+        REv: From<NetworkRequest<I, P>>,
+    {
+        self.make_request(
+            |responder| NetworkRequest::SendMessageConfirmed {
+                dest,
+                payload,
+                responder,
+            },
+            QueueKind::Network,
+        )
+        .await
+    }
+
     /// Broadcasts a network message.
     ///
     /// Broadcasts a network message to all peers connected at the time the message is sent.
@@ -495,6 +680,28 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
+    /// Broadcasts a network message to validators only.
+    ///
+    /// Sends a network message to the peers connected at the time the message is sent which
+    /// validate with one of `validators`, as far as the network can tell.
+    pub async fn broadcast_message_to_validators<I, P>(
+        self,
+        payload: P,
+        validators: HashSet<PublicKey>,
+    ) where
+        REv: From<NetworkRequest<I, P>>,
+    {
+        self.make_request(
+            |responder| NetworkRequest::BroadcastToValidators {
+                payload,
+                validators,
+                responder,
+            },
+            QueueKind::Network,
+        )
+        .await
+    }
+
     /// Gossips a network message.
     ///
     /// A low-level "gossip" function, selects `count` randomly chosen nodes on the network,
@@ -537,6 +744,94 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
+    /// Gets the protocol versions advertised by connected network peers.
+    pub async fn network_peer_protocol_versions<I>(self) -> ProtocolVersionHistogram
+    where
+        REv: From<NetworkInfoRequest<I>>,
+        I: Send + 'static,
+    {
+        self.make_request(
+            |responder| NetworkInfoRequest::GetPeerProtocolVersions { responder },
+            QueueKind::Api,
+        )
+        .await
+    }
+
+    /// Gets the bans imported from trusted signed ban lists which are currently in effect.
+    pub async fn network_imported_bans<I>(self) -> Vec<ImportedBan>
+    where
+        REv: From<NetworkInfoRequest<I>>,
+        I: Send + 'static,
+    {
+        self.make_request(
+            |responder| NetworkInfoRequest::GetImportedBans { responder },
+            QueueKind::Api,
+        )
+        .await
+    }
+
+    /// Gets the latest software attestation of every one of `validators` known to the network
+    /// component.
+    pub async fn network_attestations<I>(
+        self,
+        validators: BTreeSet<PublicKey>,
+    ) -> Vec<SignedAttestation>
+    where
+        REv: From<NetworkInfoRequest<I>>,
+        I: Send + 'static,
+    {
+        self.make_request(
+            |responder| NetworkInfoRequest::GetAttestations {
+                validators,
+                responder,
+            },
+            QueueKind::Api,
+        )
+        .await
+    }
+
+    /// Gets the data requests sent by the connected and recently connected peers.
+    pub async fn network_request_audit<I>(self) -> Vec<PeerRequestAudit>
+    where
+        REv: From<NetworkInfoRequest<I>>,
+        I: Send + 'static,
+    {
+        self.make_request(
+            |responder| NetworkInfoRequest::GetRequestAudit { responder },
+            QueueKind::Api,
+        )
+        .await
+    }
+
+    /// Gets a uniformly random sample of at most `count` connected network peers.
+    pub async fn network_peer_sample<I>(self, count: usize) -> PeerSample
+    where
+        REv: From<NetworkInfoRequest<I>>,
+        I: Send + 'static,
+    {
+        self.make_request(
+            |responder| NetworkInfoRequest::SamplePeers { count, responder },
+            QueueKind::Api,
+        )
+        .await
+    }
+
+    /// Gets the estimated offset of our clock from the median clock of connected network peers.
+    ///
+    /// Meant for consensus to tell a skewed local clock apart from network delays, e.g. before
+    /// calibrating round timers.
+    pub async fn network_time_estimate<I>(self) -> Option<NetworkTimeEstimate>
+    where
+        REv: From<NetworkInfoRequest<I>>,
+        I: Send + 'static,
+    {
+        self.make_request(
+            |responder| NetworkInfoRequest::GetNetworkTime { responder },
+            QueueKind::Network,
+        )
+        .await
+    }
+
     /// Announces that a network message has been received.
     pub(crate) async fn announce_message_received<I, P>(self, sender: I, payload: P)
     where
@@ -659,20 +954,45 @@ impl<REv> EffectBuilder<REv> {
             .await
     }
 
+    /// Hands an executed block to the linear chain, to be stored and signed by consensus.
+    pub(crate) async fn put_executed_block_to_linear_chain<I>(
+        self,
+        signing_capability: BlockSigningCapability,
+        block: Block,
+        execution_results: HashMap<DeployHash, ExecutionResult>,
+    ) where
+        REv: From<LinearChainRequest<I>>,
+    {
+        self.0
+            .schedule(
+                LinearChainRequest::ExecutedBlock {
+                    block: Box::new(block),
+                    execution_results,
+                    signing_capability,
+                },
+                QueueKind::Regular,
+            )
+            .await
+    }
+
     /// Puts the given block into the linear block store.
+    ///
+    /// Transient write failures are retried with backoff, see `write_to_storage`.
     pub(crate) async fn put_block_to_storage(self, block: Box<Block>) -> bool
     where
         REv: From<StorageRequest>,
     {
-        self.make_request(
-            |responder| StorageRequest::PutBlock { block, responder },
-            QueueKind::Regular,
-        )
+        self.write_to_storage("putting block to storage", |responder| {
+            StorageRequest::PutBlock {
+                block: block.clone(),
+                responder,
+            }
+        })
         .await
     }
 
     /// Gets the requested block from the linear block store.
-    pub(crate) async fn get_block_from_storage(self, block_hash: BlockHash) -> Option<Block>
+    pub async fn get_block_from_storage(self, block_hash: BlockHash) -> Option<Block>
     where
         REv: From<StorageRequest>,
     {
@@ -686,6 +1006,30 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
+    /// Gets the headers of the blocks stored at `heights` from storage, at most `max_count` of
+    /// them.
+    ///
+    /// Scans over long ranges are to be done in several calls, each resuming at the `next_height`
+    /// of the previous batch, so only one batch is held in memory at a time.
+    pub(crate) async fn get_block_headers_by_height_from_storage(
+        self,
+        heights: Range<u64>,
+        max_count: usize,
+    ) -> BlockHeaderBatch
+    where
+        REv: From<StorageRequest>,
+    {
+        self.make_request(
+            |responder| StorageRequest::GetBlockHeadersByHeight {
+                heights,
+                max_count,
+                responder,
+            },
+            QueueKind::Regular,
+        )
+        .await
+    }
+
     /// Gets the requested block's transfers from storage.
     pub(crate) async fn get_block_transfers_from_storage(
         self,
@@ -705,7 +1049,7 @@ impl<REv> EffectBuilder<REv> {
     }
 
     /// Requests the block at the given height.
-    pub(crate) async fn get_block_at_height_from_storage(self, height: u64) -> Option<Block>
+    pub async fn get_block_at_height_from_storage(self, height: u64) -> Option<Block>
     where
         REv: From<StorageRequest>,
     {
@@ -791,19 +1135,23 @@ impl<REv> EffectBuilder<REv> {
     }
 
     /// Puts the given deploy into the deploy store.
+    ///
+    /// Transient write failures are retried with backoff, see `write_to_storage`.
     pub(crate) async fn put_deploy_to_storage(self, deploy: Box<Deploy>) -> bool
     where
         REv: From<StorageRequest>,
     {
-        self.make_request(
-            |responder| StorageRequest::PutDeploy { deploy, responder },
-            QueueKind::Regular,
-        )
+        self.write_to_storage("putting deploy to storage", |responder| {
+            StorageRequest::PutDeploy {
+                deploy: deploy.clone(),
+                responder,
+            }
+        })
         .await
     }
 
     /// Gets the requested deploys from the deploy store.
-    pub(crate) async fn get_deploys_from_storage(
+    pub async fn get_deploys_from_storage(
         self,
         deploy_hashes: Multiple<DeployHash>,
     ) -> Vec<Option<Deploy>>
@@ -858,6 +1206,62 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
+    /// Puts a blob into the blob store, returning the hash it is stored under.
+    ///
+    /// Every call adds a reference to the blob, which should be released once it is no longer used.
+    pub(crate) async fn put_blob_to_storage(self, data: Vec<u8>) -> Digest
+    where
+        REv: From<StorageRequest>,
+    {
+        self.make_request(
+            |responder| StorageRequest::PutBlob { data, responder },
+            QueueKind::Regular,
+        )
+        .await
+    }
+
+    /// Gets the requested blob from the blob store.
+    pub(crate) async fn get_blob_from_storage(self, blob_hash: Digest) -> Option<Vec<u8>>
+    where
+        REv: From<StorageRequest>,
+    {
+        self.make_request(
+            |responder| StorageRequest::GetBlob {
+                blob_hash,
+                responder,
+            },
+            QueueKind::Regular,
+        )
+        .await
+    }
+
+    /// Releases a reference to a blob in the blob store, returning the remaining reference count.
+    pub(crate) async fn release_blob_in_storage(self, blob_hash: Digest) -> Option<u64>
+    where
+        REv: From<StorageRequest>,
+    {
+        self.make_request(
+            |responder| StorageRequest::ReleaseBlob {
+                blob_hash,
+                responder,
+            },
+            QueueKind::Regular,
+        )
+        .await
+    }
+
+    /// Deletes all unreferenced blobs from the blob store, returning the number deleted.
+    pub(crate) async fn collect_blob_garbage(self) -> usize
+    where
+        REv: From<StorageRequest>,
+    {
+        self.make_request(
+            |responder| StorageRequest::CollectBlobGarbage { responder },
+            QueueKind::Regular,
+        )
+        .await
+    }
+
     /// Gets the requested deploy using the `DeployFetcher`.
     pub(crate) async fn fetch_deploy<I>(
         self,
@@ -900,6 +1304,74 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
+    /// Gets the requested block using the `BlockFetcher`, from the best of `peers` if it is not
+    /// held locally.
+    pub(crate) async fn fetch_block_from_any<I>(
+        self,
+        block_hash: BlockHash,
+        peers: Vec<I>,
+    ) -> Option<FetchResult<Block, I>>
+    where
+        REv: From<FetcherRequest<I, Block>>,
+        I: Send + 'static,
+    {
+        self.make_request(
+            |responder| FetcherRequest::FetchFromAny {
+                id: block_hash,
+                peers,
+                responder,
+            },
+            QueueKind::Regular,
+        )
+        .await
+    }
+
+    /// Fetches all items with the given IDs from storage or from `peer`.
+    ///
+    /// The items are requested concurrently. The result for every item, `None` if it could not be
+    /// fetched, is turned into an event by `on_item` and scheduled as soon as it arrives. The
+    /// returned future completes once all items have been dealt with, returning the IDs of the
+    /// items which could not be fetched.
+    pub(crate) async fn fetch_many<T, I, Ev, F>(
+        self,
+        ids: impl IntoIterator<Item = T::Id>,
+        peer: I,
+        on_item: F,
+    ) -> Vec<T::Id>
+    where
+        T: Item + 'static,
+        I: Clone + Send + 'static,
+        REv: From<FetcherRequest<I, T>> + From<Ev>,
+        F: Fn(T::Id, Option<FetchResult<T, I>>) -> Ev,
+    {
+        let mut pending: FuturesUnordered<_> = ids
+            .into_iter()
+            .map(|id| {
+                let peer = peer.clone();
+                self.make_request(
+                    move |responder| FetcherRequest::Fetch {
+                        id,
+                        peer,
+                        responder,
+                    },
+                    QueueKind::Regular,
+                )
+                .map(move |result| (id, result))
+            })
+            .collect();
+
+        let mut missing = Vec::new();
+        while let Some((id, result)) = pending.next().await {
+            if result.is_none() {
+                missing.push(id);
+            }
+            self.0
+                .schedule(on_item(id, result), QueueKind::Regular)
+                .await;
+        }
+        missing
+    }
+
     /// Requests a linear chain block at `block_height`.
     pub(crate) async fn fetch_block_by_height<I>(
         self,
@@ -950,8 +1422,11 @@ impl<REv> EffectBuilder<REv> {
     }
 
     /// Passes a finalized proto-block to the block executor component to execute it.
-    pub(crate) async fn execute_block(self, finalized_block: FinalizedBlock)
-    where
+    pub(crate) async fn execute_block(
+        self,
+        _capability: BlockExecutionCapability,
+        finalized_block: FinalizedBlock,
+    ) where
         REv: From<BlockExecutorRequest>,
     {
         self.0
@@ -962,6 +1437,26 @@ impl<REv> EffectBuilder<REv> {
             .await
     }
 
+    /// Passes a block received from a peer to the block executor component to execute it and
+    /// check the resulting state root hash against the received one.
+    pub(crate) async fn execute_received_block(
+        self,
+        finalized_block: FinalizedBlock,
+        expected_state_root_hash: Digest,
+    ) where
+        REv: From<BlockExecutorRequest>,
+    {
+        self.0
+            .schedule(
+                BlockExecutorRequest::ExecuteReceivedBlock {
+                    finalized_block,
+                    expected_state_root_hash,
+                },
+                QueueKind::Regular,
+            )
+            .await
+    }
+
     /// Checks whether the deploys included in the block exist on the network. This includes
     /// the block's timestamp, in order that it be checked against the timestamp of the deploys
     /// within the block.
@@ -987,19 +1482,6 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
-    /// Announces that a proto block has been finalized.
-    pub(crate) async fn announce_finalized_block<I>(self, finalized_block: FinalizedBlock)
-    where
-        REv: From<ConsensusAnnouncement<I>>,
-    {
-        self.0
-            .schedule(
-                ConsensusAnnouncement::Finalized(Box::new(finalized_block)),
-                QueueKind::Regular,
-            )
-            .await
-    }
-
     pub(crate) async fn announce_block_handled<I>(self, block_header: BlockHeader)
     where
         REv: From<ConsensusAnnouncement<I>>,
@@ -1033,6 +1515,22 @@ impl<REv> EffectBuilder<REv> {
             .await
     }
 
+    /// Our own validator is at risk of equivocating, or already has.
+    pub(crate) async fn announce_equivocation_risk<I>(self, era_id: EraId, doppelganger: bool)
+    where
+        REv: From<ConsensusAnnouncement<I>>,
+    {
+        self.0
+            .schedule(
+                ConsensusAnnouncement::EquivocationRisk {
+                    era_id,
+                    doppelganger,
+                },
+                QueueKind::Regular,
+            )
+            .await
+    }
+
     /// Announce the intent to disconnect from a specific peer, which consensus thinks is faulty.
     pub(crate) async fn announce_disconnect_from_peer<I>(self, peer: I)
     where
@@ -1046,6 +1544,48 @@ impl<REv> EffectBuilder<REv> {
             .await
     }
 
+    /// Announce that the node became, or stopped being, possibly partitioned from the network.
+    pub(crate) async fn announce_partition_status<I>(
+        self,
+        era_id: EraId,
+        active_weight_percent: u64,
+        possibly_partitioned: bool,
+    ) where
+        REv: From<ConsensusAnnouncement<I>>,
+    {
+        self.0
+            .schedule(
+                ConsensusAnnouncement::PartitionStatus {
+                    era_id,
+                    active_weight_percent,
+                    possibly_partitioned,
+                },
+                QueueKind::Regular,
+            )
+            .await
+    }
+
+    /// Announce that our participation in consensus became, or stopped being, degraded.
+    pub(crate) async fn announce_participation_status<I>(
+        self,
+        era_id: EraId,
+        cited_weight_percent: u64,
+        participation_degraded: bool,
+    ) where
+        REv: From<ConsensusAnnouncement<I>>,
+    {
+        self.0
+            .schedule(
+                ConsensusAnnouncement::ParticipationStatus {
+                    era_id,
+                    cited_weight_percent,
+                    participation_degraded,
+                },
+                QueueKind::Regular,
+            )
+            .await
+    }
+
     /// The linear chain has stored a newly-created block.
     pub(crate) async fn announce_block_added(self, block_hash: BlockHash, block_header: BlockHeader)
     where
@@ -1075,6 +1615,20 @@ impl<REv> EffectBuilder<REv> {
             .await
     }
 
+    /// Announce that the node entered a different stage of degradation due to the available disk
+    /// space.
+    pub(crate) async fn announce_disk_space_stage(self, stage: DiskSpaceStage)
+    where
+        REv: From<DiskMonitorAnnouncement>,
+    {
+        self.0
+            .schedule(
+                DiskMonitorAnnouncement::StageChanged(stage),
+                QueueKind::Regular,
+            )
+            .await
+    }
+
     /// Runs the genesis process on the contract runtime.
     pub(crate) async fn commit_genesis(
         self,
@@ -1195,6 +1749,7 @@ impl<REv> EffectBuilder<REv> {
     /// Requests an execution of deploys using Contract Runtime.
     pub(crate) async fn request_execute(
         self,
+        _capability: DeployExecutionCapability,
         execute_request: ExecuteRequest,
     ) -> Result<ExecutionResults, engine_state::RootNotFound>
     where
@@ -1211,9 +1766,34 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
+    /// Requests the execution of deploys whose effects are never committed, e.g. to show clients
+    /// what a deploy would change.
+    ///
+    /// This requires a `SpeculativeExecutionCapability` rather than a `DeployExecutionCapability`,
+    /// so its results cannot be passed on to `request_commit`.
+    pub(crate) async fn speculatively_execute(
+        self,
+        _capability: SpeculativeExecutionCapability,
+        execute_request: ExecuteRequest,
+    ) -> Result<ExecutionResults, engine_state::RootNotFound>
+    where
+        REv: From<ContractRuntimeRequest>,
+    {
+        let execute_request = Box::new(execute_request);
+        self.make_request(
+            |responder| ContractRuntimeRequest::Execute {
+                execute_request,
+                responder,
+            },
+            QueueKind::Api,
+        )
+        .await
+    }
+
     /// Requests a commit of effects on the Contract Runtime component.
     pub(crate) async fn request_commit(
         self,
+        _capability: DeployExecutionCapability,
         state_root_hash: Digest,
         effects: AdditiveMap<Key, Transform>,
     ) -> Result<CommitResult, engine_state::Error>
@@ -1347,23 +1927,51 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
+    /// Returns the validator weights of the given era as known from the auction contract at
+    /// `request`'s state root hash, or `None` if the auction has not determined them.
+    ///
+    /// This operation is read only.
+    pub(crate) async fn get_validator_weights_by_era_id(
+        self,
+        request: ValidatorWeightsByEraIdRequest,
+    ) -> Result<Option<ValidatorWeights>, GetEraValidatorsError>
+    where
+        REv: From<ContractRuntimeRequest>,
+    {
+        self.make_request(
+            |responder| ContractRuntimeRequest::GetValidatorWeightsByEraId { request, responder },
+            QueueKind::Regular,
+        )
+        .await
+    }
+
     /// Gets the set of validators, the booking block and the key block for a new era
+    ///
+    /// The validators are those computed by the local auction contract as of the switch block's
+    /// state root hash, to be checked against the ones in the switch block's header.
     pub(crate) async fn create_new_era(
         self,
         booking_block_height: u64,
         key_block_height: u64,
-    ) -> (Option<Block>, Option<Block>)
+        auction_request: ValidatorWeightsByEraIdRequest,
+    ) -> (
+        Option<Block>,
+        Option<Block>,
+        Result<Option<ValidatorWeights>, GetEraValidatorsError>,
+    )
     where
         REv: From<ContractRuntimeRequest> + From<StorageRequest>,
     {
         let future_booking_block = self.get_block_at_height_from_storage(booking_block_height);
         let future_key_block = self.get_block_at_height_from_storage(key_block_height);
-        join!(future_booking_block, future_key_block)
+        let future_validators = self.get_validator_weights_by_era_id(auction_request);
+        join!(future_booking_block, future_key_block, future_validators)
     }
 
     /// Request consensus to sign a block from the linear chain and possibly start a new era.
     pub(crate) async fn handle_linear_chain_block(
         self,
+        _capability: BlockSigningCapability,
         block_header: BlockHeader,
     ) -> Option<FinalitySignature>
     where
@@ -1388,6 +1996,15 @@ impl<REv> EffectBuilder<REv> {
         .await
     }
 
+    /// Gets the validators of the current era.
+    pub(crate) async fn current_validators(self) -> BTreeSet<PublicKey>
+    where
+        REv: From<ConsensusRequest>,
+    {
+        self.make_request(ConsensusRequest::CurrentValidators, QueueKind::Api)
+            .await
+    }
+
     /// Check if validator is bonded in the future era (`era_id`).
     /// This information is known only by the Contract Runtime since consensus component
     /// knows only about currently active eras.
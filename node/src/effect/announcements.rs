@@ -13,11 +13,14 @@ use serde::Serialize;
 use casper_types::{ExecutionResult, PublicKey};
 
 use crate::{
-    components::{consensus::EraId, deploy_acceptor::Error, small_network::GossipedAddress},
+    components::{
+        consensus::EraId, deploy_acceptor::Error, disk_monitor::DiskSpaceStage,
+        small_network::GossipedAddress,
+    },
     effect::Responder,
     types::{
-        Block, BlockHash, BlockHeader, Deploy, DeployHash, DeployHeader, FinalitySignature,
-        FinalizedBlock, Item, Timestamp,
+        Block, BlockHash, BlockHeader, Deploy, DeployHash, DeployHeader, FinalitySignature, Item,
+        Timestamp,
     },
     utils::Source,
 };
@@ -121,10 +124,8 @@ impl<I: Display> Display for DeployAcceptorAnnouncement<I> {
 }
 
 /// A consensus announcement.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum ConsensusAnnouncement<I> {
-    /// A block was finalized.
-    Finalized(Box<FinalizedBlock>),
     /// A linear chain block has been handled.
     Handled(Box<BlockHeader>),
     /// An equivocation has been detected.
@@ -138,6 +139,33 @@ pub enum ConsensusAnnouncement<I> {
     },
     /// We want to disconnect from a peer due to its transgressions.
     DisconnectFromPeer(I),
+    /// The share of validator weight we recently received messages from crossed the configured
+    /// threshold.
+    PartitionStatus {
+        /// The era in which the change was detected.
+        era_id: EraId,
+        /// The percentage of the other validators' weight we recently received messages from.
+        active_weight_percent: u64,
+        /// Whether the percentage is below the threshold, i.e. we are possibly partitioned.
+        possibly_partitioned: bool,
+    },
+    /// The share of validator weight citing our own recent units crossed the configured threshold.
+    ParticipationStatus {
+        /// The era in which the change was detected.
+        era_id: EraId,
+        /// The percentage of the other validators' weight citing our recent units.
+        cited_weight_percent: u64,
+        /// Whether the percentage is below the threshold, i.e. our participation is degraded.
+        participation_degraded: bool,
+    },
+    /// Our own validator is at risk of equivocating, or already has.
+    EquivocationRisk {
+        /// The era in which the risk was detected.
+        era_id: EraId,
+        /// Whether another node is running with our validator key, rather than our own node having
+        /// equivocated.
+        doppelganger: bool,
+    },
 }
 
 impl<I> Display for ConsensusAnnouncement<I>
@@ -146,9 +174,6 @@ where
 {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            ConsensusAnnouncement::Finalized(block) => {
-                write!(formatter, "finalized proto block {}", block)
-            }
             ConsensusAnnouncement::Handled(block_header) => write!(
                 formatter,
                 "Linear chain block has been handled by consensus, height={}, hash={}",
@@ -167,12 +192,38 @@ where
             ConsensusAnnouncement::DisconnectFromPeer(peer) => {
                 write!(formatter, "Consensus wanting to disconnect from {}", peer)
             }
+            ConsensusAnnouncement::PartitionStatus {
+                era_id,
+                active_weight_percent,
+                possibly_partitioned,
+            } => write!(
+                formatter,
+                "{}% of validator weight recently active in era {}, possibly partitioned: {}",
+                active_weight_percent, era_id, possibly_partitioned
+            ),
+            ConsensusAnnouncement::ParticipationStatus {
+                era_id,
+                cited_weight_percent,
+                participation_degraded,
+            } => write!(
+                formatter,
+                "{}% of validator weight cites our units in era {}, participation degraded: {}",
+                cited_weight_percent, era_id, participation_degraded
+            ),
+            ConsensusAnnouncement::EquivocationRisk {
+                era_id,
+                doppelganger,
+            } => write!(
+                formatter,
+                "own validator at risk of equivocating in era {}, doppelganger: {}",
+                era_id, doppelganger
+            ),
         }
     }
 }
 
 /// A BlockExecutor announcement.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum BlockExecutorAnnouncement {
     /// A new block from the linear chain was produced.
     LinearChainBlock {
@@ -209,7 +260,7 @@ impl<T: Item> Display for GossiperAnnouncement<T> {
 }
 
 /// A linear chain announcement.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum LinearChainAnnouncement {
     /// A new block has been created and stored locally.
     BlockAdded {
@@ -234,3 +285,20 @@ impl Display for LinearChainAnnouncement {
         }
     }
 }
+
+/// A disk monitor announcement.
+#[derive(Debug)]
+pub enum DiskMonitorAnnouncement {
+    /// The node entered a different stage of degradation due to the available disk space.
+    StageChanged(DiskSpaceStage),
+}
+
+impl Display for DiskMonitorAnnouncement {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskMonitorAnnouncement::StageChanged(stage) => {
+                write!(formatter, "disk space stage changed to {}", stage)
+            }
+        }
+    }
+}
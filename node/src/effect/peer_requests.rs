@@ -0,0 +1,357 @@
+//! Time-bounded requests to peers.
+//!
+//! `PeerRequests` keeps track of the requests a component sends to peers and expects a response
+//! to, e.g. a `GetRequest` for an item. Every request is assigned a correlation ID and a deadline;
+//! once the deadline has passed, a timeout event carrying the ID is raised. The component hands
+//! the ID back through `PeerRequests::timed_out`, which only returns the request if it has not
+//! been answered in the meantime, so components need no timeout bookkeeping of their own.
+//!
+//! The wire protocol carries no request IDs, so a response is correlated with its request by the
+//! responding peer and the request key, usually the ID of the requested item. Only one request per
+//! peer and key is outstanding at any time.
+//!
+//! The number of pending requests is limited. Requests beyond the limit are deferred, see `defer`,
+//! and handed back through `next_deferred` once earlier ones are done. The limit is scaled to the
+//! load of the node, see `EffectBuilder::scale_concurrency`.
+//!
+//! The latency of answered requests, the number of timed out requests and the number of pending
+//! and deferred ones are exposed as metrics, prefixed with the name given on construction.
+//!
+//! The pending and deferred requests to a single peer form its queue depth, see `queue_depth`,
+//! which components choosing between peers can use to spread their requests.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Debug, Display, Formatter},
+    hash::Hash,
+    time::Duration,
+};
+
+use datasize::DataSize;
+use prometheus::{Histogram, HistogramOpts, IntCounter, IntGauge, Registry};
+use serde::Serialize;
+use tracing::debug;
+
+use super::{requests::NetworkRequest, EffectBuilder, EffectExt, Effects};
+use crate::types::{NodeId, TimeDiff, Timestamp};
+
+/// The correlation ID of a request to a peer.
+///
+/// Only unique within the `PeerRequests` which issued it.
+#[derive(Clone, Copy, DataSize, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct RequestId(u64);
+
+impl Display for RequestId {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "request-{}", self.0)
+    }
+}
+
+/// A request awaiting a response.
+#[derive(DataSize, Debug)]
+struct PendingRequest {
+    id: RequestId,
+    sent: Timestamp,
+}
+
+/// The outstanding requests of a component to peers, by peer and key.
+#[derive(DataSize, Debug)]
+pub(crate) struct PeerRequests<K>
+where
+    K: Copy + Eq + Hash + Debug,
+{
+    /// The time after which an unanswered request times out.
+    timeout: Duration,
+    /// The ID of the next request.
+    next_id: u64,
+    /// The pending requests, by recipient and key.
+    pending: HashMap<(NodeId, K), PendingRequest>,
+    /// The recipient and key of the pending requests, by ID.
+    by_id: HashMap<RequestId, (NodeId, K)>,
+    /// The maximum number of pending requests at full concurrency.
+    max_pending: usize,
+    /// The recipients and keys of requests waiting for the number of pending ones to drop.
+    deferred: VecDeque<(NodeId, K)>,
+    #[data_size(skip)]
+    metrics: PeerRequestsMetrics,
+}
+
+impl<K> PeerRequests<K>
+where
+    K: Copy + Eq + Hash + Debug,
+{
+    /// Creates a new set of requests timing out after `timeout`, with at most `max_pending` of
+    /// them pending at a time.
+    ///
+    /// Must be supplied with a name, which should be a snake-case identifier to disambiguate the
+    /// metrics of the requests from those of other components.
+    pub(crate) fn new(
+        name: &str,
+        timeout: Duration,
+        max_pending: usize,
+        registry: &Registry,
+    ) -> Result<Self, prometheus::Error> {
+        Ok(PeerRequests {
+            timeout,
+            next_id: 0,
+            pending: HashMap::new(),
+            by_id: HashMap::new(),
+            max_pending,
+            deferred: VecDeque::new(),
+            metrics: PeerRequestsMetrics::new(name, registry)?,
+        })
+    }
+
+    /// Sends `payload` to `peer` as a request for `key`, unless one is pending already.
+    ///
+    /// If no response has been registered through `responded` before the timeout elapses, the
+    /// event created by `timeout_event` is raised; it should be handled by calling `timed_out`.
+    pub(crate) fn send<REv, Ev, P, F>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        peer: NodeId,
+        key: K,
+        payload: P,
+        timeout_event: F,
+    ) -> Effects<Ev>
+    where
+        REv: From<NetworkRequest<NodeId, P>> + Send,
+        P: Send + 'static,
+        Ev: Send + 'static,
+        F: FnOnce(RequestId) -> Ev + Send + 'static,
+    {
+        let request_id = match self.start(peer.clone(), key, Timestamp::now()) {
+            Some(request_id) => request_id,
+            None => {
+                debug!(?key, %peer, "request already pending");
+                return Effects::new();
+            }
+        };
+        let mut effects = effect_builder.send_message(peer, payload).ignore();
+        effects.extend(
+            effect_builder
+                .set_timeout(self.timeout)
+                .event(move |_| timeout_event(request_id)),
+        );
+        effects
+    }
+
+    /// Registers a response from `peer` to the request for `key`.
+    ///
+    /// Returns the time the response took, or `None` if there is no such request pending, e.g.
+    /// because it timed out already.
+    pub(crate) fn responded(&mut self, peer: &NodeId, key: &K) -> Option<TimeDiff> {
+        self.complete(peer, key, Timestamp::now())
+    }
+
+    /// Returns the number of requests to `peer` which are pending or deferred.
+    pub(crate) fn queue_depth(&self, peer: &NodeId) -> usize {
+        let pending = self
+            .pending
+            .keys()
+            .filter(|(recipient, _)| recipient == peer)
+            .count();
+        let deferred = self
+            .deferred
+            .iter()
+            .filter(|(recipient, _)| recipient == peer)
+            .count();
+        pending + deferred
+    }
+
+    /// Returns whether as many requests are pending as currently allowed.
+    pub(crate) fn is_busy<REv>(&self, effect_builder: EffectBuilder<REv>) -> bool {
+        self.pending.len() >= effect_builder.scale_concurrency(self.max_pending)
+    }
+
+    /// Defers the request for `key` to `peer` until fewer requests are pending.
+    pub(crate) fn defer(&mut self, peer: NodeId, key: K) {
+        self.deferred.push_back((peer, key));
+        self.metrics.deferred.set(self.deferred.len() as i64);
+    }
+
+    /// Returns the recipient and key of the oldest deferred request, if it can be sent now.
+    pub(crate) fn next_deferred<REv>(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+    ) -> Option<(NodeId, K)> {
+        if self.is_busy(effect_builder) {
+            return None;
+        }
+        let next = self.deferred.pop_front();
+        self.metrics.deferred.set(self.deferred.len() as i64);
+        next
+    }
+
+    /// Forgets the requests for `key` to all peers, e.g. because it has been fulfilled otherwise.
+    pub(crate) fn cancel_all(&mut self, key: &K) {
+        let by_id = &mut self.by_id;
+        self.pending.retain(|(_, pending_key), pending| {
+            if pending_key != key {
+                return true;
+            }
+            by_id.remove(&pending.id);
+            false
+        });
+        self.deferred
+            .retain(|(_, deferred_key)| deferred_key != key);
+        self.metrics.pending.set(self.pending.len() as i64);
+        self.metrics.deferred.set(self.deferred.len() as i64);
+    }
+
+    /// Handles the timeout of the request with the given ID.
+    ///
+    /// Returns the recipient and key of the request if it was still pending, `None` if it has been
+    /// answered or cancelled in the meantime.
+    pub(crate) fn timed_out(&mut self, request_id: RequestId) -> Option<(NodeId, K)> {
+        let (peer, key) = self.by_id.remove(&request_id)?;
+        self.pending.remove(&(peer.clone(), key));
+        self.metrics.timed_out.inc();
+        self.metrics.pending.set(self.pending.len() as i64);
+        Some((peer, key))
+    }
+
+    /// Starts a request, returning its ID, or `None` if one for the same peer and key is pending.
+    fn start(&mut self, peer: NodeId, key: K, now: Timestamp) -> Option<RequestId> {
+        if self.pending.contains_key(&(peer.clone(), key)) {
+            return None;
+        }
+        let id = RequestId(self.next_id);
+        self.next_id += 1;
+        self.pending
+            .insert((peer.clone(), key), PendingRequest { id, sent: now });
+        self.by_id.insert(id, (peer, key));
+        self.metrics.pending.set(self.pending.len() as i64);
+        Some(id)
+    }
+
+    /// Completes the pending request for `key` to `peer`, recording and returning its latency.
+    fn complete(&mut self, peer: &NodeId, key: &K, now: Timestamp) -> Option<TimeDiff> {
+        let pending = self.pending.remove(&(peer.clone(), *key))?;
+        self.by_id.remove(&pending.id);
+        let latency = now.saturating_sub(pending.sent);
+        self.metrics
+            .latency
+            .observe(latency.millis() as f64 / 1000.0);
+        self.metrics.pending.set(self.pending.len() as i64);
+        Some(latency)
+    }
+}
+
+/// Metrics for the requests of a component to peers.
+#[derive(Debug)]
+struct PeerRequestsMetrics {
+    /// Time until a response was received, in seconds.
+    latency: Histogram,
+    /// Total number of requests which timed out.
+    timed_out: IntCounter,
+    /// Number of requests awaiting a response.
+    pending: IntGauge,
+    /// Number of requests waiting to be sent.
+    deferred: IntGauge,
+    /// Reference to the registry for unregistering.
+    registry: Registry,
+}
+
+impl PeerRequestsMetrics {
+    fn new(name: &str, registry: &Registry) -> Result<Self, prometheus::Error> {
+        let latency = Histogram::with_opts(
+            HistogramOpts::new(
+                format!("{}_request_latency_seconds", name),
+                format!(
+                    "time until a response to a request of the {} was received, in seconds",
+                    name
+                ),
+            )
+            // Create buckets from ten milliseconds to about 40 seconds.
+            .buckets(prometheus::exponential_buckets(0.01, 2.0, 13)?),
+        )?;
+        let timed_out = IntCounter::new(
+            format!("{}_requests_timed_out", name),
+            format!("number of requests of the {} which timed out", name),
+        )?;
+        let pending = IntGauge::new(
+            format!("{}_requests_pending", name),
+            format!("number of requests of the {} awaiting a response", name),
+        )?;
+        let deferred = IntGauge::new(
+            format!("{}_requests_deferred", name),
+            format!("number of requests of the {} waiting to be sent", name),
+        )?;
+
+        registry.register(Box::new(latency.clone()))?;
+        registry.register(Box::new(timed_out.clone()))?;
+        registry.register(Box::new(pending.clone()))?;
+        registry.register(Box::new(deferred.clone()))?;
+
+        Ok(PeerRequestsMetrics {
+            latency,
+            timed_out,
+            pending,
+            deferred,
+            registry: registry.clone(),
+        })
+    }
+}
+
+impl Drop for PeerRequestsMetrics {
+    fn drop(&mut self) {
+        self.registry
+            .unregister(Box::new(self.latency.clone()))
+            .expect("did not expect deregistering latency to fail");
+        self.registry
+            .unregister(Box::new(self.timed_out.clone()))
+            .expect("did not expect deregistering timed_out to fail");
+        self.registry
+            .unregister(Box::new(self.pending.clone()))
+            .expect("did not expect deregistering pending to fail");
+        self.registry
+            .unregister(Box::new(self.deferred.clone()))
+            .expect("did not expect deregistering deferred to fail");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestRng;
+
+    #[test]
+    fn should_correlate_responses_and_timeouts() {
+        let mut rng = TestRng::new();
+        let registry = Registry::new();
+        let mut requests =
+            PeerRequests::<u64>::new("test", Duration::from_secs(1), 10, &registry).unwrap();
+        let peer = NodeId::random(&mut rng);
+        let other_peer = NodeId::random(&mut rng);
+        let now = Timestamp::now();
+
+        let answered = requests.start(peer.clone(), 1, now).unwrap();
+        assert!(requests.start(peer.clone(), 1, now).is_none());
+        let unanswered = requests.start(other_peer.clone(), 1, now).unwrap();
+        assert_ne!(answered, unanswered);
+
+        assert_eq!(requests.queue_depth(&peer), 1);
+        assert_eq!(
+            requests.complete(&peer, &1, now + Duration::from_millis(200).into()),
+            Some(Duration::from_millis(200).into())
+        );
+        assert!(requests
+            .complete(&peer, &1, now + Duration::from_millis(300).into())
+            .is_none());
+        assert_eq!(requests.queue_depth(&peer), 0);
+        assert_eq!(requests.metrics.latency.get_sample_count(), 1);
+
+        // The timeout of an answered request is ignored.
+        assert!(requests.timed_out(answered).is_none());
+        assert_eq!(requests.timed_out(unanswered), Some((other_peer, 1)));
+        assert!(requests.timed_out(unanswered).is_none());
+        assert_eq!(requests.metrics.timed_out.get(), 1);
+        assert_eq!(requests.metrics.pending.get(), 0);
+
+        // A request can be repeated once the previous one is done.
+        let cancelled = requests.start(peer, 1, now).unwrap();
+        requests.cancel_all(&1);
+        assert!(requests.timed_out(cancelled).is_none());
+    }
+}
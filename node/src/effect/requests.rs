@@ -5,8 +5,9 @@
 
 use std::{
     borrow::Cow,
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fmt::{self, Debug, Display, Formatter},
+    ops::Range,
     sync::Arc,
 };
 
@@ -38,17 +39,22 @@ use hex_fmt::HexFmt;
 use super::{Multiple, Responder};
 use crate::{
     components::{
+        block_executor::BlockSigningCapability,
         chainspec_loader::ChainspecInfo,
         consensus::EraId,
         contract_runtime::{EraValidatorsRequest, ValidatorWeightsByEraIdRequest},
         deploy_acceptor::Error,
+        era_metrics::EraMetricsSnapshot,
         fetcher::FetchResult,
+        storage::{BlockHeaderBatch, TransientWriteError},
     },
     crypto::hash::Digest,
     rpcs::chain::BlockIdentifier,
     types::{
         Block as LinearBlock, Block, BlockHash, BlockHeader, Deploy, DeployHash, DeployHeader,
-        DeployMetadata, FinalitySignature, FinalizedBlock, Item, ProtoBlock, StatusFeed, Timestamp,
+        DeployMetadata, FinalitySignature, FinalizedBlock, ImportedBan, Item, NetworkTimeEstimate,
+        PeerRequestAudit, PeerSample, ProtoBlock, ProtocolVersionHistogram, SignedAttestation,
+        StatusFeed, Timestamp,
     },
     utils::DisplayIter,
     Chainspec,
@@ -91,6 +97,19 @@ pub enum NetworkRequest<I, P> {
         #[serde(skip_serializing)]
         responder: Responder<()>,
     },
+    /// Send a message on the network to a specific peer, reporting whether it was delivered.
+    ///
+    /// Unlike `SendMessage`, the responder is only called once the message has been written to the
+    /// connection to the peer, or is known not to be.
+    SendMessageConfirmed {
+        /// Message destination.
+        dest: I,
+        /// Message payload.
+        payload: P,
+        /// Responder to be called with the outcome of sending the message.
+        #[serde(skip_serializing)]
+        responder: Responder<DeliveryStatus>,
+    },
     /// Send a message on the network to all peers.
     /// Note: This request is deprecated and should be phased out, as not every network
     ///       implementation is likely to implement broadcast support.
@@ -101,6 +120,21 @@ pub enum NetworkRequest<I, P> {
         #[serde(skip_serializing)]
         responder: Responder<()>,
     },
+    /// Send a message on the network to the peers which validate with one of `validators`.
+    ///
+    /// Peers which have not announced whether they validate with a key, e.g. as they run an older
+    /// version, are included, as are all peers if the network implementation does not track which
+    /// peers are validators.
+    BroadcastToValidators {
+        /// Message payload.
+        payload: P,
+        /// The public keys of the validators to send the message to.
+        #[serde(skip_serializing)]
+        validators: HashSet<PublicKey>,
+        /// Responder to be called when all messages are queued.
+        #[serde(skip_serializing)]
+        responder: Responder<()>,
+    },
     /// Gossip a message to a random subset of peers.
     Gossip {
         /// Payload to gossip.
@@ -134,10 +168,28 @@ impl<I, P> NetworkRequest<I, P> {
                 payload: wrap_payload(payload),
                 responder,
             },
+            NetworkRequest::SendMessageConfirmed {
+                dest,
+                payload,
+                responder,
+            } => NetworkRequest::SendMessageConfirmed {
+                dest,
+                payload: wrap_payload(payload),
+                responder,
+            },
             NetworkRequest::Broadcast { payload, responder } => NetworkRequest::Broadcast {
                 payload: wrap_payload(payload),
                 responder,
             },
+            NetworkRequest::BroadcastToValidators {
+                payload,
+                validators,
+                responder,
+            } => NetworkRequest::BroadcastToValidators {
+                payload: wrap_payload(payload),
+                validators,
+                responder,
+            },
             NetworkRequest::Gossip {
                 payload,
                 count,
@@ -163,14 +215,49 @@ where
             NetworkRequest::SendMessage { dest, payload, .. } => {
                 write!(formatter, "send to {}: {}", dest, payload)
             }
+            NetworkRequest::SendMessageConfirmed { dest, payload, .. } => {
+                write!(formatter, "send confirmed to {}: {}", dest, payload)
+            }
             NetworkRequest::Broadcast { payload, .. } => {
                 write!(formatter, "broadcast: {}", payload)
             }
+            NetworkRequest::BroadcastToValidators {
+                payload,
+                validators,
+                ..
+            } => write!(
+                formatter,
+                "broadcast to {} validators: {}",
+                validators.len(),
+                payload
+            ),
             NetworkRequest::Gossip { payload, .. } => write!(formatter, "gossip: {}", payload),
         }
     }
 }
 
+/// The outcome of sending a message to a specific peer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub enum DeliveryStatus {
+    /// The message has been written to the connection, so the peer has likely received it.
+    Written,
+    /// Writing the message to the connection failed, the connection is broken.
+    Failed,
+    /// The message was dropped without being written, e.g. because there was no connection to the
+    /// peer or it was closed first.
+    Dropped,
+}
+
+impl Display for DeliveryStatus {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryStatus::Written => write!(formatter, "written"),
+            DeliveryStatus::Failed => write!(formatter, "failed"),
+            DeliveryStatus::Dropped => write!(formatter, "dropped"),
+        }
+    }
+}
+
 /// A networking info request.
 #[derive(Debug)]
 #[must_use]
@@ -181,6 +268,40 @@ pub enum NetworkInfoRequest<I> {
         // TODO - change the `String` field to a `libp2p::Multiaddr` once small_network is removed.
         responder: Responder<BTreeMap<I, String>>,
     },
+    /// Get the protocol versions advertised by connected peers.
+    GetPeerProtocolVersions {
+        /// Responder to be called with the number of peers per protocol version.
+        responder: Responder<ProtocolVersionHistogram>,
+    },
+    /// Get the bans imported from signed ban lists which are currently in effect.
+    GetImportedBans {
+        /// Responder to be called with the imported bans.
+        responder: Responder<Vec<ImportedBan>>,
+    },
+    /// Get the latest software attestation of every given validator known, including ours.
+    GetAttestations {
+        /// The validators to report attestations of.
+        validators: BTreeSet<PublicKey>,
+        /// Responder to be called with the attestations.
+        responder: Responder<Vec<SignedAttestation>>,
+    },
+    /// Get the data requests sent by peers.
+    GetRequestAudit {
+        /// Responder to be called with the requests per peer.
+        responder: Responder<Vec<PeerRequestAudit>>,
+    },
+    /// Get the estimated offset of our clock from the clocks of connected peers.
+    GetNetworkTime {
+        /// Responder to be called with the estimate, or `None` if no peer has been sampled yet.
+        responder: Responder<Option<NetworkTimeEstimate>>,
+    },
+    /// Get a uniformly random sample of the connected peers.
+    SamplePeers {
+        /// Maximum number of peers to sample.
+        count: usize,
+        /// Responder to be called with the sample.
+        responder: Responder<PeerSample>,
+    },
 }
 
 impl<I> Display for NetworkInfoRequest<I>
@@ -190,6 +311,25 @@ where
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
         match self {
             NetworkInfoRequest::GetPeers { responder: _ } => write!(formatter, "get peers"),
+            NetworkInfoRequest::GetPeerProtocolVersions { responder: _ } => {
+                write!(formatter, "get peer protocol versions")
+            }
+            NetworkInfoRequest::GetImportedBans { responder: _ } => {
+                write!(formatter, "get imported bans")
+            }
+            NetworkInfoRequest::GetAttestations { .. } => {
+                write!(formatter, "get attestations")
+            }
+            NetworkInfoRequest::GetRequestAudit { responder: _ } => {
+                write!(formatter, "get request audit")
+            }
+            NetworkInfoRequest::GetNetworkTime { responder: _ } => {
+                write!(formatter, "get network time")
+            }
+            NetworkInfoRequest::SamplePeers {
+                count,
+                responder: _,
+            } => write!(formatter, "sample {} peers", count),
         }
     }
 }
@@ -203,8 +343,9 @@ pub enum StorageRequest {
         /// Block to be stored.
         block: Box<Block>,
         /// Responder to call with the result.  Returns true if the block was stored on this
-        /// attempt or false if it was previously stored.
-        responder: Responder<bool>,
+        /// attempt or false if it was previously stored, or an error if the write failed
+        /// transiently and should be retried.
+        responder: Responder<Result<bool, TransientWriteError>>,
     },
     /// Retrieve block with given hash.
     GetBlock {
@@ -246,6 +387,18 @@ pub enum StorageRequest {
         /// local storage.
         responder: Responder<Option<BlockHeader>>,
     },
+    /// Retrieve the headers of the blocks stored at a range of heights, in a bounded batch.
+    ///
+    /// Scans over many heights are to be split into several requests, each resuming at the
+    /// `next_height` of the previous batch.
+    GetBlockHeadersByHeight {
+        /// The heights to scan, the end being exclusive.
+        heights: Range<BlockHeight>,
+        /// Maximum number of headers to return, capped at `MAX_BLOCK_HEADER_BATCH`.
+        max_count: usize,
+        /// Responder to call with the batch.
+        responder: Responder<BlockHeaderBatch>,
+    },
     /// Retrieve all transfers in a block with given hash.
     GetBlockTransfers {
         /// Hash of block to get transfers of.
@@ -259,8 +412,9 @@ pub enum StorageRequest {
         /// Deploy to store.
         deploy: Box<Deploy>,
         /// Responder to call with the result.  Returns true if the deploy was stored on this
-        /// attempt or false if it was previously stored.
-        responder: Responder<bool>,
+        /// attempt or false if it was previously stored, or an error if the write failed
+        /// transiently and should be retried.
+        responder: Responder<Result<bool, TransientWriteError>>,
     },
     /// Retrieve deploys with given hashes.
     GetDeploys {
@@ -312,6 +466,35 @@ pub enum StorageRequest {
         /// Responder to call with the result.
         responder: Responder<Option<Arc<Chainspec>>>,
     },
+    /// Store a blob in the content-addressable blob store, adding a reference to it.
+    PutBlob {
+        /// Contents of the blob.
+        #[serde(skip_serializing)]
+        data: Vec<u8>,
+        /// Responder to call with the hash the blob is stored under.
+        responder: Responder<Digest>,
+    },
+    /// Retrieve blob with given hash.
+    GetBlob {
+        /// Hash of the blob.
+        blob_hash: Digest,
+        /// Responder to call with the result.  Returns `None` if the blob doesn't exist in local
+        /// storage.
+        responder: Responder<Option<Vec<u8>>>,
+    },
+    /// Release a reference to the blob with the given hash.
+    ReleaseBlob {
+        /// Hash of the blob.
+        blob_hash: Digest,
+        /// Responder to call with the number of remaining references.  Returns `None` if the blob
+        /// was not referenced.
+        responder: Responder<Option<u64>>,
+    },
+    /// Delete all blobs that are no longer referenced.
+    CollectBlobGarbage {
+        /// Responder to call with the number of blobs deleted.
+        responder: Responder<usize>,
+    },
 }
 
 impl Display for StorageRequest {
@@ -332,6 +515,11 @@ impl Display for StorageRequest {
             StorageRequest::GetBlockHeader { block_hash, .. } => {
                 write!(formatter, "get {}", block_hash)
             }
+            StorageRequest::GetBlockHeadersByHeight { heights, .. } => write!(
+                formatter,
+                "get block headers at heights {}..{}",
+                heights.start, heights.end
+            ),
             StorageRequest::GetBlockTransfers { block_hash, .. } => {
                 write!(formatter, "get transfers for {}", block_hash)
             }
@@ -358,6 +546,18 @@ impl Display for StorageRequest {
             StorageRequest::GetChainspec { version, .. } => {
                 write!(formatter, "get chainspec {}", version)
             }
+            StorageRequest::PutBlob { data, .. } => {
+                write!(formatter, "put blob of {} bytes", data.len())
+            }
+            StorageRequest::GetBlob { blob_hash, .. } => {
+                write!(formatter, "get blob {}", blob_hash)
+            }
+            StorageRequest::ReleaseBlob { blob_hash, .. } => {
+                write!(formatter, "release blob {}", blob_hash)
+            }
+            StorageRequest::CollectBlobGarbage { .. } => {
+                write!(formatter, "collect blob garbage")
+            }
         }
     }
 }
@@ -515,11 +715,54 @@ pub enum RpcRequest<I> {
         /// Responder to call with the result.
         responder: Responder<Option<(Deploy, DeployMetadata)>>,
     },
+    /// Execute a deploy on top of the global state of a block, without committing its effects.
+    SpeculativeExec {
+        /// The block whose global state the deploy is executed on top of.
+        block: Box<LinearBlock>,
+        /// The deploy to execute.
+        deploy: Box<Deploy>,
+        /// Responder to call with the execution result.
+        responder: Responder<Result<ExecutionResult, engine_state::RootNotFound>>,
+    },
     /// Return the connected peers.
     GetPeers {
         /// Responder to call with the result.
         responder: Responder<BTreeMap<I, String>>,
     },
+    /// Return our latest protocol version and the protocol versions advertised by connected peers.
+    GetPeerProtocolVersions {
+        /// Responder to call with the result.
+        responder: Responder<(Version, ProtocolVersionHistogram)>,
+    },
+    /// Return the bans imported from signed ban lists which are currently in effect.
+    GetImportedBans {
+        /// Responder to call with the result.
+        responder: Responder<Vec<ImportedBan>>,
+    },
+    /// Return the latest software attestation of every validator known.
+    GetValidatorAttestations {
+        /// Responder to call with the result.
+        responder: Responder<Vec<SignedAttestation>>,
+    },
+    /// Return the data requests sent by peers.
+    GetPeerRequestAudit {
+        /// Responder to call with the result.
+        responder: Responder<Vec<PeerRequestAudit>>,
+    },
+    /// Return a uniformly random sample of the connected peers.
+    SamplePeers {
+        /// Maximum number of peers to sample.
+        count: usize,
+        /// Responder to call with the result.
+        responder: Responder<PeerSample>,
+    },
+    /// Return the persisted metrics snapshot of the given era, if any.
+    GetEraMetrics {
+        /// The era.
+        era_id: EraId,
+        /// Responder to call with the result.
+        responder: Responder<Option<EraMetricsSnapshot>>,
+    },
     /// Return string formatted status or `None` if an error occurred.
     GetStatus {
         /// Responder to call with the result.
@@ -574,7 +817,25 @@ impl<I> Display for RpcRequest<I> {
                 state_root_hash, purse_uref
             ),
             RpcRequest::GetDeploy { hash, .. } => write!(formatter, "get {}", hash),
+            RpcRequest::SpeculativeExec { block, deploy, .. } => write!(
+                formatter,
+                "speculatively execute {} on top of {}",
+                deploy.id(),
+                block.hash()
+            ),
             RpcRequest::GetPeers { .. } => write!(formatter, "get peers"),
+            RpcRequest::GetPeerProtocolVersions { .. } => {
+                write!(formatter, "get peer protocol versions")
+            }
+            RpcRequest::GetImportedBans { .. } => write!(formatter, "get imported bans"),
+            RpcRequest::GetValidatorAttestations { .. } => {
+                write!(formatter, "get validator attestations")
+            }
+            RpcRequest::GetPeerRequestAudit { .. } => write!(formatter, "get peer request audit"),
+            RpcRequest::SamplePeers { count, .. } => write!(formatter, "sample {} peers", count),
+            RpcRequest::GetEraMetrics { era_id, .. } => {
+                write!(formatter, "get metrics of {}", era_id)
+            }
             RpcRequest::GetStatus { .. } => write!(formatter, "get status"),
             RpcRequest::GetMetrics { .. } => write!(formatter, "get metrics"),
         }
@@ -819,12 +1080,28 @@ pub enum FetcherRequest<I, T: Item> {
         /// Responder to call with the result.
         responder: Responder<Option<FetchResult<T, I>>>,
     },
+    /// Return the specified item if it exists, else `None`, asking the best of `peers` if it is
+    /// not held locally, and the others in turn if that fails.
+    FetchFromAny {
+        /// The ID of the item to be retrieved.
+        id: T::Id,
+        /// The peers which may have the item.
+        peers: Vec<I>,
+        /// Responder to call with the result.
+        responder: Responder<Option<FetchResult<T, I>>>,
+    },
 }
 
 impl<I, T: Item> Display for FetcherRequest<I, T> {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
         match self {
             FetcherRequest::Fetch { id, .. } => write!(formatter, "request item by id {}", id),
+            FetcherRequest::FetchFromAny { id, peers, .. } => write!(
+                formatter,
+                "request item by id {} from any of {} peers",
+                id,
+                peers.len()
+            ),
         }
     }
 }
@@ -835,6 +1112,16 @@ impl<I, T: Item> Display for FetcherRequest<I, T> {
 pub enum BlockExecutorRequest {
     /// A request to execute finalized block.
     ExecuteBlock(FinalizedBlock),
+    /// A request to execute a block received from a peer, whose post-state hash is known.
+    ///
+    /// If executing it results in a different state root hash, a forensic report is written
+    /// before the block is announced.
+    ExecuteReceivedBlock {
+        /// The block to execute.
+        finalized_block: FinalizedBlock,
+        /// The post-state hash of the received block.
+        expected_state_root_hash: Digest,
+    },
 }
 
 impl Display for BlockExecutorRequest {
@@ -843,6 +1130,14 @@ impl Display for BlockExecutorRequest {
             BlockExecutorRequest::ExecuteBlock(finalized_block) => {
                 write!(f, "execute block {}", finalized_block)
             }
+            BlockExecutorRequest::ExecuteReceivedBlock {
+                finalized_block,
+                expected_state_root_hash,
+            } => write!(
+                f,
+                "execute received block {} expecting state root hash {}",
+                finalized_block, expected_state_root_hash
+            ),
         }
     }
 }
@@ -883,6 +1178,16 @@ pub enum LinearChainRequest<I> {
     /// Local request for a linear chain block at height.
     /// TODO: Unify `BlockAtHeight` and `BlockAtHeightLocal`.
     BlockAtHeightLocal(BlockHeight, Responder<Option<Block>>),
+    /// Request to store a block executed by the block executor and have consensus sign it.
+    ExecutedBlock {
+        /// The block.
+        block: Box<Block>,
+        /// The deploys' execution results.
+        execution_results: HashMap<DeployHash, ExecutionResult>,
+        /// Capability to have consensus sign the block once it is stored.
+        #[serde(skip_serializing)]
+        signing_capability: BlockSigningCapability,
+    },
 }
 
 impl<I: Display> Display for LinearChainRequest<I> {
@@ -897,6 +1202,9 @@ impl<I: Display> Display for LinearChainRequest<I> {
             LinearChainRequest::BlockAtHeightLocal(height, _) => {
                 write!(f, "local request for block at height {}", height)
             }
+            LinearChainRequest::ExecutedBlock { block, .. } => {
+                write!(f, "executed block {}", block.hash())
+            }
         }
     }
 }
@@ -909,6 +1217,8 @@ pub enum ConsensusRequest {
     HandleLinearBlock(Box<BlockHeader>, Responder<Option<FinalitySignature>>),
     /// Check whether validator identifying with the public key is bonded.
     IsBondedValidator(EraId, PublicKey, Responder<bool>),
+    /// Get the validators of the current era.
+    CurrentValidators(Responder<BTreeSet<PublicKey>>),
 }
 
 /// ChainspecLoader componenent requests.
@@ -0,0 +1,173 @@
+//! Typed subscriptions.
+//!
+//! Announcements need an arm in every reactor for every consumer. For values with many or
+//! optional consumers, a component can instead publish them, see `EffectBuilder::publish`, and any
+//! component interested in them subscribes to their type, see `EffectBuilder::subscribe`, without
+//! the reactor being involved.
+//!
+//! Subscriptions are per reactor: values published by one reactor are never received by
+//! subscribers of another, e.g. after transitioning from the joiner to the validator reactor. They
+//! are closed once the runner of their reactor is dropped, ending all subscriptions.
+//!
+//! A published value reaches every subscriber as an event scheduled on the regular queue once the
+//! effect returned by `EffectBuilder::publish` runs, just like an announcement. It is thus ordered
+//! relative to other events the same way an announcement would be, which e.g. the block proposer
+//! relies on to handle a finalized block before it is asked for the next proto block.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Mutex, MutexGuard},
+};
+
+/// Turns a published value of type `T` into a reactor event for one subscriber.
+type Subscriber<T, REv> = Box<dyn Fn(T) -> REv + Send>;
+
+/// The subscribers of all types, each a `Subscriber<T, REv>` for the type `T` it is keyed by.
+type Subscribers = HashMap<TypeId, Vec<Box<dyn Any + Send>>>;
+
+/// The subscribers of all types, shared between a runner and its event queue handles.
+#[derive(Debug)]
+pub(crate) struct Subscriptions<REv> {
+    /// The subscribers, `None` once closed.
+    subscribers: Mutex<Option<Subscribers>>,
+    _reactor_event: PhantomData<fn() -> REv>,
+}
+
+impl<REv> Default for Subscriptions<REv> {
+    fn default() -> Self {
+        Subscriptions {
+            subscribers: Mutex::new(Some(HashMap::new())),
+            _reactor_event: PhantomData,
+        }
+    }
+}
+
+impl<REv: 'static> Subscriptions<REv> {
+    /// Subscribes to all values of type `T` published from now on, turning each into an event by
+    /// `map`.
+    ///
+    /// Nothing is subscribed if the subscriptions are closed already.
+    pub(crate) fn subscribe<T, F>(&self, map: F)
+    where
+        T: 'static,
+        F: Fn(T) -> REv + Send + 'static,
+    {
+        if let Some(subscribers) = self.lock().as_mut() {
+            let subscriber: Subscriber<T, REv> = Box::new(map);
+            subscribers
+                .entry(TypeId::of::<T>())
+                .or_default()
+                .push(Box::new(subscriber));
+        }
+    }
+
+    /// Returns the events of all current subscribers of the type of `value`, in the order they
+    /// subscribed.
+    ///
+    /// Nothing is returned once closed.
+    pub(crate) fn publish<T: Clone + 'static>(&self, value: T) -> Vec<REv> {
+        let subscribers = self.lock();
+        let subscribers = match subscribers
+            .as_ref()
+            .and_then(|subscribers| subscribers.get(&TypeId::of::<T>()))
+        {
+            Some(subscribers) => subscribers,
+            None => return Vec::new(),
+        };
+        subscribers
+            .iter()
+            .map(|subscriber| {
+                let map = subscriber
+                    .downcast_ref::<Subscriber<T, REv>>()
+                    .expect("subscriptions should be keyed by their type");
+                map(value.clone())
+            })
+            .collect()
+    }
+}
+
+impl<REv> Subscriptions<REv> {
+    /// Closes the subscriptions, dropping all subscribers.
+    pub(crate) fn close(&self) {
+        self.lock().take();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Subscribers>> {
+        self.subscribers
+            .lock()
+            .expect("subscriptions lock poisoned")
+    }
+}
+
+/// Closes the subscriptions of a runner once dropped.
+///
+/// Subscriptions are leaked to be shared with the event queue handles, so they are never dropped
+/// themselves.
+#[derive(Debug)]
+pub(crate) struct SubscriptionsGuard<REv: 'static>(&'static Subscriptions<REv>);
+
+impl<REv> SubscriptionsGuard<REv> {
+    /// Creates a guard closing `subscriptions` once dropped.
+    pub(crate) fn new(subscriptions: &'static Subscriptions<REv>) -> Self {
+        SubscriptionsGuard(subscriptions)
+    }
+
+    /// Returns the guarded subscriptions.
+    pub(crate) fn get(&self) -> &'static Subscriptions<REv> {
+        self.0
+    }
+}
+
+impl<REv> Drop for SubscriptionsGuard<REv> {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Number(u32),
+        Doubled(u32),
+        Text(String),
+    }
+
+    #[test]
+    fn should_deliver_to_subscribers_of_type_in_order() {
+        let subscriptions = Subscriptions::<Event>::default();
+        assert!(subscriptions.publish(1u32).is_empty());
+
+        subscriptions.subscribe(Event::Number);
+        subscriptions.subscribe(|value: u32| Event::Doubled(2 * value));
+        subscriptions.subscribe(Event::Text);
+
+        assert_eq!(
+            subscriptions.publish(7u32),
+            vec![Event::Number(7), Event::Doubled(14)]
+        );
+        assert_eq!(
+            subscriptions.publish("value".to_string()),
+            vec![Event::Text("value".to_string())]
+        );
+    }
+
+    #[test]
+    fn should_end_subscriptions_once_closed() {
+        let subscriptions = utils::leak(Subscriptions::<Event>::default());
+        subscriptions.subscribe(Event::Number);
+        assert_eq!(subscriptions.publish(7u32), vec![Event::Number(7)]);
+
+        drop(SubscriptionsGuard::new(subscriptions));
+
+        // Nothing is published once closed, and later subscriptions are ignored.
+        assert!(subscriptions.publish(8u32).is_empty());
+        subscriptions.subscribe(Event::Number);
+        assert!(subscriptions.publish(9u32).is_empty());
+    }
+}
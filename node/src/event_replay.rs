@@ -0,0 +1,149 @@
+//! Replay of queue dumps in a sandbox.
+//!
+//! Besides its JSON and debug representations, every queue dump contains a replayable part with
+//! the queued events carrying data received from outside the node, like messages from peers and
+//! deploys submitted by clients. To reproduce a crash caused by a specific sequence of these
+//! offline, the events are replayed into a freshly constructed validator reactor, set up from the
+//! node's configuration, but isolated from the node and its network:
+//!
+//! * the storage, unit hashes and spillover files live in a temporary directory, so the reactor
+//!   starts from genesis,
+//! * the node only listens on and connects to a free port on localhost, and uses no trusted hash,
+//!   so joining completes without syncing from peers,
+//! * the API servers listen on free ports on localhost, the binary port and the webhooks of the
+//!   notifier are disabled.
+//!
+//! The events are scheduled on the queues they were dumped from, in their original order within
+//! each queue. Responses to the peers which sent them are not delivered, as none are connected.
+//!
+//! Like a queue snapshot, a dump can only be replayed by the node version that wrote it.
+
+use std::{
+    fs, io,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    path::Path,
+};
+
+use prometheus::Registry;
+use thiserror::Error;
+use tracing::info;
+
+use crate::{
+    reactor::{initializer, joiner, validator, QueuePersistenceError, Runner},
+    utils::WithDir,
+};
+
+/// Error replaying a queue dump.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The sandbox could not be set up.
+    #[error("could not set up sandbox: {0}")]
+    Sandbox(io::Error),
+
+    /// Error constructing the initializer reactor.
+    #[error("initializer error: {0}")]
+    Initializer(#[from] initializer::Error),
+
+    /// Error constructing the joiner or validator reactor.
+    #[error("validator error: {0}")]
+    Validator(#[from] validator::Error),
+
+    /// The initializer did not complete successfully.
+    #[error("failed to initialize sandbox node")]
+    Initialization,
+
+    /// The dump could not be read.
+    #[error("could not read queue dump: {0}")]
+    Dump(#[from] QueuePersistenceError),
+}
+
+/// The outcome of replaying a queue dump.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReplaySummary {
+    /// Number of events scheduled from the dump.
+    pub replayed: usize,
+    /// Number of events processed by the reactor, including those caused by the replayed ones.
+    pub processed: u64,
+}
+
+/// Replays the replayable queue dump at `dump` into a sandboxed validator reactor set up from
+/// `config`, processing up to `max_events` events.
+pub async fn replay_queue_dump(
+    config: WithDir<validator::Config>,
+    dump: &Path,
+    max_events: u64,
+) -> Result<ReplaySummary, Error> {
+    let (root, mut config) = config.into_parts();
+    let sandbox_dir = tempfile::tempdir().map_err(Error::Sandbox)?;
+    sandbox(&mut config, sandbox_dir.path()).map_err(Error::Sandbox)?;
+    info!(sandbox = %sandbox_dir.path().display(), "starting sandboxed node");
+
+    let mut rng = crate::new_rng();
+    let registry = Registry::new();
+
+    let mut initializer_runner = Runner::<initializer::Reactor>::with_metrics(
+        WithDir::new(root.clone(), config),
+        &mut rng,
+        &registry,
+    )
+    .await?;
+    initializer_runner.run(&mut rng).await;
+    let initializer = initializer_runner.into_inner();
+    if !initializer.stopped_successfully() {
+        return Err(Error::Initialization);
+    }
+
+    let mut joiner_runner = Runner::<joiner::Reactor>::with_metrics(
+        WithDir::new(root, initializer),
+        &mut rng,
+        &registry,
+    )
+    .await?;
+    joiner_runner.run(&mut rng).await;
+    let config = joiner_runner.into_inner().into_validator_config().await;
+
+    let mut validator_runner =
+        Runner::<validator::Reactor>::with_metrics(config, &mut rng, &registry).await?;
+    let replayed = validator_runner.replay_dump(dump).await?;
+    let processed = validator_runner.run_for(&mut rng, max_events).await;
+    info!(replayed, processed, "finished replaying queue dump");
+
+    Ok(ReplaySummary {
+        replayed,
+        processed,
+    })
+}
+
+/// Isolates `config` from the node it was written for, placing all files in `dir`.
+fn sandbox(config: &mut validator::Config, dir: &Path) -> io::Result<()> {
+    let storage_path = dir.join("storage");
+    let unit_hashes_folder = dir.join("unit_hashes");
+    let spillover_path = dir.join("spillover");
+    fs::create_dir_all(&unit_hashes_folder)?;
+    fs::create_dir_all(&spillover_path)?;
+
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, unused_port()?)).to_string();
+    let any_port = SocketAddr::from((Ipv4Addr::LOCALHOST, 0)).to_string();
+
+    config.node.trusted_hash = None;
+    config.node.queue_snapshot_path = None;
+    config.network.bind_address = address.clone();
+    config.network.public_address = address.clone();
+    config.network.known_addresses = vec![address];
+    config.network.systemd_support = false;
+    config.rpc_server.address = any_port.clone();
+    config.rest_server.address = any_port.clone();
+    config.event_stream_server.address = any_port;
+    config.binary_port.enable = false;
+    config.notifier.webhook_urls.clear();
+    config.storage.path = storage_path;
+    config.consensus.unit_hashes_folder = unit_hashes_folder;
+    config.event_queue_spillover.path = spillover_path.display().to_string();
+    Ok(())
+}
+
+/// Returns a port on localhost no other socket is bound to.
+fn unused_port() -> io::Result<u16> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    Ok(listener.local_addr()?.port())
+}
@@ -0,0 +1,388 @@
+//! Generation, inspection and conversion of validator keys and network TLS identities.
+//!
+//! Validator keys are written in the same layout the client's `keygen` produces and the consensus
+//! component expects for its `secret_key_path`: a PEM-encoded secret key, plus the public key both
+//! PEM- and hex-encoded. Network identities are self-signed certificates generated exactly as the
+//! node does at startup, and are validated before being written.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use openssl::x509::X509;
+use thiserror::Error;
+
+use casper_types::{AsymmetricType, PublicKey, SecretKey};
+
+use crate::{
+    crypto::{self, AsymmetricKeyExt},
+    tls::{self, TlsCert},
+    types::NodeId,
+    utils::{self, ReadFileError, WriteFileError},
+};
+
+/// Filename of the PEM-encoded validator secret key.
+pub const SECRET_KEY_PEM: &str = "secret_key.pem";
+/// Filename of the PEM-encoded validator public key.
+pub const PUBLIC_KEY_PEM: &str = "public_key.pem";
+/// Filename of the hex-encoded validator public key.
+pub const PUBLIC_KEY_HEX: &str = "public_key_hex";
+/// Filename of the PEM-encoded network TLS certificate.
+pub const NETWORK_CERT_PEM: &str = "node_cert.pem";
+/// Filename of the PEM-encoded network TLS private key.
+pub const NETWORK_KEY_PEM: &str = "node_key.pem";
+
+const PEM_CERTIFICATE_TAG: &str = "CERTIFICATE";
+const PEM_PUBLIC_KEY_TAG: &str = "PUBLIC KEY";
+
+/// Error generating, reading or converting key material.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The output directory could not be created.
+    #[error("could not create directory '{}': {error}", .path.display())]
+    CreateDir {
+        /// The directory that could not be created.
+        path: PathBuf,
+        /// The underlying OS error.
+        #[source]
+        error: io::Error,
+    },
+    /// A file would be overwritten without `force` being set.
+    #[error("refusing to overwrite existing file '{}'", .0.display())]
+    FileExists(PathBuf),
+    /// The input file could not be read.
+    #[error(transparent)]
+    ReadFile(#[from] ReadFileError),
+    /// An output file could not be written.
+    #[error(transparent)]
+    WriteFile(#[from] WriteFileError),
+    /// A validator key could not be generated, encoded or decoded.
+    #[error(transparent)]
+    Crypto(#[from] crypto::Error),
+    /// A network TLS identity could not be generated, encoded or decoded.
+    #[error("TLS identity error: {0:#}")]
+    Tls(anyhow::Error),
+    /// The key material cannot be represented in the requested format.
+    #[error("{0} cannot be encoded as {1}")]
+    UnsupportedConversion(&'static str, KeyFormat),
+}
+
+/// The signature algorithm of a validator key.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum KeyAlgorithm {
+    /// Ed25519.
+    Ed25519,
+    /// secp256k1.
+    Secp256k1,
+}
+
+impl FromStr for KeyAlgorithm {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input.eq_ignore_ascii_case("ed25519") {
+            Ok(KeyAlgorithm::Ed25519)
+        } else if input.eq_ignore_ascii_case("secp256k1") {
+            Ok(KeyAlgorithm::Secp256k1)
+        } else {
+            Err(format!(
+                "unsupported algorithm '{}', expected 'ed25519' or 'secp256k1'",
+                input
+            ))
+        }
+    }
+}
+
+/// An encoding of key material.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum KeyFormat {
+    /// PEM, as used for key files.
+    Pem,
+    /// Hex with the algorithm tag prepended, as used in chainspecs, accounts and RPCs.
+    Hex,
+}
+
+impl Display for KeyFormat {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyFormat::Pem => write!(formatter, "PEM"),
+            KeyFormat::Hex => write!(formatter, "hex"),
+        }
+    }
+}
+
+impl FromStr for KeyFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input.eq_ignore_ascii_case("pem") {
+            Ok(KeyFormat::Pem)
+        } else if input.eq_ignore_ascii_case("hex") {
+            Ok(KeyFormat::Hex)
+        } else {
+            Err(format!(
+                "unsupported format '{}', expected 'pem' or 'hex'",
+                input
+            ))
+        }
+    }
+}
+
+/// A validator key or network identity read from a file.
+pub enum KeyMaterial {
+    /// A validator secret key.
+    SecretKey(SecretKey),
+    /// A validator public key.
+    PublicKey(PublicKey),
+    /// A network TLS certificate.
+    TlsCert(TlsCert),
+}
+
+impl KeyMaterial {
+    /// Decodes PEM- or hex-encoded key material.
+    ///
+    /// The kind of PEM-encoded material is derived from its tag. Hex-encoded secret and public
+    /// keys cannot be told apart, so `hex_is_secret` selects how hex input is decoded.
+    pub fn decode(input: &[u8], hex_is_secret: bool) -> Result<Self, Error> {
+        let input = trim_ascii_whitespace(input);
+        if input.starts_with(b"-----BEGIN") {
+            let pem = pem::parse(input).map_err(crypto::Error::from)?;
+            return match pem.tag.as_str() {
+                PEM_CERTIFICATE_TAG => {
+                    let x509 = X509::from_pem(input)
+                        .map_err(|error| Error::Tls(anyhow::Error::new(error)))?;
+                    let cert =
+                        tls::validate_cert(x509).map_err(|error| Error::Tls(error.into()))?;
+                    Ok(KeyMaterial::TlsCert(cert))
+                }
+                PEM_PUBLIC_KEY_TAG => Ok(KeyMaterial::PublicKey(PublicKey::from_pem(input)?)),
+                _ => Ok(KeyMaterial::SecretKey(SecretKey::from_pem(input)?)),
+            };
+        }
+        if hex_is_secret {
+            let secret_key = SecretKey::from_hex(input).map_err(crypto::Error::from)?;
+            Ok(KeyMaterial::SecretKey(secret_key))
+        } else {
+            let public_key = PublicKey::from_hex(input).map_err(crypto::Error::from)?;
+            Ok(KeyMaterial::PublicKey(public_key))
+        }
+    }
+
+    /// Reads PEM- or hex-encoded key material from `path`, see [`decode`](#method.decode).
+    pub fn from_file<P: AsRef<Path>>(path: P, hex_is_secret: bool) -> Result<Self, Error> {
+        let input = utils::read_file(path)?;
+        Self::decode(&input, hex_is_secret)
+    }
+
+    /// Encodes the key material in the given format.
+    pub fn encode(&self, format: KeyFormat) -> Result<String, Error> {
+        let encoded = match (self, format) {
+            (KeyMaterial::SecretKey(secret_key), KeyFormat::Pem) => secret_key.to_pem()?,
+            (KeyMaterial::SecretKey(secret_key), KeyFormat::Hex) => secret_key.to_hex(),
+            (KeyMaterial::PublicKey(public_key), KeyFormat::Pem) => public_key.to_pem()?,
+            (KeyMaterial::PublicKey(public_key), KeyFormat::Hex) => public_key.to_hex(),
+            (KeyMaterial::TlsCert(cert), KeyFormat::Pem) => {
+                let pem = cert
+                    .as_x509()
+                    .to_pem()
+                    .map_err(|error| Error::Tls(anyhow::Error::new(error)))?;
+                String::from_utf8_lossy(&pem).into_owned()
+            }
+            (KeyMaterial::TlsCert(_), KeyFormat::Hex) => {
+                return Err(Error::UnsupportedConversion("a TLS certificate", format))
+            }
+        };
+        Ok(encoded)
+    }
+}
+
+impl Display for KeyMaterial {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyMaterial::SecretKey(secret_key) => {
+                writeln!(formatter, "validator secret key")?;
+                write_public_key(formatter, &PublicKey::from(secret_key))
+            }
+            KeyMaterial::PublicKey(public_key) => {
+                writeln!(formatter, "validator public key")?;
+                write_public_key(formatter, public_key)
+            }
+            KeyMaterial::TlsCert(cert) => {
+                let fingerprint = cert.public_key_fingerprint();
+                writeln!(formatter, "network TLS certificate")?;
+                writeln!(formatter, "node id:     {}", NodeId::from(fingerprint))?;
+                writeln!(formatter, "fingerprint: {}", hex::encode(fingerprint))
+            }
+        }
+    }
+}
+
+fn write_public_key(formatter: &mut Formatter<'_>, public_key: &PublicKey) -> fmt::Result {
+    let algorithm = match public_key {
+        PublicKey::System => "system",
+        PublicKey::Ed25519(_) => "Ed25519",
+        PublicKey::Secp256k1(_) => "secp256k1",
+    };
+    writeln!(formatter, "algorithm:    {}", algorithm)?;
+    writeln!(formatter, "public key:   {}", public_key.to_hex())?;
+    writeln!(
+        formatter,
+        "account hash: {}",
+        public_key.to_account_hash().to_formatted_string()
+    )
+}
+
+fn trim_ascii_whitespace(input: &[u8]) -> &[u8] {
+    let start = input
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(input.len());
+    let end = input
+        .iter()
+        .rposition(|byte| !byte.is_ascii_whitespace())
+        .map_or(start, |index| index + 1);
+    &input[start..end]
+}
+
+/// Creates `output_dir` and returns the paths of `filenames` in it.
+///
+/// Unless `force` is set, fails if any of the files already exists, before anything is written.
+fn prepare_output(
+    output_dir: &Path,
+    filenames: &[&str],
+    force: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    fs::create_dir_all(output_dir).map_err(|error| Error::CreateDir {
+        path: output_dir.to_owned(),
+        error,
+    })?;
+    let paths: Vec<PathBuf> = filenames
+        .iter()
+        .map(|filename| output_dir.join(filename))
+        .collect();
+    if !force {
+        if let Some(path) = paths.iter().find(|path| path.exists()) {
+            return Err(Error::FileExists(path.clone()));
+        }
+    }
+    Ok(paths)
+}
+
+/// Generates a validator key pair and writes it to `output_dir`.
+///
+/// The secret key is written to `secret_key.pem`, the public key to `public_key.pem` and, with the
+/// algorithm tag prepended, to `public_key_hex`. Unless `force` is set, no files are written if any
+/// of them already exists.
+pub fn generate_validator_keys(
+    output_dir: &Path,
+    algorithm: KeyAlgorithm,
+    force: bool,
+) -> Result<PublicKey, Error> {
+    let paths = prepare_output(
+        output_dir,
+        &[SECRET_KEY_PEM, PUBLIC_KEY_PEM, PUBLIC_KEY_HEX],
+        force,
+    )?;
+    let secret_key = match algorithm {
+        KeyAlgorithm::Ed25519 => SecretKey::generate_ed25519()?,
+        KeyAlgorithm::Secp256k1 => SecretKey::generate_secp256k1()?,
+    };
+    let public_key = PublicKey::from(&secret_key);
+
+    secret_key.to_file(&paths[0])?;
+    public_key.to_file(&paths[1])?;
+    utils::write_file(&paths[2], public_key.to_hex())?;
+    Ok(public_key)
+}
+
+/// Generates a network TLS identity and writes it to `output_dir`.
+///
+/// The certificate is written to `node_cert.pem` and its private key to `node_key.pem`. Unless
+/// `force` is set, no files are written if either of them already exists.
+pub fn generate_network_identity(output_dir: &Path, force: bool) -> Result<NodeId, Error> {
+    let paths = prepare_output(output_dir, &[NETWORK_CERT_PEM, NETWORK_KEY_PEM], force)?;
+    let (cert, private_key) =
+        tls::generate_node_cert().map_err(|error| Error::Tls(anyhow::Error::new(error)))?;
+    // Peers reject certificates that fail validation, so never hand out one that would.
+    let node_id = NodeId::from(
+        tls::validate_cert(cert.clone())
+            .map_err(|error| Error::Tls(error.into()))?
+            .public_key_fingerprint(),
+    );
+
+    tls::save_cert(&cert, &paths[0]).map_err(Error::Tls)?;
+    tls::save_private_key(&private_key, &paths[1]).map_err(Error::Tls)?;
+    Ok(node_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_generate_loadable_validator_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        for algorithm in &[KeyAlgorithm::Ed25519, KeyAlgorithm::Secp256k1] {
+            let public_key = generate_validator_keys(dir.path(), *algorithm, true).unwrap();
+
+            // The consensus component loads the secret key from this file.
+            let secret_key = SecretKey::from_file(dir.path().join(SECRET_KEY_PEM)).unwrap();
+            assert_eq!(PublicKey::from(&secret_key), public_key);
+
+            let hex = KeyMaterial::from_file(dir.path().join(PUBLIC_KEY_HEX), false).unwrap();
+            let pem = KeyMaterial::from_file(dir.path().join(PUBLIC_KEY_PEM), false).unwrap();
+            assert_eq!(
+                hex.encode(KeyFormat::Hex).unwrap(),
+                pem.encode(KeyFormat::Hex).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn should_not_overwrite_without_force() {
+        let dir = tempfile::tempdir().unwrap();
+        generate_validator_keys(dir.path(), KeyAlgorithm::Ed25519, false).unwrap();
+        let original = fs::read(dir.path().join(SECRET_KEY_PEM)).unwrap();
+
+        let result = generate_validator_keys(dir.path(), KeyAlgorithm::Ed25519, false);
+        assert!(matches!(result, Err(Error::FileExists(_))));
+        assert_eq!(fs::read(dir.path().join(SECRET_KEY_PEM)).unwrap(), original);
+    }
+
+    #[test]
+    fn should_generate_valid_network_identity() {
+        let dir = tempfile::tempdir().unwrap();
+        let node_id = generate_network_identity(dir.path(), false).unwrap();
+
+        tls::load_private_key(dir.path().join(NETWORK_KEY_PEM)).unwrap();
+        match KeyMaterial::from_file(dir.path().join(NETWORK_CERT_PEM), false).unwrap() {
+            KeyMaterial::TlsCert(cert) => {
+                assert_eq!(NodeId::from(cert.public_key_fingerprint()), node_id)
+            }
+            _ => panic!("expected a TLS certificate"),
+        }
+    }
+
+    #[test]
+    fn should_convert_between_pem_and_hex() {
+        let secret_key = SecretKey::generate_secp256k1().unwrap();
+        let secret_pem = secret_key.to_pem().unwrap();
+
+        let from_pem = KeyMaterial::decode(secret_pem.as_bytes(), false).unwrap();
+        let secret_hex = from_pem.encode(KeyFormat::Hex).unwrap();
+        let from_hex = KeyMaterial::decode(secret_hex.as_bytes(), true).unwrap();
+        assert_eq!(from_hex.encode(KeyFormat::Pem).unwrap(), secret_pem);
+
+        let public_hex = format!("{}\n", PublicKey::from(&secret_key).to_hex());
+        let public_key = KeyMaterial::decode(public_hex.as_bytes(), false).unwrap();
+        let public_pem = public_key.encode(KeyFormat::Pem).unwrap();
+        let roundtripped = KeyMaterial::decode(public_pem.as_bytes(), false).unwrap();
+        assert_eq!(
+            roundtripped.encode(KeyFormat::Hex).unwrap(),
+            public_hex.trim()
+        );
+    }
+}
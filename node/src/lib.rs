@@ -24,15 +24,21 @@
 
 extern crate test;
 
+mod chainspec_diff;
 pub mod components;
 mod config_migration;
 pub mod crypto;
 mod data_migration;
+mod deploy_archive;
+mod dev_node;
 pub mod effect;
+mod event_replay;
+pub mod keygen;
 pub mod logging;
+pub mod plugins;
 pub mod protocol;
 pub mod reactor;
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 pub mod testing;
 pub mod tls;
 pub mod types;
@@ -45,14 +51,26 @@ use once_cell::sync::Lazy;
 #[cfg(not(test))]
 use rand::SeedableRng;
 
+pub use chainspec_diff::{
+    diff_chainspecs, Activation, ChainspecDiff, Error as ChainspecDiffError, ParameterChange,
+};
 pub use components::{
+    binary_port::{
+        Config as BinaryPortConfig, Request as BinaryPortRequest, Response as BinaryPortResponse,
+    },
+    block_executor::Config as BlockExecutorConfig,
     chainspec_loader::{Chainspec, Error as ChainspecError},
     consensus::Config as ConsensusConfig,
-    contract_runtime::Config as ContractRuntimeConfig,
+    contract_runtime::{
+        run_fixtures as run_conformance_fixtures, Config as ContractRuntimeConfig,
+        ConformanceError, FixtureOutcome,
+    },
     deploy_acceptor::Config as DeployAcceptorConfig,
+    disk_monitor::Config as DiskMonitorConfig,
     event_stream_server::Config as EventStreamServerConfig,
     fetcher::Config as FetcherConfig,
     gossiper::{Config as GossipConfig, Error as GossipError},
+    notifier::{Config as NotifierConfig, Error as NotifierError},
     rest_server::Config as RestServerConfig,
     rpc_server::{rpcs, Config as RpcServerConfig},
     small_network::{Config as SmallNetworkConfig, Error as SmallNetworkError},
@@ -60,6 +78,11 @@ pub use components::{
 };
 pub use config_migration::{migrate_config, Error as ConfigMigrationError};
 pub use data_migration::{migrate_data, Error as DataMigrationError};
+pub use deploy_archive::{
+    export_deploys, import_deploys, DeployFilter, Error as DeployArchiveError, ImportSummary,
+};
+pub use dev_node::{setup_dev_node, DevAccount, DevNode, Error as DevNodeError, DEV_ACCOUNT_COUNT};
+pub use event_replay::{replay_queue_dump, Error as EventReplayError, ReplaySummary};
 pub use types::NodeRng;
 pub use utils::OS_PAGE_SIZE;
 
@@ -98,13 +121,29 @@ pub static VERSION_STRING_COLOR: Lazy<String> = Lazy::new(|| version_string(true
 /// Version string for the compiled node. Filled in at build time, output allocated at runtime.
 pub static VERSION_STRING: Lazy<String> = Lazy::new(|| version_string(false));
 
-/// Global flag that indicates the currently running reactor should dump its event queue.
+/// Global flag that indicates the currently running reactor should dump its event queue, set on
+/// `SIGUSR1`.
 pub static QUEUE_DUMP_REQUESTED: Lazy<Arc<AtomicBool>> =
     Lazy::new(|| Arc::new(AtomicBool::new(false)));
 
+/// Global flag that indicates the node should shut down gracefully.
+pub static TERMINATION_REQUESTED: Lazy<Arc<AtomicBool>> =
+    Lazy::new(|| Arc::new(AtomicBool::new(false)));
+
 /// Setup UNIX signal hooks for current application.
+///
+/// The first `SIGINT` or `SIGTERM` requests a graceful shutdown, a second one terminates the node
+/// immediately.
 pub fn setup_signal_hooks() {
     let _ = signal_hook::flag::register(libc::SIGUSR1, QUEUE_DUMP_REQUESTED.clone());
+    for signal in &[libc::SIGINT, libc::SIGTERM] {
+        let _ = signal_hook::flag::register_conditional_shutdown(
+            *signal,
+            1,
+            TERMINATION_REQUESTED.clone(),
+        );
+        let _ = signal_hook::flag::register(*signal, TERMINATION_REQUESTED.clone());
+    }
 }
 
 /// Constructs a new `NodeRng`.
@@ -1,26 +1,42 @@
 //! Logging via the tracing crate.
 
-use std::{env, fmt, io};
+mod log_file;
+
+use std::{
+    cell::RefCell,
+    env, fmt, io,
+    path::{Path, PathBuf},
+};
 
 use ansi_term::{Color, Style};
 use anyhow::anyhow;
 use datasize::DataSize;
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use tracing::{
     field::{Field, Visit},
-    Event, Level, Subscriber,
+    info_span, warn, Event, Level, Span, Subscriber,
 };
 use tracing_subscriber::{
     fmt::{
         format,
         time::{FormatTime, SystemTime},
-        FmtContext, FormatEvent, FormatFields, FormattedFields,
+        FmtContext, FormatEvent, FormatFields, FormattedFields, MakeWriter,
     },
+    layer::{Context, Layer, SubscriberExt},
     registry::LookupSpan,
+    util::SubscriberInitExt,
     EnvFilter,
 };
 
+use crate::{
+    components::consensus::EraId,
+    crypto::hash::Digest,
+    types::{DeployHash, NodeId},
+};
+use log_file::{LogContext, LogFile, RotationPolicy};
+
 const LOG_CONFIGURATION_ENVVAR: &str = "RUST_LOG";
 
 const LOG_FIELD_MESSAGE: &str = "message";
@@ -29,8 +45,14 @@ const LOG_FIELD_MODULE: &str = "log.module_path";
 const LOG_FIELD_FILE: &str = "log.file";
 const LOG_FIELD_LINE: &str = "log.line";
 
+const DEFAULT_MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
+const DEFAULT_MAX_ROTATED_FILES: usize = 10;
+
+/// The log file, if logging to one, kept to record context changes in it.
+static LOG_FILE: OnceCell<LogFile> = OnceCell::new();
+
 /// Logging configuration.
-#[derive(DataSize, Debug, Default, Deserialize, Serialize)]
+#[derive(DataSize, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct LoggingConfig {
     /// Output format for log.
@@ -47,15 +69,45 @@ pub struct LoggingConfig {
     /// If set, human-readable formats will abbreviate module names, `foo::bar::baz::bizz` will
     /// turn into `f:b:b:bizz`.
     abbreviate_modules: bool,
+
+    /// File to write the log to, rather than stdout.
+    ///
+    /// Every file starts with a context header naming the node version, chainspec hash, node ID
+    /// and era, so that rotated files can be interpreted on their own.
+    file: Option<PathBuf>,
+
+    /// Size in bytes beyond which the log file is rotated, unlimited if `0`.
+    max_file_size: u64,
+
+    /// Whether the log file is rotated whenever a new era starts.
+    rotate_on_era: bool,
+
+    /// Number of rotated log files kept, as `<file>.1` (the most recent) to `<file>.<n>`.
+    max_rotated_files: usize,
 }
 
 impl LoggingConfig {
-    /// Creates a new instance of LoggingConfig.
+    /// Creates a new instance of LoggingConfig, logging to stdout.
     pub fn new(format: LoggingFormat, color: bool, abbreviate_modules: bool) -> Self {
         LoggingConfig {
             format,
             color,
             abbreviate_modules,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            format: LoggingFormat::default(),
+            color: false,
+            abbreviate_modules: false,
+            file: None,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            rotate_on_era: true,
+            max_rotated_files: DEFAULT_MAX_ROTATED_FILES,
         }
     }
 }
@@ -63,7 +115,7 @@ impl LoggingConfig {
 /// Logging output format.
 ///
 /// Defaults to "text"".
-#[derive(DataSize, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, DataSize, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum LoggingFormat {
     /// Text format.
@@ -251,6 +303,89 @@ where
     }
 }
 
+thread_local! {
+    /// The last error or warning logged on this thread, if `capture_problems` is running.
+    static CAPTURED_PROBLEM: RefCell<Option<Option<String>>> = RefCell::new(None);
+}
+
+/// Records errors and warnings for `capture_problems`.
+struct ProblemCapture;
+
+impl<S: Subscriber> Layer<S> for ProblemCapture {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // More verbose levels compare as greater.
+        if *event.metadata().level() > Level::WARN {
+            return;
+        }
+        CAPTURED_PROBLEM.with(|captured| {
+            if let Some(problem) = captured.borrow_mut().as_mut() {
+                let mut visitor = MessageVisitor::default();
+                event.record(&mut visitor);
+                *problem = Some(visitor.message);
+            }
+        });
+    }
+}
+
+/// Extracts the message of a log event.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == LOG_FIELD_MESSAGE {
+            self.message = format!("{:?}", value);
+        }
+    }
+}
+
+/// Runs `f` and returns its result along with the last error or warning it logged on the current
+/// thread, if any.
+///
+/// Problems are only captured once logging has been initialized, and not from tasks `f` spawns.
+pub(crate) fn capture_problems<T, F: FnOnce() -> T>(f: F) -> (T, Option<String>) {
+    let outer = CAPTURED_PROBLEM.with(|captured| captured.replace(Some(None)));
+    let result = f();
+    let problem = CAPTURED_PROBLEM
+        .with(|captured| captured.replace(outer))
+        .flatten();
+    (result, problem)
+}
+
+/// Records the node's ID and chainspec in the context headers of the log file, if any.
+pub(crate) fn set_node_context(node_id: &NodeId, chainspec_hash: &Digest) {
+    if let Some(log_file) = LOG_FILE.get() {
+        let result = log_file.update_context(|context| {
+            context.node_id = Some(format!("{:?}", node_id));
+            context.chainspec_hash = Some(format!("{:x}", chainspec_hash));
+        });
+        if let Err(error) = result {
+            warn!(%error, "could not write context to log file");
+        }
+    }
+}
+
+/// Records the current era in the context headers of the log file, if any, rotating the file if
+/// the era changed and the config asks for it.
+pub(crate) fn enter_era(era_id: EraId) {
+    if let Some(log_file) = LOG_FILE.get() {
+        if let Err(error) = log_file.enter_era(era_id.0) {
+            warn!(%error, "could not rotate log file");
+        }
+    }
+}
+
+/// Returns a span carrying the full hash of the deploy whose processing is logged within it.
+///
+/// The reactor dispatches every event concerning a single deploy within such a span, see
+/// `Reactor::event_deploy_hash`, so that the deploy's lifecycle across components can be followed
+/// by filtering on the `deploy_hash` field, e.g. with `clogfmt --deploy`.
+pub(crate) fn deploy_span(deploy_hash: &DeployHash) -> Span {
+    info_span!("deploy", deploy_hash = %format!("{:x}", deploy_hash.inner()))
+}
+
 /// Initializes the logging system with the default parameters.
 ///
 /// See `init_params` for details.
@@ -265,6 +400,40 @@ pub fn init() -> anyhow::Result<()> {
 ///
 /// See the `README.md` for hints on how to configure logging at runtime.
 pub fn init_with_config(config: &LoggingConfig) -> anyhow::Result<()> {
+    init_with_config_in(Path::new(""), config)
+}
+
+/// Initializes the logging system, resolving a relative log file path against `root`.
+///
+/// See `init_with_config` for details.
+pub fn init_with_config_in(root: &Path, config: &LoggingConfig) -> anyhow::Result<()> {
+    if let Some(file) = &config.file {
+        let path = root.join(file);
+        let policy = RotationPolicy {
+            max_file_size: config.max_file_size,
+            rotate_on_era: config.rotate_on_era,
+            max_rotated_files: config.max_rotated_files,
+        };
+        let context = LogContext {
+            version: crate::VERSION_STRING.clone(),
+            ..Default::default()
+        };
+        let log_file = LogFile::open(path.clone(), config.format, policy, context)
+            .map_err(|error| anyhow!("could not open log file {}: {}", path.display(), error))?;
+        init_subscriber(config, log_file.clone())?;
+        // Logging is only initialized once, so the cell is empty.
+        let _ = LOG_FILE.set(log_file);
+        Ok(())
+    } else {
+        init_subscriber(config, io::stdout)
+    }
+}
+
+/// Installs the global subscriber, writing to `writer`.
+fn init_subscriber<W>(config: &LoggingConfig, writer: W) -> anyhow::Result<()>
+where
+    W: MakeWriter + Send + Sync + 'static,
+{
     let formatter = format::debug_fn(|writer, field, value| match field.name() {
         LOG_FIELD_MESSAGE => write!(writer, "{:?}", value),
         LOG_FIELD_TARGET | LOG_FIELD_MODULE | LOG_FIELD_FILE | LOG_FIELD_LINE => Ok(()),
@@ -278,18 +447,22 @@ pub fn init_with_config(config: &LoggingConfig) -> anyhow::Result<()> {
     );
 
     match config.format {
-        // Setup a new tracing-subscriber writing to `stdout` for logging.
+        // Setup a new tracing-subscriber writing to `writer` for logging.
         LoggingFormat::Text => tracing_subscriber::fmt()
-            .with_writer(io::stdout)
+            .with_writer(writer)
             .with_env_filter(filter)
             .fmt_fields(formatter)
             .event_format(FmtEvent::new(config.color, config.abbreviate_modules))
+            .finish()
+            .with(ProblemCapture)
             .try_init(),
-        // JSON logging writes to `stdout` as well but uses the JSON format.
+        // JSON logging writes to `writer` as well but uses the JSON format.
         LoggingFormat::Json => tracing_subscriber::fmt()
-            .with_writer(io::stdout)
+            .with_writer(writer)
             .with_env_filter(filter)
             .json()
+            .finish()
+            .with(ProblemCapture)
             .try_init(),
     }
     .map_err(|error| anyhow!(error))
@@ -0,0 +1,303 @@
+//! Log files rotated by size and era.
+//!
+//! Every file starts with a context header naming the node version, chainspec hash, node ID and
+//! era, so that a rotated fragment of a log can be interpreted without the files preceding it.
+//! Context learned while a file is open, e.g. the node ID once the network is up, is written as
+//! an additional header line.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use serde::Serialize;
+use serde_json::json;
+use tracing_subscriber::fmt::MakeWriter;
+
+use super::LoggingFormat;
+use crate::types::Timestamp;
+
+/// The context written at the start of every log file.
+#[derive(Clone, Debug, Default, Serialize)]
+pub(super) struct LogContext {
+    /// The node's version string.
+    pub(super) version: String,
+    /// The hash of the chainspec the node runs, once known.
+    pub(super) chainspec_hash: Option<String>,
+    /// The node's ID, once the network is up.
+    pub(super) node_id: Option<String>,
+    /// The current era, once the first block was added.
+    pub(super) era: Option<u64>,
+}
+
+/// The settings of a rotated log file.
+#[derive(Debug)]
+pub(super) struct RotationPolicy {
+    /// The size in bytes beyond which the file is rotated, unlimited if `0`.
+    pub(super) max_file_size: u64,
+    /// Whether the file is rotated whenever a new era starts.
+    pub(super) rotate_on_era: bool,
+    /// The number of rotated files kept next to the current one.
+    pub(super) max_rotated_files: usize,
+}
+
+/// A log file, rotated according to its policy.
+#[derive(Debug)]
+struct RotatingLog {
+    path: PathBuf,
+    format: LoggingFormat,
+    policy: RotationPolicy,
+    context: LogContext,
+    file: File,
+    /// The number of bytes written to the current file.
+    written: u64,
+}
+
+impl RotatingLog {
+    /// Rotates away the previous run's log, if any, and opens a new file with a context header.
+    fn open(
+        path: PathBuf,
+        format: LoggingFormat,
+        policy: RotationPolicy,
+        context: LogContext,
+    ) -> io::Result<Self> {
+        if fs::metadata(&path).map_or(false, |metadata| metadata.len() > 0) {
+            shift_rotated_files(&path, policy.max_rotated_files)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)?;
+        let mut log = RotatingLog {
+            path,
+            format,
+            policy,
+            context,
+            file,
+            written: 0,
+        };
+        log.write_header()?;
+        Ok(log)
+    }
+
+    /// Closes the current file and starts a new one with a context header.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        shift_rotated_files(&self.path, self.policy.max_rotated_files)?;
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        self.write_header()
+    }
+
+    /// Writes the current context to the file, formatted like the other log lines.
+    fn write_header(&mut self) -> io::Result<()> {
+        let timestamp = Timestamp::now();
+        let line = match self.format {
+            LoggingFormat::Text => format!(
+                "{} INFO  [casper_node::logging] log context; version={}; chainspec_hash={}; \
+                 node_id={}; era={}\n",
+                timestamp,
+                self.context.version,
+                self.context.chainspec_hash.as_deref().unwrap_or("unknown"),
+                self.context.node_id.as_deref().unwrap_or("unknown"),
+                self.context
+                    .era
+                    .map_or_else(|| "unknown".to_string(), |era| era.to_string()),
+            ),
+            LoggingFormat::Json => {
+                let header = json!({
+                    "timestamp": timestamp.to_string(),
+                    "level": "INFO",
+                    "fields": {
+                        "message": "log context",
+                        "context": self.context,
+                    },
+                    "target": "casper_node::logging",
+                });
+                format!("{}\n", header)
+            }
+        };
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    /// Records a new era, rotating the file if the policy asks for it.
+    fn enter_era(&mut self, era: u64) -> io::Result<()> {
+        let previous = self.context.era.replace(era);
+        match previous {
+            Some(previous) if previous == era => Ok(()),
+            Some(_) if self.policy.rotate_on_era => self.rotate(),
+            // The era of the current file's first lines was unknown, or we don't rotate on eras.
+            _ => self.write_header(),
+        }
+    }
+}
+
+impl Write for RotatingLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let max_file_size = self.policy.max_file_size;
+        // The header alone never triggers a rotation, so there is no loop of empty files.
+        if max_file_size > 0 && self.written > 0 && self.written + buf.len() as u64 > max_file_size
+        {
+            self.rotate()?;
+        }
+        // Events are written in one call each, so writing them whole keeps them in one file.
+        self.file.write_all(buf)?;
+        self.written += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Renames `path.1` to `path.2` and so on, dropping the oldest file, then `path` to `path.1`.
+fn shift_rotated_files(path: &Path, max_rotated_files: usize) -> io::Result<()> {
+    if max_rotated_files == 0 {
+        return fs::remove_file(path).or_else(ignore_not_found);
+    }
+    for index in (1..max_rotated_files).rev() {
+        fs::rename(rotated_path(path, index), rotated_path(path, index + 1))
+            .or_else(ignore_not_found)?;
+    }
+    fs::rename(path, rotated_path(path, 1)).or_else(ignore_not_found)
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = OsString::from(path.as_os_str());
+    rotated.push(format!(".{}", index));
+    PathBuf::from(rotated)
+}
+
+fn ignore_not_found(error: io::Error) -> io::Result<()> {
+    if error.kind() == io::ErrorKind::NotFound {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// A handle to a rotated log file, shared by the subscriber and the context updates.
+#[derive(Clone, Debug)]
+pub(super) struct LogFile(Arc<Mutex<RotatingLog>>);
+
+impl LogFile {
+    /// Opens the log file at `path`, rotating away the previous run's log.
+    pub(super) fn open(
+        path: PathBuf,
+        format: LoggingFormat,
+        policy: RotationPolicy,
+        context: LogContext,
+    ) -> io::Result<Self> {
+        let log = RotatingLog::open(path, format, policy, context)?;
+        Ok(LogFile(Arc::new(Mutex::new(log))))
+    }
+
+    /// Updates the context, writing it to the current file.
+    pub(super) fn update_context<F: FnOnce(&mut LogContext)>(&self, update: F) -> io::Result<()> {
+        let mut log = self.lock();
+        update(&mut log.context);
+        log.write_header()
+    }
+
+    /// Records a new era, rotating the file if the policy asks for it.
+    pub(super) fn enter_era(&self, era: u64) -> io::Result<()> {
+        self.lock().enter_era(era)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RotatingLog> {
+        // A panic while writing leaves the file usable, so a poisoned lock is fine to reuse.
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl MakeWriter for LogFile {
+    type Writer = LogFile;
+
+    fn make_writer(&self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> LogContext {
+        LogContext {
+            version: "1.0.0".to_string(),
+            node_id: Some("NodeId::Tls(0123)".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn read(path: &Path) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn should_rotate_by_size_and_era_with_context_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("casper-node.log");
+        fs::write(&path, "previous run\n").unwrap();
+        let policy = RotationPolicy {
+            max_file_size: 200,
+            rotate_on_era: true,
+            max_rotated_files: 2,
+        };
+        let mut log_file = LogFile::open(path.clone(), LoggingFormat::Text, policy, context())
+            .expect("should open log file");
+
+        // The previous run's log was rotated away, and the new file starts with a header.
+        assert_eq!(read(&rotated_path(&path, 1)), vec!["previous run"]);
+        let lines = read(&path);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].ends_with("node_id=NodeId::Tls(0123); era=unknown"));
+
+        // Learning the first era only adds a header line.
+        log_file.enter_era(3).unwrap();
+        log_file.write_all(b"event in era 3\n").unwrap();
+        let lines = read(&path);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with("era=3"));
+
+        // A new era starts a new file.
+        log_file.enter_era(4).unwrap();
+        assert_eq!(read(&rotated_path(&path, 1))[2], "event in era 3");
+        assert_eq!(read(&rotated_path(&path, 2)), vec!["previous run"]);
+        let lines = read(&path);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].ends_with("era=4"));
+
+        // Exceeding the size does too, and the oldest file is dropped.
+        log_file.write_all(&[b'x'; 150]).unwrap();
+        assert_eq!(read(&path).len(), 2);
+        assert_eq!(read(&rotated_path(&path, 2))[2], "event in era 3");
+        assert!(!rotated_path(&path, 3).exists());
+        let lines = read(&path);
+        assert!(lines[0].ends_with("era=4"));
+        assert_eq!(lines[1].len(), 150);
+    }
+}
@@ -0,0 +1,42 @@
+//! Plugins
+//!
+//! Plugins are custom components added to a reactor generated by the `reactor!` macro through its
+//! `plugins` section, e.g. indexers or compliance hooks. Each plugin lives in its own submodule of
+//! this module, following the same conventions as the modules of `crate::components`.
+//!
+//! Unlike regular components, plugins are not wired into the announcements of the reactor.
+//! Instead, they subscribe to the values they are interested in when being constructed, see
+//! `EffectBuilder::subscribe`. The following values are published:
+//!
+//! * `FinalizedBlock`s, once finalized by consensus,
+//! * `Deploy`s, once accepted and stored for the first time.
+//!
+//! Plugins defined in another crate, which depends on this one, are given by their full path
+//! instead, e.g. `indexer = has_effects ::casper_indexer::Indexer(effect_builder, registry);`,
+//! and their event type is expected in the same module, here `::casper_indexer::Event`. Outside of
+//! this crate, only the public methods of `EffectBuilder` are available to them, i.e. subscribing
+//! and reading blocks and deploys from storage.
+//!
+//! The requests a plugin may make are restricted to those required by `PluginReactorEventT`. To
+//! keep a plugin from depending on anything else, its `Component` implementation and constructor
+//! should be generic over the reactor event, bounded only by `PluginReactorEventT` and
+//! `From<Event>` for the plugin's own event type:
+//!
+//! ```ignore
+//! impl<REv> Component<REv> for Indexer
+//! where
+//!     REv: PluginReactorEventT + From<Event>,
+//! {
+//!     // ...
+//! }
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::effect::requests::StorageRequest;
+
+/// The requests available to plugins, which any reactor hosting plugins must route.
+pub trait PluginReactorEventT: From<StorageRequest> + Send + 'static {}
+
+impl<REv> PluginReactorEventT for REv where REv: From<StorageRequest> + Send + 'static {}
@@ -0,0 +1,157 @@
+//! Tests of a reactor hosting a plugin.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use casper_node_macros::reactor;
+use serde::Serialize;
+use smallvec::smallvec;
+
+use super::PluginReactorEventT;
+use crate::{
+    components::{storage, Component},
+    effect::{EffectBuilder, EffectExt, Effects},
+    reactor::Runner,
+    testing::TestRng,
+    types::{Deploy, DeployHash},
+    utils::WithDir,
+    NodeRng,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A plugin looking up every published deploy in storage.
+#[derive(Debug, Default)]
+pub struct DeployRecorder {
+    /// The deploys looked up so far, and whether they were found.
+    looked_up: HashMap<DeployHash, bool>,
+}
+
+/// Events of the deploy recorder.
+#[derive(Debug, Serialize)]
+pub enum Event {
+    /// A deploy was published.
+    Published(DeployHash),
+    /// A published deploy was looked up in storage.
+    LookedUp {
+        /// The hash of the deploy.
+        deploy_hash: DeployHash,
+        /// Whether it was found.
+        found: bool,
+    },
+}
+
+impl Display for Event {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Published(deploy_hash) => write!(formatter, "published {}", deploy_hash),
+            Event::LookedUp { deploy_hash, found } => {
+                write!(formatter, "looked up {}, found: {}", deploy_hash, found)
+            }
+        }
+    }
+}
+
+impl DeployRecorder {
+    /// Creates a new deploy recorder, subscribing to published deploys.
+    pub fn new<REv>(effect_builder: EffectBuilder<REv>) -> Self
+    where
+        REv: PluginReactorEventT + From<Event>,
+    {
+        effect_builder.subscribe(|deploy: Deploy| Event::Published(*deploy.id()));
+        DeployRecorder::default()
+    }
+}
+
+impl<REv> Component<REv> for DeployRecorder
+where
+    REv: PluginReactorEventT + From<Event>,
+{
+    type Event = Event;
+    type ConstructionError = Infallible;
+
+    fn handle_event(
+        &mut self,
+        effect_builder: EffectBuilder<REv>,
+        _rng: &mut NodeRng,
+        event: Self::Event,
+    ) -> Effects<Self::Event> {
+        match event {
+            Event::Published(deploy_hash) => effect_builder
+                .get_deploys_from_storage(smallvec![deploy_hash])
+                .event(move |mut deploys| Event::LookedUp {
+                    deploy_hash,
+                    found: deploys.pop().flatten().is_some(),
+                }),
+            Event::LookedUp { deploy_hash, found } => {
+                let _ = self.looked_up.insert(deploy_hash, found);
+                Effects::new()
+            }
+        }
+    }
+}
+
+reactor!(PluginHost {
+    type Config = WithDir<storage::Config>;
+
+    components: {
+        storage = Storage(&cfg, registry);
+    }
+
+    events: {}
+
+    requests: {
+        StorageRequest -> storage;
+    }
+
+    announcements: {}
+
+    plugins: {
+        deploy_recorder = infallible crate::plugins::tests::DeployRecorder(
+            effect_builder
+        );
+    }
+});
+
+#[tokio::test]
+async fn plugin_receives_published_values_and_makes_requests() {
+    let mut rng = TestRng::new();
+    let (storage_config, temp_dir) = storage::Config::default_for_tests();
+    let mut runner: Runner<PluginHost> =
+        Runner::new(WithDir::new(temp_dir.path(), storage_config), &mut rng)
+            .await
+            .expect("should create reactor hosting a plugin");
+
+    let stored = Deploy::random(&mut rng);
+    let unknown = Deploy::random(&mut rng);
+    let deploy = Box::new(stored.clone());
+    runner
+        .process_injected_effects(|effect_builder| {
+            effect_builder.put_deploy_to_storage(deploy).ignore()
+        })
+        .await;
+    runner.crank(&mut rng).await;
+
+    runner
+        .process_injected_effects(|effect_builder| {
+            let mut effects = effect_builder.publish(stored.clone()).ignore();
+            effects.extend(effect_builder.publish(unknown.clone()).ignore());
+            effects
+        })
+        .await;
+    tokio::time::timeout(TIMEOUT, async {
+        while runner.reactor().deploy_recorder.looked_up.len() < 2 {
+            runner.crank(&mut rng).await;
+        }
+    })
+    .await
+    .expect("plugin should have looked up both deploys");
+
+    let looked_up = &runner.reactor().deploy_recorder.looked_up;
+    assert_eq!(looked_up.get(stored.id()), Some(&true));
+    assert_eq!(looked_up.get(unknown.id()), Some(&false));
+}
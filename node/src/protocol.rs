@@ -8,8 +8,11 @@ use hex_fmt::HexFmt;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    components::{consensus, gossiper, small_network::GossipedAddress},
-    types::{Deploy, FinalitySignature, Item, Tag},
+    components::{
+        consensus, gossiper,
+        small_network::{GossipedAddress, LargePayload, RequestPayload},
+    },
+    types::{Deploy, FinalitySignature, Item, RequestClass, Tag},
 };
 
 /// Reactor message.
@@ -41,6 +44,9 @@ pub enum Message {
     /// Finality signature.
     #[from]
     FinalitySignature(Box<FinalitySignature>),
+    /// A deploy submitted by a peer, e.g. a light client node, to be accepted and gossiped as if
+    /// it had been received through the RPC server.
+    SubmitDeploy(Box<Deploy>),
 }
 
 impl Message {
@@ -59,6 +65,32 @@ impl Message {
     }
 }
 
+impl LargePayload for Message {
+    fn stream_data(&mut self) -> Option<&mut Vec<u8>> {
+        match self {
+            // Items like tries can be several megabytes in size. Smaller ones stay below the chunk
+            // size and are still sent as a single message.
+            Message::GetResponse {
+                serialized_item, ..
+            } => Some(serialized_item),
+            _ => None,
+        }
+    }
+}
+
+impl RequestPayload for Message {
+    fn request_class(&self) -> Option<RequestClass> {
+        match self {
+            Message::GetRequest { tag, .. } => match tag {
+                Tag::Deploy => Some(RequestClass::Deploy),
+                Tag::Block | Tag::BlockByHeight => Some(RequestClass::Block),
+                Tag::GossipedAddress => None,
+            },
+            _ => None,
+        }
+    }
+}
+
 impl Debug for Message {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -81,6 +113,7 @@ impl Debug for Message {
             Message::FinalitySignature(fs) => {
                 f.debug_tuple("FinalitySignature").field(&fs).finish()
             }
+            Message::SubmitDeploy(deploy) => f.debug_tuple("SubmitDeploy").field(&deploy).finish(),
         }
     }
 }
@@ -103,6 +136,7 @@ impl Display for Message {
             Message::FinalitySignature(fs) => {
                 write!(f, "FinalitySignature::({})", fs)
             }
+            Message::SubmitDeploy(deploy) => write!(f, "SubmitDeploy::({})", deploy.id()),
         }
     }
 }
@@ -24,12 +24,23 @@
 //! in a step-wise manner using [`crank`](struct.Runner.html#method.crank) or indefinitely using
 //! [`run`](struct.Runner.html#method.crank).
 
+mod component_stats;
 mod event_queue_metrics;
 pub mod initializer;
 pub mod initializer2;
+#[cfg(feature = "debug-assertions")]
+pub(crate) mod invariants;
 pub mod joiner;
+mod load_controller;
 mod queue_kind;
+mod queue_persistence;
+pub mod read_replica;
+mod shutdown;
+mod spillover;
+mod starvation;
 pub mod validator;
+mod watchdog;
+mod yield_policy;
 
 use std::{
     collections::HashMap,
@@ -37,6 +48,7 @@ use std::{
     fmt::{Debug, Display},
     fs::File,
     mem,
+    path::{Path, PathBuf},
     str::FromStr,
     sync::atomic::Ordering,
 };
@@ -45,21 +57,42 @@ use datasize::DataSize;
 use futures::{future::BoxFuture, FutureExt};
 use jemalloc_ctl::{epoch as jemalloc_epoch, stats::allocated as jemalloc_allocated};
 use once_cell::sync::Lazy;
-use prometheus::{self, Histogram, HistogramOpts, IntCounter, Registry};
+use prometheus::{
+    self, GaugeVec, Histogram, HistogramOpts, IntCounter, IntGauge, IntGaugeVec, Opts, Registry,
+};
 use quanta::IntoNanoseconds;
 use serde::Serialize;
-use tokio::time::{Duration, Instant};
-use tracing::{debug, debug_span, info, trace, warn};
+use tokio::{
+    runtime::Handle,
+    time::{Duration, Instant},
+};
+use tracing::{debug, debug_span, info, trace, warn, Span};
 use tracing_futures::Instrument;
 
 use crate::{
-    effect::{Effect, EffectBuilder, Effects},
-    types::Timestamp,
-    utils::{self, WeightedRoundRobin},
+    effect::{
+        subscriptions::{Subscriptions, SubscriptionsGuard},
+        Effect, EffectBuilder, Effects,
+    },
+    logging,
+    types::{DeployHash, Timestamp},
+    utils::{self, resource_usage, WeightedRoundRobin},
     NodeRng,
 };
+pub(crate) use component_stats::ComponentStats;
+use event_queue_metrics::EventQueueMetrics;
+#[cfg(feature = "debug-assertions")]
+pub use invariants::InvariantViolation;
+pub(crate) use load_controller::LoadController;
 use quanta::Clock;
 pub use queue_kind::QueueKind;
+pub use queue_persistence::{PersistedEvent, QueuePersistenceError};
+pub use read_replica::Config as ReadReplicaConfig;
+pub use shutdown::{Config as ShutdownConfig, ShutdownStage};
+pub use spillover::Config as SpilloverConfig;
+use starvation::StarvationDetector;
+pub use watchdog::{start_watchdog, Config as WatchdogConfig};
+pub use yield_policy::{Config as YieldPolicyConfig, YieldBudget};
 
 /// Optional upper threshold for total RAM allocated in mB before dumping queues to disk.
 const MEM_DUMP_THRESHOLD_MB_ENV_VAR: &str = "CL_MEM_DUMP_THRESHOLD_MB";
@@ -95,6 +128,9 @@ static DISPATCH_EVENT_THRESHOLD: Lazy<Duration> = Lazy::new(|| {
         .unwrap_or_else(|_| DEFAULT_DISPATCH_EVENT_THRESHOLD)
 });
 
+/// How long to wait for new events during a shutdown stage if the event queues are empty.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Event scheduler
 ///
 /// The scheduler is a combination of multiple event queues that are polled in a specific order. It
@@ -109,21 +145,40 @@ pub type Scheduler<Ev> = WeightedRoundRobin<Ev, QueueKind>;
 /// outside of the normal event loop. It gives different parts a chance to schedule messages that
 /// stem from things like external IO.
 #[derive(DataSize, Debug)]
-pub struct EventQueueHandle<REv>(&'static Scheduler<REv>)
+pub struct EventQueueHandle<REv>
 where
-    REv: 'static;
+    REv: 'static,
+{
+    scheduler: &'static Scheduler<REv>,
+    #[data_size(skip)]
+    component_stats: &'static ComponentStats,
+    #[data_size(skip)]
+    subscriptions: &'static Subscriptions<REv>,
+    #[data_size(skip)]
+    load_controller: &'static LoadController,
+}
 
 // Implement `Clone` and `Copy` manually, as `derive` will make it depend on `R` and `Ev` otherwise.
 impl<REv> Clone for EventQueueHandle<REv> {
     fn clone(&self) -> Self {
-        EventQueueHandle(self.0)
+        *self
     }
 }
 impl<REv> Copy for EventQueueHandle<REv> {}
 
 impl<REv> EventQueueHandle<REv> {
-    pub(crate) fn new(scheduler: &'static Scheduler<REv>) -> Self {
-        EventQueueHandle(scheduler)
+    pub(crate) fn new(
+        scheduler: &'static Scheduler<REv>,
+        component_stats: &'static ComponentStats,
+        subscriptions: &'static Subscriptions<REv>,
+        load_controller: &'static LoadController,
+    ) -> Self {
+        EventQueueHandle {
+            scheduler,
+            component_stats,
+            subscriptions,
+            load_controller,
+        }
     }
 
     /// Schedule an event on a specific queue.
@@ -132,12 +187,27 @@ impl<REv> EventQueueHandle<REv> {
     where
         REv: From<Ev>,
     {
-        self.0.push(event.into(), queue_kind).await
+        self.scheduler.push(event.into(), queue_kind).await
     }
 
     /// Returns number of events in each of the scheduler's queues.
     pub(crate) fn event_queues_counts(&self) -> HashMap<QueueKind, usize> {
-        self.0.event_queues_counts()
+        self.scheduler.event_queues_counts()
+    }
+
+    /// Returns the per-component event handling statistics of the reactor.
+    pub(crate) fn component_stats(&self) -> &'static ComponentStats {
+        self.component_stats
+    }
+
+    /// Returns the controller adapting concurrency to the load of the node.
+    pub(crate) fn load_controller(&self) -> &'static LoadController {
+        self.load_controller
+    }
+
+    /// Returns the typed subscriptions of the reactor.
+    pub(crate) fn subscriptions(&self) -> &'static Subscriptions<REv> {
+        self.subscriptions
     }
 }
 
@@ -190,8 +260,111 @@ pub trait Reactor: Sized {
         false
     }
 
+    /// Starts the given stage of an ordered shutdown, see `Runner::shutdown`.
+    ///
+    /// By default, no stage requires any action.
+    fn begin_shutdown_stage(
+        &mut self,
+        _effect_builder: EffectBuilder<Self::Event>,
+        _stage: ShutdownStage,
+    ) -> Effects<Self::Event> {
+        Effects::new()
+    }
+
+    /// Returns whether the work of the given stage of an ordered shutdown has been completed.
+    ///
+    /// By default, every stage completes as soon as it has been started.
+    fn is_shutdown_stage_complete(&mut self, _stage: ShutdownStage) -> bool {
+        true
+    }
+
+    /// Returns why a critical component is unhealthy, if one is.
+    ///
+    /// Checked about once per second while the reactor runs, see the `watchdog` module. By default,
+    /// the reactor is always healthy.
+    fn health_problem(&self) -> Option<String> {
+        None
+    }
+
     /// Instructs the reactor to update performance metrics, if any.
     fn update_metrics(&mut self, _event_queue_handle: EventQueueHandle<Self::Event>) {}
+
+    /// Converts a queued event into a form which can be replayed after a restart or spilled to
+    /// disk.
+    ///
+    /// Hands the event back if it cannot be persisted, which is the default for all events.
+    fn persist_event(event: Self::Event) -> Result<PersistedEvent, Self::Event> {
+        Err(event)
+    }
+
+    /// Returns the form `persist_event` would convert a queued event into, leaving the event in the
+    /// queue, see `Runner::dump_queues`.
+    ///
+    /// Returns `None` if the event cannot be persisted, which is the default for all events.
+    fn persisted_form(_event: &Self::Event) -> Option<PersistedEvent> {
+        None
+    }
+
+    /// Converts an event persisted by a previous instance of the reactor back into an event.
+    ///
+    /// Returns `None` if the event cannot be replayed by this reactor.
+    fn restore_event(_event: PersistedEvent) -> Option<Self::Event> {
+        None
+    }
+
+    /// Returns the event queue spillover configuration with its path resolved, if the reactor
+    /// supports spillover.
+    fn spillover_config(_cfg: &Self::Config) -> Option<SpilloverConfig> {
+        None
+    }
+
+    /// Returns how the runner yields to other tasks.
+    ///
+    /// The default implementation uses the default policy.
+    fn yield_policy_config(_cfg: &Self::Config) -> YieldPolicyConfig {
+        YieldPolicyConfig::default()
+    }
+
+    /// Returns whether the effects of `event` are critical to the latency of consensus, and run on
+    /// the dedicated consensus runtime if one is configured.
+    ///
+    /// By default, no event is critical.
+    fn is_consensus_critical(_event: &Self::Event) -> bool {
+        false
+    }
+
+    /// Returns the directory event queue dumps are written to, usually the node's data directory.
+    ///
+    /// The default implementation uses the system's temporary directory.
+    fn queue_dump_dir(_cfg: &Self::Config) -> Option<PathBuf> {
+        None
+    }
+
+    /// Returns the name of the component `event` is dispatched to, used for the per-component
+    /// statistics.
+    ///
+    /// The default implementation attributes all events to the reactor itself.
+    fn event_component(_event: &Self::Event) -> &'static str {
+        "reactor"
+    }
+
+    /// Returns the hash of the deploy `event` concerns, if it concerns a single one.
+    ///
+    /// Such events are dispatched, and their effects processed, within a `deploy` span carrying
+    /// the hash, see `logging::deploy_span`. By default, no event concerns a deploy.
+    fn event_deploy_hash(_event: &Self::Event) -> Option<DeployHash> {
+        None
+    }
+
+    /// Checks invariants spanning several of the reactor's components, returning all violated
+    /// ones.
+    ///
+    /// Called after every crank if the `debug-assertions` feature is enabled, so each check should
+    /// be cheap. By default, the reactor has no invariants.
+    #[cfg(feature = "debug-assertions")]
+    fn check_invariants(&self) -> Vec<InvariantViolation> {
+        Vec::new()
+    }
 }
 
 /// A drop-like trait for `async` compatible drop-and-wait.
@@ -227,9 +400,19 @@ where
     /// The scheduler used for the reactor.
     scheduler: &'static Scheduler<R::Event>,
 
+    /// Per-component event handling statistics, shared with the event queue handles.
+    component_stats: &'static ComponentStats,
+
+    /// Typed subscriptions, shared with the event queue handles and closed once the runner is
+    /// dropped.
+    subscriptions: SubscriptionsGuard<R::Event>,
+
     /// The reactor instance itself.
     reactor: R,
 
+    /// Adapts concurrency to the load of the node, shared with the event queue handles.
+    load_controller: &'static LoadController,
+
     /// Counter for events, to aid tracing.
     event_count: usize,
 
@@ -239,6 +422,12 @@ where
     /// Metrics for the runner.
     metrics: RunnerMetrics,
 
+    /// Metrics for the number of events in each of the scheduler's queues.
+    event_queue_metrics: EventQueueMetrics,
+
+    /// Reports queues starved by the scheduler.
+    starvation_detector: StarvationDetector,
+
     /// Check if we need to update reactor metrics every this many events.
     event_metrics_threshold: usize,
 
@@ -250,6 +439,15 @@ where
 
     /// Last queue dump timestamp
     last_queue_dump: Option<Timestamp>,
+
+    /// Directory the event queues are dumped to.
+    queue_dump_dir: PathBuf,
+
+    /// The events handled before the runner yields to other tasks.
+    yield_budget: YieldBudget,
+
+    /// The runtime the effects of consensus-critical events run on, if not the current one.
+    consensus_runtime: Option<Handle>,
 }
 
 /// Metric data for the Runner
@@ -261,6 +459,33 @@ struct RunnerMetrics {
     /// Histogram of how long it took to dispatch an event.
     event_dispatch_duration: Histogram,
 
+    /// Number of times the runner used up its budget and yielded to other tasks.
+    yields: IntCounter,
+
+    /// Number of events currently spilled to disk.
+    spilled_events: IntGauge,
+
+    /// Histogram of how long it took to read back a batch of spilled events.
+    spill_read_back_duration: Histogram,
+
+    /// Current concurrency of components relative to their configuration, in percent.
+    concurrency_percent: IntGauge,
+
+    /// CPU time attributed to each component, in seconds.
+    component_cpu_seconds: GaugeVec,
+
+    /// Number of read syscalls attributed to each component.
+    component_read_syscalls: IntGaugeVec,
+
+    /// Number of write syscalls attributed to each component.
+    component_write_syscalls: IntGaugeVec,
+
+    /// Number of bytes read attributed to each component.
+    component_read_bytes: IntGaugeVec,
+
+    /// Number of bytes written attributed to each component.
+    component_write_bytes: IntGaugeVec,
+
     /// Handle to the metrics registry, in case we need to unregister.
     registry: Registry,
 }
@@ -299,12 +524,89 @@ impl RunnerMetrics {
             ]),
         )?;
 
+        let yields = IntCounter::new(
+            "runner_yields",
+            "number of times the runner used up its budget and yielded to other tasks",
+        )?;
+
+        let spilled_events = IntGauge::new(
+            "event_queue_spilled_events",
+            "number of events currently spilled to disk",
+        )?;
+        let spill_read_back_duration = Histogram::with_opts(
+            HistogramOpts::new(
+                "event_queue_spill_read_back_duration",
+                "duration of reading back a batch of spilled events in nanoseconds",
+            )
+            .buckets(prometheus::exponential_buckets(10_000.0, 4.0, 10)?),
+        )?;
+        let concurrency_percent = IntGauge::new(
+            "runner_concurrency_percent",
+            "current concurrency of components relative to their configuration, in percent",
+        )?;
+        concurrency_percent.set(100);
+
+        // The usage attributed to components is estimated by sampling, see `resource_usage`.
+        let component_cpu_seconds = GaugeVec::new(
+            Opts::new(
+                "runner_component_cpu_seconds",
+                "estimated CPU time attributed to each component in seconds",
+            ),
+            &["component"],
+        )?;
+        let component_read_syscalls = IntGaugeVec::new(
+            Opts::new(
+                "runner_component_read_syscalls",
+                "estimated number of read syscalls attributed to each component",
+            ),
+            &["component"],
+        )?;
+        let component_write_syscalls = IntGaugeVec::new(
+            Opts::new(
+                "runner_component_write_syscalls",
+                "estimated number of write syscalls attributed to each component",
+            ),
+            &["component"],
+        )?;
+        let component_read_bytes = IntGaugeVec::new(
+            Opts::new(
+                "runner_component_read_bytes",
+                "estimated number of bytes read attributed to each component",
+            ),
+            &["component"],
+        )?;
+        let component_write_bytes = IntGaugeVec::new(
+            Opts::new(
+                "runner_component_write_bytes",
+                "estimated number of bytes written attributed to each component",
+            ),
+            &["component"],
+        )?;
+
         registry.register(Box::new(events.clone()))?;
         registry.register(Box::new(event_dispatch_duration.clone()))?;
+        registry.register(Box::new(yields.clone()))?;
+        registry.register(Box::new(spilled_events.clone()))?;
+        registry.register(Box::new(spill_read_back_duration.clone()))?;
+        registry.register(Box::new(concurrency_percent.clone()))?;
+        registry.register(Box::new(component_cpu_seconds.clone()))?;
+        registry.register(Box::new(component_read_syscalls.clone()))?;
+        registry.register(Box::new(component_write_syscalls.clone()))?;
+        registry.register(Box::new(component_read_bytes.clone()))?;
+        registry.register(Box::new(component_write_bytes.clone()))?;
 
         Ok(RunnerMetrics {
             events,
             event_dispatch_duration,
+            yields,
+            spilled_events,
+            spill_read_back_duration,
+            concurrency_percent,
+            component_cpu_seconds,
+            component_read_syscalls,
+            component_write_syscalls,
+            component_read_bytes,
+            component_write_bytes,
             registry: registry.clone(),
         })
     }
@@ -318,6 +620,57 @@ impl Drop for RunnerMetrics {
         self.registry
             .unregister(Box::new(self.event_dispatch_duration.clone()))
             .expect("did not expect deregistering event_dispatch_duration to fail");
+        self.registry
+            .unregister(Box::new(self.yields.clone()))
+            .expect("did not expect deregistering yields to fail");
+        self.registry
+            .unregister(Box::new(self.spilled_events.clone()))
+            .expect("did not expect deregistering spilled_events to fail");
+        self.registry
+            .unregister(Box::new(self.spill_read_back_duration.clone()))
+            .expect("did not expect deregistering spill_read_back_duration to fail");
+        self.registry
+            .unregister(Box::new(self.concurrency_percent.clone()))
+            .expect("did not expect deregistering concurrency_percent to fail");
+        self.registry
+            .unregister(Box::new(self.component_cpu_seconds.clone()))
+            .expect("did not expect deregistering component_cpu_seconds to fail");
+        self.registry
+            .unregister(Box::new(self.component_read_syscalls.clone()))
+            .expect("did not expect deregistering component_read_syscalls to fail");
+        self.registry
+            .unregister(Box::new(self.component_write_syscalls.clone()))
+            .expect("did not expect deregistering component_write_syscalls to fail");
+        self.registry
+            .unregister(Box::new(self.component_read_bytes.clone()))
+            .expect("did not expect deregistering component_read_bytes to fail");
+        self.registry
+            .unregister(Box::new(self.component_write_bytes.clone()))
+            .expect("did not expect deregistering component_write_bytes to fail");
+    }
+}
+
+impl RunnerMetrics {
+    /// Updates the usage metrics from the usage attributed to components so far.
+    fn update_resource_usage(&self) {
+        for (component, usage) in resource_usage::snapshot() {
+            let labels = &[component];
+            self.component_cpu_seconds
+                .with_label_values(labels)
+                .set(usage.cpu_ns as f64 / 1e9);
+            self.component_read_syscalls
+                .with_label_values(labels)
+                .set(usage.read_syscalls as i64);
+            self.component_write_syscalls
+                .with_label_values(labels)
+                .set(usage.write_syscalls as i64);
+            self.component_read_bytes
+                .with_label_values(labels)
+                .set(usage.read_bytes as i64);
+            self.component_write_bytes
+                .with_label_values(labels)
+                .set(usage.write_bytes as i64);
+        }
     }
 }
 
@@ -353,9 +706,28 @@ where
             warn!(%event_size, "large event size, consider reducing it or boxing");
         }
 
-        let scheduler = utils::leak(Scheduler::new(QueueKind::weights()));
-
-        let event_queue = EventQueueHandle::new(scheduler);
+        let metrics = RunnerMetrics::new(registry)?;
+
+        let queue_dump_dir = R::queue_dump_dir(&cfg).unwrap_or_else(env::temp_dir);
+        let yield_policy_config = R::yield_policy_config(&cfg);
+        let mut scheduler = Scheduler::new(QueueKind::weights());
+        if let Some(spillover_config) = R::spillover_config(&cfg) {
+            spillover::enable::<R>(
+                &mut scheduler,
+                &spillover_config,
+                &metrics.spilled_events,
+                &metrics.spill_read_back_duration,
+            );
+        }
+        let scheduler = utils::leak(scheduler);
+        let component_stats = utils::leak(ComponentStats::default());
+        let subscriptions = utils::leak(Subscriptions::default());
+        let load_controller = utils::leak(LoadController::default());
+
+        let event_queue =
+            EventQueueHandle::new(scheduler, component_stats, subscriptions, load_controller);
+        let event_queue_metrics = EventQueueMetrics::new(registry.clone(), event_queue)?;
+        let starvation_detector = StarvationDetector::new(registry)?;
         let (reactor, initial_effects) = R::new(cfg, registry, event_queue, rng)?;
 
         // Run all effects from component instantiation.
@@ -368,25 +740,38 @@ where
 
         Ok(Runner {
             scheduler,
+            component_stats,
+            subscriptions: SubscriptionsGuard::new(subscriptions),
             reactor,
+            load_controller,
             event_count: 0,
-            metrics: RunnerMetrics::new(registry)?,
+            metrics,
+            event_queue_metrics,
+            starvation_detector,
             last_metrics: Instant::now(),
             event_metrics_min_delay: Duration::from_secs(30),
             event_metrics_threshold: 1000,
             clock: Clock::new(),
             last_queue_dump: None,
+            queue_dump_dir,
+            yield_budget: YieldBudget::new(&yield_policy_config),
+            consensus_runtime: yield_policy::consensus_runtime(&yield_policy_config),
         })
     }
 
     /// Inject (schedule then process) effects created via a call to `create_effects` which is
     /// itself passed an instance of an `EffectBuilder`.
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub(crate) async fn process_injected_effects<F>(&mut self, create_effects: F)
     where
         F: FnOnce(EffectBuilder<R::Event>) -> Effects<R::Event>,
     {
-        let event_queue = EventQueueHandle::new(self.scheduler);
+        let event_queue = EventQueueHandle::new(
+            self.scheduler,
+            self.component_stats,
+            self.subscriptions.get(),
+            self.load_controller,
+        );
         let effect_builder = EffectBuilder::new(event_queue);
 
         let effects = create_effects(effect_builder);
@@ -406,18 +791,31 @@ where
 
         self.metrics.events.inc();
 
-        let event_queue = EventQueueHandle::new(self.scheduler);
+        let event_queue = EventQueueHandle::new(
+            self.scheduler,
+            self.component_stats,
+            self.subscriptions.get(),
+            self.load_controller,
+        );
         let effect_builder = EffectBuilder::new(event_queue);
 
         // Update metrics like memory usage and event queue sizes.
         if self.event_count % self.event_metrics_threshold == 0 {
             let now = Instant::now();
+            let _ = self
+                .starvation_detector
+                .check(&self.scheduler.busy_since(), now.into_std());
 
             // We update metrics on the first very event as well to get a good baseline.
             if now.duration_since(self.last_metrics) >= self.event_metrics_min_delay
                 || self.event_count == 0
             {
                 self.reactor.update_metrics(event_queue);
+                self.event_queue_metrics
+                    .record_event_queue_counts(&event_queue);
+                self.component_stats
+                    .set_queued(self.scheduler.count_by(R::event_component).await);
+                self.metrics.update_resource_usage();
                 self.last_metrics = now;
             }
 
@@ -438,6 +836,12 @@ where
             }
         }
 
+        if self.load_controller.sample(self.scheduler.item_count()) {
+            self.metrics
+                .concurrency_percent
+                .set(self.load_controller.factor_percent().into());
+        }
+
         // Dump event queue if requested, stopping the world.
         if crate::QUEUE_DUMP_REQUESTED.load(Ordering::SeqCst) {
             debug!("dumping event queue as requested");
@@ -448,6 +852,11 @@ where
 
         let (event, q) = self.scheduler.pop().await;
 
+        // Events concerning a single deploy are processed within a span naming it.
+        let deploy_span = R::event_deploy_hash(&event)
+            .map_or_else(Span::none, |deploy_hash| logging::deploy_span(&deploy_hash));
+        let deploy_enter = deploy_span.enter();
+
         // Create another span for tracing the processing of one event.
         let event_span = debug_span!("dispatch events", ev = self.event_count);
         let inner_enter = event_span.enter();
@@ -458,8 +867,19 @@ where
         trace!(?event, ?q);
 
         // Dispatch the event, then execute the resulting effect.
+        let component = R::event_component(&event);
+        let runtime = if R::is_consensus_critical(&event) {
+            self.consensus_runtime.as_ref()
+        } else {
+            None
+        };
         let start = self.clock.start();
-        let effects = self.reactor.dispatch_event(effect_builder, rng, event);
+        let reactor = &mut self.reactor;
+        let (effects, problem) = logging::capture_problems(|| {
+            resource_usage::measure(component, || {
+                reactor.dispatch_event(effect_builder, rng, event)
+            })
+        });
         let end = self.clock.end();
 
         // Warn if processing took a long time, record to histogram.
@@ -474,17 +894,51 @@ where
         self.metrics
             .event_dispatch_duration
             .observe(delta.into_nanos() as f64);
+        self.component_stats
+            .record_dispatch(component, delta.into_nanos(), problem);
 
         drop(inner_enter);
 
         // We create another span for the effects, but will keep the same ID.
         let effect_span = debug_span!("process effects", ev = self.event_count);
+        drop(deploy_enter);
 
-        process_effects(self.scheduler, effects)
+        process_effects_on(runtime, self.scheduler, effects)
             .instrument(effect_span)
             .await;
 
         self.event_count += 1;
+        let reactor = &self.reactor;
+        watchdog::record_crank(|| reactor.health_problem());
+
+        // Let other tasks, e.g. the ones receiving consensus messages, run on a busy node.
+        if self.yield_budget.consume().await {
+            self.metrics.yields.inc();
+        }
+
+        #[cfg(feature = "debug-assertions")]
+        self.assert_invariants().await;
+    }
+
+    /// Panics with a summary of the reactor's state if any invariant is violated.
+    #[cfg(feature = "debug-assertions")]
+    async fn assert_invariants(&self) {
+        let mut violations = self.reactor.check_invariants();
+        violations.extend(invariants::check_responder_ages(
+            *invariants::MAX_RESPONDER_AGE,
+        ));
+        if violations.is_empty() {
+            return;
+        }
+        let summary = invariants::StateSummary {
+            event_count: self.event_count,
+            queued_events: self.scheduler.count_by(R::event_component).await,
+            components: self.component_stats.snapshot(),
+            violations,
+        };
+        let summary = serde_json::to_string_pretty(&summary)
+            .unwrap_or_else(|error| format!("{:?} (failed to serialize: {})", summary, error));
+        panic!("reactor invariants violated: {}", summary);
     }
 
     /// Gets both the allocated and total memory from sys-info + jemalloc
@@ -530,11 +984,18 @@ where
         Some(AllocatedMem { allocated, total })
     }
 
-    /// Handles dumping queue contents to files in /tmp.
+    /// Handles dumping queue contents to timestamped files in the queue dump directory.
+    ///
+    /// Besides the JSON snapshot and the debug dump, the events the reactor can persist are saved
+    /// to a replayable dump, see `replay_dump`.
     async fn dump_queues(&mut self) {
         let timestamp = Timestamp::now();
         self.last_queue_dump = Some(timestamp);
-        let output_fn = format!("/tmp/queue_dump-{}.json", timestamp);
+        let output_fn = self
+            .queue_dump_dir
+            .join(format!("queue_dump-{}.json", timestamp))
+            .display()
+            .to_string();
         let mut serializer = serde_json::Serializer::pretty(match File::create(&output_fn) {
             Ok(file) => file,
             Err(error) => {
@@ -548,7 +1009,11 @@ where
             return;
         }
 
-        let debug_dump_filename = format!("/tmp/queue_dump_debug-{}.txt", timestamp);
+        let debug_dump_filename = self
+            .queue_dump_dir
+            .join(format!("queue_dump_debug-{}.txt", timestamp))
+            .display()
+            .to_string();
         let mut file = match File::create(&debug_dump_filename) {
             Ok(file) => file,
             Err(error) => {
@@ -560,6 +1025,25 @@ where
             warn!(%error, "could not serialize debug snapshot to {}", debug_dump_filename);
             return;
         }
+
+        let replay_dump_filename = self
+            .queue_dump_dir
+            .join(format!("queue_dump_replay-{}.bin", timestamp));
+        let events = self.scheduler.collect(R::persisted_form).await;
+        if let Err(error) = queue_persistence::save(&replay_dump_filename, events) {
+            warn!(
+                %error,
+                "could not save replayable snapshot to {}",
+                replay_dump_filename.display()
+            );
+            return;
+        }
+        info!(
+            %output_fn,
+            %debug_dump_filename,
+            replay_dump_filename = %replay_dump_filename.display(),
+            "dumped event queues"
+        );
     }
 
     /// Processes a single event if there is one, returns `None` otherwise.
@@ -573,12 +1057,147 @@ where
         }
     }
 
-    /// Runs the reactor until `is_stopped()` returns true.
+    /// Runs the reactor until `is_stopped()` returns true or termination of the node has been
+    /// requested.
     #[inline]
     pub async fn run(&mut self, rng: &mut NodeRng) {
+        watchdog::reactor_started();
         while !self.reactor.is_stopped() {
+            if crate::TERMINATION_REQUESTED.load(Ordering::SeqCst) {
+                info!("termination requested, stopping reactor");
+                break;
+            }
             self.crank(rng).await;
         }
+        watchdog::reactor_stopped();
+    }
+
+    /// Processes up to `max_events` events, stopping earlier if the reactor stops.
+    ///
+    /// Returns the number of events processed.
+    pub async fn run_for(&mut self, rng: &mut NodeRng, max_events: u64) -> u64 {
+        let mut processed = 0;
+        while processed < max_events && !self.reactor.is_stopped() {
+            if crate::TERMINATION_REQUESTED.load(Ordering::SeqCst) {
+                info!("termination requested, stopping reactor");
+                break;
+            }
+            self.crank(rng).await;
+            processed += 1;
+        }
+        processed
+    }
+
+    /// Takes the reactor through the stages of an ordered shutdown, see the `shutdown` module.
+    ///
+    /// Keeps processing events until each stage completes or its timeout elapses.
+    pub async fn shutdown(&mut self, rng: &mut NodeRng, config: &ShutdownConfig) {
+        for &stage in ShutdownStage::ALL.iter() {
+            let timeout = config.timeout(stage);
+            info!(%stage, ?timeout, "starting shutdown stage");
+            let started = Instant::now();
+
+            let event_queue = EventQueueHandle::new(
+                self.scheduler,
+                self.component_stats,
+                self.subscriptions.get(),
+                self.load_controller,
+            );
+            let effects = self
+                .reactor
+                .begin_shutdown_stage(EffectBuilder::new(event_queue), stage);
+            process_effects(self.scheduler, effects).await;
+
+            loop {
+                if self.reactor.is_shutdown_stage_complete(stage) {
+                    info!(%stage, elapsed = ?started.elapsed(), "completed shutdown stage");
+                    break;
+                }
+                if started.elapsed() >= timeout {
+                    warn!(%stage, ?timeout, "shutdown stage timed out, continuing regardless");
+                    break;
+                }
+                if self.try_crank(rng).await.is_none() {
+                    tokio::time::delay_for(SHUTDOWN_POLL_INTERVAL).await;
+                }
+            }
+        }
+        info!("shutdown complete");
+    }
+
+    /// Drains the event queues and saves the events the reactor can replay to `path`.
+    ///
+    /// Meant to be called once the reactor has stopped, all other queued events are discarded.
+    pub async fn persist_queues<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<(), QueuePersistenceError> {
+        let drained = self.scheduler.drain().await;
+        let total = drained.len();
+        let events: Vec<_> = drained
+            .into_iter()
+            .filter_map(|(event, queue)| R::persist_event(event).ok().map(|event| (queue, event)))
+            .collect();
+        let persisted = events.len();
+        queue_persistence::save(path.as_ref(), events)?;
+        info!(
+            path = %path.as_ref().display(),
+            persisted,
+            discarded = total - persisted,
+            "persisted event queues"
+        );
+        Ok(())
+    }
+
+    /// Schedules the events saved to `path` by `persist_queues`, if there are any.
+    pub async fn replay_queues<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<(), QueuePersistenceError> {
+        let events = queue_persistence::load(path.as_ref())?;
+        let total = events.len();
+        let replayed = self.schedule_persisted(events).await;
+        if total > 0 {
+            info!(
+                path = %path.as_ref().display(),
+                replayed,
+                skipped = total - replayed,
+                "replayed persisted event queues"
+            );
+        }
+        Ok(())
+    }
+
+    /// Schedules the events of the replayable queue dump at `path`, see `dump_queues`, leaving the
+    /// dump in place.
+    ///
+    /// Returns the number of events scheduled.
+    pub async fn replay_dump<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<usize, QueuePersistenceError> {
+        let events = queue_persistence::read(path.as_ref())?;
+        let total = events.len();
+        let replayed = self.schedule_persisted(events).await;
+        info!(
+            path = %path.as_ref().display(),
+            replayed,
+            skipped = total - replayed,
+            "replaying queue dump"
+        );
+        Ok(replayed)
+    }
+
+    /// Schedules the persisted events this reactor can restore, returning their number.
+    async fn schedule_persisted(&mut self, events: Vec<(QueueKind, PersistedEvent)>) -> usize {
+        let mut scheduled = 0;
+        for (queue, event) in events {
+            if let Some(event) = R::restore_event(event) {
+                self.scheduler.push(event, queue).await;
+                scheduled += 1;
+            }
+        }
+        scheduled
     }
 
     /// Returns a reference to the reactor.
@@ -605,16 +1224,38 @@ where
 async fn process_effects<Ev>(scheduler: &'static Scheduler<Ev>, effects: Effects<Ev>)
 where
     Ev: Send + 'static,
+{
+    process_effects_on(None, scheduler, effects).await
+}
+
+/// Spawns tasks that will process the given effects on `runtime`, or on the current one if `None`.
+#[inline]
+async fn process_effects_on<Ev>(
+    runtime: Option<&Handle>,
+    scheduler: &'static Scheduler<Ev>,
+    effects: Effects<Ev>,
+) where
+    Ev: Send + 'static,
 {
     // TODO: Properly carry around priorities.
     let queue_kind = QueueKind::default();
 
     for effect in effects {
-        tokio::spawn(async move {
+        // Effects run within the span they were created in, e.g. that of the deploy concerned.
+        let task = async move {
             for event in effect.await {
                 scheduler.push(event, queue_kind).await
             }
-        });
+        }
+        .in_current_span();
+        match runtime {
+            Some(runtime) => {
+                runtime.spawn(task);
+            }
+            None => {
+                tokio::spawn(task);
+            }
+        }
     }
 }
 
@@ -0,0 +1,110 @@
+//! Per-component statistics of event handling.
+//!
+//! The runner attributes every event it dispatches to a component, see `Reactor::event_component`,
+//! and records how long handling it took and the last error or warning logged while doing so. The
+//! number of queued events per component is refreshed whenever the runner updates its metrics.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use crate::types::ComponentStatus;
+
+/// Statistics of a single component.
+#[derive(Debug, Default)]
+struct Entry {
+    /// Number of events handled.
+    events: u64,
+    /// Total time spent handling events, in nanoseconds.
+    handling_ns: u64,
+    /// The last error or warning logged while handling an event.
+    last_error: Option<String>,
+    /// Number of events waiting in the queues as of the last refresh.
+    queued: usize,
+}
+
+/// Statistics of all components of a reactor, shared between its runner and event queue handles.
+#[derive(Debug, Default)]
+pub(crate) struct ComponentStats(Mutex<BTreeMap<&'static str, Entry>>);
+
+impl ComponentStats {
+    /// Records the handling of an event by `component`.
+    pub(super) fn record_dispatch(
+        &self,
+        component: &'static str,
+        handling_ns: u64,
+        problem: Option<String>,
+    ) {
+        let mut entries = self.0.lock().expect("component stats lock poisoned");
+        let entry = entries.entry(component).or_default();
+        entry.events += 1;
+        entry.handling_ns = entry.handling_ns.saturating_add(handling_ns);
+        if problem.is_some() {
+            entry.last_error = problem;
+        }
+    }
+
+    /// Replaces the queued event counts, components missing from `queued` have none.
+    pub(super) fn set_queued(&self, queued: HashMap<&'static str, usize>) {
+        let mut entries = self.0.lock().expect("component stats lock poisoned");
+        for entry in entries.values_mut() {
+            entry.queued = 0;
+        }
+        for (component, count) in queued {
+            entries.entry(component).or_default().queued = count;
+        }
+    }
+
+    /// Returns the statistics of every component seen so far, ordered by name.
+    pub(crate) fn snapshot(&self) -> Vec<ComponentStatus> {
+        let entries = self.0.lock().expect("component stats lock poisoned");
+        entries
+            .iter()
+            .map(|(name, entry)| ComponentStatus {
+                name: name.to_string(),
+                events_processed: entry.events,
+                average_handling_time_ns: entry.handling_ns.checked_div(entry.events).unwrap_or(0),
+                last_error: entry.last_error.clone(),
+                queued_events: entry.queued as u64,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_aggregate_per_component() {
+        let stats = ComponentStats::default();
+        stats.record_dispatch("storage", 100, None);
+        stats.record_dispatch("storage", 300, Some("disk full".to_string()));
+        stats.record_dispatch("storage", 200, None);
+        stats.record_dispatch("consensus", 50, None);
+        stats.set_queued(vec![("consensus", 7), ("network", 3)].into_iter().collect());
+
+        let snapshot = stats.snapshot();
+        let names: Vec<_> = snapshot.iter().map(|status| status.name.as_str()).collect();
+        assert_eq!(names, vec!["consensus", "network", "storage"]);
+
+        let storage = &snapshot[2];
+        assert_eq!(storage.events_processed, 3);
+        assert_eq!(storage.average_handling_time_ns, 200);
+        assert_eq!(storage.last_error.as_deref(), Some("disk full"));
+        assert_eq!(storage.queued_events, 0);
+
+        // A component only seen in the queues has not handled anything yet.
+        let network = &snapshot[1];
+        assert_eq!(network.events_processed, 0);
+        assert_eq!(network.average_handling_time_ns, 0);
+        assert_eq!(network.queued_events, 3);
+
+        stats.set_queued(HashMap::new());
+        assert!(stats
+            .snapshot()
+            .iter()
+            .all(|status| status.queued_events == 0));
+    }
+}
@@ -25,7 +25,8 @@ impl EventQueueMetrics {
         let mut event_queue_gauges: HashMap<QueueKind, IntGauge> = HashMap::new();
         for queue_kind in event_queue_handle.event_queues_counts().keys() {
             let key = format!("scheduler_queue_{}_count", queue_kind.metrics_name());
-            let queue_event_counter = IntGauge::new(key, "Event in the queue.".to_string())?;
+            let help = format!("number of events in the {} queue", queue_kind);
+            let queue_event_counter = IntGauge::new(key, help)?;
             registry.register(Box::new(queue_event_counter.clone()))?;
             let result = event_queue_gauges.insert(*queue_kind, queue_event_counter);
             assert!(result.is_none(), "Map keys should not be overwritten.");
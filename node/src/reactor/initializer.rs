@@ -1,6 +1,9 @@
 //! Reactor used to initialize a node.
 
-use std::fmt::{self, Display, Formatter};
+use std::{
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
 
 use datasize::DataSize;
 use derive_more::From;
@@ -135,7 +138,8 @@ impl reactor::Reactor for Reactor {
         let effect_builder = EffectBuilder::new(event_queue);
 
         let storage_config = config.map_ref(|cfg| cfg.storage.clone());
-        let storage = Storage::new(&storage_config)?;
+        let mut storage = Storage::new(&storage_config, registry)?;
+        storage.check_version(&chainspec)?;
 
         let contract_runtime =
             ContractRuntime::new(storage_config, &config.value().contract_runtime, registry)?;
@@ -181,4 +185,8 @@ impl reactor::Reactor for Reactor {
     fn is_stopped(&mut self) -> bool {
         self.chainspec_loader.is_stopped()
     }
+
+    fn queue_dump_dir(cfg: &Self::Config) -> Option<PathBuf> {
+        Some(cfg.with_dir(cfg.value().storage.path.clone()))
+    }
 }
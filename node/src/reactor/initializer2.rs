@@ -15,7 +15,7 @@ reactor!(Initializer {
            .clone()
            .load(cfg.dir())
            .expect("TODO: return proper error when chainspec cannot be loaded"), effect_builder);
-    storage = Storage(&cfg.map_ref(|cfg| cfg.storage.clone()));
+    storage = Storage(&cfg.map_ref(|cfg| cfg.storage.clone()), registry);
     contract_runtime = ContractRuntime(cfg.map_ref(|cfg| cfg.storage.clone()),
 &cfg.value().contract_runtime, registry);   }
 
@@ -0,0 +1,173 @@
+//! Reactor-level invariant assertions.
+//!
+//! With the `debug-assertions` feature enabled, the runner checks a set of cross-component
+//! invariants after every crank: those of the reactor itself, see `Reactor::check_invariants`,
+//! and that no responder has been waiting for a response for longer than a maximum age. Any
+//! violation panics with a serialized summary of the reactor's state, so that logic bugs surface
+//! in soak tests rather than as a node slowly grinding to a halt.
+//!
+//! The maximum responder age defaults to five minutes and can be overridden by setting the env var
+//! `CL_DEBUG_MAX_RESPONDER_AGE_SECS=<SECONDS>`.
+//!
+//! This feature is meant for testing only, never enable it for a production build.
+
+use std::{
+    any,
+    collections::{BTreeMap, HashMap},
+    env,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::types::ComponentStatus;
+
+const DEFAULT_MAX_RESPONDER_AGE: Duration = Duration::from_secs(5 * 60);
+const MAX_RESPONDER_AGE_ENV_VAR: &str = "CL_DEBUG_MAX_RESPONDER_AGE_SECS";
+
+/// The maximum time a responder may be outstanding for.
+pub(super) static MAX_RESPONDER_AGE: Lazy<Duration> = Lazy::new(|| {
+    env::var(MAX_RESPONDER_AGE_ENV_VAR)
+        .map(|age_str| {
+            let age_secs = u64::from_str(&age_str).unwrap_or_else(|error| {
+                panic!(
+                    "can't parse env var {}={} as a u64: {}",
+                    MAX_RESPONDER_AGE_ENV_VAR, age_str, error
+                )
+            });
+            Duration::from_secs(age_secs)
+        })
+        .unwrap_or(DEFAULT_MAX_RESPONDER_AGE)
+});
+
+/// The ID for the next responder to be tracked.
+static NEXT_RESPONDER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The creation time and response type of every outstanding responder, by ID.
+///
+/// IDs are assigned in order of creation, so the oldest responders come first.
+static OUTSTANDING_RESPONDERS: Lazy<Mutex<Responders>> = Lazy::new(Default::default);
+
+/// The creation time and response type of responders, by ID.
+type Responders = BTreeMap<u64, (Instant, &'static str)>;
+
+/// A violated invariant.
+#[derive(Clone, Debug, Serialize)]
+pub struct InvariantViolation {
+    /// A short description of the invariant.
+    pub invariant: &'static str,
+    /// The state which violates it.
+    pub details: String,
+}
+
+impl Display for InvariantViolation {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}: {}", self.invariant, self.details)
+    }
+}
+
+/// Registers a responder as outstanding for as long as it is alive.
+#[derive(Debug)]
+pub(crate) struct TrackedResponder(u64);
+
+impl TrackedResponder {
+    /// Starts tracking a responder for values of type `T`.
+    pub(crate) fn new<T>() -> Self {
+        let id = NEXT_RESPONDER_ID.fetch_add(1, Ordering::SeqCst);
+        OUTSTANDING_RESPONDERS
+            .lock()
+            .expect("outstanding responders lock poisoned")
+            .insert(id, (Instant::now(), any::type_name::<T>()));
+        TrackedResponder(id)
+    }
+}
+
+impl Drop for TrackedResponder {
+    fn drop(&mut self) {
+        OUTSTANDING_RESPONDERS
+            .lock()
+            .expect("outstanding responders lock poisoned")
+            .remove(&self.0);
+    }
+}
+
+/// Returns a violation if any responder has been outstanding for longer than `max_age`.
+pub(super) fn check_responder_ages(max_age: Duration) -> Option<InvariantViolation> {
+    let responders = OUTSTANDING_RESPONDERS
+        .lock()
+        .expect("outstanding responders lock poisoned");
+    check_ages(&responders, Instant::now(), max_age)
+}
+
+fn check_ages(
+    responders: &Responders,
+    now: Instant,
+    max_age: Duration,
+) -> Option<InvariantViolation> {
+    let stale: Vec<_> = responders
+        .values()
+        .take_while(|(created, _)| now.duration_since(*created) > max_age)
+        .collect();
+    let (oldest_created, oldest_type) = stale.first()?;
+    Some(InvariantViolation {
+        invariant: "no responder outstanding for longer than the maximum age",
+        details: format!(
+            "{} responders older than {:?}, the oldest a responder({}) created {:?} ago",
+            stale.len(),
+            max_age,
+            oldest_type,
+            now.duration_since(*oldest_created)
+        ),
+    })
+}
+
+/// The state of the reactor when invariants are violated.
+#[derive(Debug, Serialize)]
+pub(super) struct StateSummary {
+    /// The number of events processed.
+    pub(super) event_count: usize,
+    /// The number of queued events, by the component they are dispatched to.
+    pub(super) queued_events: HashMap<&'static str, usize>,
+    /// The event handling statistics of all components.
+    pub(super) components: Vec<ComponentStatus>,
+    /// The violated invariants.
+    pub(super) violations: Vec<InvariantViolation>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_responders_outstanding_for_too_long() {
+        let now = Instant::now();
+        let max_age = Duration::from_secs(60);
+        let mut responders = Responders::new();
+        assert!(check_ages(&responders, now, max_age).is_none());
+
+        responders.insert(0, (now, "u32"));
+        assert!(check_ages(&responders, now, max_age).is_none());
+
+        let later = now + Duration::from_secs(90);
+        responders.insert(1, (now + Duration::from_secs(45), "u64"));
+        let violation = check_ages(&responders, later, max_age).expect("should report u32");
+        assert!(violation.details.starts_with("1 responders"));
+        assert!(violation.details.contains("responder(u32)"));
+    }
+
+    #[test]
+    fn should_stop_tracking_dropped_responders() {
+        let responder = TrackedResponder::new::<u32>();
+        let id = responder.0;
+        assert!(OUTSTANDING_RESPONDERS.lock().unwrap().contains_key(&id));
+        drop(responder);
+        assert!(!OUTSTANDING_RESPONDERS.lock().unwrap().contains_key(&id));
+    }
+}
@@ -6,13 +6,14 @@ use std::{
     collections::BTreeMap,
     env,
     fmt::{self, Display, Formatter},
+    path::PathBuf,
 };
 
 use datasize::DataSize;
 use derive_more::From;
 use prometheus::Registry;
 use serde::Serialize;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 
 use casper_types::{PublicKey, U512};
 
@@ -39,8 +40,8 @@ use crate::{
         linear_chain,
         metrics::Metrics,
         network::{self, Network, ENABLE_SMALL_NET_ENV_VAR},
-        rest_server::{self, RestServer},
-        small_network::{self, GossipedAddress, SmallNetwork},
+        rest_server::{self, Readiness, RestServer},
+        small_network::{self, ChainInfo, GossipedAddress, SmallNetwork},
         storage::{self, Storage},
         Component,
     },
@@ -59,14 +60,15 @@ use crate::{
     },
     protocol::Message,
     reactor::{
-        self,
-        event_queue_metrics::EventQueueMetrics,
-        initializer,
+        self, initializer,
         validator::{self, Error, ValidatorInitConfig},
-        EventQueueHandle, Finalize,
+        EventQueueHandle, Finalize, PersistedEvent, SpilloverConfig, YieldPolicyConfig,
     },
-    types::{Block, BlockByHeight, BlockHeader, Deploy, NodeId, ProtoBlock, Tag, Timestamp},
-    utils::{Source, WithDir},
+    types::{
+        Block, BlockByHeight, BlockHeader, Deploy, DeployHash, FeatureFlags, NodeId, ProtoBlock,
+        Tag, Timestamp,
+    },
+    utils::{bounded, Source, WithDir},
     NodeRng,
 };
 
@@ -346,8 +348,6 @@ pub struct Reactor {
     #[data_size(skip)]
     pub(super) deploy_acceptor: DeployAcceptor,
     #[data_size(skip)]
-    event_queue_metrics: EventQueueMetrics,
-    #[data_size(skip)]
     pub(super) rest_server: RestServer,
     #[data_size(skip)]
     pub(super) event_stream_server: EventStreamServer,
@@ -372,6 +372,12 @@ impl reactor::Reactor for Reactor {
     ) -> Result<(Self, Effects<Self::Event>), Self::Error> {
         let (root, initializer) = initializer.into_parts();
 
+        // The REST server answers from storage and the chainspec, which are only ready once the
+        // initializer completed, so it must not start listening before.
+        if !initializer.stopped_successfully() {
+            return Err(Error::InitializationIncomplete);
+        }
+
         let initializer::Reactor {
             config,
             chainspec_loader,
@@ -380,12 +386,15 @@ impl reactor::Reactor for Reactor {
         } = initializer;
 
         // TODO: Remove wrapper around Reactor::Config instead.
-        let (_, config) = config.into_parts();
+        let (_, mut config) = config.into_parts();
+        config.network.resolve_ban_list_files(&root);
+        config.event_queue_spillover.resolve_path(&root);
+        config.block_executor.resolve_path(&root);
+        config.notifier.resolve_path(&root);
+        config.binary_port.resolve_path(&root);
 
         let memory_metrics = MemoryMetrics::new(registry.clone())?;
 
-        let event_queue_metrics = EventQueueMetrics::new(registry.clone(), event_queue)?;
-
         let metrics = Metrics::new(registry.clone());
 
         let network_config = network::Config::from(&config.network);
@@ -395,15 +404,18 @@ impl reactor::Reactor for Reactor {
             chainspec_loader.chainspec(),
             false,
         )?;
-        let genesis_config_hash = chainspec_loader.chainspec().hash();
+        let features = FeatureFlags::from_config(&config);
         let (small_network, small_network_effects) = SmallNetwork::new(
             event_queue,
             config.network.clone(),
-            genesis_config_hash,
+            registry,
+            ChainInfo::from_chainspec(chainspec_loader.chainspec()),
+            features.clone(),
+            None,
             false,
         )?;
 
-        let linear_chain_fetcher = Fetcher::new(config.fetcher);
+        let linear_chain_fetcher = Fetcher::new("linear_chain_fetcher", config.fetcher, registry)?;
 
         let mut effects = reactor::wrap_effects(Event::Network, network_effects);
         effects.extend(reactor::wrap_effects(
@@ -434,7 +446,13 @@ impl reactor::Reactor for Reactor {
             Some(hash) => info!("Synchronizing linear chain from: {:?}", hash),
         }
 
-        let rest_server = RestServer::new(config.rest_server.clone(), effect_builder)?;
+        let rest_server = RestServer::new(
+            config.rest_server.clone(),
+            config.maintenance.clone(),
+            features,
+            Readiness::Syncing,
+            effect_builder,
+        )?;
 
         let event_stream_server =
             EventStreamServer::new(config.event_stream_server.clone(), effect_builder)?;
@@ -445,17 +463,22 @@ impl reactor::Reactor for Reactor {
             block_validator_effects,
         ));
 
-        let deploy_fetcher = Fetcher::new(config.fetcher);
+        let deploy_fetcher = Fetcher::new("deploy_fetcher", config.fetcher, registry)?;
 
-        let block_by_height_fetcher = Fetcher::new(config.fetcher);
+        let block_by_height_fetcher =
+            Fetcher::new("block_by_height_fetcher", config.fetcher, registry)?;
 
-        let deploy_acceptor = DeployAcceptor::new(config.deploy_acceptor);
+        let deploy_acceptor = DeployAcceptor::new(config.deploy_acceptor.clone());
 
         let genesis_state_root_hash = chainspec_loader
             .genesis_state_root_hash()
             .expect("Should have Genesis state root hash");
 
-        let block_executor = BlockExecutor::new(genesis_state_root_hash, registry.clone());
+        let block_executor = BlockExecutor::new(
+            genesis_state_root_hash,
+            &config.block_executor,
+            registry.clone(),
+        );
 
         let linear_chain = linear_chain::LinearChain::new();
 
@@ -467,8 +490,12 @@ impl reactor::Reactor for Reactor {
             .map(|(pk, motes)| (pk, motes.value()))
             .collect();
 
-        let linear_chain_sync =
-            LinearChainSync::new(registry, init_hash, validator_weights.clone())?;
+        let linear_chain_sync = LinearChainSync::new(
+            config.linear_chain_sync,
+            registry,
+            init_hash,
+            validator_weights.clone(),
+        )?;
 
         // Used to decide whether era should be activated.
         let timestamp = Timestamp::now();
@@ -507,7 +534,6 @@ impl reactor::Reactor for Reactor {
                 init_consensus_effects,
                 block_by_height_fetcher,
                 deploy_acceptor,
-                event_queue_metrics,
                 rest_server,
                 event_stream_server,
                 memory_metrics,
@@ -554,13 +580,14 @@ impl reactor::Reactor for Reactor {
                     tag: Tag::Block,
                     serialized_item,
                 } => {
-                    let block = match bincode::deserialize(&serialized_item) {
-                        Ok(block) => Box::new(block),
-                        Err(err) => {
-                            error!("failed to decode block from {}: {}", sender, err);
-                            return Effects::new();
-                        }
-                    };
+                    let block =
+                        match bounded::from_bincode(&serialized_item, bounded::Limits::NETWORK) {
+                            Ok(block) => Box::new(block),
+                            Err(err) => {
+                                error!("failed to decode block from {}: {}", sender, err);
+                                return Effects::new();
+                            }
+                        };
                     let event = fetcher::Event::GotRemotely {
                         item: block,
                         source: Source::Peer(sender),
@@ -572,7 +599,7 @@ impl reactor::Reactor for Reactor {
                     serialized_item,
                 } => {
                     let block_at_height: BlockByHeight =
-                        match bincode::deserialize(&serialized_item) {
+                        match bounded::from_bincode(&serialized_item, bounded::Limits::NETWORK) {
                             Ok(maybe_block) => maybe_block,
                             Err(err) => {
                                 error!("failed to decode block from {}: {}", sender, err);
@@ -596,13 +623,14 @@ impl reactor::Reactor for Reactor {
                     tag: Tag::Deploy,
                     serialized_item,
                 } => {
-                    let deploy = match bincode::deserialize(&serialized_item) {
-                        Ok(deploy) => Box::new(deploy),
-                        Err(err) => {
-                            error!("failed to decode deploy from {}: {}", sender, err);
-                            return Effects::new();
-                        }
-                    };
+                    let deploy =
+                        match bounded::from_bincode(&serialized_item, bounded::Limits::NETWORK) {
+                            Ok(deploy) => Box::new(deploy),
+                            Err(err) => {
+                                error!("failed to decode deploy from {}: {}", sender, err);
+                                return Effects::new();
+                            }
+                        };
                     let event = Event::DeployAcceptor(deploy_acceptor::Event::Accept {
                         deploy,
                         source: Source::Peer(sender),
@@ -621,6 +649,14 @@ impl reactor::Reactor for Reactor {
                     warn!("finality signatures not handled in joiner reactor");
                     Effects::new()
                 }
+                Message::SubmitDeploy(deploy) => {
+                    debug!(
+                        deploy_hash = %deploy.id(),
+                        %sender,
+                        "deploy submissions not handled in joiner reactor"
+                    );
+                    Effects::new()
+                }
                 other => {
                     warn!(?other, "network announcement ignored.");
                     Effects::new()
@@ -711,16 +747,6 @@ impl reactor::Reactor for Reactor {
                 let mut effects = Effects::new();
                 let block_hash = *block.hash();
 
-                // send to linear chain
-                let reactor_event = Event::LinearChain(linear_chain::Event::LinearChainBlock {
-                    block: Box::new(block),
-                    execution_results: execution_results
-                        .iter()
-                        .map(|(hash, (_header, results))| (*hash, results.clone()))
-                        .collect(),
-                });
-                effects.extend(self.dispatch_event(effect_builder, rng, reactor_event));
-
                 // send to event stream
                 for (deploy_hash, (deploy_header, execution_result)) in execution_results {
                     let reactor_event =
@@ -752,10 +778,6 @@ impl reactor::Reactor for Reactor {
                         linear_chain_sync::Event::BlockHandled(block_header),
                     ),
                 ),
-                ConsensusAnnouncement::Finalized(_) => {
-                    // A block was finalized.
-                    Effects::new()
-                }
                 ConsensusAnnouncement::Fault {
                     era_id,
                     public_key,
@@ -777,6 +799,30 @@ impl reactor::Reactor for Reactor {
                     warn!("disconnecting from a given peer not yet implemented.");
                     Effects::new()
                 }
+                ConsensusAnnouncement::PartitionStatus {
+                    possibly_partitioned,
+                    ..
+                } => self.dispatch_event(
+                    effect_builder,
+                    rng,
+                    Event::RestServer(rest_server::Event::PartitionStatus {
+                        possibly_partitioned,
+                    }),
+                ),
+                ConsensusAnnouncement::ParticipationStatus {
+                    participation_degraded,
+                    ..
+                } => self.dispatch_event(
+                    effect_builder,
+                    rng,
+                    Event::RestServer(rest_server::Event::ParticipationStatus {
+                        participation_degraded,
+                    }),
+                ),
+                ConsensusAnnouncement::EquivocationRisk { .. } => {
+                    // The operator is only notified once the validator reactor runs.
+                    Effects::new()
+                }
             },
             Event::BlockProposerRequest(request) => {
                 // Consensus component should not be trying to create new blocks during joining
@@ -858,10 +904,111 @@ impl reactor::Reactor for Reactor {
         self.linear_chain_sync.is_synced()
     }
 
-    fn update_metrics(&mut self, event_queue_handle: EventQueueHandle<Self::Event>) {
+    fn update_metrics(&mut self, _event_queue_handle: EventQueueHandle<Self::Event>) {
         self.memory_metrics.estimate(&self);
-        self.event_queue_metrics
-            .record_event_queue_counts(&event_queue_handle);
+    }
+
+    fn event_component(event: &Self::Event) -> &'static str {
+        match event {
+            Event::Network(_) | Event::SmallNetwork(_) | Event::NetworkInfoRequest(_) => "network",
+            Event::Storage(_) => "storage",
+            Event::RestServer(_) => "rest_server",
+            Event::EventStreamServer(_) => "event_stream_server",
+            Event::MetricsRequest(_) => "metrics",
+            Event::ChainspecLoader(_) | Event::ChainspecLoaderRequest(_) => "chainspec_loader",
+            Event::BlockFetcher(_) | Event::BlockFetcherRequest(_) => "block_fetcher",
+            Event::BlockByHeightFetcher(_) | Event::BlockByHeightFetcherRequest(_) => {
+                "block_by_height_fetcher"
+            }
+            Event::DeployFetcher(_) | Event::DeployFetcherRequest(_) => "deploy_fetcher",
+            Event::DeployAcceptor(_) => "deploy_acceptor",
+            Event::BlockValidator(_)
+            | Event::BlockValidatorRequest(_)
+            | Event::ProtoBlockValidatorRequest(_) => "block_validator",
+            Event::LinearChainSync(_) => "linear_chain_sync",
+            Event::BlockExecutor(_) | Event::BlockExecutorRequest(_) => "block_executor",
+            Event::BlockProposerRequest(_) => "block_proposer",
+            Event::ContractRuntime(_) => "contract_runtime",
+            Event::LinearChain(_) => "linear_chain",
+            Event::Consensus(_) => "consensus",
+            Event::AddressGossiper(_) => "address_gossiper",
+            // Announcements are dispatched to several components at once.
+            Event::NetworkAnnouncement(_)
+            | Event::BlockExecutorAnnouncement(_)
+            | Event::ConsensusAnnouncement(_)
+            | Event::AddressGossiperAnnouncement(_)
+            | Event::DeployAcceptorAnnouncement(_)
+            | Event::LinearChainAnnouncement(_) => "announcements",
+        }
+    }
+
+    fn event_deploy_hash(event: &Self::Event) -> Option<DeployHash> {
+        match event {
+            Event::DeployAcceptor(event) => event.deploy_hash(),
+            Event::DeployFetcher(event) => event.item_id(),
+            Event::BlockExecutor(event) => event.deploy_hash(),
+            Event::DeployAcceptorAnnouncement(DeployAcceptorAnnouncement::AcceptedNewDeploy {
+                deploy,
+                ..
+            })
+            | Event::DeployAcceptorAnnouncement(DeployAcceptorAnnouncement::InvalidDeploy {
+                deploy,
+                ..
+            }) => Some(*deploy.id()),
+            _ => None,
+        }
+    }
+
+    fn persist_event(event: Self::Event) -> Result<PersistedEvent, Self::Event> {
+        match event {
+            Event::NetworkAnnouncement(NetworkAnnouncement::MessageReceived {
+                sender,
+                payload,
+            }) => Ok(PersistedEvent::MessageReceived { sender, payload }),
+            other => Err(other),
+        }
+    }
+
+    fn persisted_form(event: &Self::Event) -> Option<PersistedEvent> {
+        match event {
+            Event::NetworkAnnouncement(NetworkAnnouncement::MessageReceived {
+                sender,
+                payload,
+            }) => Some(PersistedEvent::MessageReceived {
+                sender: sender.clone(),
+                payload: payload.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn restore_event(event: PersistedEvent) -> Option<Self::Event> {
+        match event {
+            PersistedEvent::MessageReceived { sender, payload } => {
+                Some(Event::NetworkAnnouncement(
+                    NetworkAnnouncement::MessageReceived { sender, payload },
+                ))
+            }
+            PersistedEvent::DeployReceived { .. } => None,
+        }
+    }
+
+    fn spillover_config(cfg: &Self::Config) -> Option<SpilloverConfig> {
+        let mut config = cfg.value().config.value().event_queue_spillover.clone();
+        config.resolve_path(cfg.dir());
+        Some(config)
+    }
+
+    fn yield_policy_config(cfg: &Self::Config) -> YieldPolicyConfig {
+        cfg.value().config.value().yield_policy.clone()
+    }
+
+    fn is_consensus_critical(event: &Self::Event) -> bool {
+        matches!(event, Event::Consensus(_))
+    }
+
+    fn queue_dump_dir(cfg: &Self::Config) -> Option<PathBuf> {
+        Some(cfg.value().storage.root().to_path_buf())
     }
 }
 
@@ -0,0 +1,197 @@
+//! Adaptive concurrency based on the load of the node.
+//!
+//! Components spreading work across peers, like the gossiper sending an item to several peers at
+//! once or the fetchers requesting items in parallel, scale their configured concurrency by a
+//! factor kept here, see `EffectBuilder::scale_concurrency`.
+//!
+//! The runner samples the number of queued events and the share of CPU time the process used at
+//! regular intervals. While either is high, the factor is cut multiplicatively, so a node under
+//! load quickly stops adding to it. Once both are low again the factor recovers additively, up to
+//! the configured values. Loads in between leave the factor as it is, to avoid oscillating.
+
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tracing::debug;
+
+/// Minimum time between two samples of the load.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The factor in per mille corresponding to the configured concurrency.
+const FULL_FACTOR_PERMILLE: u32 = 1000;
+
+/// The factor in per mille below which the concurrency is never cut.
+const MIN_FACTOR_PERMILLE: u32 = 100;
+
+/// Per mille the factor recovers by per sample once the load is low.
+const RECOVERY_PERMILLE: u32 = 50;
+
+/// Number of queued events above which the node is considered under load.
+const HIGH_QUEUED_EVENTS: usize = 5_000;
+
+/// Number of queued events below which the node is considered idle.
+const LOW_QUEUED_EVENTS: usize = 500;
+
+/// Share of the available CPU time used above which the node is considered under load.
+const HIGH_CPU_SHARE: f64 = 0.9;
+
+/// Share of the available CPU time used below which the node is considered idle.
+const LOW_CPU_SHARE: f64 = 0.6;
+
+/// The CPU time used by the process as of a point in time.
+#[derive(Clone, Copy, Debug)]
+struct CpuSample {
+    taken: Instant,
+    cpu_time: Duration,
+}
+
+/// The concurrency factor, shared between the runner and the event queue handles.
+#[derive(Debug)]
+pub(crate) struct LoadController {
+    /// The current factor in per mille.
+    factor_permille: AtomicU32,
+    /// The latest sample, which the CPU share of the next one is relative to.
+    last_sample: Mutex<Option<CpuSample>>,
+}
+
+impl Default for LoadController {
+    fn default() -> Self {
+        LoadController {
+            factor_permille: AtomicU32::new(FULL_FACTOR_PERMILLE),
+            last_sample: Mutex::new(None),
+        }
+    }
+}
+
+impl LoadController {
+    /// Scales a configured concurrency by the current factor, never below one.
+    pub(crate) fn scale(&self, nominal: usize) -> usize {
+        let factor = self.factor_permille.load(Ordering::Relaxed) as usize;
+        (nominal.saturating_mul(factor) / FULL_FACTOR_PERMILLE as usize).max(nominal.min(1))
+    }
+
+    /// Returns the current factor in percent.
+    pub(super) fn factor_percent(&self) -> u32 {
+        self.factor_permille.load(Ordering::Relaxed) / 10
+    }
+
+    /// Samples the load and adjusts the factor, unless the previous sample is too recent.
+    ///
+    /// Returns whether the factor changed.
+    pub(super) fn sample(&self, queued_events: usize) -> bool {
+        let now = Instant::now();
+        let mut last_sample = self.last_sample.lock().expect("load sample lock poisoned");
+        if let Some(previous) = *last_sample {
+            if now.duration_since(previous.taken) < SAMPLE_INTERVAL {
+                return false;
+            }
+        }
+        let cpu_time = match process_cpu_time() {
+            Some(cpu_time) => cpu_time,
+            None => return false,
+        };
+        let cpu_share = match *last_sample {
+            Some(previous) => {
+                let elapsed = now.duration_since(previous.taken).as_secs_f64();
+                let used = cpu_time
+                    .checked_sub(previous.cpu_time)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                used / (elapsed * available_cpus() as f64)
+            }
+            None => 0.0,
+        };
+        *last_sample = Some(CpuSample {
+            taken: now,
+            cpu_time,
+        });
+
+        let previous = self.factor_permille.load(Ordering::Relaxed);
+        let factor = next_factor(previous, queued_events, cpu_share);
+        if factor == previous {
+            return false;
+        }
+        debug!(
+            queued_events,
+            cpu_share,
+            factor_permille = factor,
+            "adjusted concurrency to load"
+        );
+        self.factor_permille.store(factor, Ordering::Relaxed);
+        true
+    }
+}
+
+/// Returns the factor following `factor`, given the sampled load.
+fn next_factor(factor: u32, queued_events: usize, cpu_share: f64) -> u32 {
+    if queued_events > HIGH_QUEUED_EVENTS || cpu_share > HIGH_CPU_SHARE {
+        (factor * 3 / 4).max(MIN_FACTOR_PERMILLE)
+    } else if queued_events < LOW_QUEUED_EVENTS && cpu_share < LOW_CPU_SHARE {
+        (factor + RECOVERY_PERMILLE).min(FULL_FACTOR_PERMILLE)
+    } else {
+        factor
+    }
+}
+
+/// Returns the user and system CPU time used by the process so far.
+fn process_cpu_time() -> Option<Duration> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let to_duration = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+    };
+    Some(to_duration(usage.ru_utime) + to_duration(usage.ru_stime))
+}
+
+/// Returns the number of CPUs available to the process, at least one.
+fn available_cpus() -> u32 {
+    let cpus = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) };
+    cpus.max(1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_cut_under_load_and_recover_once_idle() {
+        let mut factor = FULL_FACTOR_PERMILLE;
+        factor = next_factor(factor, HIGH_QUEUED_EVENTS + 1, 0.0);
+        assert_eq!(factor, 750);
+        factor = next_factor(factor, 0, 0.95);
+        assert_eq!(factor, 562);
+
+        // A moderate load neither cuts nor raises the factor.
+        assert_eq!(next_factor(factor, LOW_QUEUED_EVENTS, 0.0), factor);
+        assert_eq!(next_factor(factor, 0, 0.7), factor);
+
+        for _ in 0..20 {
+            factor = next_factor(factor, HIGH_QUEUED_EVENTS + 1, 1.0);
+        }
+        assert_eq!(factor, MIN_FACTOR_PERMILLE);
+
+        for _ in 0..20 {
+            factor = next_factor(factor, 0, 0.0);
+        }
+        assert_eq!(factor, FULL_FACTOR_PERMILLE);
+    }
+
+    #[test]
+    fn should_scale_concurrency_by_factor() {
+        let controller = LoadController::default();
+        assert_eq!(controller.scale(8), 8);
+        controller
+            .factor_permille
+            .store(MIN_FACTOR_PERMILLE, Ordering::Relaxed);
+        assert_eq!(controller.scale(20), 2);
+        assert_eq!(controller.scale(3), 1);
+        assert_eq!(controller.scale(0), 0);
+    }
+}
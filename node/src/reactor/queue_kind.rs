@@ -7,12 +7,24 @@
 use std::{fmt::Display, num::NonZeroUsize};
 
 use enum_iterator::IntoEnumIterator;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// Scheduling priority.
 ///
 /// Priorities are ordered from lowest to highest.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, IntoEnumIterator, PartialOrd, Ord, Serialize)]
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Eq,
+    PartialEq,
+    Hash,
+    IntoEnumIterator,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
 pub enum QueueKind {
     /// Network events that were initiated outside of this node.
     ///
@@ -0,0 +1,203 @@
+//! Persistence of event queues across restarts.
+//!
+//! Most events cannot outlive the reactor instance they were created by, since they carry
+//! responders or refer to in-flight state. Some however only carry data received from outside the
+//! node, such as messages from peers or deploys submitted by clients. On a graceful shutdown, the
+//! runner can drain its queues and save the events its reactor converts into a `PersistedEvent`,
+//! so they are replayed instead of silently discarded when the node starts again.
+//!
+//! The same encoding is used for the replayable part of a queue dump, which `read` loads without
+//! consuming it, so a dump can be replayed as often as needed while reproducing a crash.
+//!
+//! A queue snapshot is only replayed by the exact node version that wrote it, as the encoding of
+//! events is not stable across versions.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::info;
+
+use super::QueueKind;
+use crate::{
+    protocol::Message,
+    types::{Deploy, NodeId},
+    utils::{self, ReadFileError, WriteFileError},
+};
+
+/// Error persisting or restoring event queues.
+#[derive(Debug, Error)]
+pub enum QueuePersistenceError {
+    /// The snapshot file could not be read.
+    #[error(transparent)]
+    Read(#[from] ReadFileError),
+    /// The snapshot file could not be written.
+    #[error(transparent)]
+    Write(#[from] WriteFileError),
+    /// The snapshot could not be encoded or decoded.
+    #[error("could not encode or decode queue snapshot: {0}")]
+    Encoding(#[from] bincode::Error),
+    /// The snapshot was written by a different node version.
+    #[error("queue snapshot was written by node version {found}, expected {expected}")]
+    IncompatibleVersion {
+        /// The version of this node.
+        expected: String,
+        /// The version of the node which wrote the snapshot.
+        found: String,
+    },
+}
+
+/// An event which can be replayed after a restart.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PersistedEvent {
+    /// A message received from a peer.
+    MessageReceived {
+        /// The peer which sent the message.
+        sender: NodeId,
+        /// The message.
+        payload: Message,
+    },
+    /// A deploy submitted by a client.
+    ///
+    /// The client will not receive a response once the deploy is replayed.
+    DeployReceived {
+        /// The submitted deploy.
+        deploy: Box<Deploy>,
+    },
+}
+
+/// The persisted form of the event queues.
+#[derive(Debug, Serialize, Deserialize)]
+struct QueueSnapshot {
+    /// The version of the node which wrote the snapshot.
+    node_version: String,
+    /// The persisted events in the order they would have been processed in within each queue.
+    events: Vec<(QueueKind, PersistedEvent)>,
+}
+
+/// Writes the events to the snapshot file at `path`.
+pub(super) fn save<P: AsRef<Path>>(
+    path: P,
+    events: Vec<(QueueKind, PersistedEvent)>,
+) -> Result<(), QueuePersistenceError> {
+    let snapshot = QueueSnapshot {
+        node_version: crate::VERSION_STRING.clone(),
+        events,
+    };
+    let serialized = bincode::serialize(&snapshot)?;
+    utils::write_file(path, serialized)?;
+    Ok(())
+}
+
+/// Reads the events from the snapshot file at `path`, if there is one, and removes the file.
+///
+/// The file is removed even if it cannot be decoded or was written by another node version, so it
+/// is not retried on every start.
+pub(super) fn load<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<(QueueKind, PersistedEvent)>, QueuePersistenceError> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let serialized = utils::read_file(path)?;
+    if let Err(error) = std::fs::remove_file(path) {
+        info!(path = %path.display(), %error, "could not remove queue snapshot");
+    }
+    decode(&serialized)
+}
+
+/// Reads the events from the snapshot file at `path`, leaving the file in place.
+pub(super) fn read<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<(QueueKind, PersistedEvent)>, QueuePersistenceError> {
+    let serialized = utils::read_file(path.as_ref())?;
+    decode(&serialized)
+}
+
+fn decode(serialized: &[u8]) -> Result<Vec<(QueueKind, PersistedEvent)>, QueuePersistenceError> {
+    let snapshot: QueueSnapshot = bincode::deserialize(serialized)?;
+    if snapshot.node_version != *crate::VERSION_STRING {
+        return Err(QueuePersistenceError::IncompatibleVersion {
+            expected: crate::VERSION_STRING.clone(),
+            found: snapshot.node_version,
+        });
+    }
+    Ok(snapshot.events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_roundtrip_snapshot() {
+        let mut rng = crate::new_rng();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("queues.bin");
+
+        let deploy = Box::new(Deploy::random(&mut rng));
+        let message = Message::new_get_response(&*deploy).unwrap();
+        let sender = NodeId::random(&mut rng);
+        let events = vec![
+            (
+                QueueKind::NetworkIncoming,
+                PersistedEvent::MessageReceived {
+                    sender: sender.clone(),
+                    payload: message,
+                },
+            ),
+            (
+                QueueKind::Api,
+                PersistedEvent::DeployReceived {
+                    deploy: deploy.clone(),
+                },
+            ),
+        ];
+        save(&path, events).unwrap();
+
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        match &loaded[0] {
+            (QueueKind::NetworkIncoming, PersistedEvent::MessageReceived { sender: from, .. }) => {
+                assert_eq!(*from, sender)
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        match &loaded[1] {
+            (QueueKind::Api, PersistedEvent::DeployReceived { deploy: loaded }) => {
+                assert_eq!(*loaded, deploy)
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        // The snapshot is consumed by loading it, but not by reading it.
+        assert!(!path.exists());
+        save(
+            &path,
+            vec![(QueueKind::Api, PersistedEvent::DeployReceived { deploy })],
+        )
+        .unwrap();
+        assert_eq!(read(&path).unwrap().len(), 1);
+        assert!(path.exists());
+        assert_eq!(load(&path).unwrap().len(), 1);
+        assert!(load(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_reject_snapshot_of_other_version() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("queues.bin");
+        let snapshot = QueueSnapshot {
+            node_version: "0.0.0-other".to_string(),
+            events: Vec::new(),
+        };
+        utils::write_file(&path, bincode::serialize(&snapshot).unwrap()).unwrap();
+
+        assert!(matches!(
+            load(&path),
+            Err(QueuePersistenceError::IncompatibleVersion { .. })
+        ));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,78 @@
+//! Reactor for a read replica serving API queries from storage.
+//!
+//! A read replica is a separate process opening the storage of a node read-only, see the
+//! `crate::components::storage` module, and serving the JSON-RPCs answered from storage alone on
+//! its own address. Heavy query traffic, e.g. from block explorers, is then handled entirely
+//! outside of the node and cannot slow down its event loop.
+//!
+//! The node must be configured to allow read replicas through `storage.max_read_replicas` and be
+//! running when the replica starts.
+
+use casper_node_macros::reactor;
+use datasize::DataSize;
+use serde::{Deserialize, Serialize};
+
+use crate::{types::NodeId, utils::WithDir};
+
+/// Default binding address for the read replica's JSON-RPC HTTP server.
+///
+/// Uses a fixed port per node, but binds on any interface.
+const DEFAULT_ADDRESS: &str = "0.0.0.0:0";
+
+/// Read replica configuration.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Address to bind the read replica's JSON-RPC HTTP server to.
+    pub address: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            address: DEFAULT_ADDRESS.to_string(),
+        }
+    }
+}
+
+reactor!(ReadReplica {
+    type Config = WithDir<crate::reactor::validator::Config>;
+
+    components: {
+        storage = Storage(
+            &cfg.map_ref(|cfg| cfg.storage.clone().into_read_replica()),
+            registry
+        );
+        rpc_server = RpcServer(
+            crate::components::rpc_server::Config::read_only(
+                cfg.value().read_replica.address.clone()
+            ),
+            cfg.value().maintenance.clone(),
+            crate::types::FeatureFlags::from_config(cfg.value()),
+            effect_builder
+        );
+    }
+
+    events: {}
+
+    requests: {
+        StorageRequest -> storage;
+        StateStoreRequest -> storage;
+        RpcRequest<NodeId> -> rpc_server;
+
+        // Only RPCs answered from storage are served, so none of these should be made. Any made
+        // nonetheless are discarded, failing the RPC rather than the read replica.
+        ChainspecLoaderRequest -> #;
+        ConsensusRequest -> #;
+        ContractRuntimeRequest -> #;
+        LinearChainRequest<NodeId> -> #;
+        MetricsRequest -> #;
+        NetworkInfoRequest<NodeId> -> #;
+    }
+
+    announcements: {
+        // Deploys cannot be submitted to a read replica.
+        RpcServerAnnouncement -> #;
+    }
+});
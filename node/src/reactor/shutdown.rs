@@ -0,0 +1,110 @@
+//! Ordered shutdown of a reactor.
+//!
+//! Once termination has been requested, a reactor is not simply dropped. Instead, the runner takes
+//! it through the stages of `ShutdownStage` in order: the API servers stop accepting requests,
+//! consensus stops proposing, the queued network messages are sent, storage is flushed to disk and
+//! finally the network listener is closed. The reactor keeps processing events throughout, so work
+//! in flight when termination was requested can complete.
+//!
+//! Every stage is given a configurable amount of time to complete, after which the runner logs a
+//! warning and moves on to the next stage regardless.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use datasize::DataSize;
+use serde::{Deserialize, Serialize};
+
+/// Default time given to each shutdown stage.
+const DEFAULT_STAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default time given to sending the queued network messages.
+const DEFAULT_DRAIN_NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A stage of the ordered shutdown, in the order the stages are run.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum ShutdownStage {
+    /// Stop accepting requests on the API servers and wait for the pending ones to be answered.
+    StopApi,
+    /// Stop proposing new blocks and wait for the proposals in flight.
+    StopProposing,
+    /// Say goodbye to all peers and wait until the queued messages have been sent.
+    DrainNetwork,
+    /// Flush storage to disk.
+    FlushStorage,
+    /// Close the network listener.
+    CloseListeners,
+}
+
+impl ShutdownStage {
+    /// All stages, in the order they are run.
+    pub const ALL: [ShutdownStage; 5] = [
+        ShutdownStage::StopApi,
+        ShutdownStage::StopProposing,
+        ShutdownStage::DrainNetwork,
+        ShutdownStage::FlushStorage,
+        ShutdownStage::CloseListeners,
+    ];
+}
+
+impl Display for ShutdownStage {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ShutdownStage::StopApi => "stop API",
+            ShutdownStage::StopProposing => "stop proposing",
+            ShutdownStage::DrainNetwork => "drain network",
+            ShutdownStage::FlushStorage => "flush storage",
+            ShutdownStage::CloseListeners => "close listeners",
+        };
+        formatter.write_str(name)
+    }
+}
+
+/// Ordered shutdown configuration.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Time in milliseconds to wait for pending API requests to be answered.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub stop_api_timeout: Duration,
+    /// Time in milliseconds to wait for block proposals in flight.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub stop_proposing_timeout: Duration,
+    /// Time in milliseconds to wait for the queued network messages to be sent.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub drain_network_timeout: Duration,
+    /// Time in milliseconds to wait for storage to be flushed.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub flush_storage_timeout: Duration,
+    /// Time in milliseconds to wait for the network listener to close.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub close_listeners_timeout: Duration,
+}
+
+impl Config {
+    /// Returns the time given to `stage`.
+    pub(crate) fn timeout(&self, stage: ShutdownStage) -> Duration {
+        match stage {
+            ShutdownStage::StopApi => self.stop_api_timeout,
+            ShutdownStage::StopProposing => self.stop_proposing_timeout,
+            ShutdownStage::DrainNetwork => self.drain_network_timeout,
+            ShutdownStage::FlushStorage => self.flush_storage_timeout,
+            ShutdownStage::CloseListeners => self.close_listeners_timeout,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            stop_api_timeout: DEFAULT_STAGE_TIMEOUT,
+            stop_proposing_timeout: DEFAULT_STAGE_TIMEOUT,
+            drain_network_timeout: DEFAULT_DRAIN_NETWORK_TIMEOUT,
+            flush_storage_timeout: DEFAULT_STAGE_TIMEOUT,
+            close_listeners_timeout: DEFAULT_STAGE_TIMEOUT,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Disk-backed spillover of event queues.
+//!
+//! While joining, the network incoming queue can legitimately grow very large, e.g. when peers
+//! answer requests for bulk data faster than it can be processed. With spillover enabled, events
+//! beyond a configurable number are written to a file in the spillover directory and read back in
+//! order once the queue has been worked down, keeping memory usage bounded.
+//!
+//! Only events the reactor can convert into a `PersistedEvent` are spilled, see
+//! `Reactor::persist_event`; all other events stay in memory.
+
+use std::{fs, path::Path};
+
+use datasize::DataSize;
+use prometheus::{Histogram, IntGauge};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::{PersistedEvent, QueueKind, Reactor, Scheduler};
+use crate::utils::{SpillCodec, SpillRing, Spillover};
+
+/// Default number of events a spilling queue keeps in memory.
+const DEFAULT_MAX_IN_MEMORY_EVENTS: usize = 10_000;
+
+/// Default directory for the spillover files.
+const DEFAULT_PATH: &str = "event_queue_spillover";
+
+/// The queues which spill to disk if spillover is enabled.
+const SPILLING_QUEUES: [QueueKind; 1] = [QueueKind::NetworkIncoming];
+
+/// Event queue spillover configuration.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Whether queues which can grow large spill events to disk.
+    pub enabled: bool,
+    /// Number of events such a queue keeps in memory before spilling further events.
+    pub max_in_memory_events: usize,
+    /// Directory for the spillover files. Relative paths are resolved against the config
+    /// directory.
+    pub path: String,
+}
+
+impl Config {
+    /// Resolves a relative `path` against `root`.
+    pub(crate) fn resolve_path(&mut self, root: &Path) {
+        self.path = root.join(&self.path).display().to_string();
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            enabled: false,
+            max_in_memory_events: DEFAULT_MAX_IN_MEMORY_EVENTS,
+            path: DEFAULT_PATH.to_string(),
+        }
+    }
+}
+
+/// Encodes reactor events through their `PersistedEvent` form.
+struct ReactorCodec<E> {
+    persist: fn(E) -> Result<PersistedEvent, E>,
+    restore: fn(PersistedEvent) -> Option<E>,
+}
+
+impl<E> SpillCodec<E> for ReactorCodec<E> {
+    fn encode(&self, event: E) -> Result<Vec<u8>, E> {
+        let persisted = (self.persist)(event)?;
+        bincode::serialize(&persisted).map_err(|error| {
+            warn!(%error, "could not encode event for spilling");
+            (self.restore)(persisted).expect("reactor should restore the events it persists")
+        })
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Option<E> {
+        bincode::deserialize(bytes).ok().and_then(self.restore)
+    }
+}
+
+/// Enables spillover for the spilling queues of `scheduler`, if configured.
+///
+/// The path of `config` is expected to be resolved already, see `Config::resolve_path`.
+///
+/// Failing to set up a spillover file is not fatal, the affected queue is kept in memory instead.
+pub(super) fn enable<R: Reactor>(
+    scheduler: &mut Scheduler<R::Event>,
+    config: &Config,
+    depth: &IntGauge,
+    read_back_duration: &Histogram,
+) {
+    if !config.enabled {
+        return;
+    }
+
+    let dir = Path::new(&config.path);
+    if let Err(error) = fs::create_dir_all(dir) {
+        warn!(dir = %dir.display(), %error, "could not create event queue spillover directory");
+        return;
+    }
+
+    for &queue in SPILLING_QUEUES.iter() {
+        let path = dir.join(format!("{}.spill", queue.metrics_name()));
+        let ring = match SpillRing::create(path.clone()) {
+            Ok(ring) => ring,
+            Err(error) => {
+                warn!(path = %path.display(), %error, "could not create event queue spillover file");
+                continue;
+            }
+        };
+        scheduler.enable_spillover(
+            queue,
+            Spillover::new(
+                config.max_in_memory_events,
+                ring,
+                Box::new(ReactorCodec {
+                    persist: R::persist_event,
+                    restore: R::restore_event,
+                }),
+                depth.clone(),
+                read_back_duration.clone(),
+            ),
+        );
+        info!(
+            %queue,
+            path = %path.display(),
+            max_in_memory_events = config.max_in_memory_events,
+            "enabled event queue spillover"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Detection of starved event queues.
+//!
+//! A queue is starved if it has held events continuously for longer than a threshold, without the
+//! scheduler draining it once, e.g. the `Api` queue while the `Network` queue is flooded. This
+//! points to queue weights unfit for the load of the node, so every starved queue is reported once
+//! per period of being busy, both as a warning and in the metrics.
+
+use std::{
+    collections::HashMap,
+    env,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use prometheus::{IntCounter, IntGauge, Registry};
+use tracing::warn;
+
+use super::QueueKind;
+
+/// Default time a queue may hold events continuously before it is considered starved. Can be
+/// overridden by setting the env var `CL_QUEUE_STARVATION_SECS=<SECONDS>`.
+const DEFAULT_STARVATION_THRESHOLD: Duration = Duration::from_secs(60);
+const STARVATION_THRESHOLD_ENV_VAR: &str = "CL_QUEUE_STARVATION_SECS";
+
+static STARVATION_THRESHOLD: Lazy<Duration> = Lazy::new(|| {
+    env::var(STARVATION_THRESHOLD_ENV_VAR)
+        .map(|threshold_str| {
+            let threshold_secs = u64::from_str(&threshold_str).unwrap_or_else(|error| {
+                panic!(
+                    "can't parse env var {}={} as a u64: {}",
+                    STARVATION_THRESHOLD_ENV_VAR, threshold_str, error
+                )
+            });
+            Duration::from_secs(threshold_secs)
+        })
+        .unwrap_or_else(|_| DEFAULT_STARVATION_THRESHOLD)
+});
+
+/// Reports queues which have been busy for longer than the threshold.
+#[derive(Debug)]
+pub(super) struct StarvationDetector {
+    /// Time a queue may hold events continuously before it is considered starved.
+    threshold: Duration,
+    /// The start of the busy period last reported for each queue.
+    reported: HashMap<QueueKind, Instant>,
+    /// Number of queues currently starved.
+    starved_queues: IntGauge,
+    /// Total number of times a queue was found starved.
+    starvation_alarms: IntCounter,
+    /// Instance of registry to unregister from when being dropped.
+    registry: Registry,
+}
+
+impl StarvationDetector {
+    /// Creates a detector with the threshold from the environment, registering its metrics.
+    pub(super) fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Self::with_threshold(*STARVATION_THRESHOLD, registry)
+    }
+
+    fn with_threshold(threshold: Duration, registry: &Registry) -> Result<Self, prometheus::Error> {
+        let starved_queues = IntGauge::new(
+            "scheduler_starved_queues",
+            "number of event queues which have held events for longer than the starvation \
+             threshold without being drained",
+        )?;
+        let starvation_alarms = IntCounter::new(
+            "scheduler_starvation_alarms",
+            "total number of times an event queue was found starved",
+        )?;
+        registry.register(Box::new(starved_queues.clone()))?;
+        registry.register(Box::new(starvation_alarms.clone()))?;
+
+        Ok(StarvationDetector {
+            threshold,
+            reported: HashMap::new(),
+            starved_queues,
+            starvation_alarms,
+            registry: registry.clone(),
+        })
+    }
+
+    /// Checks the queues for starvation, given since when each of them has been busy.
+    ///
+    /// Returns the queues starved for the first time in their current busy period.
+    pub(super) fn check(
+        &mut self,
+        busy_since: &HashMap<QueueKind, Option<Instant>>,
+        now: Instant,
+    ) -> Vec<QueueKind> {
+        let mut starved = 0;
+        let mut newly_starved = Vec::new();
+        for (queue_kind, since) in busy_since {
+            let since = match since {
+                Some(since) if now.saturating_duration_since(*since) > self.threshold => *since,
+                _ => continue,
+            };
+            starved += 1;
+            if self.reported.insert(*queue_kind, since) != Some(since) {
+                let busy_secs = now.saturating_duration_since(since).as_secs();
+                warn!(
+                    queue = %queue_kind,
+                    busy_secs,
+                    threshold_secs = self.threshold.as_secs(),
+                    "event queue starved, it has not been drained for longer than the threshold"
+                );
+                self.starvation_alarms.inc();
+                newly_starved.push(*queue_kind);
+            }
+        }
+        self.starved_queues.set(starved);
+        newly_starved
+    }
+}
+
+impl Drop for StarvationDetector {
+    fn drop(&mut self) {
+        self.registry
+            .unregister(Box::new(self.starved_queues.clone()))
+            .expect("did not expect deregistering starved_queues to fail");
+        self.registry
+            .unregister(Box::new(self.starvation_alarms.clone()))
+            .expect("did not expect deregistering starvation_alarms to fail");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_each_busy_period_once() {
+        let registry = Registry::new();
+        let mut detector =
+            StarvationDetector::with_threshold(Duration::from_secs(10), &registry).unwrap();
+        let start = Instant::now();
+        let busy_since: HashMap<_, _> = vec![
+            (QueueKind::Api, Some(start)),
+            (QueueKind::Network, Some(start + Duration::from_secs(5))),
+            (QueueKind::Regular, None),
+        ]
+        .into_iter()
+        .collect();
+
+        let now = start + Duration::from_secs(11);
+        assert_eq!(detector.check(&busy_since, now), vec![QueueKind::Api]);
+        assert_eq!(detector.starved_queues.get(), 1);
+        // Still the same busy period.
+        assert!(detector
+            .check(&busy_since, now + Duration::from_secs(1))
+            .is_empty());
+
+        let mut busy_since = busy_since;
+        let _ = busy_since.insert(QueueKind::Api, Some(now));
+        assert_eq!(
+            detector.check(&busy_since, now + Duration::from_secs(5)),
+            vec![QueueKind::Network]
+        );
+        assert_eq!(detector.starved_queues.get(), 1);
+        assert_eq!(detector.starvation_alarms.get(), 2);
+    }
+}
@@ -11,6 +11,7 @@ mod tests;
 use std::{
     cmp, env,
     fmt::{self, Debug, Display, Formatter},
+    path::PathBuf,
     str::FromStr,
 };
 
@@ -20,10 +21,11 @@ use prometheus::Registry;
 use serde::Serialize;
 use tracing::{debug, error, warn};
 
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 use crate::testing::network::NetworkedReactor;
 use crate::{
     components::{
+        binary_port::BinaryPort,
         block_executor::{self, BlockExecutor},
         block_proposer::{self, BlockProposer},
         block_validator::{self, BlockValidator},
@@ -31,23 +33,26 @@ use crate::{
         consensus::{self, EraSupervisor},
         contract_runtime::{self, ContractRuntime},
         deploy_acceptor::{self, DeployAcceptor},
+        disk_monitor::{self, DiskMonitor, DiskSpaceStage},
+        era_metrics::{self, EraMetrics},
         event_stream_server::{self, EventStreamServer},
         fetcher::{self, Fetcher},
         gossiper::{self, Gossiper},
         linear_chain,
         metrics::Metrics,
         network::{self, Network, ENABLE_SMALL_NET_ENV_VAR},
-        rest_server::{self, RestServer},
+        notifier::{self, Notifier},
+        rest_server::{self, Readiness, RestServer},
         rpc_server::{self, RpcServer},
-        small_network::{self, GossipedAddress, SmallNetwork},
+        small_network::{self, ChainInfo, GossipedAddress, SmallNetwork},
         storage::{self, Storage},
         Component,
     },
     effect::{
         announcements::{
             BlockExecutorAnnouncement, ConsensusAnnouncement, DeployAcceptorAnnouncement,
-            GossiperAnnouncement, LinearChainAnnouncement, NetworkAnnouncement,
-            RpcServerAnnouncement,
+            DiskMonitorAnnouncement, GossiperAnnouncement, LinearChainAnnouncement,
+            NetworkAnnouncement, RpcServerAnnouncement,
         },
         requests::{
             BlockExecutorRequest, BlockProposerRequest, BlockValidationRequest,
@@ -57,10 +62,16 @@ use crate::{
         },
         EffectBuilder, EffectExt, Effects,
     },
+    logging,
     protocol::Message,
-    reactor::{self, event_queue_metrics::EventQueueMetrics, EventQueueHandle},
-    types::{Block, Deploy, NodeId, ProtoBlock, Tag, TimeDiff, Timestamp},
-    utils::Source,
+    reactor::{
+        self, EventQueueHandle, PersistedEvent, ShutdownStage, SpilloverConfig, YieldPolicyConfig,
+    },
+    types::{
+        Block, Deploy, DeployHash, FeatureFlags, MaintenanceConfig, NodeId, ProtoBlock,
+        SoftwareAttestation, Tag, TimeDiff, Timestamp,
+    },
+    utils::{bounded, Source},
     NodeRng,
 };
 pub use config::Config;
@@ -123,6 +134,15 @@ pub enum Event {
     /// Linear chain event.
     #[from]
     LinearChain(#[serde(skip_serializing)] linear_chain::Event<NodeId>),
+    /// Era metrics event.
+    #[from]
+    EraMetrics(#[serde(skip_serializing)] era_metrics::Event),
+    /// Notifier event.
+    #[from]
+    Notifier(#[serde(skip_serializing)] notifier::Event),
+    /// Disk monitor event.
+    #[from]
+    DiskMonitor(#[serde(skip_serializing)] disk_monitor::Event),
 
     // Requests
     /// Network request.
@@ -183,6 +203,9 @@ pub enum Event {
     /// Linear chain announcement.
     #[from]
     LinearChainAnnouncement(#[serde(skip_serializing)] LinearChainAnnouncement),
+    /// Disk monitor announcement.
+    #[from]
+    DiskMonitorAnnouncement(#[serde(skip_serializing)] DiskMonitorAnnouncement),
 }
 
 impl From<RpcRequest<NodeId>> for Event {
@@ -252,6 +275,9 @@ impl Display for Event {
             Event::ContractRuntime(event) => write!(f, "contract runtime: {}", event),
             Event::BlockExecutor(event) => write!(f, "block executor: {}", event),
             Event::LinearChain(event) => write!(f, "linear-chain event {}", event),
+            Event::EraMetrics(event) => write!(f, "era metrics: {}", event),
+            Event::Notifier(event) => write!(f, "notifier: {}", event),
+            Event::DiskMonitor(event) => write!(f, "disk monitor: {}", event),
             Event::ProtoBlockValidator(event) => write!(f, "block validator: {}", event),
             Event::NetworkRequest(req) => write!(f, "network request: {}", req),
             Event::NetworkInfoRequest(req) => write!(f, "network info request: {}", req),
@@ -279,6 +305,7 @@ impl Display for Event {
                 write!(f, "address gossiper announcement: {}", ann)
             }
             Event::LinearChainAnnouncement(ann) => write!(f, "linear chain announcement: {}", ann),
+            Event::DiskMonitorAnnouncement(ann) => write!(f, "disk monitor announcement: {}", ann),
         }
     }
 }
@@ -306,6 +333,7 @@ pub struct Reactor {
     contract_runtime: ContractRuntime,
     rpc_server: RpcServer,
     rest_server: RestServer,
+    binary_port: BinaryPort,
     event_stream_server: EventStreamServer,
     chainspec_loader: ChainspecLoader,
     consensus: EraSupervisor<NodeId>,
@@ -317,21 +345,28 @@ pub struct Reactor {
     block_executor: BlockExecutor,
     proto_block_validator: BlockValidator<ProtoBlock, NodeId>,
     linear_chain: LinearChain<NodeId>,
+    era_metrics: EraMetrics,
+    notifier: Notifier,
+    disk_monitor: DiskMonitor,
 
     // Non-components.
+    maintenance: MaintenanceConfig,
+
     #[data_size(skip)] // Never allocates heap data.
     memory_metrics: MemoryMetrics,
-
-    #[data_size(skip)]
-    event_queue_metrics: EventQueueMetrics,
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 impl Reactor {
     /// Inspect consensus.
     pub(crate) fn consensus(&self) -> &EraSupervisor<NodeId> {
         &self.consensus
     }
+
+    /// Inspect storage.
+    pub(crate) fn storage(&self) -> &Storage {
+        &self.storage
+    }
 }
 
 impl reactor::Reactor for Reactor {
@@ -346,9 +381,9 @@ impl reactor::Reactor for Reactor {
         config: Self::Config,
         registry: &Registry,
         event_queue: EventQueueHandle<Self::Event>,
-        // We don't need `rng` b/c consensus component was the only one using it,
-        // and now it's being passed on from the `joiner` reactor via `config`.
-        _rng: &mut NodeRng,
+        // Consensus gets its `rng` passed on from the `joiner` reactor via `config`, this one only
+        // signs our software attestation.
+        rng: &mut NodeRng,
     ) -> Result<(Self, Effects<Event>), Error> {
         let ValidatorInitConfig {
             config,
@@ -363,8 +398,6 @@ impl reactor::Reactor for Reactor {
 
         let memory_metrics = MemoryMetrics::new(registry.clone())?;
 
-        let event_queue_metrics = EventQueueMetrics::new(registry.clone(), event_queue)?;
-
         let metrics = Metrics::new(registry.clone());
 
         let effect_builder = EffectBuilder::new(event_queue);
@@ -375,18 +408,52 @@ impl reactor::Reactor for Reactor {
             chainspec_loader.chainspec(),
             true,
         )?;
-        let genesis_config_hash = chainspec_loader.chainspec().hash();
-        let (small_network, small_network_effects) =
-            SmallNetwork::new(event_queue, config.network, genesis_config_hash, true)?;
+        let features = FeatureFlags::from_config(&config);
+        let send_attestation = config.network.send_attestation;
+        let (mut small_network, small_network_effects) = SmallNetwork::new(
+            event_queue,
+            config.network,
+            registry,
+            ChainInfo::from_chainspec(chainspec_loader.chainspec()),
+            features.clone(),
+            Some(consensus.public_signing_key()),
+            true,
+        )?;
+        let node_id = if env::var(ENABLE_SMALL_NET_ENV_VAR).is_err() {
+            network.node_id()
+        } else {
+            small_network.node_id()
+        };
+        logging::set_node_context(&node_id, &chainspec_loader.chainspec().hash());
+        if send_attestation {
+            match SoftwareAttestation::of_running_node(chainspec_loader.chainspec()) {
+                Ok(attestation) => {
+                    small_network.set_attestation(consensus.sign_attestation(attestation, rng))
+                }
+                Err(error) => warn!(%error, "could not hash our binary, not sending attestation"),
+            }
+        }
 
         let address_gossiper =
             Gossiper::new_for_complete_items("address_gossiper", config.gossip, registry)?;
 
-        let rpc_server = RpcServer::new(config.rpc_server.clone(), effect_builder)?;
-        let rest_server = RestServer::new(config.rest_server.clone(), effect_builder)?;
+        let rpc_server = RpcServer::new(
+            config.rpc_server.clone(),
+            config.maintenance.clone(),
+            features.clone(),
+            effect_builder,
+        )?;
+        let rest_server = RestServer::new(
+            config.rest_server.clone(),
+            config.maintenance.clone(),
+            features,
+            Readiness::Ready,
+            effect_builder,
+        )?;
+        let binary_port = BinaryPort::new(&config.binary_port, effect_builder)?;
 
         let deploy_acceptor = DeployAcceptor::new(config.deploy_acceptor);
-        let deploy_fetcher = Fetcher::new(config.fetcher);
+        let deploy_fetcher = Fetcher::new("deploy_fetcher", config.fetcher, registry)?;
         let deploy_gossiper = Gossiper::new_for_partial_items(
             "deploy_gossiper",
             config.gossip,
@@ -406,15 +473,34 @@ impl reactor::Reactor for Reactor {
         let genesis_state_root_hash = chainspec_loader
             .genesis_state_root_hash()
             .expect("should have state root hash");
-        let block_executor = BlockExecutor::new(genesis_state_root_hash, registry.clone())
-            .with_parent_map(latest_block);
+        let block_executor = BlockExecutor::new(
+            genesis_state_root_hash,
+            &config.block_executor,
+            registry.clone(),
+        )
+        .with_parent_map(latest_block)
+        .with_audit(consensus.public_signing_key());
         let (proto_block_validator, block_validator_effects) = BlockValidator::new(effect_builder);
         let linear_chain = LinearChain::new();
+        let era_metrics = EraMetrics::new(effect_builder);
+        let (notifier, notifier_effects) = Notifier::new(
+            config.notifier,
+            chainspec_loader.chainspec(),
+            storage.root(),
+            effect_builder,
+        )?;
+        let (disk_monitor, disk_monitor_effects) =
+            DiskMonitor::new(config.disk_monitor, storage.root(), effect_builder);
 
         effects.extend(reactor::wrap_effects(
             Event::ProtoBlockValidator,
             block_validator_effects,
         ));
+        effects.extend(reactor::wrap_effects(Event::Notifier, notifier_effects));
+        effects.extend(reactor::wrap_effects(
+            Event::DiskMonitor,
+            disk_monitor_effects,
+        ));
         effects.extend(reactor::wrap_effects(Event::Network, network_effects));
         effects.extend(reactor::wrap_effects(
             Event::SmallNetwork,
@@ -424,6 +510,14 @@ impl reactor::Reactor for Reactor {
             Event::Consensus,
             init_consensus_effects,
         ));
+        effects.extend(reactor::wrap_effects(
+            Event::Storage,
+            storage.start_compression_migration(effect_builder),
+        ));
+        effects.extend(reactor::wrap_effects(
+            Event::Storage,
+            storage.start_block_body_migration(effect_builder),
+        ));
 
         // set timeout to 5 minutes after now, or 5 minutes after genesis, whichever is later
         let now = Timestamp::now();
@@ -454,6 +548,7 @@ impl reactor::Reactor for Reactor {
                 contract_runtime,
                 rpc_server,
                 rest_server,
+                binary_port,
                 event_stream_server,
                 chainspec_loader,
                 consensus,
@@ -464,8 +559,11 @@ impl reactor::Reactor for Reactor {
                 block_executor,
                 proto_block_validator,
                 linear_chain,
+                era_metrics,
+                notifier,
+                disk_monitor,
+                maintenance: config.maintenance,
                 memory_metrics,
-                event_queue_metrics,
             },
             effects,
         ))
@@ -553,6 +651,18 @@ impl reactor::Reactor for Reactor {
                 Event::LinearChain,
                 self.linear_chain.handle_event(effect_builder, rng, event),
             ),
+            Event::EraMetrics(event) => reactor::wrap_effects(
+                Event::EraMetrics,
+                self.era_metrics.handle_event(effect_builder, rng, event),
+            ),
+            Event::Notifier(event) => reactor::wrap_effects(
+                Event::Notifier,
+                self.notifier.handle_event(effect_builder, rng, event),
+            ),
+            Event::DiskMonitor(event) => reactor::wrap_effects(
+                Event::DiskMonitor,
+                self.disk_monitor.handle_event(effect_builder, rng, event),
+            ),
 
             // Requests:
             Event::NetworkRequest(req) => {
@@ -618,7 +728,10 @@ impl reactor::Reactor for Reactor {
                     }
                     Message::GetRequest { tag, serialized_id } => match tag {
                         Tag::Deploy => {
-                            let deploy_hash = match bincode::deserialize(&serialized_id) {
+                            let deploy_hash = match bounded::from_bincode(
+                                &serialized_id,
+                                bounded::Limits::NETWORK,
+                            ) {
                                 Ok(hash) => hash,
                                 Err(error) => {
                                     error!(
@@ -654,8 +767,27 @@ impl reactor::Reactor for Reactor {
                                 }
                             }
                         }
+                        Tag::Block | Tag::BlockByHeight if self.maintenance.is_active() => {
+                            debug!(
+                                "in maintenance, not serving historical {:?} request from {}",
+                                tag, sender
+                            );
+                            return Effects::new();
+                        }
+                        Tag::Block | Tag::BlockByHeight
+                            if self.disk_monitor.stage() >= DiskSpaceStage::ArchivalDisabled =>
+                        {
+                            debug!(
+                                "low on disk space, not serving historical {:?} request from {}",
+                                tag, sender
+                            );
+                            return Effects::new();
+                        }
                         Tag::Block => {
-                            let block_hash = match bincode::deserialize(&serialized_id) {
+                            let block_hash = match bounded::from_bincode(
+                                &serialized_id,
+                                bounded::Limits::NETWORK,
+                            ) {
                                 Ok(hash) => hash,
                                 Err(error) => {
                                     error!(
@@ -670,7 +802,10 @@ impl reactor::Reactor for Reactor {
                             ))
                         }
                         Tag::BlockByHeight => {
-                            let height = match bincode::deserialize(&serialized_id) {
+                            let height = match bounded::from_bincode(
+                                &serialized_id,
+                                bounded::Limits::NETWORK,
+                            ) {
                                 Ok(block_by_height) => block_by_height,
                                 Err(error) => {
                                     error!(
@@ -694,7 +829,10 @@ impl reactor::Reactor for Reactor {
                         serialized_item,
                     } => match tag {
                         Tag::Deploy => {
-                            let deploy = match bincode::deserialize(&serialized_item) {
+                            let deploy = match bounded::from_bincode(
+                                &serialized_item,
+                                bounded::Limits::NETWORK,
+                            ) {
                                 Ok(deploy) => Box::new(deploy),
                                 Err(error) => {
                                     error!("failed to decode deploy from {}: {}", sender, error);
@@ -715,6 +853,12 @@ impl reactor::Reactor for Reactor {
                         }
                     },
                     Message::FinalitySignature(fs) => Event::LinearChain(fs.into()),
+                    Message::SubmitDeploy(deploy) => {
+                        Event::DeployAcceptor(deploy_acceptor::Event::SubmittedByPeer {
+                            deploy,
+                            sender,
+                        })
+                    }
                 };
                 self.dispatch_event(effect_builder, rng, reactor_event)
             }
@@ -786,21 +930,13 @@ impl reactor::Reactor for Reactor {
                 source: _,
             }) => Effects::new(),
             Event::ConsensusAnnouncement(consensus_announcement) => {
-                let mut reactor_event_dispatch = |dbe: block_proposer::Event| {
-                    self.dispatch_event(effect_builder, rng, Event::BlockProposer(dbe))
-                };
-
                 match consensus_announcement {
-                    ConsensusAnnouncement::Finalized(block) => {
-                        let effects =
-                            reactor_event_dispatch(block_proposer::Event::FinalizedProtoBlock {
-                                block: block.proto_block().clone(),
-                                height: block.height(),
-                            });
-                        effects
-                    }
                     ConsensusAnnouncement::Handled(_) => {
-                        debug!("Ignoring `Handled` announcement in `validator` reactor.");
+                        // Blocks are handled often enough to keep our attestation fresh.
+                        if let Some(attestation) = self.small_network.stale_attestation() {
+                            let attestation = self.consensus.sign_attestation(attestation, rng);
+                            self.small_network.set_attestation(attestation);
+                        }
                         Effects::new()
                     }
                     ConsensusAnnouncement::Fault {
@@ -808,19 +944,79 @@ impl reactor::Reactor for Reactor {
                         public_key,
                         timestamp,
                     } => {
+                        let mut effects = self.dispatch_event(
+                            effect_builder,
+                            rng,
+                            Event::Notifier(notifier::Event::Fault {
+                                era_id,
+                                public_key: public_key.clone(),
+                            }),
+                        );
                         let reactor_event =
                             Event::EventStreamServer(event_stream_server::Event::Fault {
                                 era_id,
                                 public_key: *public_key,
                                 timestamp,
                             });
-                        self.dispatch_event(effect_builder, rng, reactor_event)
+                        effects.extend(self.dispatch_event(effect_builder, rng, reactor_event));
+                        effects
                     }
                     ConsensusAnnouncement::DisconnectFromPeer(_peer) => {
                         // TODO: handle the announcement and acutally disconnect
                         warn!("Disconnecting from a given peer not yet implemented.");
                         Effects::new()
                     }
+                    ConsensusAnnouncement::PartitionStatus {
+                        possibly_partitioned,
+                        ..
+                    } => {
+                        let mut effects = self.dispatch_event(
+                            effect_builder,
+                            rng,
+                            Event::RpcServer(rpc_server::Event::PartitionStatus {
+                                possibly_partitioned,
+                            }),
+                        );
+                        effects.extend(self.dispatch_event(
+                            effect_builder,
+                            rng,
+                            Event::RestServer(rest_server::Event::PartitionStatus {
+                                possibly_partitioned,
+                            }),
+                        ));
+                        effects
+                    }
+                    ConsensusAnnouncement::ParticipationStatus {
+                        participation_degraded,
+                        ..
+                    } => {
+                        let mut effects = self.dispatch_event(
+                            effect_builder,
+                            rng,
+                            Event::RpcServer(rpc_server::Event::ParticipationStatus {
+                                participation_degraded,
+                            }),
+                        );
+                        effects.extend(self.dispatch_event(
+                            effect_builder,
+                            rng,
+                            Event::RestServer(rest_server::Event::ParticipationStatus {
+                                participation_degraded,
+                            }),
+                        ));
+                        effects
+                    }
+                    ConsensusAnnouncement::EquivocationRisk {
+                        era_id,
+                        doppelganger,
+                    } => self.dispatch_event(
+                        effect_builder,
+                        rng,
+                        Event::Notifier(notifier::Event::EquivocationRisk {
+                            era_id,
+                            doppelganger,
+                        }),
+                    ),
                 }
             }
             Event::BlockExecutorAnnouncement(BlockExecutorAnnouncement::LinearChainBlock {
@@ -830,14 +1026,11 @@ impl reactor::Reactor for Reactor {
                 let mut effects = Effects::new();
                 let block_hash = *block.hash();
 
-                // send to linear chain
-                let reactor_event = Event::LinearChain(linear_chain::Event::LinearChainBlock {
-                    block: Box::new(block),
-                    execution_results: execution_results
-                        .iter()
-                        .map(|(hash, (_header, results))| (*hash, results.clone()))
-                        .collect(),
-                });
+                // send to era metrics
+                let reactor_event = Event::EraMetrics(era_metrics::Event::block_executed(
+                    &block,
+                    &execution_results,
+                ));
                 effects.extend(self.dispatch_event(effect_builder, rng, reactor_event));
 
                 // send to event stream
@@ -868,29 +1061,257 @@ impl reactor::Reactor for Reactor {
                 block_hash,
                 block_header,
             }) => {
+                // A switch block ends its era, so later log lines belong to the next one.
+                if block_header.switch_block() {
+                    logging::enter_era(block_header.era_id().successor());
+                } else {
+                    logging::enter_era(block_header.era_id());
+                }
+                let mut effects = self.dispatch_event(
+                    effect_builder,
+                    rng,
+                    Event::Notifier(notifier::Event::BlockAdded {
+                        height: block_header.height(),
+                    }),
+                );
+                effects.extend(self.dispatch_event(
+                    effect_builder,
+                    rng,
+                    Event::DiskMonitor(disk_monitor::Event::BlockAdded),
+                ));
                 let reactor_event =
                     Event::EventStreamServer(event_stream_server::Event::BlockAdded {
                         block_hash,
                         block_header,
                     });
-                self.dispatch_event(effect_builder, rng, reactor_event)
+                effects.extend(self.dispatch_event(effect_builder, rng, reactor_event));
+                effects
             }
             Event::LinearChainAnnouncement(LinearChainAnnouncement::NewFinalitySignature(fs)) => {
                 let reactor_event =
                     Event::EventStreamServer(event_stream_server::Event::FinalitySignature(fs));
                 self.dispatch_event(effect_builder, rng, reactor_event)
             }
+            Event::DiskMonitorAnnouncement(DiskMonitorAnnouncement::StageChanged(stage)) => {
+                let paused = stage >= DiskSpaceStage::DeploysPaused;
+                let mut effects = self.dispatch_event(
+                    effect_builder,
+                    rng,
+                    Event::DeployAcceptor(deploy_acceptor::Event::SetPaused { paused }),
+                );
+                effects.extend(self.dispatch_event(
+                    effect_builder,
+                    rng,
+                    Event::RpcServer(rpc_server::Event::DiskSpaceStatus { stage }),
+                ));
+                effects.extend(self.dispatch_event(
+                    effect_builder,
+                    rng,
+                    Event::RestServer(rest_server::Event::DiskSpaceStatus { stage }),
+                ));
+                effects
+            }
+        }
+    }
+
+    fn begin_shutdown_stage(
+        &mut self,
+        _effect_builder: EffectBuilder<Self::Event>,
+        stage: ShutdownStage,
+    ) -> Effects<Self::Event> {
+        match stage {
+            ShutdownStage::StopApi => {
+                self.rpc_server.stop();
+                self.rest_server.stop();
+                self.binary_port.stop();
+            }
+            ShutdownStage::StopProposing => self.consensus.stop_proposing(),
+            ShutdownStage::DrainNetwork => self.small_network.start_draining_all(),
+            ShutdownStage::FlushStorage => {
+                if let Err(error) = self.storage.flush() {
+                    error!(%error, "failed to flush storage");
+                }
+            }
+            ShutdownStage::CloseListeners => self.small_network.close_listener(),
+        }
+        Effects::new()
+    }
+
+    fn is_shutdown_stage_complete(&mut self, stage: ShutdownStage) -> bool {
+        match stage {
+            ShutdownStage::StopApi => {
+                // Check all servers, so that each clears its handle once it has exited.
+                let rpc_server_stopped = self.rpc_server.has_stopped();
+                let binary_port_stopped = self.binary_port.has_stopped();
+                self.rest_server.has_stopped() && rpc_server_stopped && binary_port_stopped
+            }
+            ShutdownStage::StopProposing => !self.consensus.has_pending_proposals(),
+            ShutdownStage::DrainNetwork => self.small_network.is_drained(),
+            ShutdownStage::FlushStorage => true,
+            ShutdownStage::CloseListeners => self.small_network.is_listener_closed(),
+        }
+    }
+
+    fn health_problem(&self) -> Option<String> {
+        // Storage writes would fail mid-transaction, so the node is halting.
+        if self.disk_monitor.stage() == DiskSpaceStage::Halted {
+            return Some("disk space exhausted".to_string());
         }
+        None
     }
 
-    fn update_metrics(&mut self, event_queue_handle: EventQueueHandle<Self::Event>) {
+    fn update_metrics(&mut self, _event_queue_handle: EventQueueHandle<Self::Event>) {
         self.memory_metrics.estimate(&self);
-        self.event_queue_metrics
-            .record_event_queue_counts(&event_queue_handle)
+    }
+
+    #[cfg(feature = "debug-assertions")]
+    fn check_invariants(&self) -> Vec<reactor::InvariantViolation> {
+        let mut violations = Vec::new();
+        let stored_height = self.storage.highest_block_height();
+        let executed_height = self.consensus.highest_executed_block_height();
+        if executed_height > stored_height {
+            violations.push(reactor::InvariantViolation {
+                invariant: "storage contains every block handled by consensus",
+                details: format!(
+                    "highest stored block height {:?}, highest executed block height handled by \
+                     consensus {:?}",
+                    stored_height, executed_height
+                ),
+            });
+        }
+        violations
+    }
+
+    fn event_component(event: &Self::Event) -> &'static str {
+        match event {
+            Event::Network(_)
+            | Event::SmallNetwork(_)
+            | Event::NetworkRequest(_)
+            | Event::NetworkInfoRequest(_) => "network",
+            Event::BlockProposer(_) | Event::BlockProposerRequest(_) => "block_proposer",
+            Event::Storage(_) | Event::StorageRequest(_) | Event::StateStoreRequest(_) => "storage",
+            Event::RpcServer(_) => "rpc_server",
+            Event::RestServer(_) => "rest_server",
+            Event::EventStreamServer(_) => "event_stream_server",
+            Event::ChainspecLoader(_) | Event::ChainspecLoaderRequest(_) => "chainspec_loader",
+            Event::Consensus(_) => "consensus",
+            Event::DeployAcceptor(_) => "deploy_acceptor",
+            Event::DeployFetcher(_) | Event::DeployFetcherRequest(_) => "deploy_fetcher",
+            Event::DeployGossiper(_) => "deploy_gossiper",
+            Event::AddressGossiper(_) => "address_gossiper",
+            Event::ContractRuntime(_) => "contract_runtime",
+            Event::BlockExecutor(_) | Event::BlockExecutorRequest(_) => "block_executor",
+            Event::ProtoBlockValidator(_) | Event::ProtoBlockValidatorRequest(_) => {
+                "block_validator"
+            }
+            Event::LinearChain(_) => "linear_chain",
+            Event::EraMetrics(_) => "era_metrics",
+            Event::Notifier(_) => "notifier",
+            Event::DiskMonitor(_) => "disk_monitor",
+            Event::MetricsRequest(_) => "metrics",
+            // Announcements are dispatched to several components at once.
+            Event::NetworkAnnouncement(_)
+            | Event::RpcServerAnnouncement(_)
+            | Event::DeployAcceptorAnnouncement(_)
+            | Event::ConsensusAnnouncement(_)
+            | Event::BlockExecutorAnnouncement(_)
+            | Event::DeployGossiperAnnouncement(_)
+            | Event::AddressGossiperAnnouncement(_)
+            | Event::LinearChainAnnouncement(_)
+            | Event::DiskMonitorAnnouncement(_) => "announcements",
+        }
+    }
+
+    fn event_deploy_hash(event: &Self::Event) -> Option<DeployHash> {
+        match event {
+            Event::DeployAcceptor(event) => event.deploy_hash(),
+            Event::DeployFetcher(event) => event.item_id(),
+            Event::DeployGossiper(event) => Some(event.item_id()),
+            Event::BlockProposer(event) => event.deploy_hash(),
+            Event::BlockExecutor(event) => event.deploy_hash(),
+            Event::RpcServerAnnouncement(RpcServerAnnouncement::DeployReceived {
+                deploy, ..
+            })
+            | Event::DeployAcceptorAnnouncement(DeployAcceptorAnnouncement::AcceptedNewDeploy {
+                deploy,
+                ..
+            })
+            | Event::DeployAcceptorAnnouncement(DeployAcceptorAnnouncement::InvalidDeploy {
+                deploy,
+                ..
+            }) => Some(*deploy.id()),
+            Event::DeployGossiperAnnouncement(GossiperAnnouncement::NewCompleteItem(
+                deploy_hash,
+            )) => Some(*deploy_hash),
+            _ => None,
+        }
+    }
+
+    fn persist_event(event: Self::Event) -> Result<PersistedEvent, Self::Event> {
+        match event {
+            Event::NetworkAnnouncement(NetworkAnnouncement::MessageReceived {
+                sender,
+                payload,
+            }) => Ok(PersistedEvent::MessageReceived { sender, payload }),
+            Event::RpcServerAnnouncement(RpcServerAnnouncement::DeployReceived {
+                deploy, ..
+            }) => Ok(PersistedEvent::DeployReceived { deploy }),
+            other => Err(other),
+        }
+    }
+
+    fn persisted_form(event: &Self::Event) -> Option<PersistedEvent> {
+        match event {
+            Event::NetworkAnnouncement(NetworkAnnouncement::MessageReceived {
+                sender,
+                payload,
+            }) => Some(PersistedEvent::MessageReceived {
+                sender: sender.clone(),
+                payload: payload.clone(),
+            }),
+            Event::RpcServerAnnouncement(RpcServerAnnouncement::DeployReceived {
+                deploy, ..
+            }) => Some(PersistedEvent::DeployReceived {
+                deploy: deploy.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn restore_event(event: PersistedEvent) -> Option<Self::Event> {
+        let event = match event {
+            PersistedEvent::MessageReceived { sender, payload } => {
+                Event::NetworkAnnouncement(NetworkAnnouncement::MessageReceived { sender, payload })
+            }
+            PersistedEvent::DeployReceived { deploy } => {
+                Event::RpcServerAnnouncement(RpcServerAnnouncement::DeployReceived {
+                    deploy,
+                    responder: None,
+                })
+            }
+        };
+        Some(event)
+    }
+
+    fn spillover_config(cfg: &Self::Config) -> Option<SpilloverConfig> {
+        // The path was already resolved by the joiner.
+        Some(cfg.config.event_queue_spillover.clone())
+    }
+
+    fn yield_policy_config(cfg: &Self::Config) -> YieldPolicyConfig {
+        cfg.config.yield_policy.clone()
+    }
+
+    fn is_consensus_critical(event: &Self::Event) -> bool {
+        matches!(event, Event::Consensus(_))
+    }
+
+    fn queue_dump_dir(cfg: &Self::Config) -> Option<PathBuf> {
+        Some(cfg.storage.root().to_path_buf())
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 impl NetworkedReactor for Reactor {
     type NodeId = NodeId;
     fn node_id(&self) -> Self::NodeId {
@@ -1,10 +1,19 @@
+use std::{net::SocketAddr, path::Path};
+
 use datasize::DataSize;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    logging::LoggingConfig, types::NodeConfig, ConsensusConfig, ContractRuntimeConfig,
-    DeployAcceptorConfig, EventStreamServerConfig, FetcherConfig, GossipConfig, RestServerConfig,
-    RpcServerConfig, SmallNetworkConfig, StorageConfig,
+    components::linear_chain_sync::Config as LinearChainSyncConfig,
+    logging::LoggingConfig,
+    reactor::{
+        ReadReplicaConfig, ShutdownConfig, SpilloverConfig, WatchdogConfig, YieldPolicyConfig,
+    },
+    types::{MaintenanceConfig, NodeConfig},
+    utils::{self, ConfigValidationError, ConfigValidator, External},
+    BinaryPortConfig, BlockExecutorConfig, ConsensusConfig, ContractRuntimeConfig,
+    DeployAcceptorConfig, DiskMonitorConfig, EventStreamServerConfig, FetcherConfig, GossipConfig,
+    NotifierConfig, RestServerConfig, RpcServerConfig, SmallNetworkConfig, StorageConfig,
 };
 
 /// Root configuration.
@@ -32,8 +41,153 @@ pub struct Config {
     pub gossip: GossipConfig,
     /// Fetcher configuration.
     pub fetcher: FetcherConfig,
+    /// Linear chain synchronization configuration.
+    pub linear_chain_sync: LinearChainSyncConfig,
     /// Contract runtime configuration.
     pub contract_runtime: ContractRuntimeConfig,
+    /// Block executor configuration.
+    pub block_executor: BlockExecutorConfig,
     /// Deploy acceptor configuration.
     pub deploy_acceptor: DeployAcceptorConfig,
+    /// Scheduled maintenance configuration.
+    pub maintenance: MaintenanceConfig,
+    /// Event queue spillover configuration.
+    pub event_queue_spillover: SpilloverConfig,
+    /// Reactor yield policy configuration.
+    pub yield_policy: YieldPolicyConfig,
+    /// Read replica configuration.
+    pub read_replica: ReadReplicaConfig,
+    /// Ordered shutdown configuration.
+    pub shutdown: ShutdownConfig,
+    /// Systemd watchdog configuration.
+    pub watchdog: WatchdogConfig,
+    /// Operator notification configuration.
+    pub notifier: NotifierConfig,
+    /// Disk space monitor configuration.
+    pub disk_monitor: DiskMonitorConfig,
+    /// Local binary port configuration.
+    pub binary_port: BinaryPortConfig,
+}
+
+impl Config {
+    /// Checks the configuration for problems not caught while parsing, such as constraints across
+    /// fields, unresolvable addresses or unusable directories.
+    ///
+    /// Relative paths are resolved against `root`. All problems found are reported at once.
+    pub fn validate(&self, root: &Path) -> Result<(), ConfigValidationError> {
+        let mut validator = ConfigValidator::new(root);
+
+        if let External::Path(path) = &self.node.chainspec_config_path {
+            validator
+                .section("node")
+                .ensure_file("chainspec_config_path", path);
+        }
+
+        self.consensus.validate(validator.section("consensus"));
+        self.network.validate(validator.section("network"));
+        // Problems loading the chainspec itself are reported by the initializer.
+        if let Ok(chainspec) = self.node.chainspec_config_path.clone().load(root) {
+            self.network
+                .validate_against_chainspec(&chainspec, validator.section("network"));
+        }
+
+        validator
+            .section("event_stream_server")
+            .ensure_address("address", &self.event_stream_server.address);
+        validator.ensure_non_zero(
+            "event_stream_buffer_length",
+            self.event_stream_server.event_stream_buffer_length,
+        );
+        validator.ensure_non_zero(
+            "broadcast_channel_size",
+            self.event_stream_server.broadcast_channel_size,
+        );
+        validator
+            .section("rest_server")
+            .ensure_address("address", &self.rest_server.address);
+        validator
+            .section("rpc_server")
+            .ensure_address("address", &self.rpc_server.address);
+        validator
+            .section("read_replica")
+            .ensure_address("address", &self.read_replica.address);
+
+        self.storage.validate(validator.section("storage"));
+        self.gossip.validate(validator.section("gossip"));
+        self.fetcher.validate(validator.section("fetcher"));
+        self.contract_runtime
+            .validate(validator.section("contract_runtime"));
+        validator.section("block_executor").ensure(
+            self.block_executor.audit_percentage <= 100,
+            "audit_percentage",
+            "must not exceed 100",
+        );
+
+        validator.section("maintenance");
+        for (index, window) in self.maintenance.windows.iter().enumerate() {
+            validator.ensure(
+                window.start < window.end,
+                &format!("windows[{}]", index),
+                "start must be before end",
+            );
+        }
+
+        validator.section("event_queue_spillover").ensure_non_zero(
+            "max_in_memory_events",
+            self.event_queue_spillover.max_in_memory_events,
+        );
+
+        validator
+            .section("yield_policy")
+            .ensure_non_zero("max_time_per_yield", self.yield_policy.max_time_per_yield);
+
+        validator
+            .section("watchdog")
+            .ensure_non_zero("stall_timeout", self.watchdog.stall_timeout);
+
+        self.deploy_acceptor
+            .validate(validator.section("deploy_acceptor"));
+        self.notifier.validate(validator.section("notifier"));
+        self.disk_monitor
+            .validate(validator.section("disk_monitor"));
+        self.binary_port.validate(validator.section("binary_port"));
+
+        self.check_listening_conflicts(&mut validator);
+
+        validator.finish()
+    }
+
+    /// Checks that no two servers are configured to listen on the same fixed port.
+    fn check_listening_conflicts(&self, validator: &mut ConfigValidator) {
+        let listeners = [
+            ("network.bind_address", &self.network.bind_address),
+            (
+                "event_stream_server.address",
+                &self.event_stream_server.address,
+            ),
+            ("rest_server.address", &self.rest_server.address),
+            ("rpc_server.address", &self.rpc_server.address),
+            ("read_replica.address", &self.read_replica.address),
+        ];
+        // Unresolvable addresses have been reported already, port 0 picks a free port.
+        let resolved: Vec<(&str, SocketAddr)> = listeners
+            .iter()
+            .filter_map(|(field, address)| {
+                utils::resolve_address(address)
+                    .ok()
+                    .map(|resolved| (*field, resolved))
+            })
+            .filter(|(_, resolved)| resolved.port() != 0)
+            .collect();
+
+        validator.section("");
+        for (index, (field, address)) in resolved.iter().enumerate() {
+            if let Some((other_field, _)) = resolved[..index]
+                .iter()
+                .find(|(_, other)| other.port() == address.port() && other.ip() == address.ip())
+            {
+                validator.violation(field, format!("port is already used by `{}`", other_field));
+            }
+        }
+    }
 }
@@ -1,7 +1,7 @@
 use thiserror::Error;
 
 use crate::{
-    components::{contract_runtime, network, small_network, storage},
+    components::{binary_port, contract_runtime, network, notifier, small_network, storage},
     utils::ListeningError,
 };
 
@@ -24,6 +24,14 @@ pub enum Error {
     #[error("http server listening error: {0}")]
     ListeningError(#[from] ListeningError),
 
+    /// Error opening the binary port.
+    #[error("binary port error: {0}")]
+    BinaryPort(#[from] binary_port::Error),
+
+    /// `Notifier` component error.
+    #[error("notifier error: {0}")]
+    Notifier(#[from] notifier::Error),
+
     /// `Storage` component error.
     #[error("storage error: {0}")]
     Storage(#[from] storage::Error),
@@ -36,6 +44,10 @@ pub enum Error {
     #[error("contract runtime config error: {0}")]
     ContractRuntime(#[from] contract_runtime::ConfigError),
 
+    /// The joiner was started from an initializer which did not complete successfully.
+    #[error("initialization of storage and chainspec loader did not complete")]
+    InitializationIncomplete,
+
     /// Failed to serialize data.
     #[error("serialization: {0}")]
     Serialization(#[source] bincode::ErrorKind),
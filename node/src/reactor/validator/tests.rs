@@ -6,6 +6,7 @@ use std::{
 use anyhow::bail;
 use log::info;
 use num_rational::Ratio;
+use prometheus::Registry;
 use rand::Rng;
 use tempfile::TempDir;
 
@@ -121,10 +122,16 @@ impl TestChain {
 
         for idx in 0..self.keys.len() {
             let cfg = self.create_node_config(idx, first_node_port);
+            // As in a real node, the metrics are shared across all reactors.
+            let registry = Registry::new();
 
             // We create an initializer reactor here and run it to completion.
-            let mut initializer_runner =
-                Runner::<initializer::Reactor>::new(WithDir::new(root.clone(), cfg), rng).await?;
+            let mut initializer_runner = Runner::<initializer::Reactor>::with_metrics(
+                WithDir::new(root.clone(), cfg),
+                rng,
+                &registry,
+            )
+            .await?;
             initializer_runner.run(rng).await;
 
             // Now we can construct the actual node.
@@ -133,15 +140,18 @@ impl TestChain {
                 bail!("failed to initialize successfully");
             }
 
-            let mut joiner_runner =
-                Runner::<joiner::Reactor>::new(WithDir::new(root.clone(), initializer), rng)
-                    .await?;
+            let mut joiner_runner = Runner::<joiner::Reactor>::with_metrics(
+                WithDir::new(root.clone(), initializer),
+                rng,
+                &registry,
+            )
+            .await?;
             joiner_runner.run(rng).await;
 
             let config = joiner_runner.into_inner().into_validator_config().await;
 
             network
-                .add_node_with_config(config, rng)
+                .add_node_with_metrics(config, rng, &registry)
                 .await
                 .expect("could not add node to reactor");
         }
@@ -0,0 +1,227 @@
+//! Liveness supervision by the systemd watchdog.
+//!
+//! If the node runs as a systemd service with `WatchdogSec=` set, systemd expects to be notified
+//! periodically and restarts the node otherwise. A background task sends these notifications only
+//! while the running reactor makes progress: a crank must have completed within the configured
+//! stall timeout, and the reactor must not report a critical component as unhealthy. A deadlocked
+//! node is thereby restarted by systemd, rather than sitting wedged until an operator notices.
+//!
+//! While no reactor is running, e.g. while the next one is being created, notifications are sent
+//! as long as the task itself gets to run.
+
+use std::{
+    env, process,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use datasize::DataSize;
+use once_cell::sync::Lazy;
+use sd_notify::NotifyState;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+/// Env var in which systemd passes the watchdog timeout in microseconds.
+const WATCHDOG_USEC_ENV_VAR: &str = "WATCHDOG_USEC";
+/// Env var in which systemd passes the PID of the process expected to notify the watchdog.
+const WATCHDOG_PID_ENV_VAR: &str = "WATCHDOG_PID";
+
+/// Default time without a completed crank after which the reactor is considered stalled.
+const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Minimum time between two health checks of the reactor.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+static LIVENESS: Lazy<Mutex<Liveness>> = Lazy::new(Default::default);
+
+/// Watchdog configuration.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Whether to notify the systemd watchdog, if systemd enabled it for the node's service.
+    pub enabled: bool,
+    /// Time in milliseconds without a completed crank after which the reactor is considered
+    /// stalled.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub stall_timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            enabled: true,
+            stall_timeout: DEFAULT_STALL_TIMEOUT,
+        }
+    }
+}
+
+/// The progress of the running reactor.
+#[derive(Debug, Default)]
+struct Liveness {
+    /// When the running reactor last completed a crank, `None` if no reactor is running.
+    last_crank: Option<Instant>,
+    /// When the running reactor's health was last checked.
+    last_health_check: Option<Instant>,
+    /// Why a critical component of the running reactor is unhealthy, if one is.
+    problem: Option<String>,
+}
+
+impl Liveness {
+    /// Records a completed crank at `now`, checking the reactor's health if it is due.
+    fn record_crank<F: FnOnce() -> Option<String>>(&mut self, now: Instant, health_problem: F) {
+        if self.last_crank.is_none() {
+            // Only cranks of a running reactor count, not those of e.g. a shutdown.
+            return;
+        }
+        self.last_crank = Some(now);
+        let check_due = self.last_health_check.map_or(true, |last_check| {
+            now.saturating_duration_since(last_check) >= HEALTH_CHECK_INTERVAL
+        });
+        if check_due {
+            self.problem = health_problem();
+            self.last_health_check = Some(now);
+        }
+    }
+
+    /// Returns why the reactor is not making progress at `now`, if it isn't.
+    fn problem(&self, now: Instant, stall_timeout: Duration) -> Option<String> {
+        if let Some(problem) = &self.problem {
+            return Some(problem.clone());
+        }
+        let stalled_for = now.saturating_duration_since(self.last_crank?);
+        if stalled_for > stall_timeout {
+            Some(format!("no event handled for {}s", stalled_for.as_secs()))
+        } else {
+            None
+        }
+    }
+}
+
+fn liveness() -> MutexGuard<'static, Liveness> {
+    // The liveness is valid after any panic, so a poisoned lock is fine to reuse.
+    LIVENESS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Records that a reactor started running, so that its cranks are supervised from now on.
+pub(super) fn reactor_started() {
+    *liveness() = Liveness {
+        last_crank: Some(Instant::now()),
+        ..Default::default()
+    };
+}
+
+/// Records that the running reactor stopped.
+pub(super) fn reactor_stopped() {
+    *liveness() = Liveness::default();
+}
+
+/// Records a completed crank of the running reactor.
+///
+/// `health_problem` is called at most once per `HEALTH_CHECK_INTERVAL`.
+pub(super) fn record_crank<F: FnOnce() -> Option<String>>(health_problem: F) {
+    liveness().record_crank(Instant::now(), health_problem)
+}
+
+/// Starts notifying the systemd watchdog, if enabled both in the config and by systemd.
+///
+/// Must be called from within the tokio runtime, and only once.
+pub fn start_watchdog(config: &Config) {
+    if !config.enabled {
+        debug!("watchdog disabled, not notifying systemd");
+        return;
+    }
+    let interval = match notification_interval() {
+        Some(interval) => interval,
+        None => {
+            debug!("systemd watchdog not enabled for this process, not notifying");
+            return;
+        }
+    };
+    info!(
+        ?interval,
+        "notifying systemd watchdog while the reactor makes progress"
+    );
+
+    let stall_timeout = config.stall_timeout;
+    tokio::spawn(async move {
+        let mut withholding = false;
+        loop {
+            let problem = liveness().problem(Instant::now(), stall_timeout);
+            match problem {
+                None => {
+                    if withholding {
+                        info!("reactor making progress again, resuming watchdog notifications");
+                        withholding = false;
+                    }
+                    if let Err(error) = sd_notify::notify(false, &[NotifyState::Watchdog]) {
+                        warn!(%error, "could not notify systemd watchdog");
+                    }
+                }
+                Some(problem) => {
+                    if !withholding {
+                        error!(
+                            %problem,
+                            "reactor not making progress, withholding watchdog notifications"
+                        );
+                        withholding = true;
+                    }
+                }
+            }
+            tokio::time::delay_for(interval).await;
+        }
+    });
+}
+
+/// Returns half the watchdog timeout systemd set for this process, the recommended interval of
+/// notifications, or `None` if systemd expects no notifications from this process.
+fn notification_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var(WATCHDOG_PID_ENV_VAR) {
+        if pid.parse::<u32>().ok() != Some(process::id()) {
+            return None;
+        }
+    }
+    let timeout_usec: u64 = env::var(WATCHDOG_USEC_ENV_VAR).ok()?.parse().ok()?;
+    if timeout_usec == 0 {
+        return None;
+    }
+    Some(Duration::from_micros(timeout_usec) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_stalled_and_unhealthy_reactor() {
+        let stall_timeout = Duration::from_secs(10);
+        let start = Instant::now();
+        let mut liveness = Liveness::default();
+
+        // Without a running reactor, nothing can stall.
+        liveness.record_crank(start, || None);
+        assert_eq!(
+            liveness.problem(start + stall_timeout * 2, stall_timeout),
+            None
+        );
+
+        liveness.last_crank = Some(start);
+        liveness.record_crank(start + Duration::from_secs(5), || None);
+        assert_eq!(liveness.problem(start + stall_timeout, stall_timeout), None);
+        assert!(liveness
+            .problem(start + stall_timeout * 2, stall_timeout)
+            .is_some());
+
+        // Health problems are reported even while cranks complete, and checked at most once per
+        // interval.
+        let now = start + Duration::from_secs(6);
+        liveness.record_crank(now, || Some("disk full".to_string()));
+        liveness.record_crank(now, || panic!("health checked twice within interval"));
+        assert_eq!(
+            liveness.problem(now, stall_timeout),
+            Some("disk full".to_string())
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! How the reactor shares the CPU with the other tasks of the node.
+//!
+//! While events are queued, popping the next one completes immediately, so a busy reactor never
+//! hands control back to tokio on its own. All tasks on the same worker thread, e.g. the ones
+//! reading consensus messages off the network, wait until the queue runs dry. The reactor therefore
+//! yields explicitly once it has handled a configurable number of events or spent a configurable
+//! time handling them.
+//!
+//! Effects of consensus events, e.g. its timers and outgoing messages, can additionally be run on a
+//! dedicated runtime, where they do not compete with the effects of other components, see
+//! `Reactor::is_consensus_critical`.
+
+use std::time::{Duration, Instant};
+
+use datasize::DataSize;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::{Builder, Handle, Runtime};
+use tracing::{info, warn};
+
+/// Default number of events handled before yielding.
+const DEFAULT_MAX_EVENTS_PER_YIELD: u32 = 32;
+
+/// Default time spent handling events before yielding.
+const DEFAULT_MAX_TIME_PER_YIELD: Duration = Duration::from_millis(5);
+
+/// The runtime consensus effects run on, shared by all reactors run one after the other.
+static CONSENSUS_RUNTIME: OnceCell<Runtime> = OnceCell::new();
+
+/// Yield policy configuration.
+#[derive(Clone, DataSize, Debug, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Maximum number of events handled before yielding to other tasks. The reactor only yields
+    /// when its queue is empty if `0`.
+    pub max_events_per_yield: u32,
+    /// Maximum time in milliseconds spent handling events before yielding to other tasks.
+    #[serde(with = "crate::utils::milliseconds")]
+    pub max_time_per_yield: Duration,
+    /// Number of worker threads of a dedicated runtime for the effects of consensus events. They
+    /// share the node's main runtime if `0`.
+    pub consensus_threads: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_events_per_yield: DEFAULT_MAX_EVENTS_PER_YIELD,
+            max_time_per_yield: DEFAULT_MAX_TIME_PER_YIELD,
+            consensus_threads: 0,
+        }
+    }
+}
+
+/// The events the reactor may handle before it yields.
+#[derive(Debug)]
+pub struct YieldBudget {
+    max_events: u32,
+    max_time: Duration,
+    /// Events handled since the last yield.
+    events: u32,
+    /// When the reactor last yielded.
+    since: Instant,
+}
+
+impl YieldBudget {
+    /// Creates a full budget.
+    pub fn new(config: &Config) -> Self {
+        YieldBudget {
+            max_events: config.max_events_per_yield,
+            max_time: config.max_time_per_yield,
+            events: 0,
+            since: Instant::now(),
+        }
+    }
+
+    /// Records an event handled at `now`, returning whether the budget is used up.
+    fn record_event(&mut self, now: Instant) -> bool {
+        if self.max_events == 0 {
+            return false;
+        }
+        self.events += 1;
+        self.events >= self.max_events || now.saturating_duration_since(self.since) >= self.max_time
+    }
+
+    /// Records a handled event, yielding to other tasks and refilling the budget if it is used up.
+    ///
+    /// Returns whether the reactor yielded.
+    pub async fn consume(&mut self) -> bool {
+        if !self.record_event(Instant::now()) {
+            return false;
+        }
+        tokio::task::yield_now().await;
+        self.events = 0;
+        self.since = Instant::now();
+        true
+    }
+}
+
+/// Returns a handle to the runtime for the effects of consensus events, starting it on first use,
+/// or `None` if they run on the node's main runtime.
+pub(super) fn consensus_runtime(config: &Config) -> Option<Handle> {
+    if config.consensus_threads == 0 {
+        return None;
+    }
+    let runtime = CONSENSUS_RUNTIME.get_or_try_init(|| {
+        let runtime = Builder::new()
+            .threaded_scheduler()
+            .core_threads(config.consensus_threads)
+            .thread_name("consensus")
+            .enable_all()
+            .build()?;
+        info!(
+            threads = config.consensus_threads,
+            "started dedicated runtime for consensus effects"
+        );
+        Ok::<_, std::io::Error>(runtime)
+    });
+    match runtime {
+        Ok(runtime) => Some(runtime.handle().clone()),
+        Err(error) => {
+            warn!(%error, "could not start runtime for consensus effects, sharing main runtime");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(max_events_per_yield: u32, start: Instant) -> YieldBudget {
+        let config = Config {
+            max_events_per_yield,
+            max_time_per_yield: Duration::from_millis(5),
+            consensus_threads: 0,
+        };
+        YieldBudget {
+            since: start,
+            ..YieldBudget::new(&config)
+        }
+    }
+
+    #[test]
+    fn should_be_used_up_after_max_events() {
+        let start = Instant::now();
+        let mut budget = budget(3, start);
+        assert!(!budget.record_event(start));
+        assert!(!budget.record_event(start));
+        assert!(budget.record_event(start));
+    }
+
+    #[test]
+    fn should_be_used_up_after_max_time() {
+        let start = Instant::now();
+        let mut budget = budget(32, start);
+        assert!(!budget.record_event(start + Duration::from_millis(4)));
+        assert!(budget.record_event(start + Duration::from_millis(5)));
+    }
+
+    #[test]
+    fn should_never_be_used_up_without_max_events() {
+        let start = Instant::now();
+        let mut budget = budget(0, start);
+        for _ in 0..100 {
+            assert!(!budget.record_event(start + Duration::from_secs(1)));
+        }
+    }
+}
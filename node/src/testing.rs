@@ -2,9 +2,21 @@
 //!
 //! Contains various parts and components to aid writing tests and simulations using the
 //! `casper-node` library.
+//!
+//! Outside of the crate's own tests, this module is only available with the `testing` feature,
+//! which is what the `casper-node-testing` crate enables. The feature only adds to the library, the
+//! node's RNG and clocks are the production ones unless compiled for the crate's own tests.
+
+// Parts of the module are only used by the crate's own tests.
+#![cfg_attr(not(test), allow(dead_code))]
 
 mod condition_check_reactor;
+#[cfg(test)]
+pub(crate) mod fuzz;
+#[cfg(test)]
+pub(crate) mod malicious_peer;
 pub mod network;
+pub mod soak;
 mod test_rng;
 
 use std::{
@@ -22,17 +34,19 @@ use tokio::runtime::{self, Runtime};
 
 use crate::{
     components::Component,
-    effect::{EffectBuilder, Effects, Responder},
+    effect::{subscriptions::Subscriptions, EffectBuilder, Effects, Responder},
     logging,
-    reactor::{EventQueueHandle, QueueKind, Scheduler},
+    reactor::{ComponentStats, EventQueueHandle, LoadController, QueueKind, Scheduler},
+    NodeRng,
 };
 use anyhow::Context;
-pub(crate) use condition_check_reactor::ConditionCheckReactor;
-pub(crate) use test_rng::TestRng;
+pub use condition_check_reactor::ConditionCheckReactor;
+pub use test_rng::TestRng;
 
 // Lower bound for the port, below there's a high chance of hitting a system service.
 const PORT_LOWER_BOUND: u16 = 10_000;
 
+/// Asserts that `value` survives a roundtrip through `bincode` unchanged.
 pub fn bincode_roundtrip<T: Serialize + DeserializeOwned + Eq + Debug>(value: &T) {
     let serialized = bincode::serialize(value).unwrap();
     let deserialized = bincode::deserialize(serialized.as_slice()).unwrap();
@@ -90,8 +104,8 @@ pub(crate) fn init_logging() {
 /// Construction of a harness can be done straightforwardly through the `Default` trait, or the
 /// builder can be used to construct various aspects of it.
 pub(crate) struct ComponentHarness<REv: 'static> {
-    /// Test random number generator instance.
-    pub(crate) rng: TestRng,
+    /// Random number generator instance.
+    pub(crate) rng: NodeRng,
     /// Scheduler for events. Only explicitly polled by the harness.
     pub(crate) scheduler: &'static Scheduler<REv>,
     /// An event queue handle to the scheduler.
@@ -107,7 +121,7 @@ pub(crate) struct ComponentHarness<REv: 'static> {
 
 /// Builder for a `ComponentHarness`.
 pub(crate) struct ComponentHarnessBuilder<REv: 'static> {
-    rng: Option<TestRng>,
+    rng: Option<NodeRng>,
     tmp: Option<TempDir>,
     _phantom: PhantomData<REv>,
 }
@@ -129,7 +143,7 @@ impl<REv: 'static> ComponentHarnessBuilder<REv> {
     }
 
     /// Sets the test random number generator.
-    pub(crate) fn rng(mut self, rng: TestRng) -> ComponentHarnessBuilder<REv> {
+    pub(crate) fn rng(mut self, rng: NodeRng) -> ComponentHarnessBuilder<REv> {
         self.rng = Some(rng);
         self
     }
@@ -146,10 +160,14 @@ impl<REv: 'static> ComponentHarnessBuilder<REv> {
             }
         };
 
-        let rng = self.rng.unwrap_or_else(TestRng::new);
+        let rng = self.rng.unwrap_or_else(crate::new_rng);
 
         let scheduler = Box::leak(Box::new(Scheduler::new(QueueKind::weights())));
-        let event_queue_handle = EventQueueHandle::new(scheduler);
+        let component_stats = Box::leak(Box::new(ComponentStats::default()));
+        let subscriptions = Box::leak(Box::new(Subscriptions::default()));
+        let load_controller = Box::leak(Box::new(LoadController::default()));
+        let event_queue_handle =
+            EventQueueHandle::new(scheduler, component_stats, subscriptions, load_controller);
         let effect_builder = EffectBuilder::new(event_queue_handle);
         let runtime = runtime::Builder::new()
             .threaded_scheduler()
@@ -179,7 +197,7 @@ impl<REv: 'static> ComponentHarness<REv> {
     }
 
     /// Deconstructs the harness, keeping the on-disk state and test rng.
-    pub(crate) fn into_parts(self) -> (TempDir, TestRng) {
+    pub(crate) fn into_parts(self) -> (TempDir, NodeRng) {
         (self.tmp, self.rng)
     }
 
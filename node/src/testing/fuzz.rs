@@ -0,0 +1,519 @@
+//! Randomized event interleaving for reactors.
+//!
+//! A [`FuzzScenario`] describes how to construct a reactor, how to generate random but valid
+//! actions for it and which invariants must hold at all times. The [`FuzzHarness`] turns a seeded
+//! random number generator into a sequence of steps, each of which either injects an action or
+//! cranks the reactor once, and checks every invariant after each crank.
+//!
+//! Once an invariant is violated, the failing sequence is shrunk by repeatedly removing chunks of
+//! steps and replaying the remainder on a fresh reactor, until no single step can be removed
+//! without the failure disappearing. Since actions are generated upfront, a shrunk sequence is a
+//! self-contained reproducer, although events racing against timers may still make replays differ.
+
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    time::Duration,
+};
+
+use rand::Rng;
+use serde::Serialize;
+use tokio::time;
+use tracing::debug;
+
+use super::TestRng;
+use crate::{
+    effect::{EffectBuilder, Effects},
+    reactor::{Reactor, Runner},
+};
+
+/// Default number of steps generated per run.
+const DEFAULT_STEP_COUNT: usize = 200;
+/// Default upper bound on the number of replays performed while shrinking.
+const DEFAULT_MAX_SHRINK_RUNS: usize = 500;
+/// Upper bound on the number of cranks performed when draining the queue after the last step.
+const MAX_DRAIN_CRANKS: usize = 10_000;
+/// Number of consecutive empty polls after which a reactor is considered idle.
+const IDLE_POLLS: usize = 10;
+/// Delay between polls of an empty event queue, allowing spawned effects to schedule events.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A randomized test scenario for a reactor.
+pub(crate) trait FuzzScenario {
+    /// The reactor under test.
+    type Reactor: Reactor;
+    /// An action that can be injected into the reactor.
+    type Action: Clone + Debug;
+
+    /// Creates the configuration for a fresh reactor instance.
+    ///
+    /// Called once per replay, so any on-disk state must not be shared between calls.
+    fn config(&mut self) -> <Self::Reactor as Reactor>::Config;
+
+    /// Generates a random action that is valid to inject at any point.
+    fn random_action(&mut self, rng: &mut TestRng) -> Self::Action;
+
+    /// Turns an action into effects to be injected into the reactor.
+    fn inject(
+        &self,
+        action: Self::Action,
+        effect_builder: EffectBuilder<<Self::Reactor as Reactor>::Event>,
+    ) -> Effects<<Self::Reactor as Reactor>::Event>;
+
+    /// Creates a fresh set of invariants, which will be checked after every crank.
+    fn invariants(&self) -> Vec<Box<dyn Invariant<Self::Reactor>>>;
+}
+
+/// A property of a reactor that must hold after every crank.
+pub(crate) trait Invariant<R> {
+    /// The name of the invariant, used in failure reports.
+    fn name(&self) -> &str;
+
+    /// Checks the invariant, returning a description of the violation if it does not hold.
+    fn check(&mut self, reactor: &R) -> Result<(), String>;
+}
+
+/// Invariant asserting that a value extracted from the reactor never decreases.
+///
+/// Suitable for properties like the highest stored block height or the current consensus era.
+pub(crate) struct NeverDecreases<R> {
+    name: &'static str,
+    extract: fn(&R) -> Option<u64>,
+    highest_seen: Option<u64>,
+}
+
+impl<R> NeverDecreases<R> {
+    /// Creates a new invariant over the value returned by `extract`.
+    ///
+    /// `None` is treated as "not available yet", becoming `None` after a value has been seen is a
+    /// violation.
+    pub(crate) fn new(name: &'static str, extract: fn(&R) -> Option<u64>) -> Self {
+        NeverDecreases {
+            name,
+            extract,
+            highest_seen: None,
+        }
+    }
+}
+
+impl<R> Invariant<R> for NeverDecreases<R> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn check(&mut self, reactor: &R) -> Result<(), String> {
+        let current = (self.extract)(reactor);
+        match (self.highest_seen, current) {
+            (Some(highest), None) => Err(format!("value disappeared after reaching {}", highest)),
+            (Some(highest), Some(value)) if value < highest => {
+                Err(format!("value decreased from {} to {}", highest, value))
+            }
+            _ => {
+                self.highest_seen = current;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A single step of a fuzzing run.
+#[derive(Clone, Debug)]
+pub(crate) enum Step<A> {
+    /// Inject the action into the reactor.
+    Inject(A),
+    /// Process one event, if there is any.
+    Crank,
+}
+
+/// A violated invariant.
+#[derive(Clone, Debug)]
+pub(crate) struct Violation {
+    /// Name of the violated invariant.
+    pub(crate) invariant: String,
+    /// Description of the violation.
+    pub(crate) message: String,
+}
+
+/// A failed fuzzing run, with the sequence of steps shrunk as far as possible.
+#[derive(Debug)]
+pub(crate) struct Failure<A> {
+    /// The violation caused by the shrunk sequence.
+    pub(crate) violation: Violation,
+    /// The minimal sequence of steps found to reproduce the violation.
+    pub(crate) steps: Vec<Step<A>>,
+    /// The length of the sequence before shrinking.
+    pub(crate) original_len: usize,
+}
+
+impl<A: Debug> Display for Failure<A> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            formatter,
+            "invariant '{}' violated: {}",
+            self.violation.invariant, self.violation.message
+        )?;
+        writeln!(
+            formatter,
+            "minimal reproducer ({} of {} steps):",
+            self.steps.len(),
+            self.original_len
+        )?;
+        for (idx, step) in self.steps.iter().enumerate() {
+            writeln!(formatter, "  {:>4}: {:?}", idx, step)?;
+        }
+        Ok(())
+    }
+}
+
+/// Harness driving a reactor through randomized step sequences.
+pub(crate) struct FuzzHarness<S> {
+    scenario: S,
+    step_count: usize,
+    max_shrink_runs: usize,
+}
+
+impl<S> FuzzHarness<S>
+where
+    S: FuzzScenario,
+    <S::Reactor as Reactor>::Event: Serialize,
+    <S::Reactor as Reactor>::Error: From<prometheus::Error>,
+{
+    /// Creates a new harness for the given scenario.
+    pub(crate) fn new(scenario: S) -> Self {
+        FuzzHarness {
+            scenario,
+            step_count: DEFAULT_STEP_COUNT,
+            max_shrink_runs: DEFAULT_MAX_SHRINK_RUNS,
+        }
+    }
+
+    /// Sets the number of steps generated per run.
+    pub(crate) fn step_count(mut self, step_count: usize) -> Self {
+        self.step_count = step_count;
+        self
+    }
+
+    /// Sets the upper bound on replays performed while shrinking a failing sequence.
+    pub(crate) fn max_shrink_runs(mut self, max_shrink_runs: usize) -> Self {
+        self.max_shrink_runs = max_shrink_runs;
+        self
+    }
+
+    /// Generates a random sequence of steps and runs it, shrinking it on failure.
+    pub(crate) async fn run(&mut self, rng: &mut TestRng) -> Result<(), Failure<S::Action>> {
+        let steps: Vec<_> = (0..self.step_count)
+            .map(|_| {
+                if rng.gen() {
+                    Step::Inject(self.scenario.random_action(rng))
+                } else {
+                    Step::Crank
+                }
+            })
+            .collect();
+
+        match self.execute(&steps, rng).await {
+            Ok(()) => Ok(()),
+            Err(violation) => Err(self.shrink(steps, violation, rng).await),
+        }
+    }
+
+    /// Replays a sequence of steps on a fresh reactor, checking invariants after every crank.
+    ///
+    /// Once all steps have been executed, the reactor is cranked until it is idle.
+    pub(crate) async fn execute(
+        &mut self,
+        steps: &[Step<S::Action>],
+        rng: &mut TestRng,
+    ) -> Result<(), Violation> {
+        let mut runner: Runner<S::Reactor> = Runner::new(self.scenario.config(), rng)
+            .await
+            .unwrap_or_else(|_| panic!("could not create reactor for fuzzing"));
+        let mut invariants = self.scenario.invariants();
+
+        for step in steps {
+            match step {
+                Step::Inject(action) => {
+                    let scenario = &self.scenario;
+                    runner
+                        .process_injected_effects(|effect_builder| {
+                            scenario.inject(action.clone(), effect_builder)
+                        })
+                        .await;
+                }
+                Step::Crank => {
+                    if runner.try_crank(rng).await.is_some() {
+                        check_all(&mut invariants, runner.reactor())?;
+                    }
+                }
+            }
+        }
+
+        let mut idle_polls = 0;
+        let mut cranks = 0;
+        while idle_polls < IDLE_POLLS && cranks < MAX_DRAIN_CRANKS {
+            if runner.try_crank(rng).await.is_some() {
+                idle_polls = 0;
+                cranks += 1;
+                check_all(&mut invariants, runner.reactor())?;
+            } else {
+                idle_polls += 1;
+                time::delay_for(IDLE_POLL_INTERVAL).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Shrinks a failing sequence by removing ever smaller chunks of steps.
+    async fn shrink(
+        &mut self,
+        mut steps: Vec<Step<S::Action>>,
+        mut violation: Violation,
+        rng: &mut TestRng,
+    ) -> Failure<S::Action> {
+        let original_len = steps.len();
+        let mut runs = 0;
+        let mut chunk_len = steps.len() / 2;
+
+        while chunk_len > 0 && runs < self.max_shrink_runs {
+            let mut start = 0;
+            while start < steps.len() && runs < self.max_shrink_runs {
+                let end = (start + chunk_len).min(steps.len());
+                let candidate: Vec<_> = steps[..start]
+                    .iter()
+                    .chain(&steps[end..])
+                    .cloned()
+                    .collect();
+
+                runs += 1;
+                match self.execute(&candidate, rng).await {
+                    Err(candidate_violation) => {
+                        debug!(len = candidate.len(), "shrunk failing sequence");
+                        steps = candidate;
+                        violation = candidate_violation;
+                    }
+                    Ok(()) => start += chunk_len,
+                }
+            }
+            chunk_len /= 2;
+        }
+
+        Failure {
+            violation,
+            steps,
+            original_len,
+        }
+    }
+}
+
+/// Checks all invariants, returning the first violation.
+fn check_all<R>(invariants: &mut [Box<dyn Invariant<R>>], reactor: &R) -> Result<(), Violation> {
+    for invariant in invariants.iter_mut() {
+        if let Err(message) = invariant.check(reactor) {
+            return Err(Violation {
+                invariant: invariant.name().to_string(),
+                message,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::{self, Display, Formatter};
+
+    use derive_more::From;
+    use prometheus::Registry;
+    use serde::Serialize;
+    use tempfile::TempDir;
+    use thiserror::Error;
+
+    use super::*;
+    use crate::{
+        components::{
+            storage::{self, Storage},
+            Component,
+        },
+        effect::{requests::StorageRequest, EffectExt},
+        reactor::{self, EventQueueHandle},
+        types::Block,
+        utils::WithDir,
+        NodeRng,
+    };
+
+    #[derive(Debug, From, Serialize)]
+    enum Event {
+        #[from]
+        Storage(#[serde(skip_serializing)] storage::Event),
+    }
+
+    impl From<StorageRequest> for Event {
+        fn from(request: StorageRequest) -> Self {
+            Event::Storage(storage::Event::from(request))
+        }
+    }
+
+    impl Display for Event {
+        fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                Event::Storage(event) => write!(formatter, "storage: {}", event),
+            }
+        }
+    }
+
+    #[derive(Debug, Error)]
+    enum Error {
+        #[error("prometheus (metrics) error: {0}")]
+        Metrics(#[from] prometheus::Error),
+        #[error("storage error: {0}")]
+        Storage(#[from] storage::Error),
+    }
+
+    struct StorageReactor {
+        storage: Storage,
+        _storage_tempdir: TempDir,
+    }
+
+    impl Reactor for StorageReactor {
+        type Event = Event;
+        type Config = ();
+        type Error = Error;
+
+        fn new(
+            _cfg: Self::Config,
+            registry: &Registry,
+            _event_queue: EventQueueHandle<Self::Event>,
+            _rng: &mut NodeRng,
+        ) -> Result<(Self, Effects<Self::Event>), Self::Error> {
+            let (storage_config, storage_tempdir) = storage::Config::default_for_tests();
+            let storage = Storage::new(
+                &WithDir::new(storage_tempdir.path(), storage_config),
+                registry,
+            )?;
+            let reactor = StorageReactor {
+                storage,
+                _storage_tempdir: storage_tempdir,
+            };
+            Ok((reactor, Effects::new()))
+        }
+
+        fn dispatch_event(
+            &mut self,
+            effect_builder: EffectBuilder<Self::Event>,
+            rng: &mut NodeRng,
+            event: Self::Event,
+        ) -> Effects<Self::Event> {
+            match event {
+                Event::Storage(event) => reactor::wrap_effects(
+                    Event::Storage,
+                    self.storage.handle_event(effect_builder, rng, event),
+                ),
+            }
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    enum Action {
+        PutBlock(Box<Block>),
+        GetHighestBlock,
+    }
+
+    /// Scenario storing random blocks, with an optional cap on the number of stored blocks to
+    /// provoke a failure.
+    struct StorageScenario {
+        max_blocks: Option<usize>,
+    }
+
+    struct MaxBlocks(usize);
+
+    impl Invariant<StorageReactor> for MaxBlocks {
+        fn name(&self) -> &str {
+            "max blocks"
+        }
+
+        fn check(&mut self, reactor: &StorageReactor) -> Result<(), String> {
+            let count = reactor.storage.block_count();
+            if count > self.0 {
+                Err(format!(
+                    "{} blocks stored, at most {} allowed",
+                    count, self.0
+                ))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl FuzzScenario for StorageScenario {
+        type Reactor = StorageReactor;
+        type Action = Action;
+
+        fn config(&mut self) {}
+
+        fn random_action(&mut self, rng: &mut TestRng) -> Action {
+            if rng.gen_bool(0.7) {
+                Action::PutBlock(Box::new(Block::random(rng)))
+            } else {
+                Action::GetHighestBlock
+            }
+        }
+
+        fn inject(&self, action: Action, effect_builder: EffectBuilder<Event>) -> Effects<Event> {
+            match action {
+                Action::PutBlock(block) => effect_builder.put_block_to_storage(block).ignore(),
+                Action::GetHighestBlock => effect_builder.get_highest_block_from_storage().ignore(),
+            }
+        }
+
+        fn invariants(&self) -> Vec<Box<dyn Invariant<StorageReactor>>> {
+            let mut invariants: Vec<Box<dyn Invariant<StorageReactor>>> =
+                vec![Box::new(NeverDecreases::new(
+                    "storage height never decreases",
+                    |reactor: &StorageReactor| reactor.storage.highest_block_height(),
+                ))];
+            if let Some(max_blocks) = self.max_blocks {
+                invariants.push(Box::new(MaxBlocks(max_blocks)));
+            }
+            invariants
+        }
+    }
+
+    #[tokio::test]
+    async fn storage_height_never_decreases() {
+        let mut rng = TestRng::new();
+        let mut harness = FuzzHarness::new(StorageScenario { max_blocks: None }).step_count(100);
+
+        if let Err(failure) = harness.run(&mut rng).await {
+            panic!("{}", failure);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_shrink_failing_sequence_to_minimal_reproducer() {
+        let mut rng = TestRng::new();
+        let mut harness = FuzzHarness::new(StorageScenario {
+            max_blocks: Some(3),
+        })
+        .step_count(60)
+        .max_shrink_runs(1_000);
+
+        let steps: Vec<_> = (0..10)
+            .map(|_| Step::Inject(Action::PutBlock(Box::new(Block::random(&mut rng)))))
+            .chain((0..10).map(|_| Step::Crank))
+            .collect();
+        let violation = harness
+            .execute(&steps, &mut rng)
+            .await
+            .expect_err("storing ten blocks should violate the invariant");
+        let failure = harness.shrink(steps, violation, &mut rng).await;
+
+        // Four stored blocks are required to exceed the limit, everything else can be dropped.
+        assert_eq!(failure.violation.invariant, "max blocks");
+        assert_eq!(failure.original_len, 20);
+        assert_eq!(failure.steps.len(), 4);
+        assert!(failure
+            .steps
+            .iter()
+            .all(|step| matches!(step, Step::Inject(Action::PutBlock(_)))));
+    }
+}
@@ -0,0 +1,108 @@
+//! Models of misbehaving peers for testing the fetcher and gossiper.
+//!
+//! A [`MaliciousPeer`] is not part of the test network; it only has a node ID. Tests direct
+//! requests at it and inject whatever it would send back into the node under test, which lets them
+//! exercise the timeout and validation paths of the components deterministically.
+
+use crate::{
+    protocol::Message,
+    testing::TestRng,
+    types::{Block, Deploy, Item, NodeId},
+};
+
+/// The ways in which a malicious peer misbehaves when asked for an item.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Behavior {
+    /// Responds with a corrupted copy of the item, see `Corruptible`.
+    ServeCorrupted,
+    /// Starts sending the item but never finishes, so only a truncated response arrives.
+    StallMidTransfer,
+    /// Advertises the item but never responds to requests for it.
+    NeverProvide,
+}
+
+impl Behavior {
+    /// All the behaviors, for tests which should be run against each of them.
+    pub(crate) const ALL: [Behavior; 3] = [
+        Behavior::ServeCorrupted,
+        Behavior::StallMidTransfer,
+        Behavior::NeverProvide,
+    ];
+}
+
+/// An item a malicious peer can serve a corrupted copy of.
+pub(crate) trait Corruptible: Item {
+    /// Returns a copy of the item whose header was tampered with, so it no longer matches its hash.
+    fn corrupted(&self) -> Self;
+}
+
+impl Corruptible for Deploy {
+    fn corrupted(&self) -> Self {
+        let mut json = serde_json::to_value(self).expect("should encode deploy");
+        let gas_price = json["header"]["gas_price"]
+            .as_u64()
+            .expect("should have gas price");
+        json["header"]["gas_price"] = gas_price.wrapping_add(1).into();
+        serde_json::from_value(json).expect("should decode corrupted deploy")
+    }
+}
+
+impl Corruptible for Block {
+    fn corrupted(&self) -> Self {
+        let mut corrupted = self.clone();
+        corrupted.set_height(self.height().wrapping_add(1));
+        corrupted
+    }
+}
+
+/// A peer which misbehaves in a fixed way.
+#[derive(Clone, Debug)]
+pub(crate) struct MaliciousPeer {
+    node_id: NodeId,
+    behavior: Behavior,
+}
+
+impl MaliciousPeer {
+    /// Creates a malicious peer with a random node ID.
+    pub(crate) fn new(rng: &mut TestRng, behavior: Behavior) -> Self {
+        MaliciousPeer {
+            node_id: NodeId::random_tls(rng),
+            behavior,
+        }
+    }
+
+    /// Returns the node ID of the peer.
+    pub(crate) fn node_id(&self) -> &NodeId {
+        &self.node_id
+    }
+
+    /// Returns the way the peer misbehaves.
+    pub(crate) fn behavior(&self) -> Behavior {
+        self.behavior
+    }
+
+    /// Returns the message the peer sends in response to a `GetRequest` for `item`, if any.
+    pub(crate) fn respond_to_get_request<T: Corruptible>(&self, item: &T) -> Option<Message> {
+        match self.behavior {
+            Behavior::ServeCorrupted => Some(
+                Message::new_get_response(&item.corrupted()).expect("should create get-response"),
+            ),
+            Behavior::StallMidTransfer => {
+                match Message::new_get_response(item).expect("should create get-response") {
+                    Message::GetResponse {
+                        tag,
+                        mut serialized_item,
+                    } => {
+                        serialized_item.truncate(serialized_item.len() / 2);
+                        Some(Message::GetResponse {
+                            tag,
+                            serialized_item,
+                        })
+                    }
+                    _ => unreachable!("should be a get-response"),
+                }
+            }
+            Behavior::NeverProvide => None,
+        }
+    }
+}
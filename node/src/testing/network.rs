@@ -7,8 +7,12 @@ use std::{
     time::Duration,
 };
 
-use fake_instant::FakeClock as Instant;
+// The gossip timeouts of the crate's own tests run on a fake clock, which is advanced while
+// waiting.
+#[cfg(test)]
+use fake_instant::FakeClock;
 use futures::future::{BoxFuture, FutureExt};
+use prometheus::Registry;
 use serde::Serialize;
 use tokio::time;
 use tracing::{debug, error_span};
@@ -18,7 +22,6 @@ use super::ConditionCheckReactor;
 use crate::{
     effect::{EffectBuilder, Effects},
     reactor::{Finalize, Reactor, Runner},
-    testing::TestRng,
     NodeRng,
 };
 
@@ -66,13 +69,13 @@ where
     /// generated ID collides.
     pub async fn add_node(
         &mut self,
-        rng: &mut TestRng,
+        rng: &mut NodeRng,
     ) -> Result<(R::NodeId, &mut Runner<ConditionCheckReactor<R>>), R::Error> {
         self.add_node_with_config(Default::default(), rng).await
     }
 
     /// Adds `count` new nodes to the network, and returns their IDs.
-    pub async fn add_nodes(&mut self, rng: &mut TestRng, count: usize) -> Vec<R::NodeId> {
+    pub async fn add_nodes(&mut self, rng: &mut NodeRng, count: usize) -> Vec<R::NodeId> {
         let mut node_ids = vec![];
         for _ in 0..count {
             let (node_id, _runner) = self.add_node(rng).await.unwrap();
@@ -105,7 +108,24 @@ where
         cfg: R::Config,
         rng: &mut NodeRng,
     ) -> Result<(R::NodeId, &mut Runner<ConditionCheckReactor<R>>), R::Error> {
-        let runner: Runner<ConditionCheckReactor<R>> = Runner::new(cfg, rng).await?;
+        self.add_node_with_metrics(cfg, rng, &Registry::new()).await
+    }
+
+    /// Creates a new networking node on the network, registering its metrics in `registry`.
+    ///
+    /// Allows a node to be set up like a real one, whose reactors all share a single registry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a duplicate node ID is being inserted.
+    pub async fn add_node_with_metrics(
+        &mut self,
+        cfg: R::Config,
+        rng: &mut NodeRng,
+        registry: &Registry,
+    ) -> Result<(R::NodeId, &mut Runner<ConditionCheckReactor<R>>), R::Error> {
+        let runner: Runner<ConditionCheckReactor<R>> =
+            Runner::with_metrics(cfg, rng, registry).await?;
 
         let node_id = runner.reactor().node_id();
 
@@ -127,7 +147,7 @@ where
     }
 
     /// Crank the specified runner once, returning the number of events processed.
-    pub async fn crank(&mut self, node_id: &R::NodeId, rng: &mut TestRng) -> usize {
+    pub async fn crank(&mut self, node_id: &R::NodeId, rng: &mut NodeRng) -> usize {
         let runner = self.nodes.get_mut(node_id).expect("should find node");
 
         let node_id = runner.reactor().node_id();
@@ -145,7 +165,7 @@ where
     pub async fn crank_until<F>(
         &mut self,
         node_id: &R::NodeId,
-        rng: &mut TestRng,
+        rng: &mut NodeRng,
         condition: F,
         within: Duration,
     ) where
@@ -162,10 +182,11 @@ where
             .unwrap()
     }
 
-    async fn crank_and_check_indefinitely(&mut self, node_id: &R::NodeId, rng: &mut TestRng) {
+    async fn crank_and_check_indefinitely(&mut self, node_id: &R::NodeId, rng: &mut NodeRng) {
         loop {
             if self.crank(node_id, rng).await == 0 {
-                Instant::advance_time(POLL_INTERVAL.as_millis() as u64);
+                #[cfg(test)]
+                FakeClock::advance_time(POLL_INTERVAL.as_millis() as u64);
                 time::delay_for(POLL_INTERVAL).await;
                 continue;
             }
@@ -184,7 +205,7 @@ where
     }
 
     /// Crank all runners once, returning the number of events processed.
-    pub async fn crank_all(&mut self, rng: &mut TestRng) -> usize {
+    pub async fn crank_all(&mut self, rng: &mut NodeRng) -> usize {
         let mut event_count = 0;
         for node in self.nodes.values_mut() {
             let node_id = node.reactor().node_id();
@@ -204,7 +225,7 @@ where
     /// # Panics
     ///
     /// Panics if after `within` the event queues are still not idle.
-    pub async fn settle(&mut self, rng: &mut TestRng, quiet_for: Duration, within: Duration) {
+    pub async fn settle(&mut self, rng: &mut NodeRng, quiet_for: Duration, within: Duration) {
         time::timeout(within, self.settle_indefinitely(rng, quiet_for))
             .await
             .unwrap_or_else(|_| {
@@ -215,7 +236,7 @@ where
             })
     }
 
-    async fn settle_indefinitely(&mut self, rng: &mut TestRng, quiet_for: Duration) {
+    async fn settle_indefinitely(&mut self, rng: &mut NodeRng, quiet_for: Duration) {
         let mut no_events = false;
         loop {
             if self.crank_all(rng).await == 0 {
@@ -225,7 +246,8 @@ where
                     break;
                 } else {
                     no_events = true;
-                    Instant::advance_time(quiet_for.as_millis() as u64);
+                    #[cfg(test)]
+                    FakeClock::advance_time(quiet_for.as_millis() as u64);
                     time::delay_for(quiet_for).await;
                 }
             } else {
@@ -239,21 +261,36 @@ where
     /// # Panics
     ///
     /// If the `condition` is not reached inside of `within`, panics.
-    pub async fn settle_on<F>(&mut self, rng: &mut TestRng, condition: F, within: Duration)
+    pub async fn settle_on<F>(&mut self, rng: &mut NodeRng, condition: F, within: Duration)
+    where
+        F: Fn(&Nodes<R>) -> bool,
+    {
+        if !self.try_settle_on(rng, condition, within).await {
+            panic!(format!(
+                "network did not settle on condition within {:?}",
+                within
+            ))
+        }
+    }
+
+    /// Runs the main loop of every reactor until `condition` is true.
+    ///
+    /// Returns whether the `condition` was reached inside of `within`.
+    pub async fn try_settle_on<F>(
+        &mut self,
+        rng: &mut NodeRng,
+        condition: F,
+        within: Duration,
+    ) -> bool
     where
         F: Fn(&Nodes<R>) -> bool,
     {
         time::timeout(within, self.settle_on_indefinitely(rng, condition))
             .await
-            .unwrap_or_else(|_| {
-                panic!(format!(
-                    "network did not settle on condition within {:?}",
-                    within
-                ))
-            })
+            .is_ok()
     }
 
-    async fn settle_on_indefinitely<F>(&mut self, rng: &mut TestRng, condition: F)
+    async fn settle_on_indefinitely<F>(&mut self, rng: &mut NodeRng, condition: F)
     where
         F: Fn(&Nodes<R>) -> bool,
     {
@@ -265,7 +302,8 @@ where
 
             if self.crank_all(rng).await == 0 {
                 // No events processed, wait for a bit to avoid 100% cpu usage.
-                Instant::advance_time(POLL_INTERVAL.as_millis() as u64);
+                #[cfg(test)]
+                FakeClock::advance_time(POLL_INTERVAL.as_millis() as u64);
                 time::delay_for(POLL_INTERVAL).await;
             }
         }
@@ -0,0 +1,780 @@
+//! Soak tests of an in-process testnet.
+//!
+//! A soak test runs a network of validators for a long time while a scenario injects failures on
+//! a schedule: node restarts, network partitions and storms of deploys, as well as protocol
+//! upgrades at activation points set up in the chainspec. Safety and liveness are checked
+//! continuously while the scenario runs:
+//!
+//! * safety: no two nodes ever store different blocks at the same height, and
+//! * liveness: the highest block of the network keeps growing, at least once per
+//!   `liveness_timeout`.
+//!
+//! Scenarios are read from TOML files, see `resources/test/soak` for examples. Times are given as
+//! human-readable durations, e.g. `"90s"` or `"1h 30min"`, and events are scheduled relative to
+//! the start of the scenario, which is also when the nodes are started:
+//!
+//! ```toml
+//! validators = 5
+//! duration = "8h"
+//! liveness_timeout = "5min"
+//!
+//! [[upgrades]]
+//! activation_height = 500
+//! protocol_version = "1.1.0"
+//!
+//! [[events]]
+//! at = "30min"
+//! action = "restart"
+//! node = 2
+//! downtime = "1min"
+//! ```
+//!
+//! A partitioned node is paused: it is not cranked until the partition heals, so it neither
+//! processes nor sends any messages in the meantime, while its peers keep queueing messages for it.
+//! A restarted node keeps its storage and rejoins the network from the highest block known to the
+//! nodes that are still running.
+//!
+//! The chainspec is based on `resources/local/chainspec.toml`, which must have been generated from
+//! its template beforehand.
+
+use std::{
+    cmp,
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+#[cfg(test)]
+use fake_instant::FakeClock;
+use num_rational::Ratio;
+use prometheus::Registry;
+use rand::Rng;
+use semver::Version;
+use serde::Deserialize;
+use tempfile::TempDir;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use casper_execution_engine::{
+    core::engine_state::{executable_deploy_item::ExecutableDeployItem, genesis::GenesisAccount},
+    shared::motes::Motes,
+};
+use casper_types::{
+    bytesrepr::Bytes, runtime_args, standard_payment::ARG_AMOUNT, PublicKey, RuntimeArgs,
+    SecretKey, U512,
+};
+
+use super::network::Network;
+use crate::{
+    components::{
+        chainspec_loader::{ActivationPoint, UpgradePoint},
+        gossiper, small_network, storage,
+    },
+    crypto::AsymmetricKeyExt,
+    effect::EffectExt,
+    reactor::{initializer, joiner, validator, Reactor as _, Runner},
+    types::{BlockHash, Deploy, NodeId, TimeDiff, Timestamp},
+    utils::{read_file, External, Loadable, ReadFileError, WithDir, RESOURCES_PATH},
+    Chainspec, NodeRng,
+};
+
+/// Time from the start of the scenario until genesis, to allow for all validators to start up.
+const GENESIS_DELAY: Duration = Duration::from_secs(45);
+/// Time to wait before cranking again if no node had an event to process.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Balance of every genesis account, large enough to pay for all deploys of a long scenario.
+const ACCOUNT_BALANCE: u64 = 1_000_000_000_000_000_000;
+/// Amount transferred by every deploy of a deploy storm.
+const TRANSFER_AMOUNT: u64 = 2_500_000_000;
+/// Payment for every deploy of a deploy storm.
+const PAYMENT_AMOUNT: u64 = 1_000_000_000;
+
+/// Error running a soak test.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Failed to read the scenario file.
+    #[error("could not read scenario: {0}")]
+    ReadScenario(#[from] ReadFileError),
+    /// Failed to parse the scenario file.
+    #[error("could not parse scenario: {0}")]
+    ParseScenario(#[from] toml::de::Error),
+    /// The scenario is inconsistent.
+    #[error("invalid scenario: {0}")]
+    InvalidScenario(String),
+    /// Failed to load the chainspec the network is based on.
+    #[error("could not load chainspec: {0}")]
+    LoadChainspec(String),
+    /// A node failed to start.
+    #[error("node {index} failed to start: {message}")]
+    StartNode {
+        /// The index of the node.
+        index: usize,
+        /// What went wrong.
+        message: String,
+    },
+    /// Two nodes stored different blocks at the same height.
+    #[error(
+        "safety violated: node {index} stored block {hash} at height {height}, but block {expected} \
+         was stored there before"
+    )]
+    Safety {
+        /// The index of the node storing the conflicting block.
+        index: usize,
+        /// The height of the blocks.
+        height: u64,
+        /// The hash of the block stored first.
+        expected: BlockHash,
+        /// The hash of the conflicting block.
+        hash: BlockHash,
+    },
+    /// The network did not add any block for too long.
+    #[error("liveness violated: no block above height {height} for {stalled_for}")]
+    Liveness {
+        /// The height of the highest block.
+        height: u64,
+        /// The time since the highest block was first seen.
+        stalled_for: TimeDiff,
+    },
+}
+
+/// A soak test scenario.
+#[derive(Debug, Deserialize)]
+// Disallow unknown fields so that typos in scenario files are caught instead of ignored.
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// The number of validators in the network.
+    pub validators: usize,
+    /// The total duration of the scenario.
+    pub duration: TimeDiff,
+    /// The maximum time the network may go without adding a block, once past genesis.
+    pub liveness_timeout: TimeDiff,
+    /// The interval between two checks of safety and liveness.
+    #[serde(default = "default_check_interval")]
+    pub check_interval: TimeDiff,
+    /// The minimum number of blocks per era.
+    #[serde(default = "default_minimum_era_height")]
+    pub minimum_era_height: u64,
+    /// The minimum duration of an era.
+    #[serde(default = "default_era_duration")]
+    pub era_duration: TimeDiff,
+    /// The protocol upgrades to set up in the chainspec.
+    #[serde(default)]
+    pub upgrades: Vec<Upgrade>,
+    /// The events to inject, in any order.
+    #[serde(default)]
+    pub events: Vec<ScheduledAction>,
+}
+
+fn default_check_interval() -> TimeDiff {
+    Duration::from_secs(1).into()
+}
+
+fn default_minimum_era_height() -> u64 {
+    10
+}
+
+fn default_era_duration() -> TimeDiff {
+    Duration::from_secs(60).into()
+}
+
+/// A protocol upgrade at an activation point.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Upgrade {
+    /// The block height at which the upgrade activates.
+    pub activation_height: u64,
+    /// The protocol version after the upgrade.
+    pub protocol_version: Version,
+}
+
+/// An action to inject at a given time.
+#[derive(Debug, Deserialize)]
+pub struct ScheduledAction {
+    /// The time since the start of the scenario at which to inject the action.
+    pub at: TimeDiff,
+    /// The action.
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+/// An action injected into the network.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// Stops a node and restarts it with the same storage after `downtime`.
+    Restart {
+        /// The index of the node.
+        node: usize,
+        /// The time the node stays down for.
+        downtime: TimeDiff,
+    },
+    /// Cuts the given nodes off from the rest of the network for `duration`.
+    Partition {
+        /// The indices of the nodes.
+        nodes: Vec<usize>,
+        /// The time until the partition heals.
+        duration: TimeDiff,
+    },
+    /// Submits `deploys` transfers between genesis accounts to a node at once.
+    DeployStorm {
+        /// The index of the node.
+        node: usize,
+        /// The number of deploys.
+        deploys: usize,
+    },
+}
+
+impl Scenario {
+    /// Reads a scenario from a TOML file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let scenario: Scenario = toml::from_slice(&read_file(path)?)?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Checks that the scenario only refers to existing nodes.
+    fn validate(&self) -> Result<(), Error> {
+        if self.validators == 0 {
+            return Err(Error::InvalidScenario(
+                "need at least one validator".to_string(),
+            ));
+        }
+        for scheduled in &self.events {
+            let nodes = match &scheduled.action {
+                Action::Restart { node, .. } | Action::DeployStorm { node, .. } => vec![*node],
+                Action::Partition { nodes, .. } => nodes.clone(),
+            };
+            if let Some(node) = nodes.into_iter().find(|node| *node >= self.validators) {
+                return Err(Error::InvalidScenario(format!(
+                    "event at {} refers to node {}, but there are only {} validators",
+                    scheduled.at, node, self.validators
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Summary of a completed soak test.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The height of the highest block at the end of the scenario.
+    pub highest_block_height: Option<u64>,
+    /// The number of restarts.
+    pub restarts: usize,
+    /// The number of partitions.
+    pub partitions: usize,
+    /// The number of deploys submitted.
+    pub deploys: usize,
+}
+
+impl Display for Report {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        let height = self
+            .highest_block_height
+            .map_or_else(|| "none".to_string(), |height| height.to_string());
+        write!(
+            formatter,
+            "highest block: {}, restarts: {}, partitions: {}, deploys: {}",
+            height, self.restarts, self.partitions, self.deploys
+        )
+    }
+}
+
+/// The state of a node in the soak test.
+enum NodeState {
+    /// The node is part of the network and cranked.
+    Running(NodeId),
+    /// The node is part of the network but not cranked until the given time.
+    Paused { node_id: NodeId, until: Instant },
+    /// The node is stopped until the given time.
+    Down { until: Instant },
+    /// The node is catching up with the network before rejoining it.
+    Joining(Box<JoiningNode>),
+}
+
+/// A node catching up with the network.
+struct JoiningNode {
+    runner: Runner<joiner::Reactor>,
+    /// The metrics registry shared by all reactors of the node, as in a real node.
+    registry: Registry,
+}
+
+/// The nodes of a soak test and the configuration they share.
+struct Testnet {
+    keys: Vec<SecretKey>,
+    chainspec: Chainspec,
+    first_node_port: u16,
+    /// The storage of every node, kept across restarts.
+    storages: Vec<(storage::Config, TempDir)>,
+    network: Network<validator::Reactor>,
+    states: Vec<NodeState>,
+}
+
+/// Runs `scenario` to completion, failing on the first violation of safety or liveness.
+pub async fn run(scenario: &Scenario, rng: &mut NodeRng) -> Result<Report, Error> {
+    let start = Instant::now();
+    let mut testnet = Testnet::new(scenario, rng)?;
+    testnet.start(rng).await?;
+
+    let mut events: Vec<_> = scenario.events.iter().collect();
+    events.sort_by_key(|scheduled| scheduled.at);
+    let mut events = events.into_iter().peekable();
+
+    let end = start + Duration::from(scenario.duration);
+    let liveness_timeout = Duration::from(scenario.liveness_timeout);
+    let check_interval = Duration::from(scenario.check_interval);
+    let mut next_check = start;
+    let mut last_progress = start + GENESIS_DELAY;
+    let mut safety = SafetyChecker::default();
+    let mut report = Report::default();
+
+    while Instant::now() < end {
+        let now = Instant::now();
+        while let Some(scheduled) = events
+            .peek()
+            .filter(|scheduled| start + Duration::from(scheduled.at) <= now)
+            .copied()
+        {
+            events.next();
+            info!(at = %scheduled.at, action = ?scheduled.action, "injecting action");
+            testnet.inject(&scheduled.action, &mut report, rng).await;
+        }
+
+        if testnet.crank(rng).await? == 0 {
+            // Only the crate's own tests drive gossip timeouts by a fake clock.
+            #[cfg(test)]
+            FakeClock::advance_time(POLL_INTERVAL.as_millis() as u64);
+            tokio::time::delay_for(POLL_INTERVAL).await;
+        }
+
+        if now >= next_check {
+            next_check = now + check_interval;
+            safety.check(&testnet)?;
+            let height = testnet.highest_block_height();
+            if height > report.highest_block_height {
+                report.highest_block_height = height;
+                last_progress = cmp::max(last_progress, now);
+            } else if now.duration_since(last_progress) > liveness_timeout {
+                return Err(Error::Liveness {
+                    height: height.unwrap_or_default(),
+                    stalled_for: now.duration_since(last_progress).into(),
+                });
+            }
+        }
+    }
+
+    safety.check(&testnet)?;
+    report.highest_block_height = testnet.highest_block_height();
+    Ok(report)
+}
+
+impl Testnet {
+    /// Generates keys for the validators and creates a matching chainspec.
+    fn new(scenario: &Scenario, rng: &mut NodeRng) -> Result<Self, Error> {
+        let keys: Vec<SecretKey> = (0..scenario.validators)
+            .map(|_| SecretKey::random(rng))
+            .collect();
+
+        let mut chainspec = Chainspec::from_file(RESOURCES_PATH.join("local/chainspec.toml"))
+            .map_err(|error| Error::LoadChainspec(error.to_string()))?;
+        chainspec.genesis.accounts = keys
+            .iter()
+            .map(|secret_key| {
+                let public_key = PublicKey::from(secret_key);
+                GenesisAccount::new(
+                    public_key,
+                    public_key.to_account_hash(),
+                    Motes::new(U512::from(ACCOUNT_BALANCE)),
+                    Motes::new(U512::from(rng.gen_range(100, 999))),
+                )
+            })
+            .collect();
+        chainspec.genesis.timestamp = Timestamp::now() + GENESIS_DELAY.into();
+        chainspec.genesis.highway_config.minimum_era_height = scenario.minimum_era_height;
+        chainspec.genesis.highway_config.finality_threshold_fraction = Ratio::new(34, 100);
+        chainspec.genesis.highway_config.era_duration = scenario.era_duration;
+        chainspec.upgrades = scenario
+            .upgrades
+            .iter()
+            .map(|upgrade| UpgradePoint {
+                activation_point: ActivationPoint {
+                    height: upgrade.activation_height,
+                },
+                protocol_version: upgrade.protocol_version.clone(),
+                new_wasm_config: None,
+                new_system_config: None,
+                new_deploy_config: None,
+                new_validator_slots: None,
+                emergency_validators: None,
+            })
+            .collect();
+
+        let storages = keys
+            .iter()
+            .map(|_| storage::Config::default_for_tests())
+            .collect();
+
+        Ok(Testnet {
+            keys,
+            chainspec,
+            first_node_port: super::unused_port_on_localhost(),
+            storages,
+            network: Network::new(),
+            states: Vec::new(),
+        })
+    }
+
+    /// Starts all nodes before genesis.
+    async fn start(&mut self, rng: &mut NodeRng) -> Result<(), Error> {
+        for index in 0..self.keys.len() {
+            let mut joining = self.create_joiner(index, None, rng).await?;
+            joining.runner.run(rng).await;
+            let node_id = self.add_validator(index, joining, rng).await?;
+            self.states.push(NodeState::Running(node_id));
+        }
+        Ok(())
+    }
+
+    /// Creates the configuration of the `index`th validator.
+    fn create_node_config(
+        &self,
+        index: usize,
+        trusted_hash: Option<BlockHash>,
+    ) -> validator::Config {
+        let mut cfg = validator::Config {
+            network: if index == 0 {
+                small_network::Config::default_local_net_first_node(self.first_node_port)
+            } else {
+                small_network::Config::default_local_net(self.first_node_port)
+            },
+            gossip: gossiper::Config::new_with_small_timeouts(),
+            ..Default::default()
+        };
+        cfg.node.chainspec_config_path = External::value(self.chainspec.clone());
+        cfg.node.trusted_hash = trusted_hash;
+        cfg.consensus.secret_key_path = External::value(self.keys[index].duplicate());
+
+        let (storage_cfg, storage_dir) = &self.storages[index];
+        cfg.consensus.unit_hashes_folder = storage_dir.path().to_path_buf();
+        cfg.storage = storage_cfg.clone();
+        cfg
+    }
+
+    /// Runs the initializer of the `index`th node and creates its joiner.
+    async fn create_joiner(
+        &self,
+        index: usize,
+        trusted_hash: Option<BlockHash>,
+        rng: &mut NodeRng,
+    ) -> Result<Box<JoiningNode>, Error> {
+        let start_error = |message: String| Error::StartNode { index, message };
+        let root = RESOURCES_PATH.join("local");
+        let cfg = self.create_node_config(index, trusted_hash);
+        let registry = Registry::new();
+
+        let mut initializer_runner = Runner::<initializer::Reactor>::with_metrics(
+            WithDir::new(root.clone(), cfg),
+            rng,
+            &registry,
+        )
+        .await
+        .map_err(|error| start_error(error.to_string()))?;
+        initializer_runner.run(rng).await;
+        let initializer = initializer_runner.into_inner();
+        if !initializer.stopped_successfully() {
+            return Err(start_error("failed to initialize".to_string()));
+        }
+
+        let runner = Runner::<joiner::Reactor>::with_metrics(
+            WithDir::new(root, initializer),
+            rng,
+            &registry,
+        )
+        .await
+        .map_err(|error| start_error(error.to_string()))?;
+        Ok(Box::new(JoiningNode { runner, registry }))
+    }
+
+    /// Turns a joiner which caught up with the network into a validator and adds it.
+    async fn add_validator(
+        &mut self,
+        index: usize,
+        joining: Box<JoiningNode>,
+        rng: &mut NodeRng,
+    ) -> Result<NodeId, Error> {
+        let JoiningNode { runner, registry } = *joining;
+        let config = runner.into_inner().into_validator_config().await;
+        let (node_id, _) = self
+            .network
+            .add_node_with_metrics(config, rng, &registry)
+            .await
+            .map_err(|error| Error::StartNode {
+                index,
+                message: error.to_string(),
+            })?;
+        info!(index, %node_id, "validator running");
+        Ok(node_id)
+    }
+
+    /// Cranks every node which is not paused or down once, returning the number of events
+    /// processed.
+    async fn crank(&mut self, rng: &mut NodeRng) -> Result<usize, Error> {
+        let now = Instant::now();
+        let mut event_count = 0;
+        for index in 0..self.states.len() {
+            match &mut self.states[index] {
+                NodeState::Running(node_id) => {
+                    let node_id = node_id.clone();
+                    event_count += self.network.crank(&node_id, rng).await;
+                }
+                NodeState::Paused { node_id, until } => {
+                    if *until <= now {
+                        info!(index, "partition healed");
+                        let node_id = node_id.clone();
+                        self.states[index] = NodeState::Running(node_id);
+                    }
+                }
+                NodeState::Down { until } => {
+                    if *until <= now {
+                        info!(index, "restarting node");
+                        let trusted_hash = self.highest_block_hash();
+                        let joining = self.create_joiner(index, trusted_hash, rng).await?;
+                        self.states[index] = NodeState::Joining(joining);
+                    }
+                }
+                NodeState::Joining(joining) => {
+                    if joining.runner.try_crank(rng).await.is_some() {
+                        event_count += 1;
+                    }
+                    if joining.runner.reactor_mut().is_stopped() {
+                        let joining = match std::mem::replace(
+                            &mut self.states[index],
+                            NodeState::Down { until: now },
+                        ) {
+                            NodeState::Joining(joining) => joining,
+                            _ => unreachable!("node is joining"),
+                        };
+                        let node_id = self.add_validator(index, joining, rng).await?;
+                        self.states[index] = NodeState::Running(node_id);
+                    }
+                }
+            }
+        }
+        Ok(event_count)
+    }
+
+    /// Injects an action into the network.
+    async fn inject(&mut self, action: &Action, report: &mut Report, rng: &mut NodeRng) {
+        let now = Instant::now();
+        match action {
+            Action::Restart { node, downtime } => match self.live_node_id(*node) {
+                Some(node_id) => {
+                    // Dropping the node's reactor shuts down its networking.
+                    drop(self.network.remove_node(&node_id));
+                    self.states[*node] = NodeState::Down {
+                        until: now + Duration::from(*downtime),
+                    };
+                    report.restarts += 1;
+                }
+                None => warn!(node, "cannot restart node which is not running"),
+            },
+            Action::Partition { nodes, duration } => {
+                for node in nodes {
+                    match self.live_node_id(*node) {
+                        Some(node_id) => {
+                            self.states[*node] = NodeState::Paused {
+                                node_id,
+                                until: now + Duration::from(*duration),
+                            }
+                        }
+                        None => warn!(node, "cannot partition node which is not running"),
+                    }
+                }
+                report.partitions += 1;
+            }
+            Action::DeployStorm { node, deploys } => match self.live_node_id(*node) {
+                Some(node_id) => {
+                    for _ in 0..*deploys {
+                        let deploy = Box::new(self.create_transfer(rng));
+                        self.network
+                            .process_injected_effect_on(&node_id, |effect_builder| {
+                                effect_builder
+                                    .announce_deploy_received(deploy, None)
+                                    .ignore()
+                            })
+                            .await;
+                    }
+                    report.deploys += deploys;
+                }
+                None => warn!(node, "cannot submit deploys to node which is not running"),
+            },
+        }
+    }
+
+    /// Returns the ID of the `index`th node if it is part of the network.
+    fn live_node_id(&self, index: usize) -> Option<NodeId> {
+        match &self.states[index] {
+            NodeState::Running(node_id) | NodeState::Paused { node_id, .. } => {
+                Some(node_id.clone())
+            }
+            NodeState::Down { .. } | NodeState::Joining(_) => None,
+        }
+    }
+
+    /// Creates a transfer between two random genesis accounts.
+    fn create_transfer(&self, rng: &mut NodeRng) -> Deploy {
+        let source = &self.keys[rng.gen_range(0, self.keys.len())];
+        let target = PublicKey::from(&self.keys[rng.gen_range(0, self.keys.len())]);
+        let payment = ExecutableDeployItem::ModuleBytes {
+            module_bytes: Bytes::new(),
+            args: runtime_args! {
+                ARG_AMOUNT => U512::from(PAYMENT_AMOUNT)
+            },
+        };
+        let session = ExecutableDeployItem::Transfer {
+            args: runtime_args! {
+                "amount" => U512::from(TRANSFER_AMOUNT),
+                "target" => target.to_account_hash().value(),
+                "id" => Option::<u64>::None
+            },
+        };
+        Deploy::new(
+            Timestamp::now(),
+            self.chainspec.genesis.deploy_config.max_ttl,
+            1,
+            vec![],
+            self.chainspec.genesis.name.clone(),
+            payment,
+            session,
+            source,
+            rng,
+        )
+    }
+
+    /// Returns the storage of every node in the network, by index.
+    fn storages(&self) -> impl Iterator<Item = (usize, &storage::Storage)> {
+        let nodes = self.network.nodes();
+        (0..self.states.len()).filter_map(move |index| {
+            let node_id = self.live_node_id(index)?;
+            let runner = nodes.get(&node_id)?;
+            Some((index, runner.reactor().inner().storage()))
+        })
+    }
+
+    /// Returns the height of the highest block stored by any node in the network.
+    fn highest_block_height(&self) -> Option<u64> {
+        self.storages()
+            .filter_map(|(_, storage)| storage.highest_block_height())
+            .max()
+    }
+
+    /// Returns the hash of the highest block stored by any node in the network.
+    fn highest_block_hash(&self) -> Option<BlockHash> {
+        self.storages()
+            .filter_map(|(_, storage)| {
+                let height = storage.highest_block_height()?;
+                Some((height, storage.block_hash_at_height(height)?))
+            })
+            .max_by_key(|(height, _)| *height)
+            .map(|(_, hash)| hash)
+    }
+}
+
+/// Checks that all nodes agree on the block at every height.
+#[derive(Default)]
+struct SafetyChecker {
+    /// The block first seen at every height.
+    blocks: BTreeMap<u64, BlockHash>,
+    /// The heights up to which each node has been checked, exclusive, by index.
+    checked: BTreeMap<usize, u64>,
+}
+
+impl SafetyChecker {
+    /// Checks the blocks stored by every node since the last check.
+    fn check(&mut self, testnet: &Testnet) -> Result<(), Error> {
+        for (index, storage) in testnet.storages() {
+            let highest = match storage.highest_block_height() {
+                Some(highest) => highest,
+                None => continue,
+            };
+            let next = self.checked.entry(index).or_default();
+            for height in *next..=highest {
+                // Nodes which joined from a trusted hash don't have all blocks below it.
+                let hash = match storage.block_hash_at_height(height) {
+                    Some(hash) => hash,
+                    None => continue,
+                };
+                let expected = *self.blocks.entry(height).or_insert(hash);
+                if expected != hash {
+                    return Err(Error::Safety {
+                        index,
+                        height,
+                        expected,
+                        hash,
+                    });
+                }
+            }
+            *next = highest + 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_scenario() {
+        let scenario: Scenario = toml::from_str(
+            r#"
+            validators = 3
+            duration = "10min"
+            liveness_timeout = "1min"
+
+            [[upgrades]]
+            activation_height = 20
+            protocol_version = "1.1.0"
+
+            [[events]]
+            at = "2min"
+            action = "partition"
+            nodes = [0, 2]
+            duration = "30s"
+
+            [[events]]
+            at = "1min"
+            action = "deploy_storm"
+            node = 1
+            deploys = 100
+            "#,
+        )
+        .unwrap();
+        scenario.validate().unwrap();
+
+        assert_eq!(scenario.check_interval, default_check_interval());
+        assert_eq!(scenario.upgrades[0].protocol_version, Version::new(1, 1, 0));
+        assert_eq!(
+            scenario.events[0].action,
+            Action::Partition {
+                nodes: vec![0, 2],
+                duration: Duration::from_secs(30).into(),
+            }
+        );
+        assert_eq!(
+            scenario.events[1].action,
+            Action::DeployStorm {
+                node: 1,
+                deploys: 100,
+            }
+        );
+
+        let invalid = Scenario {
+            validators: 2,
+            ..scenario
+        };
+        assert!(matches!(invalid.validate(), Err(Error::InvalidScenario(_))));
+    }
+}
@@ -1,5 +1,3 @@
-#![cfg(test)]
-
 use std::{
     cell::RefCell,
     cmp, env,
@@ -12,10 +12,13 @@
 //!   [`SIGNATURE_DIGEST`](constant.SIGNATURE_DIGEST.html)),
 //! * construction of TLS acceptors for listening TCP sockets
 //!   ([`create_tls_acceptor`](fn.create_tls_acceptor.html)),
-//! * construction of TLS connectors for outgoing TCP connections
-//!   ([`create_tls_connector`](fn.create_tls_connector.html)),
+//! * construction of TLS connectors for outgoing TCP connections, optionally resuming sessions of
+//!   earlier connections ([`TlsConnector`](struct.TlsConnector.html)),
 //! * creation and validation of self-signed certificates
 //!   ([`generate_node_cert`](fn.generate_node_cert.html)),
+//! * creation and validation of transport certificates issued by node certificates, for TLS stacks
+//!   not supporting the node certificate's curve
+//!   ([`generate_transport_cert`](fn.generate_transport_cert.html)),
 //! * signing and verification of arbitrary values using keys from certificates
 //!   ([`Signature`](struct.Signature.html), [`Signed`](struct.Signed.html)), and
 //! * `serde` support for certificates ([`x509_serde`](x509_serde/index.html))
@@ -26,25 +29,33 @@ use std::{
     fmt::{self, Debug, Display, Formatter},
     hash::Hash,
     marker::PhantomData,
+    net::SocketAddr,
     path::Path,
     str,
+    sync::{Arc, Mutex},
     time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Context;
 use datasize::DataSize;
 use hex_fmt::HexFmt;
+use linked_hash_map::LinkedHashMap;
 use nid::Nid;
+use once_cell::sync::Lazy;
 use openssl::{
     asn1::{Asn1Integer, Asn1IntegerRef, Asn1Time},
     bn::{BigNum, BigNumContext},
     ec,
     error::ErrorStack,
+    ex_data::Index,
     hash::{DigestBytes, MessageDigest},
     nid,
     pkey::{PKey, PKeyRef, Private},
     sha,
-    ssl::{SslAcceptor, SslConnector, SslContextBuilder, SslMethod, SslVerifyMode, SslVersion},
+    ssl::{
+        ConnectConfiguration, Ssl, SslAcceptor, SslConnector, SslContextBuilder, SslMethod,
+        SslOptions, SslSession, SslSessionCacheMode, SslVerifyMode, SslVersion,
+    },
     x509::{X509Builder, X509Name, X509NameBuilder, X509NameRef, X509Ref, X509},
 };
 #[cfg(test)]
@@ -74,6 +85,29 @@ const SIGNATURE_CURVE: Nid = Nid::SECP521R1;
 /// The chosen signature algorithm (**SHA512**).
 const SIGNATURE_DIGEST: Nid = Nid::SHA512;
 
+/// The elliptic curve of transport certificates (**P-256**), see `generate_transport_cert`.
+const TRANSPORT_CURVE: Nid = Nid::X9_62_PRIME256V1;
+
+/// The TLS 1.3 cipher suites supported by OpenSSL, any of which may be configured.
+pub(crate) const TLS13_CIPHER_SUITES: [&str; 5] = [
+    "TLS_AES_256_GCM_SHA384",
+    "TLS_CHACHA20_POLY1305_SHA256",
+    "TLS_AES_128_GCM_SHA256",
+    "TLS_AES_128_CCM_SHA256",
+    "TLS_AES_128_CCM_8_SHA256",
+];
+
+/// Context sessions are bound to on the server side, a session is only resumed within it.
+const SESSION_ID_CONTEXT: &[u8] = b"casper-node";
+
+/// Maximum number of addresses whose sessions are kept for resumption, sessions of the least
+/// recently connected addresses are dropped beyond.
+const MAX_CACHED_SESSIONS: usize = 1024;
+
+/// Index of the peer address on outgoing connections, used to file away their sessions.
+static PEER_ADDRESS_INDEX: Lazy<Index<Ssl, SocketAddr>> =
+    Lazy::new(|| Ssl::new_ex_index().expect("could not allocate ex data index"));
+
 /// OpenSSL result type alias.
 ///
 /// Many functions rely solely on `openssl` functions and return this kind of result.
@@ -281,34 +315,167 @@ pub fn generate_node_cert() -> SslResult<(X509, PKey<Private>)> {
     Ok((cert, private_key))
 }
 
+/// Generates a (key, certificate) pair issued by the node certificate `node_cert`.
+///
+/// The `rustls` TLS stack, used e.g. by QUIC, does not support the P-521 curve of node
+/// certificates. A node instead presents a transport certificate on a curve it supports, followed
+/// by its node certificate, which is checked using `validate_transport_cert`.
+pub(crate) fn generate_transport_cert(
+    node_cert: &X509Ref,
+    node_key: &PKeyRef<Private>,
+) -> SslResult<(X509, PKey<Private>)> {
+    let ec_group = ec::EcGroup::from_curve_name(TRANSPORT_CURVE)?;
+    let private_key = PKey::from_ec_key(ec::EcKey::generate(ec_group.as_ref())?)?;
+
+    let mut builder = X509Builder::new()?;
+    builder.set_version(2)?;
+    // Serial number one is taken by the node certificate.
+    builder.set_serial_number(mknum(2)?.as_ref())?;
+    builder.set_issuer_name(node_cert.subject_name())?;
+    builder
+        .set_subject_name(mkname("US", "Casper Blockchain", "casper-node-transport")?.as_ref())?;
+    builder.set_not_before(node_cert.not_before())?;
+    builder.set_not_after(node_cert.not_after())?;
+
+    // The certificate is signed by the node key, tying it to the node's identity.
+    builder.set_pubkey(private_key.as_ref())?;
+    builder.sign(node_key, Sha512::create_message_digest())?;
+
+    Ok((builder.build(), private_key))
+}
+
+/// Configurable TLS parameters, on top of the fixed ones defined in this crate.
+#[derive(Clone, Debug)]
+pub(crate) struct TlsOptions {
+    /// The TLS 1.3 cipher suites to offer, from `TLS13_CIPHER_SUITES`, in order of preference.
+    ///
+    /// If empty, the OpenSSL defaults are used.
+    pub(crate) cipher_suites: Vec<String>,
+    /// Whether sessions may be resumed when reconnecting, skipping the certificate exchange.
+    pub(crate) session_resumption: bool,
+}
+
+impl Default for TlsOptions {
+    fn default() -> Self {
+        TlsOptions {
+            cipher_suites: Vec::new(),
+            session_resumption: true,
+        }
+    }
+}
+
 /// Creates a TLS acceptor for a server.
 ///
 /// The acceptor will restrict TLS parameters to secure one defined in this crate that are
-/// compatible with connectors built with `create_tls_connector`.
+/// compatible with connectors built with `TlsConnector::new`. Sessions are only resumed if the
+/// very same acceptor issued them, so it should be shared by all incoming connections.
 ///
 /// Incoming certificates must still be validated using `validate_cert`.
 pub(crate) fn create_tls_acceptor(
     cert: &X509Ref,
     private_key: &PKeyRef<Private>,
+    options: &TlsOptions,
 ) -> SslResult<SslAcceptor> {
     let mut builder = SslAcceptor::mozilla_modern_v5(SslMethod::tls_server())?;
-    set_context_options(&mut builder, cert, private_key)?;
+    set_context_options(&mut builder, cert, private_key, options)?;
+
+    if options.session_resumption {
+        // Requesting client certificates makes OpenSSL refuse to resume sessions without a
+        // session ID context.
+        builder.set_session_id_context(SESSION_ID_CONTEXT)?;
+        builder.set_session_cache_mode(SslSessionCacheMode::SERVER);
+    } else {
+        builder.set_options(SslOptions::NO_TICKET);
+        builder.set_session_cache_mode(SslSessionCacheMode::OFF);
+    }
 
     Ok(builder.build())
 }
 
-/// Creates a TLS acceptor for a client.
+/// A TLS connector for outgoing connections, which resumes the sessions of earlier connections to
+/// the same address if enabled.
 ///
-/// A connector compatible with the acceptor created using `create_tls_acceptor`. Server
-/// certificates must always be validated using `validate_cert` after connecting.
-pub(crate) fn create_tls_connector(
-    cert: &X509Ref,
-    private_key: &PKeyRef<Private>,
-) -> SslResult<SslConnector> {
-    let mut builder = SslConnector::builder(SslMethod::tls_client())?;
-    set_context_options(&mut builder, cert, private_key)?;
+/// OpenSSL leaves it to clients to keep sessions around and offer them again, so sessions issued
+/// by servers are kept by their address here, for up to `MAX_CACHED_SESSIONS` addresses. Only one
+/// connector should be created and shared by all outgoing connections, as a session can only be
+/// used with the connector that obtained it.
+#[derive(Clone)]
+pub(crate) struct TlsConnector {
+    /// The underlying connector.
+    connector: SslConnector,
+    /// The most recent session obtained from each address, least recently used first, if
+    /// resumption is enabled.
+    sessions: Option<Arc<Mutex<LinkedHashMap<SocketAddr, SslSession>>>>,
+}
 
-    Ok(builder.build())
+impl TlsConnector {
+    /// Creates a new connector.
+    ///
+    /// The connector is compatible with the acceptor created using `create_tls_acceptor`. Server
+    /// certificates must always be validated using `validate_cert` after connecting.
+    pub(crate) fn new(
+        cert: &X509Ref,
+        private_key: &PKeyRef<Private>,
+        options: &TlsOptions,
+    ) -> SslResult<Self> {
+        let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+        set_context_options(&mut builder, cert, private_key, options)?;
+
+        let sessions = if options.session_resumption {
+            let sessions: Arc<Mutex<LinkedHashMap<SocketAddr, SslSession>>> = Default::default();
+            let new_sessions = Arc::clone(&sessions);
+            builder.set_session_cache_mode(
+                SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL,
+            );
+            builder.set_new_session_callback(move |ssl, session| {
+                if let Some(peer_address) = ssl.ex_data(*PEER_ADDRESS_INDEX) {
+                    let mut sessions = new_sessions.lock().expect("session cache lock poisoned");
+                    sessions.remove(peer_address);
+                    sessions.insert(*peer_address, session);
+                    while sessions.len() > MAX_CACHED_SESSIONS {
+                        sessions.pop_front();
+                    }
+                }
+            });
+            Some(sessions)
+        } else {
+            None
+        };
+
+        Ok(TlsConnector {
+            connector: builder.build(),
+            sessions,
+        })
+    }
+
+    /// Creates the configuration for a connection to `peer_address`, offering to resume the last
+    /// session with it, if any.
+    pub(crate) fn configure(&self, peer_address: SocketAddr) -> SslResult<ConnectConfiguration> {
+        let mut config = self.connector.configure()?;
+
+        if let Some(ref sessions) = self.sessions {
+            config.set_ex_data(*PEER_ADDRESS_INDEX, peer_address);
+            let session = sessions
+                .lock()
+                .expect("session cache lock poisoned")
+                .get_refresh(&peer_address)
+                .cloned();
+            if let Some(session) = session {
+                // Safe, as all sessions were obtained through the context of `self.connector`.
+                unsafe { config.set_session(&session)? };
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+impl Debug for TlsConnector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConnector")
+            .field("session_resumption", &self.sessions.is_some())
+            .finish()
+    }
 }
 
 /// Sets common options of both acceptor and connector on TLS context.
@@ -318,8 +485,12 @@ fn set_context_options(
     ctx: &mut SslContextBuilder,
     cert: &X509Ref,
     private_key: &PKeyRef<Private>,
+    options: &TlsOptions,
 ) -> SslResult<()> {
     ctx.set_min_proto_version(Some(SslVersion::TLS1_3))?;
+    if !options.cipher_suites.is_empty() {
+        ctx.set_ciphersuites(&options.cipher_suites.join(":"))?;
+    }
 
     ctx.set_certificate(cert)?;
     ctx.set_private_key(private_key)?;
@@ -428,6 +599,9 @@ pub enum ValidationError {
         #[source]
         ErrorStack,
     ),
+    /// Transport certificate not issued by the node certificate presented along with it.
+    #[error("transport certificate was not issued by the node certificate")]
+    WrongIssuer,
 }
 
 /// Checks that the cryptographic parameters on a certificate are correct and returns the
@@ -459,22 +633,7 @@ pub(crate) fn validate_cert(cert: X509) -> Result<TlsCert, ValidationError> {
     }
 
     // Check expiration times against current time.
-    let asn1_now = Asn1Time::from_unix(now()).map_err(ValidationError::TimeIssue)?;
-    if asn1_now
-        .compare(cert.not_before())
-        .map_err(ValidationError::TimeIssue)?
-        != Ordering::Greater
-    {
-        return Err(ValidationError::NotYetValid);
-    }
-
-    if asn1_now
-        .compare(cert.not_after())
-        .map_err(ValidationError::TimeIssue)?
-        != Ordering::Less
-    {
-        return Err(ValidationError::Expired);
-    }
+    check_validity_period(&cert)?;
 
     // Ensure that the key is using the correct curve parameters.
     let public_key = cert
@@ -530,6 +689,67 @@ pub(crate) fn validate_cert(cert: X509) -> Result<TlsCert, ValidationError> {
     })
 }
 
+/// Checks that the transport certificate `transport_cert` was issued by the node certificate
+/// `node_cert`, see `generate_transport_cert`, and validates the latter.
+///
+/// Returns the validated node certificate, from which the peer's identity is derived as for any
+/// other connection.
+pub(crate) fn validate_transport_cert(
+    transport_cert: &X509Ref,
+    node_cert: X509,
+) -> Result<TlsCert, ValidationError> {
+    let node_cert = validate_cert(node_cert)?;
+
+    if transport_cert.signature_algorithm().object().nid() != SIGNATURE_ALGORITHM {
+        return Err(ValidationError::WrongSignatureAlgorithm);
+    }
+
+    let issuer = name_to_string(transport_cert.issuer_name())
+        .map_err(ValidationError::CorruptSubjectOrIssuer)?;
+    let node_subject = name_to_string(node_cert.x509.subject_name())
+        .map_err(ValidationError::CorruptSubjectOrIssuer)?;
+    if issuer != node_subject {
+        return Err(ValidationError::WrongIssuer);
+    }
+
+    check_validity_period(transport_cert)?;
+
+    let node_public_key = node_cert
+        .x509
+        .public_key()
+        .map_err(ValidationError::CannotReadPublicKey)?;
+    if !transport_cert
+        .verify(&node_public_key)
+        .map_err(ValidationError::FailedToValidateSignature)?
+    {
+        return Err(ValidationError::InvalidSignature);
+    }
+
+    Ok(node_cert)
+}
+
+/// Checks that the current time lies within the validity period of `cert`.
+fn check_validity_period(cert: &X509Ref) -> Result<(), ValidationError> {
+    let asn1_now = Asn1Time::from_unix(now()).map_err(ValidationError::TimeIssue)?;
+    if asn1_now
+        .compare(cert.not_before())
+        .map_err(ValidationError::TimeIssue)?
+        != Ordering::Greater
+    {
+        return Err(ValidationError::NotYetValid);
+    }
+
+    if asn1_now
+        .compare(cert.not_after())
+        .map_err(ValidationError::TimeIssue)?
+        != Ordering::Less
+    {
+        return Err(ValidationError::Expired);
+    }
+
+    Ok(())
+}
+
 /// Loads a certificate from a file.
 pub(crate) fn load_cert<P: AsRef<Path>>(src: P) -> anyhow::Result<X509> {
     let pem = read_file(src.as_ref()).with_context(|| "failed to load certificate")?;
@@ -817,7 +1037,17 @@ impl Hash for Sha512 {
 
 #[cfg(test)]
 mod test {
-    use super::{generate_node_cert, mkname, name_to_string, validate_cert, TlsCert};
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use super::{
+        create_tls_acceptor, generate_node_cert, generate_transport_cert, mkname, name_to_string,
+        validate_cert, validate_transport_cert, TlsCert, TlsConnector, TlsOptions, ValidationError,
+        TLS13_CIPHER_SUITES,
+    };
 
     #[test]
     fn simple_name_to_string() {
@@ -843,4 +1073,89 @@ mod test {
 
         assert_eq!(serialized, serialized_again);
     }
+
+    #[test]
+    fn should_accept_configured_cipher_suites() {
+        let (cert, private_key) = generate_node_cert().expect("failed to generate key, cert pair");
+        let options = TlsOptions {
+            cipher_suites: TLS13_CIPHER_SUITES
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            session_resumption: false,
+        };
+
+        assert!(create_tls_acceptor(&cert, &private_key, &options).is_ok());
+        assert!(TlsConnector::new(&cert, &private_key, &options).is_ok());
+
+        let options = TlsOptions {
+            cipher_suites: vec!["TLS_NOT_A_CIPHER_SUITE".to_string()],
+            ..Default::default()
+        };
+        assert!(create_tls_acceptor(&cert, &private_key, &options).is_err());
+    }
+
+    #[test]
+    fn should_resume_session_when_reconnecting() {
+        let (cert, private_key) = generate_node_cert().expect("failed to generate key, cert pair");
+        let options = TlsOptions::default();
+        let acceptor = create_tls_acceptor(&cert, &private_key, &options).unwrap();
+        let connector = TlsConnector::new(&cert, &private_key, &options).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                let mut tls_stream = acceptor.accept(stream).unwrap();
+                tls_stream.write_all(b"x").unwrap();
+                // Wait for the client to hang up.
+                let _ = tls_stream.read(&mut [0; 1]);
+            }
+        });
+
+        let connect = || {
+            let mut config = connector.configure(address).unwrap();
+            config.set_verify_hostname(false);
+            let stream = TcpStream::connect(address).unwrap();
+            let mut tls_stream = config
+                .connect("this-will-not-be-checked.example.com", stream)
+                .unwrap();
+            // The session tickets are processed when reading the data sent after them.
+            tls_stream.read_exact(&mut [0; 1]).unwrap();
+            tls_stream.ssl().session_reused()
+        };
+        assert!(!connect(), "first connection should not resume a session");
+        assert!(connect(), "second connection should resume the session");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn should_validate_transport_cert_issued_by_node_cert() {
+        let (cert, private_key) = generate_node_cert().expect("failed to generate key, cert pair");
+        let (transport_cert, _transport_key) = generate_transport_cert(&cert, &private_key)
+            .expect("failed to generate transport cert");
+
+        let node_cert = validate_transport_cert(&transport_cert, cert.clone())
+            .expect("transport cert should be valid");
+        assert_eq!(
+            node_cert.public_key_fingerprint(),
+            validate_cert(cert)
+                .expect("generated cert is not valid")
+                .public_key_fingerprint()
+        );
+    }
+
+    #[test]
+    fn should_reject_transport_cert_issued_by_other_node() {
+        let (cert, private_key) = generate_node_cert().expect("failed to generate key, cert pair");
+        let (other_cert, _) = generate_node_cert().expect("failed to generate key, cert pair");
+        let (transport_cert, _transport_key) = generate_transport_cert(&cert, &private_key)
+            .expect("failed to generate transport cert");
+
+        assert!(matches!(
+            validate_transport_cert(&transport_cert, other_cert),
+            Err(ValidationError::InvalidSignature)
+        ));
+    }
 }
@@ -1,12 +1,20 @@
 //! Common types used across multiple components.
 
+mod attestation;
+mod ban_list;
 mod block;
 mod deploy;
+mod feature_flags;
 mod item;
 pub mod json_compatibility;
+mod maintenance;
+mod network_time;
 mod node_config;
 mod node_id;
+mod peer_sample;
 mod peers_map;
+mod protocol_version_histogram;
+mod request_audit;
 mod status_feed;
 mod timestamp;
 
@@ -14,6 +22,8 @@ use rand::{CryptoRng, RngCore};
 #[cfg(not(test))]
 use rand_chacha::ChaCha20Rng;
 
+pub use attestation::{SignedAttestation, SoftwareAttestation};
+pub use ban_list::{BanEntry, BanListError, BanListPolicy, BanTarget, ImportedBan, SignedBanList};
 pub use block::{
     json_compatibility::JsonBlock, Block, BlockHash, BlockHeader, BlockValidationError,
     FinalitySignature,
@@ -21,13 +31,20 @@ pub use block::{
 pub(crate) use block::{BlockByHeight, BlockLike, FinalizedBlock, ProtoBlock};
 pub use deploy::{
     Approval, Deploy, DeployHash, DeployHeader, DeployMetadata, DeployValidationFailure,
-    Error as DeployError,
+    Error as DeployError, ARG_MIN_PROTOCOL_VERSION,
 };
+pub use feature_flags::FeatureFlags;
 pub use item::{Item, Tag};
+pub use maintenance::{MaintenanceConfig, MaintenanceWindow};
+pub use network_time::NetworkTimeEstimate;
+pub(crate) use network_time::TimeSample;
 pub use node_config::NodeConfig;
 pub(crate) use node_id::NodeId;
+pub use peer_sample::{PeerDirection, PeerSample, SampledPeer};
 pub use peers_map::PeersMap;
-pub use status_feed::{GetStatusResult, StatusFeed};
+pub use protocol_version_histogram::ProtocolVersionHistogram;
+pub use request_audit::{PeerRequestAudit, RequestClass, RequestClassAudit};
+pub use status_feed::{ComponentStatus, GetStatusResult, StatusFeed};
 pub use timestamp::{TimeDiff, Timestamp};
 
 /// An object-safe RNG trait that requires a cryptographically strong random number generator.
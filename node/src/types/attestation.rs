@@ -0,0 +1,182 @@
+// TODO - remove once schemars stops causing warning.
+#![allow(clippy::field_reassign_with_default)]
+
+use std::{env, fs, io};
+
+use datasize::DataSize;
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use casper_types::{PublicKey, SecretKey, Signature};
+
+use crate::{
+    crypto::{
+        self,
+        hash::{self, Digest},
+        AsymmetricKeyExt,
+    },
+    rpcs::docs::DocExample,
+    types::Timestamp,
+    Chainspec, NodeRng,
+};
+
+static SIGNED_ATTESTATION: Lazy<SignedAttestation> = Lazy::new(|| {
+    let secret_key = SecretKey::doc_example();
+    let validator = PublicKey::from(secret_key);
+    let created = *Timestamp::doc_example();
+    let attestation = SoftwareAttestation {
+        binary_hash: Digest::from([3u8; Digest::LENGTH]),
+        protocol_version: Version::new(1, 0, 0),
+        chainspec_hash: Digest::from([4u8; Digest::LENGTH]),
+    };
+    // Ed25519 signatures are deterministic, so the example is the same on every run.
+    let bytes = SignedAttestation::signed_bytes(&validator, created, &attestation);
+    let signature = crypto::sign(bytes, secret_key, &validator, &mut crate::new_rng());
+    SignedAttestation {
+        validator,
+        created,
+        attestation,
+        signature,
+    }
+});
+
+/// The software a node runs, as attested to by a validator.
+#[derive(
+    Clone, DataSize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(deny_unknown_fields)]
+pub struct SoftwareAttestation {
+    /// The hash of the node binary.
+    pub binary_hash: Digest,
+    /// The latest protocol version the node's chainspec supports.
+    #[data_size(skip)]
+    #[schemars(with = "String")]
+    pub protocol_version: Version,
+    /// The hash of the node's chainspec.
+    pub chainspec_hash: Digest,
+}
+
+impl SoftwareAttestation {
+    /// Describes the running binary, with the given chainspec.
+    ///
+    /// Reads the whole binary to hash it, so this is best done once at startup.
+    pub fn of_running_node(chainspec: &Chainspec) -> io::Result<Self> {
+        let binary = fs::read(env::current_exe()?)?;
+        Ok(SoftwareAttestation {
+            binary_hash: hash::hash(&binary),
+            protocol_version: chainspec.latest_protocol_version(),
+            chainspec_hash: chainspec.hash(),
+        })
+    }
+}
+
+/// A software attestation signed by the validator making it.
+///
+/// The signature only proves which validator made the attestation, not that it runs the attested
+/// software: attestations are informational, e.g. to tell how many validators run a patched
+/// binary during a security incident.
+#[derive(Clone, DataSize, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SignedAttestation {
+    /// The public key of the attesting validator.
+    validator: PublicKey,
+    /// When the attestation was signed.
+    created: Timestamp,
+    /// The attested software.
+    attestation: SoftwareAttestation,
+    /// The validator's signature over all of the above.
+    signature: Signature,
+}
+
+impl SignedAttestation {
+    /// Signs the given attestation with the validator's secret key.
+    pub fn sign(
+        attestation: SoftwareAttestation,
+        secret_key: &SecretKey,
+        rng: &mut NodeRng,
+    ) -> Self {
+        let validator = PublicKey::from(secret_key);
+        let created = Timestamp::now();
+        let bytes = Self::signed_bytes(&validator, created, &attestation);
+        let signature = crypto::sign(bytes, secret_key, &validator, rng);
+        SignedAttestation {
+            validator,
+            created,
+            attestation,
+            signature,
+        }
+    }
+
+    /// Checks that the signature was made by the validator over the attestation.
+    pub fn verify(&self) -> Result<(), crypto::Error> {
+        let bytes = Self::signed_bytes(&self.validator, self.created, &self.attestation);
+        crypto::verify(bytes, &self.signature, &self.validator)
+    }
+
+    /// Returns the public key of the attesting validator.
+    pub fn validator(&self) -> &PublicKey {
+        &self.validator
+    }
+
+    /// Returns when the attestation was signed.
+    pub fn created(&self) -> Timestamp {
+        self.created
+    }
+
+    /// Returns the attested software.
+    pub fn attestation(&self) -> &SoftwareAttestation {
+        &self.attestation
+    }
+
+    fn signed_bytes(
+        validator: &PublicKey,
+        created: Timestamp,
+        attestation: &SoftwareAttestation,
+    ) -> Vec<u8> {
+        bincode::serialize(&(validator, created, attestation))
+            .expect("should serialize attestation")
+    }
+}
+
+impl DocExample for SignedAttestation {
+    fn doc_example() -> &'static Self {
+        &*SIGNED_ATTESTATION
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attestation() -> SoftwareAttestation {
+        SoftwareAttestation {
+            binary_hash: hash::hash(b"casper-node"),
+            protocol_version: Version::new(1, 0, 0),
+            chainspec_hash: hash::hash(b"chainspec"),
+        }
+    }
+
+    #[test]
+    fn should_verify_after_bincode_roundtrip() {
+        let mut rng = crate::new_rng();
+        let secret_key = SecretKey::random(&mut rng);
+        let signed = SignedAttestation::sign(attestation(), &secret_key, &mut rng);
+
+        let decoded: SignedAttestation =
+            bincode::deserialize(&bincode::serialize(&signed).unwrap()).unwrap();
+        assert_eq!(decoded, signed);
+        decoded.verify().unwrap();
+    }
+
+    #[test]
+    fn should_reject_tampered_attestation() {
+        let mut rng = crate::new_rng();
+        let secret_key = SecretKey::random(&mut rng);
+        let mut signed = SignedAttestation::sign(attestation(), &secret_key, &mut rng);
+
+        signed.attestation.binary_hash = hash::hash(b"patched casper-node");
+        assert!(signed.verify().is_err());
+    }
+}
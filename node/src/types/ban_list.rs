@@ -0,0 +1,233 @@
+// TODO - remove once schemars stops causing warning.
+#![allow(clippy::field_reassign_with_default)]
+
+use std::{
+    fmt::{self, Display, Formatter},
+    net::IpAddr,
+    path::Path,
+};
+
+use datasize::DataSize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use casper_types::{PublicKey, SecretKey, Signature};
+
+use crate::{
+    crypto,
+    types::{NodeId, Timestamp},
+    utils::{self, ReadFileError},
+    NodeRng,
+};
+
+/// Error reading or verifying a signed ban list.
+#[derive(Debug, Error)]
+pub enum BanListError {
+    /// The ban list file could not be read.
+    #[error(transparent)]
+    ReadFile(#[from] ReadFileError),
+    /// The ban list is not valid JSON.
+    #[error("could not parse ban list: {0}")]
+    Parse(#[from] serde_json::Error),
+    /// The signature does not match the issuer and contents.
+    #[error("invalid ban list signature: {0}")]
+    InvalidSignature(#[source] crypto::Error),
+}
+
+/// A peer to be banned, either by node ID or by IP address.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BanTarget {
+    /// A node with the given ID, wherever it connects from.
+    NodeId(#[schemars(with = "String")] NodeId),
+    /// Any node connecting from or listening on the given IP address.
+    Ip(#[schemars(with = "String")] IpAddr),
+}
+
+impl BanTarget {
+    /// Returns whether a peer with the given ID and IP address is covered by this target.
+    pub(crate) fn matches(&self, peer_id: Option<&NodeId>, peer_ip: IpAddr) -> bool {
+        match self {
+            BanTarget::NodeId(node_id) => peer_id == Some(node_id),
+            BanTarget::Ip(ip) => *ip == peer_ip,
+        }
+    }
+}
+
+impl Display for BanTarget {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BanTarget::NodeId(node_id) => write!(formatter, "{}", node_id),
+            BanTarget::Ip(ip) => write!(formatter, "{}", ip),
+        }
+    }
+}
+
+/// A single entry of a ban list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BanEntry {
+    /// The banned peer.
+    pub target: BanTarget,
+    /// Why the peer was banned, for the benefit of other operators.
+    pub reason: String,
+    /// When the ban ends, or `None` for a permanent ban.
+    pub expires: Option<Timestamp>,
+}
+
+impl BanEntry {
+    /// Returns whether the ban is still in effect at `now`.
+    pub fn is_active(&self, now: Timestamp) -> bool {
+        self.expires.map_or(true, |expires| now < expires)
+    }
+}
+
+/// A ban list signed by the operator who issued it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignedBanList {
+    /// The public key of the issuing operator.
+    issuer: PublicKey,
+    /// When the list was signed.
+    created: Timestamp,
+    /// The banned peers.
+    entries: Vec<BanEntry>,
+    /// The issuer's signature over all of the above.
+    signature: Signature,
+}
+
+impl SignedBanList {
+    /// Signs the given entries with the issuer's secret key.
+    pub fn sign(entries: Vec<BanEntry>, secret_key: &SecretKey, rng: &mut NodeRng) -> Self {
+        let issuer = PublicKey::from(secret_key);
+        let created = Timestamp::now();
+        let bytes = Self::signed_bytes(&issuer, created, &entries);
+        let signature = crypto::sign(bytes, secret_key, &issuer, rng);
+        SignedBanList {
+            issuer,
+            created,
+            entries,
+            signature,
+        }
+    }
+
+    /// Reads a ban list from a JSON file and verifies its signature.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, BanListError> {
+        let data = utils::read_file(path)?;
+        let ban_list: SignedBanList = serde_json::from_slice(&data)?;
+        ban_list.verify()?;
+        Ok(ban_list)
+    }
+
+    /// Encodes the ban list as JSON, in the format expected by `from_file`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("should serialize ban list")
+    }
+
+    /// Checks that the signature was made by the issuer over the list's contents.
+    pub fn verify(&self) -> Result<(), BanListError> {
+        let bytes = Self::signed_bytes(&self.issuer, self.created, &self.entries);
+        crypto::verify(bytes, &self.signature, &self.issuer).map_err(BanListError::InvalidSignature)
+    }
+
+    /// Returns the public key of the issuing operator.
+    pub fn issuer(&self) -> &PublicKey {
+        &self.issuer
+    }
+
+    /// Returns when the list was signed.
+    pub fn created(&self) -> Timestamp {
+        self.created
+    }
+
+    /// Returns the banned peers.
+    pub fn entries(&self) -> &[BanEntry] {
+        &self.entries
+    }
+
+    fn signed_bytes(issuer: &PublicKey, created: Timestamp, entries: &[BanEntry]) -> Vec<u8> {
+        bincode::serialize(&(issuer, created, entries)).expect("should serialize ban list")
+    }
+}
+
+/// How entries of signed ban lists from trusted issuers are applied.
+#[derive(Copy, Clone, DataSize, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BanListPolicy {
+    /// Refuse connections to and from banned peers.
+    Enforce,
+    /// Only log connections to and from banned peers.
+    LogOnly,
+}
+
+impl Default for BanListPolicy {
+    fn default() -> Self {
+        BanListPolicy::Enforce
+    }
+}
+
+/// An entry of an imported ban list, as reported in diagnostics.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ImportedBan {
+    /// The public key of the operator who issued the ban.
+    pub issuer: PublicKey,
+    /// The ban itself.
+    #[serde(flatten)]
+    pub entry: BanEntry,
+    /// Whether connections to the banned peer are refused, rather than only logged.
+    pub enforced: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+    use crate::{crypto::AsymmetricKeyExt, testing::TestRng};
+
+    fn entries(rng: &mut TestRng) -> Vec<BanEntry> {
+        vec![
+            BanEntry {
+                target: BanTarget::NodeId(NodeId::random_tls(rng)),
+                reason: "sent invalid blocks".to_string(),
+                expires: None,
+            },
+            BanEntry {
+                target: BanTarget::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+                reason: "connection flooding".to_string(),
+                expires: Some(Timestamp::from(1000)),
+            },
+        ]
+    }
+
+    #[test]
+    fn should_verify_after_json_roundtrip() {
+        let mut rng = crate::new_rng();
+        let secret_key = SecretKey::random(&mut rng);
+        let ban_list = SignedBanList::sign(entries(&mut rng), &secret_key, &mut rng);
+
+        let decoded: SignedBanList = serde_json::from_str(&ban_list.to_json()).unwrap();
+        assert_eq!(decoded, ban_list);
+        decoded.verify().unwrap();
+    }
+
+    #[test]
+    fn should_reject_tampered_list() {
+        let mut rng = crate::new_rng();
+        let secret_key = SecretKey::random(&mut rng);
+        let mut ban_list = SignedBanList::sign(entries(&mut rng), &secret_key, &mut rng);
+
+        ban_list.entries.pop();
+        assert!(ban_list.verify().is_err());
+    }
+
+    #[test]
+    fn should_expire_entries() {
+        let mut rng = crate::new_rng();
+        let entries = entries(&mut rng);
+        assert!(entries[0].is_active(Timestamp::from(u64::MAX)));
+        assert!(entries[1].is_active(Timestamp::from(999)));
+        assert!(!entries[1].is_active(Timestamp::from(1000)));
+    }
+}
@@ -9,6 +9,7 @@ use std::{
     error::Error as StdError,
     fmt::{self, Debug, Display, Formatter},
     hash::Hash,
+    mem,
 };
 
 use blake2::{
@@ -18,7 +19,7 @@ use blake2::{
 
 use datasize::DataSize;
 use hex::FromHexError;
-use hex_fmt::{HexFmt, HexList};
+use hex_fmt::HexList;
 use once_cell::sync::Lazy;
 #[cfg(test)]
 use rand::Rng;
@@ -474,9 +475,9 @@ impl Display for FinalizedBlock {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
         write!(
             formatter,
-            "finalized block {:10} in era {:?}, height {}, deploys {:10}, random bit {}, \
+            "finalized block {} in era {:?}, height {}, deploys {:10}, random bit {}, \
             timestamp {}",
-            HexFmt(self.proto_block.hash().inner()),
+            self.proto_block.hash().inner(),
             self.era_id,
             self.height,
             HexList(&self.proto_block.wasm_deploys),
@@ -899,6 +900,27 @@ impl Block {
         self.header.height()
     }
 
+    /// Removes the deploy and transfer hashes from the block, returning them.
+    ///
+    /// The block hash is left untouched, so the block only verifies again once the hashes have
+    /// been put back using `restore_deploy_and_transfer_hashes`.
+    pub(crate) fn take_deploy_and_transfer_hashes(&mut self) -> (Vec<DeployHash>, Vec<DeployHash>) {
+        (
+            mem::take(&mut self.header.deploy_hashes),
+            mem::take(&mut self.header.transfer_hashes),
+        )
+    }
+
+    /// Puts back the deploy and transfer hashes removed by `take_deploy_and_transfer_hashes`.
+    pub(crate) fn restore_deploy_and_transfer_hashes(
+        &mut self,
+        deploy_hashes: Vec<DeployHash>,
+        transfer_hashes: Vec<DeployHash>,
+    ) {
+        self.header.deploy_hashes = deploy_hashes;
+        self.header.transfer_hashes = transfer_hashes;
+    }
+
     /// Appends the given signature to this block's proofs.  It should have been validated prior to
     /// this via `BlockHash::verify()`.
     pub(crate) fn append_proof(
@@ -4,6 +4,7 @@
 use std::{
     array::TryFromSliceError,
     collections::HashMap,
+    convert::TryFrom,
     error::Error as StdError,
     fmt::{self, Debug, Display, Formatter},
 };
@@ -16,19 +17,21 @@ use once_cell::sync::Lazy;
 #[cfg(test)]
 use rand::{Rng, RngCore};
 use schemars::JsonSchema;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::warn;
 
 use casper_execution_engine::{
     core::engine_state::{executable_deploy_item::ExecutableDeployItem, DeployItem},
-    shared::motes::Motes,
+    shared::{gas::Gas, motes::Motes},
 };
 use casper_types::{
     bytesrepr::{self, FromBytes, ToBytes},
     runtime_args,
     standard_payment::ARG_AMOUNT,
-    AsymmetricType, ExecutionResult, PublicKey, RuntimeArgs, SecretKey, Signature, U512,
+    AsymmetricType, ExecutionResult, ProtocolVersion, PublicKey, RuntimeArgs, SecretKey, Signature,
+    U512,
 };
 
 use super::{BlockHash, Item, Tag, TimeDiff, Timestamp};
@@ -46,6 +49,13 @@ use crate::{
     NodeRng,
 };
 
+/// Name of the payment arg by which a deploy declares the minimum protocol version it requires, as
+/// a string like "1.1.0".
+///
+/// Being part of the body, the requirement is covered by the deploy's hash and approvals, and the
+/// standard payment ignores it.
+pub const ARG_MIN_PROTOCOL_VERSION: &str = "min_protocol_version";
+
 static DEPLOY: Lazy<Deploy> = Lazy::new(|| {
     let payment_args = runtime_args! {
         "quantity" => 1000
@@ -126,6 +136,53 @@ pub enum DeployValidationFailure {
         got: TimeDiff,
     },
 
+    /// Insufficient time-to-live.
+    #[error("time-to-live of {got} is below minimum of {min_ttl}")]
+    InsufficientTimeToLive {
+        /// The minimum time-to-live.
+        min_ttl: TimeDiff,
+        /// The received time-to-live.
+        got: TimeDiff,
+    },
+
+    /// Deploy timestamp is too far in the future.
+    #[error(
+        "timestamp of {got} is later than {max_timestamp_drift} past current time {current_time}"
+    )]
+    TimestampInFuture {
+        /// The time the deploy was validated at.
+        current_time: Timestamp,
+        /// How far the timestamp may be in the future.
+        max_timestamp_drift: TimeDiff,
+        /// The received timestamp.
+        got: Timestamp,
+    },
+
+    /// Blocks cannot contain any deploy of this kind.
+    #[error("the chainspec does not allow any {kind} in a block")]
+    ExcludedFromBlocks {
+        /// The kind of deploy, e.g. "transfers".
+        kind: String,
+    },
+
+    /// The deploy is larger than a whole block may be.
+    #[error("deploy size of {got} bytes exceeds block size limit of {max_block_size}")]
+    ExcessiveSize {
+        /// The block size limit in bytes.
+        max_block_size: u32,
+        /// The serialized size of the deploy in bytes.
+        got: usize,
+    },
+
+    /// The deploy pays for more gas than a whole block may use.
+    #[error("payment for {got} gas exceeds block gas limit of {block_gas_limit}")]
+    ExcessiveGasLimit {
+        /// The block gas limit.
+        block_gas_limit: u64,
+        /// The amount of gas paid for by the deploy.
+        got: U512,
+    },
+
     /// The provided body hash does not match the actual hash of the body.
     #[error("the provided body hash does not match the actual hash of the body")]
     InvalidBodyHash,
@@ -142,6 +199,22 @@ pub enum DeployValidationFailure {
         /// The approval validation error.
         error_msg: String,
     },
+
+    /// The minimum protocol version declared by the deploy is not a version.
+    #[error("invalid minimum protocol version: {got}")]
+    InvalidMinProtocolVersion {
+        /// The declared value.
+        got: String,
+    },
+
+    /// The deploy requires a later protocol version than the current one.
+    #[error("deploy requires protocol version {required}, but the current one is {current}")]
+    UnsupportedProtocolVersion {
+        /// The minimum protocol version required by the deploy.
+        required: String,
+        /// The current protocol version.
+        current: String,
+    },
 }
 
 /// Errors other than validation failures relating to `Deploy`s.
@@ -158,6 +231,10 @@ pub enum Error {
     /// Failed to get "amount" from `payment()`'s runtime args.
     #[error("invalid payment: missing \"amount\" arg")]
     InvalidPayment,
+
+    /// The minimum protocol version declared in `payment()`'s runtime args is invalid.
+    #[error("invalid payment: {0}")]
+    InvalidMinProtocolVersion(DeployValidationFailure),
 }
 
 impl From<FromHexError> for Error {
@@ -533,6 +610,9 @@ impl Deploy {
     pub fn deploy_type(&self) -> Result<DeployType, Error> {
         let header = self.header().clone();
         let size = self.serialized_length();
+        let min_protocol_version = self
+            .min_protocol_version()
+            .map_err(Error::InvalidMinProtocolVersion)?;
         if self.session().is_transfer() {
             // TODO: we need a non-zero value constant for wasm-less transfer cost.
             let payment_amount = Motes::zero();
@@ -540,6 +620,7 @@ impl Deploy {
                 header,
                 payment_amount,
                 size,
+                min_protocol_version,
             })
         } else {
             let payment_item = self.payment().clone();
@@ -562,10 +643,48 @@ impl Deploy {
                 header,
                 payment_amount,
                 size,
+                min_protocol_version,
             })
         }
     }
 
+    /// Returns the minimum protocol version the deploy requires, if it declares one via the
+    /// `ARG_MIN_PROTOCOL_VERSION` payment arg.
+    pub fn min_protocol_version(&self) -> Result<Option<Version>, DeployValidationFailure> {
+        let value = match self.payment().args().get(ARG_MIN_PROTOCOL_VERSION) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let version = value.clone().into_t::<String>().map_err(|_| {
+            DeployValidationFailure::InvalidMinProtocolVersion {
+                got: format!("value of type {:?}", value.cl_type()),
+            }
+        })?;
+        match Version::parse(&version) {
+            Ok(parsed) if to_protocol_version(&parsed).is_some() => Ok(Some(parsed)),
+            _ => Err(DeployValidationFailure::InvalidMinProtocolVersion { got: version }),
+        }
+    }
+
+    /// Returns an error if the deploy requires a later protocol version than `current`.
+    pub fn check_protocol_version(&self, current: &Version) -> Result<(), DeployValidationFailure> {
+        match self.min_protocol_version()? {
+            Some(required) if required > *current => {
+                warn!(
+                    deploy_hash = %self.id(),
+                    %required,
+                    %current,
+                    "deploy requires a later protocol version"
+                );
+                Err(DeployValidationFailure::UnsupportedProtocolVersion {
+                    required: required.to_string(),
+                    current: current.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Returns true if and only if:
     ///   * the deploy hash is correct (should be the hash of the header), and
     ///   * the body hash is correct (should be the hash of the body), and
@@ -583,7 +702,7 @@ impl Deploy {
 
     /// Returns true if and only if:
     ///   * the chain_name is correct,
-    ///   * the configured parameters are complied with,
+    ///   * the configured parameters are complied with at `current_time`,
     ///   * the deploy is valid
     ///
     /// Note: if everything else checks out, calls the computationally expensive `is_valid` method.
@@ -591,6 +710,7 @@ impl Deploy {
         &mut self,
         chain_name: String,
         config: DeployConfig,
+        current_time: Timestamp,
     ) -> Result<(), DeployValidationFailure> {
         let header = self.header();
         if header.chain_name() != chain_name {
@@ -632,9 +752,96 @@ impl Deploy {
             });
         }
 
+        if header.ttl() < config.min_ttl {
+            warn!(
+                deploy_hash = %self.id(),
+                deploy_header = %header,
+                min_ttl = %config.min_ttl,
+                "deploy ttl insufficient"
+            );
+            return Err(DeployValidationFailure::InsufficientTimeToLive {
+                min_ttl: config.min_ttl,
+                got: header.ttl(),
+            });
+        }
+
+        if header.timestamp() > current_time + config.max_timestamp_drift {
+            warn!(
+                deploy_hash = %self.id(),
+                deploy_header = %header,
+                %current_time,
+                max_timestamp_drift = %config.max_timestamp_drift,
+                "deploy timestamp in the future"
+            );
+            return Err(DeployValidationFailure::TimestampInFuture {
+                current_time,
+                max_timestamp_drift: config.max_timestamp_drift,
+                got: header.timestamp(),
+            });
+        }
+
+        self.fits_in_block(&config)?;
+
         self.is_valid()
     }
 
+    /// Returns an error if the deploy could never be included in a block under the limits of
+    /// `config`, so that it is not kept around waiting for a chance that never comes.
+    fn fits_in_block(&self, config: &DeployConfig) -> Result<(), DeployValidationFailure> {
+        if self.session().is_transfer() {
+            if config.block_max_transfer_count == 0 {
+                warn!(deploy_hash = %self.id(), "transfers excluded from blocks");
+                return Err(DeployValidationFailure::ExcludedFromBlocks {
+                    kind: "transfers".to_string(),
+                });
+            }
+            // Transfers are not subject to the size and gas limits of blocks.
+            return Ok(());
+        }
+
+        if config.block_max_deploy_count == 0 {
+            warn!(deploy_hash = %self.id(), "deploys excluded from blocks");
+            return Err(DeployValidationFailure::ExcludedFromBlocks {
+                kind: "deploys".to_string(),
+            });
+        }
+
+        let size = self.serialized_length();
+        if size > config.max_block_size as usize {
+            warn!(
+                deploy_hash = %self.id(),
+                size,
+                max_block_size = %config.max_block_size,
+                "deploy larger than a block"
+            );
+            return Err(DeployValidationFailure::ExcessiveSize {
+                max_block_size: config.max_block_size,
+                got: size,
+            });
+        }
+
+        // Deploys without a standard payment amount are judged by the block proposer instead.
+        let payment_amount = match self.deploy_type() {
+            Ok(deploy_type) => deploy_type.payment_amount(),
+            Err(_) => return Ok(()),
+        };
+        if let Some(gas) = Gas::from_motes(payment_amount, self.header().gas_price()) {
+            if gas > Gas::from(config.block_gas_limit) {
+                warn!(
+                    deploy_hash = %self.id(),
+                    %gas,
+                    block_gas_limit = %config.block_gas_limit,
+                    "deploy gas exceeds block gas limit"
+                );
+                return Err(DeployValidationFailure::ExcessiveGasLimit {
+                    block_gas_limit: config.block_gas_limit,
+                    got: gas.value(),
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Generates a random instance using a `TestRng`.
     #[cfg(test)]
     pub fn random(rng: &mut TestRng) -> Self {
@@ -758,6 +965,12 @@ impl From<Deploy> for DeployItem {
             .iter()
             .map(|approval| approval.signer().to_account_hash())
             .collect();
+        // Deploys declaring an invalid version are rejected on acceptance. Should one get here
+        // regardless, it must not run unconditionally, so it requires a version never reached.
+        let min_protocol_version = match deploy.min_protocol_version() {
+            Ok(version) => version.as_ref().and_then(to_protocol_version),
+            Err(_) => Some(ProtocolVersion::from_parts(u32::MAX, u32::MAX, u32::MAX)),
+        };
 
         DeployItem::new(
             address,
@@ -766,10 +979,21 @@ impl From<Deploy> for DeployItem {
             deploy.header().gas_price(),
             authorization_keys,
             casper_types::DeployHash::new(deploy.id().inner().to_array()),
+            min_protocol_version,
         )
     }
 }
 
+/// Converts a semantic version to a `ProtocolVersion`, or returns `None` if any of its parts is out
+/// of range.
+fn to_protocol_version(version: &Version) -> Option<ProtocolVersion> {
+    Some(ProtocolVersion::from_parts(
+        u32::try_from(version.major).ok()?,
+        u32::try_from(version.minor).ok()?,
+        u32::try_from(version.patch).ok()?,
+    ))
+}
+
 /// The deploy mutable metadata.
 ///
 /// Currently a stop-gap measure to associate an immutable deploy with additional metadata. Holds
@@ -886,6 +1110,29 @@ mod tests {
         )
     }
 
+    fn create_wasm_deploy(rng: &mut TestRng, payment_amount: U512, session_size: usize) -> Deploy {
+        let secret_key = SecretKey::random(rng);
+        Deploy::new(
+            Timestamp::now(),
+            DeployConfig::default().max_ttl,
+            1,
+            vec![],
+            "net-1".to_string(),
+            ExecutableDeployItem::ModuleBytes {
+                module_bytes: Bytes::new(),
+                args: runtime_args! {
+                    ARG_AMOUNT => payment_amount
+                },
+            },
+            ExecutableDeployItem::ModuleBytes {
+                module_bytes: Bytes::from(vec![0; session_size]),
+                args: RuntimeArgs::new(),
+            },
+            &secret_key,
+            rng,
+        )
+    }
+
     #[test]
     fn is_valid() {
         let mut rng = crate::new_rng();
@@ -983,7 +1230,7 @@ mod tests {
             &chain_name,
         );
         deploy
-            .is_acceptable(chain_name, deploy_config)
+            .is_acceptable(chain_name, deploy_config, Timestamp::now())
             .expect("should be acceptable");
     }
 
@@ -1007,7 +1254,7 @@ mod tests {
         };
 
         assert_eq!(
-            deploy.is_acceptable(expected_chain_name, deploy_config),
+            deploy.is_acceptable(expected_chain_name, deploy_config, Timestamp::now()),
             Err(expected_error)
         );
         assert!(
@@ -1037,7 +1284,7 @@ mod tests {
         };
 
         assert_eq!(
-            deploy.is_acceptable(chain_name, deploy_config),
+            deploy.is_acceptable(chain_name, deploy_config, Timestamp::now()),
             Err(expected_error)
         );
         assert!(
@@ -1067,7 +1314,116 @@ mod tests {
         };
 
         assert_eq!(
-            deploy.is_acceptable(chain_name, deploy_config),
+            deploy.is_acceptable(chain_name, deploy_config, Timestamp::now()),
+            Err(expected_error)
+        );
+        assert!(
+            deploy.is_valid.is_none(),
+            "deploy should not have run expensive `is_valid` call"
+        );
+    }
+
+    #[test]
+    fn not_acceptable_due_to_insufficient_ttl() {
+        let mut rng = crate::new_rng();
+        let chain_name = "net-1".to_string();
+        let deploy_config = DeployConfig::default();
+
+        let ttl = TimeDiff::from(deploy_config.min_ttl.millis() - 1);
+
+        let mut deploy = create_deploy(
+            &mut rng,
+            ttl,
+            deploy_config.max_dependencies.into(),
+            &chain_name,
+        );
+
+        let expected_error = DeployValidationFailure::InsufficientTimeToLive {
+            min_ttl: deploy_config.min_ttl,
+            got: ttl,
+        };
+
+        assert_eq!(
+            deploy.is_acceptable(chain_name, deploy_config, Timestamp::now()),
+            Err(expected_error)
+        );
+        assert!(
+            deploy.is_valid.is_none(),
+            "deploy should not have run expensive `is_valid` call"
+        );
+    }
+
+    #[test]
+    fn not_acceptable_due_to_timestamp_in_future() {
+        let mut rng = crate::new_rng();
+        let chain_name = "net-1".to_string();
+        let deploy_config = DeployConfig::default();
+
+        let mut deploy = create_deploy(
+            &mut rng,
+            deploy_config.max_ttl,
+            deploy_config.max_dependencies.into(),
+            &chain_name,
+        );
+        let timestamp = deploy.header().timestamp();
+
+        // A timestamp within the allowed drift is fine.
+        let current_time = timestamp - deploy_config.max_timestamp_drift;
+        deploy
+            .is_acceptable(chain_name.clone(), deploy_config, current_time)
+            .expect("should be acceptable");
+
+        let current_time = current_time - TimeDiff::from(1);
+        let expected_error = DeployValidationFailure::TimestampInFuture {
+            current_time,
+            max_timestamp_drift: deploy_config.max_timestamp_drift,
+            got: timestamp,
+        };
+        assert_eq!(
+            deploy.is_acceptable(chain_name, deploy_config, current_time),
+            Err(expected_error)
+        );
+    }
+
+    #[test]
+    fn not_acceptable_due_to_excluded_transfers() {
+        let mut rng = crate::new_rng();
+        let chain_name = "net-1".to_string();
+        let mut deploy_config = DeployConfig::default();
+        deploy_config.block_max_transfer_count = 0;
+
+        let mut deploy = create_deploy(&mut rng, deploy_config.max_ttl, 0, &chain_name);
+
+        let expected_error = DeployValidationFailure::ExcludedFromBlocks {
+            kind: "transfers".to_string(),
+        };
+
+        assert_eq!(
+            deploy.is_acceptable(chain_name, deploy_config, Timestamp::now()),
+            Err(expected_error)
+        );
+        assert!(
+            deploy.is_valid.is_none(),
+            "deploy should not have run expensive `is_valid` call"
+        );
+    }
+
+    #[test]
+    fn not_acceptable_due_to_excessive_size() {
+        let mut rng = crate::new_rng();
+        let chain_name = "net-1".to_string();
+        let mut deploy_config = DeployConfig::default();
+        deploy_config.max_block_size = 1_000;
+
+        let mut deploy = create_wasm_deploy(&mut rng, U512::from(1), 1_000);
+
+        let expected_error = DeployValidationFailure::ExcessiveSize {
+            max_block_size: deploy_config.max_block_size,
+            got: deploy.serialized_length(),
+        };
+
+        assert_eq!(
+            deploy.is_acceptable(chain_name, deploy_config, Timestamp::now()),
             Err(expected_error)
         );
         assert!(
@@ -1075,4 +1431,94 @@ mod tests {
             "deploy should not have run expensive `is_valid` call"
         );
     }
+
+    #[test]
+    fn not_acceptable_due_to_excessive_gas() {
+        let mut rng = crate::new_rng();
+        let chain_name = "net-1".to_string();
+        let mut deploy_config = DeployConfig::default();
+        deploy_config.block_gas_limit = 100;
+
+        // With a gas price of 1, the payment amount is the amount of gas.
+        let mut deploy = create_wasm_deploy(&mut rng, U512::from(101), 0);
+
+        let expected_error = DeployValidationFailure::ExcessiveGasLimit {
+            block_gas_limit: deploy_config.block_gas_limit,
+            got: U512::from(101),
+        };
+
+        assert_eq!(
+            deploy.is_acceptable(chain_name.clone(), deploy_config, Timestamp::now()),
+            Err(expected_error)
+        );
+        assert!(
+            deploy.is_valid.is_none(),
+            "deploy should not have run expensive `is_valid` call"
+        );
+
+        let mut deploy = create_wasm_deploy(&mut rng, U512::from(100), 0);
+        deploy
+            .is_acceptable(chain_name, deploy_config, Timestamp::now())
+            .expect("should be acceptable");
+    }
+
+    #[test]
+    fn not_acceptable_due_to_unsupported_protocol_version() {
+        let mut rng = crate::new_rng();
+        let create = |rng: &mut TestRng, version: &str| {
+            Deploy::new(
+                Timestamp::now(),
+                DeployConfig::default().max_ttl,
+                1,
+                vec![],
+                "net-1".to_string(),
+                ExecutableDeployItem::ModuleBytes {
+                    module_bytes: Bytes::new(),
+                    args: runtime_args! {
+                        ARG_AMOUNT => U512::from(1),
+                        ARG_MIN_PROTOCOL_VERSION => version.to_string()
+                    },
+                },
+                ExecutableDeployItem::ModuleBytes {
+                    module_bytes: Bytes::new(),
+                    args: RuntimeArgs::new(),
+                },
+                &SecretKey::random(rng),
+                rng,
+            )
+        };
+        let current = Version::new(1, 1, 0);
+
+        let deploy = create(&mut rng, "1.1.0");
+        assert_eq!(deploy.min_protocol_version(), Ok(Some(current.clone())));
+        deploy
+            .check_protocol_version(&current)
+            .expect("should be supported");
+
+        let expected_error = DeployValidationFailure::UnsupportedProtocolVersion {
+            required: "1.2.0".to_string(),
+            current: current.to_string(),
+        };
+        let deploy = create(&mut rng, "1.2.0");
+        assert_eq!(deploy.check_protocol_version(&current), Err(expected_error));
+
+        let expected_error = DeployValidationFailure::InvalidMinProtocolVersion {
+            got: "latest".to_string(),
+        };
+        let deploy = create(&mut rng, "latest");
+        assert_eq!(
+            deploy.check_protocol_version(&current),
+            Err(expected_error.clone())
+        );
+        assert!(matches!(
+            deploy.deploy_type(),
+            Err(Error::InvalidMinProtocolVersion(error)) if error == expected_error
+        ));
+
+        let expected_error = DeployValidationFailure::InvalidMinProtocolVersion {
+            got: "4294967296.0.0".to_string(),
+        };
+        let deploy = create(&mut rng, "4294967296.0.0");
+        assert_eq!(deploy.check_protocol_version(&current), Err(expected_error));
+    }
 }
@@ -0,0 +1,115 @@
+// TODO - remove once schemars stops causing warning.
+#![allow(clippy::field_reassign_with_default)]
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use datasize::DataSize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::small_network::{ATTESTATION_FEATURE, STREAMING_FEATURE, VALIDATOR_KEY_FEATURE},
+    reactor::validator::Config,
+};
+
+/// Optional functionality of a node, and whether it is enabled.
+///
+/// Flags are derived from the configuration and from the features the binary was built with. They
+/// are logged at startup, reported by the status endpoints and sent to peers in the handshake, so
+/// that the setup of a node can be seen at a glance.
+#[derive(Clone, DataSize, Debug, Default, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+pub struct FeatureFlags(BTreeMap<String, bool>);
+
+impl FeatureFlags {
+    /// Collects the feature flags of a node running with the given configuration.
+    pub fn from_config(config: &Config) -> Self {
+        let mut flags = FeatureFlags::default();
+
+        // Build features.
+        flags.set("vendored_openssl", cfg!(feature = "vendored-openssl"));
+        flags.set("debug_assertions", cfg!(debug_assertions));
+
+        // Configuration.
+        flags.set("storage_compression", config.storage.enable_compression());
+        flags.set("read_replicas", config.storage.max_read_replicas() > 0);
+        flags.set("verify_accounts", config.deploy_acceptor.verify_accounts());
+        flags.set(
+            "peer_deploy_submission",
+            config.deploy_acceptor.max_peer_submissions() > 0,
+        );
+        flags.set("deploy_policies", config.deploy_acceptor.has_policies());
+        flags.set("reject_clock_skew", config.network.reject_clock_skew);
+        flags.set("systemd_support", config.network.systemd_support);
+        flags.set(STREAMING_FEATURE, config.network.stream_chunk_size > 0);
+        flags.set(VALIDATOR_KEY_FEATURE, true);
+        flags.set(ATTESTATION_FEATURE, true);
+        flags.set(
+            "request_quotas",
+            config.network.deploy_request_quota > 0 || config.network.block_request_quota > 0,
+        );
+        flags.set("trusted_hash", config.node.trusted_hash.is_some());
+        flags.set(
+            "maintenance_windows",
+            !config.maintenance.windows.is_empty(),
+        );
+
+        flags
+    }
+
+    /// Sets whether the feature `name` is enabled.
+    pub fn set<S: Into<String>>(&mut self, name: S, enabled: bool) {
+        self.0.insert(name.into(), enabled);
+    }
+
+    /// Returns whether the feature `name` is enabled, or `None` if it is unknown.
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.0.get(name).copied()
+    }
+
+    /// Returns an iterator over all features and whether they are enabled.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.0
+            .iter()
+            .map(|(name, enabled)| (name.as_str(), *enabled))
+    }
+}
+
+impl Display for FeatureFlags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (index, (name, enabled)) in self.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}={}", name, if enabled { "on" } else { "off" })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_reflect_config() {
+        let mut config = Config::default();
+        config.network.reject_clock_skew = true;
+
+        let flags = FeatureFlags::from_config(&config);
+        assert_eq!(flags.is_enabled("reject_clock_skew"), Some(true));
+        assert_eq!(flags.is_enabled("trusted_hash"), Some(false));
+        assert_eq!(flags.is_enabled(STREAMING_FEATURE), Some(true));
+        assert_eq!(flags.is_enabled("no_such_feature"), None);
+    }
+
+    #[test]
+    fn should_display_sorted_flags() {
+        let mut flags = FeatureFlags::default();
+        flags.set("b", false);
+        flags.set("a", true);
+        assert_eq!(flags.to_string(), "a=on, b=off");
+    }
+}
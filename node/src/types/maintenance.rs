@@ -0,0 +1,86 @@
+use datasize::DataSize;
+use serde::{Deserialize, Serialize};
+
+use crate::types::Timestamp;
+
+/// A period of time during which the node is under scheduled maintenance.
+#[derive(Clone, Copy, DataSize, Debug, Deserialize, Serialize, PartialEq, Eq)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct MaintenanceWindow {
+    /// Start of the window, inclusive.
+    pub start: Timestamp,
+    /// End of the window, exclusive.
+    pub end: Timestamp,
+}
+
+impl MaintenanceWindow {
+    /// Returns whether `timestamp` lies within the window.
+    pub fn contains(&self, timestamp: Timestamp) -> bool {
+        self.start <= timestamp && timestamp < self.end
+    }
+}
+
+/// Maintenance configuration.
+///
+/// While inside one of the configured windows, the node reduces non-essential work, e.g. it stops
+/// serving historical blocks to peers, and reports that it is in maintenance in status responses.
+#[derive(Clone, DataSize, Debug, Default, Deserialize, Serialize)]
+// Disallow unknown fields to ensure config files and command-line overrides contain valid keys.
+#[serde(deny_unknown_fields)]
+pub struct MaintenanceConfig {
+    /// Scheduled maintenance windows.
+    pub windows: Vec<MaintenanceWindow>,
+}
+
+impl MaintenanceConfig {
+    /// Returns whether the node is in a maintenance window at `timestamp`.
+    pub fn is_active_at(&self, timestamp: Timestamp) -> bool {
+        self.windows.iter().any(|window| window.contains(timestamp))
+    }
+
+    /// Returns whether the node is currently in a maintenance window.
+    pub fn is_active(&self) -> bool {
+        self.is_active_at(Timestamp::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_active_window() {
+        let config = MaintenanceConfig {
+            windows: vec![
+                MaintenanceWindow {
+                    start: Timestamp::from(1_000),
+                    end: Timestamp::from(2_000),
+                },
+                MaintenanceWindow {
+                    start: Timestamp::from(5_000),
+                    end: Timestamp::from(6_000),
+                },
+            ],
+        };
+
+        assert!(!config.is_active_at(Timestamp::from(999)));
+        assert!(config.is_active_at(Timestamp::from(1_000)));
+        assert!(config.is_active_at(Timestamp::from(1_999)));
+        assert!(!config.is_active_at(Timestamp::from(2_000)));
+        assert!(config.is_active_at(Timestamp::from(5_500)));
+        assert!(!MaintenanceConfig::default().is_active());
+    }
+
+    #[test]
+    fn should_parse_windows_from_toml() {
+        let config: MaintenanceConfig = toml::from_str(
+            "windows = [{ start = '2021-01-01T00:00:00Z', end = '2021-01-01T02:00:00Z' }]",
+        )
+        .unwrap();
+        let inside = "2021-01-01T01:00:00Z".parse().unwrap();
+        let outside = "2021-01-01T03:00:00Z".parse().unwrap();
+        assert!(config.is_active_at(inside));
+        assert!(!config.is_active_at(outside));
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+
+use super::Timestamp;
+
+/// The offset of a peer's clock measured in a single round-trip time exchange.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TimeSample {
+    /// The peer's clock minus ours, in milliseconds.
+    offset_ms: i64,
+    /// The time the exchange spent in transit, in milliseconds.
+    ///
+    /// The true offset differs from `offset_ms` by at most half of this.
+    round_trip_ms: u64,
+}
+
+impl TimeSample {
+    /// Computes the sample of an exchange, in which we sent a request at `origin`, the peer
+    /// received it at `received` and responded at `sent` according to its clock, and the
+    /// response arrived at `arrived`.
+    pub(crate) fn from_exchange(
+        origin: Timestamp,
+        received: Timestamp,
+        sent: Timestamp,
+        arrived: Timestamp,
+    ) -> Self {
+        let origin = origin.millis() as i64;
+        let received = received.millis() as i64;
+        let sent = sent.millis() as i64;
+        let arrived = arrived.millis() as i64;
+        let offset_ms = ((received - origin) + (sent - arrived)) / 2;
+        let round_trip_ms = ((arrived - origin) - (sent - received)).max(0) as u64;
+        TimeSample {
+            offset_ms,
+            round_trip_ms,
+        }
+    }
+}
+
+/// An estimate of how far our clock is off from the median clock of our peers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkTimeEstimate {
+    /// The median offset of the peers' clocks relative to ours, in milliseconds.
+    ///
+    /// Positive values indicate our clock is behind the network.
+    pub offset_ms: i64,
+    /// The lower bound of the offset, in milliseconds.
+    pub lower_bound_ms: i64,
+    /// The upper bound of the offset, in milliseconds.
+    pub upper_bound_ms: i64,
+    /// The number of peers the estimate is based on.
+    pub peers: usize,
+}
+
+impl NetworkTimeEstimate {
+    /// Estimates the network time from the samples of several peers, or returns `None` if there
+    /// are none.
+    ///
+    /// Each sample bounds a peer's true offset to within half its round-trip time. The bounds of
+    /// the estimate are the medians of these per-peer bounds, so a minority of peers with wrong
+    /// clocks or slow links cannot widen or shift them arbitrarily.
+    pub(crate) fn from_samples<'a, T>(samples: T) -> Option<Self>
+    where
+        T: IntoIterator<Item = &'a TimeSample>,
+    {
+        let samples: Vec<_> = samples.into_iter().collect();
+        let half_round_trip = |sample: &TimeSample| (sample.round_trip_ms / 2) as i64;
+        let offset_ms = median(samples.iter().map(|sample| sample.offset_ms))?;
+        let lower_bound_ms = median(
+            samples
+                .iter()
+                .map(|sample| sample.offset_ms - half_round_trip(sample)),
+        )?;
+        let upper_bound_ms = median(
+            samples
+                .iter()
+                .map(|sample| sample.offset_ms + half_round_trip(sample)),
+        )?;
+        Some(NetworkTimeEstimate {
+            offset_ms,
+            lower_bound_ms,
+            upper_bound_ms,
+            peers: samples.len(),
+        })
+    }
+
+    /// Returns how far our clock is off by at least, if that exceeds `tolerance_ms`.
+    ///
+    /// Positive values indicate our clock is behind the network.
+    pub fn local_clock_error(&self, tolerance_ms: u64) -> Option<i64> {
+        let tolerance_ms = tolerance_ms as i64;
+        if self.lower_bound_ms > tolerance_ms {
+            Some(self.lower_bound_ms)
+        } else if self.upper_bound_ms < -tolerance_ms {
+            Some(self.upper_bound_ms)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the median of the values, or the lower of the two middle values for an even count.
+fn median<T: IntoIterator<Item = i64>>(values: T) -> Option<i64> {
+    let mut values: Vec<_> = values.into_iter().collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[(values.len() - 1) / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(offset_ms: i64, round_trip_ms: u64) -> TimeSample {
+        TimeSample {
+            offset_ms,
+            round_trip_ms,
+        }
+    }
+
+    #[test]
+    fn should_correct_for_round_trip() {
+        // The peer is 1000 ms ahead, and each direction takes 50 ms.
+        let measured = TimeSample::from_exchange(
+            Timestamp::from(10_000),
+            Timestamp::from(11_050),
+            Timestamp::from(11_060),
+            Timestamp::from(10_110),
+        );
+        assert_eq!(measured, sample(1000, 100));
+
+        // Clocks are in sync, but the peer's response took longer than the request.
+        let measured = TimeSample::from_exchange(
+            Timestamp::from(10_000),
+            Timestamp::from(10_010),
+            Timestamp::from(10_010),
+            Timestamp::from(10_100),
+        );
+        assert_eq!(measured, sample(-40, 100));
+    }
+
+    #[test]
+    fn should_estimate_median_with_bounds() {
+        let samples = [
+            sample(900, 200),
+            sample(1000, 100),
+            sample(1100, 40),
+            // A peer with a wildly wrong clock does not move the estimate much.
+            sample(-60_000, 10),
+            sample(1050, 60),
+        ];
+        let estimate = NetworkTimeEstimate::from_samples(&samples).unwrap();
+        assert_eq!(
+            estimate,
+            NetworkTimeEstimate {
+                offset_ms: 1000,
+                lower_bound_ms: 950,
+                upper_bound_ms: 1050,
+                peers: 5,
+            }
+        );
+        assert_eq!(estimate.local_clock_error(500), Some(950));
+        assert_eq!(estimate.local_clock_error(1000), None);
+
+        assert_eq!(NetworkTimeEstimate::from_samples(&[]), None);
+    }
+
+    #[test]
+    fn should_report_clock_ahead_of_network() {
+        let estimate = NetworkTimeEstimate::from_samples(&[sample(-2000, 400)]).unwrap();
+        assert_eq!(estimate.local_clock_error(1000), Some(-1800));
+    }
+}
@@ -14,6 +14,9 @@ pub struct NodeConfig {
     pub chainspec_config_path: External<Chainspec>,
     /// Hash used as a trust anchor when joining, if any.
     pub trusted_hash: Option<BlockHash>,
+    /// File to save unprocessed events to on a graceful shutdown, and to replay them from on the
+    /// next start, if any. Relative paths are resolved against the config directory.
+    pub queue_snapshot_path: Option<String>,
 }
 
 impl Default for NodeConfig {
@@ -21,6 +24,7 @@ impl Default for NodeConfig {
         NodeConfig {
             chainspec_config_path: External::path(DEFAULT_CHAINSPEC_CONFIG_PATH),
             trusted_hash: None,
+            queue_snapshot_path: None,
         }
     }
 }
@@ -7,12 +7,12 @@ use datasize::DataSize;
 use hex_fmt::HexFmt;
 use libp2p::PeerId;
 use once_cell::sync::Lazy;
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 use rand::{Rng, RngCore};
 use serde::{de::Error as SerdeError, Deserialize, Deserializer, Serialize, Serializer};
 
-#[cfg(test)]
-use crate::testing::TestRng;
+#[cfg(any(test, feature = "testing"))]
+use crate::NodeRng;
 use crate::{rpcs::docs::DocExample, tls::KeyFingerprint};
 
 /// The network identifier for a node.
@@ -24,9 +24,9 @@ pub enum NodeId {
 }
 
 impl NodeId {
-    /// Generates a random instance using a `TestRng`.
-    #[cfg(test)]
-    pub(crate) fn random(rng: &mut TestRng) -> Self {
+    /// Generates a random instance.
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn random(rng: &mut NodeRng) -> Self {
         if rng.gen() {
             Self::random_tls(rng)
         } else {
@@ -34,15 +34,15 @@ impl NodeId {
         }
     }
 
-    /// Generates a random Tls instance using a `TestRng`.
-    #[cfg(test)]
-    pub(crate) fn random_tls(rng: &mut TestRng) -> Self {
+    /// Generates a random Tls instance.
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn random_tls(rng: &mut NodeRng) -> Self {
         NodeId::Tls(rng.gen())
     }
 
-    /// Generates a random P2p instance using a `TestRng`.
-    #[cfg(test)]
-    pub(crate) fn random_p2p(rng: &mut TestRng) -> Self {
+    /// Generates a random P2p instance.
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn random_p2p(rng: &mut NodeRng) -> Self {
         let mut bytes = [0u8; 32];
         rng.fill_bytes(&mut bytes[..]);
         let multihash = multihash::wrap(multihash::Code::Identity, &bytes);
@@ -0,0 +1,133 @@
+// TODO - remove once schemars stops causing warning.
+#![allow(clippy::field_reassign_with_default)]
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::types::NodeId;
+
+/// Which side opened the connections to a peer.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerDirection {
+    /// Only we connected to the peer.
+    Outgoing,
+    /// Only the peer connected to us.
+    Incoming,
+    /// Both sides connected to each other.
+    Both,
+}
+
+/// A peer in a sample of the peers connected to a node.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SampledPeer {
+    /// The node ID of the peer.
+    pub node_id: String,
+    /// The address the peer listens on.
+    ///
+    /// Only given if we connected to the peer and the address is publicly routable, since the
+    /// address of an incoming connection is not one the peer can be reached at.
+    pub address: Option<String>,
+    /// Which side opened the connections to the peer.
+    pub direction: PeerDirection,
+    /// The number of seconds since the longest lasting connection to the peer was established.
+    pub connected_secs: u64,
+}
+
+impl SampledPeer {
+    pub(crate) fn new(
+        node_id: &NodeId,
+        listening_address: Option<SocketAddr>,
+        direction: PeerDirection,
+        connected_for: Duration,
+    ) -> Self {
+        SampledPeer {
+            node_id: node_id.to_string(),
+            address: listening_address
+                .filter(|address| is_publicly_routable(address.ip()))
+                .map(|address| address.to_string()),
+            direction,
+            connected_secs: connected_for.as_secs(),
+        }
+    }
+}
+
+/// A bounded, uniformly random sample of the peers connected to a node.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PeerSample {
+    /// The number of peers the sample was drawn from.
+    pub total_peers: u64,
+    /// The sampled peers.
+    pub peers: Vec<SampledPeer>,
+}
+
+/// Returns whether `ip` may be reachable from the public internet.
+///
+/// Loopback, private, link-local and similar addresses are withheld, as they only reveal details
+/// of the local network of a peer.
+fn is_publicly_routable(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation())
+        }
+        IpAddr::V6(ip) => {
+            let first_segment = ip.segments()[0];
+            let is_unique_local = first_segment & 0xfe00 == 0xfc00;
+            let is_link_local = first_segment & 0xffc0 == 0xfe80;
+            !(ip.is_loopback() || ip.is_unspecified() || is_unique_local || is_link_local)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_only_disclose_publicly_routable_addresses() {
+        let node_id = NodeId::random(&mut crate::testing::TestRng::new());
+        let connected_for = Duration::from_millis(2_500);
+        let sample = |address: &str| {
+            SampledPeer::new(
+                &node_id,
+                Some(address.parse().unwrap()),
+                PeerDirection::Outgoing,
+                connected_for,
+            )
+            .address
+        };
+
+        assert_eq!(sample("34.1.2.3:35000"), Some("34.1.2.3:35000".to_string()));
+        assert_eq!(
+            sample("[2001:4860::1]:35000"),
+            Some("[2001:4860::1]:35000".to_string())
+        );
+        for address in &[
+            "127.0.0.1:35000",
+            "10.0.0.1:35000",
+            "192.168.1.1:35000",
+            "169.254.0.1:35000",
+            "[::1]:35000",
+            "[fd00::1]:35000",
+            "[fe80::1]:35000",
+        ] {
+            assert_eq!(sample(address), None, "{} should be withheld", address);
+        }
+
+        let peer = SampledPeer::new(&node_id, None, PeerDirection::Incoming, connected_for);
+        assert_eq!(peer.address, None);
+        assert_eq!(peer.connected_secs, 2);
+    }
+}
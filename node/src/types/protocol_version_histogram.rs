@@ -0,0 +1,115 @@
+// TODO - remove once schemars stops causing warning.
+#![allow(clippy::field_reassign_with_default)]
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct VersionCount {
+    #[schemars(with = "String")]
+    version: Version,
+    peers: u64,
+}
+
+/// The number of connected peers advertising each protocol version in their handshake.
+#[derive(Clone, Default, Serialize, Deserialize, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProtocolVersionHistogram {
+    /// The number of peers per protocol version, in ascending order of versions.
+    versions: Vec<VersionCount>,
+    /// The number of peers running a node version that does not advertise its protocol version.
+    unknown: u64,
+}
+
+impl ProtocolVersionHistogram {
+    /// Counts the given protocol versions of peers, `None` standing for an unknown version.
+    pub(crate) fn new<'a, T>(peer_versions: T) -> Self
+    where
+        T: IntoIterator<Item = Option<&'a Version>>,
+    {
+        let mut counts = BTreeMap::new();
+        let mut unknown = 0;
+        for maybe_version in peer_versions {
+            match maybe_version {
+                Some(version) => *counts.entry(version.clone()).or_insert(0) += 1,
+                None => unknown += 1,
+            }
+        }
+        let versions = counts
+            .into_iter()
+            .map(|(version, peers)| VersionCount { version, peers })
+            .collect();
+        ProtocolVersionHistogram { versions, unknown }
+    }
+
+    /// Returns an iterator over all advertised versions and their number of peers, in ascending
+    /// order of versions.
+    pub fn iter(&self) -> impl Iterator<Item = (&Version, u64)> {
+        self.versions
+            .iter()
+            .map(|entry| (&entry.version, entry.peers))
+    }
+
+    /// Returns the number of peers which did not advertise a protocol version.
+    pub fn unknown(&self) -> u64 {
+        self.unknown
+    }
+
+    /// Returns the total number of peers.
+    pub fn total(&self) -> u64 {
+        self.iter().map(|(_, peers)| peers).sum::<u64>() + self.unknown
+    }
+
+    /// Returns the number of peers advertising `version` or a later one.
+    pub fn count_at_least(&self, version: &Version) -> u64 {
+        self.iter()
+            .filter(|(peer_version, _)| *peer_version >= version)
+            .map(|(_, peers)| peers)
+            .sum()
+    }
+
+    /// Returns the percentage of peers advertising `version` or a later one, or `None` if there
+    /// are no peers.
+    ///
+    /// Peers with an unknown version are counted as not running `version`.
+    pub fn percent_at_least(&self, version: &Version) -> Option<u64> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        Some(self.count_at_least(version) * 100 / total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_count_versions() {
+        let v1 = Version::new(1, 0, 0);
+        let v2 = Version::new(1, 1, 0);
+        let v3 = Version::new(2, 0, 0);
+        let histogram =
+            ProtocolVersionHistogram::new(vec![Some(&v2), Some(&v1), None, Some(&v2), Some(&v3)]);
+
+        let versions: Vec<_> = histogram.iter().collect();
+        assert_eq!(versions, vec![(&v1, 1), (&v2, 2), (&v3, 1)]);
+        assert_eq!(histogram.unknown(), 1);
+        assert_eq!(histogram.total(), 5);
+        assert_eq!(histogram.count_at_least(&v2), 3);
+        assert_eq!(histogram.percent_at_least(&v2), Some(60));
+        assert_eq!(histogram.percent_at_least(&Version::new(3, 0, 0)), Some(0));
+    }
+
+    #[test]
+    fn should_have_no_percentage_without_peers() {
+        let histogram = ProtocolVersionHistogram::new(vec![]);
+        assert_eq!(histogram.total(), 0);
+        assert_eq!(histogram.percent_at_least(&Version::new(1, 0, 0)), None);
+    }
+}
@@ -0,0 +1,73 @@
+// TODO - remove once schemars stops causing warning.
+#![allow(clippy::field_reassign_with_default)]
+
+use std::fmt::{self, Display, Formatter};
+
+use datasize::DataSize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The cost class of a data request served to peers.
+#[derive(
+    Clone,
+    Copy,
+    DataSize,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestClass {
+    /// Requests for deploys, cheap to serve from storage.
+    Deploy,
+    /// Requests for blocks by hash or height, which may have to be read from cold storage.
+    Block,
+}
+
+impl Display for RequestClass {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestClass::Deploy => write!(formatter, "deploy"),
+            RequestClass::Block => write!(formatter, "block"),
+        }
+    }
+}
+
+/// The requests of one class a peer sent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RequestClassAudit {
+    /// The cost class of the requests.
+    pub class: RequestClass,
+    /// The number of requests served within the current window.
+    pub in_window: u32,
+    /// The maximum number of requests served per window, `None` if unlimited.
+    pub quota: Option<u32>,
+    /// The number of requests served since the peer was first seen.
+    pub served: u64,
+    /// The number of requests dropped for exceeding the quota since the peer was first seen.
+    pub throttled: u64,
+}
+
+/// The data requests a peer sent us.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PeerRequestAudit {
+    /// The peer's node ID.
+    pub node_id: String,
+    /// The peer's requests, per cost class.
+    pub classes: Vec<RequestClassAudit>,
+}
+
+impl PeerRequestAudit {
+    /// Returns the number of requests of all classes served within the current window.
+    pub fn in_window(&self) -> u32 {
+        self.classes.iter().map(|class| class.in_window).sum()
+    }
+}
@@ -16,10 +16,11 @@ use casper_types::PublicKey;
 
 use crate::{
     components::{
-        chainspec_loader::ChainspecInfo, consensus::EraId, rpc_server::rpcs::docs::DocExample,
+        chainspec_loader::ChainspecInfo, consensus::EraId, disk_monitor::DiskSpaceStage,
+        rpc_server::rpcs::docs::DocExample,
     },
     crypto::hash::Digest,
-    types::{Block, BlockHash, NodeId, PeersMap, Timestamp},
+    types::{Block, BlockHash, FeatureFlags, NodeId, PeersMap, Timestamp},
 };
 
 static GET_STATUS_RESULT: Lazy<GetStatusResult> = Lazy::new(|| {
@@ -32,6 +33,18 @@ static GET_STATUS_RESULT: Lazy<GetStatusResult> = Lazy::new(|| {
         peers,
         chainspec_info: ChainspecInfo::doc_example().clone(),
         version: crate::VERSION_STRING.as_str(),
+        in_maintenance: false,
+        features: FeatureFlags::default(),
+        possibly_partitioned: false,
+        participation_degraded: false,
+        disk_space_stage: DiskSpaceStage::Normal,
+        components: vec![ComponentStatus {
+            name: "storage".to_string(),
+            events_processed: 1_024,
+            average_handling_time_ns: 85_000,
+            last_error: None,
+            queued_events: 3,
+        }],
     };
     GetStatusResult::from(status_feed)
 });
@@ -48,23 +61,65 @@ pub struct StatusFeed<I> {
     pub chainspec_info: ChainspecInfo,
     /// The compiled node version.
     pub version: &'static str,
+    /// Whether the node is currently in a scheduled maintenance window.
+    pub in_maintenance: bool,
+    /// The optional functionality enabled on this node.
+    pub features: FeatureFlags,
+    /// Whether too few validators have been heard from recently, suggesting that the node is cut
+    /// off from the rest of the network.
+    pub possibly_partitioned: bool,
+    /// Whether too few validators cite this node's recent units while it is validating.
+    pub participation_degraded: bool,
+    /// How far the node is degraded due to a lack of disk space.
+    pub disk_space_stage: DiskSpaceStage,
+    /// Event handling statistics of each component.
+    pub components: Vec<ComponentStatus>,
 }
 
 impl<I> StatusFeed<I> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         last_added_block: Option<Block>,
         peers: BTreeMap<I, String>,
         chainspec_info: ChainspecInfo,
+        in_maintenance: bool,
+        features: FeatureFlags,
+        possibly_partitioned: bool,
+        participation_degraded: bool,
+        disk_space_stage: DiskSpaceStage,
+        components: Vec<ComponentStatus>,
     ) -> Self {
         StatusFeed {
             last_added_block,
             peers,
             chainspec_info,
             version: crate::VERSION_STRING.as_str(),
+            in_maintenance,
+            features,
+            possibly_partitioned,
+            participation_degraded,
+            disk_space_stage,
+            components,
         }
     }
 }
 
+/// Event handling statistics of a single component, as seen by the reactor.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentStatus {
+    /// The name of the component.
+    pub name: String,
+    /// The number of events the component has handled.
+    pub events_processed: u64,
+    /// The average time the component took to handle an event, in nanoseconds.
+    pub average_handling_time_ns: u64,
+    /// The last error or warning logged while the component was handling an event.
+    pub last_error: Option<String>,
+    /// The number of events for the component waiting in the event queues.
+    pub queued_events: u64,
+}
+
 /// Minimal info of a `Block`.
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
@@ -107,6 +162,19 @@ pub struct GetStatusResult {
     pub last_added_block_info: Option<MinimalBlockInfo>,
     /// The compiled node version.
     pub build_version: String,
+    /// Whether the node is currently in a scheduled maintenance window.
+    pub in_maintenance: bool,
+    /// The optional functionality enabled on this node.
+    pub features: FeatureFlags,
+    /// Whether too few validators have been heard from recently, suggesting that the node is cut
+    /// off from the rest of the network.
+    pub possibly_partitioned: bool,
+    /// Whether too few validators cite this node's recent units while it is validating.
+    pub participation_degraded: bool,
+    /// How far the node is degraded due to a lack of disk space.
+    pub disk_space_stage: DiskSpaceStage,
+    /// Event handling statistics of each component.
+    pub components: Vec<ComponentStatus>,
 }
 
 impl GetStatusResult {
@@ -141,6 +209,12 @@ impl From<StatusFeed<NodeId>> for GetStatusResult {
             peers,
             last_added_block_info,
             build_version,
+            in_maintenance: status_feed.in_maintenance,
+            features: status_feed.features,
+            possibly_partitioned: status_feed.possibly_partitioned,
+            participation_degraded: status_feed.participation_degraded,
+            disk_space_stage: status_feed.disk_space_stage,
+            components: status_feed.components,
         }
     }
 }
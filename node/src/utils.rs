@@ -1,32 +1,43 @@
 //! Various functions that are not limited to a particular module, but are too small to warrant
 //! being factored out into standalone crates.
 
+pub(crate) mod bounded;
+mod config_validation;
 mod external;
 mod median;
 pub mod milliseconds;
+pub(crate) mod resource_usage;
 mod round_robin;
+mod spill_ring;
 
 use std::{
     cell::RefCell,
+    ffi::CString,
     fmt::{self, Display, Formatter},
-    fs, io,
+    fs, io, mem,
     net::{SocketAddr, ToSocketAddrs},
+    os::unix::ffi::OsStrExt,
     path::{Path, PathBuf},
 };
 
 use datasize::DataSize;
+use futures::FutureExt;
 use hyper::server::{conn::AddrIncoming, Builder, Server};
 use libc::{c_long, sysconf, _SC_PAGESIZE};
 use once_cell::sync::Lazy;
 use serde::Serialize;
 use thiserror::Error;
+use tokio::task::JoinHandle;
 use tracing::warn;
 
-#[cfg(test)]
+pub(crate) use config_validation::ConfigValidator;
+pub use config_validation::{ConfigValidationError, ConfigViolation};
+#[cfg(any(test, feature = "testing"))]
 pub use external::RESOURCES_PATH;
 pub use external::{External, LoadError, Loadable};
 pub(crate) use median::weighted_median;
-pub(crate) use round_robin::WeightedRoundRobin;
+pub(crate) use round_robin::{SpillCodec, Spillover, WeightedRoundRobin};
+pub(crate) use spill_ring::SpillRing;
 
 /// Sensible default for many if not all systems.
 const DEFAULT_PAGE_SIZE: usize = 4096;
@@ -118,6 +129,49 @@ pub(crate) fn start_listening(address: &str) -> Result<Builder<AddrIncoming>, Li
     })
 }
 
+/// Returns whether the task behind `join_handle` has exited, without waiting for it.
+///
+/// The handle is cleared once the task has exited; a cleared handle counts as exited.
+pub(crate) fn has_exited(join_handle: &mut Option<JoinHandle<()>>) -> bool {
+    let exited = match join_handle {
+        Some(handle) => handle.now_or_never().is_some(),
+        None => true,
+    };
+    if exited {
+        *join_handle = None;
+    }
+    exited
+}
+
+/// The size of a file system and the space available on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct DiskSpace {
+    /// The size of the file system in bytes.
+    pub(crate) total_bytes: u64,
+    /// The space available to unprivileged processes in bytes.
+    pub(crate) available_bytes: u64,
+}
+
+/// Returns the size of and the space available on the file system holding `path`.
+///
+/// May block, e.g. on network file systems.
+pub(crate) fn disk_space(path: &Path) -> io::Result<DiskSpace> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    // Safe, as all-zero bytes are a valid `statvfs`.
+    let mut stats: libc::statvfs = unsafe { mem::zeroed() };
+    // Safe, as `c_path` is a valid, nul-terminated string and `stats` a valid `statvfs`, both
+    // outliving the call.
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fragment_size = stats.f_frsize as u64;
+    Ok(DiskSpace {
+        total_bytes: (stats.f_blocks as u64).saturating_mul(fragment_size),
+        available_bytes: (stats.f_bavail as u64).saturating_mul(fragment_size),
+    })
+}
+
 /// Moves a value to the heap and then forgets about, leaving only a static reference behind.
 #[inline]
 pub(crate) fn leak<T>(value: T) -> &'static T {
@@ -241,7 +295,7 @@ impl<T> WithDir<T> {
     }
 
     /// Get a reference to the inner value.
-    pub(crate) fn value(&self) -> &T {
+    pub fn value(&self) -> &T {
         &self.value
     }
 
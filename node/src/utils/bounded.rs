@@ -0,0 +1,654 @@
+//! Resource-limited deserialization of untrusted inputs.
+//!
+//! Length prefixes in binary encodings allow a small crafted payload to claim huge strings or
+//! collections, and nested encodings allow it to recurse arbitrarily deep. Data received from
+//! peers or RPC clients is therefore deserialized through a wrapper around the format's
+//! deserializer which enforces `Limits` on the total size of the buffers allocated, the nesting
+//! depth and the length of every collection, failing with a typed error as soon as one of them is
+//! exceeded, see `Error::LimitExceeded`.
+//!
+//! For binary formats, the size of the input itself is also limited, and the format reader is
+//! limited to the bytes actually present, so buffers are never allocated based on a length prefix
+//! alone.
+
+use std::{
+    cell::Cell,
+    fmt::{self, Formatter},
+};
+
+use bincode::Options;
+use serde::{
+    de::{
+        self, DeserializeOwned, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess,
+        Visitor,
+    },
+    Deserialize, Deserializer,
+};
+use thiserror::Error;
+
+/// Limits on the resources used to deserialize a single input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Limits {
+    /// The maximum number of bytes of the input and of all strings and byte buffers in it.
+    pub(crate) max_allocation: u64,
+    /// The maximum nesting depth of sequences, maps, enums and options.
+    pub(crate) max_depth: usize,
+    /// The maximum number of elements of any single sequence or map.
+    pub(crate) max_collection_length: usize,
+}
+
+impl Limits {
+    /// Limits for messages received from peers.
+    ///
+    /// Large enough for a block of the maximum size allowed by the chainspec, serialized as a
+    /// sequence of bytes inside a message.
+    pub(crate) const NETWORK: Limits = Limits {
+        max_allocation: 32 * 1024 * 1024,
+        max_depth: 64,
+        max_collection_length: 16 * 1024 * 1024,
+    };
+
+    /// Limits for the parameters of requests received from RPC clients.
+    pub(crate) const RPC: Limits = Limits {
+        max_allocation: 16 * 1024 * 1024,
+        max_depth: 32,
+        max_collection_length: 1024 * 1024,
+    };
+}
+
+/// A deserialization limit which has been exceeded.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// Too many bytes would have been allocated.
+    #[error("allocation of more than {0} bytes")]
+    Allocation(u64),
+    /// The input is nested too deeply.
+    #[error("nesting deeper than {0} levels")]
+    Depth(usize),
+    /// A collection has too many elements.
+    #[error("collection of more than {0} elements")]
+    CollectionLength(usize),
+}
+
+/// Error deserializing an untrusted input.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A deserialization limit has been exceeded.
+    #[error("deserialization limit exceeded: {0}")]
+    LimitExceeded(LimitExceeded),
+    /// The input is not valid bincode.
+    #[error(transparent)]
+    Bincode(#[from] bincode::Error),
+    /// The input is not valid JSON.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// The input is not valid MessagePack.
+    #[error(transparent)]
+    MessagePack(#[from] rmp_serde::decode::Error),
+}
+
+/// Deserializes a bincode-encoded value, as produced by `bincode::serialize`.
+pub(crate) fn from_bincode<T: DeserializeOwned>(bytes: &[u8], limits: Limits) -> Result<T, Error> {
+    check_input_size(bytes, limits)?;
+    let options = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(limits.max_allocation);
+    let mut deserializer = bincode::Deserializer::from_slice(bytes, options);
+    deserialize(&mut deserializer, limits)
+}
+
+/// Deserializes a MessagePack-encoded value.
+pub(crate) fn from_msgpack<T: DeserializeOwned>(bytes: &[u8], limits: Limits) -> Result<T, Error> {
+    check_input_size(bytes, limits)?;
+    let mut deserializer = rmp_serde::Deserializer::from_read_ref(bytes);
+    deserialize(&mut deserializer, limits)
+}
+
+/// Deserializes a JSON-encoded value.
+pub(crate) fn from_json<T: DeserializeOwned>(bytes: &[u8], limits: Limits) -> Result<T, Error> {
+    check_input_size(bytes, limits)?;
+    let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+    let value = deserialize(&mut deserializer, limits)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Deserializes a value from an already parsed JSON value.
+pub(crate) fn from_json_value<T: DeserializeOwned>(
+    value: serde_json::Value,
+    limits: Limits,
+) -> Result<T, Error> {
+    deserialize(value, limits)
+}
+
+fn check_input_size(bytes: &[u8], limits: Limits) -> Result<(), Error> {
+    if bytes.len() as u64 > limits.max_allocation {
+        return Err(Error::LimitExceeded(LimitExceeded::Allocation(
+            limits.max_allocation,
+        )));
+    }
+    Ok(())
+}
+
+/// Deserializes a value through `deserializer`, enforcing `limits`.
+fn deserialize<'de, T, D>(deserializer: D, limits: Limits) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+    Error: From<D::Error>,
+{
+    let budget = Budget::new(limits);
+    T::deserialize(Bounded {
+        inner: deserializer,
+        budget: &budget,
+    })
+    .map_err(|error| match budget.exceeded.get() {
+        // The format's error only wraps the message of the limit exceeded.
+        Some(exceeded) => Error::LimitExceeded(exceeded),
+        None => Error::from(error),
+    })
+}
+
+/// The resources used so far while deserializing a single input.
+struct Budget {
+    limits: Limits,
+    depth: Cell<usize>,
+    allocated: Cell<u64>,
+    /// The first limit exceeded, if any.
+    exceeded: Cell<Option<LimitExceeded>>,
+}
+
+impl Budget {
+    fn new(limits: Limits) -> Self {
+        Budget {
+            limits,
+            depth: Cell::new(0),
+            allocated: Cell::new(0),
+            exceeded: Cell::new(None),
+        }
+    }
+
+    fn exceed<E: de::Error>(&self, exceeded: LimitExceeded) -> E {
+        if self.exceeded.get().is_none() {
+            self.exceeded.set(Some(exceeded));
+        }
+        E::custom(exceeded)
+    }
+
+    /// Runs `f` one nesting level deeper.
+    fn nested<T, E: de::Error, F: FnOnce() -> Result<T, E>>(&self, f: F) -> Result<T, E> {
+        let depth = self.depth.get() + 1;
+        if depth > self.limits.max_depth {
+            return Err(self.exceed(LimitExceeded::Depth(self.limits.max_depth)));
+        }
+        self.depth.set(depth);
+        let result = f();
+        self.depth.set(depth - 1);
+        result
+    }
+
+    fn allocate<E: de::Error>(&self, bytes: usize) -> Result<(), E> {
+        let allocated = self.allocated.get().saturating_add(bytes as u64);
+        if allocated > self.limits.max_allocation {
+            return Err(self.exceed(LimitExceeded::Allocation(self.limits.max_allocation)));
+        }
+        self.allocated.set(allocated);
+        Ok(())
+    }
+
+    fn check_length<E: de::Error>(&self, length: usize) -> Result<(), E> {
+        if length > self.limits.max_collection_length {
+            return Err(self.exceed(LimitExceeded::CollectionLength(
+                self.limits.max_collection_length,
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A deserializer enforcing the limits of its budget on everything it deserializes.
+struct Bounded<'b, D> {
+    inner: D,
+    budget: &'b Budget,
+}
+
+impl<'b, D> Bounded<'b, D> {
+    fn visitor<V>(&self, visitor: V) -> BoundedVisitor<'b, V> {
+        BoundedVisitor {
+            inner: visitor,
+            budget: self.budget,
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                let visitor = self.visitor(visitor);
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, 'b, D: Deserializer<'de>> Deserializer<'de> for Bounded<'b, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_i128(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_u128(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// A visitor charging the budget for what it visits, wrapping any nested deserializer.
+struct BoundedVisitor<'b, V> {
+    inner: V,
+    budget: &'b Budget,
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, value: $ty) -> Result<Self::Value, E> {
+                self.inner.$method(value)
+            }
+        )*
+    };
+}
+
+macro_rules! forward_visit_allocating {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, value: $ty) -> Result<Self::Value, E> {
+                self.budget.allocate(value.len())?;
+                self.inner.$method(value)
+            }
+        )*
+    };
+}
+
+impl<'de, 'b, V: Visitor<'de>> Visitor<'de> for BoundedVisitor<'b, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    forward_visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+    }
+
+    forward_visit_allocating! {
+        visit_str(&str),
+        visit_borrowed_str(&'de str),
+        visit_string(String),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let budget = self.budget;
+        budget.nested(|| {
+            self.inner.visit_some(Bounded {
+                inner: deserializer,
+                budget,
+            })
+        })
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let budget = self.budget;
+        budget.nested(|| {
+            self.inner.visit_newtype_struct(Bounded {
+                inner: deserializer,
+                budget,
+            })
+        })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let budget = self.budget;
+        if let Some(length) = seq.size_hint() {
+            budget.check_length(length)?;
+        }
+        budget.nested(|| {
+            self.inner.visit_seq(BoundedAccess {
+                inner: seq,
+                budget,
+                count: 0,
+            })
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let budget = self.budget;
+        if let Some(length) = map.size_hint() {
+            budget.check_length(length)?;
+        }
+        budget.nested(|| {
+            self.inner.visit_map(BoundedAccess {
+                inner: map,
+                budget,
+                count: 0,
+            })
+        })
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let budget = self.budget;
+        budget.nested(|| {
+            self.inner.visit_enum(BoundedAccess {
+                inner: data,
+                budget,
+                count: 0,
+            })
+        })
+    }
+}
+
+/// A seed deserializing through a `Bounded` deserializer.
+struct BoundedSeed<'b, S> {
+    inner: S,
+    budget: &'b Budget,
+}
+
+impl<'de, 'b, S: DeserializeSeed<'de>> DeserializeSeed<'de> for BoundedSeed<'b, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.inner.deserialize(Bounded {
+            inner: deserializer,
+            budget: self.budget,
+        })
+    }
+}
+
+/// Access to the elements of a sequence or map, or to the variant of an enum.
+struct BoundedAccess<'b, A> {
+    inner: A,
+    budget: &'b Budget,
+    /// The number of elements accessed so far.
+    count: usize,
+}
+
+impl<'b, A> BoundedAccess<'b, A> {
+    fn seed<S>(&self, seed: S) -> BoundedSeed<'b, S> {
+        BoundedSeed {
+            inner: seed,
+            budget: self.budget,
+        }
+    }
+
+    /// Counts an element, failing if there are too many.
+    fn count_element<E: de::Error>(&mut self) -> Result<(), E> {
+        self.count += 1;
+        self.budget.check_length(self.count)
+    }
+}
+
+impl<'de, 'b, A: SeqAccess<'de>> SeqAccess<'de> for BoundedAccess<'b, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let seed = self.seed(seed);
+        let element = self.inner.next_element_seed(seed)?;
+        if element.is_some() {
+            self.count_element()?;
+        }
+        Ok(element)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, 'b, A: MapAccess<'de>> MapAccess<'de> for BoundedAccess<'b, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let seed = self.seed(seed);
+        let key = self.inner.next_key_seed(seed)?;
+        if key.is_some() {
+            self.count_element()?;
+        }
+        Ok(key)
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        let seed = self.seed(seed);
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, 'b, A: EnumAccess<'de>> EnumAccess<'de> for BoundedAccess<'b, A> {
+    type Error = A::Error;
+    type Variant = BoundedAccess<'b, A::Variant>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), Self::Error> {
+        let seed = self.seed(seed);
+        let (value, variant) = self.inner.variant_seed(seed)?;
+        Ok((
+            value,
+            BoundedAccess {
+                inner: variant,
+                budget: self.budget,
+                count: 0,
+            },
+        ))
+    }
+}
+
+impl<'de, 'b, A: VariantAccess<'de>> VariantAccess<'de> for BoundedAccess<'b, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        let seed = self.seed(seed);
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = BoundedVisitor {
+            inner: visitor,
+            budget: self.budget,
+        };
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = BoundedVisitor {
+            inner: visitor,
+            budget: self.budget,
+        };
+        self.inner.struct_variant(fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        max_allocation: 1024,
+        max_depth: 4,
+        max_collection_length: 8,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Nested {
+        Leaf(String),
+        Node(Vec<Nested>),
+    }
+
+    fn nested(depth: usize) -> Nested {
+        (0..depth).fold(Nested::Leaf("leaf".to_string()), |inner, _| {
+            Nested::Node(vec![inner])
+        })
+    }
+
+    #[test]
+    fn should_deserialize_within_limits() {
+        let value = nested(1);
+        let bincode = bincode::serialize(&value).unwrap();
+        assert_eq!(from_bincode::<Nested>(&bincode, LIMITS).unwrap(), value);
+        let msgpack = rmp_serde::to_vec(&value).unwrap();
+        assert_eq!(from_msgpack::<Nested>(&msgpack, LIMITS).unwrap(), value);
+        let json = serde_json::to_vec(&value).unwrap();
+        assert_eq!(from_json::<Nested>(&json, LIMITS).unwrap(), value);
+
+        let map: BTreeMap<u8, String> = (0..8).map(|key| (key, key.to_string())).collect();
+        let json = serde_json::to_value(&map).unwrap();
+        assert_eq!(
+            from_json_value::<BTreeMap<u8, String>>(json, LIMITS).unwrap(),
+            map
+        );
+    }
+
+    #[test]
+    fn should_reject_deep_nesting() {
+        let bincode = bincode::serialize(&nested(10)).unwrap();
+        assert!(matches!(
+            from_bincode::<Nested>(&bincode, LIMITS),
+            Err(Error::LimitExceeded(LimitExceeded::Depth(4)))
+        ));
+        let json = serde_json::to_vec(&nested(10)).unwrap();
+        assert!(matches!(
+            from_json::<Nested>(&json, LIMITS),
+            Err(Error::LimitExceeded(LimitExceeded::Depth(4)))
+        ));
+    }
+
+    #[test]
+    fn should_reject_long_collections() {
+        let bincode = bincode::serialize(&vec![0u16; 9]).unwrap();
+        assert!(matches!(
+            from_bincode::<Vec<u16>>(&bincode, LIMITS),
+            Err(Error::LimitExceeded(LimitExceeded::CollectionLength(8)))
+        ));
+        // JSON has no length prefix, so the elements are counted.
+        let json = serde_json::to_vec(&vec![0u16; 9]).unwrap();
+        assert!(matches!(
+            from_json::<Vec<u16>>(&json, LIMITS),
+            Err(Error::LimitExceeded(LimitExceeded::CollectionLength(8)))
+        ));
+    }
+
+    #[test]
+    fn should_not_allocate_claimed_lengths() {
+        // A string claiming to be 4 GiB long, followed by nothing.
+        let mut bincode = u64::from(u32::max_value()).to_le_bytes().to_vec();
+        bincode.extend_from_slice(b"short");
+        assert!(from_bincode::<String>(&bincode, LIMITS).is_err());
+
+        let mut msgpack = vec![0xdb];
+        msgpack.extend_from_slice(&u32::max_value().to_be_bytes());
+        assert!(from_msgpack::<String>(&msgpack, LIMITS).is_err());
+
+        // The strings are accounted for in total, not just individually.
+        let strings = vec!["x".repeat(300); 4];
+        let json = serde_json::to_value(&strings).unwrap();
+        assert!(matches!(
+            from_json_value::<Vec<String>>(json, LIMITS),
+            Err(Error::LimitExceeded(LimitExceeded::Allocation(1024)))
+        ));
+    }
+}
@@ -0,0 +1,231 @@
+//! Validation of a parsed configuration.
+//!
+//! Parsing only ensures that every value has the right type. Constraints spanning several fields,
+//! or depending on the environment the node runs in, are checked in a separate pass right after
+//! parsing. All problems found are collected, so that an operator can fix them in one go instead of
+//! restarting the node once per mistake.
+
+use std::{
+    ffi::CString,
+    fmt::{self, Display, Formatter},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use super::resolve_address;
+
+/// A single problem found in the configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigViolation {
+    /// Path of the offending field, e.g. `network.bind_address`.
+    pub field: String,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl Display for ConfigViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// The configuration failed validation.
+#[derive(Debug, Error)]
+pub struct ConfigValidationError {
+    /// All problems found, in the order they were detected.
+    pub violations: Vec<ConfigViolation>,
+}
+
+impl Display for ConfigValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid configuration, {} problem(s) found:",
+            self.violations.len()
+        )?;
+        for violation in &self.violations {
+            write!(f, "\n  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects violations while the sections of a configuration are checked.
+#[derive(Debug)]
+pub(crate) struct ConfigValidator {
+    /// Directory relative paths in the configuration are resolved against.
+    root: PathBuf,
+    /// Name of the section currently being checked, used as the prefix of field paths.
+    section: &'static str,
+    /// Violations found so far.
+    violations: Vec<ConfigViolation>,
+}
+
+impl ConfigValidator {
+    /// Creates a new validator resolving relative paths against `root`.
+    pub(crate) fn new<P: Into<PathBuf>>(root: P) -> Self {
+        ConfigValidator {
+            root: root.into(),
+            section: "",
+            violations: Vec::new(),
+        }
+    }
+
+    /// Sets the section subsequent checks are reported under.
+    pub(crate) fn section(&mut self, section: &'static str) -> &mut Self {
+        self.section = section;
+        self
+    }
+
+    /// Records a violation of `field` in the current section.
+    pub(crate) fn violation<M: Display>(&mut self, field: &str, message: M) {
+        let field = if self.section.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", self.section, field)
+        };
+        self.violations.push(ConfigViolation {
+            field,
+            message: message.to_string(),
+        });
+    }
+
+    /// Records a violation of `field` unless `condition` holds.
+    pub(crate) fn ensure<M: Display>(&mut self, condition: bool, field: &str, message: M) {
+        if !condition {
+            self.violation(field, message)
+        }
+    }
+
+    /// Checks that `value` is not zero.
+    pub(crate) fn ensure_non_zero<T: Default + PartialEq>(&mut self, field: &str, value: T) {
+        self.ensure(value != T::default(), field, "must not be zero")
+    }
+
+    /// Checks that `address` can be resolved to a socket address.
+    pub(crate) fn ensure_address(&mut self, field: &str, address: &str) {
+        if let Err(err) = resolve_address(address) {
+            self.violation(field, err)
+        }
+    }
+
+    /// Checks that `path` refers to an existing file.
+    pub(crate) fn ensure_file(&mut self, field: &str, path: &Path) {
+        let full_path = self.resolve(path);
+        if !full_path.is_file() {
+            self.violation(
+                field,
+                format!("file {} does not exist", full_path.display()),
+            )
+        }
+    }
+
+    /// Checks that `path` is a writable directory, or can be created as one.
+    pub(crate) fn ensure_writable_dir(&mut self, field: &str, path: &Path) {
+        let full_path = self.resolve(path);
+
+        // The directory is created on first use, so its closest existing ancestor needs to be
+        // writable instead if it is missing.
+        let existing = match full_path.ancestors().find(|ancestor| ancestor.exists()) {
+            Some(existing) => existing,
+            None => {
+                return self.violation(
+                    field,
+                    format!("{} has no existing parent", full_path.display()),
+                )
+            }
+        };
+
+        if !existing.is_dir() {
+            self.violation(field, format!("{} is not a directory", existing.display()))
+        } else if !is_writable(existing) {
+            self.violation(
+                field,
+                format!("directory {} is not writable", existing.display()),
+            )
+        }
+    }
+
+    /// Finishes validation, returning all violations found if there are any.
+    pub(crate) fn finish(self) -> Result<(), ConfigValidationError> {
+        if self.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigValidationError {
+                violations: self.violations,
+            })
+        }
+    }
+
+    /// Adds the root directory as a parent if `path` is relative.
+    fn resolve(&self, path: &Path) -> PathBuf {
+        if path.is_relative() {
+            self.root.join(path)
+        } else {
+            path.to_owned()
+        }
+    }
+}
+
+/// Returns whether the current process may write to `path`.
+fn is_writable(path: &Path) -> bool {
+    match CString::new(path.as_os_str().as_bytes()) {
+        // Safe, as `c_path` is a valid, nul-terminated string outliving the call.
+        Ok(c_path) => unsafe { libc::access(c_path.as_ptr(), libc::W_OK) == 0 },
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn should_collect_all_violations() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let file = tempdir.path().join("file");
+        fs::write(&file, b"").unwrap();
+
+        let mut validator = ConfigValidator::new(tempdir.path());
+        validator.section("network");
+        validator.ensure_address("bind_address", "not an address");
+        validator.ensure_address("public_address", "127.0.0.1:0");
+        validator.ensure_non_zero("gossip_interval", 0u64);
+        validator.section("storage");
+        validator.ensure_writable_dir("path", Path::new("file/storage"));
+        validator.ensure_writable_dir("other_path", Path::new("new/nested/dir"));
+        validator.ensure_file("missing_file", Path::new("missing"));
+        validator.ensure_file("present_file", Path::new("file"));
+
+        let fields: Vec<_> = validator
+            .finish()
+            .unwrap_err()
+            .violations
+            .into_iter()
+            .map(|violation| violation.field)
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "network.bind_address",
+                "network.gossip_interval",
+                "storage.path",
+                "storage.missing_file",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_accept_valid_config() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut validator = ConfigValidator::new(tempdir.path());
+        validator.section("storage");
+        validator.ensure_writable_dir("path", Path::new("storage"));
+        validator.ensure_non_zero("max_block_store_size", 4096usize);
+        assert!(validator.finish().is_ok());
+    }
+}
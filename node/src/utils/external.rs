@@ -24,7 +24,7 @@ use super::{read_file, ReadFileError};
 use crate::{crypto, crypto::AsymmetricKeyExt, tls};
 
 /// Path to bundled resources.
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 pub static RESOURCES_PATH: Lazy<PathBuf> =
     Lazy::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../resources"));
 
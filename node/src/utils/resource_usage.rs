@@ -0,0 +1,311 @@
+//! Attribution of CPU time and IO to components.
+//!
+//! Work done while the runner dispatches an event is attributed to the component the event belongs
+//! to, see `Reactor::event_component`. Work done outside of dispatching, in tasks run on behalf of
+//! a component such as the per-connection readers and writers of the network or the blocking pool
+//! of the contract runtime, is attributed by wrapping the task, see `AttributeExt` and
+//! `measure_blocking`.
+//!
+//! Measuring reads the CPU time of the current thread and its IO counters from
+//! `/proc/thread-self/io`, which is too costly to do on every dispatch or poll. Short sections are
+//! therefore sampled: only every `SAMPLE_INTERVAL`th section run on a thread is measured, counting
+//! its usage `SAMPLE_INTERVAL` times. The totals of components doing little work are rough
+//! estimates, while those of the components doing most of the work, the ones worth looking at,
+//! converge to the actual usage. Blocking sections run long compared to the cost of measuring
+//! them, so they are always measured.
+//!
+//! The totals are kept per process and exported as metrics by the reactor runner. Without a
+//! `/proc` file system, only CPU time is counted.
+
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    fs::File,
+    future::Future,
+    io::Read,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+use once_cell::sync::Lazy;
+
+/// Only every this many short sections on a thread are measured.
+pub(crate) const SAMPLE_INTERVAL: u64 = 16;
+
+/// Path of the IO counters of the current thread.
+const THREAD_IO_PATH: &str = "/proc/thread-self/io";
+
+/// Usage totals of all components measured so far.
+static TOTALS: Lazy<Mutex<BTreeMap<&'static str, Usage>>> = Lazy::new(Default::default);
+
+thread_local! {
+    /// Number of short sections run on the current thread, to pick the sampled ones.
+    static SECTIONS: Cell<u64> = Cell::new(0);
+}
+
+/// CPU time and IO of a thread or attributed to a component.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Usage {
+    /// CPU time, in nanoseconds.
+    pub(crate) cpu_ns: u64,
+    /// Number of read syscalls, e.g. `read` or `recvmsg`.
+    pub(crate) read_syscalls: u64,
+    /// Number of write syscalls, e.g. `write` or `sendmsg`.
+    pub(crate) write_syscalls: u64,
+    /// Number of bytes read, including reads served from the page cache and from sockets.
+    pub(crate) read_bytes: u64,
+    /// Number of bytes written.
+    pub(crate) write_bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, other: Usage) {
+        self.cpu_ns = self.cpu_ns.saturating_add(other.cpu_ns);
+        self.read_syscalls = self.read_syscalls.saturating_add(other.read_syscalls);
+        self.write_syscalls = self.write_syscalls.saturating_add(other.write_syscalls);
+        self.read_bytes = self.read_bytes.saturating_add(other.read_bytes);
+        self.write_bytes = self.write_bytes.saturating_add(other.write_bytes);
+    }
+}
+
+/// The counters of the current thread at one point in time.
+#[derive(Clone, Copy, Debug)]
+struct Snapshot {
+    usage: Usage,
+    /// Number of bytes read from `THREAD_IO_PATH` to take the snapshot, zero if it failed.
+    own_read_bytes: u64,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the counters of the current thread.
+    fn take() -> Self {
+        let mut buffer = [0; 512];
+        // A single `read` is issued, so the snapshot itself adds exactly one read syscall.
+        let read = File::open(THREAD_IO_PATH).and_then(|mut file| file.read(&mut buffer));
+        let (mut usage, own_read_bytes) = match read {
+            Ok(length) => (
+                parse_thread_io(&String::from_utf8_lossy(&buffer[..length])),
+                length as u64,
+            ),
+            Err(_) => (Usage::default(), 0),
+        };
+        usage.cpu_ns = thread_cpu_ns();
+        Snapshot {
+            usage,
+            own_read_bytes,
+        }
+    }
+
+    /// Returns the usage since `earlier`, multiplied by `factor`.
+    ///
+    /// The read of `THREAD_IO_PATH` taking `earlier` is not counted.
+    fn usage_since(&self, earlier: &Snapshot, factor: u64) -> Usage {
+        let (own_read_syscalls, own_read_bytes) = if earlier.own_read_bytes > 0 {
+            (1, earlier.own_read_bytes)
+        } else {
+            (0, 0)
+        };
+        let delta = |later: u64, earlier: u64, own: u64| {
+            later
+                .saturating_sub(earlier)
+                .saturating_sub(own)
+                .saturating_mul(factor)
+        };
+        Usage {
+            cpu_ns: delta(self.usage.cpu_ns, earlier.usage.cpu_ns, 0),
+            read_syscalls: delta(
+                self.usage.read_syscalls,
+                earlier.usage.read_syscalls,
+                own_read_syscalls,
+            ),
+            write_syscalls: delta(self.usage.write_syscalls, earlier.usage.write_syscalls, 0),
+            read_bytes: delta(
+                self.usage.read_bytes,
+                earlier.usage.read_bytes,
+                own_read_bytes,
+            ),
+            write_bytes: delta(self.usage.write_bytes, earlier.usage.write_bytes, 0),
+        }
+    }
+}
+
+/// Parses the IO counters in the format of `/proc/<pid>/io`, ignoring unknown or malformed lines.
+fn parse_thread_io(text: &str) -> Usage {
+    let mut usage = Usage::default();
+    for line in text.lines() {
+        let mut parts = line.splitn(2, ':');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => match value.trim().parse() {
+                Ok(value) => (key, value),
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+        match key {
+            "rchar" => usage.read_bytes = value,
+            "wchar" => usage.write_bytes = value,
+            "syscr" => usage.read_syscalls = value,
+            "syscw" => usage.write_syscalls = value,
+            _ => (),
+        }
+    }
+    usage
+}
+
+/// Returns the CPU time used by the current thread in nanoseconds, zero if it is unavailable.
+fn thread_cpu_ns() -> u64 {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // Safe, as `time` is a valid `timespec` outliving the call.
+    if unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) } != 0 {
+        return 0;
+    }
+    (time.tv_sec as u64)
+        .saturating_mul(1_000_000_000)
+        .saturating_add(time.tv_nsec as u64)
+}
+
+/// Runs `section`, attributing its usage to `component` if it is sampled.
+///
+/// Meant for short sections which run often, like dispatching an event or polling a future.
+pub(crate) fn measure<T, F: FnOnce() -> T>(component: &'static str, section: F) -> T {
+    let sampled = SECTIONS.with(|sections| {
+        let count = sections.get();
+        sections.set(count.wrapping_add(1));
+        count % SAMPLE_INTERVAL == 0
+    });
+    if sampled {
+        measure_scaled(component, SAMPLE_INTERVAL, section)
+    } else {
+        section()
+    }
+}
+
+/// Runs `section`, always attributing its usage to `component`.
+///
+/// Meant for long sections, like tasks on the blocking thread pool.
+pub(crate) fn measure_blocking<T, F: FnOnce() -> T>(component: &'static str, section: F) -> T {
+    measure_scaled(component, 1, section)
+}
+
+fn measure_scaled<T, F: FnOnce() -> T>(component: &'static str, factor: u64, section: F) -> T {
+    let before = Snapshot::take();
+    let result = section();
+    let usage = Snapshot::take().usage_since(&before, factor);
+    TOTALS
+        .lock()
+        .expect("resource usage lock poisoned")
+        .entry(component)
+        .or_default()
+        .add(usage);
+    result
+}
+
+/// Returns the usage attributed to every component so far, ordered by name.
+pub(crate) fn snapshot() -> Vec<(&'static str, Usage)> {
+    TOTALS
+        .lock()
+        .expect("resource usage lock poisoned")
+        .iter()
+        .map(|(component, usage)| (*component, *usage))
+        .collect()
+}
+
+/// A future whose polls are attributed to a component, see `AttributeExt`.
+pub(crate) struct Attributed<F> {
+    component: &'static str,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Attributed<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let future = this.future.as_mut();
+        measure(this.component, || future.poll(cx))
+    }
+}
+
+/// Extension for futures run on behalf of a component outside of dispatching its events.
+pub(crate) trait AttributeExt: Future + Sized {
+    /// Attributes the work done while polling this future to `component`.
+    fn attribute_to(self, component: &'static str) -> Attributed<Self> {
+        Attributed {
+            component,
+            future: Box::pin(self),
+        }
+    }
+}
+
+impl<F: Future> AttributeExt for F {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_thread_io() {
+        let text = "rchar: 4292\nwchar: 323\nsyscr: 11\nsyscw: 6\nread_bytes: 0\n\
+                    write_bytes: 4096\ncancelled_write_bytes: 0\n";
+        assert_eq!(
+            parse_thread_io(text),
+            Usage {
+                cpu_ns: 0,
+                read_syscalls: 11,
+                write_syscalls: 6,
+                read_bytes: 4292,
+                write_bytes: 323,
+            }
+        );
+        assert_eq!(parse_thread_io("rchar: many\nsyscw"), Usage::default());
+    }
+
+    #[test]
+    fn should_not_count_own_read_and_scale_samples() {
+        let snapshot = |cpu_ns, read_syscalls, read_bytes, own_read_bytes| Snapshot {
+            usage: Usage {
+                cpu_ns,
+                read_syscalls,
+                write_syscalls: 2,
+                read_bytes,
+                write_bytes: 10,
+            },
+            own_read_bytes,
+        };
+        let before = snapshot(1_000, 5, 400, 90);
+        let after = snapshot(1_500, 7, 500, 95);
+        assert_eq!(
+            after.usage_since(&before, 1),
+            Usage {
+                cpu_ns: 500,
+                read_syscalls: 1,
+                write_syscalls: 0,
+                read_bytes: 10,
+                write_bytes: 0,
+            }
+        );
+        assert_eq!(after.usage_since(&before, SAMPLE_INTERVAL).cpu_ns, 8_000);
+
+        // Without the IO counters, nothing is subtracted.
+        let before = snapshot(1_000, 0, 0, 0);
+        let after = snapshot(1_500, 0, 0, 0);
+        assert_eq!(after.usage_since(&before, 1).read_syscalls, 0);
+    }
+
+    #[test]
+    fn should_attribute_blocking_sections() {
+        let sum = measure_blocking("resource_usage_test", || {
+            (0..5_000_000u64).fold(0u64, |sum, value| sum.wrapping_add(value * value))
+        });
+        assert_ne!(sum, 0);
+        let (_, usage) = snapshot()
+            .into_iter()
+            .find(|(component, _)| *component == "resource_usage_test")
+            .expect("should have usage");
+        assert!(usage.cpu_ns > 0);
+    }
+}
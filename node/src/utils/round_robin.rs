@@ -3,20 +3,37 @@
 //! This module implements a weighted round-robin scheduler that ensures no deadlocks occur, but
 //! still allows prioritizing events from one source over another. The module uses `tokio`'s
 //! synchronization primitives under the hood.
+//!
+//! Individual queues can be given a disk-backed spillover, see `Spillover`, to bound their memory
+//! usage if they can legitimately grow very large.
+//!
+//! For each queue, the scheduler tracks since when it has continuously held items, see
+//! `busy_since`, so queues starved by the weighting of the others can be detected.
 
 use std::{
     collections::{HashMap, VecDeque},
-    fmt::Debug,
+    fmt::{self, Debug, Formatter},
     fs::File,
     hash::Hash,
     io::{self, BufWriter, Write},
     num::NonZeroUsize,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::{
+        self,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Instant,
 };
 
 use enum_iterator::IntoEnumIterator;
+use prometheus::{Histogram, IntGauge};
 use serde::{ser::SerializeMap, Serialize, Serializer};
 use tokio::sync::{Mutex, Semaphore};
+use tracing::{error, warn};
+
+use super::spill_ring::SpillRing;
+
+/// Maximum number of spilled items read back into memory at once.
+const READ_BACK_BATCH_SIZE: usize = 256;
 
 /// Weighted round-robin scheduler.
 ///
@@ -44,10 +61,18 @@ pub struct WeightedRoundRobin<I, K> {
 }
 
 /// State that wraps queue and its event count.
+///
+/// The event count includes items that were spilled to disk, while `queue` only holds the items in
+/// memory.
 #[derive(Debug)]
 struct QueueState<I> {
     event_count: AtomicUsize,
     queue: Mutex<VecDeque<I>>,
+    /// Disk-backed overflow, if enabled. Always locked after `queue`.
+    spillover: Option<Mutex<Spillover<I>>>,
+    /// Since when the queue has held items without running empty, `None` while it is empty. Only
+    /// changed while `queue` is locked.
+    busy_since: sync::Mutex<Option<Instant>>,
 }
 
 impl<I> QueueState<I> {
@@ -55,18 +80,54 @@ impl<I> QueueState<I> {
         QueueState {
             event_count: AtomicUsize::new(0),
             queue: Mutex::new(VecDeque::new()),
+            spillover: None,
+            busy_since: sync::Mutex::new(None),
         }
     }
 
     #[inline]
     async fn push_back(&self, element: I) {
-        self.queue.lock().await.push_back(element);
-        self.event_count.fetch_add(1, Ordering::SeqCst);
+        let mut queue = self.queue.lock().await;
+        match &self.spillover {
+            Some(spillover) => spillover.lock().await.push(&mut queue, element),
+            None => queue.push_back(element),
+        }
+        if self.event_count.fetch_add(1, Ordering::SeqCst) == 0 {
+            self.set_busy_since(Some(Instant::now()));
+        }
+    }
+
+    /// Reads spilled items back into `queue`, the locked in-memory queue, if it ran empty.
+    ///
+    /// If `all` is set, every spilled item is read back, irrespective of whether `queue` is empty.
+    ///
+    /// Returns the number of spilled items that were lost because they could not be read back. They
+    /// are already removed from the event count.
+    async fn read_back(&self, queue: &mut VecDeque<I>, all: bool) -> usize {
+        let spillover = match &self.spillover {
+            Some(spillover) if all || queue.is_empty() => spillover,
+            _ => return 0,
+        };
+        let lost = spillover.lock().await.read_back(queue, all);
+        self.sub_count(lost);
+        lost
     }
 
     #[inline]
     fn dec_count(&self) {
-        self.event_count.fetch_sub(1, Ordering::SeqCst);
+        self.sub_count(1);
+    }
+
+    /// Removes `count` items from the event count, marking the queue as no longer busy if it ran
+    /// empty.
+    fn sub_count(&self, count: usize) {
+        if count > 0 && self.event_count.fetch_sub(count, Ordering::SeqCst) == count {
+            self.set_busy_since(None);
+        }
+    }
+
+    fn set_busy_since(&self, busy_since: Option<Instant>) {
+        *self.busy_since.lock().expect("busy since lock poisoned") = busy_since;
     }
 
     #[inline]
@@ -75,6 +136,146 @@ impl<I> QueueState<I> {
     }
 }
 
+/// Converts queued items to and from bytes, so they can be spilled to disk.
+pub(crate) trait SpillCodec<I>: Send + Sync {
+    /// Encodes an item, or hands it back if it cannot be spilled.
+    fn encode(&self, item: I) -> Result<Vec<u8>, I>;
+
+    /// Decodes an item encoded by `encode`, returning `None` if that fails.
+    fn decode(&self, bytes: &[u8]) -> Option<I>;
+}
+
+/// Disk-backed overflow of a single queue.
+///
+/// Once the in-memory part of the queue holds `max_in_memory` items, further items are written to
+/// a `SpillRing` and read back in batches when the in-memory part runs empty. Items the codec
+/// cannot encode are kept in memory; if such an item arrives while spilled items are pending, it
+/// and all items after it are held in `tail` until the ring has been read back, which preserves
+/// the order of the queue at the cost of exceeding the memory bound in that rare case.
+pub(crate) struct Spillover<I> {
+    /// Number of items kept in memory before spilling.
+    max_in_memory: usize,
+    /// The spilled items.
+    ring: SpillRing,
+    /// Items queued behind the spilled ones which could not be spilled themselves.
+    tail: VecDeque<I>,
+    /// Codec for the spilled items.
+    codec: Box<dyn SpillCodec<I>>,
+    /// Gauge tracking the number of spilled items.
+    depth: IntGauge,
+    /// Histogram of the time taken to read back a batch of spilled items, in nanoseconds.
+    read_back_duration: Histogram,
+}
+
+impl<I> Spillover<I> {
+    /// Creates a new spillover writing to `ring`.
+    pub(crate) fn new(
+        max_in_memory: usize,
+        ring: SpillRing,
+        codec: Box<dyn SpillCodec<I>>,
+        depth: IntGauge,
+        read_back_duration: Histogram,
+    ) -> Self {
+        Spillover {
+            max_in_memory,
+            ring,
+            tail: VecDeque::new(),
+            codec,
+            depth,
+            read_back_duration,
+        }
+    }
+
+    /// Adds an item to the back of the queue, of which `queue` is the in-memory part.
+    fn push(&mut self, queue: &mut VecDeque<I>, item: I) {
+        if !self.tail.is_empty() {
+            self.tail.push_back(item);
+            return;
+        }
+        if self.ring.is_empty() && queue.len() < self.max_in_memory {
+            queue.push_back(item);
+            return;
+        }
+
+        match self.codec.encode(item) {
+            Ok(bytes) => match self.ring.push(&bytes) {
+                Ok(()) => self.depth.inc(),
+                Err(error) => {
+                    warn!(%error, "could not spill event to disk, keeping it in memory");
+                    match self.codec.decode(&bytes) {
+                        Some(item) => self.keep_in_memory(queue, item),
+                        None => error!("could not decode event which failed to spill, dropping it"),
+                    }
+                }
+            },
+            Err(item) => self.keep_in_memory(queue, item),
+        }
+    }
+
+    /// Keeps an item in memory, behind any spilled items.
+    fn keep_in_memory(&mut self, queue: &mut VecDeque<I>, item: I) {
+        if self.ring.is_empty() {
+            queue.push_back(item);
+        } else {
+            self.tail.push_back(item);
+        }
+    }
+
+    /// Moves one batch of spilled items, or all of them if `all` is set, to the back of `queue`.
+    ///
+    /// Returns the number of items lost because they could not be read back.
+    fn read_back(&mut self, queue: &mut VecDeque<I>, all: bool) -> usize {
+        let mut lost = 0;
+        if !self.ring.is_empty() {
+            let start = Instant::now();
+            let mut read = 0;
+            while all || read < READ_BACK_BATCH_SIZE {
+                match self.ring.pop() {
+                    Ok(Some(bytes)) => {
+                        read += 1;
+                        match self.codec.decode(&bytes) {
+                            Some(item) => queue.push_back(item),
+                            None => {
+                                error!("could not decode spilled event, dropping it");
+                                lost += 1;
+                            }
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(error) => {
+                        // We cannot tell where the next record starts, so all of them are lost.
+                        error!(%error, spilled = self.ring.len(), "could not read back spilled events, dropping them");
+                        lost += self.ring.len();
+                        if let Err(error) = self.ring.clear() {
+                            error!(%error, "could not clear spill file");
+                        }
+                        break;
+                    }
+                }
+            }
+            self.read_back_duration
+                .observe(start.elapsed().as_nanos() as f64);
+        }
+        self.depth.set(self.ring.len() as i64);
+
+        if self.ring.is_empty() {
+            queue.append(&mut self.tail);
+        }
+        lost
+    }
+}
+
+impl<I> Debug for Spillover<I> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Spillover")
+            .field("max_in_memory", &self.max_in_memory)
+            .field("spilled", &self.ring.len())
+            .field("tail", &self.tail.len())
+            .finish()
+    }
+}
+
 /// The inner state of the queue iteration.
 #[derive(Copy, Clone, Debug)]
 struct IterationState<K> {
@@ -107,7 +308,8 @@ where
 {
     /// Create a snapshot of the queue by locking it and serializing it.
     ///
-    /// The serialized events are streamed directly into `serializer`.
+    /// The serialized events are streamed directly into `serializer`. Events spilled to disk are
+    /// not included.
     ///
     /// # Warning
     ///
@@ -145,6 +347,68 @@ where
     }
 }
 
+impl<I, K> WeightedRoundRobin<I, K>
+where
+    K: Copy + Clone + Eq + Hash + IntoEnumIterator,
+{
+    /// Removes all items from all queues.
+    ///
+    /// Returns the items of each queue in the order they would have been popped in, with the
+    /// queues in the order defined by `IntoEnumIterator`. The same locking caveats as for
+    /// `snapshot` apply.
+    pub(crate) async fn drain(&self) -> Vec<(I, K)> {
+        let mut locks = Vec::new();
+        for kind in K::into_enum_iter() {
+            let queue_state = self
+                .queues
+                .get(&kind)
+                .expect("missing queue while draining");
+            locks.push((kind, queue_state, queue_state.queue.lock().await));
+        }
+
+        let mut items = Vec::new();
+        for (kind, queue_state, mut guard) in locks {
+            let lost = queue_state.read_back(&mut guard, true).await;
+            self.forget_permits(lost);
+            for item in guard.drain(..) {
+                queue_state.dec_count();
+                if let Ok(permit) = self.total.try_acquire() {
+                    permit.forget();
+                }
+                items.push((item, kind));
+            }
+        }
+        items
+    }
+
+    /// Collects the items of all queues `f` maps to a value, without removing them.
+    ///
+    /// The values are returned in the order of `drain`, and the same locking caveats as for
+    /// `snapshot` apply. Items spilled to disk are not included.
+    pub(crate) async fn collect<T, F>(&self, mut f: F) -> Vec<(T, K)>
+    where
+        F: FnMut(&I) -> Option<T>,
+    {
+        let mut locks = Vec::new();
+        for kind in K::into_enum_iter() {
+            let queue_guard = self
+                .queues
+                .get(&kind)
+                .expect("missing queue while collecting")
+                .queue
+                .lock()
+                .await;
+            locks.push((kind, queue_guard));
+        }
+
+        let mut values = Vec::new();
+        for (kind, guard) in locks {
+            values.extend(guard.iter().filter_map(&mut f).map(|value| (value, kind)));
+        }
+        values
+    }
+}
+
 impl<I, K> WeightedRoundRobin<I, K>
 where
     I: Debug,
@@ -169,6 +433,9 @@ where
         for (kind, guard) in locks {
             let queue = &*guard;
             writer.write_all(format!("Queue: {:?} ({}) [\n", kind, queue.len()).as_bytes())?;
+            if let Some(spillover) = &self.queues[&kind].spillover {
+                writer.write_all(format!("\t{:?}\n", &*spillover.lock().await).as_bytes())?;
+            }
             for event in queue.iter() {
                 writer.write_all(format!("\t{:?}\n", event).as_bytes())?;
             }
@@ -213,6 +480,18 @@ where
         }
     }
 
+    /// Enables spilling to disk for the queue identified by key.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the queue identified by key `queue` does not exist.
+    pub(crate) fn enable_spillover(&mut self, queue: K, spillover: Spillover<I>) {
+        self.queues
+            .get_mut(&queue)
+            .expect("tried to enable spillover for non-existent queue")
+            .spillover = Some(Mutex::new(spillover));
+    }
+
     /// Pushes an item to a queue identified by key.
     ///
     /// ## Panics
@@ -233,37 +512,60 @@ where
     ///
     /// Asynchronously waits until a queue is non-empty or panics if an internal error occurred.
     pub(crate) async fn pop(&self) -> (I, K) {
-        self.total.acquire().await.forget();
-
-        let mut inner = self.state.lock().await;
-
-        // We know we have at least one item in a queue.
-        loop {
-            let queue_state = self
-                .queues
-                // The queue disappearing should never happen.
-                .get(&inner.active_slot.key)
-                .expect("the queue disappeared. this should not happen");
-
-            let mut current_queue = queue_state.queue.lock().await;
-
-            if inner.active_slot.tickets == 0 || current_queue.is_empty() {
-                // Go to next queue slot if we've exhausted the current queue.
-                inner.active_slot_idx = (inner.active_slot_idx + 1) % self.slots.len();
-                inner.active_slot = self.slots[inner.active_slot_idx];
-                continue;
+        'acquire: loop {
+            self.total.acquire().await.forget();
+
+            let mut inner = self.state.lock().await;
+
+            // We know we have at least one item in a queue.
+            loop {
+                let queue_state = self
+                    .queues
+                    // The queue disappearing should never happen.
+                    .get(&inner.active_slot.key)
+                    .expect("the queue disappeared. this should not happen");
+
+                let mut current_queue = queue_state.queue.lock().await;
+
+                let lost = queue_state.read_back(&mut current_queue, false).await;
+                if lost > 0 && self.forget_permits(lost) {
+                    // The permit we acquired belonged to one of the lost items, so we have to wait
+                    // for another one.
+                    continue 'acquire;
+                }
+
+                if inner.active_slot.tickets == 0 || current_queue.is_empty() {
+                    // Go to next queue slot if we've exhausted the current queue.
+                    inner.active_slot_idx = (inner.active_slot_idx + 1) % self.slots.len();
+                    inner.active_slot = self.slots[inner.active_slot_idx];
+                    continue;
+                }
+
+                // We have hit a queue that is not empty. Decrease tickets and pop.
+                inner.active_slot.tickets -= 1;
+
+                let item = current_queue
+                    .pop_front()
+                    // We hold the queue's lock and checked `is_empty` earlier.
+                    .expect("item disappeared. this should not happen");
+                queue_state.dec_count();
+                break 'acquire (item, inner.active_slot.key);
             }
+        }
+    }
 
-            // We have hit a queue that is not empty. Decrease tickets and pop.
-            inner.active_slot.tickets -= 1;
-
-            let item = current_queue
-                .pop_front()
-                // We hold the queue's lock and checked `is_empty` earlier.
-                .expect("item disappeared. this should not happen");
-            queue_state.dec_count();
-            break (item, inner.active_slot.key);
+    /// Removes the permits of `count` items that were lost.
+    ///
+    /// Returns `true` if there were fewer permits available, which can only happen if the caller
+    /// holds the permit of a lost item.
+    fn forget_permits(&self, count: usize) -> bool {
+        for _ in 0..count {
+            match self.total.try_acquire() {
+                Ok(permit) => permit.forget(),
+                Err(_) => return true,
+            }
         }
+        false
     }
 
     /// Returns the number of events currently in the queue.
@@ -278,6 +580,37 @@ where
             .map(|(key, queue)| (*key, queue.event_count()))
             .collect()
     }
+
+    /// Returns since when each of the queues has held items without running empty, `None` for the
+    /// empty ones.
+    pub(crate) fn busy_since(&self) -> HashMap<K, Option<Instant>> {
+        self.queues
+            .iter()
+            .map(|(key, queue)| {
+                let busy_since = *queue.busy_since.lock().expect("busy since lock poisoned");
+                (*key, busy_since)
+            })
+            .collect()
+    }
+
+    /// Counts the items held in memory across all queues by the class `classify` assigns them.
+    ///
+    /// Queues are locked one at a time, so the counts are not a consistent snapshot. Items spilled
+    /// to disk are not counted.
+    pub(crate) async fn count_by<C, F>(&self, classify: F) -> HashMap<C, usize>
+    where
+        C: Eq + Hash,
+        F: Fn(&I) -> C,
+    {
+        let mut counts = HashMap::new();
+        for queue_state in self.queues.values() {
+            let guard = queue_state.queue.lock().await;
+            for item in guard.iter() {
+                *counts.entry(classify(item)).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
 }
 
 #[cfg(test)]
@@ -289,7 +622,7 @@ mod tests {
     use super::*;
 
     #[repr(usize)]
-    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, IntoEnumIterator)]
     enum QueueKind {
         One = 1,
         Two,
@@ -326,4 +659,160 @@ mod tests {
         assert_eq!(('f', QueueKind::Two), scheduler.pop().await);
         assert_eq!(('c', QueueKind::One), scheduler.pop().await);
     }
+
+    /// Spills lowercase characters, but keeps all others in memory.
+    struct LowercaseCodec;
+
+    impl SpillCodec<char> for LowercaseCodec {
+        fn encode(&self, item: char) -> Result<Vec<u8>, char> {
+            if item.is_ascii_lowercase() {
+                Ok(vec![item as u8])
+            } else {
+                Err(item)
+            }
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Option<char> {
+            bytes.first().map(|byte| *byte as char)
+        }
+    }
+
+    fn spilling_scheduler(
+        temp_dir: &tempfile::TempDir,
+        max_in_memory: usize,
+    ) -> (WeightedRoundRobin<char, QueueKind>, IntGauge) {
+        let depth = IntGauge::new("spilled", "spilled items").unwrap();
+        let read_back_duration = Histogram::with_opts(prometheus::HistogramOpts::new(
+            "read_back_duration",
+            "read back duration",
+        ))
+        .unwrap();
+        let ring = SpillRing::create(temp_dir.path().join("spill")).unwrap();
+        let mut scheduler = WeightedRoundRobin::<char, QueueKind>::new(weights());
+        scheduler.enable_spillover(
+            QueueKind::One,
+            Spillover::new(
+                max_in_memory,
+                ring,
+                Box::new(LowercaseCodec),
+                depth.clone(),
+                read_back_duration,
+            ),
+        );
+        (scheduler, depth)
+    }
+
+    #[tokio::test]
+    async fn should_preserve_order_when_spilling() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (scheduler, depth) = spilling_scheduler(&temp_dir, 2);
+
+        // `a` and `b` stay in memory, `c` and `d` are spilled, `E` cannot be spilled and forces `f`
+        // to stay in memory behind it.
+        for item in "abcdEf".chars() {
+            scheduler.push(item, QueueKind::One).await;
+        }
+        assert_eq!(depth.get(), 2);
+        assert_eq!(scheduler.item_count(), 6);
+        assert_eq!(scheduler.event_queues_counts()[&QueueKind::One], 6);
+
+        let mut popped = String::new();
+        for _ in 0..6 {
+            popped.push(scheduler.pop().await.0);
+        }
+        assert_eq!(popped, "abcdEf");
+        assert_eq!(depth.get(), 0);
+        assert_eq!(scheduler.item_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_drain_spilled_items() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (scheduler, depth) = spilling_scheduler(&temp_dir, 1);
+
+        for item in "abc".chars() {
+            scheduler.push(item, QueueKind::One).await;
+        }
+        scheduler.push('x', QueueKind::Two).await;
+        assert_eq!(depth.get(), 2);
+
+        assert_eq!(
+            vec![
+                ('a', QueueKind::One),
+                ('b', QueueKind::One),
+                ('c', QueueKind::One),
+                ('x', QueueKind::Two)
+            ],
+            scheduler.drain().await
+        );
+        assert_eq!(depth.get(), 0);
+        assert_eq!(0, scheduler.item_count());
+    }
+
+    #[tokio::test]
+    async fn should_drain_all_queues() {
+        let scheduler = WeightedRoundRobin::<char, QueueKind>::new(weights());
+        scheduler.push('a', QueueKind::Two).await;
+        scheduler.push('b', QueueKind::One).await;
+        scheduler.push('c', QueueKind::Two).await;
+
+        assert_eq!(
+            vec![
+                ('b', QueueKind::One),
+                ('a', QueueKind::Two),
+                ('c', QueueKind::Two)
+            ],
+            scheduler.drain().await
+        );
+        assert_eq!(0, scheduler.item_count());
+        assert!(scheduler
+            .event_queues_counts()
+            .values()
+            .all(|count| *count == 0));
+
+        // The scheduler remains usable.
+        scheduler.push('d', QueueKind::One).await;
+        assert_eq!(('d', QueueKind::One), scheduler.pop().await);
+    }
+
+    #[tokio::test]
+    async fn should_collect_without_removing() {
+        let scheduler = WeightedRoundRobin::<char, QueueKind>::new(weights());
+        scheduler.push('a', QueueKind::Two).await;
+        scheduler.push('B', QueueKind::One).await;
+        scheduler.push('c', QueueKind::Two).await;
+
+        let collected = scheduler
+            .collect(|item| {
+                if item.is_lowercase() {
+                    Some(item.to_ascii_uppercase())
+                } else {
+                    None
+                }
+            })
+            .await;
+        assert_eq!(
+            vec![('A', QueueKind::Two), ('C', QueueKind::Two)],
+            collected
+        );
+        assert_eq!(3, scheduler.item_count());
+    }
+
+    #[tokio::test]
+    async fn should_track_busy_queues_until_drained() {
+        let scheduler = WeightedRoundRobin::<char, QueueKind>::new(weights());
+        assert!(scheduler.busy_since().values().all(Option::is_none));
+
+        scheduler.push('a', QueueKind::One).await;
+        let busy_since = scheduler.busy_since()[&QueueKind::One].expect("should be busy");
+        assert!(scheduler.busy_since()[&QueueKind::Two].is_none());
+
+        // Popping an item without draining the queue keeps it busy since the first push.
+        scheduler.push('b', QueueKind::One).await;
+        assert_eq!(('a', QueueKind::One), scheduler.pop().await);
+        assert_eq!(scheduler.busy_since()[&QueueKind::One], Some(busy_since));
+
+        assert_eq!(('b', QueueKind::One), scheduler.pop().await);
+        assert!(scheduler.busy_since()[&QueueKind::One].is_none());
+    }
 }
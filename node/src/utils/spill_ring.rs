@@ -0,0 +1,138 @@
+//! A disk-backed FIFO of byte records.
+//!
+//! Records are appended to a single file, each prefixed with its length as a little-endian `u32`,
+//! and read back in the order they were written. Whenever the last record has been read, the file
+//! is truncated and writing starts from its beginning again, so the file only grows as long as
+//! records are written faster than they are read.
+
+use std::{
+    convert::TryFrom,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use tracing::debug;
+
+/// A disk-backed FIFO of byte records.
+#[derive(Debug)]
+pub(crate) struct SpillRing {
+    /// Path of the backing file, removed when the ring is dropped.
+    path: PathBuf,
+    /// The backing file.
+    file: File,
+    /// Offset of the next record to read.
+    read_pos: u64,
+    /// Offset at which the next record is written.
+    write_pos: u64,
+    /// Number of records not read yet.
+    len: usize,
+}
+
+impl SpillRing {
+    /// Creates an empty ring backed by the file at `path`, truncating it if it exists.
+    pub(crate) fn create(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(SpillRing {
+            path,
+            file,
+            read_pos: 0,
+            write_pos: 0,
+            len: 0,
+        })
+    }
+
+    /// Appends a record.
+    pub(crate) fn push(&mut self, record: &[u8]) -> io::Result<()> {
+        let length = u32::try_from(record.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record too large"))?;
+        self.file.seek(SeekFrom::Start(self.write_pos))?;
+        self.file.write_all(&length.to_le_bytes())?;
+        self.file.write_all(record)?;
+        self.write_pos += 4 + u64::from(length);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes and returns the oldest record, if any.
+    pub(crate) fn pop(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.file.seek(SeekFrom::Start(self.read_pos))?;
+        let mut length = [0; 4];
+        self.file.read_exact(&mut length)?;
+        let length = u32::from_le_bytes(length);
+        let mut record = vec![0; length as usize];
+        self.file.read_exact(&mut record)?;
+        self.read_pos += 4 + u64::from(length);
+        self.len -= 1;
+
+        if self.len == 0 {
+            self.clear()?;
+        }
+        Ok(Some(record))
+    }
+
+    /// Discards all records.
+    pub(crate) fn clear(&mut self) -> io::Result<()> {
+        self.len = 0;
+        self.read_pos = 0;
+        self.write_pos = 0;
+        self.file.set_len(0)
+    }
+
+    /// Returns the number of records not read yet.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether all records have been read.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Drop for SpillRing {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.path) {
+            debug!(path = %self.path.display(), %error, "could not remove spill file");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_return_records_in_order_and_rewind_when_empty() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("ring");
+        let mut ring = SpillRing::create(path.clone()).unwrap();
+
+        ring.push(b"one").unwrap();
+        ring.push(b"").unwrap();
+        ring.push(b"three").unwrap();
+        assert_eq!(ring.len(), 3);
+
+        assert_eq!(ring.pop().unwrap(), Some(b"one".to_vec()));
+        ring.push(b"four").unwrap();
+        assert_eq!(ring.pop().unwrap(), Some(Vec::new()));
+        assert_eq!(ring.pop().unwrap(), Some(b"three".to_vec()));
+        assert_eq!(ring.pop().unwrap(), Some(b"four".to_vec()));
+        assert!(ring.is_empty());
+        assert_eq!(ring.pop().unwrap(), None);
+
+        // The file is truncated once all records have been read.
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+
+        drop(ring);
+        assert!(!path.exists());
+    }
+}
@@ -1,7 +1,8 @@
 use crate::{
     parse::{ReactorDefinition, Target},
-    util::suffix_ident,
+    util::{suffix_ident, to_ident},
 };
+use inflector::cases::snakecase::to_snake_case;
 use proc_macro2::TokenStream;
 use quote::quote;
 
@@ -181,6 +182,71 @@ pub(crate) fn generate_reactor_types(def: &ReactorDefinition) -> TokenStream {
     )
 }
 
+/// Generates a test module checking the routing of requests and announcements.
+///
+/// For every route to a component, the test checks that the component's event type can be created
+/// from the routed request or announcement, one route at a time, so a broken route is reported by
+/// name instead of as an error somewhere inside the generated `dispatch_event`. Announcements with
+/// several handling targets are checked to be `Clone`, as they are broadcast. Routes to a
+/// discard, a panic or a dispatch method are declared deliberately and not checked.
+pub(crate) fn generate_routing_tests(def: &ReactorDefinition) -> TokenStream {
+    let reactor_ident = def.reactor_ident();
+    let event_ident = def.event_ident();
+    let module_ident = to_ident(&format!(
+        "{}_routing_tests",
+        to_snake_case(&reactor_ident.to_string())
+    ));
+
+    let mut conversions = Vec::new();
+    let mut broadcasts = Vec::new();
+    let mut push_route = |routed_type: TokenStream, target: &Target| match target {
+        Target::Dest(ref dest) => {
+            let dest_component_type = def.component(dest).full_component_type();
+            conversions.push(quote!(
+                    assert_routable::<
+                        <#dest_component_type as crate::components::Component<super::#event_ident>>::Event,
+                        #routed_type,
+                    >();
+                ));
+        }
+        Target::Discard | Target::Panic | Target::Dispatch(_) => {}
+    };
+
+    for request in def.requests() {
+        push_route(request.full_request_type(), request.target());
+    }
+    for announcement in def.announcements() {
+        if announcement.handling_targets().count() > 1 {
+            let full_announcement_type = announcement.full_announcement_type();
+            broadcasts.push(quote!(
+                assert_broadcastable::<#full_announcement_type>();
+            ));
+        }
+        for target in announcement.targets() {
+            push_route(announcement.full_announcement_type(), target);
+        }
+    }
+
+    quote!(
+        #[cfg(test)]
+        mod #module_ident {
+            /// Compiles only if `R` is routable to a component with event type `T`.
+            #[allow(dead_code)]
+            fn assert_routable<T: From<R>, R>() {}
+
+            /// Compiles only if `A` can be broadcast to several targets.
+            #[allow(dead_code)]
+            fn assert_broadcastable<A: Clone>() {}
+
+            #[test]
+            fn routes_to_declared_targets() {
+                #(#conversions)*
+                #(#broadcasts)*
+            }
+        }
+    )
+}
+
 /// Generates the reactor implementation itself.
 pub(crate) fn generate_reactor_impl(def: &ReactorDefinition) -> TokenStream {
     let reactor_ident = def.reactor_ident();
@@ -256,23 +322,46 @@ pub(crate) fn generate_reactor_impl(def: &ReactorDefinition) -> TokenStream {
         }
     }
 
-    // Announcements dispatched also.
+    // Announcements are broadcast to all of their targets, in the order given. Every target but the
+    // last one receives a clone, so only announcements with several handling targets need to be
+    // `Clone`.
     for announcement in def.announcements() {
         let announcement_variant_ident = announcement.variant_ident();
 
+        if announcement
+            .targets()
+            .any(|target| matches!(target, Target::Panic))
+        {
+            // The parser ensures `!` is the only target.
+            dispatches.push(quote!(
+                #event_ident::#announcement_variant_ident(announcement) => {
+                    panic!("announcement received that was expressively declared as panic: {:?}",
+                           announcement)
+                },
+            ));
+            continue;
+        }
+
+        // Discards are skipped.
+        // TODO: Add `trace!` call here? Consider the log spam though.
+        let handling_targets: Vec<_> = announcement.handling_targets().collect();
+
+        if handling_targets.is_empty() {
+            dispatches.push(quote!(
+                #event_ident::#announcement_variant_ident(_announcement) => Default::default(),
+            ));
+            continue;
+        }
+
         let mut announcement_dispatches = Vec::new();
-        for target in announcement.targets() {
+        for (index, target) in handling_targets.iter().enumerate() {
+            let routed = if index + 1 < handling_targets.len() {
+                quote!(::std::clone::Clone::clone(&announcement))
+            } else {
+                quote!(announcement)
+            };
+
             match target {
-                Target::Discard => {
-                    // Don't do anything.
-                    // TODO: Add `trace!` call here? Consider the log spam though.
-                }
-                Target::Panic => {
-                    announcement_dispatches.push(quote!(
-                        panic!("announcement received that was expressively declard as panic: {:?}",
-                               announcement);
-                    ));
-                }
                 Target::Dest(ref dest) => {
                     let dest_component_type = def.component(dest).full_component_type();
                     let dest_variant_ident = def.component(dest).variant_ident();
@@ -280,7 +369,7 @@ pub(crate) fn generate_reactor_impl(def: &ReactorDefinition) -> TokenStream {
 
                     announcement_dispatches.push(quote!(
                         // Dispatch announcement to target:
-                        let dest_event = <#dest_component_type as crate::components::Component<Self::Event>>::Event::from(announcement);
+                        let dest_event = <#dest_component_type as crate::components::Component<Self::Event>>::Event::from(#routed);
 
                         let effects = crate::reactor::wrap_effects(
                             #event_ident::#dest_variant_ident,
@@ -292,11 +381,12 @@ pub(crate) fn generate_reactor_impl(def: &ReactorDefinition) -> TokenStream {
                 }
                 Target::Dispatch(ref fname) => {
                     announcement_dispatches.push(quote!(
-                        let effects = self.#fname(effect_builder, rng, announcement);
+                        let effects = self.#fname(effect_builder, rng, #routed);
 
                         announcement_effects.extend(effects.into_iter());
                     ));
                 }
+                Target::Discard | Target::Panic => unreachable!("filtered out above"),
             }
         }
 
@@ -28,6 +28,7 @@ pub fn reactor(input: TokenStream) -> TokenStream {
     output.extend(gen::generate_reactor(&def));
     output.extend(gen::generate_reactor_types(&def));
     output.extend(gen::generate_reactor_impl(&def));
+    output.extend(gen::generate_routing_tests(&def));
 
     output.into()
 }
@@ -34,7 +34,7 @@ pub(crate) struct ReactorDefinition {
     /// A full type that will later be the `Reactor::Config` associated type.
     config_type: RustType,
 
-    /// Mapping of component attribute names to their types.
+    /// Mapping of component attribute names to their types, including plugins.
     ///
     /// Example: "net" maps to `crate::components::small_net::SmallNet<NodeId>`.
     components: IndexMap<Ident, ComponentDefinition>,
@@ -77,7 +77,7 @@ impl ReactorDefinition {
         self.announcements.iter()
     }
 
-    /// Returns an iterator over all component definitions.
+    /// Returns an iterator over all component definitions, including plugins.
     pub fn components(&self) -> impl Iterator<Item = &ComponentDefinition> {
         self.components.values()
     }
@@ -99,8 +99,7 @@ impl ReactorDefinition {
 
     /// Returns the type for the event associated with a specific component.
     pub fn component_event(&self, component: &ComponentDefinition) -> TokenStream {
-        let component_type = component.component_type();
-        let module_ident = component_type.module_ident();
+        let module_path = component.module_path();
 
         let event_ident = if let Some(event_def) = self.events.get(component.field_ident()) {
             let path = event_def.event_type.as_given();
@@ -110,7 +109,7 @@ impl ReactorDefinition {
             quote!(#ident)
         };
 
-        quote!(crate::components::#module_ident::#event_ident)
+        quote!(#module_path::#event_ident)
     }
 }
 
@@ -171,6 +170,28 @@ impl Parse for ReactorDefinition {
             .into_iter()
             .collect();
 
+        // Plugins, optional.
+        if content.peek(kw::plugins) {
+            let plugin_content;
+            let _: kw::plugins = content.parse()?;
+            let _: Token!(:) = content.parse()?;
+            braced!(plugin_content in content);
+
+            for mut pdef in plugin_content
+                .parse_terminated::<ComponentDefinition, Token!(;)>(ComponentDefinition::parse)?
+            {
+                let name = pdef.name.to_string();
+                if components.keys().any(|ident| ident.to_string() == name) {
+                    return Err(syn::Error::new_spanned(
+                        &pdef.name,
+                        format!("A plugin has the same name as a component: {}", pdef.name),
+                    ));
+                }
+                pdef.is_plugin = true;
+                components.insert(pdef.name.clone(), pdef);
+            }
+        }
+
         // We can now perform some rudimentary checks. Component keys are converted to strings, so
         // rid them of their span information.
         let component_keys: IndexSet<_> =
@@ -246,6 +267,9 @@ pub(crate) struct ComponentDefinition {
     has_effects: bool,
     /// Whether or not the component's `new` function returns a component instead of a `Result`.
     is_infallible: bool,
+    /// Whether the component is a plugin, found in `crate::plugins` instead of
+    /// `crate::components`.
+    is_plugin: bool,
 }
 
 impl ComponentDefinition {
@@ -269,13 +293,33 @@ impl ComponentDefinition {
         &self.component_type
     }
 
-    /// Returns the full path for a component by prefixing it with `crate::components::`, e.g.
-    /// `crate::components::small_net::SmallNet<NodeId>`
+    /// Returns the path of the module the component is found in, e.g.
+    /// `crate::components::small_net`.
+    ///
+    /// Plugins given with a path, e.g. `::indexer::Indexer`, are found in the module of that path,
+    /// so they can be defined in other crates. Any other component is found in its canonical module
+    /// inside of `crate::components`, or `crate::plugins` for plugins.
+    pub(crate) fn module_path(&self) -> TokenStream {
+        match self.component_type.module_path() {
+            Some(module_path) if self.is_plugin => module_path,
+            _ => {
+                let root_module = if self.is_plugin {
+                    to_ident("plugins")
+                } else {
+                    to_ident("components")
+                };
+                let module_ident = self.component_type.module_ident();
+                quote!(crate::#root_module::#module_ident)
+            }
+        }
+    }
+
+    /// Returns the full path for a component by prefixing it with its module path, e.g.
+    /// `crate::components::small_net::SmallNet<NodeId>`, see `module_path`.
     pub fn full_component_type(&self) -> TokenStream {
-        let component_type = self.component_type();
-        let module_ident = component_type.module_ident();
-        let ty = component_type.ty();
-        quote!(crate::components::#module_ident::#ty)
+        let module_path = self.module_path();
+        let ty = self.component_type().ty();
+        quote!(#module_path::#ty)
     }
 
     /// Returns the full path for a component's event e.g. `crate::components::small_net::Error`
@@ -301,6 +345,7 @@ impl Debug for ComponentDefinition {
             .field("name", &self.name.to_string())
             .field("component_type", &self.component_type)
             .field("component_arguments", &"TODO: fmtargs")
+            .field("is_plugin", &self.is_plugin)
             .finish()
     }
 }
@@ -338,6 +383,7 @@ impl Parse for ComponentDefinition {
             component_arguments: args.into_iter().collect(),
             has_effects,
             is_infallible,
+            is_plugin: false,
         })
     }
 }
@@ -438,6 +484,13 @@ impl AnnouncementDefinition {
         self.targets.iter()
     }
 
+    /// Returns an iterator over the targets which handle the announcement, i.e. all but discards.
+    pub(crate) fn handling_targets(&self) -> impl Iterator<Item = &Target> {
+        self.targets
+            .iter()
+            .filter(|target| !matches!(target, Target::Discard))
+    }
+
     /// Returns an ident identifying the announcement that is suitable for a variant, e.g.
     /// `NetworkAnnouncement`.
     pub fn variant_ident(&self) -> Ident {
@@ -448,14 +501,40 @@ impl AnnouncementDefinition {
 impl Parse for AnnouncementDefinition {
     fn parse(input: ParseStream) -> Result<Self> {
         let announcement_type = RustType::new(input.parse()?);
-        let _: Token!(->) = input.parse()?;
+        let arrow: Token!(->) = input.parse()?;
+
+        // A single target may be given without brackets, like the target of a request.
+        let targets: Vec<Target> = if input.peek(syn::token::Bracket) {
+            let content;
+            bracketed!(content in input);
+            content
+                .parse_terminated::<Target, Token!(,)>(Target::parse)?
+                .into_iter()
+                .collect()
+        } else {
+            vec![input.parse()?]
+        };
 
-        let content;
-        bracketed!(content in input);
-        let targets = content
-            .parse_terminated::<Target, Token!(,)>(Target::parse)?
-            .into_iter()
-            .collect();
+        if targets.len() > 1 && targets.iter().any(|target| matches!(target, Target::Panic)) {
+            return Err(syn::Error::new_spanned(
+                arrow,
+                "An announcement routed to `!` cannot have further targets",
+            ));
+        }
+        let mut seen = IndexSet::new();
+        for target in &targets {
+            if let Target::Dest(ident) | Target::Dispatch(ident) = target {
+                if !seen.insert(format!("{:?}", target)) {
+                    return Err(syn::Error::new_spanned(
+                        ident,
+                        format!(
+                            "An announcement is routed to the same target twice: {}",
+                            ident
+                        ),
+                    ));
+                }
+            }
+        }
 
         Ok(AnnouncementDefinition {
             announcement_type,
@@ -524,6 +603,7 @@ mod kw {
     syn::custom_keyword!(events);
     syn::custom_keyword!(requests);
     syn::custom_keyword!(announcements);
+    syn::custom_keyword!(plugins);
     syn::custom_keyword!(infallible);
     syn::custom_keyword!(has_effects);
 }
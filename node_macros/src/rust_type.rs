@@ -56,6 +56,18 @@ impl RustType {
         &self.0
     }
 
+    /// Returns the path of the module containing the type as it was given, e.g. `::indexer` for
+    /// `::indexer::Indexer`, or `None` if it was given without a path.
+    pub fn module_path(&self) -> Option<TokenStream> {
+        let segment_count = self.0.segments.len();
+        if segment_count < 2 {
+            return None;
+        }
+        let leading_colon = &self.0.leading_colon;
+        let segments = self.0.segments.iter().take(segment_count - 1);
+        Some(quote!(#leading_colon #(#segments)::*))
+    }
+
     /// Returns the module name that canonically would contain the type, e.g. `small_net`.
     ///
     /// Based on the identifier only, i.e. will discard any actualy path.
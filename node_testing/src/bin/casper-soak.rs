@@ -0,0 +1,43 @@
+//! # Casper soak tests
+//!
+//! Runs a soak test scenario against an in-process testnet of validators, exiting with an error as
+//! soon as safety or liveness is violated. Run with `--help` to see available command-line
+//! arguments, and see `resources/test/soak` for example scenarios.
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use structopt::StructOpt;
+use tokio::runtime::Builder;
+use tracing::info;
+
+use casper_node::{logging, new_rng, testing::soak, MAX_THREAD_COUNT};
+
+/// Command-line arguments.
+#[derive(Debug, StructOpt)]
+#[structopt(about = "Runs a soak test scenario against an in-process testnet")]
+struct Args {
+    /// Path to the scenario file.
+    scenario: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::from_args();
+    logging::init()?;
+
+    let scenario = soak::Scenario::from_file(&args.scenario)
+        .with_context(|| format!("failed to load {}", args.scenario.display()))?;
+
+    let mut runtime = Builder::new()
+        .threaded_scheduler()
+        .enable_all()
+        .max_threads(MAX_THREAD_COUNT)
+        .build()?;
+
+    let report = runtime.block_on(async {
+        let mut rng = new_rng();
+        soak::run(&scenario, &mut rng).await
+    })?;
+    info!(%report, "soak test passed");
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! # Casper node testing support
+//!
+//! Test infrastructure for running in-process networks of `casper-node` reactors, for use in the
+//! integration tests of downstream projects. See the `README.md` for an overview.
+//!
+//! This crate enables the `testing` feature of `casper-node`, so it should only ever be used as a
+//! dev-dependency.
+
+#![doc(html_root_url = "https://docs.rs/casper-node-testing/0.7.0")]
+#![doc(
+    html_favicon_url = "https://raw.githubusercontent.com/CasperLabs/casper-node/master/images/CasperLabs_Logo_Favicon_RGB_50px.png",
+    html_logo_url = "https://raw.githubusercontent.com/CasperLabs/casper-node/master/images/CasperLabs_Logo_Symbol_RGB.png",
+    test(attr(forbid(warnings)))
+)]
+#![warn(missing_docs)]
+
+pub use casper_node::{
+    components::in_memory_network::{InMemoryNetwork, NetworkController},
+    new_rng,
+    reactor::validator,
+    testing::{
+        network::{Network, NetworkedReactor, Nodes},
+        soak, ConditionCheckReactor, TestRng,
+    },
+    NodeRng,
+};
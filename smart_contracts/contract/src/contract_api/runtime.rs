@@ -9,7 +9,7 @@ use core::mem::MaybeUninit;
 use casper_types::{
     account::AccountHash,
     api_error,
-    bytesrepr::{self, FromBytes},
+    bytesrepr::{self, FromBytes, ToBytes},
     contracts::{ContractVersion, NamedKeys},
     ApiError, BlockTime, CLTyped, CLValue, ContractHash, ContractPackageHash, Key, Phase,
     RuntimeArgs, URef, BLAKE2B_DIGEST_LENGTH, BLOCKTIME_SERIALIZED_LENGTH, PHASE_SERIALIZED_LENGTH,
@@ -320,6 +320,19 @@ pub fn blake2b<T: AsRef<[u8]>>(input: T) -> [u8; BLAKE2B_DIGEST_LENGTH] {
     ret
 }
 
+/// Emits an event named `name` carrying `data`.
+///
+/// Events are recorded, along with the key of the emitting contract or account, in the execution
+/// result of the deploy and published by nodes on their event stream.  Events emitted by a deploy
+/// whose execution fails are discarded.
+pub fn emit_event<T: CLTyped + ToBytes>(name: &str, data: T) {
+    let data = CLValue::from_t(data).unwrap_or_revert();
+    let (name_ptr, name_size, _bytes1) = contract_api::to_ptr(name);
+    let (data_ptr, data_size, _bytes2) = contract_api::to_ptr(data);
+    let result = unsafe { ext_ffi::casper_emit_event(name_ptr, name_size, data_ptr, data_size) };
+    api_error::result_from(result).unwrap_or_revert();
+}
+
 fn read_host_buffer_into(dest: &mut [u8]) -> Result<usize, ApiError> {
     let mut bytes_written = MaybeUninit::uninit();
     let ret = unsafe {
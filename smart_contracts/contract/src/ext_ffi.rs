@@ -700,6 +700,21 @@ extern "C" {
         out_ptr: *mut u8,
         out_size: usize,
     ) -> i32;
+    /// Emits a structured event which is recorded in the execution result of the deploy, provided
+    /// its execution succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_ptr` - pointer to serialized event name
+    /// * `name_size` - size of serialized event name
+    /// * `data_ptr` - pointer to serialized event data, as a `CLValue`
+    /// * `data_size` - size of serialized event data
+    pub fn casper_emit_event(
+        name_ptr: *const u8,
+        name_size: usize,
+        data_ptr: *const u8,
+        data_size: usize,
+    ) -> i32;
     /// Prints data directly to stanadard output on the host.
     ///
     /// # Arguments
@@ -0,0 +1,22 @@
+#![no_std]
+#![no_main]
+
+use casper_contract::contract_api::runtime;
+use casper_types::{ApiError, U512};
+
+const EVENT_NAME: &str = "amount_recorded";
+
+const ARG_AMOUNT: &str = "amount";
+const ARG_REVERT: &str = "revert";
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let amount: U512 = runtime::get_named_arg(ARG_AMOUNT);
+    let revert: bool = runtime::get_named_arg(ARG_REVERT);
+
+    runtime::emit_event(EVENT_NAME, amount);
+
+    if revert {
+        runtime::revert(ApiError::User(0));
+    }
+}
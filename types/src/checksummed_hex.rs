@@ -0,0 +1,141 @@
+//! Hex encoding with a checksum in the case of the letters.
+//!
+//! Similar to EIP-55, the hash of the encoded bytes decides for every hex digit which is a letter
+//! whether it is written in upper- or lowercase.  Decoding accepts checksummed strings as well as
+//! strings in a single case, so hex produced by other tools remains valid, but rejects mixed-case
+//! strings whose checksum doesn't match, which catches most accidental edits of a copied string.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Display, Formatter};
+
+use hex::FromHexError;
+
+use crate::account;
+
+/// The maximum number of bytes whose encoding is checksummed.
+///
+/// Longer inputs are encoded in lowercase, as every hex digit takes one bit of the 256 bit hash.
+pub const SMALL_BYTES_COUNT: usize = 75;
+
+/// Encodes `input` as hex, checksummed if it is at most `SMALL_BYTES_COUNT` bytes long.
+pub fn encode<T: AsRef<[u8]>>(input: T) -> String {
+    let input = input.as_ref();
+    let encoded = hex::encode(input);
+    if input.len() > SMALL_BYTES_COUNT {
+        return encoded;
+    }
+
+    let hash = account::blake2b(input);
+    encoded
+        .chars()
+        .enumerate()
+        .map(|(index, digit)| {
+            let upper = hash[index / 8] & (0x80 >> (index % 8)) != 0;
+            if upper {
+                digit.to_ascii_uppercase()
+            } else {
+                digit
+            }
+        })
+        .collect()
+}
+
+/// Decodes hex which is either checksummed as produced by `encode`, or in a single case.
+///
+/// A digit not matching the checksum is reported as an invalid character.
+pub fn decode<T: AsRef<[u8]>>(input: T) -> Result<Vec<u8>, FromHexError> {
+    let input = input.as_ref();
+    let bytes = hex::decode(input)?;
+
+    let is_mixed_case =
+        input.iter().any(u8::is_ascii_lowercase) && input.iter().any(u8::is_ascii_uppercase);
+    if !is_mixed_case || bytes.len() > SMALL_BYTES_COUNT {
+        return Ok(bytes);
+    }
+
+    let expected = encode(&bytes);
+    match input
+        .iter()
+        .zip(expected.as_bytes())
+        .position(|(actual, expected)| actual != expected)
+    {
+        Some(index) => Err(FromHexError::InvalidHexCharacter {
+            c: char::from(input[index]),
+            index,
+        }),
+        None => Ok(bytes),
+    }
+}
+
+/// Displays the checksummed hex encoding of the wrapped bytes.
+///
+/// Like `hex_fmt::HexFmt`, the encoding is abbreviated to its first and last digits if a width is
+/// given and the encoding is longer.
+pub struct ChecksummedHexFmt<T>(pub T);
+
+impl<T: AsRef<[u8]>> Display for ChecksummedHexFmt<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        let encoded = encode(&self.0);
+        match formatter.width() {
+            Some(width) if encoded.len() > width => {
+                let half = width.saturating_sub(2) / 2;
+                write!(
+                    formatter,
+                    "{}..{}",
+                    &encoded[..half],
+                    &encoded[encoded.len() - half..]
+                )
+            }
+            _ => formatter.write_str(&encoded),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_roundtrip_checksummed_and_single_case_hex() {
+        let bytes: Vec<u8> = (0..=255).take(SMALL_BYTES_COUNT).collect();
+        let encoded = encode(&bytes);
+        assert_ne!(encoded, hex::encode(&bytes));
+        assert!(encoded.eq_ignore_ascii_case(&hex::encode(&bytes)));
+
+        assert_eq!(decode(&encoded).unwrap(), bytes);
+        assert_eq!(decode(encoded.to_ascii_lowercase()).unwrap(), bytes);
+        assert_eq!(decode(encoded.to_ascii_uppercase()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn should_reject_hex_with_wrong_checksum() {
+        let bytes = [0xab; 32];
+        let mut encoded = encode(&bytes).into_bytes();
+        let index = encoded
+            .iter()
+            .position(u8::is_ascii_uppercase)
+            .expect("checksum should uppercase some letter");
+        encoded[index].make_ascii_lowercase();
+        assert!(matches!(
+            decode(&encoded),
+            Err(FromHexError::InvalidHexCharacter { index: actual, .. }) if actual == index
+        ));
+    }
+
+    #[test]
+    fn should_abbreviate_to_width() {
+        let bytes = [0xab; 32];
+        let encoded = encode(&bytes);
+        assert_eq!(format!("{}", ChecksummedHexFmt(&bytes)), encoded);
+        assert_eq!(
+            format!("{:10}", ChecksummedHexFmt(&bytes)),
+            format!("{}..{}", &encoded[..4], &encoded[60..])
+        );
+    }
+
+    #[test]
+    fn should_not_checksum_long_input() {
+        let bytes = [0xab; SMALL_BYTES_COUNT + 1];
+        assert_eq!(encode(&bytes), hex::encode(&bytes));
+    }
+}
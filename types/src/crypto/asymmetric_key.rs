@@ -25,6 +25,7 @@ use crate::{
     account::AccountHash,
     bytesrepr,
     bytesrepr::{FromBytes, ToBytes, U8_SERIALIZED_LENGTH},
+    checksummed_hex::{self, ChecksummedHexFmt},
     crypto::Error,
     CLType, CLTyped, Tagged,
 };
@@ -57,38 +58,33 @@ pub const SYSTEM_ACCOUNT: PublicKey = PublicKey::System;
 
 /// Operations on asymmetric cryptographic type
 pub trait AsymmetricType: Sized + AsRef<[u8]> + Tagged<u8> {
-    /// Converts the signature to hex, where the first byte represents the algorithm tag.
+    /// Converts the signature to checksummed hex, where the first byte represents the algorithm
+    /// tag.
     fn to_hex(&self) -> String {
         let bytes = iter::once(&self.tag())
             .chain(self.as_ref())
             .copied()
             .collect::<Vec<u8>>();
-        hex::encode(bytes)
+        checksummed_hex::encode(bytes)
     }
 
     /// Tries to decode a signature from its hex-representation.  The hex format should be as
-    /// produced by `Signature::to_hex()`.
+    /// produced by `Signature::to_hex()`, or the same in a single case.
     fn from_hex<A: AsRef<[u8]>>(input: A) -> Result<Self, Error> {
         if input.as_ref().len() < 2 {
             return Err(Error::AsymmetricKey("too short".to_string()));
         }
 
-        let (tag_bytes, key_bytes) = input.as_ref().split_at(2);
-        let mut tag = [0u8; 1];
-        hex::decode_to_slice(tag_bytes, tag.as_mut())?;
+        // The checksum covers the tag as well, so the input is decoded as a whole.
+        let bytes = checksummed_hex::decode(input)?;
+        let (tag, key_bytes) = bytes.split_first().expect("should have a tag byte");
 
-        match tag[0] {
-            ED25519_TAG => {
-                let bytes = hex::decode(key_bytes)?;
-                Self::ed25519_from_bytes(&bytes)
-            }
-            SECP256K1_TAG => {
-                let bytes = hex::decode(key_bytes)?;
-                Self::secp256k1_from_bytes(&bytes)
-            }
+        match *tag {
+            ED25519_TAG => Self::ed25519_from_bytes(key_bytes),
+            SECP256K1_TAG => Self::secp256k1_from_bytes(key_bytes),
             _ => Err(Error::AsymmetricKey(format!(
                 "invalid tag.  Expected {} or {}, got {}",
-                ED25519_TAG, SECP256K1_TAG, tag[0]
+                ED25519_TAG, SECP256K1_TAG, tag
             ))),
         }
     }
@@ -431,7 +427,7 @@ impl Debug for PublicKey {
             formatter,
             "PublicKey::{}({})",
             self.variant_name(),
-            HexFmt(self)
+            ChecksummedHexFmt(self)
         )
     }
 }
@@ -442,7 +438,7 @@ impl Display for PublicKey {
             formatter,
             "PubKey::{}({:10})",
             self.variant_name(),
-            HexFmt(self)
+            ChecksummedHexFmt(self)
         )
     }
 }
@@ -675,7 +671,7 @@ impl Debug for Signature {
             formatter,
             "Signature::{}({})",
             self.variant_name(),
-            HexFmt(self)
+            ChecksummedHexFmt(self)
         )
     }
 }
@@ -686,7 +682,7 @@ impl Display for Signature {
             formatter,
             "Sig::{}({:10})",
             self.variant_name(),
-            HexFmt(self)
+            ChecksummedHexFmt(self)
         )
     }
 }
@@ -23,7 +23,7 @@ use rand::{
 };
 #[cfg(feature = "std")]
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeStructVariant, Deserialize, Deserializer, Serialize, Serializer};
 
 #[cfg(feature = "std")]
 use crate::KEY_HASH_LENGTH;
@@ -37,6 +37,7 @@ use crate::{
 /// Constants to track ExecutionResult serialization.
 const EXECUTION_RESULT_FAILURE_TAG: u8 = 0;
 const EXECUTION_RESULT_SUCCESS_TAG: u8 = 1;
+const EXECUTION_RESULT_SUCCESS_WITH_EVENTS_TAG: u8 = 2;
 
 /// Constants to track operation serialization.
 const OP_READ_TAG: u8 = 0;
@@ -96,17 +97,27 @@ static EXECUTION_RESULT: Lazy<ExecutionResult> = Lazy::new(|| {
         TransferAddr::new([130; KEY_HASH_LENGTH]),
     ];
 
+    let events = vec![ContractEvent {
+        emitter: "hash-2c4a11c062a8a337bfc97e27fd66291caeb2c65865dcb5d3ef3759c4c97efecb"
+            .to_string(),
+        name: "token_minted".to_string(),
+        data: CLValue::from_t(U512::from(1_000)).unwrap(),
+    }];
+
     ExecutionResult::Success {
         effect,
         transfers,
+        events,
         cost: U512::from(123_456),
     }
 });
 
 /// The result of executing a single deploy.
-#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
-#[cfg_attr(feature = "std", derive(JsonSchema))]
-#[serde(deny_unknown_fields)]
+///
+/// Successful results without events are encoded as they were before events were introduced, both
+/// by `ToBytes` and by binary `serde` formats, so previously stored results can still be read.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(JsonSchema), serde(deny_unknown_fields))]
 pub enum ExecutionResult {
     /// The result of a failed execution.
     Failure {
@@ -125,6 +136,8 @@ pub enum ExecutionResult {
         effect: ExecutionEffect,
         /// A record of Transfers performed while executing the deploy.
         transfers: Vec<TransferAddr>,
+        /// The events emitted by contracts while executing the deploy, in order of emission.
+        events: Vec<ContractEvent>,
         /// The cost of executing the deploy.
         cost: U512,
     },
@@ -181,9 +194,16 @@ impl Distribution<ExecutionResult> for Standard {
                 error_message: format!("Error message {}", rng.gen::<u64>()),
             }
         } else {
+            let event_count = rng.gen_range(0, 6);
+            let mut events = vec![];
+            for _ in 0..event_count {
+                events.push(rng.gen());
+            }
+
             ExecutionResult::Success {
                 effect,
                 transfers,
+                events,
                 cost: rng.gen::<u64>().into(),
             }
         }
@@ -209,13 +229,26 @@ impl ToBytes for ExecutionResult {
             ExecutionResult::Success {
                 effect,
                 transfers,
+                events,
                 cost,
-            } => {
+            } if events.is_empty() => {
                 buffer.push(EXECUTION_RESULT_SUCCESS_TAG);
                 buffer.extend(effect.to_bytes()?);
                 buffer.extend(transfers.to_bytes()?);
                 buffer.extend(cost.to_bytes()?);
             }
+            ExecutionResult::Success {
+                effect,
+                transfers,
+                events,
+                cost,
+            } => {
+                buffer.push(EXECUTION_RESULT_SUCCESS_WITH_EVENTS_TAG);
+                buffer.extend(effect.to_bytes()?);
+                buffer.extend(transfers.to_bytes()?);
+                buffer.extend(events.to_bytes()?);
+                buffer.extend(cost.to_bytes()?);
+            }
         }
         Ok(buffer)
     }
@@ -237,10 +270,22 @@ impl ToBytes for ExecutionResult {
                 ExecutionResult::Success {
                     effect,
                     transfers,
+                    events,
+                    cost,
+                } if events.is_empty() => {
+                    effect.serialized_length()
+                        + transfers.serialized_length()
+                        + cost.serialized_length()
+                }
+                ExecutionResult::Success {
+                    effect,
+                    transfers,
+                    events,
                     cost,
                 } => {
                     effect.serialized_length()
                         + transfers.serialized_length()
+                        + events.serialized_length()
                         + cost.serialized_length()
                 }
             }
@@ -271,6 +316,20 @@ impl FromBytes for ExecutionResult {
                 let execution_result = ExecutionResult::Success {
                     effect,
                     transfers,
+                    events: Vec::new(),
+                    cost,
+                };
+                Ok((execution_result, remainder))
+            }
+            EXECUTION_RESULT_SUCCESS_WITH_EVENTS_TAG => {
+                let (effect, remainder) = ExecutionEffect::from_bytes(remainder)?;
+                let (transfers, remainder) = Vec::<TransferAddr>::from_bytes(remainder)?;
+                let (events, remainder) = Vec::<ContractEvent>::from_bytes(remainder)?;
+                let (cost, remainder) = U512::from_bytes(remainder)?;
+                let execution_result = ExecutionResult::Success {
+                    effect,
+                    transfers,
+                    events,
                     cost,
                 };
                 Ok((execution_result, remainder))
@@ -280,6 +339,169 @@ impl FromBytes for ExecutionResult {
     }
 }
 
+impl Serialize for ExecutionResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let human_readable = serializer.is_human_readable();
+        match self {
+            ExecutionResult::Failure {
+                effect,
+                transfers,
+                cost,
+                error_message,
+            } => {
+                let mut variant =
+                    serializer.serialize_struct_variant("ExecutionResult", 0, "Failure", 4)?;
+                variant.serialize_field("effect", effect)?;
+                variant.serialize_field("transfers", transfers)?;
+                variant.serialize_field("cost", cost)?;
+                variant.serialize_field("error_message", error_message)?;
+                variant.end()
+            }
+            ExecutionResult::Success {
+                effect,
+                transfers,
+                cost,
+                events,
+            } if !human_readable && events.is_empty() => {
+                let mut variant =
+                    serializer.serialize_struct_variant("ExecutionResult", 1, "Success", 3)?;
+                variant.serialize_field("effect", effect)?;
+                variant.serialize_field("transfers", transfers)?;
+                variant.serialize_field("cost", cost)?;
+                variant.end()
+            }
+            ExecutionResult::Success {
+                effect,
+                transfers,
+                events,
+                cost,
+            } => {
+                let (index, name) = if human_readable {
+                    (1, "Success")
+                } else {
+                    (2, "SuccessWithEvents")
+                };
+                let mut variant =
+                    serializer.serialize_struct_variant("ExecutionResult", index, name, 4)?;
+                variant.serialize_field("effect", effect)?;
+                variant.serialize_field("transfers", transfers)?;
+                variant.serialize_field("events", events)?;
+                variant.serialize_field("cost", cost)?;
+                variant.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ExecutionResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Ok(match ExecutionResultJson::deserialize(deserializer)? {
+                ExecutionResultJson::Failure {
+                    effect,
+                    transfers,
+                    cost,
+                    error_message,
+                } => ExecutionResult::Failure {
+                    effect,
+                    transfers,
+                    cost,
+                    error_message,
+                },
+                ExecutionResultJson::Success {
+                    effect,
+                    transfers,
+                    events,
+                    cost,
+                } => ExecutionResult::Success {
+                    effect,
+                    transfers,
+                    events,
+                    cost,
+                },
+            })
+        } else {
+            Ok(match ExecutionResultBinary::deserialize(deserializer)? {
+                ExecutionResultBinary::Failure {
+                    effect,
+                    transfers,
+                    cost,
+                    error_message,
+                } => ExecutionResult::Failure {
+                    effect,
+                    transfers,
+                    cost,
+                    error_message,
+                },
+                ExecutionResultBinary::Success {
+                    effect,
+                    transfers,
+                    cost,
+                } => ExecutionResult::Success {
+                    effect,
+                    transfers,
+                    events: Vec::new(),
+                    cost,
+                },
+                ExecutionResultBinary::SuccessWithEvents {
+                    effect,
+                    transfers,
+                    events,
+                    cost,
+                } => ExecutionResult::Success {
+                    effect,
+                    transfers,
+                    events,
+                    cost,
+                },
+            })
+        }
+    }
+}
+
+/// Human-readable representation of an `ExecutionResult`.
+#[derive(Deserialize)]
+#[serde(rename = "ExecutionResult", deny_unknown_fields)]
+enum ExecutionResultJson {
+    Failure {
+        effect: ExecutionEffect,
+        transfers: Vec<TransferAddr>,
+        cost: U512,
+        error_message: String,
+    },
+    Success {
+        effect: ExecutionEffect,
+        transfers: Vec<TransferAddr>,
+        #[serde(default)]
+        events: Vec<ContractEvent>,
+        cost: U512,
+    },
+}
+
+/// Binary representation of an `ExecutionResult`, in which `Success` keeps the layout from before
+/// events were introduced.
+#[derive(Deserialize)]
+#[serde(rename = "ExecutionResult")]
+enum ExecutionResultBinary {
+    Failure {
+        effect: ExecutionEffect,
+        transfers: Vec<TransferAddr>,
+        cost: U512,
+        error_message: String,
+    },
+    Success {
+        effect: ExecutionEffect,
+        transfers: Vec<TransferAddr>,
+        cost: U512,
+    },
+    SuccessWithEvents {
+        effect: ExecutionEffect,
+        transfers: Vec<TransferAddr>,
+        events: Vec<ContractEvent>,
+        cost: U512,
+    },
+}
+
 /// The effect of executing a single deploy.
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Default, Debug)]
 #[cfg_attr(feature = "std", derive(JsonSchema))]
@@ -316,6 +538,60 @@ impl FromBytes for ExecutionEffect {
     }
 }
 
+/// A structured event emitted by a contract while executing a deploy.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "std", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ContractEvent {
+    /// The formatted string of the `Key` of the contract, or for session code the account, which
+    /// emitted the event.
+    pub emitter: String,
+    /// The name of the event.
+    pub name: String,
+    /// The data of the event.
+    pub data: CLValue,
+}
+
+impl ToBytes for ContractEvent {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut buffer = bytesrepr::allocate_buffer(self)?;
+        buffer.extend(self.emitter.to_bytes()?);
+        buffer.extend(self.name.to_bytes()?);
+        buffer.extend(self.data.to_bytes()?);
+        Ok(buffer)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.emitter.serialized_length()
+            + self.name.serialized_length()
+            + self.data.serialized_length()
+    }
+}
+
+impl FromBytes for ContractEvent {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (emitter, remainder) = String::from_bytes(bytes)?;
+        let (name, remainder) = String::from_bytes(remainder)?;
+        let (data, remainder) = CLValue::from_bytes(remainder)?;
+        let event = ContractEvent {
+            emitter,
+            name,
+            data,
+        };
+        Ok((event, remainder))
+    }
+}
+
+impl Distribution<ContractEvent> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> ContractEvent {
+        ContractEvent {
+            emitter: rng.gen::<u64>().to_string(),
+            name: format!("event {}", rng.gen::<u64>()),
+            data: CLValue::from_t(rng.gen::<u64>()).unwrap(),
+        }
+    }
+}
+
 /// An operation performed while executing a deploy.
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
 #[cfg_attr(feature = "std", derive(JsonSchema))]
@@ -664,4 +940,116 @@ mod tests {
         let execution_result: ExecutionResult = rng.gen();
         bytesrepr::test_serialization_roundtrip(&execution_result);
     }
+
+    /// `ExecutionResult` as it was derived before events were introduced.
+    #[derive(Serialize)]
+    enum LegacyExecutionResult {
+        #[allow(dead_code)]
+        Failure {
+            effect: ExecutionEffect,
+            transfers: Vec<TransferAddr>,
+            cost: U512,
+            error_message: String,
+        },
+        Success {
+            effect: ExecutionEffect,
+            transfers: Vec<TransferAddr>,
+            cost: U512,
+        },
+    }
+
+    fn success_without_events(rng: &mut SmallRng) -> ExecutionResult {
+        ExecutionResult::Success {
+            effect: ExecutionEffect {
+                operations: vec![],
+                transforms: vec![TransformEntry {
+                    key: rng.gen::<u64>().to_string(),
+                    transform: rng.gen(),
+                }],
+            },
+            transfers: vec![TransferAddr::new(rng.gen())],
+            events: vec![],
+            cost: rng.gen::<u64>().into(),
+        }
+    }
+
+    #[test]
+    fn should_decode_legacy_bytesrepr_success() {
+        let mut rng = get_rng();
+        let execution_result = success_without_events(&mut rng);
+        let (effect, transfers, cost) = match &execution_result {
+            ExecutionResult::Success {
+                effect,
+                transfers,
+                cost,
+                ..
+            } => (effect, transfers, cost),
+            ExecutionResult::Failure { .. } => unreachable!(),
+        };
+
+        let mut legacy_bytes = vec![EXECUTION_RESULT_SUCCESS_TAG];
+        legacy_bytes.extend(effect.to_bytes().unwrap());
+        legacy_bytes.extend(transfers.to_bytes().unwrap());
+        legacy_bytes.extend(cost.to_bytes().unwrap());
+
+        assert_eq!(
+            bytesrepr::deserialize::<ExecutionResult>(legacy_bytes.clone()).unwrap(),
+            execution_result
+        );
+        // Results without events are still written in the legacy layout.
+        assert_eq!(execution_result.to_bytes().unwrap(), legacy_bytes);
+    }
+
+    #[test]
+    fn should_decode_legacy_bincode_success() {
+        let mut rng = get_rng();
+        let execution_result = success_without_events(&mut rng);
+        let legacy = match execution_result.clone() {
+            ExecutionResult::Success {
+                effect,
+                transfers,
+                cost,
+                ..
+            } => LegacyExecutionResult::Success {
+                effect,
+                transfers,
+                cost,
+            },
+            ExecutionResult::Failure { .. } => unreachable!(),
+        };
+
+        let legacy_bytes = bincode::serialize(&legacy).unwrap();
+        assert_eq!(
+            bincode::deserialize::<ExecutionResult>(&legacy_bytes).unwrap(),
+            execution_result
+        );
+        assert_eq!(bincode::serialize(&execution_result).unwrap(), legacy_bytes);
+    }
+
+    #[test]
+    fn bincode_test_execution_result() {
+        let mut rng = get_rng();
+        for _ in 0..10 {
+            let execution_result: ExecutionResult = rng.gen();
+            let bytes = bincode::serialize(&execution_result).unwrap();
+            assert_eq!(
+                bincode::deserialize::<ExecutionResult>(&bytes).unwrap(),
+                execution_result
+            );
+        }
+    }
+
+    #[test]
+    fn should_decode_legacy_json_success() {
+        let mut rng = get_rng();
+        let execution_result = success_without_events(&mut rng);
+        let mut json = serde_json::to_value(&execution_result).unwrap();
+        assert!(json["Success"]["events"].is_array());
+        json["Success"].as_object_mut().unwrap().remove("events");
+
+        assert_eq!(
+            serde_json::from_value::<ExecutionResult>(json).unwrap(),
+            execution_result
+        );
+    }
 }
@@ -29,6 +29,7 @@ pub mod api_error;
 pub mod auction;
 mod block_time;
 pub mod bytesrepr;
+pub mod checksummed_hex;
 mod cl_type;
 mod cl_value;
 mod contract_wasm;
@@ -71,7 +72,7 @@ pub use contracts::{
 pub use crypto::*;
 pub use deploy_info::DeployInfo;
 pub use execution_result::{
-    ExecutionEffect, ExecutionResult, OpKind, Operation, Transform, TransformEntry,
+    ContractEvent, ExecutionEffect, ExecutionResult, OpKind, Operation, Transform, TransformEntry,
 };
 pub use json_pretty_printer::json_pretty_print;
 #[doc(inline)]